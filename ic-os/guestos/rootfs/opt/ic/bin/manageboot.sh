@@ -112,6 +112,11 @@ Usage:
       has been confirmed previously already (safe to call as many times
       as you like, will not initiate I/O if nothing to be written).
 
+    rollback
+      Revert to the previous system if the present system has not been
+      confirmed yet, and trigger a reboot immediately. This is used when the
+      present system failed its health checks after an upgrade.
+
     current
       Output currently booted system (A or B) on stdout and exit.
 
@@ -180,6 +185,8 @@ case "${ACTION}" in
 
     upgrade-commit) ;&
 
+    rollback) ;&
+
     confirm)
         # Re-execute script as root (unless root already) for operations that
         # require privilege.
@@ -236,6 +243,20 @@ case "${ACTION}" in
         trap -- '' SIGTERM
         reboot
         ;;
+    rollback)
+        if [ "${boot_cycle}" != "failsafe_check" ]; then
+            echo "Cannot roll back, the present system is not in its first boot after an upgrade." >&2
+            exit 1
+        fi
+
+        # The bootloader reverts to the other system on the next boot, unless
+        # the present system is confirmed.
+        sync
+        # Ignore termination signals from the following reboot, so that
+        # the script exits without error.
+        trap -- '' SIGTERM
+        reboot
+        ;;
     confirm)
         if [ "$boot_cycle" != "stable" ]; then
             boot_cycle=stable
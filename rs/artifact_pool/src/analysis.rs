@@ -0,0 +1,426 @@
+//! Offline analysis of consensus artifacts read from a persistent pool or a
+//! backup, used by the `missing-shares` and `verify-chain` subcommands of the
+//! consensus pool utility.
+
+use ic_types::{
+    consensus::{Block, CatchUpPackage, ConsensusMessage, HasHeight},
+    crypto::{threshold_sig::ni_dkg::NiDkgTag, CryptoHashOf},
+    Height, NodeId,
+};
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The notarization and finalization status of a single height.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct HeightSignatures {
+    pub height: Height,
+    pub notarized: bool,
+    pub finalized: bool,
+    #[serde(serialize_with = "serialize_node_ids")]
+    pub notarization_signers: BTreeSet<NodeId>,
+    #[serde(serialize_with = "serialize_node_ids")]
+    pub missing_notarization_signers: BTreeSet<NodeId>,
+    #[serde(serialize_with = "serialize_node_ids")]
+    pub finalization_signers: BTreeSet<NodeId>,
+    #[serde(serialize_with = "serialize_node_ids")]
+    pub missing_finalization_signers: BTreeSet<NodeId>,
+}
+
+// Serializes node IDs in their textual representation rather than as bytes.
+fn serialize_node_ids<S: Serializer>(
+    nodes: &BTreeSet<NodeId>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(nodes.iter().map(NodeId::to_string))
+}
+
+/// Returns the nodes of the subnet according to the low threshold DKG
+/// transcript of the given catch-up package, i.e. the nodes expected to sign
+/// notarizations and finalizations above its height.
+pub fn subnet_members(cup: &CatchUpPackage) -> BTreeSet<NodeId> {
+    cup.content
+        .block
+        .as_ref()
+        .payload
+        .as_ref()
+        .as_summary()
+        .dkg
+        .current_transcript(&NiDkgTag::LowThreshold)
+        .committee
+        .get()
+        .clone()
+}
+
+/// Returns, for every height between the lowest and the highest height of the
+/// given artifacts that lacks a notarization or a finalization, the nodes that
+/// signed a share or an aggregate at this height and the nodes that did not.
+/// The expected signers are the given `members` together with any other
+/// signer seen in the artifacts.
+pub fn missing_shares(
+    artifacts: &[ConsensusMessage],
+    members: &BTreeSet<NodeId>,
+) -> Vec<HeightSignatures> {
+    let mut heights = BTreeMap::<Height, HeightSignatures>::new();
+    let mut nodes = members.clone();
+    for msg in artifacts {
+        let height = msg.height();
+        let entry = heights.entry(height).or_insert_with(|| HeightSignatures {
+            height,
+            ..Default::default()
+        });
+        let (signers, notarization) = match msg {
+            ConsensusMessage::Notarization(x) => {
+                entry.notarized = true;
+                (x.signature.signers.clone(), true)
+            }
+            ConsensusMessage::NotarizationShare(x) => (vec![x.signature.signer], true),
+            ConsensusMessage::Finalization(x) => {
+                entry.finalized = true;
+                (x.signature.signers.clone(), false)
+            }
+            ConsensusMessage::FinalizationShare(x) => (vec![x.signature.signer], false),
+            _ => continue,
+        };
+        for signer in signers {
+            nodes.insert(signer);
+            if notarization {
+                entry.notarization_signers.insert(signer);
+            } else {
+                entry.finalization_signers.insert(signer);
+            }
+        }
+    }
+
+    let (min, max) = match (heights.keys().next(), heights.keys().next_back()) {
+        (Some(min), Some(max)) => (*min, *max),
+        _ => return Vec::new(),
+    };
+    let mut result = Vec::new();
+    let mut height = min;
+    while height <= max {
+        let mut signatures = heights.remove(&height).unwrap_or_else(|| HeightSignatures {
+            height,
+            ..Default::default()
+        });
+        if !signatures.notarized || !signatures.finalized {
+            for node in &nodes {
+                if !signatures.notarization_signers.contains(node) {
+                    signatures.missing_notarization_signers.insert(*node);
+                }
+                if !signatures.finalization_signers.contains(node) {
+                    signatures.missing_finalization_signers.insert(*node);
+                }
+            }
+            result.push(signatures);
+        }
+        height = height.increment();
+    }
+    result
+}
+
+/// A violation of the hash chain found by [`verify_chain`].
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ChainError {
+    pub height: Height,
+    pub block: CryptoHashOf<Block>,
+    pub error: &'static str,
+}
+
+/// The outcome of [`verify_chain`].
+#[derive(Debug, Default)]
+pub struct ChainReport {
+    /// The number of distinct blocks found.
+    pub blocks: usize,
+    /// The lowest and highest height with blocks, if any block was found.
+    pub heights: Option<(Height, Height)>,
+    /// The violations found, sorted by height.
+    pub errors: Vec<ChainError>,
+}
+
+/// Verifies that every block matches its hash and that its parent is a known
+/// block at the previous height, and that the finalized blocks form a chain.
+/// Blocks are taken from block proposals and catch-up packages. Parents are
+/// only checked for blocks above the lowest height with blocks.
+pub fn verify_chain(artifacts: &[ConsensusMessage]) -> ChainReport {
+    let mut blocks = BTreeMap::<Height, BTreeMap<CryptoHashOf<Block>, &Block>>::new();
+    let mut finalized = BTreeMap::<Height, CryptoHashOf<Block>>::new();
+    let mut notarized = Vec::new();
+    let mut errors = Vec::new();
+    for msg in artifacts {
+        match msg {
+            ConsensusMessage::BlockProposal(proposal) => {
+                let height = proposal.height();
+                let hash = proposal.content.get_hash().clone();
+                if !proposal.check_integrity() {
+                    errors.push(ChainError {
+                        height,
+                        block: hash.clone(),
+                        error: "block does not match its hash",
+                    });
+                }
+                blocks
+                    .entry(height)
+                    .or_default()
+                    .insert(hash, proposal.content.as_ref());
+            }
+            ConsensusMessage::CatchUpPackage(cup) => {
+                let hash = cup.content.block.get_hash().clone();
+                blocks
+                    .entry(cup.height())
+                    .or_default()
+                    .insert(hash, cup.content.block.as_ref());
+            }
+            ConsensusMessage::Finalization(finalization) => {
+                finalized.insert(finalization.height(), finalization.content.block.clone());
+            }
+            ConsensusMessage::Notarization(notarization) => {
+                notarized.push((notarization.height(), notarization.content.block.clone()));
+            }
+            _ => (),
+        }
+    }
+
+    let (lowest_height, highest_height) = match (blocks.keys().next(), blocks.keys().next_back()) {
+        (Some(min), Some(max)) => (*min, *max),
+        _ => {
+            return ChainReport {
+                errors,
+                ..Default::default()
+            }
+        }
+    };
+    for (height, hashes) in &blocks {
+        if *height == lowest_height {
+            continue;
+        }
+        let parents = blocks.get(&height.decrement());
+        for (hash, block) in hashes {
+            if !parents.map_or(false, |parents| parents.contains_key(&block.parent)) {
+                errors.push(ChainError {
+                    height: *height,
+                    block: hash.clone(),
+                    error: "parent is not a known block at the previous height",
+                });
+            }
+        }
+    }
+    for (height, hash) in notarized.iter().chain(finalized.iter()) {
+        if !blocks
+            .get(height)
+            .map_or(false, |hashes| hashes.contains_key(hash))
+        {
+            errors.push(ChainError {
+                height: *height,
+                block: hash.clone(),
+                error: "notarized or finalized block is not a known block",
+            });
+        }
+    }
+    for (height, hash) in finalized.iter().filter(|(height, _)| height.get() > 0) {
+        let parent = finalized.get(&height.decrement());
+        let block = blocks.get(height).and_then(|hashes| hashes.get(hash));
+        if let (Some(parent), Some(block)) = (parent, block) {
+            if &block.parent != parent {
+                errors.push(ChainError {
+                    height: *height,
+                    block: hash.clone(),
+                    error: "parent is not the finalized block at the previous height",
+                });
+            }
+        }
+    }
+
+    errors.sort_by_key(|error| error.height);
+    ChainReport {
+        blocks: blocks.values().map(BTreeMap::len).sum(),
+        heights: Some((lowest_height, highest_height)),
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::{
+        consensus::{fake::*, make_genesis},
+        types::ids::node_test_id,
+    };
+    use ic_types::{
+        consensus::*,
+        crypto::{crypto_hash, CryptoHash},
+    };
+
+    fn notarization_share(block: &Block, node: u64) -> ConsensusMessage {
+        ConsensusMessage::NotarizationShare(NotarizationShare::fake(block, node_test_id(node)))
+    }
+
+    fn finalization_share(block: &Block, node: u64) -> ConsensusMessage {
+        ConsensusMessage::FinalizationShare(FinalizationShare::fake(block, node_test_id(node)))
+    }
+
+    fn notarization(block: &Block) -> ConsensusMessage {
+        ConsensusMessage::Notarization(Notarization::fake(NotarizationContent::new(
+            block.height,
+            crypto_hash(block),
+        )))
+    }
+
+    fn finalization(block: &Block) -> ConsensusMessage {
+        ConsensusMessage::Finalization(Finalization::fake(FinalizationContent::new(
+            block.height,
+            crypto_hash(block),
+        )))
+    }
+
+    fn proposal(block: &Block) -> ConsensusMessage {
+        ConsensusMessage::BlockProposal(BlockProposal::fake(block.clone(), node_test_id(0)))
+    }
+
+    /// Returns the genesis catch-up package and `n` blocks on top of it.
+    fn chain(n: usize) -> (CatchUpPackage, Vec<Block>) {
+        let cup = make_genesis(ic_types::consensus::dkg::Summary::fake());
+        let mut blocks = vec![cup.content.block.as_ref().clone()];
+        for _ in 0..n {
+            let block = Block::from_parent(blocks.last().unwrap());
+            blocks.push(block);
+        }
+        blocks.remove(0);
+        (cup, blocks)
+    }
+
+    #[test]
+    fn test_subnet_members() {
+        let (cup, _) = chain(0);
+        assert_eq!(
+            subnet_members(&cup),
+            vec![node_test_id(0)].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn test_missing_shares() {
+        let (_, blocks) = chain(4);
+        let members = (1..=4).map(node_test_id).collect::<BTreeSet<_>>();
+        let mut artifacts = Vec::new();
+        // Height 1 is notarized and finalized.
+        artifacts.push(notarization(&blocks[0]));
+        artifacts.push(finalization(&blocks[0]));
+        // Height 2 is missing entirely.
+        // Height 3 is notarized, only nodes 1 and 2 sent finalization shares.
+        artifacts.push(notarization(&blocks[2]));
+        artifacts.push(finalization_share(&blocks[2], 1));
+        artifacts.push(finalization_share(&blocks[2], 2));
+        // Height 4 has notarization shares from nodes 1 and 3 only.
+        artifacts.push(notarization_share(&blocks[3], 1));
+        artifacts.push(notarization_share(&blocks[3], 3));
+
+        let result = missing_shares(&artifacts, &members);
+        assert_eq!(
+            result.iter().map(|x| x.height.get()).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        assert!(!result[0].notarized && !result[0].finalized);
+        assert_eq!(result[0].missing_notarization_signers, members);
+        assert_eq!(result[0].missing_finalization_signers, members);
+
+        assert!(result[1].notarized && !result[1].finalized);
+        assert_eq!(
+            result[1].missing_finalization_signers,
+            vec![node_test_id(3), node_test_id(4)].into_iter().collect()
+        );
+
+        // Node 4 did not sign anything in the whole range and is still
+        // reported, because it is a member of the subnet.
+        assert_eq!(
+            result[2].missing_notarization_signers,
+            vec![node_test_id(2), node_test_id(4)].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn test_missing_shares_includes_unknown_signers() {
+        let (_, blocks) = chain(1);
+        let members = vec![node_test_id(1)].into_iter().collect();
+        let artifacts = vec![notarization_share(&blocks[0], 5)];
+
+        let result = missing_shares(&artifacts, &members);
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            result[0].missing_notarization_signers,
+            vec![node_test_id(1)].into_iter().collect()
+        );
+        assert_eq!(
+            result[0].missing_finalization_signers,
+            vec![node_test_id(1), node_test_id(5)].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn test_verify_valid_chain() {
+        let (cup, blocks) = chain(3);
+        let mut artifacts = vec![ConsensusMessage::CatchUpPackage(cup)];
+        for block in &blocks {
+            artifacts.push(proposal(block));
+            artifacts.push(notarization(block));
+            artifacts.push(finalization(block));
+        }
+
+        let report = verify_chain(&artifacts);
+        assert_eq!(report.errors, Vec::new());
+        assert_eq!(report.blocks, 4);
+        assert_eq!(report.heights, Some((Height::from(0), Height::from(3))));
+    }
+
+    #[test]
+    fn test_verify_chain_with_broken_parent_link() {
+        let (cup, blocks) = chain(3);
+        let mut orphan = Block::from_parent(&blocks[1]);
+        orphan.parent = CryptoHashOf::from(CryptoHash(vec![1, 2, 3]));
+        let mut artifacts = vec![ConsensusMessage::CatchUpPackage(cup)];
+        for block in &blocks[..2] {
+            artifacts.push(proposal(block));
+            artifacts.push(finalization(block));
+        }
+        artifacts.push(proposal(&orphan));
+        artifacts.push(finalization(&orphan));
+
+        let report = verify_chain(&artifacts);
+        let orphan_hash = crypto_hash(&orphan);
+        assert_eq!(
+            report.errors,
+            vec![
+                ChainError {
+                    height: Height::from(3),
+                    block: orphan_hash.clone(),
+                    error: "parent is not a known block at the previous height",
+                },
+                ChainError {
+                    height: Height::from(3),
+                    block: orphan_hash,
+                    error: "parent is not the finalized block at the previous height",
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_chain_with_unknown_finalized_block() {
+        let (cup, blocks) = chain(2);
+        let artifacts = vec![
+            ConsensusMessage::CatchUpPackage(cup),
+            proposal(&blocks[0]),
+            finalization(&blocks[0]),
+            finalization(&blocks[1]),
+        ];
+
+        let report = verify_chain(&artifacts);
+        assert_eq!(
+            report.errors,
+            vec![ChainError {
+                height: Height::from(2),
+                block: crypto_hash(&blocks[1]),
+                error: "notarized or finalized block is not a known block",
+            }]
+        );
+    }
+}
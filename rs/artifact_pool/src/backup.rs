@@ -641,6 +641,132 @@ impl BackupArtifact {
     }
 }
 
+impl From<BackupArtifact> for ConsensusMessage {
+    fn from(artifact: BackupArtifact) -> Self {
+        use BackupArtifact::*;
+        match artifact {
+            Finalization(artifact) => ConsensusMessage::Finalization(*artifact),
+            Notarization(artifact) => ConsensusMessage::Notarization(*artifact),
+            BlockProposal(artifact) => ConsensusMessage::BlockProposal(*artifact),
+            RandomTape(artifact) => ConsensusMessage::RandomTape(*artifact),
+            RandomBeacon(artifact) => ConsensusMessage::RandomBeacon(*artifact),
+            CatchUpPackage(artifact) => ConsensusMessage::CatchUpPackage(*artifact),
+        }
+    }
+}
+
+/// Artifacts read from a backup by [`read_backup`].
+#[derive(Debug, Default)]
+pub struct BackupContents {
+    /// The artifacts read, sorted by height.
+    pub artifacts: Vec<ConsensusMessage>,
+    /// The files and directories which could not be read, e.g. because they
+    /// are corrupt, together with the respective error.
+    pub unreadable: Vec<(PathBuf, io::Error)>,
+}
+
+/// Reads all artifacts with a height in the given range from the backup at
+/// `path`. The path may point to a replica version directory or to any of its
+/// ancestors, e.g. the directory of a subnet, in which case the artifacts of
+/// all replica versions below it are returned. Files which are not artifacts
+/// written by [`Backup`] are ignored. Artifacts which cannot be read are
+/// skipped and reported in [`BackupContents::unreadable`], so that a single
+/// corrupt file does not hide the rest of the backup.
+pub fn read_backup(path: &Path, range: &HeightRange) -> Result<BackupContents, io::Error> {
+    let mut leaves = Vec::new();
+    get_leaves(path, &mut leaves)?;
+    let mut contents = BackupContents::default();
+    for leaf in leaves {
+        let height = match leaf
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<u64>().ok())
+        {
+            Some(height) => Height::from(height),
+            None => continue,
+        };
+        if height < range.min || height > range.max {
+            continue;
+        }
+        let entries = match fs::read_dir(&leaf) {
+            Ok(entries) => entries,
+            Err(err) => {
+                contents.unreadable.push((leaf, err));
+                continue;
+            }
+        };
+        for entry in entries {
+            let file = match entry {
+                Ok(entry) => entry.path(),
+                Err(err) => {
+                    contents.unreadable.push((leaf.clone(), err));
+                    continue;
+                }
+            };
+            match BackupArtifact::read_from_disk(&file) {
+                Ok(Some(artifact)) => contents.artifacts.push(ConsensusMessage::from(artifact)),
+                Ok(None) => (),
+                Err(err) => contents.unreadable.push((file, err)),
+            }
+        }
+    }
+    contents.artifacts.sort_by_key(|artifact| artifact.height());
+    Ok(contents)
+}
+
+impl BackupArtifact {
+    /// Reads an artifact from a file written by [`BackupArtifact::write_to_disk`].
+    /// The type of the artifact is derived from the file name. Returns `None`
+    /// if the file name does not belong to any backed up artifact type.
+    pub fn read_from_disk(file: &Path) -> Result<Option<Self>, io::Error> {
+        let file_name = match file.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.ends_with(".bin") => name,
+            _ => return Ok(None),
+        };
+        let artifact = if file_name.starts_with("finalization_") {
+            let proto = pb::Finalization::decode(fs::read(file)?.as_slice())?;
+            BackupArtifact::Finalization(Box::new(
+                Finalization::try_from(proto).map_err(|err| invalid_data(file, err))?,
+            ))
+        } else if file_name.starts_with("notarization_") {
+            let proto = pb::Notarization::decode(fs::read(file)?.as_slice())?;
+            BackupArtifact::Notarization(Box::new(
+                Notarization::try_from(proto).map_err(|err| invalid_data(file, err))?,
+            ))
+        } else if file_name.starts_with("block_proposal_") {
+            let proto = pb::BlockProposal::decode(fs::read(file)?.as_slice())?;
+            BackupArtifact::BlockProposal(Box::new(
+                BlockProposal::try_from(proto).map_err(|err| invalid_data(file, err))?,
+            ))
+        } else if file_name == "random_tape.bin" {
+            let proto = pb::RandomTape::decode(fs::read(file)?.as_slice())?;
+            BackupArtifact::RandomTape(Box::new(
+                RandomTape::try_from(proto).map_err(|err| invalid_data(file, err))?,
+            ))
+        } else if file_name == "random_beacon.bin" {
+            let proto = pb::RandomBeacon::decode(fs::read(file)?.as_slice())?;
+            BackupArtifact::RandomBeacon(Box::new(
+                RandomBeacon::try_from(proto).map_err(|err| invalid_data(file, err))?,
+            ))
+        } else if file_name == "catch_up_package.bin" {
+            let proto = pb::CatchUpPackage::decode(fs::read(file)?.as_slice())?;
+            BackupArtifact::CatchUpPackage(Box::new(
+                CatchUpPackage::try_from(&proto).map_err(|err| invalid_data(file, err))?,
+            ))
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(artifact))
+    }
+}
+
+fn invalid_data<E: std::fmt::Debug>(file: &Path, err: E) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Failed to deserialize {}: {:?}", file.display(), err),
+    )
+}
+
 // Dumps a CryptoHash to a hex-encoded string.
 pub(super) fn bytes_to_hex_str<T>(hash: &CryptoHashOf<T>) -> String {
     hash.clone()
@@ -730,4 +856,50 @@ mod tests {
             BlockProposal::try_from(pb::BlockProposal::decode(buf.as_slice()).unwrap()).unwrap()
         );
     }
+
+    #[test]
+    fn test_read_backup() {
+        let backup_dir = tempfile::Builder::new().tempdir().unwrap();
+        let version_path = backup_dir.path().join("subnet").join("version");
+        let artifacts = (1..=5)
+            .flat_map(|height| {
+                let height = Height::from(height);
+                vec![
+                    ConsensusMessage::RandomTape(RandomTape::fake(RandomTapeContent::new(height))),
+                    ConsensusMessage::Finalization(Finalization::fake(FinalizationContent::new(
+                        height,
+                        CryptoHashOf::from(CryptoHash(vec![height.get() as u8])),
+                    ))),
+                    ConsensusMessage::Notarization(Notarization::fake(NotarizationContent::new(
+                        height,
+                        CryptoHashOf::from(CryptoHash(vec![height.get() as u8])),
+                    ))),
+                ]
+            })
+            .collect::<Vec<_>>();
+        store_artifacts(artifacts.clone(), &version_path).unwrap();
+        // Files not written by the backup are ignored.
+        fs::write(version_path.join("0").join("1").join("unknown.bin"), b"").unwrap();
+        // Corrupt artifacts are skipped and reported.
+        let corrupt_file = version_path
+            .join("0")
+            .join("3")
+            .join("finalization_corrupt.bin");
+        fs::write(&corrupt_file, [0xff; 3]).unwrap();
+
+        let range = HeightRange::new(Height::from(2), Height::from(4));
+        let mut expected = artifacts
+            .into_iter()
+            .filter(|artifact| artifact.height() >= range.min && artifact.height() <= range.max)
+            .collect::<Vec<_>>();
+        let contents = read_backup(backup_dir.path(), &range).unwrap();
+        let mut read = contents.artifacts;
+        let key = |artifact: &ConsensusMessage| format!("{:?}", artifact);
+        expected.sort_by_key(key);
+        read.sort_by_key(key);
+        assert_eq!(read, expected);
+        assert_eq!(contents.unreadable.len(), 1);
+        assert_eq!(contents.unreadable[0].0, corrupt_file);
+        assert_eq!(contents.unreadable[0].1.kind(), io::ErrorKind::InvalidData);
+    }
 }
@@ -1,20 +1,28 @@
 use clap::{arg, Arg, Command};
 use ic_artifact_pool::{
+    analysis,
+    backup::read_backup,
     certification_pool::CertificationPoolImpl,
     consensus_pool::{PoolSectionOps, UncachedConsensusPoolImpl},
+    migration::{migrate_persistent_pool, persistent_pool_digests},
 };
-use ic_config::artifact_pool::ArtifactPoolConfig;
+use ic_config::artifact_pool::{ArtifactPoolConfig, ArtifactPoolTomlConfig};
 use ic_interfaces::consensus_pool::*;
 use ic_logger::{LoggerImpl, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_types::{
-    consensus::{certification::CertificationMessage, CatchUpPackage, ConsensusMessageHashable},
+    consensus::{
+        certification::CertificationMessage, CatchUpPackage, ConsensusMessage,
+        ConsensusMessageHashable, HasHeight,
+    },
     time::current_time,
+    Height,
 };
 use prost::Message;
 use serde::{Deserialize, Serialize};
 use serde_bytes_repr::{ByteFmtDeserializer, ByteFmtSerializer};
 use serde_json::{Deserializer, Serializer};
+use std::collections::BTreeSet;
 use std::convert::TryFrom;
 use std::io::BufRead;
 use std::io::Write;
@@ -50,7 +58,67 @@ fn main() {
                         .takes_value(true),
                 ),
         )
-        .arg(arg!(<PATH>       "PATH to the consensus pool directory"));
+        .subcommand(
+            Command::new("migrate")
+                .about("Migrate the pool to another directory and persistent pool backend")
+                .arg(backend_arg("source-backend", "lmdb"))
+                .arg(
+                    Arg::new("target")
+                        .short('t')
+                        .long("target")
+                        .value_name("TARGET_PATH")
+                        .help("Path to the target consensus pool directory")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(backend_arg("target-backend", "rocksdb")),
+        )
+        .subcommand(
+            Command::new("digest")
+                .about("Print the number and digest of artifacts of every type")
+                .arg(backend_arg("backend", "lmdb")),
+        )
+        .subcommand(
+            Command::new("dump")
+                .about("Dump artifacts in the given height range to stdout")
+                .arg(source_arg())
+                .arg(
+                    Arg::new("artifact")
+                        .short('a')
+                        .long("artifact")
+                        .value_name("NAME")
+                        .help("Artifact name")
+                        .multiple_occurrences(true)
+                        .multiple_values(true)
+                        .takes_value(true),
+                )
+                .args(height_range_args()),
+        )
+        .subcommand(
+            Command::new("missing-shares")
+                .about(
+                    "List the heights without a notarization or finalization, and the \
+                     nodes from which no share was seen at these heights",
+                )
+                .long_about(
+                    "List the heights without a notarization or finalization, and the \
+                     nodes from which no share was seen at these heights. The nodes of \
+                     the subnet are taken from the highest catch-up package at or below \
+                     the given range; for backups, only catch-up packages within the \
+                     range are considered. If no catch-up package is found, only the \
+                     nodes which signed an artifact in the range are considered, so a \
+                     node which was down for the whole range is not reported.",
+                )
+                .arg(source_arg())
+                .args(height_range_args()),
+        )
+        .subcommand(
+            Command::new("verify-chain")
+                .about("Verify the hashes of blocks and the hash chain between them")
+                .arg(source_arg())
+                .args(height_range_args()),
+        )
+        .arg(arg!(<PATH>       "PATH to the consensus pool or backup directory"));
     let mut help = Vec::new();
     app.write_help(&mut help)
         .expect("Unable to output help message");
@@ -64,6 +132,16 @@ fn main() {
         import(path)
     } else if let Some(matches) = matches.subcommand_matches("export-cup-proto") {
         export_cup_proto(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("migrate") {
+        migrate(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("digest") {
+        digest(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("dump") {
+        dump(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("missing-shares") {
+        missing_shares(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("verify-chain") {
+        verify_chain(path, matches)
     } else {
         eprintln!(
             "{}",
@@ -124,6 +202,69 @@ fn open_certification_pool(path: &str, read_only: bool) -> CertificationPoolImpl
     CertificationPoolImpl::new(config, log, MetricsRegistry::new())
 }
 
+fn backend_arg(name: &'static str, default: &'static str) -> Arg<'static> {
+    Arg::new(name)
+        .long(name)
+        .value_name("BACKEND")
+        .help("Persistent pool backend, either lmdb or rocksdb")
+        .possible_values(["lmdb", "rocksdb"])
+        .default_value(default)
+        .takes_value(true)
+}
+
+fn source_arg() -> Arg<'static> {
+    Arg::new("source")
+        .long("source")
+        .value_name("SOURCE")
+        .help(
+            "Where to read the artifacts from: a persistent pool with the lmdb or \
+             rocksdb backend, or a backup directory",
+        )
+        .possible_values(["lmdb", "rocksdb", "backup"])
+        .default_value("lmdb")
+        .takes_value(true)
+}
+
+fn height_range_args() -> [Arg<'static>; 2] {
+    [
+        Arg::new("from")
+            .long("from")
+            .value_name("HEIGHT")
+            .help("Lowest height to consider (inclusive)")
+            .takes_value(true),
+        Arg::new("to")
+            .long("to")
+            .value_name("HEIGHT")
+            .help("Highest height to consider (inclusive)")
+            .takes_value(true),
+    ]
+}
+
+fn parse_height_range(matches: &clap::ArgMatches) -> HeightRange {
+    let parse = |name: &str, default: u64| {
+        matches.value_of(name).map_or(default, |value| {
+            value
+                .parse::<u64>()
+                .unwrap_or_else(|err| panic!("Invalid height '{}': {}", value, err))
+        })
+    };
+    HeightRange::new(
+        Height::from(parse("from", 0)),
+        Height::from(parse("to", u64::MAX)),
+    )
+}
+
+fn pool_config(path: &str, backend: &str) -> ArtifactPoolConfig {
+    let mut toml_config = ArtifactPoolTomlConfig::new(PathBuf::from(path), None);
+    toml_config.consensus_pool_backend = Some(backend.to_string());
+    ArtifactPoolConfig::from(toml_config)
+}
+
+fn new_logger() -> ReplicaLogger {
+    let logger = LoggerImpl::new(&Default::default(), "consensus_pool_util".to_string());
+    ReplicaLogger::new(logger.root.clone().into())
+}
+
 fn from_str<'a, T: Deserialize<'a>>(json: &'a str) -> Result<T, serde_json::Error> {
     let mut json_de = Deserializer::from_str(json);
     let bytefmt_json_de = ByteFmtDeserializer::new_hex(&mut json_de);
@@ -272,3 +413,240 @@ fn export_cup_proto(path: &str, matches: &clap::ArgMatches) {
     file.write_all(&buf)
         .unwrap_or_else(|err| panic!("Cannot write to file {}: {:?}", filename, err));
 }
+
+fn migrate(path: &str, matches: &clap::ArgMatches) {
+    let source = pool_config(
+        path,
+        matches
+            .value_of("source-backend")
+            .expect("Expect a source backend"),
+    );
+    let target = pool_config(
+        matches.value_of("target").expect("Expect a target path"),
+        matches
+            .value_of("target-backend")
+            .expect("Expect a target backend"),
+    );
+    let summaries = migrate_persistent_pool(source, target, new_logger())
+        .unwrap_or_else(|err| panic!("Migration failed: {}", err));
+    for (artifact, summary) in summaries {
+        println!(
+            "{}: copied={} skipped={} {}",
+            artifact, summary.copied, summary.skipped, summary.digest
+        );
+    }
+}
+
+fn digest(path: &str, matches: &clap::ArgMatches) {
+    let config = pool_config(path, matches.value_of("backend").expect("Expect a backend"));
+    let digests = persistent_pool_digests(config, new_logger())
+        .unwrap_or_else(|err| panic!("Cannot compute digests: {}", err));
+    for (artifact, digest) in digests {
+        println!("{}: {}", artifact, digest);
+    }
+}
+
+/// Artifacts read by the analysis subcommands, either from a persistent pool
+/// or from a backup. Backups only contain the consensus artifacts needed to
+/// replay the chain, i.e. no shares and no certifications.
+enum ArtifactSource {
+    Pool {
+        consensus_pool: UncachedConsensusPoolImpl,
+        certification_pool: CertificationPoolImpl,
+    },
+    Backup(PathBuf),
+}
+
+impl ArtifactSource {
+    fn open(path: &str, matches: &clap::ArgMatches) -> Self {
+        match matches.value_of("source").expect("Expect a source") {
+            "backup" => ArtifactSource::Backup(PathBuf::from(path)),
+            backend => {
+                let mut config = pool_config(path, backend);
+                config.persistent_pool_read_only = true;
+                ArtifactSource::Pool {
+                    consensus_pool: UncachedConsensusPoolImpl::new(config.clone(), new_logger()),
+                    certification_pool: CertificationPoolImpl::new(
+                        config,
+                        new_logger(),
+                        MetricsRegistry::new(),
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Returns all validated consensus artifacts in the given height range.
+    fn consensus_artifacts(&self, range: &HeightRange) -> Vec<ConsensusMessage> {
+        let consensus_pool = match self {
+            ArtifactSource::Pool { consensus_pool, .. } => consensus_pool,
+            ArtifactSource::Backup(path) => {
+                let contents = read_backup(path, range)
+                    .unwrap_or_else(|err| panic!("Cannot read backup: {}", err));
+                for (path, err) in contents.unreadable {
+                    eprintln!("Skipping unreadable {}: {}", path.display(), err);
+                }
+                return contents.artifacts;
+            }
+        };
+        let pool = consensus_pool.validated();
+        let mut artifacts = Vec::new();
+        artifacts.extend(messages_in_range(pool.random_beacon(), range));
+        artifacts.extend(messages_in_range(pool.finalization(), range));
+        artifacts.extend(messages_in_range(pool.notarization(), range));
+        artifacts.extend(messages_in_range(pool.block_proposal(), range));
+        artifacts.extend(messages_in_range(pool.random_beacon_share(), range));
+        artifacts.extend(messages_in_range(pool.notarization_share(), range));
+        artifacts.extend(messages_in_range(pool.finalization_share(), range));
+        artifacts.extend(messages_in_range(pool.random_tape(), range));
+        artifacts.extend(messages_in_range(pool.random_tape_share(), range));
+        artifacts.extend(messages_in_range(pool.catch_up_package(), range));
+        artifacts.extend(messages_in_range(pool.catch_up_package_share(), range));
+        artifacts
+    }
+
+    /// Returns the highest catch-up package at or below the top of the given
+    /// range. For backups, only the given artifacts read from the range are
+    /// searched.
+    fn catch_up_package(
+        &self,
+        range: &HeightRange,
+        artifacts: &[ConsensusMessage],
+    ) -> Option<CatchUpPackage> {
+        match self {
+            ArtifactSource::Pool { consensus_pool, .. } => consensus_pool
+                .validated()
+                .catch_up_package()
+                .get_by_height_range(HeightRange::new(Height::from(0), range.max))
+                .last(),
+            ArtifactSource::Backup(_) => artifacts
+                .iter()
+                .filter_map(|msg| match msg {
+                    ConsensusMessage::CatchUpPackage(cup) => Some(cup.clone()),
+                    _ => None,
+                })
+                .max_by_key(|cup| cup.height()),
+        }
+    }
+
+    /// Returns all certification artifacts in the given height range.
+    fn certification_artifacts(&self, range: &HeightRange) -> Vec<CertificationMessage> {
+        match self {
+            ArtifactSource::Pool {
+                certification_pool, ..
+            } => {
+                let pool = &certification_pool.persistent_pool;
+                pool.certifications()
+                    .get_by_height_range(range.clone())
+                    .map(CertificationMessage::Certification)
+                    .chain(
+                        pool.certification_shares()
+                            .get_by_height_range(range.clone())
+                            .map(CertificationMessage::CertificationShare),
+                    )
+                    .collect()
+            }
+            ArtifactSource::Backup(_) => Vec::new(),
+        }
+    }
+}
+
+fn messages_in_range<T: ConsensusMessageHashable + 'static>(
+    pool: &dyn HeightIndexedPool<T>,
+    range: &HeightRange,
+) -> impl Iterator<Item = ConsensusMessage> {
+    pool.get_by_height_range(range.clone())
+        .map(ConsensusMessageHashable::into_message)
+}
+
+fn artifact_name(msg: &ConsensusMessage) -> &'static str {
+    match msg {
+        ConsensusMessage::RandomBeacon(_) => "RandomBeacon",
+        ConsensusMessage::Finalization(_) => "Finalization",
+        ConsensusMessage::Notarization(_) => "Notarization",
+        ConsensusMessage::BlockProposal(_) => "BlockProposal",
+        ConsensusMessage::RandomBeaconShare(_) => "RandomBeaconShare",
+        ConsensusMessage::NotarizationShare(_) => "NotarizationShare",
+        ConsensusMessage::FinalizationShare(_) => "FinalizationShare",
+        ConsensusMessage::RandomTape(_) => "RandomTape",
+        ConsensusMessage::RandomTapeShare(_) => "RandomTapeShare",
+        ConsensusMessage::CatchUpPackage(_) => "CatchUpPackage",
+        ConsensusMessage::CatchUpPackageShare(_) => "CatchUpPackageShare",
+    }
+}
+
+fn dump(path: &str, matches: &clap::ArgMatches) {
+    let artifacts = match matches.values_of("artifact") {
+        Some(names) => parse_artifact_names(&names.collect::<Vec<&str>>()),
+        None => ALL_ARTIFACT_NAMES.to_vec(),
+    };
+    let range = parse_height_range(matches);
+    let source = ArtifactSource::open(path, matches);
+
+    let consensus_artifacts = source.consensus_artifacts(&range);
+    let certification_artifacts = source.certification_artifacts(&range);
+    for artifact in artifacts {
+        for msg in consensus_artifacts
+            .iter()
+            .filter(|msg| artifact_name(msg) == artifact)
+        {
+            println!("{}", to_string(msg));
+        }
+        for msg in certification_artifacts.iter().filter(|msg| match msg {
+            CertificationMessage::Certification(_) => artifact == "Certification",
+            CertificationMessage::CertificationShare(_) => artifact == "CertificationShare",
+        }) {
+            println!("{}", to_string(msg));
+        }
+    }
+}
+
+/// Prints, for every height in the range that lacks a notarization or a
+/// finalization, the nodes that signed a share or an aggregate at this height
+/// and the nodes that did not.
+fn missing_shares(path: &str, matches: &clap::ArgMatches) {
+    let range = parse_height_range(matches);
+    let source = ArtifactSource::open(path, matches);
+
+    let artifacts = source.consensus_artifacts(&range);
+    let members = match source.catch_up_package(&range, &artifacts) {
+        Some(cup) => analysis::subnet_members(&cup),
+        None => {
+            eprintln!(
+                "No catch-up package found at or below the given range, only nodes which \
+                 signed an artifact in the range are considered"
+            );
+            BTreeSet::new()
+        }
+    };
+    for signatures in analysis::missing_shares(&artifacts, &members) {
+        println!("{}", to_string(&signatures));
+    }
+}
+
+/// Verifies the hashes of blocks and the hash chain between them, see
+/// [`analysis::verify_chain`]. Exits with an error code if a violation was
+/// found.
+fn verify_chain(path: &str, matches: &clap::ArgMatches) {
+    let range = parse_height_range(matches);
+    let source = ArtifactSource::open(path, matches);
+
+    let report = analysis::verify_chain(&source.consensus_artifacts(&range));
+    let (lowest_height, highest_height) = match report.heights {
+        Some(heights) => heights,
+        None => return,
+    };
+    for error in &report.errors {
+        println!("{}", to_string(error));
+    }
+    eprintln!(
+        "Verified {} blocks between heights {} and {}: {} errors",
+        report.blocks,
+        lowest_height,
+        highest_height,
+        report.errors.len()
+    );
+    if !report.errors.is_empty() {
+        std::process::exit(1);
+    }
+}
@@ -60,9 +60,10 @@ impl CertificationPoolImpl {
                 ),
             ) as Box<_>,
             #[cfg(feature = "rocksdb_backend")]
-            PersistentPoolBackend::RocksDB(config) => Box::new(
+            PersistentPoolBackend::RocksDB(rocksdb_config) => Box::new(
                 crate::rocksdb_pool::PersistentHeightIndexedPool::new_certification_pool(
-                    config,
+                    rocksdb_config,
+                    config.persistent_pool_read_only,
                     log.clone(),
                 ),
             ) as Box<_>,
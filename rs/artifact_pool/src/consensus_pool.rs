@@ -275,9 +275,10 @@ impl UncachedConsensusPoolImpl {
                 ),
             ) as Box<_>,
             #[cfg(feature = "rocksdb_backend")]
-            PersistentPoolBackend::RocksDB(config) => Box::new(
+            PersistentPoolBackend::RocksDB(rocksdb_config) => Box::new(
                 crate::rocksdb_pool::PersistentHeightIndexedPool::new_consensus_pool(
-                    config,
+                    rocksdb_config,
+                    config.persistent_pool_read_only,
                     log.clone(),
                 ),
             ) as Box<_>,
@@ -11,8 +11,7 @@ use ic_interfaces::{
     },
     ingress_pool::{
         ChangeAction, ChangeSet, IngressPool, IngressPoolObject, IngressPoolSelect,
-        IngressPoolThrottler, PoolSection, SelectResult, UnvalidatedIngressArtifact,
-        ValidatedIngressArtifact,
+        IngressPoolThrottler, PoolSection, UnvalidatedIngressArtifact, ValidatedIngressArtifact,
     },
     time_source::TimeSource,
 };
@@ -22,17 +21,23 @@ use ic_types::{
     artifact::{Advert, IngressMessageAttribute, IngressMessageId, Priority, PriorityFn},
     artifact_kind::IngressArtifact,
     messages::{MessageId, SignedIngress, EXPECTED_MESSAGE_ID_LENGTH},
-    CountBytes, NodeId, Time,
+    CountBytes, NodeId, Time, UserId,
 };
-use prometheus::IntCounter;
-use std::collections::BTreeMap;
+use prometheus::{IntCounter, IntGaugeVec};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
+/// The number of senders with the most validated messages in the pool that
+/// are reported in the `ingress_pool_top_senders_messages` metric.
+const NUMBER_OF_TOP_SENDERS: usize = 10;
+
 #[derive(Clone)]
 struct IngressPoolSection<T: AsRef<IngressPoolObject>> {
     /// Do not insert or remove elements in this map directly. Use this struct's
     /// associated functions [`insert`], [`remove`] and [`purge_below`].
     artifacts: BTreeMap<IngressMessageId, T>,
+    /// The number of artifacts per sender. Updated alongside `artifacts`.
+    senders: HashMap<UserId, usize>,
     metrics: PoolMetrics,
     /// Note: The byte size is updated incrementally as a side-effect of insert, remove
     /// and purge invocations. Never modify the artifacts map directly! Use the
@@ -49,6 +54,7 @@ impl<T: AsRef<IngressPoolObject>> IngressPoolSection<T> {
     fn new(metrics: PoolMetrics) -> IngressPoolSection<T> {
         IngressPoolSection {
             artifacts: BTreeMap::new(),
+            senders: HashMap::new(),
             metrics,
             byte_size: 0,
         }
@@ -61,6 +67,7 @@ impl<T: AsRef<IngressPoolObject>> IngressPoolSection<T> {
             .with_label_values(&["insert"])
             .start_timer();
         let new_artifact_size = artifact.as_ref().count_bytes();
+        let sender = artifact.as_ref().signed_ingress.sender();
         self.metrics.observe_insert(new_artifact_size);
         if let Some(previous) = self.artifacts.insert(message_id, artifact) {
             let prev_size = previous.as_ref().count_bytes();
@@ -69,6 +76,7 @@ impl<T: AsRef<IngressPoolObject>> IngressPoolSection<T> {
             self.metrics.observe_duplicate(prev_size);
         } else {
             self.byte_size += new_artifact_size;
+            *self.senders.entry(sender).or_default() += 1;
         }
         // SAFETY: Checking byte size invariant
         section_ok(self);
@@ -84,6 +92,7 @@ impl<T: AsRef<IngressPoolObject>> IngressPoolSection<T> {
         if let Some(artifact) = &removed {
             self.byte_size -= artifact.as_ref().count_bytes();
             self.metrics.observe_remove(artifact.as_ref().count_bytes());
+            self.decrement_sender_count(artifact.as_ref().signed_ingress.sender());
         }
         // SAFETY: Checking byte size invariant
         section_ok(self);
@@ -115,11 +124,26 @@ impl<T: AsRef<IngressPoolObject>> IngressPoolSection<T> {
             let artifact_size = artifact.as_ref().count_bytes();
             self.byte_size -= artifact_size;
             self.metrics.observe_remove(artifact_size);
+            self.decrement_sender_count(artifact.as_ref().signed_ingress.sender());
         }
         // SAFETY: Checking byte size invariant
         section_ok(self);
         Box::new(to_remove.into_values())
     }
+    /// Returns the number of artifacts of the given sender.
+    fn count_by_sender(&self, sender: &UserId) -> usize {
+        self.senders.get(sender).copied().unwrap_or_default()
+    }
+
+    fn decrement_sender_count(&mut self, sender: UserId) {
+        if let std::collections::hash_map::Entry::Occupied(mut entry) = self.senders.entry(sender) {
+            *entry.get_mut() -= 1;
+            if *entry.get() == 0 {
+                entry.remove();
+            }
+        }
+    }
+
     /// Counts the exact bytes by iterating over the artifact btreemap, instead
     /// of returning the memoized byte_size.
     fn count_bytes_slow(&self) -> usize {
@@ -189,7 +213,11 @@ pub struct IngressPoolImpl {
     // Track unvalidated pool quota usage only
     ingress_pool_max_count: usize,
     ingress_pool_max_bytes: usize,
+    ingress_pool_max_count_per_sender: usize,
+    ingress_pool_max_count_anonymous: usize,
     ingress_messages_throttled: IntCounter,
+    ingress_messages_dropped_per_sender: IntCounter,
+    top_senders: IntGaugeVec,
     node_id: NodeId,
     log: ReplicaLogger,
 }
@@ -206,10 +234,21 @@ impl IngressPoolImpl {
         IngressPoolImpl {
             ingress_pool_max_count: config.ingress_pool_max_count,
             ingress_pool_max_bytes: config.ingress_pool_max_bytes,
+            ingress_pool_max_count_per_sender: config.ingress_pool_max_count_per_sender,
+            ingress_pool_max_count_anonymous: config.ingress_pool_max_count_anonymous,
             ingress_messages_throttled: metrics_registry.int_counter(
                 "ingress_messages_throttled",
                 "Number of throttled ingress messages",
             ),
+            ingress_messages_dropped_per_sender: metrics_registry.int_counter(
+                "ingress_messages_dropped_per_sender_limit",
+                "Number of ingress messages dropped because their sender exceeded its slots in the pool",
+            ),
+            top_senders: metrics_registry.int_gauge_vec(
+                "ingress_pool_top_senders_messages",
+                "Number of messages in the ingress pool of the senders with the most messages",
+                &["sender"],
+            ),
             validated: IngressPoolSection::new(PoolMetrics::new(
                 metrics_registry.clone(),
                 POOL_INGRESS,
@@ -242,6 +281,35 @@ impl IngressPoolImpl {
             }
         }
     }
+
+    /// Returns whether the given sender already has
+    /// `ingress_pool_max_count_per_sender` messages in the validated section,
+    /// or `ingress_pool_max_count_anonymous` for the anonymous principal, which
+    /// anyone can send messages as.
+    ///
+    /// Only validated messages are counted, as the sender of an unvalidated
+    /// message has not been authenticated yet.
+    fn exceeds_sender_limit(&self, sender: &UserId) -> bool {
+        let limit = if sender.get().is_anonymous() {
+            self.ingress_pool_max_count_anonymous
+        } else {
+            self.ingress_pool_max_count_per_sender
+        };
+        self.validated.count_by_sender(sender) >= limit
+    }
+
+    /// Updates the metric reporting the senders with the most validated
+    /// messages in the pool.
+    fn update_top_senders_metric(&self) {
+        let mut occupancy: Vec<_> = self.validated.senders.iter().collect();
+        occupancy.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+        self.top_senders.reset();
+        for (sender, count) in occupancy.into_iter().take(NUMBER_OF_TOP_SENDERS) {
+            self.top_senders
+                .with_label_values(&[&sender.to_string()])
+                .set(*count as i64);
+        }
+    }
 }
 
 impl IngressPool for IngressPoolImpl {
@@ -308,18 +376,31 @@ impl MutablePool<IngressArtifact, ChangeSet> for IngressPoolImpl {
                     attribute,
                     integrity_hash,
                 )) => {
-                    if source_node_id == self.node_id {
-                        adverts.push(Advert {
-                            size,
-                            id: message_id.clone(),
-                            attribute: attribute.clone(),
-                            integrity_hash: integrity_hash.clone(),
-                        });
-                    }
                     // remove it from unvalidated pool and remove it from peer_index, move it
                     // to the validated pool
                     match self.remove_unvalidated(&message_id) {
-                        Some((unvalidated_artifact, size)) => {
+                        Some((unvalidated_artifact, _))
+                            if self.exceeds_sender_limit(
+                                &unvalidated_artifact.message.signed_ingress.sender(),
+                            ) =>
+                        {
+                            self.ingress_messages_dropped_per_sender.inc();
+                            debug!(
+                                self.log,
+                                "Ingress pool: drop message {} because sender {} has no slots left",
+                                message_id,
+                                unvalidated_artifact.message.signed_ingress.sender()
+                            );
+                        }
+                        Some((unvalidated_artifact, unvalidated_size)) => {
+                            if source_node_id == self.node_id {
+                                adverts.push(Advert {
+                                    size,
+                                    id: message_id.clone(),
+                                    attribute: attribute.clone(),
+                                    integrity_hash: integrity_hash.clone(),
+                                });
+                            }
                             self.validated.insert(
                                 message_id,
                                 ValidatedIngressArtifact {
@@ -329,7 +410,8 @@ impl MutablePool<IngressArtifact, ChangeSet> for IngressPoolImpl {
                             );
                             debug!(
                                 self.log,
-                                "Ingress pool: move {} bytes from unvalidated to validated", size
+                                "Ingress pool: move {} bytes from unvalidated to validated",
+                                unvalidated_size
                             );
                         }
                         None => {
@@ -386,6 +468,9 @@ impl MutablePool<IngressArtifact, ChangeSet> for IngressPoolImpl {
                 }
             }
         }
+        if changed {
+            self.update_top_senders_metric();
+        }
         ChangeResult {
             purged,
             adverts,
@@ -419,10 +504,8 @@ impl IngressPoolSelect for IngressPoolImpl {
     fn select_validated<'a>(
         &self,
         range: std::ops::RangeInclusive<Time>,
-        mut f: Box<dyn FnMut(&IngressPoolObject) -> SelectResult<SignedIngress> + 'a>,
+        f: Box<dyn FnOnce(Vec<&IngressPoolObject>) -> Vec<SignedIngress> + 'a>,
     ) -> Vec<SignedIngress> {
-        let mut collected = Vec::new();
-
         let mut artifacts = self
             .validated()
             .get_all_by_expiry_range(range)
@@ -433,14 +516,10 @@ impl IngressPoolSelect for IngressPoolImpl {
         // times, we sort the ingress messages by the time they were delivered to the pool.
         artifacts.sort_unstable_by_key(|artifact| artifact.timestamp);
 
-        for artifact in artifacts {
-            match f(&artifact.msg) {
-                SelectResult::Selected(msg) => collected.push(msg),
-                SelectResult::Skip => (),
-                SelectResult::Abort => break,
-            }
-        }
-        collected
+        f(artifacts
+            .into_iter()
+            .map(|artifact| &artifact.msg)
+            .collect())
     }
 }
 
@@ -504,11 +583,13 @@ mod tests {
     use ic_interfaces::artifact_pool::MutablePool;
     use ic_interfaces::time_source::{SysTimeSource, TimeSource};
     use ic_test_utilities::{
-        mock_time, types::ids::node_test_id, types::messages::SignedIngressBuilder,
+        mock_time,
+        types::ids::{node_test_id, user_test_id},
+        types::messages::SignedIngressBuilder,
         FastForwardTimeSource,
     };
     use ic_test_utilities_logger::with_test_replica_logger;
-    use ic_types::artifact::IngressMessageAttribute;
+    use ic_types::{artifact::IngressMessageAttribute, PrincipalId};
     use rand::Rng;
     use std::time::Duration;
 
@@ -895,8 +976,11 @@ mod tests {
 
                 let selected = ingress_pool.select_validated(
                     time(10)..=time(50),
-                    Box::new(|ingress_obj| {
-                        SelectResult::Selected(ingress_obj.signed_ingress.clone())
+                    Box::new(|ingress_objs| {
+                        ingress_objs
+                            .into_iter()
+                            .map(|ingress_obj| ingress_obj.signed_ingress.clone())
+                            .collect()
                     }),
                 );
                 assert_eq!(
@@ -930,18 +1014,21 @@ mod tests {
                 insert_validated_artifact_with_timestamps(&mut ingress_pool, 5, time(5), time(10));
                 insert_validated_artifact_with_timestamps(&mut ingress_pool, 6, time(6), time(10));
 
-                let mut counter = 0;
+                let mut num_candidates = 0;
 
                 let selected = ingress_pool.select_validated(
                     time(10)..=time(10),
-                    Box::new(|ingress_obj| {
-                        let result = match counter {
-                            1 | 3 => SelectResult::Skip,
-                            4 => SelectResult::Abort,
-                            _ => SelectResult::Selected(ingress_obj.signed_ingress.clone()),
-                        };
-                        counter += 1;
-                        result
+                    Box::new(|ingress_objs| {
+                        num_candidates = ingress_objs.len();
+                        ingress_objs
+                            .into_iter()
+                            // Abort at the fifth message
+                            .take(4)
+                            .enumerate()
+                            // Skip every other message
+                            .filter(|(i, _)| i % 2 == 0)
+                            .map(|(_, ingress_obj)| ingress_obj.signed_ingress.clone())
+                            .collect()
                     }),
                 );
 
@@ -952,6 +1039,7 @@ mod tests {
                 // nonce = 4 Aborted
                 // nonce = 5 not selected because already aborted
                 // nonce = 6 not selected because already aborted
+                assert_eq!(num_candidates, 7);
                 assert_eq!(
                     selected
                         .iter()
@@ -963,6 +1051,65 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_validation_drops_messages_of_sender_without_slots() {
+        with_test_replica_logger(|log| {
+            ic_test_utilities::artifact_pool_config::with_test_pool_config(|mut pool_config| {
+                pool_config.ingress_pool_max_count_per_sender = 2;
+                pool_config.ingress_pool_max_count_anonymous = 3;
+                let time_source = FastForwardTimeSource::new();
+                let metrics_registry = MetricsRegistry::new();
+                let mut ingress_pool =
+                    IngressPoolImpl::new(node_test_id(0), pool_config, metrics_registry, log);
+
+                // Three messages of user 1 and four of the anonymous principal.
+                let anonymous = UserId::from(PrincipalId::new_anonymous());
+                let senders = [
+                    user_test_id(1),
+                    user_test_id(1),
+                    user_test_id(1),
+                    anonymous,
+                    anonymous,
+                    anonymous,
+                    anonymous,
+                ];
+                let mut changeset = ChangeSet::new();
+                for (nonce, sender) in senders.into_iter().enumerate() {
+                    let ingress_msg = SignedIngressBuilder::new()
+                        .sender(sender)
+                        .nonce(nonce as u64)
+                        .build();
+                    changeset.push(ChangeAction::MoveToValidated((
+                        IngressMessageId::from(&ingress_msg),
+                        node_test_id(100),
+                        0,
+                        IngressMessageAttribute::new(&ingress_msg),
+                        ic_types::crypto::crypto_hash(ingress_msg.binary()).get(),
+                    )));
+                    ingress_pool.insert(UnvalidatedArtifact {
+                        message: ingress_msg,
+                        peer_id: node_test_id(100),
+                        timestamp: time_source.get_relative_time(),
+                    });
+                }
+
+                // Unvalidated messages do not count towards the limit.
+                assert_eq!(ingress_pool.unvalidated.size(), 7);
+                assert_eq!(ingress_pool.validated.count_by_sender(&user_test_id(1)), 0);
+
+                ingress_pool.apply_changes(&SysTimeSource::new(), changeset);
+
+                // The third message of user 1 and the fourth of the anonymous principal,
+                // which has its own limit, are dropped on validation.
+                assert_eq!(ingress_pool.unvalidated.size(), 0);
+                assert_eq!(ingress_pool.validated.size(), 5);
+                assert_eq!(ingress_pool.validated.count_by_sender(&user_test_id(1)), 2);
+                assert_eq!(ingress_pool.validated.count_by_sender(&anonymous), 3);
+                assert_eq!(ingress_pool.ingress_messages_dropped_per_sender.get(), 2);
+            })
+        })
+    }
+
     fn insert_validated_artifact(ingress_pool: &mut IngressPoolImpl, nonce: u64) {
         insert_validated_artifact_with_timestamps(
             ingress_pool,
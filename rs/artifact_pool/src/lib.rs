@@ -1,3 +1,4 @@
+pub mod analysis;
 pub mod canister_http_pool;
 pub mod certification_pool;
 pub mod consensus_pool;
@@ -8,6 +9,7 @@ mod height_index;
 pub mod ingress_pool;
 mod inmemory_pool;
 mod metrics;
+pub mod migration;
 mod pool_common;
 #[cfg(test)]
 mod test_utils;
@@ -0,0 +1,595 @@
+//! Migration of the persistent consensus and certification pools from one
+//! [`PersistentPoolBackend`] to another.
+//!
+//! Artifacts are streamed height by height from the source into the target
+//! pool, which maintains its own height index as the artifacts are inserted.
+//! Artifacts that are already present in the target are skipped, so an
+//! interrupted migration is resumed by simply running it again. After the copy,
+//! both pools are compared by the number and a digest of the artifacts of every
+//! type.
+//!
+//! ECDSA artifacts are only persisted by the LMDB backend, so a migration from
+//! LMDB to another backend fails if the source pool contains any.
+use crate::{
+    certification_pool::{CertificationPoolImpl, MutablePoolSection as CertificationPoolSection},
+    consensus_pool::{
+        InitializablePoolSection, MutablePoolSection, PoolSectionOps, UncachedConsensusPoolImpl,
+    },
+    ecdsa_pool::EcdsaPoolImpl,
+};
+use ic_config::artifact_pool::{ArtifactPoolConfig, PersistentPoolBackend};
+use ic_crypto_sha2::Sha256;
+use ic_interfaces::{
+    consensus_pool::{HeightIndexedPool, PoolSection, ValidatedConsensusArtifact},
+    ecdsa::EcdsaPool,
+};
+use ic_logger::{info, ReplicaLogger};
+use ic_metrics::MetricsRegistry;
+use ic_types::{
+    consensus::{
+        certification::{Certification, CertificationMessage, CertificationShare},
+        ConsensusMessageHashable, HasHeight,
+    },
+    crypto::{crypto_hash, CryptoHashable},
+    time::current_time,
+    Height,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Errors that can occur when migrating a persistent pool.
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The source and the target pool are stored at the same path.
+    SamePath,
+    /// The given backend is not supported by this build.
+    UnsupportedBackend(String),
+    /// The source pool contains ECDSA artifacts, which the target backend does
+    /// not persist.
+    EcdsaArtifactsNotMigrated(usize),
+    /// The target pool differs from the source pool after the migration.
+    VerificationFailed {
+        artifact: &'static str,
+        source: ArtifactDigest,
+        target: ArtifactDigest,
+    },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::SamePath => {
+                write!(
+                    f,
+                    "Source and target pool must be stored at different paths"
+                )
+            }
+            MigrationError::UnsupportedBackend(backend) => {
+                write!(f, "Unsupported persistent pool backend: {}", backend)
+            }
+            MigrationError::EcdsaArtifactsNotMigrated(count) => write!(
+                f,
+                "The source pool contains {} ECDSA artifacts, which the target backend \
+                 does not persist",
+                count
+            ),
+            MigrationError::VerificationFailed {
+                artifact,
+                source,
+                target,
+            } => write!(
+                f,
+                "{} artifacts differ after migration: source {}, target {}",
+                artifact, source, target
+            ),
+        }
+    }
+}
+
+/// The number of artifacts of a type in a pool, and a digest over their
+/// heights and hashes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ArtifactDigest {
+    pub count: usize,
+    pub hash: [u8; 32],
+}
+
+impl fmt::Display for ArtifactDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "count={} hash=", self.count)?;
+        for byte in self.hash {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// The result of migrating the artifacts of one type.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ArtifactMigrationSummary {
+    /// The number of artifacts copied into the target pool.
+    pub copied: usize,
+    /// The number of artifacts that were already present in the target pool.
+    pub skipped: usize,
+    /// The digest of the artifacts, which is equal for source and target.
+    pub digest: ArtifactDigest,
+}
+
+/// Copies all validated consensus and certification artifacts from the
+/// persistent pool described by `source` into the one described by `target`,
+/// and verifies that both pools contain the same artifacts afterwards.
+///
+/// Returns a summary for every artifact type, by name.
+pub fn migrate_persistent_pool(
+    source: ArtifactPoolConfig,
+    target: ArtifactPoolConfig,
+    log: ReplicaLogger,
+) -> Result<BTreeMap<&'static str, ArtifactMigrationSummary>, MigrationError> {
+    check_backend_supported(&source)?;
+    check_backend_supported(&target)?;
+    if source.persistent_pool_db_path() == target.persistent_pool_db_path() {
+        return Err(MigrationError::SamePath);
+    }
+
+    let mut source = source;
+    source.persistent_pool_read_only = true;
+    if !matches!(
+        target.persistent_pool_backend,
+        PersistentPoolBackend::Lmdb(_)
+    ) {
+        let count = count_ecdsa_artifacts(&source, &log);
+        if count > 0 {
+            return Err(MigrationError::EcdsaArtifactsNotMigrated(count));
+        }
+    }
+    let source_consensus_pool = UncachedConsensusPoolImpl::new(source.clone(), log.clone());
+    let source_certification_pool =
+        CertificationPoolImpl::new(source, log.clone(), MetricsRegistry::new());
+    let mut target_consensus_pool = UncachedConsensusPoolImpl::new(target.clone(), log.clone());
+    let target_certification_pool =
+        CertificationPoolImpl::new(target, log.clone(), MetricsRegistry::new());
+
+    // The highest CUP is inserted together with the protobuf it was received as,
+    // such that it is served unchanged from the target pool.
+    let source_section = source_consensus_pool.validated.pool_section();
+    let target_section = &mut *target_consensus_pool.validated;
+    if source_section.catch_up_package().height_range().is_some() {
+        let cup = source_section.catch_up_package().get_highest().ok();
+        if let Some(cup) = cup.filter(|cup| !target_section.contains(&cup.get_id())) {
+            info!(log, "Migrating CatchUpPackage at height {}", cup.height());
+            target_section.insert_cup_with_proto(source_section.highest_catch_up_package_proto());
+        }
+    }
+
+    let mut summaries = BTreeMap::new();
+    let mut record = |name: &'static str, (copied, skipped): (usize, usize)| {
+        info!(
+            log,
+            "Migrated {} artifacts: {} copied, {} skipped", name, copied, skipped
+        );
+        summaries.insert(
+            name,
+            ArtifactMigrationSummary {
+                copied,
+                skipped,
+                digest: ArtifactDigest::default(),
+            },
+        );
+    };
+    record(
+        "RandomBeacon",
+        copy_consensus(source_section, target_section, |p| p.random_beacon()),
+    );
+    record(
+        "Finalization",
+        copy_consensus(source_section, target_section, |p| p.finalization()),
+    );
+    record(
+        "Notarization",
+        copy_consensus(source_section, target_section, |p| p.notarization()),
+    );
+    record(
+        "BlockProposal",
+        copy_consensus(source_section, target_section, |p| p.block_proposal()),
+    );
+    record(
+        "RandomBeaconShare",
+        copy_consensus(source_section, target_section, |p| p.random_beacon_share()),
+    );
+    record(
+        "NotarizationShare",
+        copy_consensus(source_section, target_section, |p| p.notarization_share()),
+    );
+    record(
+        "FinalizationShare",
+        copy_consensus(source_section, target_section, |p| p.finalization_share()),
+    );
+    record(
+        "RandomTape",
+        copy_consensus(source_section, target_section, |p| p.random_tape()),
+    );
+    record(
+        "RandomTapeShare",
+        copy_consensus(source_section, target_section, |p| p.random_tape_share()),
+    );
+    record(
+        "CatchUpPackage",
+        copy_consensus(source_section, target_section, |p| p.catch_up_package()),
+    );
+    record(
+        "CatchUpPackageShare",
+        copy_consensus(source_section, target_section, |p| {
+            p.catch_up_package_share()
+        }),
+    );
+
+    let source_section = &*source_certification_pool.persistent_pool;
+    let target_section = &*target_certification_pool.persistent_pool;
+    record(
+        "Certification",
+        copy_certification(
+            source_section,
+            target_section,
+            |p| p.certifications(),
+            CertificationMessage::Certification,
+        ),
+    );
+    record(
+        "CertificationShare",
+        copy_certification(
+            source_section,
+            target_section,
+            |p| p.certification_shares(),
+            CertificationMessage::CertificationShare,
+        ),
+    );
+
+    let source_digests = digests(&source_consensus_pool, &source_certification_pool);
+    let target_digests = digests(&target_consensus_pool, &target_certification_pool);
+    for (name, summary) in summaries.iter_mut() {
+        let source = source_digests[name].clone();
+        let target = target_digests[name].clone();
+        if source != target {
+            return Err(MigrationError::VerificationFailed {
+                artifact: name,
+                source,
+                target,
+            });
+        }
+        summary.digest = source;
+    }
+    Ok(summaries)
+}
+
+/// Returns the [`ArtifactDigest`] of every type of validated consensus and
+/// certification artifact in the persistent pool described by `config`, by
+/// name.
+pub fn persistent_pool_digests(
+    config: ArtifactPoolConfig,
+    log: ReplicaLogger,
+) -> Result<BTreeMap<&'static str, ArtifactDigest>, MigrationError> {
+    check_backend_supported(&config)?;
+    let mut config = config;
+    config.persistent_pool_read_only = true;
+    let consensus_pool = UncachedConsensusPoolImpl::new(config.clone(), log.clone());
+    let certification_pool = CertificationPoolImpl::new(config, log, MetricsRegistry::new());
+    Ok(digests(&consensus_pool, &certification_pool))
+}
+
+fn check_backend_supported(config: &ArtifactPoolConfig) -> Result<(), MigrationError> {
+    match &config.persistent_pool_backend {
+        PersistentPoolBackend::Lmdb(_) => Ok(()),
+        #[cfg(feature = "rocksdb_backend")]
+        PersistentPoolBackend::RocksDB(_) => Ok(()),
+        #[allow(unreachable_patterns)]
+        backend => Err(MigrationError::UnsupportedBackend(format!("{:?}", backend))),
+    }
+}
+
+/// Returns the number of validated ECDSA artifacts in the persistent pool
+/// described by `config`, which must be opened read-only.
+fn count_ecdsa_artifacts(config: &ArtifactPoolConfig, log: &ReplicaLogger) -> usize {
+    // Only the LMDB backend persists ECDSA artifacts. Opening a read-only pool
+    // fails if the ECDSA database was never created.
+    if !matches!(
+        config.persistent_pool_backend,
+        PersistentPoolBackend::Lmdb(_)
+    ) || !config
+        .persistent_pool_db_path()
+        .join("ecdsa")
+        .join("data.mdb")
+        .exists()
+    {
+        return 0;
+    }
+    let pool = EcdsaPoolImpl::new(config.clone(), log.clone(), MetricsRegistry::new());
+    let section = pool.validated();
+    section.signed_dealings().count()
+        + section.dealing_support().count()
+        + section.signature_shares().count()
+        + section.complaints().count()
+        + section.openings().count()
+}
+
+/// Copies the consensus artifacts of the pool selected by `select` from
+/// `source` into `target`, one height at a time. Returns the number of copied
+/// and skipped artifacts.
+fn copy_consensus<T: ConsensusMessageHashable>(
+    source: &dyn PoolSection<ValidatedConsensusArtifact>,
+    target: &mut dyn InitializablePoolSection,
+    select: for<'a> fn(
+        &'a dyn PoolSection<ValidatedConsensusArtifact>,
+    ) -> &'a dyn HeightIndexedPool<T>,
+) -> (usize, usize) {
+    let (mut copied, mut skipped) = (0, 0);
+    let Some(range) = select(source).height_range() else {
+        return (copied, skipped);
+    };
+    for height in range.min.get()..=range.max.get() {
+        let height = Height::from(height);
+        let existing: HashSet<_> = select(target.pool_section())
+            .get_by_height(height)
+            .map(|message| message.get_id())
+            .collect();
+        let mut ops = PoolSectionOps::new();
+        for message in select(source).get_by_height(height) {
+            let id = message.get_id();
+            if existing.contains(&id) {
+                skipped += 1;
+                continue;
+            }
+            ops.insert(ValidatedConsensusArtifact {
+                timestamp: source.get_timestamp(&id).unwrap_or_else(current_time),
+                msg: message.into_message(),
+            });
+            copied += 1;
+        }
+        if !ops.ops.is_empty() {
+            target.mutate(ops);
+        }
+    }
+    (copied, skipped)
+}
+
+/// Copies the certification artifacts of the pool selected by `select` from
+/// `source` into `target`, one height at a time. Returns the number of copied
+/// and skipped artifacts.
+fn copy_certification<T: CryptoHashable>(
+    source: &dyn CertificationPoolSection,
+    target: &dyn CertificationPoolSection,
+    select: for<'a> fn(&'a dyn CertificationPoolSection) -> &'a dyn HeightIndexedPool<T>,
+    into_message: fn(T) -> CertificationMessage,
+) -> (usize, usize) {
+    let (mut copied, mut skipped) = (0, 0);
+    let Some(range) = select(source).height_range() else {
+        return (copied, skipped);
+    };
+    for height in range.min.get()..=range.max.get() {
+        let height = Height::from(height);
+        let existing: HashSet<_> = select(target)
+            .get_by_height(height)
+            .map(|message| crypto_hash(&message).get())
+            .collect();
+        for message in select(source).get_by_height(height) {
+            if existing.contains(&crypto_hash(&message).get()) {
+                skipped += 1;
+            } else {
+                target.insert(into_message(message));
+                copied += 1;
+            }
+        }
+    }
+    (copied, skipped)
+}
+
+fn digests(
+    consensus_pool: &UncachedConsensusPoolImpl,
+    certification_pool: &CertificationPoolImpl,
+) -> BTreeMap<&'static str, ArtifactDigest> {
+    fn consensus_hash<T: ConsensusMessageHashable>(message: &T) -> Vec<u8> {
+        message.get_cm_hash().digest().0.clone()
+    }
+    let consensus = consensus_pool.validated.pool_section();
+    let certification = &*certification_pool.persistent_pool;
+    BTreeMap::from([
+        (
+            "RandomBeacon",
+            digest(consensus.random_beacon(), consensus_hash),
+        ),
+        (
+            "Finalization",
+            digest(consensus.finalization(), consensus_hash),
+        ),
+        (
+            "Notarization",
+            digest(consensus.notarization(), consensus_hash),
+        ),
+        (
+            "BlockProposal",
+            digest(consensus.block_proposal(), consensus_hash),
+        ),
+        (
+            "RandomBeaconShare",
+            digest(consensus.random_beacon_share(), consensus_hash),
+        ),
+        (
+            "NotarizationShare",
+            digest(consensus.notarization_share(), consensus_hash),
+        ),
+        (
+            "FinalizationShare",
+            digest(consensus.finalization_share(), consensus_hash),
+        ),
+        (
+            "RandomTape",
+            digest(consensus.random_tape(), consensus_hash),
+        ),
+        (
+            "RandomTapeShare",
+            digest(consensus.random_tape_share(), consensus_hash),
+        ),
+        (
+            "CatchUpPackage",
+            digest(consensus.catch_up_package(), consensus_hash),
+        ),
+        (
+            "CatchUpPackageShare",
+            digest(consensus.catch_up_package_share(), consensus_hash),
+        ),
+        (
+            "Certification",
+            digest::<Certification>(certification.certifications(), |message| {
+                crypto_hash(message).get().0
+            }),
+        ),
+        (
+            "CertificationShare",
+            digest::<CertificationShare>(certification.certification_shares(), |message| {
+                crypto_hash(message).get().0
+            }),
+        ),
+    ])
+}
+
+/// Computes the [`ArtifactDigest`] of the given pool. Within a height, the
+/// hashes are sorted, such that the digest does not depend on the order in
+/// which a backend stores the artifacts.
+fn digest<T>(pool: &dyn HeightIndexedPool<T>, hash: fn(&T) -> Vec<u8>) -> ArtifactDigest {
+    let mut count = 0;
+    let mut hasher = Sha256::new();
+    if let Some(range) = pool.height_range() {
+        for height in range.min.get()..=range.max.get() {
+            let mut hashes: Vec<_> = pool
+                .get_by_height(Height::from(height))
+                .map(|message| hash(&message))
+                .collect();
+            hashes.sort_unstable();
+            for hash in &hashes {
+                hasher.write(&height.to_be_bytes());
+                hasher.write(hash);
+            }
+            count += hashes.len();
+        }
+    }
+    ArtifactDigest {
+        count,
+        hash: hasher.finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{finalization_share_ops, random_beacon_ops};
+    use ic_test_utilities_logger::with_test_replica_logger;
+
+    fn populate_source(config: ArtifactPoolConfig, log: ReplicaLogger) {
+        let mut pool = UncachedConsensusPoolImpl::new(config, log);
+        pool.validated.mutate(random_beacon_ops());
+        pool.validated.mutate(finalization_share_ops());
+    }
+
+    #[test]
+    fn test_migration_copies_and_resumes() {
+        with_test_replica_logger(|log| {
+            ic_test_utilities::artifact_pool_config::with_test_pool_configs(2, |configs| {
+                let (source, target) = (configs[0].clone(), configs[1].clone());
+                populate_source(source.clone(), log.clone());
+
+                let summaries =
+                    migrate_persistent_pool(source.clone(), target.clone(), log.clone()).unwrap();
+                assert_eq!(summaries["RandomBeacon"].copied, 16);
+                assert_eq!(summaries["RandomBeacon"].skipped, 0);
+                assert_eq!(summaries["RandomBeacon"].digest.count, 16);
+                assert!(summaries["FinalizationShare"].copied > 0);
+                assert_eq!(summaries["BlockProposal"].digest.count, 0);
+
+                // Running the migration again only skips the copied artifacts.
+                let resumed =
+                    migrate_persistent_pool(source.clone(), target.clone(), log.clone()).unwrap();
+                assert_eq!(resumed["RandomBeacon"].copied, 0);
+                assert_eq!(resumed["RandomBeacon"].skipped, 16);
+
+                assert_eq!(
+                    persistent_pool_digests(source, log.clone()).unwrap(),
+                    persistent_pool_digests(target, log).unwrap()
+                );
+            })
+        })
+    }
+
+    #[test]
+    fn test_migration_rejects_same_path() {
+        with_test_replica_logger(|log| {
+            ic_test_utilities::artifact_pool_config::with_test_pool_config(|config| {
+                assert!(matches!(
+                    migrate_persistent_pool(config.clone(), config, log),
+                    Err(MigrationError::SamePath)
+                ));
+            })
+        })
+    }
+
+    #[cfg(feature = "rocksdb_backend")]
+    #[test]
+    fn test_migration_from_lmdb_to_rocksdb() {
+        use ic_config::artifact_pool::ArtifactPoolTomlConfig;
+        with_test_replica_logger(|log| {
+            ic_test_utilities::artifact_pool_config::with_test_pool_configs(2, |configs| {
+                let source = configs[0].clone();
+                let mut toml_config =
+                    ArtifactPoolTomlConfig::new(configs[1].persistent_pool_db_path(), None);
+                toml_config.consensus_pool_backend = Some("rocksdb".to_string());
+                let target = ArtifactPoolConfig::from(toml_config);
+                populate_source(source.clone(), log.clone());
+
+                let summaries = migrate_persistent_pool(source, target, log).unwrap();
+                assert_eq!(summaries["RandomBeacon"].copied, 16);
+                assert_eq!(summaries["RandomBeacon"].digest.count, 16);
+            })
+        })
+    }
+
+    #[cfg(feature = "rocksdb_backend")]
+    #[test]
+    fn test_migration_to_rocksdb_rejects_ecdsa_artifacts() {
+        use ic_config::artifact_pool::ArtifactPoolTomlConfig;
+        use ic_crypto_test_utils_canister_threshold_sigs::dummy_values::dummy_idkg_dealing_for_tests;
+        use ic_interfaces::time_source::SysTimeSource;
+        use ic_interfaces::{artifact_pool::MutablePool, ecdsa::EcdsaChangeAction};
+        use ic_test_utilities::{consensus::fake::FakeSigner, types::ids::NODE_1};
+        use ic_types::{
+            consensus::ecdsa::EcdsaMessage,
+            crypto::canister_threshold_sig::idkg::SignedIDkgDealing, signature::BasicSignature,
+        };
+        with_test_replica_logger(|log| {
+            ic_test_utilities::artifact_pool_config::with_test_pool_configs(2, |configs| {
+                let source = configs[0].clone();
+                let mut toml_config =
+                    ArtifactPoolTomlConfig::new(configs[1].persistent_pool_db_path(), None);
+                toml_config.consensus_pool_backend = Some("rocksdb".to_string());
+                let target = ArtifactPoolConfig::from(toml_config);
+                populate_source(source.clone(), log.clone());
+                {
+                    let mut ecdsa_pool =
+                        EcdsaPoolImpl::new(source.clone(), log.clone(), MetricsRegistry::new());
+                    let dealing = SignedIDkgDealing {
+                        content: dummy_idkg_dealing_for_tests(),
+                        signature: BasicSignature::fake(NODE_1),
+                    };
+                    ecdsa_pool.apply_changes(
+                        &SysTimeSource::new(),
+                        vec![EcdsaChangeAction::AddToValidated(
+                            EcdsaMessage::EcdsaSignedDealing(dealing),
+                        )],
+                    );
+                }
+
+                assert!(matches!(
+                    migrate_persistent_pool(source, target, log),
+                    Err(MigrationError::EcdsaArtifactsNotMigrated(1))
+                ));
+            })
+        })
+    }
+}
@@ -75,26 +75,29 @@ struct PersistentHeightIndexedPoolConfig {
     skip_fsync_for_tests: bool,
     db_path: PathBuf,
     purge_interval: Height,
+    read_only: bool,
 }
 
 impl PersistentHeightIndexedPoolConfig {
-    fn for_consensus(config: RocksDBConfig) -> Self {
+    fn for_consensus(config: RocksDBConfig, read_only: bool) -> Self {
         let mut db_path = config.persistent_pool_validated_persistent_db_path;
         db_path.push("consensus");
         Self {
             skip_fsync_for_tests: config.persistent_pool_validated_skip_fsync_for_tests,
             db_path,
             purge_interval: config.persistent_pool_validated_purge_interval,
+            read_only,
         }
     }
 
-    fn for_certification(config: RocksDBConfig) -> Self {
+    fn for_certification(config: RocksDBConfig, read_only: bool) -> Self {
         let mut db_path = config.persistent_pool_validated_persistent_db_path;
         db_path.push("certification");
         Self {
             skip_fsync_for_tests: config.persistent_pool_validated_skip_fsync_for_tests,
             db_path,
             purge_interval: config.persistent_pool_validated_purge_interval,
+            read_only,
         }
     }
 }
@@ -207,7 +210,11 @@ impl<T: HasCFInfos> PersistentHeightIndexedPool<T> {
             .collect();
 
         let path = Path::new(&config.db_path);
-        let result = DB::open_cf_descriptors(&db_options, path, cfs);
+        let result = if config.read_only {
+            DB::open_cf_descriptors_read_only(&db_options, path, cfs, false)
+        } else {
+            DB::open_cf_descriptors(&db_options, path, cfs)
+        };
 
         match result {
             Ok(db) => PersistentHeightIndexedPool {
@@ -349,10 +356,11 @@ impl<T: HasCFInfos> Drop for PersistentHeightIndexedPool<T> {
 impl PersistentHeightIndexedPool<ConsensusMessage> {
     pub fn new_consensus_pool(
         config: RocksDBConfig,
+        read_only: bool,
         log: ReplicaLogger,
     ) -> PersistentHeightIndexedPool<ConsensusMessage> {
         PersistentHeightIndexedPool::new(
-            PersistentHeightIndexedPoolConfig::for_consensus(config),
+            PersistentHeightIndexedPoolConfig::for_consensus(config, read_only),
             log,
         )
     }
@@ -1021,10 +1029,11 @@ impl CertificationType for CertificationShare {
 impl PersistentHeightIndexedPool<CertificationMessage> {
     pub fn new_certification_pool(
         config: RocksDBConfig,
+        read_only: bool,
         log: ReplicaLogger,
     ) -> PersistentHeightIndexedPool<CertificationMessage> {
         PersistentHeightIndexedPool::new(
-            PersistentHeightIndexedPoolConfig::for_certification(config),
+            PersistentHeightIndexedPoolConfig::for_certification(config, read_only),
             log,
         )
     }
@@ -1223,7 +1232,7 @@ mod tests {
         }
 
         fn new_consensus_pool(self, log: ReplicaLogger) -> Self::PersistentHeightIndexedPool {
-            PersistentHeightIndexedPool::new_consensus_pool(self, log)
+            PersistentHeightIndexedPool::new_consensus_pool(self, false, log)
         }
 
         fn persistent_pool_validated_persistent_db_path(&self) -> &PathBuf {
@@ -1272,8 +1281,11 @@ mod tests {
             config.persistent_pool_validated_purge_interval = Height::from(8);
             // create a pool and purge at height 10
             {
-                let mut pool =
-                    PersistentHeightIndexedPool::new_consensus_pool(config.clone(), log.clone());
+                let mut pool = PersistentHeightIndexedPool::new_consensus_pool(
+                    config.clone(),
+                    false,
+                    log.clone(),
+                );
                 // insert a few things
                 let rb_ops = random_beacon_ops();
                 pool.mutate(rb_ops.clone());
@@ -1293,7 +1305,7 @@ mod tests {
             std::thread::sleep(std::time::Duration::from_millis(1000));
             // create the same pool again, check if purge was persisted
             {
-                let pool = PersistentHeightIndexedPool::new_consensus_pool(config, log);
+                let pool = PersistentHeightIndexedPool::new_consensus_pool(config, false, log);
                 assert_eq!(
                     pool.random_beacon().height_range().map(|r| r.min),
                     Some(Height::from(10))
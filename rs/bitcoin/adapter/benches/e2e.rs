@@ -120,6 +120,7 @@ fn e2e(criterion: &mut Criterion) {
                     no_op_logger(),
                     adapter_state.clone(),
                     handler,
+                    blockchain_state.clone(),
                     transaction_manager_tx,
                     &MetricsRegistry::default(),
                 );
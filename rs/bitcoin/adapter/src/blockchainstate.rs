@@ -1,10 +1,17 @@
 //! The module is responsible for keeping track of the blockchain state.
 //!
-use crate::{common::BlockHeight, config::Config, metrics::BlockchainStateMetrics};
+use crate::{
+    common::BlockHeight, config::Config, header_file::HeaderFile, metrics::BlockchainStateMetrics,
+};
 use bitcoin::{blockdata::constants::genesis_block, Block, BlockHash, BlockHeader, Network};
 use ic_btc_validation::{validate_header, HeaderStore, ValidateHeaderError};
+use ic_logger::{warn, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, VecDeque},
+    mem::size_of,
+    path::Path,
+};
 use thiserror::Error;
 
 /// This field contains the datatype used to store "work" of a Bitcoin blockchain
@@ -99,6 +106,13 @@ pub struct BlockchainState {
 
     /// Used to determine how validation should be handled with `validate_header`.
     network: Network,
+
+    /// Fork branches whose tip is more than this number of blocks below the active tip are pruned.
+    fork_pruning_depth: BlockHeight,
+
+    /// This field stores the file to which added headers are persisted, if any.
+    header_file: Option<HeaderFile>,
+
     metrics: BlockchainStateMetrics,
 }
 
@@ -121,10 +135,86 @@ impl BlockchainState {
             block_cache,
             tips,
             network: config.network,
+            fork_pruning_depth: config.fork_pruning_depth,
+            header_file: None,
             metrics: BlockchainStateMetrics::new(metrics_registry),
         }
     }
 
+    /// Restores the headers persisted to the file at the given path and persists the headers
+    /// added from now on to it. The headers were validated before being persisted,
+    /// so they are only checked to connect to the cached headers.
+    ///
+    /// A file that cannot be read is discarded, in which case the headers are synced from
+    /// the Bitcoin network again.
+    pub fn restore_headers(&mut self, path: &Path, logger: &ReplicaLogger) {
+        let (header_file, headers) = match HeaderFile::open(path) {
+            Ok(result) => result,
+            Err(err) => {
+                warn!(
+                    logger,
+                    "Discarding header store {} that cannot be read: {}",
+                    path.display(),
+                    err
+                );
+                self.metrics.header_store_errors.inc();
+                match std::fs::remove_file(path).and_then(|()| HeaderFile::open(path)) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        warn!(
+                            logger,
+                            "Failed to recreate header store {}, headers are not persisted: {}",
+                            path.display(),
+                            err
+                        );
+                        self.metrics.header_store_errors.inc();
+                        return;
+                    }
+                }
+            }
+        };
+
+        for header in headers {
+            let block_hash = header.block_hash();
+            if !self.header_cache.contains_key(&block_hash)
+                && self.header_cache.contains_key(&header.prev_blockhash)
+            {
+                let _ = self.insert_header(header);
+            }
+        }
+
+        self.tips.sort_unstable_by(|a, b| b.work.cmp(&a.work));
+        self.prune_stale_forks();
+
+        // Drop the pruned and unconnected headers from the file.
+        let header_count = header_file.header_count();
+        self.header_file = Some(header_file);
+        if header_count != self.header_cache.len() - 1 {
+            self.rewrite_header_file();
+        }
+        self.update_tip_metrics();
+    }
+
+    /// Returns all headers but the genesis header, with every header preceded by its parent.
+    fn headers_in_bfs_order(&self) -> Vec<BlockHeader> {
+        let mut headers = Vec::with_capacity(self.header_cache.len());
+        let mut queue: VecDeque<BlockHash> = self
+            .header_cache
+            .get(&self.genesis_block_header.block_hash())
+            .map(|node| node.children.clone())
+            .unwrap_or_default()
+            .into();
+
+        while let Some(block_hash) = queue.pop_front() {
+            if let Some(node) = self.header_cache.get(&block_hash) {
+                headers.push(node.header);
+                queue.extend(node.children.iter().copied());
+            }
+        }
+
+        headers
+    }
+
     /// Returns the genesis header that the store is initialized with.
     pub fn genesis(&self) -> &BlockHeader {
         &self.genesis_block_header
@@ -160,16 +250,87 @@ impl BlockchainState {
 
         // Sort the tips by the total work
         self.tips.sort_unstable_by(|a, b| b.work.cmp(&a.work));
+        if self.prune_stale_forks() > 0 {
+            self.rewrite_header_file();
+        } else {
+            self.flush_header_file();
+        }
+        self.update_tip_metrics();
+
+        (block_hashes_of_added_headers, err)
+    }
+
+    fn update_tip_metrics(&self) {
         self.metrics.tips.set(self.tips.len() as i64);
         self.metrics
             .tip_height
             .set(self.get_active_chain_tip().height.into());
+    }
 
-        (block_hashes_of_added_headers, err)
+    fn flush_header_file(&mut self) {
+        if let Some(Err(_)) = self.header_file.as_mut().map(|f| f.flush()) {
+            self.metrics.header_store_errors.inc();
+        }
+    }
+
+    /// Replaces the content of the header file with the cached headers, dropping the
+    /// pruned ones.
+    fn rewrite_header_file(&mut self) {
+        if self.header_file.is_none() {
+            return;
+        }
+        let headers = self.headers_in_bfs_order();
+        if let Some(Err(_)) = self.header_file.as_mut().map(|f| f.rewrite(headers.iter())) {
+            self.metrics.header_store_errors.inc();
+        }
+    }
+
+    /// Removes the fork branches whose tip is more than `fork_pruning_depth` blocks below
+    /// the active tip from the header and block caches. A branch is removed up to the
+    /// header it forks off from. The active chain is never pruned.
+    /// Returns the number of pruned headers.
+    fn prune_stale_forks(&mut self) -> usize {
+        let active_tip_hash = self.get_active_chain_tip().header.block_hash();
+        let min_height = self
+            .get_active_chain_tip()
+            .height
+            .saturating_sub(self.fork_pruning_depth);
+
+        let (stale_tips, tips): (Vec<Tip>, Vec<Tip>) = std::mem::take(&mut self.tips)
+            .into_iter()
+            .partition(|tip| tip.height < min_height && tip.header.block_hash() != active_tip_hash);
+        self.tips = tips;
+
+        let mut pruned: usize = 0;
+        for tip in stale_tips {
+            let mut block_hash = tip.header.block_hash();
+            while let Some(node) = self.header_cache.get(&block_hash) {
+                // Stop at the header the branch forks off from.
+                if !node.children.is_empty() {
+                    break;
+                }
+
+                let prev_hash = node.header.prev_blockhash;
+                self.header_cache.remove(&block_hash);
+                self.block_cache.remove(&block_hash);
+                pruned += 1;
+
+                match self.header_cache.get_mut(&prev_hash) {
+                    Some(parent) => parent.children.retain(|child| *child != block_hash),
+                    None => break,
+                }
+                block_hash = prev_hash;
+            }
+        }
+
+        if pruned > 0 {
+            self.metrics.header_cache_size.sub(pruned as i64);
+            self.metrics.pruned_headers.inc_by(pruned as u64);
+        }
+        pruned
     }
 
     /// This method adds the input header to the `header_cache`.
-    #[allow(clippy::indexing_slicing)]
     fn add_header(&mut self, header: BlockHeader) -> Result<AddHeaderResult, AddHeaderError> {
         let block_hash = header.block_hash();
 
@@ -183,6 +344,21 @@ impl BlockchainState {
             return Err(AddHeaderError::InvalidHeader(block_hash, err));
         }
 
+        let result = self.insert_header(header)?;
+
+        if let Some(Err(_)) = self.header_file.as_mut().map(|f| f.append(&header)) {
+            self.metrics.header_store_errors.inc();
+        }
+
+        Ok(result)
+    }
+
+    /// This method inserts the header, which has to be validated, into the `header_cache`
+    /// and updates the tips.
+    #[allow(clippy::indexing_slicing)]
+    fn insert_header(&mut self, header: BlockHeader) -> Result<AddHeaderResult, AddHeaderError> {
+        let block_hash = header.block_hash();
+
         let parent = self
             .header_cache
             .get_mut(&header.prev_blockhash)
@@ -237,6 +413,7 @@ impl BlockchainState {
         let _ = self
             .add_header(block.header)
             .map_err(AddBlockError::Header)?;
+        self.flush_header_file();
         self.tips.sort_unstable_by(|a, b| b.work.cmp(&a.work));
         self.block_cache.insert(block_hash, block);
         self.metrics
@@ -330,6 +507,26 @@ impl BlockchainState {
     pub fn get_block_cache_size(&self) -> usize {
         self.block_cache.values().fold(0, |sum, b| b.size() + sum)
     }
+
+    /// Returns the number of headers in the header cache.
+    pub fn get_header_cache_len(&self) -> usize {
+        self.header_cache.len()
+    }
+
+    /// Returns the number of fork branches besides the active chain.
+    pub fn get_fork_count(&self) -> usize {
+        self.tips.len().saturating_sub(1)
+    }
+
+    /// Returns an estimate of the memory used by the header and block caches in bytes.
+    pub fn get_memory_usage(&self) -> usize {
+        let header_cache_size = self.header_cache.values().fold(0, |sum, node| {
+            sum + size_of::<(BlockHash, HeaderNode)>()
+                + node.children.capacity() * size_of::<BlockHash>()
+        });
+
+        header_cache_size + self.get_block_cache_size()
+    }
 }
 
 impl HeaderStore for BlockchainState {
@@ -355,6 +552,7 @@ mod test {
     use super::*;
     use crate::{common::test_common::TestState, config::test::ConfigBuilder};
     use ic_btc_adapter_test_utils::{block_1, block_2, generate_header, generate_headers};
+    use ic_logger::replica_logger::no_op_logger;
     use std::collections::HashSet;
 
     #[test]
@@ -683,6 +881,147 @@ mod test {
         assert_eq!(state.get_active_chain_tip().header, h4);
     }
 
+    /// Tests that fork branches which fall more than `fork_pruning_depth` blocks behind
+    /// the active tip are pruned up to the header they fork off from.
+    #[test]
+    fn test_pruning_stale_forks() {
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_fork_pruning_depth(10)
+            .build();
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let initial_header = *state.genesis();
+
+        let chain = generate_headers(initial_header.block_hash(), initial_header.time, 10, &[]);
+        let chain_hashes: Vec<BlockHash> = chain.iter().map(|header| header.block_hash()).collect();
+        // Fork off from the header at height 5.
+        let fork_chain = generate_headers(chain_hashes[4], chain[4].time, 3, &chain_hashes);
+        state.add_headers(&chain);
+        state.add_headers(&fork_chain);
+        assert_eq!(state.get_fork_count(), 1);
+
+        // The fork tip at height 8 is exactly 10 blocks below the active tip.
+        let extension = generate_headers(chain_hashes[9], chain[9].time, 8, &[]);
+        state.add_headers(&extension);
+        assert_eq!(state.get_fork_count(), 1);
+
+        let extension = generate_headers(
+            extension.last().unwrap().block_hash(),
+            extension.last().unwrap().time,
+            1,
+            &[],
+        );
+        state.add_headers(&extension);
+        assert_eq!(state.get_fork_count(), 0);
+        assert_eq!(state.get_active_chain_tip().height, 19);
+        assert_eq!(state.get_header_cache_len(), 20);
+        for header in &fork_chain {
+            assert!(state.get_cached_header(&header.block_hash()).is_none());
+        }
+        assert_eq!(
+            state.get_cached_header(&chain_hashes[4]).unwrap().children,
+            vec![chain_hashes[5]]
+        );
+    }
+
+    /// Tests that persisted headers are restored when the state is created again
+    /// and that stale forks are dropped from the store.
+    #[test]
+    fn test_restoring_persisted_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("headers");
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_header_store_path(path.clone())
+            .build();
+
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        state.restore_headers(&path, &no_op_logger());
+        let initial_header = *state.genesis();
+        let chain = generate_headers(initial_header.block_hash(), initial_header.time, 16, &[]);
+        let chain_hashes: Vec<BlockHash> = chain.iter().map(|header| header.block_hash()).collect();
+        let fork_chain = generate_headers(chain_hashes[1], chain[1].time, 2, &chain_hashes);
+        state.add_headers(&chain);
+        state.add_headers(&fork_chain);
+        drop(state);
+
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        state.restore_headers(&path, &no_op_logger());
+        assert_eq!(state.get_active_chain_tip().height, 16);
+        assert_eq!(
+            state.get_active_chain_tip().header.block_hash(),
+            *chain_hashes.last().unwrap()
+        );
+        assert_eq!(state.get_fork_count(), 1);
+        assert_eq!(state.get_header_cache_len(), 19);
+        drop(state);
+
+        // Restarting with a smaller pruning depth drops the fork from the store.
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_header_store_path(path.clone())
+            .with_fork_pruning_depth(5)
+            .build();
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        state.restore_headers(&path, &no_op_logger());
+        assert_eq!(state.get_fork_count(), 0);
+        assert_eq!(state.get_header_cache_len(), 17);
+        assert_eq!(state.header_file.as_ref().unwrap().header_count(), 16);
+        assert!(state.get_memory_usage() > 0);
+    }
+
+    /// Tests that forks pruned while the adapter is running are dropped from the store.
+    #[test]
+    fn test_pruned_headers_are_dropped_from_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("headers");
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_fork_pruning_depth(5)
+            .build();
+
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        state.restore_headers(&path, &no_op_logger());
+        let initial_header = *state.genesis();
+        let chain = generate_headers(initial_header.block_hash(), initial_header.time, 4, &[]);
+        let chain_hashes: Vec<BlockHash> = chain.iter().map(|header| header.block_hash()).collect();
+        let fork_chain = generate_headers(chain_hashes[1], chain[1].time, 1, &chain_hashes);
+        state.add_headers(&chain);
+        state.add_headers(&fork_chain);
+        assert_eq!(state.header_file.as_ref().unwrap().header_count(), 5);
+
+        let last = chain.last().unwrap();
+        let extension = generate_headers(last.block_hash(), last.time, 8, &[]);
+        state.add_headers(&extension);
+        assert_eq!(state.get_fork_count(), 0);
+        assert_eq!(state.header_file.as_ref().unwrap().header_count(), 12);
+        let (_, headers) = HeaderFile::open(&path).unwrap();
+        assert_eq!(headers.len(), 12);
+    }
+
+    /// Tests that a header store that cannot be read doesn't keep the state from being created.
+    #[test]
+    fn test_unreadable_header_store_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("headers");
+        // A link to a directory cannot be opened as a file.
+        let target = dir.path().join("target");
+        std::fs::create_dir(&target).unwrap();
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+
+        let config = ConfigBuilder::new().with_network(Network::Regtest).build();
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        state.restore_headers(&path, &no_op_logger());
+
+        let initial_header = *state.genesis();
+        let chain = generate_headers(initial_header.block_hash(), initial_header.time, 2, &[]);
+        let (added, err) = state.add_headers(&chain);
+        assert!(err.is_none());
+        assert_eq!(added.len(), 2);
+        let (_, headers) = HeaderFile::open(&path).unwrap();
+        assert_eq!(headers, chain);
+    }
+
     /// Test header store `get_header` function.
     #[test]
     fn test_headerstore_get_header() {
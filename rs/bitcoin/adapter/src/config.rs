@@ -46,6 +46,20 @@ pub struct Config {
     /// Specifies which unix domain socket should be used for serving incoming requests.
     #[serde(default)]
     pub incoming_source: IncomingSource,
+    /// Path of the file in which validated headers are persisted, so that the adapter
+    /// resumes syncing from the persisted tip after a restart. If not set, headers
+    /// are only kept in memory.
+    #[serde(default)]
+    pub header_store_path: Option<PathBuf>,
+    /// Fork branches whose tip is more than this number of blocks below the active
+    /// tip are pruned from the header cache.
+    #[serde(default = "default_fork_pruning_depth")]
+    pub fork_pruning_depth: u32,
+    /// Path of the unix domain socket on which the admin service is served. The admin
+    /// service is kept off the socket of the replica, so that only local operators can
+    /// reach it. If not set, the admin service is not served.
+    #[serde(default)]
+    pub admin_socket_path: Option<PathBuf>,
 }
 
 /// Set the default idle seconds to one hour.
@@ -53,6 +67,11 @@ fn default_idle_seconds() -> u64 {
     3600
 }
 
+/// Set the default fork pruning depth to roughly one week of blocks.
+fn default_fork_pruning_depth() -> u32 {
+    1008
+}
+
 impl Config {
     /// This function returns the port to use based on the Bitcoin network provided.
     pub fn network_port(&self) -> u16 {
@@ -75,6 +94,9 @@ impl Default for Config {
             ipv6_only: false,
             logger: LoggerConfig::default(),
             incoming_source: Default::default(),
+            header_store_path: None,
+            fork_pruning_depth: default_fork_pruning_depth(),
+            admin_socket_path: None,
         }
     }
 }
@@ -116,6 +138,16 @@ pub mod test {
             self
         }
 
+        pub fn with_header_store_path(mut self, header_store_path: PathBuf) -> Self {
+            self.config.header_store_path = Some(header_store_path);
+            self
+        }
+
+        pub fn with_fork_pruning_depth(mut self, fork_pruning_depth: u32) -> Self {
+            self.config.fork_pruning_depth = fork_pruning_depth;
+            self
+        }
+
         pub fn build(self) -> Config {
             self.config
         }
@@ -0,0 +1,185 @@
+//! The module persists validated headers to disk, so that the header cache can be
+//! restored when the adapter restarts instead of syncing it from the Bitcoin network again.
+//!
+use bitcoin::{
+    consensus::{deserialize, serialize},
+    BlockHeader,
+};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// The size of a consensus encoded block header.
+const HEADER_SIZE: usize = 80;
+
+/// An append-only file of consensus encoded block headers.
+/// Headers are appended in the order they are added to the header cache,
+/// so that every header is preceded by its parent.
+#[derive(Debug)]
+pub struct HeaderFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// This field stores the number of headers in the file.
+    header_count: usize,
+}
+
+fn open_for_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)
+}
+
+impl HeaderFile {
+    /// Opens the file at the given path, creating it if it doesn't exist, and returns it
+    /// along with the headers it contains. A trailing partial header left by an interrupted
+    /// write is discarded.
+    pub fn open(path: &Path) -> io::Result<(Self, Vec<BlockHeader>)> {
+        let mut file = open_for_append(path)?;
+
+        let mut data = vec![];
+        file.read_to_end(&mut data)?;
+
+        let complete_len = data.len() - data.len() % HEADER_SIZE;
+        if complete_len != data.len() {
+            file.set_len(complete_len as u64)?;
+            data.truncate(complete_len);
+        }
+
+        let headers = data
+            .chunks_exact(HEADER_SIZE)
+            .map(deserialize)
+            .collect::<Result<Vec<BlockHeader>, _>>()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        let header_file = Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            header_count: headers.len(),
+        };
+
+        Ok((header_file, headers))
+    }
+
+    /// Returns the number of headers in the file.
+    pub fn header_count(&self) -> usize {
+        self.header_count
+    }
+
+    /// Appends the header to the file. The header is only guaranteed to be written
+    /// once [HeaderFile::flush](HeaderFile::flush) has been called.
+    pub fn append(&mut self, header: &BlockHeader) -> io::Result<()> {
+        self.writer.write_all(&serialize(header))?;
+        self.header_count += 1;
+        Ok(())
+    }
+
+    /// Flushes the appended headers to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Replaces the content of the file with the given headers. This is used to drop
+    /// headers that have been pruned from the header cache.
+    /// The headers are written to a temporary file first, so that the file is never left
+    /// in an incomplete state.
+    pub fn rewrite<'a>(
+        &mut self,
+        headers: impl Iterator<Item = &'a BlockHeader>,
+    ) -> io::Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        let mut header_count = 0;
+        for header in headers {
+            writer.write_all(&serialize(header))?;
+            header_count += 1;
+        }
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+
+        fs::rename(&tmp_path, &self.path)?;
+
+        self.writer = BufWriter::new(open_for_append(&self.path)?);
+        self.header_count = header_count;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::{blockdata::constants::genesis_block, Network};
+    use ic_btc_adapter_test_utils::generate_headers;
+    use tempfile::tempdir;
+
+    /// Tests that appended headers are returned when the file is opened again.
+    #[test]
+    fn test_headers_are_restored() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("headers");
+        let genesis = genesis_block(Network::Regtest).header;
+        let chain = generate_headers(genesis.block_hash(), genesis.time, 5, &[]);
+
+        let (mut header_file, headers) = HeaderFile::open(&path).unwrap();
+        assert!(headers.is_empty());
+        for header in &chain {
+            header_file.append(header).unwrap();
+        }
+        header_file.flush().unwrap();
+        drop(header_file);
+
+        let (header_file, headers) = HeaderFile::open(&path).unwrap();
+        assert_eq!(headers, chain);
+        assert_eq!(header_file.header_count(), 5);
+    }
+
+    /// Tests that a partially written header is discarded.
+    #[test]
+    fn test_partial_header_is_discarded() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("headers");
+        let genesis = genesis_block(Network::Regtest).header;
+        let chain = generate_headers(genesis.block_hash(), genesis.time, 2, &[]);
+
+        let mut data = serialize(&chain[0]);
+        data.extend_from_slice(&serialize(&chain[1])[..HEADER_SIZE / 2]);
+        fs::write(&path, data).unwrap();
+
+        let (mut header_file, headers) = HeaderFile::open(&path).unwrap();
+        assert_eq!(headers, vec![chain[0]]);
+
+        header_file.append(&chain[1]).unwrap();
+        header_file.flush().unwrap();
+        drop(header_file);
+
+        let (_, headers) = HeaderFile::open(&path).unwrap();
+        assert_eq!(headers, chain);
+    }
+
+    /// Tests that rewriting the file replaces its content.
+    #[test]
+    fn test_rewrite() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("headers");
+        let genesis = genesis_block(Network::Regtest).header;
+        let chain = generate_headers(genesis.block_hash(), genesis.time, 4, &[]);
+
+        let (mut header_file, _) = HeaderFile::open(&path).unwrap();
+        for header in &chain {
+            header_file.append(header).unwrap();
+        }
+        header_file.rewrite(chain.iter().take(2)).unwrap();
+        header_file.append(&chain[2]).unwrap();
+        header_file.flush().unwrap();
+        assert_eq!(header_file.header_count(), 3);
+        drop(header_file);
+
+        let (_, headers) = HeaderFile::open(&path).unwrap();
+        assert_eq!(headers, chain[..3].to_vec());
+    }
+}
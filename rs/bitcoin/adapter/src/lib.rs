@@ -34,6 +34,8 @@ mod connection;
 /// This module contains code that is used to manage multiple connections to
 /// BTC nodes.
 mod connectionmanager;
+/// This module contains the on-disk store of validated headers.
+mod header_file;
 mod metrics;
 /// The module is responsible for awaiting messages from bitcoin peers and dispaching them
 /// to the correct component.
@@ -41,6 +43,9 @@ mod router;
 /// This module contains code that is used to handle interactions to connected
 /// BTC streams (SOCKS and TCP).
 mod rpc_server;
+/// This module contains a simulated Bitcoin network backend serving a scripted chain,
+/// used to test the adapter end to end without connecting to Bitcoin nodes.
+pub mod simulation;
 mod stream;
 mod transaction_store;
 
@@ -200,7 +205,11 @@ pub fn start_grpc_server_and_router(
     adapter_state: AdapterState,
 ) {
     let (blockchain_manager_tx, blockchain_manager_rx) = channel(100);
-    let blockchain_state = Arc::new(Mutex::new(BlockchainState::new(config, metrics_registry)));
+    let mut blockchain_state = BlockchainState::new(config, metrics_registry);
+    if let Some(path) = &config.header_store_path {
+        blockchain_state.restore_headers(path, &logger);
+    }
+    let blockchain_state = Arc::new(Mutex::new(blockchain_state));
     let get_successors_handler = GetSuccessorsHandler::new(
         config,
         // The get successor handler should be low latency, and instead of not sharing state and
@@ -217,6 +226,7 @@ pub fn start_grpc_server_and_router(
         logger.clone(),
         adapter_state.clone(),
         get_successors_handler,
+        blockchain_state.clone(),
         transaction_manager_tx,
         metrics_registry,
     );
@@ -96,6 +96,8 @@ pub struct BlockchainStateMetrics {
     pub block_cache_elements: IntGauge,
     pub header_cache_size: IntGauge,
     pub tips: IntGauge,
+    pub pruned_headers: IntCounter,
+    pub header_store_errors: IntCounter,
 }
 
 impl BlockchainStateMetrics {
@@ -113,6 +115,14 @@ impl BlockchainStateMetrics {
                 "Number of headers stored in the adapter.",
             ),
             tips: metrics_registry.int_gauge("blockchain_tips", "Number of active tips."),
+            pruned_headers: metrics_registry.int_counter(
+                "pruned_headers_total",
+                "Number of headers of stale forks pruned from the header cache.",
+            ),
+            header_store_errors: metrics_registry.int_counter(
+                "header_store_errors_total",
+                "Number of failures to persist headers to the header store.",
+            ),
         }
     }
 }
@@ -2,19 +2,24 @@ use crate::{
     config::{Config, IncomingSource},
     get_successors_handler::{GetSuccessorsRequest, GetSuccessorsResponse},
     metrics::{ServiceMetrics, LABEL_GET_SUCCESSOR, LABEL_SEND_TRANSACTION},
-    AdapterState, GetSuccessorsHandler, TransactionManagerRequest,
+    AdapterState, BlockchainState, GetSuccessorsHandler, TransactionManagerRequest,
 };
 use bitcoin::{consensus::Encodable, hashes::Hash, BlockHash};
 use ic_async_utils::{incoming_from_first_systemd_socket, incoming_from_path};
 use ic_btc_service::{
+    btc_admin_service_server::{BtcAdminService, BtcAdminServiceServer},
     btc_service_server::{BtcService, BtcServiceServer},
-    BtcServiceGetSuccessorsRequest, BtcServiceGetSuccessorsResponse,
-    BtcServiceSendTransactionRequest, BtcServiceSendTransactionResponse,
+    BtcAdminGetStatusRequest, BtcAdminGetStatusResponse, BtcServiceGetSuccessorsRequest,
+    BtcServiceGetSuccessorsResponse, BtcServiceSendTransactionRequest,
+    BtcServiceSendTransactionResponse,
 };
 use ic_logger::{debug, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
-use std::convert::{TryFrom, TryInto};
-use tokio::sync::mpsc::Sender;
+use std::{
+    convert::{TryFrom, TryInto},
+    sync::Arc,
+};
+use tokio::sync::{mpsc::Sender, Mutex};
 use tonic::{transport::Server, Request, Response, Status};
 
 struct BtcServiceImpl {
@@ -25,6 +30,10 @@ struct BtcServiceImpl {
     metrics: ServiceMetrics,
 }
 
+struct BtcAdminServiceImpl {
+    blockchain_state: Arc<Mutex<BlockchainState>>,
+}
+
 impl TryFrom<BtcServiceGetSuccessorsRequest> for GetSuccessorsRequest {
     type Error = Status;
 
@@ -120,12 +129,34 @@ impl BtcService for BtcServiceImpl {
     }
 }
 
-/// Spawns in a separate Tokio task the BTC adapter gRPC service.
+#[tonic::async_trait]
+impl BtcAdminService for BtcAdminServiceImpl {
+    async fn get_status(
+        &self,
+        _request: Request<BtcAdminGetStatusRequest>,
+    ) -> Result<Response<BtcAdminGetStatusResponse>, Status> {
+        // Unlike the requests of the replica, status requests don't keep the adapter from becoming idle.
+        let state = self.blockchain_state.lock().await;
+        let tip = state.get_active_chain_tip();
+
+        Ok(Response::new(BtcAdminGetStatusResponse {
+            tip_height: tip.height,
+            tip_hash: tip.header.block_hash().to_vec(),
+            fork_count: state.get_fork_count() as u64,
+            header_count: state.get_header_cache_len() as u64,
+            memory_usage_bytes: state.get_memory_usage() as u64,
+        }))
+    }
+}
+
+/// Spawns in a separate Tokio task the BTC adapter gRPC service and, if an admin socket
+/// is configured, in another one the admin gRPC service.
 pub fn start_grpc_server(
     config: Config,
     logger: ReplicaLogger,
     adapter_state: AdapterState,
     get_successors_handler: GetSuccessorsHandler,
+    blockchain_state: Arc<Mutex<BlockchainState>>,
     transaction_manager_tx: Sender<TransactionManagerRequest>,
     metrics_registry: &MetricsRegistry,
 ) {
@@ -136,6 +167,16 @@ pub fn start_grpc_server(
         logger,
         metrics: ServiceMetrics::new(metrics_registry),
     };
+    if let Some(admin_socket_path) = config.admin_socket_path.clone() {
+        let btc_admin_impl = BtcAdminServiceImpl { blockchain_state };
+        tokio::spawn(async move {
+            Server::builder()
+                .add_service(BtcAdminServiceServer::new(btc_admin_impl))
+                .serve_with_incoming(incoming_from_path(admin_socket_path))
+                .await
+                .expect("gRPC admin server crashed");
+        });
+    }
     tokio::spawn(async move {
         match config.incoming_source {
             IncomingSource::Path(uds_path) => {
@@ -0,0 +1,366 @@
+//! The module contains a simulated Bitcoin network backend. Instead of syncing from Bitcoin
+//! peers, the adapter serves a scripted regtest chain, so that `get_successors` and
+//! `send_transaction` can be tested end to end without `bitcoind` or any networking.
+//!
+//! The chain is described by branches: the first one starts at the genesis block and every
+//! following one forks off from a block of a previously described branch. Blocks are
+//! generated deterministically, so the same script always yields the same block hashes.
+//! Adding a branch with more work than the active chain simulates a reorg.
+use crate::{
+    common::BlockHeight, config::Config, rpc_server::start_grpc_server, AdapterState,
+    BlockchainManagerRequest, BlockchainState, GetSuccessorsHandler, TransactionManagerRequest,
+};
+use bitcoin::{
+    blockdata::{constants::genesis_block, script::Builder},
+    Block, BlockHash, BlockHeader, Network, OutPoint, Script, Transaction, TxIn, TxOut, Witness,
+};
+use ic_logger::ReplicaLogger;
+use ic_metrics::MetricsRegistry;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use thiserror::Error;
+use tokio::sync::{mpsc::channel, Mutex};
+
+/// The number of seconds between two generated blocks.
+const BLOCK_INTERVAL_SECONDS: u32 = 600;
+
+/// The coinbase reward of generated blocks in satoshi.
+const COINBASE_VALUE: u64 = 50 * 100_000_000;
+
+/// A branch of the scripted chain.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Branch {
+    /// The name used to refer to the branch.
+    pub name: String,
+    /// The name of the branch this branch forks off from. The branch starts at the
+    /// genesis block if it's not set.
+    #[serde(default)]
+    pub parent: Option<String>,
+    /// The height of the block of the parent branch this branch forks off from.
+    #[serde(default)]
+    pub fork_height: BlockHeight,
+    /// The number of blocks of the branch.
+    pub length: u32,
+}
+
+/// Errors that can occur while generating the scripted chain.
+#[derive(Debug, Error)]
+pub enum SimulationError {
+    /// This variant is used when the simulation is started for a network other than regtest.
+    #[error("The simulation only supports regtest, got {0}")]
+    UnsupportedNetwork(Network),
+    /// This variant is used when a branch name is used twice.
+    #[error("Branch {0} already exists")]
+    DuplicateBranch(String),
+    /// This variant is used when a branch refers to a parent that hasn't been described.
+    #[error("Unknown parent branch {0}")]
+    UnknownBranch(String),
+    /// This variant is used when a branch forks off above the tip of its parent.
+    #[error("Branch {0} forks off above the tip of its parent")]
+    ForkHeightOutOfRange(String),
+    /// This variant is used when a generated block is rejected by the blockchain state.
+    #[error("Generated block was rejected: {0}")]
+    InvalidBlock(String),
+}
+
+/// Generates the blocks of the scripted chain.
+#[derive(Debug)]
+struct ScriptedChain {
+    /// The headers of every branch indexed by height, starting with the genesis header.
+    branches: HashMap<String, Vec<BlockHeader>>,
+    genesis: BlockHeader,
+}
+
+impl ScriptedChain {
+    fn new(genesis: BlockHeader) -> Self {
+        Self {
+            branches: HashMap::new(),
+            genesis,
+        }
+    }
+
+    /// Generates the blocks of the branch in ascending order of height.
+    fn add_branch(&mut self, branch: &Branch) -> Result<Vec<Block>, SimulationError> {
+        if self.branches.contains_key(&branch.name) {
+            return Err(SimulationError::DuplicateBranch(branch.name.clone()));
+        }
+
+        let mut headers = match &branch.parent {
+            None => vec![self.genesis],
+            Some(parent) => self
+                .branches
+                .get(parent)
+                .ok_or_else(|| SimulationError::UnknownBranch(parent.clone()))?
+                .get(..=branch.fork_height as usize)
+                .ok_or_else(|| SimulationError::ForkHeightOutOfRange(branch.name.clone()))?
+                .to_vec(),
+        };
+
+        let mut blocks = vec![];
+        for _ in 0..branch.length {
+            let prev = headers.last().copied().unwrap_or(self.genesis);
+            let block = generate_block(&prev, headers.len() as BlockHeight, &branch.name);
+            headers.push(block.header);
+            blocks.push(block);
+        }
+
+        self.branches.insert(branch.name.clone(), headers);
+        Ok(blocks)
+    }
+
+    fn tip(&self, name: &str) -> Option<BlockHash> {
+        self.branches
+            .get(name)
+            .and_then(|headers| headers.last())
+            .map(|header| header.block_hash())
+    }
+}
+
+/// Generates a block on top of the given header. The coinbase contains the height and the
+/// branch name, so that blocks at the same height of different branches differ.
+fn generate_block(prev: &BlockHeader, height: BlockHeight, branch: &str) -> Block {
+    let coinbase = Transaction {
+        version: 1,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: Builder::new()
+                .push_int(height.into())
+                .push_slice(branch.as_bytes())
+                .into_script(),
+            sequence: u32::MAX,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: COINBASE_VALUE,
+            script_pubkey: Script::new(),
+        }],
+    };
+
+    let mut block = Block {
+        header: BlockHeader {
+            version: 1,
+            prev_blockhash: prev.block_hash(),
+            merkle_root: Default::default(),
+            time: prev.time + BLOCK_INTERVAL_SECONDS,
+            bits: prev.bits,
+            nonce: 0,
+        },
+        txdata: vec![coinbase],
+    };
+    block.header.merkle_root = block.compute_merkle_root().unwrap_or_default();
+
+    let target = block.header.target();
+    while block.header.validate_pow(&target).is_err() {
+        block.header.nonce += 1;
+    }
+
+    block
+}
+
+/// A running adapter gRPC service backed by a scripted chain.
+pub struct SimulatedNetwork {
+    blockchain_state: Arc<Mutex<BlockchainState>>,
+    chain: ScriptedChain,
+    /// This field contains the raw transactions received by `send_transaction`.
+    sent_transactions: Arc<RwLock<Vec<Vec<u8>>>>,
+}
+
+impl SimulatedNetwork {
+    /// Generates the given branches and serves them on the `incoming_source` of the config.
+    /// Must be called from within a Tokio runtime.
+    pub fn start(
+        config: &Config,
+        logger: ReplicaLogger,
+        metrics_registry: &MetricsRegistry,
+        branches: &[Branch],
+    ) -> Result<Self, SimulationError> {
+        if config.network != Network::Regtest {
+            return Err(SimulationError::UnsupportedNetwork(config.network));
+        }
+
+        let blockchain_state = Arc::new(Mutex::new(BlockchainState::new(config, metrics_registry)));
+        let mut network = Self {
+            blockchain_state: blockchain_state.clone(),
+            chain: ScriptedChain::new(genesis_block(Network::Regtest).header),
+            sent_transactions: Arc::new(RwLock::new(vec![])),
+        };
+
+        {
+            let mut state = blockchain_state
+                .try_lock()
+                .expect("The state is not shared yet.");
+            for branch in branches {
+                network.add_branch_to_state(&mut state, branch)?;
+            }
+        }
+
+        // The blocks are all in the cache already, so there is nothing to download or prune.
+        let (blockchain_manager_tx, mut blockchain_manager_rx) =
+            channel::<BlockchainManagerRequest>(100);
+        tokio::spawn(async move { while blockchain_manager_rx.recv().await.is_some() {} });
+
+        let (transaction_manager_tx, mut transaction_manager_rx) = channel(100);
+        let sent_transactions = network.sent_transactions.clone();
+        tokio::spawn(async move {
+            while let Some(TransactionManagerRequest::SendTransaction(transaction)) =
+                transaction_manager_rx.recv().await
+            {
+                sent_transactions.write().push(transaction);
+            }
+        });
+
+        let get_successors_handler = GetSuccessorsHandler::new(
+            config,
+            blockchain_state.clone(),
+            blockchain_manager_tx,
+            metrics_registry,
+        );
+
+        start_grpc_server(
+            config.clone(),
+            logger,
+            AdapterState::new(config.idle_seconds),
+            get_successors_handler,
+            blockchain_state,
+            transaction_manager_tx,
+            metrics_registry,
+        );
+
+        Ok(network)
+    }
+
+    fn add_branch_to_state(
+        &mut self,
+        state: &mut BlockchainState,
+        branch: &Branch,
+    ) -> Result<Vec<BlockHash>, SimulationError> {
+        let blocks = self.chain.add_branch(branch)?;
+
+        blocks
+            .into_iter()
+            .map(|block| {
+                let block_hash = block.block_hash();
+                state
+                    .add_block(block)
+                    .map(|_| block_hash)
+                    .map_err(|err| SimulationError::InvalidBlock(err.to_string()))
+            })
+            .collect()
+    }
+
+    /// Adds a branch to the running simulation and returns the hashes of its blocks.
+    /// A branch with more work than the active chain causes a reorg.
+    pub async fn add_branch(&mut self, branch: &Branch) -> Result<Vec<BlockHash>, SimulationError> {
+        let blockchain_state = self.blockchain_state.clone();
+        let mut state = blockchain_state.lock().await;
+        self.add_branch_to_state(&mut state, branch)
+    }
+
+    /// Returns the hash of the tip of the given branch.
+    pub fn branch_tip(&self, name: &str) -> Option<BlockHash> {
+        self.chain.tip(name)
+    }
+
+    /// Returns the hash of the tip of the active chain.
+    pub async fn active_tip(&self) -> BlockHash {
+        self.blockchain_state
+            .lock()
+            .await
+            .get_active_chain_tip()
+            .header
+            .block_hash()
+    }
+
+    /// Returns the raw transactions received by `send_transaction` so far.
+    pub fn sent_transactions(&self) -> Vec<Vec<u8>> {
+        self.sent_transactions.read().clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn branch(name: &str, parent: Option<&str>, fork_height: BlockHeight, length: u32) -> Branch {
+        Branch {
+            name: name.to_string(),
+            parent: parent.map(str::to_string),
+            fork_height,
+            length,
+        }
+    }
+
+    /// Tests that the same script yields the same blocks and that branches differ.
+    #[test]
+    fn test_blocks_are_deterministic() {
+        let genesis = genesis_block(Network::Regtest).header;
+        let script = [
+            branch("main", None, 0, 5),
+            branch("fork", Some("main"), 2, 3),
+        ];
+
+        let mut chain_a = ScriptedChain::new(genesis);
+        let mut chain_b = ScriptedChain::new(genesis);
+        for branch in &script {
+            assert_eq!(
+                chain_a.add_branch(branch).unwrap(),
+                chain_b.add_branch(branch).unwrap()
+            );
+        }
+
+        let main = &chain_a.branches["main"];
+        let fork = &chain_a.branches["fork"];
+        assert_eq!(main.len(), 6);
+        assert_eq!(fork.len(), 6);
+        assert_eq!(main[..3], fork[..3]);
+        assert_ne!(main[3], fork[3]);
+    }
+
+    /// Tests that invalid scripts are rejected.
+    #[test]
+    fn test_invalid_scripts() {
+        let mut chain = ScriptedChain::new(genesis_block(Network::Regtest).header);
+        chain.add_branch(&branch("main", None, 0, 5)).unwrap();
+
+        assert!(matches!(
+            chain.add_branch(&branch("main", None, 0, 1)),
+            Err(SimulationError::DuplicateBranch(_))
+        ));
+        assert!(matches!(
+            chain.add_branch(&branch("fork", Some("other"), 0, 1)),
+            Err(SimulationError::UnknownBranch(_))
+        ));
+        assert!(matches!(
+            chain.add_branch(&branch("fork", Some("main"), 6, 1)),
+            Err(SimulationError::ForkHeightOutOfRange(_))
+        ));
+    }
+
+    /// Tests that generated blocks pass the validation of the blockchain state.
+    #[test]
+    fn test_blocks_are_valid() {
+        let config = crate::config::test::ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .build();
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut chain = ScriptedChain::new(*state.genesis());
+
+        for block in chain.add_branch(&branch("main", None, 0, 10)).unwrap() {
+            state.add_block(block).unwrap();
+        }
+        for block in chain
+            .add_branch(&branch("fork", Some("main"), 4, 7))
+            .unwrap()
+        {
+            state.add_block(block).unwrap();
+        }
+
+        assert_eq!(state.get_active_chain_tip().height, 11);
+        assert_eq!(
+            Some(state.get_active_chain_tip().header.block_hash()),
+            chain.tip("fork")
+        );
+    }
+}
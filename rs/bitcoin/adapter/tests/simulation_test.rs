@@ -0,0 +1,151 @@
+use bitcoin::{consensus::encode::deserialize, Block, BlockHash};
+use ic_btc_adapter::{
+    config::{Config, IncomingSource},
+    simulation::{Branch, SimulatedNetwork},
+};
+use ic_btc_adapter_client::setup_bitcoin_adapter_clients;
+use ic_btc_interface::Network;
+use ic_btc_types_internal::{
+    BitcoinAdapterRequestWrapper, BitcoinAdapterResponseWrapper, GetSuccessorsRequestInitial,
+    SendTransactionRequest,
+};
+use ic_config::adapters::AdaptersConfig;
+use ic_interfaces_adapter_client::{Options, RpcAdapterClient};
+use ic_logger::replica_logger::no_op_logger;
+use ic_metrics::MetricsRegistry;
+use tempfile::{Builder, TempPath};
+use tokio::runtime::Runtime;
+
+type BitcoinAdapterClient = Box<
+    dyn RpcAdapterClient<BitcoinAdapterRequestWrapper, Response = BitcoinAdapterResponseWrapper>,
+>;
+
+fn branch(name: &str, parent: Option<&str>, fork_height: u32, length: u32) -> Branch {
+    Branch {
+        name: name.to_string(),
+        parent: parent.map(str::to_string),
+        fork_height,
+        length,
+    }
+}
+
+fn start_simulation_and_client(
+    rt: &Runtime,
+    branches: &[Branch],
+) -> (SimulatedNetwork, BitcoinAdapterClient, TempPath) {
+    let mut network = None;
+    let (client, path) = Builder::new()
+        .make(|uds_path| {
+            Ok(rt.block_on(async {
+                let config = Config {
+                    network: bitcoin::Network::Regtest,
+                    incoming_source: IncomingSource::Path(uds_path.to_path_buf()),
+                    ..Default::default()
+                };
+                let metrics_registry = MetricsRegistry::new();
+
+                network = Some(
+                    SimulatedNetwork::start(&config, no_op_logger(), &metrics_registry, branches)
+                        .unwrap(),
+                );
+
+                let adapters_config = AdaptersConfig {
+                    bitcoin_mainnet_uds_path: Some(uds_path.into()),
+                    bitcoin_mainnet_uds_metrics_path: None,
+                    bitcoin_testnet_uds_path: None,
+                    bitcoin_testnet_uds_metrics_path: None,
+                    https_outcalls_uds_path: None,
+                    https_outcalls_uds_metrics_path: None,
+                };
+
+                setup_bitcoin_adapter_clients(
+                    no_op_logger(),
+                    &metrics_registry,
+                    tokio::runtime::Handle::current(),
+                    adapters_config,
+                )
+                .btc_mainnet_client
+            }))
+        })
+        .unwrap()
+        .into_parts();
+
+    (network.unwrap(), client, path)
+}
+
+fn get_successors(
+    adapter_client: &BitcoinAdapterClient,
+    anchor: BlockHash,
+    processed_block_hashes: &[BlockHash],
+) -> Vec<Block> {
+    let request = BitcoinAdapterRequestWrapper::GetSuccessorsRequest(GetSuccessorsRequestInitial {
+        network: Network::Regtest,
+        anchor: anchor[..].to_vec(),
+        processed_block_hashes: processed_block_hashes
+            .iter()
+            .map(|hash| hash[..].to_vec())
+            .collect(),
+    });
+
+    match adapter_client.send_blocking(request, Options::default()) {
+        Ok(BitcoinAdapterResponseWrapper::GetSuccessorsResponse(response)) => response
+            .blocks
+            .iter()
+            .map(|block| deserialize(block).unwrap())
+            .collect(),
+        other => panic!("Unexpected response: {:?}", other),
+    }
+}
+
+/// Checks that the scripted blocks are served in BFS order, including the ones of a reorg.
+#[test]
+fn test_get_successors_with_reorg() {
+    let rt = Runtime::new().unwrap();
+    let (mut network, client, _path) =
+        start_simulation_and_client(&rt, &[branch("main", None, 0, 5)]);
+    let genesis = bitcoin::blockdata::constants::genesis_block(bitcoin::Network::Regtest);
+
+    let blocks = get_successors(&client, genesis.block_hash(), &[]);
+    assert_eq!(blocks.len(), 5);
+    assert_eq!(
+        blocks.last().unwrap().block_hash(),
+        network.branch_tip("main").unwrap()
+    );
+    let main_hashes: Vec<BlockHash> = blocks.iter().map(|block| block.block_hash()).collect();
+
+    // Fork off from height 2 with more work than the main branch.
+    let fork_hashes = rt
+        .block_on(network.add_branch(&branch("fork", Some("main"), 2, 4)))
+        .unwrap();
+    assert_eq!(
+        rt.block_on(network.active_tip()),
+        network.branch_tip("fork").unwrap()
+    );
+
+    let blocks = get_successors(&client, genesis.block_hash(), &main_hashes);
+    let hashes: Vec<BlockHash> = blocks.iter().map(|block| block.block_hash()).collect();
+    assert_eq!(hashes, fork_hashes);
+}
+
+/// Checks that sent transactions are captured.
+#[test]
+fn test_send_transaction_is_captured() {
+    let rt = Runtime::new().unwrap();
+    let (network, client, _path) = start_simulation_and_client(&rt, &[branch("main", None, 0, 1)]);
+
+    let request = BitcoinAdapterRequestWrapper::SendTransactionRequest(SendTransactionRequest {
+        network: Network::Regtest,
+        transaction: vec![1, 2, 3],
+    });
+    assert!(matches!(
+        client.send_blocking(request, Options::default()),
+        Ok(BitcoinAdapterResponseWrapper::SendTransactionResponse(_))
+    ));
+
+    let mut tries = 0;
+    while network.sent_transactions().is_empty() && tries < 10 {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        tries += 1;
+    }
+    assert_eq!(network.sent_transactions(), vec![vec![1, 2, 3]]);
+}
@@ -0,0 +1,307 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{boxed, Body, Bytes, Full},
+    extract::State,
+    http::{HeaderMap, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+use candid::Principal;
+use dashmap::DashMap;
+use lru::LruCache;
+use prometheus::{
+    register_int_counter_vec_with_registry, register_int_gauge_with_registry, IntCounterVec,
+    IntGauge, Registry,
+};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+use crate::routes::{ApiError, ErrorCause, RequestContext, RequestType};
+
+// Node-signed query responses sign over the request id, so a cached response can only be
+// served to an identical request, i.e. a retry with the same nonce and expiry.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub canister_id: Principal,
+    pub request_id: [u8; 32],
+}
+
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+struct CacheEntry {
+    response: CachedResponse,
+    expires: Instant,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+    Bypass,
+}
+
+impl CacheStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hit => "hit",
+            Self::Miss => "miss",
+            Self::Bypass => "bypass",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CacheParams {
+    // Maximum total size of the cached response bodies
+    pub max_size: usize,
+    // Responses with larger bodies are not cached
+    pub max_item_size: usize,
+    pub ttl: Duration,
+    // Canisters whose responses are never cached
+    pub bypass_canisters: HashSet<Principal>,
+}
+
+struct CacheMetrics {
+    counter: IntCounterVec,
+    items: IntGauge,
+    size: IntGauge,
+}
+
+impl CacheMetrics {
+    fn new(registry: &Registry) -> Self {
+        Self {
+            counter: register_int_counter_vec_with_registry!(
+                "cache_total",
+                "Counts requests by their cache status",
+                &["status"],
+                registry
+            )
+            .unwrap(),
+
+            items: register_int_gauge_with_registry!(
+                "cache_items",
+                "Number of responses in the cache",
+                registry
+            )
+            .unwrap(),
+
+            size: register_int_gauge_with_registry!(
+                "cache_size_bytes",
+                "Total size of the response bodies in the cache",
+                registry
+            )
+            .unwrap(),
+        }
+    }
+}
+
+struct Entries {
+    lru: LruCache<CacheKey, CacheEntry>,
+    size: usize,
+}
+
+// In-memory LRU cache of anonymous query responses.
+// Entries are only shared between identical requests, i.e. client retries and
+// duplicated requests, not between different clients calling the same method.
+pub struct Cache {
+    params: CacheParams,
+    entries: Mutex<Entries>,
+    // Locks of the keys being fetched, used to coalesce concurrent misses.
+    // The lock holds the response of the request that was sent upstream, so that
+    // waiters get it even if it was not cached.
+    inflight: DashMap<CacheKey, Arc<AsyncMutex<Option<CachedResponse>>>>,
+    metrics: CacheMetrics,
+}
+
+impl Cache {
+    pub fn new(params: CacheParams, registry: &Registry) -> Self {
+        Self {
+            params,
+            entries: Mutex::new(Entries {
+                lru: LruCache::unbounded(),
+                size: 0,
+            }),
+            inflight: DashMap::new(),
+            metrics: CacheMetrics::new(registry),
+        }
+    }
+
+    // Build the cache key if the request is eligible for caching
+    pub fn key(&self, ctx: &RequestContext) -> Option<CacheKey> {
+        if ctx.request_type != RequestType::Query || ctx.is_anonymous() != Some(true) {
+            return None;
+        }
+
+        let canister_id = ctx.canister_id?;
+
+        // The replica rejects the request if the canister ids don't match, don't serve it from the cache
+        if ctx.canister_id_cbor != Some(canister_id)
+            || self.params.bypass_canisters.contains(&canister_id)
+        {
+            return None;
+        }
+
+        Some(CacheKey {
+            canister_id,
+            request_id: ctx.request_id?,
+        })
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.lru.get(key) {
+            None => return None,
+            Some(e) if e.expires > Instant::now() => return Some(e.response.clone()),
+            Some(_) => {}
+        };
+
+        // Entry has expired
+        if let Some(e) = entries.lru.pop(key) {
+            entries.size -= e.response.body.len();
+        }
+        self.update_gauges(&entries);
+
+        None
+    }
+
+    pub fn insert(&self, key: CacheKey, response: CachedResponse) {
+        let size = response.body.len();
+        if size > self.params.max_item_size {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+
+        let entry = CacheEntry {
+            response,
+            expires: Instant::now() + self.params.ttl,
+        };
+
+        if let Some(old) = entries.lru.put(key, entry) {
+            entries.size -= old.response.body.len();
+        }
+        entries.size += size;
+
+        // Evict the least recently used entries until we fit
+        while entries.size > self.params.max_size {
+            match entries.lru.pop_lru() {
+                Some((_, e)) => entries.size -= e.response.body.len(),
+                None => break,
+            }
+        }
+
+        self.update_gauges(&entries);
+    }
+
+    // Acquire the lock for the given key, only one request per key is sent upstream at a time
+    pub async fn lock(&self, key: &CacheKey) -> OwnedMutexGuard<Option<CachedResponse>> {
+        let lock = self.inflight.entry(key.clone()).or_default().clone();
+        lock.lock_owned().await
+    }
+
+    // Release the lock and remove it if nobody else is waiting for it
+    pub fn unlock(&self, key: &CacheKey, guard: OwnedMutexGuard<Option<CachedResponse>>) {
+        drop(guard);
+        self.inflight
+            .remove_if(key, |_, v| Arc::strong_count(v) == 1);
+    }
+
+    fn update_gauges(&self, entries: &Entries) {
+        self.metrics.items.set(entries.lru.len() as i64);
+        self.metrics.size.set(entries.size as i64);
+    }
+
+    fn record(&self, status: CacheStatus) {
+        self.metrics
+            .counter
+            .with_label_values(&[status.as_str()])
+            .inc();
+    }
+}
+
+fn cached_to_response(cached: CachedResponse, ctx: RequestContext) -> Response {
+    let mut response = Response::new(boxed(Full::from(cached.body)));
+    *response.status_mut() = cached.status;
+    *response.headers_mut() = cached.headers;
+
+    // Inject context into response so that metrics can be recorded
+    response.extensions_mut().insert(ctx);
+
+    response
+}
+
+// Serves eligible query requests from the cache
+pub async fn cache_middleware(
+    State(cache): State<Arc<Cache>>,
+    Extension(ctx): Extension<RequestContext>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, ApiError> {
+    let key = match cache.key(&ctx) {
+        Some(v) => v,
+        None => {
+            cache.record(CacheStatus::Bypass);
+            return Ok(next.run(request).await);
+        }
+    };
+
+    if let Some(cached) = cache.get(&key) {
+        cache.record(CacheStatus::Hit);
+        return Ok(cached_to_response(cached, ctx));
+    }
+
+    // Wait for a concurrent request for the same key to finish and reuse its response
+    let mut guard = cache.lock(&key).await;
+
+    if let Some(cached) = guard.clone().or_else(|| cache.get(&key)) {
+        cache.unlock(&key, guard);
+        cache.record(CacheStatus::Hit);
+        return Ok(cached_to_response(cached, ctx));
+    }
+
+    cache.record(CacheStatus::Miss);
+    let response = next.run(request).await;
+
+    let (parts, body) = response.into_parts();
+    let body = hyper::body::to_bytes(body).await;
+
+    let body = match body {
+        Ok(v) => v,
+        Err(e) => {
+            cache.unlock(&key, guard);
+            return Err(ErrorCause::ReplicaUnreachable(format!(
+                "unable to read response body: {e}"
+            ))
+            .into());
+        }
+    };
+
+    let cached = CachedResponse {
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: body.clone(),
+    };
+
+    // Only successful responses are cached, but the waiters get any response
+    if parts.status == StatusCode::OK {
+        cache.insert(key.clone(), cached.clone());
+    }
+    *guard = Some(cached);
+    cache.unlock(&key, guard);
+
+    Ok(Response::from_parts(parts, boxed(Full::from(body))))
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,219 @@
+use super::*;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use axum::{middleware, routing::post, Router};
+use tower::ServiceExt;
+
+fn params() -> CacheParams {
+    CacheParams {
+        max_size: 1024,
+        max_item_size: 512,
+        ttl: Duration::from_secs(60),
+        bypass_canisters: HashSet::from([
+            Principal::from_text("qoctq-giaaa-aaaaa-aaaea-cai").unwrap()
+        ]),
+    }
+}
+
+fn ctx() -> RequestContext {
+    let canister_id = Principal::from_text("sxiki-5ygae-aq").unwrap();
+
+    RequestContext {
+        canister_id: Some(canister_id),
+        canister_id_cbor: Some(canister_id),
+        sender: Some(Principal::anonymous()),
+        method_name: Some("http_request".to_string()),
+        request_id: Some([1; 32]),
+        request_type: RequestType::Query,
+        ..Default::default()
+    }
+}
+
+fn response(size: usize) -> CachedResponse {
+    CachedResponse {
+        status: StatusCode::OK,
+        headers: HeaderMap::new(),
+        body: Bytes::from(vec![0; size]),
+    }
+}
+
+fn key(i: u8) -> CacheKey {
+    CacheKey {
+        request_id: [i; 32],
+        ..Cache::new(params(), &Registry::new()).key(&ctx()).unwrap()
+    }
+}
+
+#[test]
+fn test_key() {
+    let cache = Cache::new(params(), &Registry::new());
+
+    let key = cache.key(&ctx()).unwrap();
+    assert_eq!(key.request_id, [1; 32]);
+
+    // Not parsed as a query
+    let mut c = ctx();
+    c.request_id = None;
+    assert!(cache.key(&c).is_none());
+
+    // Authenticated
+    let mut c = ctx();
+    c.sender = Some(Principal::from_text("sqjm4-qahae-aq").unwrap());
+    assert!(cache.key(&c).is_none());
+
+    // Not a query
+    let mut c = ctx();
+    c.request_type = RequestType::Call;
+    assert!(cache.key(&c).is_none());
+
+    // Canister id mismatch
+    let mut c = ctx();
+    c.canister_id_cbor = None;
+    assert!(cache.key(&c).is_none());
+
+    // Bypassed canister
+    let mut c = ctx();
+    c.canister_id = Some(Principal::from_text("qoctq-giaaa-aaaaa-aaaea-cai").unwrap());
+    c.canister_id_cbor = c.canister_id;
+    assert!(cache.key(&c).is_none());
+}
+
+#[test]
+fn test_get_insert() {
+    let cache = Cache::new(params(), &Registry::new());
+
+    assert!(cache.get(&key(0)).is_none());
+    cache.insert(key(0), response(100));
+    assert_eq!(cache.get(&key(0)).unwrap().body.len(), 100);
+
+    // Too large to be cached
+    cache.insert(key(1), response(600));
+    assert!(cache.get(&key(1)).is_none());
+}
+
+#[test]
+fn test_eviction() {
+    let cache = Cache::new(params(), &Registry::new());
+
+    for i in 0..4 {
+        cache.insert(key(i), response(300));
+    }
+
+    // Only the 3 most recent entries fit
+    assert!(cache.get(&key(0)).is_none());
+    for i in 1..4 {
+        assert!(cache.get(&key(i)).is_some());
+    }
+    assert_eq!(cache.entries.lock().unwrap().size, 900);
+}
+
+#[test]
+fn test_ttl() {
+    let cache = Cache::new(
+        CacheParams {
+            ttl: Duration::ZERO,
+            ..params()
+        },
+        &Registry::new(),
+    );
+
+    cache.insert(key(0), response(100));
+    assert!(cache.get(&key(0)).is_none());
+    assert_eq!(cache.entries.lock().unwrap().size, 0);
+}
+
+#[tokio::test]
+async fn test_cache_middleware_coalescing() {
+    let cache = Arc::new(Cache::new(params(), &Registry::new()));
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let handler = {
+        let calls = calls.clone();
+        move || {
+            let calls = calls.clone();
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                "foobar"
+            }
+        }
+    };
+
+    let router = Router::new()
+        .route(
+            "/",
+            post(handler).layer(middleware::from_fn_with_state(
+                cache.clone(),
+                cache_middleware,
+            )),
+        )
+        .layer(Extension(ctx()));
+
+    let requests = (0..10).map(|_| {
+        router
+            .clone()
+            .oneshot(Request::post("/").body(Body::empty()).unwrap())
+    });
+
+    for resp in futures::future::join_all(requests).await {
+        let resp = resp.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(body, "foobar");
+    }
+
+    // Only one request reached the handler
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert!(cache.inflight.is_empty());
+}
+
+#[tokio::test]
+async fn test_cache_middleware_coalescing_error() {
+    let cache = Arc::new(Cache::new(params(), &Registry::new()));
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let handler = {
+        let calls = calls.clone();
+        move || {
+            let calls = calls.clone();
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+            }
+        }
+    };
+
+    let router = Router::new()
+        .route(
+            "/",
+            post(handler).layer(middleware::from_fn_with_state(
+                cache.clone(),
+                cache_middleware,
+            )),
+        )
+        .layer(Extension(ctx()));
+
+    let requests = (0..10).map(|_| {
+        router
+            .clone()
+            .oneshot(Request::post("/").body(Body::empty()).unwrap())
+    });
+
+    // The waiters are released with the error response of the first request
+    for resp in futures::future::join_all(requests).await {
+        let resp = resp.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(body, "unavailable");
+    }
+
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert!(cache.inflight.is_empty());
+
+    // Errors are not cached
+    assert!(cache.get(&cache.key(&ctx()).unwrap()).is_none());
+}
@@ -25,6 +25,7 @@ use crate::{
     http::HttpClient,
     metrics::{MetricParams, WithMetrics},
     persist::Persist,
+    scoring::WithScoring,
     snapshot::RoutingTable,
     snapshot::{Node, Subnet},
 };
@@ -466,5 +467,18 @@ impl<T: Check> Check for WithMetrics<T> {
     }
 }
 
+#[async_trait]
+impl<T: Check> Check for WithScoring<T> {
+    async fn check(&self, node: &Node) -> Result<CheckResult, CheckError> {
+        let start_time = Instant::now();
+        let out = self.0.check(node).await;
+
+        let latency = out.as_ref().map_or(start_time.elapsed(), |x| x.latency);
+        self.1.observe(node, latency, out.is_ok());
+
+        out
+    }
+}
+
 #[cfg(test)]
 mod test;
@@ -1,9 +1,14 @@
 use std::{net::SocketAddr, path::PathBuf};
 
+use candid::Principal;
+
 use clap::{Args, Parser};
 use url::Url;
 
-use crate::core::{AUTHOR_NAME, SERVICE_NAME};
+use crate::{
+    core::{AUTHOR_NAME, SERVICE_NAME},
+    scoring::RoutingStrategy,
+};
 
 #[derive(Parser)]
 #[clap(name = SERVICE_NAME)]
@@ -18,6 +23,15 @@ pub struct Cli {
     #[command(flatten, next_help_heading = "health")]
     pub health: HealthChecksConfig,
 
+    #[command(flatten, next_help_heading = "routing")]
+    pub routing: RoutingConfig,
+
+    #[command(flatten, next_help_heading = "rate_limiting")]
+    pub rate_limiting: RateLimitingConfig,
+
+    #[command(flatten, next_help_heading = "cache")]
+    pub cache: CacheConfig,
+
     #[command(flatten, next_help_heading = "firewall")]
     pub firewall: FirewallConfig,
 
@@ -95,6 +109,78 @@ pub struct HealthChecksConfig {
     pub max_height_lag: u64,
 }
 
+#[derive(Args)]
+pub struct RoutingConfig {
+    /// Strategy to pick a node within a subnet
+    #[clap(long, value_enum, default_value = "random")]
+    pub routing_strategy: RoutingStrategy,
+
+    /// Weight of the newest sample in the latency and error rate moving averages (0..1]
+    #[clap(long, default_value = "0.3", value_parser = parse_ewma_alpha)]
+    pub routing_ewma_alpha: f64,
+
+    /// Error rate [0..1] at which a node is temporarily ejected from routing
+    #[clap(long, default_value = "0.5", value_parser = parse_error_rate)]
+    pub routing_ejection_error_rate: f64,
+
+    /// Minimum number of samples collected before a node can be ejected
+    #[clap(long, default_value = "10")]
+    pub routing_ejection_min_samples: u32,
+
+    /// For how long a node is ejected from routing in seconds
+    #[clap(long, default_value = "30")]
+    pub routing_ejection_duration: u64,
+}
+
+fn parse_ewma_alpha(value: &str) -> Result<f64, String> {
+    let alpha: f64 = value.parse().map_err(|err| format!("invalid number: {err}"))?;
+    if alpha > 0.0 && alpha <= 1.0 {
+        Ok(alpha)
+    } else {
+        Err(format!("{alpha} is not in the range (0, 1]"))
+    }
+}
+
+fn parse_error_rate(value: &str) -> Result<f64, String> {
+    let rate: f64 = value.parse().map_err(|err| format!("invalid number: {err}"))?;
+    if (0.0..=1.0).contains(&rate) {
+        Ok(rate)
+    } else {
+        Err(format!("{rate} is not in the range [0, 1]"))
+    }
+}
+
+#[derive(Args)]
+pub struct RateLimitingConfig {
+    /// The path to the YAML file with rate limiting rules, rate limiting is disabled if not set
+    #[clap(long)]
+    pub rate_limit_rules_path: Option<PathBuf>,
+
+    /// How frequently to check the rules file for changes in seconds
+    #[clap(long, default_value = "10")]
+    pub rate_limit_reload_interval: u64,
+}
+
+#[derive(Args)]
+pub struct CacheConfig {
+    /// Maximum size of the anonymous query response cache in bytes, caching is disabled if not set.
+    /// Responses are only served to identical requests (e.g. retries), since they are signed over the request id
+    #[clap(long)]
+    pub cache_size_bytes: Option<usize>,
+
+    /// Maximum size of a single response to be cached in bytes
+    #[clap(long, default_value = "131072")]
+    pub cache_max_item_size_bytes: usize,
+
+    /// Time-to-live of the cached responses in seconds
+    #[clap(long, default_value = "1")]
+    pub cache_ttl: u64,
+
+    /// Comma separated list of canister IDs whose responses are never cached
+    #[clap(long, value_delimiter = ',')]
+    pub cache_bypass_canisters: Vec<Principal>,
+}
+
 #[derive(Args)]
 pub struct FirewallConfig {
     /// The path to the nftables replica ruleset file to update
@@ -130,3 +216,30 @@ pub struct MonitoringConfig {
     #[clap(long, default_value = "127.0.0.1:9090")]
     pub metrics_addr: SocketAddr,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ewma_alpha() {
+        assert_eq!(parse_ewma_alpha("0.3"), Ok(0.3));
+        assert_eq!(parse_ewma_alpha("1"), Ok(1.0));
+        assert!(parse_ewma_alpha("0").is_err());
+        assert!(parse_ewma_alpha("-0.1").is_err());
+        assert!(parse_ewma_alpha("1.5").is_err());
+        assert!(parse_ewma_alpha("NaN").is_err());
+        assert!(parse_ewma_alpha("foo").is_err());
+    }
+
+    #[test]
+    fn test_parse_error_rate() {
+        assert_eq!(parse_error_rate("0"), Ok(0.0));
+        assert_eq!(parse_error_rate("0.5"), Ok(0.5));
+        assert_eq!(parse_error_rate("1"), Ok(1.0));
+        assert!(parse_error_rate("-0.1").is_err());
+        assert!(parse_error_rate("1.1").is_err());
+        assert!(parse_error_rate("NaN").is_err());
+        assert!(parse_error_rate("foo").is_err());
+    }
+}
@@ -32,6 +32,7 @@ use {
 };
 
 use crate::{
+    cache::{self, Cache, CacheParams},
     check::{Checker, Runner as CheckRunner},
     cli::Cli,
     configuration::{
@@ -43,7 +44,11 @@ use crate::{
     metrics::{self, HttpMetricParams, MetricParams, WithMetrics, HTTP_DURATION_BUCKETS},
     nns::{Load, Loader},
     persist,
+    rate_limit::{RateLimit, RateLimiter, RulesLoader},
     routes::{self, Health, Lookup, Proxy, ProxyRouter, RootKey},
+    scoring::{
+        EwmaParams, EwmaSelector, NodeSelector, RandomSelector, RoutingStrategy, WithScoring,
+    },
     snapshot::Runner as SnapshotRunner,
     tls_verify::TlsVerifier,
 };
@@ -219,15 +224,34 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
     );
     let configuration_runner = WithThrottle(configuration_runner, ThrottleParams::new(10 * SECOND));
 
+    // Node Selection
+    let node_selector: Arc<dyn NodeSelector> = match cli.routing.routing_strategy {
+        RoutingStrategy::Random => Arc::new(RandomSelector),
+        RoutingStrategy::Ewma => Arc::new(EwmaSelector::new(
+            EwmaParams {
+                alpha: cli.routing.routing_ewma_alpha,
+                ejection_error_rate: cli.routing.routing_ejection_error_rate,
+                ejection_min_samples: cli.routing.routing_ejection_min_samples,
+                ejection_duration: Duration::from_secs(cli.routing.routing_ejection_duration),
+            },
+            &registry,
+        )),
+    };
+
     // Server / API
     let proxy_router = ProxyRouter::new(
         http_client.clone(),
         Arc::clone(&lookup_table),
+        Arc::clone(&node_selector),
         [DER_PREFIX.as_slice(), nns_pub_key.into_bytes().as_slice()].concat(),
     );
 
     let proxy_router = Arc::new(proxy_router);
 
+    // Rate Limiting
+    let rate_limiter = Arc::new(RateLimiter::new(&registry));
+    let rl = rate_limiter.clone() as Arc<dyn RateLimit>;
+
     let (p, lk, rk, h) = (
         proxy_router.clone() as Arc<dyn Proxy>,
         proxy_router.clone() as Arc<dyn Lookup>,
@@ -235,11 +259,30 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
         proxy_router.clone() as Arc<dyn Health>,
     );
 
+    // Anonymous Query Cache
+    let cache = cli.cache.cache_size_bytes.map(|max_size| {
+        Arc::new(Cache::new(
+            CacheParams {
+                max_size,
+                max_item_size: cli.cache.cache_max_item_size_bytes,
+                ttl: Duration::from_secs(cli.cache.cache_ttl),
+                bypass_canisters: cli.cache.cache_bypass_canisters.iter().cloned().collect(),
+            },
+            &registry,
+        ))
+    });
+
     let routers_https = {
+        let mut query_route = post(routes::query).with_state(p.clone());
+        if let Some(cache) = cache {
+            query_route = query_route.layer(middleware::from_fn_with_state(
+                cache,
+                cache::cache_middleware,
+            ));
+        }
+
         let r1 = Router::new()
-            .route("/api/v2/canister/:canister_id/query", {
-                post(routes::query).with_state(p.clone())
-            })
+            .route("/api/v2/canister/:canister_id/query", query_route)
             .route("/api/v2/canister/:canister_id/call", {
                 post(routes::call).with_state(p.clone())
             })
@@ -250,7 +293,7 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
                 ServiceBuilder::new()
                     .layer(DefaultBodyLimit::max(2 * MB))
                     .layer(middleware::from_fn_with_state(
-                        lk.clone(),
+                        (lk.clone(), rl.clone()),
                         routes::preprocess_request,
                     )),
             );
@@ -289,7 +332,11 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
         .map(|ip| {
             Server::bind(SocketAddr::new(ip, cli.listen.http_port))
                 .acceptor(DefaultAcceptor)
-                .serve(routers_http.clone().into_make_service()) // TODO change back to routers_http - for now routing http==https
+                .serve(
+                    routers_http
+                        .clone()
+                        .into_make_service_with_connect_info::<SocketAddr>(),
+                ) // TODO change back to routers_http - for now routing http==https
         });
 
     // HTTPS
@@ -299,7 +346,11 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
         .map(|ip| {
             Server::bind(SocketAddr::new(ip, cli.listen.https_port))
                 .acceptor(tls_acceptor.clone())
-                .serve(routers_https.clone().into_make_service())
+                .serve(
+                    routers_https
+                        .clone()
+                        .into_make_service_with_connect_info::<SocketAddr>(),
+                )
         });
 
     // Snapshots
@@ -315,6 +366,7 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
         persist::Persister::new(Arc::clone(&lookup_table)),
         MetricParams::new(&registry, "persist"),
     );
+    let persister = WithScoring(persister, Arc::clone(&node_selector));
 
     let checker = Checker::new(http_client);
     let checker = WithMetrics(
@@ -331,6 +383,8 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
         cli.health.check_retries,
         Duration::from_secs(cli.health.check_retry_interval),
     );
+    // Score the outcome of the check as a whole, not every retried attempt
+    let checker = WithScoring(checker, node_selector);
 
     let check_runner = CheckRunner::new(
         Arc::clone(&routing_table),
@@ -346,12 +400,29 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
     );
 
     // Runners
-    let runners: Vec<Box<dyn Run>> = vec![
+    let mut runners: Vec<Box<dyn Run>> = vec![
         Box::new(configuration_runner),
         Box::new(snapshot_runner),
         Box::new(check_runner),
     ];
 
+    // Rate Limiting Rules
+    if let Some(path) = cli.rate_limiting.rate_limit_rules_path.clone() {
+        let rules_loader = RulesLoader::new(path, rate_limiter);
+        let rules_loader = WithMetrics(
+            rules_loader,
+            MetricParams::new(&registry, "run_rate_limit_rules"),
+        );
+        let rules_loader = WithThrottle(
+            rules_loader,
+            ThrottleParams::new(Duration::from_secs(
+                cli.rate_limiting.rate_limit_reload_interval,
+            )),
+        );
+
+        runners.push(Box::new(rules_loader));
+    }
+
     TokioScope::scope_and_block(|s| {
         s.spawn(
             axum::Server::bind(&cli.monitoring.metrics_addr)
@@ -1,4 +1,5 @@
 mod acme;
+mod cache;
 mod check;
 mod cli;
 mod configuration;
@@ -9,7 +10,9 @@ mod http;
 mod metrics;
 mod nns;
 mod persist;
+mod rate_limit;
 mod routes;
+mod scoring;
 mod snapshot;
 mod tls_verify;
 
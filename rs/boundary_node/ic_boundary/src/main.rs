@@ -4,6 +4,7 @@ use clap::Parser;
 use crate::cli::Cli;
 
 mod acme;
+mod cache;
 mod check;
 mod cli;
 mod configuration;
@@ -14,7 +15,9 @@ mod http;
 mod metrics;
 mod nns;
 mod persist;
+mod rate_limit;
 mod routes;
+mod scoring;
 mod snapshot;
 mod tls_verify;
 
@@ -1,4 +1,4 @@
-use std::{sync::Arc, time::Instant};
+use std::{collections::HashSet, sync::Arc, time::Instant};
 
 use anyhow::Error;
 use arc_swap::ArcSwapOption;
@@ -9,6 +9,7 @@ use tracing::{error, info};
 
 use crate::{
     metrics::{MetricParams, WithMetrics},
+    scoring::WithScoring,
     snapshot::{Node, RoutingTable},
 };
 
@@ -221,5 +222,21 @@ impl<T: Persist> Persist for WithMetrics<T> {
     }
 }
 
+#[async_trait]
+impl<T: Persist> Persist for WithScoring<T> {
+    // Drop the scores of the nodes that are no longer in the routing table
+    async fn persist(&self, rt: RoutingTable) -> Result<PersistStatus, Error> {
+        let node_ids = rt
+            .subnets
+            .iter()
+            .flat_map(|x| x.nodes.iter().map(|x| x.id))
+            .collect::<HashSet<_>>();
+
+        self.1.retain(&node_ids);
+
+        self.0.persist(rt).await
+    }
+}
+
 #[cfg(test)]
 pub mod test;
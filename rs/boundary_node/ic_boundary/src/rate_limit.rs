@@ -0,0 +1,344 @@
+use std::{
+    collections::HashSet,
+    net::IpAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{anyhow, Context, Error};
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use candid::Principal;
+use dashmap::DashMap;
+use prometheus::{
+    register_int_counter_vec_with_registry, register_int_gauge_with_registry, IntCounterVec,
+    IntGauge, Registry,
+};
+use serde::{de::Error as _, Deserialize, Deserializer};
+use tracing::info;
+
+use crate::{
+    core::Run,
+    routes::{RequestContext, RequestType},
+};
+
+// Attribute of the request that is used to select the token bucket
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyBy {
+    Ip,
+    Sender,
+    Canister,
+    Method,
+}
+
+// Rate limiting rule, applies to requests matching all of the specified matchers.
+// Every distinct combination of `key_by` attributes gets its own token bucket
+// which holds up to `burst` tokens and is refilled with `limit` tokens every `interval_sec`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    #[serde(default, deserialize_with = "deserialize_principals")]
+    pub canister_ids: Option<Vec<Principal>>,
+    pub methods: Option<Vec<String>>,
+    pub request_types: Option<Vec<RequestType>>,
+    #[serde(default)]
+    pub key_by: Vec<KeyBy>,
+    pub limit: u32,
+    pub interval_sec: u64,
+    pub burst: Option<u32>,
+}
+
+// Principals are given in their textual representation
+fn deserialize_principals<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<Principal>>, D::Error> {
+    Option::<Vec<String>>::deserialize(deserializer)?
+        .map(|ids| {
+            ids.iter()
+                .map(|x| Principal::from_text(x).map_err(D::Error::custom))
+                .collect()
+        })
+        .transpose()
+}
+
+impl Rule {
+    fn matches(&self, ctx: &RequestContext) -> bool {
+        if let Some(ids) = &self.canister_ids {
+            if !ctx.canister_id.map_or(false, |x| ids.contains(&x)) {
+                return false;
+            }
+        }
+
+        if let Some(methods) = &self.methods {
+            if !ctx
+                .method_name
+                .as_ref()
+                .map_or(false, |x| methods.contains(x))
+            {
+                return false;
+            }
+        }
+
+        if let Some(types) = &self.request_types {
+            if !types.contains(&ctx.request_type) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn key(&self, ctx: &RequestContext, ip: Option<IpAddr>) -> String {
+        self.key_by
+            .iter()
+            .map(|x| match x {
+                KeyBy::Ip => ip.map(|x| x.to_string()),
+                KeyBy::Sender => ctx.sender.map(|x| x.to_string()),
+                KeyBy::Canister => ctx.canister_id.map(|x| x.to_string()),
+                KeyBy::Method => ctx.method_name.clone(),
+            })
+            .map(|x| x.unwrap_or_else(|| "unknown".to_string()))
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+
+    fn capacity(&self) -> f64 {
+        self.burst.unwrap_or(self.limit) as f64
+    }
+
+    // Tokens per second
+    fn rate(&self) -> f64 {
+        self.limit as f64 / self.interval_sec as f64
+    }
+}
+
+// Parses and validates the rules
+pub fn parse_rules(data: &str) -> Result<Vec<Rule>, Error> {
+    let rules: Vec<Rule> = serde_yaml::from_str(data).context("unable to parse rules")?;
+
+    let mut names = HashSet::new();
+    for r in rules.iter() {
+        if !names.insert(r.name.as_str()) {
+            return Err(anyhow!("duplicate rule name '{}'", r.name));
+        }
+
+        if r.limit == 0 || r.interval_sec == 0 || r.burst == Some(0) {
+            return Err(anyhow!(
+                "rule '{}': limit, interval_sec and burst must be positive",
+                r.name
+            ));
+        }
+    }
+
+    Ok(rules)
+}
+
+struct Bucket {
+    tokens: f64,
+    capacity: f64,
+    rate: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(rule: &Rule, now: Instant) -> Self {
+        Self {
+            tokens: rule.capacity(),
+            capacity: rule.capacity(),
+            rate: rule.rate(),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+    }
+
+    // Check if a token is available or return the time to wait until one is
+    fn check(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+
+        if self.tokens >= 1.0 {
+            return Ok(());
+        }
+
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+    }
+
+    // Take a token that was found to be available by `check`. A concurrent request
+    // might have taken it in the meantime, in which case the bucket goes into debt
+    // that is paid back by the refill.
+    fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+}
+
+struct RateLimitMetrics {
+    counter: IntCounterVec,
+    buckets: IntGauge,
+}
+
+impl RateLimitMetrics {
+    fn new(registry: &Registry) -> Self {
+        Self {
+            counter: register_int_counter_vec_with_registry!(
+                "rate_limit_total",
+                "Counts requests evaluated by rate limiting rules",
+                &["rule", "status"],
+                registry
+            )
+            .unwrap(),
+
+            buckets: register_int_gauge_with_registry!(
+                "rate_limit_buckets",
+                "Number of active rate limiting token buckets",
+                registry
+            )
+            .unwrap(),
+        }
+    }
+}
+
+pub trait RateLimit: Send + Sync {
+    // Check if the request is allowed, otherwise return the time after which it can be retried
+    fn check(&self, ctx: &RequestContext, ip: Option<IpAddr>) -> Result<(), Duration>;
+}
+
+pub struct RateLimiter {
+    rules: ArcSwap<Vec<Rule>>,
+    // Token buckets keyed by rule name and request key
+    buckets: DashMap<(String, String), Bucket>,
+    metrics: RateLimitMetrics,
+}
+
+impl RateLimiter {
+    pub fn new(registry: &Registry) -> Self {
+        Self {
+            rules: ArcSwap::from_pointee(vec![]),
+            buckets: DashMap::new(),
+            metrics: RateLimitMetrics::new(registry),
+        }
+    }
+
+    // Replaces the rules, all the existing buckets are dropped
+    pub fn set_rules(&self, rules: Vec<Rule>) {
+        self.rules.store(Arc::new(rules));
+        self.buckets.clear();
+        self.metrics.buckets.set(0);
+    }
+
+    // Drops buckets that are full, they're equivalent to non-existing ones
+    pub fn prune(&self) {
+        let now = Instant::now();
+
+        self.buckets.retain(|_, b| {
+            b.refill(now);
+            b.tokens < b.capacity
+        });
+
+        self.metrics.buckets.set(self.buckets.len() as i64);
+    }
+}
+
+impl RateLimit for RateLimiter {
+    // Tokens are only taken if all matching rules admit the request,
+    // so that a request rejected by one rule doesn't drain the buckets of the others
+    fn check(&self, ctx: &RequestContext, ip: Option<IpAddr>) -> Result<(), Duration> {
+        let now = Instant::now();
+        let rules = self.rules.load();
+
+        let mut keys = vec![];
+        let mut retry_after = None;
+
+        for rule in rules.iter().filter(|x| x.matches(ctx)) {
+            let key = (rule.name.clone(), rule.key(ctx, ip));
+
+            let out = self
+                .buckets
+                .entry(key.clone())
+                .or_insert_with(|| Bucket::new(rule, now))
+                .check(now);
+
+            let status = if out.is_ok() { "ok" } else { "limited" };
+            self.metrics
+                .counter
+                .with_label_values(&[rule.name.as_str(), status])
+                .inc();
+
+            match out {
+                Ok(()) => keys.push(key),
+                // The request can only be retried once every rule admits it
+                Err(wait) => retry_after = retry_after.max(Some(wait)),
+            }
+        }
+
+        if let Some(wait) = retry_after {
+            return Err(wait);
+        }
+
+        for key in keys {
+            if let Some(mut bucket) = self.buckets.get_mut(&key) {
+                bucket.take();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Reloads the rules when the file changes and prunes unused buckets
+pub struct RulesLoader {
+    path: PathBuf,
+    limiter: Arc<RateLimiter>,
+    modified: Option<SystemTime>,
+}
+
+impl RulesLoader {
+    pub fn new(path: PathBuf, limiter: Arc<RateLimiter>) -> Self {
+        Self {
+            path,
+            limiter,
+            modified: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Run for RulesLoader {
+    async fn run(&mut self) -> Result<(), Error> {
+        self.limiter.prune();
+
+        let modified = tokio::fs::metadata(&self.path)
+            .await
+            .and_then(|x| x.modified())
+            .context("unable to stat rules file")?;
+
+        if self.modified == Some(modified) {
+            return Ok(());
+        }
+
+        let data = tokio::fs::read_to_string(&self.path)
+            .await
+            .context("unable to read rules file")?;
+
+        // Keep the old rules if the new ones are invalid
+        let rules = parse_rules(&data)?;
+        info!(
+            "Loaded {} rate limiting rules from {}",
+            rules.len(),
+            self.path.display()
+        );
+
+        self.limiter.set_rules(rules);
+        self.modified = Some(modified);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
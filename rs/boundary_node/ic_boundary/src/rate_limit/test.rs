@@ -0,0 +1,140 @@
+use super::*;
+
+use std::net::Ipv4Addr;
+
+const RULES: &str = r#"
+- name: calls_per_ip
+  request_types: [call]
+  key_by: [ip]
+  limit: 2
+  interval_sec: 60
+
+- name: foo_per_canister
+  canister_ids: [sxiki-5ygae-aq]
+  methods: [foo]
+  key_by: [canister, method]
+  limit: 1
+  interval_sec: 3600
+  burst: 3
+"#;
+
+fn ctx(request_type: RequestType, method_name: &str) -> RequestContext {
+    RequestContext {
+        request_type,
+        canister_id: Some(Principal::from_text("sxiki-5ygae-aq").unwrap()),
+        sender: Some(Principal::from_text("sqjm4-qahae-aq").unwrap()),
+        method_name: Some(method_name.to_string()),
+        ..Default::default()
+    }
+}
+
+fn ip(i: u8) -> Option<IpAddr> {
+    Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, i)))
+}
+
+#[test]
+fn test_parse_rules() -> Result<(), Error> {
+    let rules = parse_rules(RULES)?;
+
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].request_types, Some(vec![RequestType::Call]));
+    assert_eq!(rules[0].key_by, vec![KeyBy::Ip]);
+    assert_eq!(rules[1].key_by, vec![KeyBy::Canister, KeyBy::Method]);
+    assert_eq!(rules[1].capacity(), 3.0);
+
+    // Duplicate names
+    let dup = format!("{RULES}{}", &RULES[RULES.find("- name: foo").unwrap()..]);
+    assert!(parse_rules(&dup).is_err());
+
+    // Zero limit
+    assert!(parse_rules("- {name: foo, limit: 0, interval_sec: 1}").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_rate_limit_by_ip() -> Result<(), Error> {
+    let rl = RateLimiter::new(&Registry::new());
+    rl.set_rules(parse_rules(RULES)?);
+
+    let call = ctx(RequestType::Call, "bar");
+
+    assert!(rl.check(&call, ip(1)).is_ok());
+    assert!(rl.check(&call, ip(1)).is_ok());
+
+    // Bucket is exhausted and refills with one token every 30s
+    let retry_after = rl.check(&call, ip(1)).unwrap_err();
+    assert!(retry_after > Duration::from_secs(29) && retry_after <= Duration::from_secs(30));
+
+    // Other IPs and request types are not affected
+    assert!(rl.check(&call, ip(2)).is_ok());
+    assert!(rl.check(&ctx(RequestType::Query, "bar"), ip(1)).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_rate_limit_by_canister_method() -> Result<(), Error> {
+    let rl = RateLimiter::new(&Registry::new());
+    rl.set_rules(parse_rules(RULES)?);
+
+    let query = ctx(RequestType::Query, "foo");
+
+    // Burst is allowed regardless of IP
+    for i in 0..3 {
+        assert!(rl.check(&query, ip(i)).is_ok());
+    }
+    assert!(rl.check(&query, ip(4)).is_err());
+
+    // Other methods are not affected
+    assert!(rl.check(&ctx(RequestType::Query, "bar"), ip(4)).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_rejected_request_does_not_drain_other_buckets() -> Result<(), Error> {
+    let rl = RateLimiter::new(&Registry::new());
+    rl.set_rules(parse_rules(RULES)?);
+
+    let call = ctx(RequestType::Call, "foo");
+
+    // Both rules match, the per-IP one runs out first
+    assert!(rl.check(&call, ip(1)).is_ok());
+    assert!(rl.check(&call, ip(1)).is_ok());
+
+    // Rejected by the per-IP rule, the per-canister bucket keeps its last token
+    for _ in 0..5 {
+        assert!(rl.check(&call, ip(1)).is_err());
+    }
+    assert!(rl.check(&call, ip(2)).is_ok());
+
+    // Now the per-canister bucket is exhausted and waits for the longer of the two
+    let retry_after = rl.check(&call, ip(1)).unwrap_err();
+    assert!(retry_after > Duration::from_secs(3599));
+
+    Ok(())
+}
+
+#[test]
+fn test_set_rules_and_prune() -> Result<(), Error> {
+    let rl = RateLimiter::new(&Registry::new());
+    rl.set_rules(parse_rules(RULES)?);
+
+    let call = ctx(RequestType::Call, "bar");
+    assert!(rl.check(&call, ip(1)).is_ok());
+    assert_eq!(rl.buckets.len(), 1);
+
+    // Bucket is not full yet
+    rl.prune();
+    assert_eq!(rl.buckets.len(), 1);
+
+    // Replacing the rules resets the buckets
+    rl.set_rules(vec![]);
+    assert_eq!(rl.buckets.len(), 0);
+    for _ in 0..10 {
+        assert!(rl.check(&call, ip(1)).is_ok());
+    }
+
+    Ok(())
+}
@@ -3,18 +3,25 @@ use arc_swap::ArcSwapOption;
 use async_trait::async_trait;
 use axum::{
     body::{Body, StreamBody},
-    extract::{Path, State},
+    extract::{ConnectInfo, Path, State},
     http::{Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Extension,
 };
 use candid::Principal;
-use http::{header, request::Parts, Method};
-use ic_types::messages::{HttpStatusResponse, ReplicaHealthStatus};
-use rand::seq::SliceRandom;
+use http::{header, request::Parts, HeaderValue, Method};
+use ic_types::messages::{
+    HttpQueryContent, HttpRequestEnvelope, HttpStatusResponse, ReplicaHealthStatus,
+};
 use serde::{Deserialize, Serialize};
-use std::{fmt, str::FromStr, sync::Arc};
+use std::{
+    fmt,
+    net::SocketAddr,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use url::Url;
 
 #[cfg(feature = "tls")]
@@ -27,12 +34,15 @@ use {
     tokio::sync::RwLock,
 };
 
-use crate::{http::HttpClient, persist::Routes, snapshot::Node};
+use crate::{
+    http::HttpClient, persist::Routes, rate_limit::RateLimit, scoring::NodeSelector, snapshot::Node,
+};
 
 const ANONYMOUS_PRINCIPAL: Principal = Principal::anonymous();
 
 // Type of IC request
-#[derive(Default, Clone, Copy, PartialEq)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum RequestType {
     #[default]
     Status,
@@ -65,6 +75,7 @@ pub enum ErrorCause {
     SubnetNotFound,
     NoHealthyNodes,
     ReplicaUnreachable(String),
+    RateLimited(Duration), // Retry after
     Other(String),
 }
 
@@ -80,6 +91,7 @@ impl ErrorCause {
             Self::SubnetNotFound => StatusCode::BAD_REQUEST, // TODO change to 404?
             Self::NoHealthyNodes => StatusCode::INTERNAL_SERVER_ERROR,
             Self::ReplicaUnreachable(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 
@@ -106,6 +118,7 @@ impl fmt::Display for ErrorCause {
             Self::SubnetNotFound => write!(f, "subnet_not_found"),
             Self::NoHealthyNodes => write!(f, "no_healthy_nodes"),
             Self::ReplicaUnreachable(_) => write!(f, "replica_unreachable"),
+            Self::RateLimited(_) => write!(f, "rate_limited"),
         }
     }
 }
@@ -118,6 +131,8 @@ pub struct RequestContext {
     pub node: Option<Node>,
     pub sender: Option<Principal>,
     pub method_name: Option<String>,
+    // Representation-independent hash of the request content, only set for queries
+    pub request_id: Option<[u8; 32]>,
     pub request_type: RequestType,
     pub error_cause: ErrorCause,
     pub request_size: u32,
@@ -174,6 +189,7 @@ pub trait RootKey: Sync + Send {
 pub struct ProxyRouter {
     http_client: Arc<dyn HttpClient>,
     published_routes: Arc<ArcSwapOption<Routes>>,
+    node_selector: Arc<dyn NodeSelector>,
     root_key: Vec<u8>,
 }
 
@@ -181,11 +197,13 @@ impl ProxyRouter {
     pub fn new(
         http_client: Arc<dyn HttpClient>,
         published_routes: Arc<ArcSwapOption<Routes>>,
+        node_selector: Arc<dyn NodeSelector>,
         root_key: Vec<u8>,
     ) -> Self {
         Self {
             http_client,
             published_routes,
+            node_selector,
             root_key,
         }
     }
@@ -216,10 +234,16 @@ impl Proxy for ProxyRouter {
         *request.body_mut() = Some(body.into());
 
         // Execute request
-        let response = self
-            .http_client
-            .execute(request)
-            .await
+        let start_time = Instant::now();
+        let response = self.http_client.execute(request).await;
+
+        // Feed the outcome to the node selector, the latency is measured until the headers are received
+        let ok = response
+            .as_ref()
+            .map_or(false, |x| !x.status().is_server_error());
+        self.node_selector.observe(&node, start_time.elapsed(), ok);
+
+        let response = response
             .map_err(|e| ErrorCause::ReplicaUnreachable(format!("HTTP call failed: {e}")))?;
 
         // Convert Reqwest response into Axum one with body streaming
@@ -244,12 +268,11 @@ impl Lookup for ProxyRouter {
             .lookup(id.to_owned())
             .ok_or(ErrorCause::SubnetNotFound)?; // Requested canister route wasn't found
 
-        // Pick random node
-        let node = subnet
-            .nodes
-            .choose(&mut rand::thread_rng())
-            .ok_or(ErrorCause::NoHealthyNodes)? // No healhy nodes in subnet
-            .clone();
+        // Pick a node according to the routing strategy
+        let node = self
+            .node_selector
+            .select(&subnet.nodes)
+            .ok_or(ErrorCause::NoHealthyNodes)?; // No healhy nodes in subnet
 
         Ok(node)
     }
@@ -324,6 +347,20 @@ pub async fn read_body(request: Request<Body>) -> Result<(Parts, Vec<u8>), Error
 
 // Parses body as a generic CBOR request and enriches the context
 pub fn parse_body(ctx: &mut RequestContext, body: &[u8]) -> Result<(), Error> {
+    // Queries are decoded as such right away, since their request id is needed for caching
+    if ctx.request_type == RequestType::Query {
+        let envelope: HttpRequestEnvelope<HttpQueryContent> = serde_cbor::from_slice(body)?;
+        let HttpQueryContent::Query { query } = &envelope.content;
+
+        ctx.sender = Some(Principal::try_from_slice(&query.sender.0)?);
+        ctx.canister_id_cbor = Some(Principal::try_from_slice(&query.canister_id.0)?);
+        ctx.method_name = Some(query.method_name.clone());
+        // The request id covers all the fields of the request, including the nonce and the expiry
+        ctx.request_id = Some(envelope.content.representation_independent_hash());
+
+        return Ok(());
+    }
+
     let envelope: ICRequestEnvelope = serde_cbor::from_slice(body)?;
     let content = envelope.content;
 
@@ -348,12 +385,24 @@ pub enum ApiError {
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        (match self {
-            ApiError::_Custom(c, b) => (c, b),
-            ApiError::ProxyError(c) => (c.status_code(), c.to_string()),
-            ApiError::Unspecified(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
-        })
-        .into_response()
+        match self {
+            ApiError::_Custom(c, b) => (c, b).into_response(),
+            ApiError::ProxyError(c) => {
+                let mut resp = (c.status_code(), c.to_string()).into_response();
+
+                // Tell the client when to come back, rounding up to whole seconds
+                if let ErrorCause::RateLimited(retry_after) = c {
+                    let secs = retry_after.as_secs() + (retry_after.subsec_nanos() > 0) as u64;
+                    resp.headers_mut()
+                        .insert(header::RETRY_AFTER, HeaderValue::from(secs.max(1)));
+                }
+
+                resp
+            }
+            ApiError::Unspecified(err) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+            }
+        }
     }
 }
 
@@ -375,14 +424,33 @@ pub async fn postprocess_response(request: Request<Body>, next: Next<Body>) -> i
     resp
 }
 
+// Infer the request type from the last path segment
+fn request_type_from_path(path: &str) -> RequestType {
+    match path.rsplit('/').next() {
+        Some("query") => RequestType::Query,
+        Some("call") => RequestType::Call,
+        Some("read_state") => RequestType::ReadState,
+        _ => RequestType::Status,
+    }
+}
+
 // Preprocess the request before handing it over to handlers
 pub async fn preprocess_request(
-    State(lk): State<Arc<dyn Lookup>>,
+    State((lk, rl)): State<(Arc<dyn Lookup>, Arc<dyn RateLimit>)>,
     Path(canister_id): Path<String>,
     request: Request<Body>,
     next: Next<Body>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let mut ctx = RequestContext::default();
+    let mut ctx = RequestContext {
+        request_type: request_type_from_path(request.uri().path()),
+        ..Default::default()
+    };
+
+    // Source address is only available if the server was started with connect info
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|x| x.0.ip());
 
     // Consume body
     let (parts, body) = read_body(request).await?;
@@ -397,6 +465,9 @@ pub async fn preprocess_request(
 
     parse_body(&mut ctx, &body).map_err(|err| ErrorCause::UnableToParseCBOR(err.to_string()))?;
 
+    // Apply rate limiting rules
+    rl.check(&ctx, ip).map_err(ErrorCause::RateLimited)?;
+
     // Try to look up a target node using canister id
     ctx.node = Some(lk.lookup(&canister_id).await?);
 
@@ -105,8 +105,18 @@ async fn test_query() -> Result<(), Error> {
 
     let body = serde_cbor::to_vec(&envelope).unwrap();
 
-    let mut ctx = RequestContext::default();
+    let mut ctx = RequestContext {
+        request_type: RequestType::Query,
+        ..Default::default()
+    };
     parse_body(&mut ctx, &body)?;
+    assert_eq!(ctx.sender, Some(sender));
+    assert_eq!(ctx.canister_id_cbor, Some(canister_id));
+    assert_eq!(ctx.method_name, Some("foobar".to_string()));
+    assert_eq!(
+        ctx.request_id,
+        Some(envelope.content.representation_independent_hash())
+    );
     ctx.canister_id = Some(canister_id);
     ctx.node = Some(node);
 
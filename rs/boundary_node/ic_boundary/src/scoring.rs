@@ -0,0 +1,307 @@
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use candid::Principal;
+use clap::ValueEnum;
+use dashmap::DashMap;
+use prometheus::{
+    register_gauge_vec_with_registry, register_int_counter_vec_with_registry,
+    register_int_gauge_vec_with_registry, GaugeVec, IntCounterVec, IntGaugeVec, Registry,
+};
+use rand::seq::{index::sample, SliceRandom};
+use tracing::warn;
+
+use crate::snapshot::Node;
+
+// Lower bound for the success rate when calculating the score
+// so that nodes failing all requests still get a finite score
+const MIN_SUCCESS_RATE: f64 = 0.05;
+
+// Latency in seconds assumed for nodes that failed all their samples
+// so that they score worse than any node that has responded
+const FAILED_NODE_LATENCY: f64 = 60.0;
+
+const LABELS_NODE: &[&str] = &["subnet_id", "node_id"];
+
+// Strategy used to pick a node within a subnet
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+pub enum RoutingStrategy {
+    // Pick a random healthy node
+    Random,
+    // Pick the better-scored one of two random healthy nodes
+    Ewma,
+}
+
+// Picks the node to send a request to and learns from the outcome of requests and checks
+pub trait NodeSelector: Send + Sync {
+    // Select a node out of the given healthy nodes of a subnet
+    fn select(&self, nodes: &[Node]) -> Option<Node>;
+
+    // Record the outcome of a request or a health check sent to the node
+    fn observe(&self, node: &Node, latency: Duration, ok: bool);
+
+    // Drop the state of all the nodes that are not in the given set
+    fn retain(&self, node_ids: &HashSet<Principal>);
+}
+
+// Wrapper that feeds the outcome of the calls to the inner type into a node selector
+pub struct WithScoring<T>(pub T, pub Arc<dyn NodeSelector>);
+
+pub struct RandomSelector;
+
+impl NodeSelector for RandomSelector {
+    fn select(&self, nodes: &[Node]) -> Option<Node> {
+        nodes.choose(&mut rand::thread_rng()).cloned()
+    }
+
+    fn observe(&self, _node: &Node, _latency: Duration, _ok: bool) {}
+
+    fn retain(&self, _node_ids: &HashSet<Principal>) {}
+}
+
+#[derive(Clone, Debug)]
+pub struct EwmaParams {
+    // Weight of the newest sample in the moving averages, 0..1
+    pub alpha: f64,
+    // Error rate at which the node is ejected
+    pub ejection_error_rate: f64,
+    // Minimum number of samples before the node can be ejected
+    pub ejection_min_samples: u32,
+    // For how long the node is ejected
+    pub ejection_duration: Duration,
+}
+
+struct NodeStats {
+    subnet_id: Principal,
+    // Moving average of the latency in seconds, None until first successful sample
+    latency: Option<f64>,
+    // Moving average of the error rate, 0..1
+    error_rate: f64,
+    samples: u32,
+    ejected_until: Option<Instant>,
+}
+
+impl NodeStats {
+    fn new(subnet_id: Principal) -> Self {
+        Self {
+            subnet_id,
+            latency: None,
+            error_rate: 0.0,
+            samples: 0,
+            ejected_until: None,
+        }
+    }
+
+    fn is_ejected(&self, now: Instant) -> bool {
+        self.ejected_until.map_or(false, |x| x > now)
+    }
+
+    // The lower the latency and the error rate - the lower (better) is the score
+    fn score(&self) -> f64 {
+        self.latency.unwrap_or(FAILED_NODE_LATENCY) / (1.0 - self.error_rate).max(MIN_SUCCESS_RATE)
+    }
+}
+
+struct ScoringMetrics {
+    latency: GaugeVec,
+    error_rate: GaugeVec,
+    score: GaugeVec,
+    ejected: IntGaugeVec,
+    ejections: IntCounterVec,
+}
+
+impl ScoringMetrics {
+    fn new(registry: &Registry) -> Self {
+        Self {
+            latency: register_gauge_vec_with_registry!(
+                "node_latency_ewma_sec",
+                "Moving average of the node latency in seconds",
+                LABELS_NODE,
+                registry
+            )
+            .unwrap(),
+
+            error_rate: register_gauge_vec_with_registry!(
+                "node_error_rate_ewma",
+                "Moving average of the node error rate",
+                LABELS_NODE,
+                registry
+            )
+            .unwrap(),
+
+            score: register_gauge_vec_with_registry!(
+                "node_score",
+                "Routing score of the node, lower is better",
+                LABELS_NODE,
+                registry
+            )
+            .unwrap(),
+
+            ejected: register_int_gauge_vec_with_registry!(
+                "node_ejected",
+                "Whether the node is currently ejected from routing",
+                LABELS_NODE,
+                registry
+            )
+            .unwrap(),
+
+            ejections: register_int_counter_vec_with_registry!(
+                "node_ejections_total",
+                "Counts ejections of the node from routing",
+                LABELS_NODE,
+                registry
+            )
+            .unwrap(),
+        }
+    }
+}
+
+// Selector that keeps exponentially weighted moving averages of latency and error rate
+// per node and uses the power of two choices to pick one, ejecting failing nodes for a while
+pub struct EwmaSelector {
+    params: EwmaParams,
+    stats: DashMap<Principal, NodeStats>,
+    metrics: ScoringMetrics,
+}
+
+impl EwmaSelector {
+    pub fn new(params: EwmaParams, registry: &Registry) -> Self {
+        Self {
+            params,
+            stats: DashMap::new(),
+            metrics: ScoringMetrics::new(registry),
+        }
+    }
+
+    // Nodes without any statistics get the best score so that they receive some traffic
+    fn score(&self, node_id: &Principal) -> f64 {
+        self.stats.get(node_id).map_or(0.0, |x| x.score())
+    }
+
+    fn is_ejected(&self, node_id: &Principal, now: Instant) -> bool {
+        self.stats.get(node_id).map_or(false, |x| x.is_ejected(now))
+    }
+}
+
+impl NodeSelector for EwmaSelector {
+    fn select(&self, nodes: &[Node]) -> Option<Node> {
+        let now = Instant::now();
+
+        let mut candidates = nodes
+            .iter()
+            .filter(|x| !self.is_ejected(&x.id, now))
+            .collect::<Vec<_>>();
+
+        // If all the nodes are ejected then it's better to ignore ejections than to fail
+        if candidates.is_empty() {
+            candidates = nodes.iter().collect();
+        }
+
+        let node = match candidates.len() {
+            0 => return None,
+            1 => candidates[0],
+            n => {
+                let idx = sample(&mut rand::thread_rng(), n, 2);
+                let (a, b) = (candidates[idx.index(0)], candidates[idx.index(1)]);
+
+                if self.score(&b.id) < self.score(&a.id) {
+                    b
+                } else {
+                    a
+                }
+            }
+        };
+
+        Some(node.clone())
+    }
+
+    fn observe(&self, node: &Node, latency: Duration, ok: bool) {
+        let now = Instant::now();
+        let alpha = self.params.alpha;
+
+        let mut stats = self
+            .stats
+            .entry(node.id)
+            .or_insert_with(|| NodeStats::new(node.subnet_id));
+
+        // Latency of failed requests is not representative, e.g. connection refused is fast
+        if ok {
+            let latency = latency.as_secs_f64();
+            stats.latency = Some(
+                stats
+                    .latency
+                    .map_or(latency, |x| alpha * latency + (1.0 - alpha) * x),
+            );
+        }
+
+        let error = if ok { 0.0 } else { 1.0 };
+        stats.error_rate = alpha * error + (1.0 - alpha) * stats.error_rate;
+        stats.samples = stats.samples.saturating_add(1);
+
+        let subnet_id = stats.subnet_id.to_string();
+        let node_id = node.id.to_string();
+        let labels = &[subnet_id.as_str(), node_id.as_str()];
+
+        // Eject the node and give it a clean slate once the ejection is over
+        if !stats.is_ejected(now)
+            && stats.samples >= self.params.ejection_min_samples
+            && stats.error_rate >= self.params.ejection_error_rate
+        {
+            warn!(
+                subnet_id,
+                node_id,
+                error_rate = stats.error_rate,
+                "Ejecting node from routing for {}s",
+                self.params.ejection_duration.as_secs()
+            );
+
+            stats.ejected_until = Some(now + self.params.ejection_duration);
+            stats.error_rate = 0.0;
+            stats.samples = 0;
+            self.metrics.ejections.with_label_values(labels).inc();
+        }
+
+        self.metrics
+            .latency
+            .with_label_values(labels)
+            .set(stats.latency.unwrap_or(0.0));
+        self.metrics
+            .error_rate
+            .with_label_values(labels)
+            .set(stats.error_rate);
+        self.metrics
+            .score
+            .with_label_values(labels)
+            .set(stats.score());
+        self.metrics
+            .ejected
+            .with_label_values(labels)
+            .set(stats.is_ejected(now) as i64);
+    }
+
+    fn retain(&self, node_ids: &HashSet<Principal>) {
+        self.stats.retain(|node_id, stats| {
+            if node_ids.contains(node_id) {
+                return true;
+            }
+
+            let subnet_id = stats.subnet_id.to_string();
+            let node_id = node_id.to_string();
+            let labels = &[subnet_id.as_str(), node_id.as_str()];
+
+            let _ = self.metrics.latency.remove_label_values(labels);
+            let _ = self.metrics.error_rate.remove_label_values(labels);
+            let _ = self.metrics.score.remove_label_values(labels);
+            let _ = self.metrics.ejected.remove_label_values(labels);
+            let _ = self.metrics.ejections.remove_label_values(labels);
+
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,125 @@
+use super::*;
+
+use crate::persist::test::node;
+
+fn selector(ejection_duration: Duration) -> EwmaSelector {
+    EwmaSelector::new(
+        EwmaParams {
+            alpha: 0.5,
+            ejection_error_rate: 0.6,
+            ejection_min_samples: 2,
+            ejection_duration,
+        },
+        &Registry::new(),
+    )
+}
+
+fn subnet_id() -> Principal {
+    Principal::from_text("f7crg-kabae").unwrap()
+}
+
+#[test]
+fn test_ewma() {
+    let sel = selector(Duration::from_secs(60));
+    let n = node(0, subnet_id());
+
+    sel.observe(&n, Duration::from_millis(100), true);
+    sel.observe(&n, Duration::from_millis(300), true);
+    sel.observe(&n, Duration::from_millis(900), false);
+
+    let stats = sel.stats.get(&n.id).unwrap();
+    // Failed samples don't affect latency
+    assert!((stats.latency.unwrap() - 0.2).abs() < 1e-9);
+    assert!((stats.error_rate - 0.5).abs() < 1e-9);
+    assert!((stats.score() - 0.4).abs() < 1e-9);
+}
+
+#[test]
+fn test_select_prefers_faster_node() {
+    let sel = selector(Duration::from_secs(60));
+    let (fast, slow) = (node(0, subnet_id()), node(1, subnet_id()));
+
+    sel.observe(&fast, Duration::from_millis(10), true);
+    sel.observe(&slow, Duration::from_millis(1000), true);
+
+    // With two nodes both are always sampled, so the faster one wins
+    let nodes = vec![fast.clone(), slow];
+    for _ in 0..100 {
+        assert_eq!(sel.select(&nodes), Some(fast.clone()));
+    }
+
+    assert_eq!(sel.select(&[]), None);
+}
+
+#[test]
+fn test_select_avoids_failing_node() {
+    let sel = selector(Duration::from_secs(60));
+    let (slow, failing) = (node(0, subnet_id()), node(1, subnet_id()));
+
+    sel.observe(&slow, Duration::from_millis(1000), true);
+    // Single failure is below the ejection threshold
+    sel.observe(&failing, Duration::from_millis(10), false);
+    assert!(!sel.is_ejected(&failing.id, Instant::now()));
+
+    // Node without a single successful sample scores worse than a responsive one
+    assert!(sel.score(&failing.id) > sel.score(&slow.id));
+
+    let nodes = vec![slow.clone(), failing];
+    for _ in 0..100 {
+        assert_eq!(sel.select(&nodes), Some(slow.clone()));
+    }
+}
+
+#[test]
+fn test_ejection() {
+    let sel = selector(Duration::from_secs(60));
+    let (good, bad) = (node(0, subnet_id()), node(1, subnet_id()));
+
+    // Unknown nodes get the best score, so make the good one slower than the bad one
+    sel.observe(&good, Duration::from_millis(500), true);
+    sel.observe(&bad, Duration::from_millis(10), true);
+    sel.observe(&bad, Duration::from_millis(10), false);
+    sel.observe(&bad, Duration::from_millis(10), false);
+
+    assert!(sel.is_ejected(&bad.id, Instant::now()));
+    assert!(!sel.is_ejected(&good.id, Instant::now()));
+
+    // Ejected node is never selected
+    let nodes = vec![good.clone(), bad.clone()];
+    for _ in 0..100 {
+        assert_eq!(sel.select(&nodes), Some(good.clone()));
+    }
+
+    // Unless there is no other choice
+    assert_eq!(sel.select(&[bad.clone()]), Some(bad));
+}
+
+#[test]
+fn test_ejection_expires() {
+    let sel = selector(Duration::ZERO);
+    let n = node(0, subnet_id());
+
+    sel.observe(&n, Duration::from_millis(10), false);
+    sel.observe(&n, Duration::from_millis(10), false);
+
+    // Node gets a clean slate after ejection
+    let stats = sel.stats.get(&n.id).unwrap();
+    assert!(stats.ejected_until.is_some());
+    assert!(!stats.is_ejected(Instant::now()));
+    assert_eq!(stats.samples, 0);
+    assert_eq!(stats.error_rate, 0.0);
+}
+
+#[test]
+fn test_retain() {
+    let sel = selector(Duration::from_secs(60));
+    let (n0, n1) = (node(0, subnet_id()), node(1, subnet_id()));
+
+    sel.observe(&n0, Duration::from_millis(10), true);
+    sel.observe(&n1, Duration::from_millis(10), true);
+
+    sel.retain(&HashSet::from([n0.id]));
+
+    assert!(sel.stats.contains_key(&n0.id));
+    assert!(!sel.stats.contains_key(&n1.id));
+}
@@ -1,2 +1,3 @@
 pub static REQUIRE_CERTIFICATION_HEADER_NAME: &str = "x-icx-require-certification";
 pub static IC_CERTIFICATE_HEADER_NAME: &str = "ic-certificate";
+pub static CERTIFICATE_EXPRESSION_HEADER_NAME: &str = "ic-certificateexpression";
@@ -0,0 +1,359 @@
+//! Support of `Range` requests for chunk-certified assets, see RFC 9110 section 14.
+
+use std::sync::Arc;
+
+use candid::{
+    types::{value::IDLValue, Label},
+    Nat,
+};
+use futures::{stream, StreamExt};
+use hyper::{
+    http::header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE},
+    Body, Response, StatusCode,
+};
+use ic_agent::Agent;
+use ic_utils::{
+    call::SyncCall,
+    interfaces::http_request::{
+        HttpRequestCanister, HttpRequestStreamingCallbackAny, StreamingCallbackHttpResponse,
+        StreamingStrategy, Token,
+    },
+};
+use tracing::warn;
+
+use crate::http::headers::{CERTIFICATE_EXPRESSION_HEADER_NAME, IC_CERTIFICATE_HEADER_NAME};
+use crate::http::response::HttpResponse;
+use crate::validate::ChunkCertification;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A single byte range of the `Range` header.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    /// `bytes=<first>-[<last>]`
+    FromTo(u64, Option<u64>),
+    /// `bytes=-<length>`
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// Parses the value of the `Range` header.
+    /// Multiple ranges are not supported, `None` is returned for them as for invalid values
+    /// and the header should be ignored.
+    pub fn parse(value: &str) -> Option<Self> {
+        let range = value.trim().strip_prefix("bytes=")?;
+        if range.contains(',') {
+            return None;
+        }
+
+        let (first, last) = range.trim().split_once('-')?;
+        let last = match last.trim() {
+            "" => None,
+            v => Some(v.parse().ok()?),
+        };
+
+        match (first.trim(), last) {
+            ("", Some(len)) => Some(Self::Suffix(len)),
+            ("", None) => None,
+            (first, last) => {
+                let first = first.parse().ok()?;
+                match last {
+                    Some(last) if last < first => None,
+                    _ => Some(Self::FromTo(first, last)),
+                }
+            }
+        }
+    }
+
+    /// Returns the inclusive bounds of the range for a body of the given length,
+    /// `None` if the range is not satisfiable.
+    pub fn resolve(&self, length: u64) -> Option<(u64, u64)> {
+        match *self {
+            Self::FromTo(first, _) if first >= length => None,
+            Self::FromTo(first, last) => {
+                Some((first, last.map_or(length - 1, |x| x.min(length - 1))))
+            }
+            Self::Suffix(0) => None,
+            Self::Suffix(_) if length == 0 => None,
+            Self::Suffix(len) => Some((length.saturating_sub(len), length - 1)),
+        }
+    }
+}
+
+// Returns the token of the asset canister's streaming callback which fetches the given chunk.
+// The asset canister keeps the index of the chunk in the `index` field of the token.
+fn chunk_token(token: &Token, index: u64) -> Option<Token> {
+    let mut fields = match &token.0 {
+        IDLValue::Record(fields) => fields.clone(),
+        _ => return None,
+    };
+
+    let field = fields
+        .iter_mut()
+        .find(|x| x.id.get_id() == Label::Named("index".into()).get_id())?;
+    field.val = IDLValue::Nat(Nat::from(index));
+
+    Some(Token(IDLValue::Record(fields)))
+}
+
+// Returns the part of the chunk starting at `offset` that lies within the inclusive range
+fn slice_chunk(mut data: Vec<u8>, offset: u64, start: u64, end: u64) -> Vec<u8> {
+    data.truncate((end + 1).saturating_sub(offset).min(data.len() as u64) as usize);
+    data.drain(..(start.saturating_sub(offset) as usize).min(data.len()));
+    data
+}
+
+struct ChunkFetcher {
+    agent: Agent,
+    first_chunk: Vec<u8>,
+    strategy: Option<StreamingStrategy<Token, HttpRequestStreamingCallbackAny>>,
+    certification: ChunkCertification,
+}
+
+impl ChunkFetcher {
+    async fn fetch(&self, index: u64) -> Result<Vec<u8>, BoxError> {
+        // The first chunk is part of the response
+        if index == 0 {
+            return Ok(self.first_chunk.clone());
+        }
+
+        let callback = match &self.strategy {
+            Some(StreamingStrategy::Callback(v)) => v,
+            None => {
+                return Err(format!("chunk {index} requested without a streaming strategy").into())
+            }
+        };
+
+        let token = chunk_token(&callback.token, index)
+            .ok_or("streaming token does not contain a chunk index")?;
+
+        let canister = HttpRequestCanister::create(&self.agent, callback.callback.0.principal);
+        let (StreamingCallbackHttpResponse { body, .. },) = canister
+            .http_request_stream_callback(&callback.callback.0.method, token)
+            .call()
+            .await?;
+
+        Ok(body)
+    }
+
+    // Fetches and verifies the chunk, returning only the part of it within the range
+    async fn fetch_range(&self, index: u64, start: u64, end: u64) -> Result<Vec<u8>, BoxError> {
+        let data = self.fetch(index).await?;
+
+        if let Err(e) = self.certification.verify_chunk(index, &data) {
+            warn!("Chunk failed verification: {e}");
+            return Err(e.into());
+        }
+
+        let offset = self.certification.chunk_offset(index);
+        Ok(slice_chunk(data, offset, start, end))
+    }
+}
+
+/// Creates a `206 Partial Content` response for the range, fetching and verifying only the
+/// chunks covering it.
+pub fn create_range_response(
+    agent: &Agent,
+    response: HttpResponse,
+    strategy: Option<StreamingStrategy<Token, HttpRequestStreamingCallbackAny>>,
+    certification: ChunkCertification,
+    range: ByteRange,
+) -> Result<Response<Body>, hyper::http::Error> {
+    let length = certification.length();
+    let (start, end) = match range.resolve(length) {
+        Some(v) => v,
+        None => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(CONTENT_RANGE, format!("bytes */{length}"))
+                .body(Body::empty())
+        }
+    };
+
+    let mut builder = Response::builder().status(StatusCode::PARTIAL_CONTENT);
+
+    // The certificate covers the full response, it's of no use to the client
+    for (name, value) in &response.headers {
+        if ![
+            IC_CERTIFICATE_HEADER_NAME,
+            CERTIFICATE_EXPRESSION_HEADER_NAME,
+            CONTENT_LENGTH.as_str(),
+            CONTENT_RANGE.as_str(),
+            ACCEPT_RANGES.as_str(),
+        ]
+        .iter()
+        .any(|x| x.eq_ignore_ascii_case(name))
+        {
+            builder = builder.header(name, value);
+        }
+    }
+
+    let indices = certification.chunks_for(start, end);
+    let fetcher = Arc::new(ChunkFetcher {
+        agent: agent.clone(),
+        first_chunk: response.body,
+        strategy,
+        certification,
+    });
+
+    // Chunks are fetched one by one, so only one of them is held in memory at any time
+    let body = stream::iter(indices).then(move |index| {
+        let fetcher = fetcher.clone();
+        async move { fetcher.fetch_range(index, start, end).await }
+    });
+
+    builder
+        .header(CONTENT_RANGE, format!("bytes {start}-{end}/{length}"))
+        .header(CONTENT_LENGTH, end - start + 1)
+        .header(ACCEPT_RANGES, "bytes")
+        .header("X-IC-Streaming-Response", "true")
+        .body(Body::wrap_stream(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::types::value::IDLField;
+    use ic_agent::agent::http_transport::{hyper::Uri, HyperReplicaV2Transport};
+    use sha2::{Digest, Sha256};
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    fn agent() -> Agent {
+        let uri = Uri::from_static("http://www.example.com");
+        let transport = HyperReplicaV2Transport::<Body>::create(uri).unwrap();
+        Agent::builder().with_transport(transport).build().unwrap()
+    }
+
+    fn response(body: &[u8]) -> HttpResponse {
+        HttpResponse {
+            status_code: 200,
+            headers: vec![
+                ("Content-Type".to_string(), "video/mp4".to_string()),
+                (IC_CERTIFICATE_HEADER_NAME.to_string(), "cert".to_string()),
+            ],
+            streaming_body: None,
+            has_streaming_body: true,
+            body: body.to_vec(),
+        }
+    }
+
+    // Body "abcdefghij" in chunks of 4 bytes
+    fn certification() -> ChunkCertification {
+        let chunks: [&[u8]; 3] = [b"abcd", b"efgh", b"ij"];
+        ChunkCertification::new(
+            4,
+            10,
+            chunks.iter().map(|x| Sha256::digest(x).to_vec()).collect(),
+        )
+    }
+
+    #[test]
+    fn parse_range() {
+        assert_eq!(
+            ByteRange::parse("bytes=0-499"),
+            Some(ByteRange::FromTo(0, Some(499)))
+        );
+        assert_eq!(
+            ByteRange::parse("bytes=500-"),
+            Some(ByteRange::FromTo(500, None))
+        );
+        assert_eq!(ByteRange::parse("bytes=-500"), Some(ByteRange::Suffix(500)));
+
+        assert_eq!(ByteRange::parse("bytes=0-1, 5-6"), None);
+        assert_eq!(ByteRange::parse("bytes=5-1"), None);
+        assert_eq!(ByteRange::parse("bytes=-"), None);
+        assert_eq!(ByteRange::parse("items=0-1"), None);
+    }
+
+    #[test]
+    fn resolve_range() {
+        assert_eq!(
+            ByteRange::FromTo(0, Some(499)).resolve(1000),
+            Some((0, 499))
+        );
+        assert_eq!(ByteRange::FromTo(500, None).resolve(1000), Some((500, 999)));
+        assert_eq!(
+            ByteRange::FromTo(500, Some(5000)).resolve(1000),
+            Some((500, 999))
+        );
+        assert_eq!(ByteRange::Suffix(100).resolve(1000), Some((900, 999)));
+        assert_eq!(ByteRange::Suffix(5000).resolve(1000), Some((0, 999)));
+
+        assert_eq!(ByteRange::FromTo(1000, None).resolve(1000), None);
+        assert_eq!(ByteRange::Suffix(0).resolve(1000), None);
+        assert_eq!(ByteRange::Suffix(10).resolve(0), None);
+    }
+
+    #[test]
+    fn slice_chunks() {
+        // Chunk "efgh" at offset 4
+        assert_eq!(slice_chunk(b"efgh".to_vec(), 4, 5, 6), b"fg");
+        assert_eq!(slice_chunk(b"efgh".to_vec(), 4, 0, 9), b"efgh");
+        assert_eq!(slice_chunk(b"efgh".to_vec(), 4, 2, 4), b"e");
+        assert_eq!(slice_chunk(b"efgh".to_vec(), 4, 7, 9), b"h");
+    }
+
+    #[test]
+    fn set_chunk_index() {
+        let field = |name: &str, val| IDLField {
+            id: Label::Named(name.to_string()),
+            val,
+        };
+
+        let token = Token(IDLValue::Record(vec![
+            field("key", IDLValue::Text("/video.mp4".into())),
+            field("index", IDLValue::Nat(Nat::from(0u64))),
+        ]));
+
+        let token = chunk_token(&token, 5).unwrap();
+        assert_eq!(
+            token.0,
+            IDLValue::Record(vec![
+                field("key", IDLValue::Text("/video.mp4".into())),
+                field("index", IDLValue::Nat(Nat::from(5u64))),
+            ])
+        );
+
+        assert!(chunk_token(&Token(IDLValue::Null), 5).is_none());
+    }
+
+    #[test]
+    fn partial_content() {
+        let range = ByteRange::FromTo(1, Some(2));
+        let out = create_range_response(&agent(), response(b"abcd"), None, certification(), range)
+            .unwrap();
+
+        assert_eq!(out.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(out.headers()[CONTENT_RANGE], "bytes 1-2/10");
+        assert_eq!(out.headers()[CONTENT_LENGTH], "2");
+        assert_eq!(out.headers()["content-type"], "video/mp4");
+        assert!(!out.headers().contains_key(IC_CERTIFICATE_HEADER_NAME));
+
+        let body = aw!(hyper::body::to_bytes(out.into_body())).unwrap();
+        assert_eq!(&body[..], b"bc");
+    }
+
+    #[test]
+    fn partial_content_of_tampered_chunk() {
+        let range = ByteRange::FromTo(0, Some(2));
+        let out = create_range_response(&agent(), response(b"abcX"), None, certification(), range)
+            .unwrap();
+
+        assert!(aw!(hyper::body::to_bytes(out.into_body())).is_err());
+    }
+
+    #[test]
+    fn range_not_satisfiable() {
+        let range = ByteRange::FromTo(10, None);
+        let out = create_range_response(&agent(), response(b"abcd"), None, certification(), range)
+            .unwrap();
+
+        assert_eq!(out.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(out.headers()[CONTENT_RANGE], "bytes */10");
+    }
+}
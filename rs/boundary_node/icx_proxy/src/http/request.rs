@@ -1,8 +1,9 @@
 use crate::error::ErrorFactory;
 use crate::http::body::read_streaming_body;
 use crate::http::headers::REQUIRE_CERTIFICATION_HEADER_NAME;
+use crate::http::range::ByteRange;
 use crate::proxy::REQUEST_BODY_SIZE_LIMIT;
-use hyper::http::request::Parts;
+use hyper::http::{header::RANGE, request::Parts};
 use hyper::{Body, Uri};
 use ic_response_verification::types::Request;
 use tracing::trace;
@@ -57,6 +58,18 @@ impl HttpRequest {
         false
     }
 
+    /// Returns the byte range requested by the `Range` header, only `GET` requests are considered.
+    pub fn range(&self) -> Option<ByteRange> {
+        if self.method != "GET" {
+            return None;
+        }
+
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(RANGE.as_str()))
+            .and_then(|(_, value)| ByteRange::parse(value))
+    }
+
     /// Reads the body stream enforcing the request body size limit.
     pub async fn read_body(body: Body) -> Result<Vec<u8>, ErrorFactory> {
         read_streaming_body(body, REQUEST_BODY_SIZE_LIMIT).await
@@ -66,6 +79,7 @@ impl HttpRequest {
 #[cfg(test)]
 mod tests {
     use crate::http::headers::REQUIRE_CERTIFICATION_HEADER_NAME;
+    use crate::http::range::ByteRange;
     use crate::http::request::HttpRequest;
     use hyper::Uri;
 
@@ -96,4 +110,19 @@ mod tests {
 
         assert!(!request.is_certification_required());
     }
+
+    #[test]
+    fn range_of_get_requests() {
+        let mut request = HttpRequest {
+            uri: Uri::from_static("http://localhost"),
+            headers: vec![("Range".to_string(), "bytes=0-99".to_string())],
+            method: "GET".to_string(),
+            body: Vec::new(),
+        };
+
+        assert_eq!(request.range(), Some(ByteRange::FromTo(0, Some(99))));
+
+        request.method = "POST".to_string();
+        assert_eq!(request.range(), None);
+    }
 }
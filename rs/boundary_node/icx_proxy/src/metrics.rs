@@ -1,4 +1,4 @@
-use std::{borrow::Cow, net::SocketAddr, pin::Pin, time::Instant};
+use std::{net::SocketAddr, pin::Pin, time::Instant};
 
 use anyhow::{Context, Error};
 
@@ -32,7 +32,10 @@ use prometheus::{Encoder as PrometheusEncoder, Registry, TextEncoder};
 
 use crate::http::request::HttpRequest;
 use crate::http::response::HttpResponse;
-use crate::{logging::add_trace_layer, validate::Validate};
+use crate::{
+    logging::add_trace_layer,
+    validate::{ChunkCertification, Validate, ValidationError, ValidationInfo},
+};
 
 /// The options for metrics
 #[derive(Args)]
@@ -76,7 +79,7 @@ impl<T: Validate> Validate for WithMetrics<T> {
         canister_id: &Principal,
         request: &HttpRequest,
         response: &HttpResponse,
-    ) -> Result<(), Cow<'static, str>> {
+    ) -> Result<ValidationInfo, ValidationError> {
         let out = self.0.validate(agent, canister_id, request, response);
 
         let mut status = if out.is_ok() { "ok" } else { "fail" };
@@ -84,7 +87,49 @@ impl<T: Validate> Validate for WithMetrics<T> {
             status = "skip";
         }
 
-        let labels = &[KeyValue::new("status", status)];
+        let error = out.as_ref().err().map_or("none", ValidationError::short);
+        let version = match &out {
+            Ok(ValidationInfo {
+                version: Some(v), ..
+            }) => v.to_string(),
+            _ => "none".to_string(),
+        };
+
+        let labels = &[
+            KeyValue::new("status", status),
+            KeyValue::new("error", error),
+            KeyValue::new("version", version),
+        ];
+
+        let MetricParams { counter } = &self.1;
+        counter.add(1, labels);
+
+        out
+    }
+
+    fn validate_chunks(
+        &self,
+        agent: &Agent,
+        canister_id: &Principal,
+        request: &HttpRequest,
+        response: &HttpResponse,
+    ) -> Result<Option<ChunkCertification>, ValidationError> {
+        let out = self
+            .0
+            .validate_chunks(agent, canister_id, request, response);
+
+        let status = match &out {
+            Ok(Some(_)) => "ok",
+            Ok(None) => "skip",
+            Err(_) => "fail",
+        };
+        let error = out.as_ref().err().map_or("none", ValidationError::short);
+
+        let labels = &[
+            KeyValue::new("status", status),
+            KeyValue::new("error", error),
+            KeyValue::new("version", "chunks"),
+        ];
 
         let MetricParams { counter } = &self.1;
         counter.add(1, labels);
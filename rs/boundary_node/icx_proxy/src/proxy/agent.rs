@@ -30,13 +30,14 @@ use tracing::{enabled, error, info, instrument, trace, warn, Level};
 
 use crate::error::ErrorFactory;
 use crate::http;
+use crate::http::range::create_range_response;
 use crate::http::request::HttpRequest;
 use crate::http::response::{AgentResponseAny, HttpResponse};
 use crate::metrics::RequestContext;
 use crate::{
     canister_id,
     proxy::{AppState, HandleError, HyperService, REQUEST_BODY_SIZE_LIMIT},
-    validate::Validate,
+    validate::{verify_chunks, Validate, ValidationInfo},
 };
 
 // The maximum length of a body we should log as tracing.
@@ -330,26 +331,101 @@ async fn process_request_inner(
         agent_response
     };
 
-    let http_response = HttpResponse::from((agent, agent_response));
-    let mut response_builder =
-        Response::builder().status(StatusCode::from_u16(http_response.status_code)?);
-    for (name, value) in &http_response.headers {
-        response_builder = response_builder.header(name, value);
+    // Ranges are only served for assets whose chunks are certified, the callback is needed to fetch them
+    let range = http_request
+        .range()
+        .filter(|_| !is_update_call && agent_response.status_code == StatusCode::OK.as_u16());
+    let streaming_strategy = range
+        .as_ref()
+        .and_then(|_| agent_response.streaming_strategy.clone());
+
+    let mut http_response = HttpResponse::from((agent, agent_response));
+
+    if let Some(range) = range {
+        match validator.validate_chunks(agent, &canister_id, &http_request, &http_response) {
+            // The asset is not chunk-certified, fall back to the full response
+            Ok(None) => {}
+            Ok(Some(certification)) => {
+                let mut response = create_range_response(
+                    agent,
+                    http_response,
+                    streaming_strategy,
+                    certification,
+                    range,
+                )?;
+
+                response.extensions_mut().insert(RequestContext {
+                    request_size: http_request.body.len() as u64,
+                    streaming_request: true,
+                });
+
+                info!(
+                    ">> {:?} {} {} range",
+                    &response.version(),
+                    response.status().as_u16(),
+                    response.status().to_string(),
+                );
+
+                return Ok(response);
+            }
+            Err(e) => {
+                return Ok(Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(e.to_string().into())
+                    .unwrap());
+            }
+        }
     }
 
-    // At the moment verification is only performed if the response is not using a streaming
-    // strategy. Performing verification for those requests would required to join all the chunks
-    // and this could cause memory issues and possibly create DOS attack vectors.
-    let should_validate = !http_response.has_streaming_body && !is_update_call;
-    if should_validate {
-        let validation = validator.validate(agent, &canister_id, &http_request, &http_response);
-
-        if validation.is_err() {
-            return Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(validation.unwrap_err().into())
-                .unwrap());
+    // Bodies of responses using a streaming strategy are not joined, as this could cause memory
+    // issues and possibly create DOS attack vectors. If the canister certified the chunks of the
+    // body, every chunk is verified before it's passed on, otherwise the body isn't verified.
+    let should_validate = !is_update_call;
+    if let Some(body) = http_response.streaming_body.take() {
+        let certification = if should_validate {
+            match validator.validate_chunks(agent, &canister_id, &http_request, &http_response) {
+                Ok(v) => v,
+                Err(e) => {
+                    return Ok(Response::builder()
+                        .status(StatusCode::INTERNAL_SERVER_ERROR)
+                        .body(e.to_string().into())
+                        .unwrap());
+                }
+            }
+        } else {
+            None
+        };
+
+        http_response.streaming_body = Some(match certification {
+            Some(certification) => verify_chunks(body, certification),
+            None => body,
+        });
+    }
+
+    let validation_info = if should_validate && !http_response.has_streaming_body {
+        match validator.validate(agent, &canister_id, &http_request, &http_response) {
+            Ok(v) => v,
+            Err(e) => {
+                return Ok(Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(e.to_string().into())
+                    .unwrap());
+            }
         }
+    } else {
+        ValidationInfo::default()
+    };
+
+    // With certification v2 only the certified headers are passed on
+    let headers = validation_info
+        .certified_headers
+        .as_ref()
+        .unwrap_or(&http_response.headers);
+
+    let mut response_builder =
+        Response::builder().status(StatusCode::from_u16(http_response.status_code)?);
+    for (name, value) in headers {
+        response_builder = response_builder.header(name, value);
     }
 
     let mut response = response_builder
@@ -2,12 +2,51 @@ use crate::http::request::HttpRequest;
 use crate::http::response::HttpResponse;
 use candid::Principal;
 use ic_agent::Agent;
-use ic_response_verification::{verify_request_response_pair, MIN_VERIFICATION_VERSION};
-use std::borrow::Cow;
+use ic_response_verification::{
+    types::VerificationInfo, verify_request_response_pair, ResponseVerificationError,
+    MIN_VERIFICATION_VERSION,
+};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+mod chunks;
+
+pub use chunks::{verify_chunks, ChunkCertification};
+
 const MAX_CERT_TIME_OFFSET_NS: u128 = 300_000_000_000;
 
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationError {
+    #[error("Response is not certified")]
+    Uncertified,
+
+    #[error("Certificate is invalid: {0}")]
+    CertificateInvalid(String),
+
+    #[error("Certification expression does not match the response: {0}")]
+    ExpressionMismatch(String),
+}
+
+impl ValidationError {
+    /// Short name of the error, suitable as a metric label.
+    pub fn short(&self) -> &'static str {
+        match self {
+            Self::Uncertified => "uncertified",
+            Self::CertificateInvalid(_) => "certificate_invalid",
+            Self::ExpressionMismatch(_) => "expression_mismatch",
+        }
+    }
+}
+
+/// Outcome of a successful validation.
+#[derive(Debug, Default, PartialEq)]
+pub struct ValidationInfo {
+    /// Version of the response verification, `None` if the response was not verified
+    pub version: Option<u16>,
+    /// Headers certified by a v2 certification expression, these are the only ones
+    /// that should be passed on to the client. `None` if all headers can be passed on.
+    pub certified_headers: Option<Vec<(String, String)>>,
+}
+
 pub trait Validate: Sync + Send {
     fn validate(
         &self,
@@ -15,7 +54,17 @@ pub trait Validate: Sync + Send {
         canister_id: &Principal,
         request: &HttpRequest,
         response: &HttpResponse,
-    ) -> Result<(), Cow<'static, str>>;
+    ) -> Result<ValidationInfo, ValidationError>;
+
+    /// Returns the certified chunks of a streamed body, which are verified while it's being
+    /// streamed. `None` if the body is not chunk-certified.
+    fn validate_chunks(
+        &self,
+        agent: &Agent,
+        canister_id: &Principal,
+        request: &HttpRequest,
+        response: &HttpResponse,
+    ) -> Result<Option<ChunkCertification>, ValidationError>;
 }
 
 #[derive(Clone)]
@@ -34,9 +83,9 @@ impl Validate for Validator {
         canister_id: &Principal,
         request: &HttpRequest,
         response: &HttpResponse,
-    ) -> Result<(), Cow<'static, str>> {
+    ) -> Result<ValidationInfo, ValidationError> {
         if cfg!(feature = "skip_body_verification") {
-            return Ok(());
+            return Ok(ValidationInfo::default());
         }
 
         match (
@@ -46,21 +95,61 @@ impl Validate for Validator {
             // TODO: Remove this (FOLLOW-483)
             // Canisters don't have to provide certified variables
             // This should change in the future, grandfathering in current implementations
-            (false, false) => Ok(()),
-            (_, _) => {
-                let ic_public_key = agent.read_root_key();
-                verify_request_response_pair(
-                    request.into(),
-                    response.into(),
-                    canister_id.as_slice(),
-                    get_current_time_in_ns(),
-                    MAX_CERT_TIME_OFFSET_NS,
-                    ic_public_key.as_slice(),
-                    MIN_VERIFICATION_VERSION,
-                )
-                .map_err(|_| "Body does not pass verification")?;
-                Ok(())
+            (false, false) => return Ok(ValidationInfo::default()),
+            (true, false) => return Err(ValidationError::Uncertified),
+            (_, true) => {}
+        };
+
+        let ic_public_key = agent.read_root_key();
+        let info = verify_request_response_pair(
+            request.into(),
+            response.into(),
+            canister_id.as_slice(),
+            get_current_time_in_ns(),
+            MAX_CERT_TIME_OFFSET_NS,
+            ic_public_key.as_slice(),
+            MIN_VERIFICATION_VERSION,
+        )?;
+
+        Ok(ValidationInfo::from(info))
+    }
+
+    fn validate_chunks(
+        &self,
+        agent: &Agent,
+        canister_id: &Principal,
+        request: &HttpRequest,
+        response: &HttpResponse,
+    ) -> Result<Option<ChunkCertification>, ValidationError> {
+        if cfg!(feature = "skip_body_verification") {
+            return Ok(None);
+        }
+
+        chunks::validate(agent, canister_id, request, response)
+    }
+}
+
+impl From<VerificationInfo> for ValidationInfo {
+    fn from(info: VerificationInfo) -> Self {
+        Self {
+            version: Some(info.verification_version),
+            // Only v2 certification returns the certified response
+            certified_headers: info.response.map(|response| response.headers),
+        }
+    }
+}
+
+impl From<ResponseVerificationError> for ValidationError {
+    fn from(err: ResponseVerificationError) -> Self {
+        use ResponseVerificationError::{CelError, InvalidExpressionPath, InvalidResponseHashes};
+
+        match err {
+            // The certificate itself is fine, but the certification expression it covers
+            // is malformed or doesn't match the headers and body of the response
+            CelError(_) | InvalidExpressionPath | InvalidResponseHashes => {
+                Self::ExpressionMismatch(err.to_string())
             }
+            _ => Self::CertificateInvalid(err.to_string()),
         }
     }
 }
@@ -76,6 +165,7 @@ fn get_current_time_in_ns() -> u128 {
 
 #[cfg(test)]
 mod tests {
+    use crate::http::headers::REQUIRE_CERTIFICATION_HEADER_NAME;
     use crate::http::request::HttpRequest;
     use crate::http::response::HttpResponse;
     use candid::Principal;
@@ -86,8 +176,9 @@ mod tests {
         },
         Agent,
     };
+    use ic_response_verification::ResponseVerificationError;
 
-    use crate::validate::{Validate, Validator};
+    use crate::validate::{Validate, ValidationError, ValidationInfo, Validator};
 
     #[test]
     fn validate_nop() {
@@ -115,6 +206,55 @@ mod tests {
             },
         );
 
-        assert_eq!(out, Ok(()));
+        assert_eq!(out, Ok(ValidationInfo::default()));
+    }
+
+    #[test]
+    fn validate_uncertified() {
+        let canister_id = Principal::from_text("wwc2m-2qaaa-aaaac-qaaaa-cai").unwrap();
+        let uri = Uri::from_static("http://www.example.com");
+        let transport = HyperReplicaV2Transport::<Body>::create(uri.clone()).unwrap();
+        let agent = Agent::builder().with_transport(transport).build().unwrap();
+        let validator = Validator::new();
+
+        let mut request = HttpRequest {
+            uri,
+            method: String::from("GET"),
+            body: Vec::new(),
+            headers: vec![(
+                REQUIRE_CERTIFICATION_HEADER_NAME.to_string(),
+                "true".to_string(),
+            )],
+        };
+        let mut response = HttpResponse {
+            status_code: 200,
+            headers: Vec::new(),
+            streaming_body: None,
+            has_streaming_body: false,
+            body: Vec::new(),
+        };
+
+        let out = validator.validate(&agent, &canister_id, &request, &response);
+        assert_eq!(out, Err(ValidationError::Uncertified));
+
+        request.headers.clear();
+        response.headers = vec![("IC-Certificate".to_string(), "garbage".to_string())];
+
+        let out = validator.validate(&agent, &canister_id, &request, &response);
+        assert!(matches!(out, Err(ValidationError::CertificateInvalid(_))));
+    }
+
+    #[test]
+    fn expression_errors_are_not_certificate_errors() {
+        let err = ValidationError::from(ResponseVerificationError::InvalidResponseHashes);
+        assert!(matches!(err, ValidationError::ExpressionMismatch(_)));
+        assert_eq!(err.short(), "expression_mismatch");
+
+        let err = ValidationError::from(ResponseVerificationError::InvalidExpressionPath);
+        assert!(matches!(err, ValidationError::ExpressionMismatch(_)));
+
+        let err = ValidationError::from(ResponseVerificationError::CertificateVerificationFailed);
+        assert!(matches!(err, ValidationError::CertificateInvalid(_)));
+        assert_eq!(err.short(), "certificate_invalid");
     }
 }
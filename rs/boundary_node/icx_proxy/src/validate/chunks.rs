@@ -0,0 +1,388 @@
+//! Verification of response bodies that are streamed using the streaming callback.
+//!
+//! The body of such responses is never held in memory as a whole, so it can't be passed to
+//! the library. Canisters can instead certify every chunk of the body, next to the response
+//! itself, in the following subtree:
+//!
+//! ```text
+//! http_chunks/<url path>/chunk_size -> leb128(size of all chunks but the last)
+//! http_chunks/<url path>/length     -> leb128(length of the body)
+//! http_chunks/<url path>/chunks/<decimal chunk index> -> sha256(chunk)
+//! ```
+//!
+//! The chunk with index 0 is the body of the response to `http_request`, the following ones are
+//! returned by the streaming callback. Each chunk is checked against its hash before it's passed
+//! on to the client, a chunk that doesn't match terminates the stream instead. Only the chunks
+//! are certified by this subtree, the headers are passed on as with v1 certification.
+
+use candid::Principal;
+use futures::stream;
+use hyper::{body::HttpBody, Body};
+use ic_agent::{
+    hash_tree::{HashTree, Label, LookupResult},
+    lookup_value, Agent, Certificate,
+};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::http::headers::IC_CERTIFICATE_HEADER_NAME;
+use crate::http::request::HttpRequest;
+use crate::http::response::HttpResponse;
+use crate::validate::{get_current_time_in_ns, ValidationError, MAX_CERT_TIME_OFFSET_NS};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const CHUNKS_LABEL: &str = "http_chunks";
+
+/// Certified chunks of a streamed body.
+#[derive(Debug, PartialEq)]
+pub struct ChunkCertification {
+    chunk_size: u64,
+    length: u64,
+    hashes: Vec<Vec<u8>>,
+}
+
+impl ChunkCertification {
+    pub fn new(chunk_size: u64, length: u64, hashes: Vec<Vec<u8>>) -> Self {
+        Self {
+            chunk_size,
+            length,
+            hashes,
+        }
+    }
+
+    /// Length of the whole body.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn num_chunks(&self) -> u64 {
+        self.hashes.len() as u64
+    }
+
+    /// Returns the indices of the chunks covering the given inclusive byte range.
+    pub fn chunks_for(&self, start: u64, end: u64) -> std::ops::RangeInclusive<u64> {
+        start / self.chunk_size..=end / self.chunk_size
+    }
+
+    /// Returns the offset of the first byte of the chunk.
+    pub fn chunk_offset(&self, index: u64) -> u64 {
+        index * self.chunk_size
+    }
+
+    pub fn verify_chunk(&self, index: u64, data: &[u8]) -> Result<(), ValidationError> {
+        use ValidationError::CertificateInvalid;
+
+        let hash = self
+            .hashes
+            .get(index as usize)
+            .ok_or_else(|| CertificateInvalid(format!("chunk {index} is not certified")))?;
+
+        let expected_len = self
+            .chunk_size
+            .min(self.length.saturating_sub(self.chunk_offset(index)));
+        if data.len() as u64 != expected_len {
+            return Err(CertificateInvalid(format!(
+                "chunk {index} has length {}, expected {expected_len}",
+                data.len()
+            )));
+        }
+
+        if hash.as_slice() != Sha256::digest(data).as_slice() {
+            return Err(CertificateInvalid(format!(
+                "chunk {index} does not match its certified hash"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Wraps the body so that every chunk is verified before it's passed on.
+/// Only a single chunk is held in memory at any time.
+pub fn verify_chunks(body: Body, certification: ChunkCertification) -> Body {
+    let stream = stream::unfold(Some((body, certification, 0)), |state| async move {
+        let (mut body, certification, index) = state?;
+
+        let result = match body.data().await {
+            Some(Ok(chunk)) => match certification.verify_chunk(index, &chunk) {
+                Ok(()) => return Some((Ok(chunk), Some((body, certification, index + 1)))),
+                Err(e) => e,
+            },
+            Some(Err(e)) => return Some((Err(BoxError::from(e)), None)),
+            None if index == certification.num_chunks() => return None,
+            None => ValidationError::CertificateInvalid(format!(
+                "stream ended after {index} of {} chunks",
+                certification.num_chunks()
+            )),
+        };
+
+        warn!("Streamed body failed verification: {result}");
+        Some((Err(BoxError::from(result)), None))
+    });
+
+    Body::wrap_stream(stream)
+}
+
+/// Returns the certified chunks of the streamed body, `None` if it's not chunk-certified.
+pub fn validate(
+    agent: &Agent,
+    canister_id: &Principal,
+    request: &HttpRequest,
+    response: &HttpResponse,
+) -> Result<Option<ChunkCertification>, ValidationError> {
+    use ValidationError::CertificateInvalid;
+
+    let header = match response
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(IC_CERTIFICATE_HEADER_NAME))
+    {
+        Some((_, value)) => value,
+        None => return Ok(None),
+    };
+
+    let (certificate, tree) = parse_certificate_header(header)
+        .ok_or_else(|| CertificateInvalid("malformed certificate header".into()))?;
+
+    let tree: HashTree<Vec<u8>> = serde_cbor::from_slice(&tree)
+        .map_err(|e| CertificateInvalid(format!("unable to decode tree: {e}")))?;
+
+    let path = |labels: &[&[u8]]| -> Vec<Label<Vec<u8>>> {
+        [CHUNKS_LABEL.as_bytes(), request.uri.path().as_bytes()]
+            .iter()
+            .chain(labels)
+            .map(|label| Label::from(label.to_vec()))
+            .collect()
+    };
+
+    let lookup_number = |label: &str| match tree.lookup_path(&path(&[label.as_bytes()])) {
+        LookupResult::Found(v) => decode_leb128(v)
+            .and_then(|x| u64::try_from(x).ok())
+            .ok_or_else(|| CertificateInvalid(format!("malformed {label}"))),
+        _ => Err(CertificateInvalid(format!("missing {label}"))),
+    };
+
+    let length = match tree.lookup_path(&path(&[b"length"])) {
+        LookupResult::Absent => return Ok(None),
+        _ => lookup_number("length")?,
+    };
+    let chunk_size = lookup_number("chunk_size")?;
+    if chunk_size == 0 {
+        return Err(CertificateInvalid("chunk size must be positive".into()));
+    }
+
+    verify_certificate(agent, canister_id, &certificate, &tree)?;
+
+    let hashes = (0..((length + chunk_size - 1) / chunk_size).max(1))
+        .map(
+            |index| match tree.lookup_path(&path(&[b"chunks", index.to_string().as_bytes()])) {
+                LookupResult::Found(v) => Ok(v.to_vec()),
+                _ => Err(CertificateInvalid(format!("missing hash of chunk {index}"))),
+            },
+        )
+        .collect::<Result<_, _>>()?;
+
+    Ok(Some(ChunkCertification::new(chunk_size, length, hashes)))
+}
+
+// Checks the validity of the certificate and that it certifies the tree of the canister.
+// For responses that aren't streamed this is done by the library.
+fn verify_certificate(
+    agent: &Agent,
+    canister_id: &Principal,
+    certificate: &[u8],
+    tree: &HashTree<Vec<u8>>,
+) -> Result<(), ValidationError> {
+    use ValidationError::CertificateInvalid;
+
+    let cert: Certificate = serde_cbor::from_slice(certificate)
+        .map_err(|e| CertificateInvalid(format!("unable to decode certificate: {e}")))?;
+
+    agent
+        .verify(&cert, *canister_id)
+        .map_err(|e| CertificateInvalid(e.to_string()))?;
+
+    let time = lookup_value(&cert, vec!["time".as_bytes()])
+        .ok()
+        .and_then(decode_leb128)
+        .ok_or_else(|| CertificateInvalid("unable to read certificate time".into()))?;
+
+    if time.abs_diff(get_current_time_in_ns()) > MAX_CERT_TIME_OFFSET_NS {
+        return Err(CertificateInvalid(
+            "certificate time is out of bounds".into(),
+        ));
+    }
+
+    let witness = lookup_value(
+        &cert,
+        vec![
+            "canister".as_bytes(),
+            canister_id.as_slice(),
+            "certified_data".as_bytes(),
+        ],
+    )
+    .map_err(|_| CertificateInvalid("certificate is missing certified data".into()))?;
+
+    if tree.digest() != witness {
+        return Err(CertificateInvalid(
+            "tree does not match the certified data".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+// Returns the certificate and the tree of an `IC-Certificate` header, e.g.
+// `certificate=:<base64>:, tree=:<base64>:, version=2, expr_path=:<base64>:`
+fn parse_certificate_header(header: &str) -> Option<(Vec<u8>, Vec<u8>)> {
+    let mut certificate = None;
+    let mut tree = None;
+
+    for field in header.split(',') {
+        let (name, value) = field.trim().split_once('=')?;
+        let value = || base64::decode(value.trim_matches(':')).ok();
+        match name {
+            "certificate" => certificate = value(),
+            "tree" => tree = value(),
+            _ => {}
+        }
+    }
+
+    Some((certificate?, tree?))
+}
+
+fn decode_leb128(data: &[u8]) -> Option<u128> {
+    let mut out: u128 = 0;
+    for (i, b) in data.iter().enumerate() {
+        out |= ((b & 0x7f) as u128).checked_shl(7 * i as u32)?;
+        if b & 0x80 == 0 {
+            return Some(out);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::RESPONSE_BODY_SIZE_LIMIT;
+    use futures::StreamExt;
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    // Reads the body without keeping it, returns the number of bytes and whether it completed
+    async fn drain(mut body: Body) -> (usize, bool) {
+        let mut len = 0;
+        while let Some(chunk) = body.data().await {
+            match chunk {
+                Ok(v) => len += v.len(),
+                Err(_) => return (len, false),
+            }
+        }
+
+        (len, true)
+    }
+
+    fn chunked(chunks: impl Iterator<Item = Vec<u8>> + Send + 'static) -> Body {
+        Body::wrap_stream(stream::iter(chunks.map(Ok::<_, BoxError>)))
+    }
+
+    fn certification(chunks: &[Vec<u8>]) -> ChunkCertification {
+        ChunkCertification {
+            chunk_size: chunks[0].len() as u64,
+            length: chunks.iter().map(|x| x.len() as u64).sum(),
+            hashes: chunks.iter().map(|x| Sha256::digest(x).to_vec()).collect(),
+        }
+    }
+
+    #[test]
+    fn verify_chunk() {
+        let c = certification(&[b"abcd".to_vec(), b"efgh".to_vec(), b"ij".to_vec()]);
+
+        assert!(c.verify_chunk(0, b"abcd").is_ok());
+        assert!(c.verify_chunk(2, b"ij").is_ok());
+
+        assert!(c.verify_chunk(1, b"efgX").is_err());
+        assert!(c.verify_chunk(2, b"ijk").is_err());
+        assert!(c.verify_chunk(3, b"").is_err());
+    }
+
+    #[test]
+    fn chunks_for_range() {
+        let c = certification(&[b"abcd".to_vec(), b"efgh".to_vec(), b"ij".to_vec()]);
+
+        assert_eq!(c.chunks_for(0, 3), 0..=0);
+        assert_eq!(c.chunks_for(3, 4), 0..=1);
+        assert_eq!(c.chunks_for(5, 9), 1..=2);
+        assert_eq!(c.chunk_offset(2), 8);
+    }
+
+    #[test]
+    fn stream_body_larger_than_limit() {
+        const CHUNK_SIZE: usize = 2 * 1024 * 1024;
+        const NUM_CHUNKS: usize = 6 * RESPONSE_BODY_SIZE_LIMIT / CHUNK_SIZE;
+
+        // The chunks are generated while being streamed, the body never exists as a whole
+        let chunk = |i: usize| vec![i as u8; CHUNK_SIZE];
+        let c = ChunkCertification {
+            chunk_size: CHUNK_SIZE as u64,
+            length: (NUM_CHUNKS * CHUNK_SIZE) as u64,
+            hashes: (0..NUM_CHUNKS)
+                .map(|i| Sha256::digest(chunk(i)).to_vec())
+                .collect(),
+        };
+
+        let body = verify_chunks(chunked((0..NUM_CHUNKS).map(chunk)), c);
+        assert_eq!(aw!(drain(body)), (NUM_CHUNKS * CHUNK_SIZE, true));
+    }
+
+    #[test]
+    fn stream_stops_before_tampered_chunk() {
+        let chunks = vec![b"abcd".to_vec(), b"efgh".to_vec(), b"ij".to_vec()];
+        let c = certification(&chunks);
+
+        let tampered = vec![b"abcd".to_vec(), b"efgX".to_vec(), b"ij".to_vec()];
+        let body = verify_chunks(chunked(tampered.into_iter()), c);
+
+        // Only the first chunk is passed on
+        assert_eq!(aw!(drain(body)), (4, false));
+    }
+
+    #[test]
+    fn stream_fails_on_missing_chunks() {
+        let chunks = vec![b"abcd".to_vec(), b"efgh".to_vec(), b"ij".to_vec()];
+        let c = certification(&chunks);
+
+        let body = verify_chunks(chunked(chunks.into_iter().take(2)), c);
+        assert_eq!(aw!(drain(body)), (8, false));
+    }
+
+    #[test]
+    fn certificate_header() {
+        let header = format!(
+            "certificate=:{}:, tree=:{}:, version=2, expr_path=:{}:",
+            base64::encode(b"cert"),
+            base64::encode(b"tree"),
+            base64::encode(b"path"),
+        );
+
+        assert_eq!(
+            parse_certificate_header(&header),
+            Some((b"cert".to_vec(), b"tree".to_vec()))
+        );
+        assert_eq!(parse_certificate_header("version=2"), None);
+    }
+
+    #[test]
+    fn leb128() {
+        assert_eq!(decode_leb128(&[0x00]), Some(0));
+        assert_eq!(decode_leb128(&[0xe5, 0x8e, 0x26]), Some(624485));
+        assert_eq!(decode_leb128(&[0x80]), None);
+    }
+}
@@ -44,9 +44,15 @@ use tracing::info;
 mod metrics;
 use metrics::{MetricParams, WithMetrics};
 
+mod probes;
+use probes::{Caller, ProbeRunner};
+
 mod retry;
 use retry::WithRetry;
 
+mod slo;
+use slo::SloTracker;
+
 const SERVICE_NAME: &str = "prober";
 
 const MINUTE: Duration = Duration::from_secs(60);
@@ -80,6 +86,10 @@ struct Cli {
     #[clap(long, default_value = "1m")]
     probe_interval: humantime::Duration,
 
+    /// Definitions of probes of existing canisters and their SLOs
+    #[clap(long)]
+    probes_path: Option<PathBuf>,
+
     #[clap(long, default_value = "127.0.0.1:9090")]
     metrics_addr: SocketAddr,
 }
@@ -105,7 +115,9 @@ async fn main() -> Result<(), Error> {
     let provider = MeterProvider::builder().with_reader(exporter).build();
     let meter = provider.meter(SERVICE_NAME);
 
-    let metrics_handler = metrics_handler.layer(Extension(MetricsHandlerArgs { registry }));
+    let metrics_handler = metrics_handler.layer(Extension(MetricsHandlerArgs {
+        registry: registry.clone(),
+    }));
     let metrics_router = Router::new().route("/metrics", get(metrics_handler));
 
     let loader = RoutesLoader::new(cli.routes_dir.clone());
@@ -200,6 +212,45 @@ async fn main() -> Result<(), Error> {
         }));
     }
 
+    // Keeps the registration of the SLO gauges alive while the prober runs
+    let mut slo_metrics = None;
+    if let Some(probes_path) = &cli.probes_path {
+        let (slo_config, probes) = probes::load_config(probes_path)?;
+        let slo = Arc::new(SloTracker::new(slo_config));
+        slo_metrics = Some(slo::register_metrics(&meter, SERVICE_NAME, slo.clone())?);
+        let caller = Arc::new(Caller {});
+
+        for probe in probes {
+            for subnet_id in probe.definition.subnets.clone() {
+                let interval = probe.definition.interval;
+
+                let runner = ProbeRunner::new(
+                    loader.clone(),
+                    create_agent_fn(identity.clone(), root_key.clone()),
+                    caller.clone(),
+                    probe.clone(),
+                    slo.clone(),
+                );
+                let runner = WithMetrics(
+                    runner,
+                    MetricParams::new(&meter, SERVICE_NAME, "canister_probe"),
+                );
+                let mut runner = WithThrottle(runner, ThrottleParams::new(interval));
+
+                futs.push(task::spawn(async move {
+                    // Custom probes don't use a wallet
+                    let context = TestContext {
+                        wallet_id: String::new(),
+                        subnet_id,
+                    };
+                    loop {
+                        let _ = runner.run(&context).await;
+                    }
+                }));
+            }
+        }
+    }
+
     futs.push(task::spawn(
         axum::Server::bind(&cli.metrics_addr)
             .serve(metrics_router.into_make_service())
@@ -210,6 +261,8 @@ async fn main() -> Result<(), Error> {
         let _ = fut.await?;
     }
 
+    drop(slo_metrics);
+
     Ok(())
 }
 
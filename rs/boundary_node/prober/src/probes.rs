@@ -0,0 +1,499 @@
+use std::{collections::HashSet, fs::File, path::Path, str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Context, Error};
+use async_trait::async_trait;
+use candid::{IDLArgs, Principal};
+use ic_agent::Agent;
+use mockall::automock;
+use serde::{Deserialize, Deserializer};
+use tokio::time::{timeout, Instant};
+use tracing::info;
+
+use crate::{
+    slo::{SloConfig, SloTracker, ALL},
+    CreateAgentFn, Load, Run, TestContext,
+};
+
+pub fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    humantime::parse_duration(&s).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_principal<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Principal, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Principal::from_text(s).map_err(serde::de::Error::custom)
+}
+
+fn default_arg() -> String {
+    "()".to_string()
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CallType {
+    Query,
+    Update,
+}
+
+// Definition of a probe of an existing canister
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct ProbeDefinition {
+    pub name: String,
+    #[serde(deserialize_with = "deserialize_principal")]
+    pub canister_id: Principal,
+    pub method: String,
+    pub call_type: CallType,
+    // Argument in the candid textual representation
+    #[serde(default = "default_arg")]
+    pub arg: String,
+    // Reply in the candid textual representation, numbers need a type annotation, e.g. `(1 : nat64)`.
+    // Any reply is accepted if it's not set.
+    pub expected_reply: Option<String>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+    // Subnets whose nodes the probe is sent to
+    pub subnets: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct ProbesConfig {
+    #[serde(default)]
+    pub slo: SloConfig,
+    pub probes: Vec<ProbeDefinition>,
+}
+
+// Probe definition with the candid values parsed
+#[derive(Debug, Clone)]
+pub struct CanisterProbe {
+    pub definition: ProbeDefinition,
+    arg: Vec<u8>,
+    expected_reply: Option<IDLArgs>,
+}
+
+impl CanisterProbe {
+    pub fn new(definition: ProbeDefinition) -> Result<Self, Error> {
+        let arg = IDLArgs::from_str(&definition.arg)
+            .context("failed to parse arg")?
+            .to_bytes()
+            .context("failed to encode arg")?;
+
+        let expected_reply = definition
+            .expected_reply
+            .as_deref()
+            .map(IDLArgs::from_str)
+            .transpose()
+            .context("failed to parse expected reply")?;
+
+        Ok(Self {
+            definition,
+            arg,
+            expected_reply,
+        })
+    }
+
+    fn check_reply(&self, reply: &[u8]) -> Result<(), Error> {
+        let expected = match &self.expected_reply {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        let reply = IDLArgs::from_bytes(reply).context("failed to decode reply")?;
+        if reply.args != expected.args {
+            return Err(anyhow!("unexpected reply: {} != {}", reply, expected));
+        }
+
+        Ok(())
+    }
+}
+
+pub fn load_config(path: &Path) -> Result<(SloConfig, Vec<CanisterProbe>), Error> {
+    let f = File::open(path).with_context(|| format!("failed to open file {}", path.display()))?;
+    let config: ProbesConfig = serde_json::from_reader(f).context("failed to parse json")?;
+
+    config.slo.validate().context("invalid slo config")?;
+
+    let mut names = HashSet::new();
+    let probes = config
+        .probes
+        .into_iter()
+        .map(|p| {
+            if p.name == ALL || !names.insert(p.name.clone()) {
+                return Err(anyhow!("probe name '{}' is reserved or not unique", p.name));
+            }
+
+            if p.subnets.is_empty() || p.interval.is_zero() {
+                return Err(anyhow!(
+                    "probe '{}' requires subnets and a non-zero interval",
+                    p.name
+                ));
+            }
+
+            let name = p.name.clone();
+            CanisterProbe::new(p).with_context(|| format!("invalid probe '{name}'"))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok((config.slo, probes))
+}
+
+#[automock]
+#[async_trait]
+pub trait Call: 'static + Sync + Send {
+    async fn call(
+        &self,
+        agent: &Agent,
+        canister_id: Principal,
+        method: &str,
+        call_type: CallType,
+        arg: &[u8],
+    ) -> Result<Vec<u8>, Error>;
+}
+
+pub struct Caller {}
+
+#[async_trait]
+impl Call for Caller {
+    async fn call(
+        &self,
+        agent: &Agent,
+        canister_id: Principal,
+        method: &str,
+        call_type: CallType,
+        arg: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        match call_type {
+            CallType::Query => agent
+                .query(&canister_id, method)
+                .with_arg(arg)
+                .call()
+                .await
+                .context("failed to query canister"),
+
+            CallType::Update => agent
+                .update(&canister_id, method)
+                .with_arg(arg)
+                .call_and_wait()
+                .await
+                .context("failed to update canister"),
+        }
+    }
+}
+
+// Sends the probe to the nodes of the subnet given in the context, one node per run in a round-robin fashion.
+// The outcome is recorded by the SLO tracker.
+pub struct ProbeRunner<L, C> {
+    loader: Arc<L>,
+    create_agent: Box<dyn CreateAgentFn>,
+    caller: Arc<C>,
+    probe: CanisterProbe,
+    slo: Arc<SloTracker>,
+    next_node: usize,
+}
+
+impl<L, C> ProbeRunner<L, C> {
+    pub(crate) fn new(
+        loader: Arc<L>,
+        create_agent: impl CreateAgentFn,
+        caller: Arc<C>,
+        probe: CanisterProbe,
+        slo: Arc<SloTracker>,
+    ) -> Self {
+        Self {
+            loader,
+            create_agent: Box::new(create_agent),
+            caller,
+            probe,
+            slo,
+            next_node: 0,
+        }
+    }
+}
+
+#[async_trait]
+impl<L: Load, C: Call> Run for ProbeRunner<L, C> {
+    async fn run(&mut self, context: &TestContext) -> Result<(), Error> {
+        let routes = self.loader.load().await?;
+
+        let subnet = routes
+            .subnets
+            .iter()
+            .find(|subnet| subnet.subnet_id == context.subnet_id)
+            .ok_or_else(|| anyhow!("Subnet not found"))?;
+
+        if subnet.nodes.is_empty() {
+            return Err(anyhow!("Subnet has no nodes"));
+        }
+
+        let node = subnet.nodes[self.next_node % subnet.nodes.len()].clone();
+        self.next_node = self.next_node.wrapping_add(1);
+
+        let (node_id, _, agent) = (self.create_agent)(node).context("failed to create agent")?;
+
+        let ProbeDefinition {
+            name,
+            canister_id,
+            method,
+            call_type,
+            interval,
+            ..
+        } = &self.probe.definition;
+
+        let start_time = Instant::now();
+
+        // A probe that doesn't complete within its interval is considered failed
+        let out = timeout(
+            *interval,
+            self.caller
+                .call(&agent, *canister_id, method, *call_type, &self.probe.arg),
+        )
+        .await
+        .unwrap_or_else(|_| Err(anyhow!("probe timed out after {:?}", interval)))
+        .and_then(|reply| self.probe.check_reply(&reply));
+
+        let duration = start_time.elapsed();
+        self.slo
+            .record(&context.subnet_id, name, out.is_ok(), duration);
+
+        info!(
+            action = "canister_probe",
+            probe = name.as_str(),
+            subnet_id = context.subnet_id.as_str(),
+            node_id = node_id.as_str(),
+            canister = canister_id.to_string().as_str(),
+            status = if out.is_ok() { "ok" } else { "fail" },
+            duration = duration.as_secs_f64(),
+            error = ?out.as_ref().err(),
+        );
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    use candid::Encode;
+    use ic_agent::agent::http_transport::ReqwestHttpReplicaV2Transport;
+    use indoc::indoc;
+    use mockall::predicate;
+
+    use crate::{MockLoad, NodeRoute, Routes, SubnetRoute};
+
+    fn definition() -> ProbeDefinition {
+        ProbeDefinition {
+            name: String::from("balance"),
+            canister_id: Principal::from_text("rwlgt-iiaaa-aaaaa-aaaaa-cai").unwrap(),
+            method: String::from("balance"),
+            call_type: CallType::Query,
+            arg: String::from(r#"("foo")"#),
+            expected_reply: Some(String::from("(42 : nat64)")),
+            interval: Duration::from_secs(30),
+            subnets: vec![String::from("subnet-1")],
+        }
+    }
+
+    #[test]
+    fn it_loads_config() -> Result<(), Error> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        write!(
+            file,
+            "{}",
+            indoc! {r#"{
+                "slo": { "availability_target": 0.99, "windows": ["1h"] },
+                "probes": [{
+                    "name": "balance",
+                    "canister_id": "rwlgt-iiaaa-aaaaa-aaaaa-cai",
+                    "method": "balance",
+                    "call_type": "query",
+                    "arg": "(\"foo\")",
+                    "expected_reply": "(42 : nat64)",
+                    "interval": "30s",
+                    "subnets": ["subnet-1"]
+                }]
+            }"#}
+        )?;
+
+        let (slo, probes) = load_config(file.path())?;
+
+        assert_eq!(slo.availability_target, 0.99);
+        assert_eq!(slo.windows, vec![Duration::from_secs(3600)]);
+        assert_eq!(slo.latency_target, SloConfig::default().latency_target);
+
+        assert_eq!(probes.len(), 1);
+        assert_eq!(probes[0].definition, definition());
+        assert_eq!(probes[0].arg, Encode!(&"foo")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_checks_replies() -> Result<(), Error> {
+        let probe = CanisterProbe::new(definition())?;
+
+        assert!(probe.check_reply(&Encode!(&42u64)?).is_ok());
+        assert!(probe.check_reply(&Encode!(&43u64)?).is_err());
+        assert!(probe.check_reply(&Encode!(&42u32)?).is_err());
+        assert!(probe.check_reply(b"garbage").is_err());
+
+        // Any reply is accepted without an expectation
+        let probe = CanisterProbe::new(ProbeDefinition {
+            expected_reply: None,
+            ..definition()
+        })?;
+        assert!(probe.check_reply(&Encode!(&"anything")?).is_ok());
+
+        assert!(CanisterProbe::new(ProbeDefinition {
+            arg: String::from("(unbalanced"),
+            ..definition()
+        })
+        .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_runs_probes() -> Result<(), Error> {
+        let mut loader = MockLoad::new();
+        loader.expect_load().times(4).returning(|| {
+            Ok(Routes {
+                subnets: vec![SubnetRoute {
+                    subnet_id: String::from("subnet-1"),
+                    nodes: vec![
+                        NodeRoute {
+                            node_id: String::from("node-1"),
+                            socket_addr: String::from("socket-1"),
+                        },
+                        NodeRoute {
+                            node_id: String::from("node-2"),
+                            socket_addr: String::from("socket-2"),
+                        },
+                    ],
+                }],
+            })
+        });
+
+        let mut caller = MockCall::new();
+        let mut replies = vec![42u64, 42, 0, 42].into_iter();
+        caller
+            .expect_call()
+            .times(4)
+            .with(
+                predicate::always(), // agent
+                predicate::eq(Principal::from_text("rwlgt-iiaaa-aaaaa-aaaaa-cai")?),
+                predicate::eq("balance"),
+                predicate::eq(CallType::Query),
+                predicate::eq(Encode!(&"foo")?),
+            )
+            .returning(move |_, _, _, _, _| Ok(Encode!(&replies.next().unwrap())?));
+
+        // Nodes are probed in a round-robin fashion
+        let nodes = Arc::new(std::sync::Mutex::new(vec![]));
+        let create_agent = {
+            let nodes = nodes.clone();
+            move |route: NodeRoute| -> Result<(String, String, Agent), Error> {
+                nodes.lock().unwrap().push(route.node_id.clone());
+
+                let transport = ReqwestHttpReplicaV2Transport::create("http://test")
+                    .context("failed to create transport")?;
+                let agent = Agent::builder().with_transport(transport).build()?;
+
+                Ok((route.node_id, route.socket_addr, agent))
+            }
+        };
+
+        let slo = Arc::new(SloTracker::new(SloConfig::default()));
+
+        let mut runner = ProbeRunner::new(
+            Arc::new(loader),
+            create_agent,
+            Arc::new(caller),
+            CanisterProbe::new(definition())?,
+            slo.clone(),
+        );
+
+        let context = TestContext {
+            wallet_id: String::new(),
+            subnet_id: String::from("subnet-1"),
+        };
+
+        assert!(runner.run(&context).await.is_ok());
+        assert!(runner.run(&context).await.is_ok());
+        assert!(runner.run(&context).await.is_err());
+        assert!(runner.run(&context).await.is_ok());
+
+        assert_eq!(
+            *nodes.lock().unwrap(),
+            vec!["node-1", "node-2", "node-1", "node-2"]
+        );
+
+        Ok(())
+    }
+
+    struct HangingCaller;
+
+    #[async_trait]
+    impl Call for HangingCaller {
+        async fn call(
+            &self,
+            _agent: &Agent,
+            _canister_id: Principal,
+            _method: &str,
+            _call_type: CallType,
+            _arg: &[u8],
+        ) -> Result<Vec<u8>, Error> {
+            futures::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn it_fails_probes_that_time_out() -> Result<(), Error> {
+        let mut loader = MockLoad::new();
+        loader.expect_load().times(1).returning(|| {
+            Ok(Routes {
+                subnets: vec![SubnetRoute {
+                    subnet_id: String::from("subnet-1"),
+                    nodes: vec![NodeRoute {
+                        node_id: String::from("node-1"),
+                        socket_addr: String::from("socket-1"),
+                    }],
+                }],
+            })
+        });
+
+        let create_agent = |route: NodeRoute| -> Result<(String, String, Agent), Error> {
+            let transport = ReqwestHttpReplicaV2Transport::create("http://test")
+                .context("failed to create transport")?;
+            let agent = Agent::builder().with_transport(transport).build()?;
+
+            Ok((route.node_id, route.socket_addr, agent))
+        };
+
+        let mut runner = ProbeRunner::new(
+            Arc::new(loader),
+            create_agent,
+            Arc::new(HangingCaller),
+            CanisterProbe::new(ProbeDefinition {
+                interval: Duration::from_millis(10),
+                ..definition()
+            })?,
+            Arc::new(SloTracker::new(SloConfig::default())),
+        );
+
+        let context = TestContext {
+            wallet_id: String::new(),
+            subnet_id: String::from("subnet-1"),
+        };
+
+        let err = runner.run(&context).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+
+        Ok(())
+    }
+}
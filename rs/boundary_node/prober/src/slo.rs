@@ -0,0 +1,395 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Error;
+use opentelemetry::{
+    metrics::{CallbackRegistration, Meter},
+    KeyValue,
+};
+use serde::Deserialize;
+
+use crate::probes::deserialize_duration;
+
+// Label value used for the aggregations over all subnets or all probes
+pub const ALL: &str = "all";
+
+const LATENCY_QUANTILES: &[f64] = &[0.5, 0.9, 0.99];
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct SloConfig {
+    // Ratio of successful probes
+    pub availability_target: f64,
+    // Ratio of successful probes that complete within the latency threshold
+    pub latency_target: f64,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub latency_threshold: Duration,
+    // Rolling windows over which the SLOs are computed
+    #[serde(deserialize_with = "deserialize_durations")]
+    pub windows: Vec<Duration>,
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            availability_target: 0.999,
+            latency_target: 0.99,
+            latency_threshold: Duration::from_secs(1),
+            windows: vec![
+                Duration::from_secs(5 * 60),
+                Duration::from_secs(60 * 60),
+                Duration::from_secs(6 * 60 * 60),
+            ],
+        }
+    }
+}
+
+fn deserialize_durations<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Duration>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|x| humantime::parse_duration(x).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+impl SloConfig {
+    pub fn validate(&self) -> Result<(), Error> {
+        for target in [self.availability_target, self.latency_target] {
+            if !(0.0..1.0).contains(&target) {
+                return Err(anyhow::anyhow!("SLO targets must be within [0, 1)"));
+            }
+        }
+
+        if self.windows.is_empty() || self.windows.contains(&Duration::ZERO) {
+            return Err(anyhow::anyhow!(
+                "at least one non-zero SLO window is required"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn max_window(&self) -> Duration {
+        self.windows.iter().max().copied().unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Sample {
+    time: Instant,
+    ok: bool,
+    latency: Duration,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SloReport {
+    pub total: usize,
+    pub availability: f64,
+    // Rate at which the error budget is consumed, 1.0 means it's exhausted exactly at the end of the SLO period
+    pub availability_burn_rate: f64,
+    pub latency_burn_rate: f64,
+    // Latency quantiles of the successful probes
+    pub latency_quantiles: Vec<(f64, Duration)>,
+}
+
+fn quantile(sorted: &[Duration], q: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+
+    // Nearest-rank method
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// Tracks the probe results over rolling windows and computes the SLO burn rates
+// per subnet and probe, as well as aggregated over all probes of a subnet and over all subnets of a probe.
+pub struct SloTracker {
+    config: SloConfig,
+    // Samples keyed by subnet id and probe name
+    samples: Mutex<HashMap<(String, String), VecDeque<Sample>>>,
+}
+
+impl SloTracker {
+    pub fn new(config: SloConfig) -> Self {
+        Self {
+            config,
+            samples: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record(&self, subnet_id: &str, probe: &str, ok: bool, latency: Duration) {
+        self.record_at(Instant::now(), subnet_id, probe, ok, latency)
+    }
+
+    fn record_at(&self, time: Instant, subnet_id: &str, probe: &str, ok: bool, latency: Duration) {
+        let sample = Sample { time, ok, latency };
+        let mut samples = self.samples.lock().unwrap();
+
+        for (subnet_id, probe) in [(subnet_id, probe), (subnet_id, ALL), (ALL, probe)] {
+            let samples = samples
+                .entry((subnet_id.to_string(), probe.to_string()))
+                .or_default();
+
+            samples.push_back(sample);
+            self.drop_expired(samples, time);
+        }
+    }
+
+    // Drops the samples that are outside of all windows
+    fn drop_expired(&self, samples: &mut VecDeque<Sample>, now: Instant) {
+        let max_window = self.config.max_window();
+        while let Some(s) = samples.front() {
+            if now.saturating_duration_since(s.time) <= max_window {
+                break;
+            }
+            samples.pop_front();
+        }
+    }
+
+    // Returns the reports of all subnets and probes for all windows ending at the given time.
+    // Probes that stopped reporting thus age out of the windows instead of keeping their last values.
+    fn reports_at(&self, now: Instant) -> Vec<(String, String, Duration, SloReport)> {
+        let mut samples = self.samples.lock().unwrap();
+
+        let mut reports = vec![];
+        for ((subnet_id, probe), samples) in samples.iter_mut() {
+            self.drop_expired(samples, now);
+
+            for window in &self.config.windows {
+                reports.push((
+                    subnet_id.clone(),
+                    probe.clone(),
+                    *window,
+                    self.report(samples, now, *window),
+                ));
+            }
+        }
+
+        reports
+    }
+
+    fn report(&self, samples: &VecDeque<Sample>, now: Instant, window: Duration) -> SloReport {
+        let samples = samples
+            .iter()
+            .filter(|s| now.saturating_duration_since(s.time) <= window)
+            .collect::<Vec<_>>();
+
+        let total = samples.len();
+        let ok = samples.iter().filter(|s| s.ok).count();
+
+        let mut latencies = samples
+            .iter()
+            .filter(|s| s.ok)
+            .map(|s| s.latency)
+            .collect::<Vec<_>>();
+        latencies.sort();
+
+        let slow = latencies
+            .iter()
+            .filter(|x| **x > self.config.latency_threshold)
+            .count();
+
+        let ratio = |n: usize, d: usize| if d == 0 { 0.0 } else { n as f64 / d as f64 };
+
+        let availability = if total == 0 { 1.0 } else { ratio(ok, total) };
+
+        SloReport {
+            total,
+            availability,
+            availability_burn_rate: (1.0 - availability) / (1.0 - self.config.availability_target),
+            latency_burn_rate: ratio(slow, latencies.len()) / (1.0 - self.config.latency_target),
+            latency_quantiles: LATENCY_QUANTILES
+                .iter()
+                .map(|q| (*q, quantile(&latencies, *q)))
+                .collect(),
+        }
+    }
+}
+
+// Registers the SLO gauges with the meter. They are computed from the tracked samples
+// whenever the metrics are collected, so that they stay current when probes stop or hang.
+pub fn register_metrics(
+    meter: &Meter,
+    namespace: &str,
+    tracker: Arc<SloTracker>,
+) -> Result<Box<dyn CallbackRegistration>, Error> {
+    let samples = meter
+        .f64_observable_gauge(format!("{namespace}.slo.samples"))
+        .with_description("Number of probes within the window")
+        .init();
+    let availability = meter
+        .f64_observable_gauge(format!("{namespace}.slo.availability"))
+        .with_description("Ratio of successful probes within the window")
+        .init();
+    let availability_burn_rate = meter
+        .f64_observable_gauge(format!("{namespace}.slo.availability_burn_rate"))
+        .with_description(
+            "Rate at which the availability error budget is consumed within the window",
+        )
+        .init();
+    let latency_burn_rate = meter
+        .f64_observable_gauge(format!("{namespace}.slo.latency_burn_rate"))
+        .with_description("Rate at which the latency error budget is consumed within the window")
+        .init();
+    let latency = meter
+        .f64_observable_gauge(format!("{namespace}.slo.latency_sec"))
+        .with_description("Latency quantiles of the successful probes within the window")
+        .init();
+
+    let instruments = [
+        samples.as_any(),
+        availability.as_any(),
+        availability_burn_rate.as_any(),
+        latency_burn_rate.as_any(),
+        latency.as_any(),
+    ];
+
+    let registration = meter.register_callback(&instruments, move |observer| {
+        for (subnet_id, probe, window, report) in tracker.reports_at(Instant::now()) {
+            let labels = &[
+                KeyValue::new("subnet_id", subnet_id),
+                KeyValue::new("probe", probe),
+                KeyValue::new("window", humantime::format_duration(window).to_string()),
+            ];
+
+            observer.observe_f64(&samples, report.total as f64, labels);
+            observer.observe_f64(&availability, report.availability, labels);
+            observer.observe_f64(
+                &availability_burn_rate,
+                report.availability_burn_rate,
+                labels,
+            );
+            observer.observe_f64(&latency_burn_rate, report.latency_burn_rate, labels);
+
+            for (q, v) in &report.latency_quantiles {
+                let mut labels = labels.to_vec();
+                labels.push(KeyValue::new("quantile", q.to_string()));
+                observer.observe_f64(&latency, v.as_secs_f64(), &labels);
+            }
+        }
+    })?;
+
+    Ok(registration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    impl SloTracker {
+        // Returns the report for the given subnet and probe over the window ending at the given time
+        fn get_at(
+            &self,
+            now: Instant,
+            subnet_id: &str,
+            probe: &str,
+            window: Duration,
+        ) -> Option<SloReport> {
+            let samples = self.samples.lock().unwrap();
+            let samples = samples.get(&(subnet_id.to_string(), probe.to_string()))?;
+
+            Some(self.report(samples, now, window))
+        }
+    }
+
+    fn config() -> SloConfig {
+        SloConfig {
+            availability_target: 0.9,
+            latency_target: 0.5,
+            latency_threshold: 100 * MS,
+            windows: vec![Duration::from_secs(60), Duration::from_secs(600)],
+        }
+    }
+
+    #[test]
+    fn it_computes_quantiles() {
+        let sorted = (1..=100).map(|x| x * MS).collect::<Vec<_>>();
+
+        assert_eq!(quantile(&sorted, 0.5), 50 * MS);
+        assert_eq!(quantile(&sorted, 0.99), 99 * MS);
+        assert_eq!(quantile(&sorted, 1.0), 100 * MS);
+        assert_eq!(quantile(&[], 0.5), Duration::ZERO);
+    }
+
+    #[test]
+    fn it_computes_burn_rates() -> Result<(), Error> {
+        let tracker = SloTracker::new(config());
+        let now = Instant::now();
+        let minute = Duration::from_secs(60);
+
+        // 10% failures, half of the successful probes are slow
+        for i in 0..10 {
+            let latency = if i % 2 == 0 { 50 * MS } else { 200 * MS };
+            tracker.record_at(now, "subnet-1", "probe-1", i != 0, latency);
+        }
+
+        let report = tracker.get_at(now, "subnet-1", "probe-1", minute).unwrap();
+        assert_eq!(report.total, 10);
+        assert_eq!(report.availability, 0.9);
+        assert!((report.availability_burn_rate - 1.0).abs() < 1e-9);
+        // 5 out of 9 successful probes are slow
+        assert!((report.latency_burn_rate - 5.0 / 9.0 / 0.5).abs() < 1e-9);
+        assert_eq!(report.latency_quantiles[0], (0.5, 200 * MS));
+
+        // Aggregations
+        assert_eq!(
+            tracker.get_at(now, "subnet-1", ALL, minute).unwrap().total,
+            10
+        );
+        assert_eq!(
+            tracker.get_at(now, ALL, "probe-1", minute).unwrap().total,
+            10
+        );
+        assert!(tracker.get_at(now, "subnet-2", ALL, minute).is_none());
+
+        // Samples leave the shorter window first
+        let later = now + 2 * minute;
+        tracker.record_at(later, "subnet-1", "probe-1", true, 10 * MS);
+
+        let report = tracker
+            .get_at(later, "subnet-1", "probe-1", minute)
+            .unwrap();
+        assert_eq!(report.total, 1);
+        assert_eq!(report.availability_burn_rate, 0.0);
+
+        let report = tracker
+            .get_at(later, "subnet-1", "probe-1", 10 * minute)
+            .unwrap();
+        assert_eq!(report.total, 11);
+
+        // Samples outside of all windows are dropped
+        tracker.record_at(later + 20 * minute, "subnet-1", "probe-1", true, 10 * MS);
+        assert_eq!(
+            tracker.samples.lock().unwrap()[&("subnet-1".to_string(), "probe-1".to_string())].len(),
+            1
+        );
+
+        // Reports are computed for the given time, so samples age out when no probes arrive
+        let reports = tracker.reports_at(later + 40 * minute);
+        assert!(!reports.is_empty());
+        assert!(reports.iter().all(|(_, _, _, report)| report.total == 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_validates_config() {
+        assert!(config().validate().is_ok());
+        assert!(SloConfig::default().validate().is_ok());
+
+        let mut c = config();
+        c.availability_target = 1.0;
+        assert!(c.validate().is_err());
+
+        let mut c = config();
+        c.windows = vec![];
+        assert!(c.validate().is_err());
+    }
+}
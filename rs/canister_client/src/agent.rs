@@ -369,7 +369,8 @@ impl Agent {
         )
     }
 
-    async fn get_status(&self) -> Result<HttpStatusResponse, String> {
+    /// Requests the status of this node by querying /api/v2/status
+    pub async fn get_status(&self) -> Result<HttpStatusResponse, String> {
         let bytes = self
             .http_client
             .get_with_response(
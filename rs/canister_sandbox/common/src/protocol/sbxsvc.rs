@@ -9,8 +9,8 @@ use ic_embedders::{CompilationResult, SerializedModule, SerializedModuleBytes};
 use ic_interfaces::execution_environment::HypervisorResult;
 use ic_replicated_state::{
     page_map::{
-        CheckpointSerialization, MappingSerialization, PageAllocatorSerialization,
-        PageMapSerialization,
+        CheckpointSerialization, MappingSerialization, OverlayFileSerialization,
+        PageAllocatorSerialization, PageMapSerialization, StorageSerialization,
     },
     Global, NumWasmPages,
 };
@@ -115,11 +115,30 @@ impl EnumerateInnerFileDescriptors for MemorySerialization {
 // canister-sandbox.
 impl EnumerateInnerFileDescriptors for PageMapSerialization {
     fn enumerate_fds<'a>(&'a mut self, fds: &mut Vec<&'a mut std::os::unix::io::RawFd>) {
-        self.checkpoint.enumerate_fds(fds);
+        self.storage.enumerate_fds(fds);
         self.page_allocator.enumerate_fds(fds);
     }
 }
 
+// The trait is implemented here to avoid dependency of relicated-state on
+// canister-sandbox.
+impl EnumerateInnerFileDescriptors for StorageSerialization {
+    fn enumerate_fds<'a>(&'a mut self, fds: &mut Vec<&'a mut std::os::unix::io::RawFd>) {
+        self.base.enumerate_fds(fds);
+        for overlay in self.overlays.iter_mut() {
+            overlay.enumerate_fds(fds);
+        }
+    }
+}
+
+// The trait is implemented here to avoid dependency of relicated-state on
+// canister-sandbox.
+impl EnumerateInnerFileDescriptors for OverlayFileSerialization {
+    fn enumerate_fds<'a>(&'a mut self, fds: &mut Vec<&'a mut std::os::unix::io::RawFd>) {
+        self.mapping.enumerate_fds(fds);
+    }
+}
+
 // The trait is implemented here to avoid dependency of relicated-state on
 // canister-sandbox.
 impl EnumerateInnerFileDescriptors for CheckpointSerialization {
@@ -5,6 +5,10 @@ use std::path::PathBuf;
 /// Default capacity, in number of messages, for validated and unvalidated pools
 const MAX_INGRESS_POOL_VALIDATED_CAPACITY: usize = 1024;
 const MAX_INGRESS_POOL_UNVALIDATED_CAPACITY_PER_PEER: usize = 100_000_000;
+/// Default maximum number of messages of a single sender in the ingress pool
+const MAX_INGRESS_POOL_COUNT_PER_SENDER: usize = 1_000;
+/// Default maximum number of messages of the anonymous principal in the ingress pool
+const MAX_INGRESS_POOL_COUNT_ANONYMOUS: usize = 10_000;
 const MAX_CONSENSUS_POOL_VALIDATED_CAPACITY: usize = 2048;
 const MAX_CONSENSUS_POOL_UNVALIDATED_CAPACITY_PER_PEER: usize = 2048;
 const PERSISTENT_POOL_VALIDATED_PURGE_INTERVAL: u64 = 5000;
@@ -77,6 +81,13 @@ pub struct ArtifactPoolConfig {
     /// Maximum byte size of ingress pool. If exceeded, we start throttling ingress.
     /// We also throttle if [`ingress_pool_size_max_count`] is exceeded.
     pub ingress_pool_max_bytes: usize,
+    /// Maximum number of validated messages of a single sender in the ingress
+    /// pool. Further messages of that sender are dropped on validation until
+    /// some of its messages leave the pool.
+    pub ingress_pool_max_count_per_sender: usize,
+    /// Same as [`Self::ingress_pool_max_count_per_sender`], but for the anonymous
+    /// principal, which is shared by everyone and thus has more slots.
+    pub ingress_pool_max_count_anonymous: usize,
     /// The maximum size, in number of messages, of the unvalidated section
     /// of the artifact pool, per peer.
     pub consensus_pool_unvalidated_capacity_per_peer: usize,
@@ -148,6 +159,8 @@ impl From<ArtifactPoolTomlConfig> for ArtifactPoolConfig {
                 MAX_INGRESS_POOL_UNVALIDATED_CAPACITY_PER_PEER,
             ingress_pool_max_count: toml_config.ingress_pool_max_count,
             ingress_pool_max_bytes: toml_config.ingress_pool_max_bytes,
+            ingress_pool_max_count_per_sender: MAX_INGRESS_POOL_COUNT_PER_SENDER,
+            ingress_pool_max_count_anonymous: MAX_INGRESS_POOL_COUNT_ANONYMOUS,
             consensus_pool_unvalidated_capacity_per_peer: MAX_CONSENSUS_POOL_VALIDATED_CAPACITY,
             consensus_pool_validated_capacity: MAX_CONSENSUS_POOL_UNVALIDATED_CAPACITY_PER_PEER,
             persistent_pool_backend,
@@ -126,6 +126,16 @@ pub const SAMPLE_CONFIG: &str = r#"
         //   CspVault is run as a separate process, which can be reached via a Unix socket.
         //   It also has an optional Unix socket for exporting metrics.
         csp_vault_type: { unix_socket: { logic: "/some/path/to/socket", metrics: "/some/path/to/another_socket" } },
+        // The encryption at rest of the secret key stores. The secret key stores are stored
+        // unencrypted if not set.
+        // - EXAMPLE: secret_key_store_encryption: { kek_source: { systemd_credential: { name: "sks-kek" } } },
+        //   The secret key stores are sealed with a key derived from the key-encryption key (KEK),
+        //   which is read from a file (`file: { path: ... }`), an environment variable
+        //   (`environment_variable: { name: ... }`, holding a hex- or base64-encoded 32-byte key)
+        //   or a systemd credential. A running vault re-encrypts the secret key stores with the
+        //   KEK currently found at `kek_source` via `rotate_secret_key_store_encryption_key`.
+        //   After a restart, stores still sealed with the previous KEK are re-encrypted if it is
+        //   configured as `previous_kek_source`.
     },
     // ========================================
     // Configuration of the message scheduling.
@@ -41,6 +41,33 @@ pub enum CspVaultType {
     },
 }
 
+/// The source of a key-encryption key (KEK), from which the key that seals a
+/// secret key store on disk is derived.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyEncryptionKeySource {
+    /// The KEK is the content of the file at `path`.
+    File { path: PathBuf },
+    /// The KEK is the value of the environment variable `name`, which must be a
+    /// hex- or base64-encoded 32-byte key.
+    EnvironmentVariable { name: String },
+    /// The KEK is the systemd credential `name`, i.e., the file `name` in the
+    /// directory given by the environment variable `CREDENTIALS_DIRECTORY`.
+    SystemdCredential { name: String },
+}
+
+/// Configures the encryption at rest of the secret key stores.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct SecretKeyStoreEncryptionConfig {
+    /// The source of the KEK that the secret key stores are sealed with.
+    pub kek_source: KeyEncryptionKeySource,
+    /// The source of the previous KEK, while the KEK is rotated. Secret key
+    /// stores that are still sealed with the previous KEK are re-encrypted with
+    /// the current KEK when they are opened.
+    #[serde(default)]
+    pub previous_kek_source: Option<KeyEncryptionKeySource>,
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 #[serde(default)]
 #[cfg_attr(test, derive(Arbitrary))]
@@ -60,6 +87,10 @@ pub struct CryptoConfig {
     )]
     pub crypto_root: PathBuf,
     pub csp_vault_type: CspVaultType,
+    /// If set, the secret key stores are encrypted at rest. Existing plaintext
+    /// secret key stores are encrypted when they are opened.
+    #[cfg_attr(test, proptest(value = "None"))]
+    pub secret_key_store_encryption: Option<SecretKeyStoreEncryptionConfig>,
 }
 
 impl Default for CryptoConfig {
@@ -67,6 +98,7 @@ impl Default for CryptoConfig {
         Self {
             crypto_root: PathBuf::from(CRYPTO_ROOT_DEFAULT_PATH),
             csp_vault_type: CspVaultType::InReplica,
+            secret_key_store_encryption: None,
         }
     }
 }
@@ -77,6 +109,7 @@ impl CryptoConfig {
         Self {
             crypto_root,
             csp_vault_type: CspVaultType::InReplica,
+            secret_key_store_encryption: None,
         }
     }
 
@@ -94,6 +127,7 @@ impl CryptoConfig {
                 logic: logic_socket_path,
                 metrics: metrics_socket_path,
             },
+            secret_key_store_encryption: None,
         }
     }
 
@@ -213,6 +247,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn config_with_secret_key_store_encryption_serializes_and_deserializes() {
+        CryptoConfig::run_with_temp_config(|config| {
+            serde_test(CryptoConfig {
+                secret_key_store_encryption: Some(SecretKeyStoreEncryptionConfig {
+                    kek_source: KeyEncryptionKeySource::SystemdCredential {
+                        name: "sks-kek".to_string(),
+                    },
+                    previous_kek_source: Some(KeyEncryptionKeySource::File {
+                        path: PathBuf::from("/var/lib/ic/crypto/sks-kek.old"),
+                    }),
+                }),
+                ..config
+            })
+        });
+    }
+
     #[test]
     fn should_create_path_as_directory() {
         CryptoConfig::run_with_temp_config(|config| assert!(config.crypto_root.is_dir()));
@@ -222,6 +222,11 @@ pub struct Config {
 
     /// Indicate whether query stats should be collected or not.
     pub query_stats_aggregation: FlagStatus,
+
+    /// Indicates whether canisters are limited to `DEFAULT_OUTPUT_QUEUES_CAPACITY`
+    /// messages across all of their output queues. Beyond it, new requests are
+    /// neither enqueued nor inducted. Subnet queues are exempt.
+    pub canister_output_queues_capacity: FlagStatus,
 }
 
 impl Default for Config {
@@ -285,6 +290,7 @@ impl Default for Config {
             query_cache_capacity: QUERY_CACHE_CAPACITY,
             max_compilation_cache_size: MAX_COMPILATION_CACHE_SIZE,
             query_stats_aggregation: FlagStatus::Disabled,
+            canister_output_queues_capacity: FlagStatus::Disabled,
         }
     }
 }
@@ -8,6 +8,20 @@ pub struct Config {
     /// A feature flag that enables/disables the file backed memory allocator.
     #[serde(default = "file_backed_memory_allocator_default")]
     pub file_backed_memory_allocator: FlagStatus,
+    /// A feature flag that enables/disables writing page maps as overlay
+    /// files instead of rewriting their checkpoint files.
+    #[serde(default = "lsmt_storage_default")]
+    pub lsmt_storage: FlagStatus,
+    /// A feature flag that enables/disables recomputing the hashes of all
+    /// chunks reused by incremental manifest computation and comparing them
+    /// against the reused ones. Meant for debugging only, as it defeats the
+    /// purpose of incremental computation.
+    #[serde(default = "manifest_debug_check_default")]
+    pub manifest_debug_check: FlagStatus,
+    /// The number of threads used to hash the files of a checkpoint when
+    /// computing its manifest.
+    #[serde(default = "manifest_hashing_threads_default")]
+    pub manifest_hashing_threads: u32,
 }
 
 impl Config {
@@ -15,6 +29,9 @@ impl Config {
         Self {
             state_root,
             file_backed_memory_allocator: file_backed_memory_allocator_default(),
+            lsmt_storage: lsmt_storage_default(),
+            manifest_debug_check: manifest_debug_check_default(),
+            manifest_hashing_threads: manifest_hashing_threads_default(),
         }
     }
 
@@ -32,3 +49,15 @@ impl Config {
 fn file_backed_memory_allocator_default() -> FlagStatus {
     FlagStatus::Enabled
 }
+
+fn lsmt_storage_default() -> FlagStatus {
+    FlagStatus::Disabled
+}
+
+fn manifest_debug_check_default() -> FlagStatus {
+    FlagStatus::Disabled
+}
+
+fn manifest_hashing_threads_default() -> u32 {
+    16
+}
@@ -476,12 +476,14 @@ impl CertifierImpl {
                 }
 
                 Box::new(
-                    certification_pool
-                        .unvalidated_shares_at_height(*height)
-                        .filter_map(move |share| {
-                            self.validate_share(certification_pool, hash, share)
-                        })
-                        .chain(cert_change_set.into_iter()),
+                    self.validate_shares(
+                        certification_pool,
+                        *height,
+                        hash,
+                        certification_pool.unvalidated_shares_at_height(*height),
+                    )
+                    .into_iter()
+                    .chain(cert_change_set.into_iter()),
                 )
             })
             .collect()
@@ -544,24 +546,69 @@ impl CertifierImpl {
         }
     }
 
-    fn validate_share(
+    /// Validates the given unvalidated shares of one height. The signatures of
+    /// all shares that pass the other checks are verified in one batch.
+    fn validate_shares<'a>(
+        &self,
+        certification_pool: &dyn CertificationPool,
+        height: Height,
+        hash: &CryptoHashOfPartialState,
+        shares: impl Iterator<Item = &'a CertificationShare>,
+    ) -> ChangeSet {
+        let mut change_set = ChangeSet::new();
+        let mut shares_to_verify = Vec::new();
+        for share in shares {
+            match self.check_share(certification_pool, hash, share) {
+                Ok(()) => shares_to_verify.push(share),
+                Err(action) => change_set.extend(action),
+            }
+        }
+        if shares_to_verify.is_empty() {
+            return change_set;
+        }
+
+        let dkg_id =
+            match active_high_threshold_transcript(self.consensus_pool_cache.as_ref(), height) {
+                Some(transcript) => transcript.dkg_id,
+                None => return change_set,
+            };
+        let signed_shares: Vec<_> = shares_to_verify.iter().map(|share| &share.signed).collect();
+        let results = self.crypto.verify_batch(&signed_shares, dkg_id);
+        for (share, result) in shares_to_verify.into_iter().zip(results) {
+            let msg = CertificationMessage::CertificationShare(share.clone());
+            match result.map_err(VerifierError::from) {
+                Ok(()) => change_set.push(ChangeAction::MoveToValidated(msg)),
+                Err(ValidationError::Permanent(err)) => {
+                    change_set.push(ChangeAction::HandleInvalid(msg, format!("{:?}", err)))
+                }
+                Err(ValidationError::Transient(err)) => {
+                    debug!(self.log, "Couldn't verify share signature: {:?}", err);
+                }
+            }
+        }
+        change_set
+    }
+
+    /// Performs all checks of the given share except for the signature
+    /// verification. Returns `Err` with the action to take if the share cannot
+    /// be validated, or `Err(None)` if the share should be skipped for now.
+    fn check_share(
         &self,
         certification_pool: &dyn CertificationPool,
         hash: &CryptoHashOfPartialState,
         share: &CertificationShare,
-    ) -> Option<ChangeAction> {
-        let msg = CertificationMessage::CertificationShare(share.clone());
+    ) -> Result<(), Option<ChangeAction>> {
         let content = &share.signed.content;
         // If the share has an invalid content or does not belong to the
         // committee
         if !hash.eq(&content.hash) {
-            return Some(ChangeAction::HandleInvalid(
-                msg,
+            return Err(Some(ChangeAction::HandleInvalid(
+                CertificationMessage::CertificationShare(share.clone()),
                 format!(
                     "Unexpected state hash (expected: {:?}, received: {:?})",
                     hash, content.hash
                 ),
-            ));
+            )));
         }
         let signer = share.signed.signature.signer;
         match self.membership.node_belongs_to_threshold_committee(
@@ -575,14 +622,14 @@ impl CertifierImpl {
                     self.log,
                     "Couldn't check committee membership during share validation: {:?}", err
                 );
-                None
+                Err(None)
             }
             // If the signer does not belong to the signers committee at the
             // given height, reject this artifact.
-            Ok(false) => Some(ChangeAction::HandleInvalid(
-                msg,
+            Ok(false) => Err(Some(ChangeAction::HandleInvalid(
+                CertificationMessage::CertificationShare(share.clone()),
                 "Signer does not belong to the committee".to_string(),
-            )),
+            ))),
             // The signer is valid.
             Ok(true) => {
                 // If the signer has signed a share before, invalidate the new one.
@@ -590,32 +637,11 @@ impl CertifierImpl {
                     .shares_at_height(share.height)
                     .any(|valid_share| signer == valid_share.signed.signature.signer)
                 {
-                    return Some(ChangeAction::RemoveFromUnvalidated(msg));
+                    return Err(Some(ChangeAction::RemoveFromUnvalidated(
+                        CertificationMessage::CertificationShare(share.clone()),
+                    )));
                 }
-                // Verify the signature.
-                Some(
-                    match self
-                        .crypto
-                        .verify(
-                            &share.signed,
-                            active_high_threshold_transcript(
-                                self.consensus_pool_cache.as_ref(),
-                                share.height,
-                            )?
-                            .dkg_id,
-                        )
-                        .map_err(VerifierError::from)
-                    {
-                        Ok(()) => ChangeAction::MoveToValidated(msg),
-                        Err(ValidationError::Permanent(err)) => {
-                            ChangeAction::HandleInvalid(msg, format!("{:?}", err))
-                        }
-                        Err(ValidationError::Transient(err)) => {
-                            debug!(self.log, "Couldn't verify share signature: {:?}", err);
-                            return None;
-                        }
-                    },
-                )
+                Ok(())
             }
         }
     }
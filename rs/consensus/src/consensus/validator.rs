@@ -314,6 +314,11 @@ trait NotaryIssued: Sized + HasHeight + std::fmt::Debug {
         signed_message: &Signed<Self, MultiSignatureShare<Self>>,
         registry_version: RegistryVersion,
     ) -> ValidationResult<CryptoError>;
+    fn verify_multi_sig_individual_batch(
+        crypto: &dyn ConsensusCrypto,
+        signed_messages: &[&Signed<Self, MultiSignatureShare<Self>>],
+        registry_version: RegistryVersion,
+    ) -> Vec<ValidationResult<CryptoError>>;
     fn is_duplicate(&self, pool: &PoolReader) -> bool;
     fn dependencies_validated(&self, pool: &PoolReader) -> Result<(), &str>;
 }
@@ -339,6 +344,14 @@ impl NotaryIssued for NotarizationContent {
         crypto.verify(signed_message, registry_version)
     }
 
+    fn verify_multi_sig_individual_batch(
+        crypto: &dyn ConsensusCrypto,
+        signed_messages: &[&Signed<Self, MultiSignatureShare<Self>>],
+        registry_version: RegistryVersion,
+    ) -> Vec<ValidationResult<CryptoError>> {
+        crypto.verify_batch(signed_messages, registry_version)
+    }
+
     fn is_duplicate(&self, pool: &PoolReader) -> bool {
         pool.pool()
             .validated()
@@ -384,6 +397,14 @@ impl NotaryIssued for FinalizationContent {
         crypto.verify(signed_message, registry_version)
     }
 
+    fn verify_multi_sig_individual_batch(
+        crypto: &dyn ConsensusCrypto,
+        signed_messages: &[&Signed<Self, MultiSignatureShare<Self>>],
+        registry_version: RegistryVersion,
+    ) -> Vec<ValidationResult<CryptoError>> {
+        crypto.verify_batch(signed_messages, registry_version)
+    }
+
     fn is_duplicate(&self, pool: &PoolReader) -> bool {
         pool.pool()
             .validated()
@@ -446,15 +467,26 @@ impl<T: NotaryIssued> SignatureVerify for Signed<T, MultiSignatureShare<T>> {
         crypto: &dyn ConsensusCrypto,
         pool: &PoolReader<'_>,
     ) -> ValidationResult<ValidatorError> {
-        let height = self.height();
-        let previous_beacon = get_previous_beacon(pool, height)?;
-        verify_notary(membership, height, &previous_beacon, self.signature.signer)?;
-        let registry_version = get_registry_version(pool, height)?;
+        let registry_version = verify_notary_issued_share_signer(membership, pool, self)?;
         T::verify_multi_sig_individual(crypto, self, registry_version)?;
         Ok(())
     }
 }
 
+/// Checks that the signer of a `NotarizationShare` or `FinalizationShare` is
+/// in the notary committee, and returns the registry version that the
+/// signature has to be verified with.
+fn verify_notary_issued_share_signer<T: NotaryIssued>(
+    membership: &Membership,
+    pool: &PoolReader<'_>,
+    share: &Signed<T, MultiSignatureShare<T>>,
+) -> Result<RegistryVersion, ValidatorError> {
+    let height = share.height();
+    let previous_beacon = get_previous_beacon(pool, height)?;
+    verify_notary(membership, height, &previous_beacon, share.signature.signer)?;
+    get_registry_version(pool, height)
+}
+
 fn get_previous_beacon(
     pool: &PoolReader<'_>,
     height: Height,
@@ -692,7 +724,8 @@ impl Validator {
     }
 
     /// Return a `ChangeSet` of `FinalizationShare`s. See
-    /// `validate_notary_issued` for details about exactly what is checked.
+    /// `validate_notary_issued_shares` for details about exactly what is
+    /// checked.
     fn validate_finalization_shares(&self, pool_reader: &PoolReader<'_>) -> ChangeSet {
         let max_height = match pool_reader
             .pool()
@@ -711,9 +744,7 @@ impl Validator {
             .finalization_share()
             .get_by_height_range(range);
 
-        finalization_shares
-            .filter_map(|share| self.validate_notary_issued(pool_reader, share))
-            .collect()
+        self.validate_notary_issued_shares(pool_reader, finalization_shares)
     }
 
     /// Return a `ChangeSet` of `Notarization`s. See
@@ -738,7 +769,8 @@ impl Validator {
     }
 
     /// Return a `ChangeSet` of `NotarizationShare`s. See
-    /// `validate_notary_issued` for details about exactly what is checked.
+    /// `validate_notary_issued_shares` for details about exactly what is
+    /// checked.
     fn validate_notarization_shares(&self, pool_reader: &PoolReader<'_>) -> ChangeSet {
         let max_height = match pool_reader
             .pool()
@@ -757,9 +789,7 @@ impl Validator {
             .notarization_share()
             .get_by_height_range(range);
 
-        notarization_shares
-            .filter_map(|share| self.validate_notary_issued(pool_reader, share))
-            .collect()
+        self.validate_notary_issued_shares(pool_reader, notarization_shares)
     }
 
     /// Validate a single `Signed`, `NotaryIssued` value. This involves checking
@@ -811,6 +841,73 @@ impl Validator {
         }
     }
 
+    /// Validate `NotarizationShare`s or `FinalizationShare`s. Each share
+    /// undergoes the same checks as in `validate_notary_issued`, except that
+    /// the signatures of all shares that pass the other checks are verified in
+    /// one batch per registry version, which is considerably cheaper than
+    /// verifying them one by one.
+    fn validate_notary_issued_shares<T>(
+        &self,
+        pool_reader: &PoolReader<'_>,
+        shares: impl Iterator<Item = Signed<T, MultiSignatureShare<T>>>,
+    ) -> ChangeSet
+    where
+        Signed<T, MultiSignatureShare<T>>: ConsensusMessageHashable + Clone,
+        T: NotaryIssued + HasVersion,
+    {
+        let mut change_set = ChangeSet::new();
+        let mut shares_to_verify = BTreeMap::<RegistryVersion, Vec<_>>::new();
+        for share in shares {
+            if check_protocol_version(share.content.version()).is_err() {
+                change_set.push(ChangeAction::RemoveFromUnvalidated(share.into_message()));
+                continue;
+            }
+            // This is checked before entering this function.
+            debug_assert!(share.height() > pool_reader.get_finalized_height());
+            if share.content.is_duplicate(pool_reader) {
+                change_set.push(ChangeAction::RemoveFromUnvalidated(share.into_message()));
+                continue;
+            }
+            if let Err(err) = share.content.dependencies_validated(pool_reader) {
+                if self.unvalidated_for_too_long(pool_reader, &share.get_id()) {
+                    warn!(every_n_seconds => LOG_EVERY_N_SECONDS,
+                          self.log,
+                          "{} {:?}", err, share.content
+                    );
+                }
+                continue;
+            }
+            match verify_notary_issued_share_signer(self.membership.as_ref(), pool_reader, &share) {
+                Ok(registry_version) => shares_to_verify
+                    .entry(registry_version)
+                    .or_default()
+                    .push(share),
+                Err(err) => change_set.extend(self.compute_action_from_artifact_verification(
+                    pool_reader,
+                    Err(err),
+                    share.into_message(),
+                )),
+            }
+        }
+
+        for (registry_version, shares) in shares_to_verify {
+            let share_refs: Vec<_> = shares.iter().collect();
+            let results = T::verify_multi_sig_individual_batch(
+                self.crypto.as_ref(),
+                &share_refs,
+                registry_version,
+            );
+            for (share, result) in shares.into_iter().zip(results) {
+                change_set.extend(self.compute_action_from_artifact_verification(
+                    pool_reader,
+                    result.map_err(ValidatorError::from),
+                    share.into_message(),
+                ));
+            }
+        }
+        change_set
+    }
+
     /// Return a `ChangeSet` containing status updates concerning any currently
     /// unvalidated blocks that can now be marked valid or invalid. See
     /// `check_block_validity`.
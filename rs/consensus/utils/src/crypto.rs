@@ -44,6 +44,23 @@ pub trait SignVerify<Message, Signature, KeySelector> {
         message: &Signed<Message, Signature>,
         selector: KeySelector,
     ) -> ValidationResult<CryptoError>;
+
+    /// Verify the signatures of a batch of Signed messages that use the same
+    /// key selector. Return one result per message, in the same order. The
+    /// default implementation verifies the messages one by one.
+    fn verify_batch(
+        &self,
+        messages: &[&Signed<Message, Signature>],
+        selector: KeySelector,
+    ) -> Vec<ValidationResult<CryptoError>>
+    where
+        KeySelector: Clone,
+    {
+        messages
+            .iter()
+            .map(|message| self.verify(message, selector.clone()))
+            .collect()
+    }
 }
 
 impl<C: BasicSigner<BlockMetadata> + BasicSigVerifier<BlockMetadata>>
@@ -184,6 +201,24 @@ impl<Message: Signable, C: MultiSigner<Message> + MultiSigVerifier<Message>>
             selector,
         )
     }
+
+    fn verify_batch(
+        &self,
+        messages: &[&Signed<Message, MultiSignatureShare<Message>>],
+        selector: RegistryVersion,
+    ) -> Vec<ValidationResult<CryptoError>> {
+        let shares: Vec<_> = messages
+            .iter()
+            .map(|message| {
+                (
+                    &message.signature.signature,
+                    &message.content,
+                    message.signature.signer,
+                )
+            })
+            .collect();
+        self.verify_multi_sig_individual_batch(&shares, selector)
+    }
 }
 
 impl<Message, C> SignVerify<Message, MultiSignatureShare<CryptoHashOf<Message>>, RegistryVersion>
@@ -215,6 +250,23 @@ where
             selector,
         )
     }
+
+    fn verify_batch(
+        &self,
+        messages: &[&Signed<Message, MultiSignatureShare<CryptoHashOf<Message>>>],
+        selector: RegistryVersion,
+    ) -> Vec<ValidationResult<CryptoError>> {
+        let hashes: Vec<_> = messages
+            .iter()
+            .map(|message| ic_types::crypto::crypto_hash(&message.content))
+            .collect();
+        let shares: Vec<_> = messages
+            .iter()
+            .zip(hashes.iter())
+            .map(|(message, hash)| (&message.signature.signature, hash, message.signature.signer))
+            .collect();
+        self.verify_multi_sig_individual_batch(&shares, selector)
+    }
 }
 
 impl<Message: Signable, C: ThresholdSigner<Message> + ThresholdSigVerifier<Message>>
@@ -243,6 +295,24 @@ where {
             message.signature.signer,
         )
     }
+
+    fn verify_batch(
+        &self,
+        messages: &[&Signed<Message, ThresholdSignatureShare<Message>>],
+        dkg_id: NiDkgId,
+    ) -> Vec<ValidationResult<CryptoError>> {
+        let shares: Vec<_> = messages
+            .iter()
+            .map(|message| {
+                (
+                    &message.signature.signature,
+                    &message.content,
+                    message.signature.signer,
+                )
+            })
+            .collect();
+        self.verify_threshold_sig_share_batch(&shares, dkg_id)
+    }
 }
 
 /// A trait that unifies the aggregation and verification interface
@@ -553,6 +623,13 @@ mod tests {
         ) -> CryptoResult<()> {
             Ok(())
         }
+        fn verify_multi_sig_individual_batch(
+            &self,
+            shares: &[(&IndividualMultiSigOf<MessageId>, &MessageId, NodeId)],
+            _registry_version: RegistryVersion,
+        ) -> Vec<CryptoResult<()>> {
+            shares.iter().map(|_| Ok(())).collect()
+        }
         fn combine_multi_sig_individuals(
             &self,
             _signatures: BTreeMap<NodeId, IndividualMultiSigOf<MessageId>>,
@@ -121,6 +121,53 @@ pub fn verify_individual(
     }
 }
 
+/// Verifies a batch of individual multisignatures, each over its own message
+/// and under its own public key.
+///
+/// The signatures are checked together with a randomized linear combination
+/// of the pairing equations, which is considerably cheaper than verifying them
+/// one by one. If the batch is invalid the result does not identify the
+/// offending signature; callers that need to know it have to fall back to
+/// [`verify_individual`].
+///
+/// # Errors
+/// * `CryptoError::MalformedSignature` if any of the signatures cannot be
+///   parsed as a G1 point.
+/// * `CryptoError::MalformedPublicKey` if any of the public keys cannot be
+///   parsed as a valid G2 point.
+/// * `CryptoError::SignatureVerification` if verification of the batch fails.
+pub fn verify_individual_batch<R: Rng + CryptoRng>(
+    sigs_pks_msgs: &[(&IndividualSignatureBytes, &PublicKeyBytes, &[u8])],
+    rng: &mut R,
+) -> Result<(), CryptoError> {
+    let parsed: Vec<(IndividualSignature, PublicKey, &[u8])> = sigs_pks_msgs
+        .iter()
+        .map(|(signature_bytes, public_key_bytes, message)| {
+            Ok((
+                IndividualSignature::try_from(*signature_bytes)?,
+                PublicKey::try_from(*public_key_bytes)?,
+                *message,
+            ))
+        })
+        .collect::<Result<_, CryptoError>>()?;
+    let refs: Vec<(&IndividualSignature, &PublicKey, &[u8])> = parsed
+        .iter()
+        .map(|(signature, public_key, message)| (signature, public_key, *message))
+        .collect();
+    if crypto::verify_individual_message_signature_batch(&refs[..], rng) {
+        Ok(())
+    } else {
+        Err(CryptoError::SignatureVerification {
+            algorithm: AlgorithmId::MultiBls12_381,
+            public_key_bytes: Vec::new(),
+            sig_bytes: Vec::new(),
+            internal_error:
+                "Batch verification of individual contributions to multisignatures failed"
+                    .to_string(),
+        })
+    }
+}
+
 /// Verifies a combined multisignature over the given `message` using the given
 /// array of `public_keys`.
 ///
@@ -99,6 +99,49 @@ proptest! {
         assert!(multi_sig::verify_individual(&message, &evil_signature, &public_key).is_err())
     }
 
+    #[test]
+    fn batch_verification_of_individual_signatures_succeeds(
+      keys in proptest::collection::vec(arbitrary::key_pair_bytes(), 1..10),
+      messages in proptest::collection::vec(proptest::collection::vec(any::<u8>(), 0..100), 10),
+    ) {
+        let signatures: Vec<IndividualSignatureBytes> = keys
+            .iter()
+            .zip(messages.iter())
+            .map(|((secret_key, _), message)| multi_sig::sign(message, secret_key))
+            .collect();
+        let batch: Vec<_> = signatures
+            .iter()
+            .zip(keys.iter())
+            .zip(messages.iter())
+            .map(|((signature, (_, public_key)), message)| (signature, public_key, &message[..]))
+            .collect();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        assert!(multi_sig::verify_individual_batch(&batch, &mut rng).is_ok())
+    }
+
+    #[test]
+    fn batch_verification_with_incorrect_individual_signature_fails(
+      keys in proptest::collection::vec(arbitrary::key_pair_bytes(), 1..10),
+      message in proptest::collection::vec(any::<u8>(), 0..100),
+      evil_signature in arbitrary::individual_signature_bytes(),
+      evil_index in any::<prop::sample::Index>(),
+    ) {
+        let mut signatures: Vec<IndividualSignatureBytes> = keys
+            .iter()
+            .map(|(secret_key, _)| multi_sig::sign(&message, secret_key))
+            .collect();
+        let evil_index = evil_index.index(signatures.len());
+        prop_assume!(evil_signature != signatures[evil_index]);
+        signatures[evil_index] = evil_signature;
+        let batch: Vec<_> = signatures
+            .iter()
+            .zip(keys.iter())
+            .map(|(signature, (_, public_key))| (signature, public_key, &message[..]))
+            .collect();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        assert!(multi_sig::verify_individual_batch(&batch, &mut rng).is_err())
+    }
+
     #[test]
     fn incorrect_pop_fails(
       keys in arbitrary::key_pair_bytes(),
@@ -7,7 +7,8 @@ use crate::types::{
 };
 
 use ic_crypto_internal_bls12_381_type::{
-    verify_bls_signature, G1Projective, G2Affine, G2Projective, Scalar,
+    verify_bls_signature, verify_bls_signature_batch, G1Affine, G1Projective, G2Affine,
+    G2Projective, Scalar,
 };
 
 use ic_crypto_sha2::{Context, DomainSeparationContext};
@@ -97,6 +98,32 @@ pub fn verify_individual_message_signature(
     let hash = hash_message_to_g1(message);
     verify_point(&hash, signature, public_key)
 }
+/// Verifies a batch of individual signatures, each over its own message and
+/// under its own public key.
+///
+/// The batch is checked with a single randomized linear combination of the
+/// pairing equations, so a `false` result does not tell which signature is
+/// invalid.
+pub fn verify_individual_message_signature_batch<R: Rng + CryptoRng>(
+    sigs_pks_msgs: &[(&IndividualSignature, &PublicKey, &[u8])],
+    rng: &mut R,
+) -> bool {
+    let affine_sigs_pks_msgs: Vec<(G1Affine, G2Affine, G1Affine)> = sigs_pks_msgs
+        .iter()
+        .map(|(signature, public_key, message)| {
+            (
+                signature.to_affine(),
+                public_key.to_affine(),
+                hash_message_to_g1(message).to_affine(),
+            )
+        })
+        .collect();
+    let refs: Vec<(&G1Affine, &G2Affine, &G1Affine)> = affine_sigs_pks_msgs
+        .iter()
+        .map(|(signature, public_key, message)| (signature, public_key, message))
+        .collect();
+    verify_bls_signature_batch(&refs[..], rng)
+}
 pub fn verify_pop(pop: &Pop, public_key: &PublicKey) -> bool {
     let public_key_bytes = PublicKeyBytes::from(public_key);
     let mut domain_separated_public_key: Vec<u8> = vec![];
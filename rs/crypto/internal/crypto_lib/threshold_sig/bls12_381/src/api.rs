@@ -220,6 +220,46 @@ pub fn verify_individual_signature(
     crypto::verify_individual_sig(message, &signature, &pk)
 }
 
+/// Verifies that a batch of individual signatures is valid.
+///
+/// The signatures may be over different messages and by different
+/// signatories. They are checked together with a randomized linear combination
+/// of the pairing equations, which is considerably cheaper than calling
+/// `verify_individual_signature(..)` for each of them.
+///
+/// # Arguments:
+/// * `msgs_sigs_pks` are the signed messages, the individual signatures and
+///   the individual public keys of the respective signatories.
+/// * `seed` is used to sample the random coefficients of the linear
+///   combination. It does not need to be secret but must not be predictable
+///   by whoever created the signatures.
+/// # Panics
+/// This method is not expected to panic.
+/// # Errors
+/// * If any signature or public key cannot be parsed, or if the batch is not
+///   valid, this will return an error. In the latter case the error does not
+///   identify which of the signatures is invalid.
+pub fn verify_individual_signature_batch(
+    msgs_sigs_pks: &[(&[u8], IndividualSignatureBytes, PublicKeyBytes)],
+    seed: Seed,
+) -> CryptoResult<()> {
+    let parsed: Vec<(&[u8], IndividualSignature, PublicKey)> = msgs_sigs_pks
+        .iter()
+        .map(|(message, signature, public_key)| {
+            Ok((
+                *message,
+                IndividualSignature::try_from(signature)?,
+                PublicKey::try_from(public_key)?,
+            ))
+        })
+        .collect::<CryptoResult<_>>()?;
+    let refs: Vec<(&[u8], &IndividualSignature, &PublicKey)> = parsed
+        .iter()
+        .map(|(message, signature, public_key)| (*message, signature, public_key))
+        .collect();
+    crypto::verify_individual_sig_batch(&refs[..], seed)
+}
+
 /// Verifies that a combined signature is valid.
 ///
 /// # Arguments
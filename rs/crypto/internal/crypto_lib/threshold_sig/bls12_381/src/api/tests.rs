@@ -54,6 +54,40 @@ fn test_individual_signature_verifies(
     }
 }
 
+/// Batches of individual signatures on different messages should be verifiable,
+/// and a single bad signature should make the batch fail.
+fn test_individual_signature_batch_verifies(
+    seed: Seed,
+    group_size: NumberOfNodes,
+    threshold: NumberOfNodes,
+    message: &[u8],
+) {
+    let rng = &mut seed.into_rng();
+    let (public_coefficients, secret_keys) =
+        util::generate_threshold_key(Seed::from_rng(rng), threshold, group_size)
+            .expect("Failed to deal");
+    let messages: Vec<Vec<u8>> = (0..secret_keys.len())
+        .map(|index| [message, &index.to_be_bytes()[..]].concat())
+        .collect();
+    let mut batch: Vec<(&[u8], IndividualSignatureBytes, PublicKeyBytes)> = (0..)
+        .zip(secret_keys.iter().zip(messages.iter()))
+        .map(|(index, (secret_key, message))| {
+            let signature = tsig::sign_message(message, secret_key).expect("Failed to sign");
+            let public_key = tsig::individual_public_key(&public_coefficients, index)
+                .expect("failed to generate public key");
+            (&message[..], signature, public_key)
+        })
+        .collect();
+    assert_eq!(
+        tsig::verify_individual_signature_batch(&batch, Seed::from_rng(rng)),
+        Ok(())
+    );
+
+    let evil_index = rng.gen_range(0..batch.len());
+    batch[evil_index].0 = b"some other message";
+    assert!(tsig::verify_individual_signature_batch(&batch, Seed::from_rng(rng)).is_err());
+}
+
 fn test_combined_signature_verifies(
     seed: Seed,
     group_size: NumberOfNodes,
@@ -319,6 +353,10 @@ proptest! {
             test_individual_signature_verifies(Seed::from_bytes(&seed), NumberOfNodes::from(threshold + redundancy), NumberOfNodes::from(threshold), &message);
         }
         #[test]
+        fn individual_signature_batch_verifies(seed: [u8;32], threshold in 1_u32..20, redundancy in 0_u32..20, message: Vec<u8>) {
+            test_individual_signature_batch_verifies(Seed::from_bytes(&seed), NumberOfNodes::from(threshold + redundancy), NumberOfNodes::from(threshold), &message);
+        }
+        #[test]
         fn combined_signature_verifies(seed: [u8;32], threshold in 1_u32..20, redundancy in 0_u32..20, message: Vec<u8>) {
             test_combined_signature_verifies(Seed::from_bytes(&seed), NumberOfNodes::from(threshold + redundancy), NumberOfNodes::from(threshold), &message);
         }
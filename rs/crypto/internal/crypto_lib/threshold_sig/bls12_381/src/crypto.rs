@@ -7,7 +7,9 @@ use super::types::{
 use crate::api::dkg_errors::InvalidArgumentError;
 
 use crate::types::PublicKey;
-use ic_crypto_internal_bls12_381_type::{verify_bls_signature, G1Projective, G2Affine, Scalar};
+use ic_crypto_internal_bls12_381_type::{
+    verify_bls_signature, verify_bls_signature_batch, G1Affine, G1Projective, G2Affine, Scalar,
+};
 use ic_crypto_internal_seed::Seed;
 use ic_crypto_internal_types::sign::threshold_sig::public_key::bls12_381::PublicKeyBytes;
 use ic_types::{
@@ -244,6 +246,42 @@ pub(crate) fn verify_individual_sig(
     }
 }
 
+/// Verifies a batch of individual signatures, each over its own message and
+/// against its own public key, using a randomized linear combination of the
+/// pairing equations.
+///
+/// # Returns
+/// * OK, if all signatures in the batch are valid
+/// * Err, otherwise. The error does not identify the invalid signature(s).
+pub(crate) fn verify_individual_sig_batch(
+    msgs_sigs_pks: &[(&[u8], &IndividualSignature, &PublicKey)],
+    seed: Seed,
+) -> CryptoResult<()> {
+    let affine_sigs_pks_msgs: Vec<(G1Affine, G2Affine, G1Affine)> = msgs_sigs_pks
+        .iter()
+        .map(|(message, signature, public_key)| {
+            (
+                signature.to_affine(),
+                public_key.0.to_affine(),
+                hash_message_to_g1(message).to_affine(),
+            )
+        })
+        .collect();
+    let refs: Vec<(&G1Affine, &G2Affine, &G1Affine)> = affine_sigs_pks_msgs
+        .iter()
+        .map(|(signature, public_key, message)| (signature, public_key, message))
+        .collect();
+    match verify_bls_signature_batch(&refs[..], &mut seed.into_rng()) {
+        true => Ok(()),
+        false => Err(CryptoError::SignatureVerification {
+            algorithm: AlgorithmId::ThresBls12_381,
+            public_key_bytes: Vec::new(),
+            sig_bytes: Vec::new(),
+            internal_error: "Invalid batch of individual threshold signatures".to_string(),
+        }),
+    }
+}
+
 /// Verifies a combined signature against the provided public key.
 ///
 /// # Returns
@@ -22,6 +22,7 @@ pub use csp_pop::arb_csp_pop;
 pub use csp_public_key::arb_csp_public_key;
 pub use csp_public_key_store_error::arb_csp_public_key_store_error;
 pub use csp_secret_key_store_contains_error::arb_csp_secret_key_store_contains_error;
+pub use csp_secret_key_store_encryption_key_rotation_error::arb_csp_secret_key_store_encryption_key_rotation_error;
 pub use csp_signature::arb_csp_signature;
 pub use csp_threshold_sign_error::arb_csp_threshold_sign_error;
 pub use csp_tls_keygen_error::arb_csp_tls_keygen_error;
@@ -339,6 +340,17 @@ mod csp_secret_key_store_contains_error {
     );
 }
 
+mod csp_secret_key_store_encryption_key_rotation_error {
+    use super::*;
+    use ic_crypto_internal_csp::vault::api::CspSecretKeyStoreEncryptionKeyRotationError;
+
+    proptest_strategy_for_enum!(CspSecretKeyStoreEncryptionKeyRotationError;
+        EncryptionNotConfigured,
+        KeyEncryptionKeyLoadingError => {error in ".*"},
+        TransientInternalError => {internal_error in ".*"}
+    );
+}
+
 pub mod registry_client_error {
     use super::*;
     use crate::common::arb_registry_version;
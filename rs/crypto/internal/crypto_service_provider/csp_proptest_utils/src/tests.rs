@@ -152,6 +152,15 @@ should_have_a_strategy_for_each_variant!(
     TransientInternalError { .. }
 );
 
+use ic_crypto_internal_csp::vault::api::CspSecretKeyStoreEncryptionKeyRotationError;
+should_have_a_strategy_for_each_variant!(
+    CspSecretKeyStoreEncryptionKeyRotationError,
+    CspSecretKeyStoreEncryptionKeyRotationError::EncryptionNotConfigured,
+    EncryptionNotConfigured,
+    KeyEncryptionKeyLoadingError { .. },
+    TransientInternalError { .. }
+);
+
 use ic_types::registry::RegistryClientError;
 should_have_a_strategy_for_each_variant!(
     RegistryClientError,
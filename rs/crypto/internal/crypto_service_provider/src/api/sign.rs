@@ -137,4 +137,28 @@ pub trait CspSigVerifier {
         msg: &[u8],
         algorithm_id: AlgorithmId,
     ) -> CryptoResult<()>;
+
+    /// Verifies a batch of individual multisignatures, each over its own
+    /// message and under its own public key.
+    /// # Arguments
+    /// * `sigs_pks_msgs` triples of individual signatures, public keys and
+    ///   signed messages to be verified
+    /// * `algorithm_id` the signature algorithm
+    /// # Errors
+    /// * [`CryptoError::InternalError`] in case of internal CSP error, e.g., if
+    ///   the CSP failed to generate the required randomness.
+    /// * [`CryptoError::AlgorithmNotSupported`] if the signature algorithm
+    ///   does not support multisignatures.
+    /// * [`CryptoError::SignatureVerification`] if the batch was checked and
+    ///   found to be invalid. The error does not identify which of the
+    ///   signatures is invalid.
+    /// * [`CryptoError::MalformedPublicKey`] if a public key is malformed
+    /// * [`CryptoError::MalformedSignature`] if a signature is malformed
+    /// # Returns
+    /// `Ok(())` if all signatures are valid or an `Err` otherwise
+    fn verify_multisig_individual_batch(
+        &self,
+        sigs_pks_msgs: &[(CspSignature, CspPublicKey, Vec<u8>)],
+        algorithm_id: AlgorithmId,
+    ) -> CryptoResult<()>;
 }
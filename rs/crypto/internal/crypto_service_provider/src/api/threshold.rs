@@ -84,6 +84,17 @@ pub trait ThresholdSignatureCspClient {
         public_key: CspThresholdSigPublicKey,
     ) -> CryptoResult<()>;
 
+    /// Checks whether a batch of individual signatures is valid.
+    ///
+    /// The signatures may be over different messages and by different nodes.
+    /// If the batch is invalid, the error does not identify which of the
+    /// signatures is invalid.
+    fn threshold_verify_individual_signature_batch(
+        &self,
+        algorithm_id: AlgorithmId,
+        msgs_sigs_pks: &[(Vec<u8>, CspSignature, CspThresholdSigPublicKey)],
+    ) -> CryptoResult<()>;
+
     /// Checks whether a combined signature is valid.
     /// If sufficient valid signatures are combined, the result will pass this
     /// test and this is the ultimate goal of the threshold signature scheme.
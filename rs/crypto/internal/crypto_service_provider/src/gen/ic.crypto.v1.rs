@@ -20,4 +20,26 @@ pub struct SecretKeyStore {
     #[prost(map = "string, message", tag = "3")]
     pub key_id_to_secret_key_v1:
         ::std::collections::HashMap<::prost::alloc::string::String, SecretKeyV1>,
+    /// The secret keys sealed with a key derived from a key-encryption key (KEK).
+    /// If set, `key_id_to_secret_key_v1` is empty and the sealed data is the
+    /// serialization of a `SecretKeyStore` that contains the secret keys.
+    #[prost(message, optional, tag = "4")]
+    pub encrypted_secret_keys: ::core::option::Option<EncryptedSecretKeys>,
+}
+/// EncryptedSecretKeys stores sealed secret keys.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EncryptedSecretKeys {
+    /// SHA-256 hash identifying the KEK.
+    #[prost(bytes = "vec", tag = "1")]
+    pub kek_id: ::prost::alloc::vec::Vec<u8>,
+    /// Salt used for deriving the data encryption key from the KEK.
+    #[prost(bytes = "vec", tag = "2")]
+    pub salt: ::prost::alloc::vec::Vec<u8>,
+    /// Nonce of the XChaCha20-Poly1305 encryption.
+    #[prost(bytes = "vec", tag = "3")]
+    pub nonce: ::prost::alloc::vec::Vec<u8>,
+    /// XChaCha20-Poly1305 ciphertext of the serialized `SecretKeyStore`.
+    #[prost(bytes = "vec", tag = "4")]
+    pub ciphertext: ::prost::alloc::vec::Vec<u8>,
 }
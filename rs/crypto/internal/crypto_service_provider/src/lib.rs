@@ -191,8 +191,12 @@ impl Csp {
             logger,
             "Proceeding with an in-replica csp_vault, CryptoConfig: {:?}", config
         );
-        let csp_vault =
-            LocalCspVault::new_in_dir(&config.crypto_root, metrics.clone(), new_logger!(&logger));
+        let csp_vault = LocalCspVault::new_in_dir(
+            &config.crypto_root,
+            config.secret_key_store_encryption.as_ref(),
+            metrics.clone(),
+            new_logger!(&logger),
+        );
         Csp::builder(csp_vault, logger, metrics).build()
     }
 
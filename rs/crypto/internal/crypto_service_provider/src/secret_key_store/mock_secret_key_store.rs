@@ -1,4 +1,5 @@
 use crate::key_id::KeyId;
+use crate::secret_key_store::proto_store::KeyEncryptionKey;
 use crate::secret_key_store::{
     Scope, SecretKeyStore, SecretKeyStoreInsertionError, SecretKeyStoreWriteError,
 };
@@ -17,5 +18,6 @@ mock! {
         fn remove(&mut self, id: &KeyId) -> Result<bool, SecretKeyStoreWriteError>;
         fn retain<F>(&mut self, filter: F, scope: Scope) -> Result<(), SecretKeyStoreWriteError>
             where F: Fn(&KeyId, &CspSecretKey) -> bool + 'static;
+        fn rotate_key_encryption_key(&mut self, kek: KeyEncryptionKey) -> Result<(), SecretKeyStoreWriteError>;
     }
 }
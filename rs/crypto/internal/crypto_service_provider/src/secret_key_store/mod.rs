@@ -3,6 +3,7 @@
 use crate::key_id::KeyId;
 use crate::types::CspSecretKey;
 pub use ic_crypto_internal_types::scope;
+use proto_store::KeyEncryptionKey;
 pub use scope::Scope;
 use std::fmt;
 
@@ -96,6 +97,13 @@ pub trait SecretKeyStore: Send + Sync {
     fn retain<F>(&mut self, _filter: F, _scope: Scope) -> Result<(), SecretKeyStoreWriteError>
     where
         F: Fn(&KeyId, &CspSecretKey) -> bool + 'static;
+
+    /// Re-encrypts the store at rest with the key-encryption key `kek`, which
+    /// is used for all subsequent writes.
+    fn rotate_key_encryption_key(
+        &mut self,
+        kek: KeyEncryptionKey,
+    ) -> Result<(), SecretKeyStoreWriteError>;
 }
 
 /// Errors that can occur while inserting a key into the secret key store
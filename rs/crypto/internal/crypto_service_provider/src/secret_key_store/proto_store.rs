@@ -7,12 +7,14 @@ use crate::secret_key_store::{
 };
 use crate::types::CspSecretKey;
 use hex::{FromHex, ToHex};
-use ic_config::crypto::CryptoConfig;
+use ic_config::crypto::{CryptoConfig, SecretKeyStoreEncryptionConfig};
 use ic_crypto_internal_threshold_sig_bls12381::ni_dkg::groth20_bls12_381::types::convert_keyset_to_keyset_with_pop;
 use ic_crypto_internal_threshold_sig_bls12381::ni_dkg::types::CspFsEncryptionKeySet;
+use ic_crypto_secrets_containers::SecretBytes;
 use ic_logger::{debug, info, replica_logger::no_op_logger, warn, ReplicaLogger};
 use parking_lot::RwLock;
 use prost::Message;
+use rand::rngs::OsRng;
 use std::borrow::BorrowMut;
 use std::collections::HashMap;
 use std::fs;
@@ -21,10 +23,19 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 
+mod encryption;
 #[cfg(test)]
 mod tests;
 
+pub use encryption::{
+    KeyEncryptionKey, KeyEncryptionKeyLoadingError, ENCODED_KEK_LEN, MIN_KEK_LEN,
+};
+
 const CURRENT_SKS_VERSION: u32 = 3;
+/// Version of secret key stores whose keys are sealed in `encrypted_secret_keys`. It is higher
+/// than all versions of unencrypted secret key stores, so that binaries without support for
+/// encryption refuse to open an encrypted secret key store rather than treating it as empty.
+const ENCRYPTED_SKS_VERSION: u32 = 4;
 
 fn key_id_from_hex(key_id_hex: &str) -> KeyId {
     KeyId::from_hex(key_id_hex).unwrap_or_else(|_| panic!("Error parsing hex KeyId {}", key_id_hex))
@@ -40,10 +51,14 @@ type SecretKeys = HashMap<KeyId, (CspSecretKey, Option<Scope>)>;
 
 /// A secret key store that persists data to the filesystem, using protobufs for
 /// serialization
+///
+/// If the store is opened with a key-encryption key (KEK), the data is encrypted at rest, see
+/// [`ProtoSecretKeyStore::open_encrypted`].
 pub struct ProtoSecretKeyStore {
     proto_file: PathBuf,
     old_proto_file_to_zeroize: PathBuf,
     keys: Arc<RwLock<SecretKeys>>,
+    kek: Option<KeyEncryptionKey>,
     logger: ReplicaLogger,
 }
 
@@ -60,7 +75,75 @@ impl ProtoSecretKeyStore {
     /// # Panics
     ///  - If the crypto root directory does not have the required permissions
     ///  - If the secret key store file is not a POSIX regular file
+    ///  - If the secret key store is encrypted
     pub fn open(dir: &Path, file_name: &str, logger: Option<ReplicaLogger>) -> Self {
+        Self::open_internal(dir, file_name, None, None, logger)
+    }
+
+    /// Creates a `ProtoSecretKeyStore` instance backed by a file that is encrypted at rest with a
+    /// key derived from `kek`. The same assumptions as for [`Self::open`] apply to `dir` and
+    /// `file_name`.
+    ///
+    /// An existing unencrypted secret key store, or one that is sealed with `previous_kek`, is
+    /// re-encrypted with `kek` when it is opened.
+    ///
+    /// # Panics
+    ///  - If the crypto root directory does not have the required permissions
+    ///  - If the secret key store file is not a POSIX regular file
+    ///  - If the secret key store is sealed with neither `kek` nor `previous_kek`, or cannot be
+    ///    unsealed
+    ///  - If the re-encrypted secret key store cannot be written
+    pub fn open_encrypted(
+        dir: &Path,
+        file_name: &str,
+        kek: KeyEncryptionKey,
+        previous_kek: Option<KeyEncryptionKey>,
+        logger: Option<ReplicaLogger>,
+    ) -> Self {
+        Self::open_internal(dir, file_name, Some(kek), previous_kek, logger)
+    }
+
+    /// Creates a `ProtoSecretKeyStore` instance that is encrypted at rest if `encryption` is
+    /// given (see [`Self::open_encrypted`]), and unencrypted otherwise (see [`Self::open`]).
+    ///
+    /// # Panics
+    ///  - If a KEK cannot be loaded from its configured source
+    ///  - In the cases documented for [`Self::open`] and [`Self::open_encrypted`]
+    pub fn open_with_encryption_config(
+        dir: &Path,
+        file_name: &str,
+        encryption: Option<&SecretKeyStoreEncryptionConfig>,
+        logger: Option<ReplicaLogger>,
+    ) -> Self {
+        match encryption {
+            None => Self::open(dir, file_name, logger),
+            Some(config) => {
+                let load_kek = |source| {
+                    KeyEncryptionKey::load(source).unwrap_or_else(|e| {
+                        panic!(
+                            "error loading key-encryption key for secret key store {}: {}",
+                            file_name, e
+                        )
+                    })
+                };
+                Self::open_encrypted(
+                    dir,
+                    file_name,
+                    load_kek(&config.kek_source),
+                    config.previous_kek_source.as_ref().map(load_kek),
+                    logger,
+                )
+            }
+        }
+    }
+
+    fn open_internal(
+        dir: &Path,
+        file_name: &str,
+        kek: Option<KeyEncryptionKey>,
+        previous_kek: Option<KeyEncryptionKey>,
+        logger: Option<ReplicaLogger>,
+    ) -> Self {
         CryptoConfig::check_dir_has_required_permissions(dir)
             .expect("wrong crypto root permissions");
         let proto_file = dir.join(file_name);
@@ -75,21 +158,37 @@ impl ProtoSecretKeyStore {
             }
         }
         let old_proto_file_to_zeroize = dir.join(format!("{}.old", file_name));
-        let secret_keys = match Self::read_sks_data_from_disk(&proto_file) {
-            Some(sks_proto) => sks_proto,
-            None => SecretKeys::new(),
-        };
+        let (secret_keys, needs_reencryption) =
+            match Self::read_sks_data_from_disk(&proto_file, kek.as_ref(), previous_kek.as_ref()) {
+                Some(sks_data) => sks_data,
+                None => (SecretKeys::new(), false),
+            };
         let logger = logger.unwrap_or_else(no_op_logger);
         let sks = ProtoSecretKeyStore {
             proto_file,
             old_proto_file_to_zeroize,
             keys: Arc::new(RwLock::new(secret_keys)),
+            kek,
             logger,
         };
         sks.clean_up_old_sks();
+        if needs_reencryption {
+            info!(
+                sks.logger,
+                "Encrypting secret key store {} with the current key-encryption key",
+                sks.proto_file.to_string_lossy()
+            );
+            with_write_lock(&sks.keys, |keys| sks.write_secret_keys_to_disk(keys))
+                .unwrap_or_else(|e| panic!("error encrypting secret key store: {}", e));
+        }
         sks
     }
 
+    /// Returns whether the secret key store is encrypted at rest.
+    pub fn is_encrypted(&self) -> bool {
+        self.kek.is_some()
+    }
+
     /// Returns the path to the protobuf file storing the keys.
     pub fn proto_file_path(&self) -> &Path {
         self.proto_file.as_path()
@@ -180,6 +279,10 @@ impl ProtoSecretKeyStore {
         secret_keys: &SecretKeys,
     ) -> Result<(), SecretKeyStoreWriteError> {
         let sks_proto = ProtoSecretKeyStore::secret_keys_to_sks_proto(secret_keys)?;
+        let sks_proto = match &self.kek {
+            Some(kek) => ProtoSecretKeyStore::seal_sks_proto(&sks_proto, kek)?,
+            None => sks_proto,
+        };
         match self.proto_file.try_exists() {
             Ok(exists) => {
                 if exists {
@@ -226,14 +329,23 @@ impl ProtoSecretKeyStore {
         Ok(())
     }
 
-    fn read_sks_data_from_disk(sks_data_file: &Path) -> Option<SecretKeys> {
+    /// Reads the secret keys from disk, unsealing them if the secret key store is encrypted.
+    /// The returned flag indicates whether the secret key store must be re-encrypted with `kek`,
+    /// i.e., whether it is unencrypted or sealed with `previous_kek`.
+    fn read_sks_data_from_disk(
+        sks_data_file: &Path,
+        kek: Option<&KeyEncryptionKey>,
+        previous_kek: Option<&KeyEncryptionKey>,
+    ) -> Option<(SecretKeys, bool)> {
         match fs::read(sks_data_file) {
             Ok(data) => {
                 let sks_pb = pb::SecretKeyStore::decode(&*data).unwrap_or_else(
                     |_ignored_so_that_no_data_is_leaked| panic!("error parsing SKS protobuf data"),
                 );
+                let (sks_pb, needs_reencryption) =
+                    ProtoSecretKeyStore::unseal_sks_proto(sks_pb, kek, previous_kek);
                 let keys = ProtoSecretKeyStore::migrate_to_current_version(sks_pb);
-                Some(keys)
+                Some((keys, needs_reencryption))
             }
             Err(err) => {
                 if err.kind() == ErrorKind::NotFound {
@@ -245,6 +357,56 @@ impl ProtoSecretKeyStore {
         }
     }
 
+    fn unseal_sks_proto(
+        sks_proto: pb::SecretKeyStore,
+        kek: Option<&KeyEncryptionKey>,
+        previous_kek: Option<&KeyEncryptionKey>,
+    ) -> (pb::SecretKeyStore, bool) {
+        let sealed = match &sks_proto.encrypted_secret_keys {
+            Some(sealed) => sealed,
+            None => return (sks_proto, kek.is_some()),
+        };
+        if sks_proto.version != ENCRYPTED_SKS_VERSION {
+            panic!(
+                "Unexpected encrypted SecretKeyStore-proto version: {}",
+                sks_proto.version
+            )
+        }
+        let kek = kek.unwrap_or_else(|| {
+            panic!("secret key store is encrypted, but no key-encryption key was provided")
+        });
+        let (unsealing_kek, needs_reencryption) = match previous_kek {
+            Some(previous_kek)
+                if sealed.kek_id != kek.id() && sealed.kek_id == previous_kek.id() =>
+            {
+                (previous_kek, true)
+            }
+            _ => (kek, false),
+        };
+        let data = unsealing_kek
+            .unseal(sealed)
+            .unwrap_or_else(|e| panic!("error unsealing secret key store: {}", e));
+        let sks_pb = pb::SecretKeyStore::decode(data.expose_secret()).unwrap_or_else(
+            |_ignored_so_that_no_data_is_leaked| panic!("error parsing unsealed SKS protobuf data"),
+        );
+        (sks_pb, needs_reencryption)
+    }
+
+    fn seal_sks_proto(
+        sks_proto: &pb::SecretKeyStore,
+        kek: &KeyEncryptionKey,
+    ) -> Result<pb::SecretKeyStore, SecretKeyStoreWriteError> {
+        let data = SecretBytes::new(sks_proto.encode_to_vec());
+        let sealed = kek
+            .seal(data.expose_secret(), &mut OsRng)
+            .map_err(SecretKeyStoreWriteError::SerializationError)?;
+        Ok(pb::SecretKeyStore {
+            version: ENCRYPTED_SKS_VERSION,
+            encrypted_secret_keys: Some(sealed),
+            ..Default::default()
+        })
+    }
+
     fn migrate_to_current_version(sks_proto: pb::SecretKeyStore) -> SecretKeys {
         match sks_proto.version {
             CURRENT_SKS_VERSION => ProtoSecretKeyStore::sks_proto_to_secret_keys(&sks_proto),
@@ -449,6 +611,27 @@ impl SecretKeyStore for ProtoSecretKeyStore {
             Ok(())
         })
     }
+
+    /// If the secret key store was unencrypted so far, it is encrypted at rest from now on. If
+    /// writing the re-encrypted secret key store fails, the previous KEK is kept.
+    fn rotate_key_encryption_key(
+        &mut self,
+        kek: KeyEncryptionKey,
+    ) -> Result<(), SecretKeyStoreWriteError> {
+        let kek_id = hex::encode(kek.id());
+        let previous_kek = self.kek.replace(kek);
+        let result = with_write_lock(&self.keys, |keys| self.write_secret_keys_to_disk(keys));
+        match result {
+            Ok(()) => info!(
+                self.logger,
+                "Re-encrypted secret key store {} with key-encryption key {}",
+                self.proto_file.to_string_lossy(),
+                kek_id
+            ),
+            Err(_) => self.kek = previous_kek,
+        }
+        result
+    }
 }
 
 impl Drop for ProtoSecretKeyStore {
@@ -0,0 +1,203 @@
+//! Encryption at rest of secret key stores
+//!
+//! A secret key store is sealed with XChaCha20-Poly1305 under a data
+//! encryption key that is derived with HMAC-SHA256 from a key-encryption key
+//! (KEK) and a fresh random salt. The KEK itself never leaves the node, it is
+//! read from the source configured in the
+//! [`SecretKeyStoreEncryptionConfig`](ic_config::crypto::SecretKeyStoreEncryptionConfig).
+//! A KEK from an environment variable must be a hex- or base64-encoded
+//! [`ENCODED_KEK_LEN`]-byte key.
+use super::pb;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use ic_config::crypto::KeyEncryptionKeySource;
+use ic_crypto_internal_hmac::{Hmac, Sha256 as HmacSha256};
+use ic_crypto_secrets_containers::SecretBytes;
+use ic_crypto_sha2::{DomainSeparationContext, Sha256};
+use rand::{CryptoRng, Rng};
+use std::fmt;
+use std::path::PathBuf;
+
+/// The minimal length of a KEK in bytes.
+pub const MIN_KEK_LEN: usize = 32;
+/// The length in bytes of a KEK that is given encoded in an environment variable.
+pub const ENCODED_KEK_LEN: usize = 32;
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+const KEK_ID_DOMAIN: &str = "ic-crypto-secret-key-store-kek-id";
+const DATA_ENCRYPTION_KEY_DOMAIN: &[u8] = b"ic-crypto-secret-key-store-data-encryption-key";
+const SYSTEMD_CREDENTIALS_DIRECTORY: &str = "CREDENTIALS_DIRECTORY";
+
+/// A key-encryption key (KEK) that secret key stores are sealed with.
+#[derive(Clone)]
+pub struct KeyEncryptionKey {
+    id: [u8; 32],
+    secret: SecretBytes,
+}
+
+impl KeyEncryptionKey {
+    /// Loads the KEK from the given `source`.
+    pub fn load(source: &KeyEncryptionKeySource) -> Result<Self, KeyEncryptionKeyLoadingError> {
+        let secret = match source {
+            KeyEncryptionKeySource::File { path } => read_kek_file(path.clone())?,
+            KeyEncryptionKeySource::EnvironmentVariable { name } => {
+                let value = std::env::var(name).map_err(|e| {
+                    KeyEncryptionKeyLoadingError::NotFound(format!(
+                        "cannot read environment variable {}: {}",
+                        name, e
+                    ))
+                })?;
+                decode_kek(value.trim())?
+            }
+            KeyEncryptionKeySource::SystemdCredential { name } => {
+                let credentials_dir =
+                    std::env::var(SYSTEMD_CREDENTIALS_DIRECTORY).map_err(|e| {
+                        KeyEncryptionKeyLoadingError::NotFound(format!(
+                            "cannot read environment variable {}: {}",
+                            SYSTEMD_CREDENTIALS_DIRECTORY, e
+                        ))
+                    })?;
+                read_kek_file(PathBuf::from(credentials_dir).join(name))?
+            }
+        };
+        Self::from_secret(secret)
+    }
+
+    /// Creates a KEK from the given secret, which must be at least
+    /// [`MIN_KEK_LEN`] bytes long.
+    pub fn from_secret(secret: SecretBytes) -> Result<Self, KeyEncryptionKeyLoadingError> {
+        let len = secret.expose_secret().len();
+        if len < MIN_KEK_LEN {
+            return Err(KeyEncryptionKeyLoadingError::TooShort { len });
+        }
+        let mut hash = Sha256::new_with_context(&DomainSeparationContext::new(KEK_ID_DOMAIN));
+        hash.write(secret.expose_secret());
+        Ok(Self {
+            id: hash.finish(),
+            secret,
+        })
+    }
+
+    /// Returns the ID of this KEK, which is stored alongside the sealed data.
+    pub fn id(&self) -> &[u8; 32] {
+        &self.id
+    }
+
+    /// Seals the given `plaintext` under a data encryption key that is derived
+    /// from this KEK and a fresh salt.
+    pub(super) fn seal<R: Rng + CryptoRng>(
+        &self,
+        plaintext: &[u8],
+        rng: &mut R,
+    ) -> Result<pb::EncryptedSecretKeys, String> {
+        let salt: [u8; SALT_LEN] = rng.gen();
+        let nonce: [u8; NONCE_LEN] = rng.gen();
+        let ciphertext = self
+            .data_encryption_key(&salt)
+            .encrypt(XNonce::from_slice(&nonce), plaintext)
+            .map_err(|_| "error encrypting the secret key store".to_string())?;
+        Ok(pb::EncryptedSecretKeys {
+            kek_id: self.id.to_vec(),
+            salt: salt.to_vec(),
+            nonce: nonce.to_vec(),
+            ciphertext,
+        })
+    }
+
+    /// Unseals the given `sealed` data, which must have been sealed with this
+    /// KEK.
+    pub(super) fn unseal(&self, sealed: &pb::EncryptedSecretKeys) -> Result<SecretBytes, String> {
+        if sealed.kek_id != self.id {
+            return Err("the secret key store was sealed with a different KEK".to_string());
+        }
+        if sealed.nonce.len() != NONCE_LEN {
+            return Err(format!(
+                "invalid nonce length: expected {} but got {}",
+                NONCE_LEN,
+                sealed.nonce.len()
+            ));
+        }
+        self.data_encryption_key(&sealed.salt)
+            .decrypt(
+                XNonce::from_slice(&sealed.nonce),
+                sealed.ciphertext.as_slice(),
+            )
+            .map(SecretBytes::new)
+            .map_err(|_| "error decrypting the secret key store".to_string())
+    }
+
+    fn data_encryption_key(&self, salt: &[u8]) -> XChaCha20Poly1305 {
+        let mut hmac = Hmac::<HmacSha256>::new(self.secret.expose_secret());
+        hmac.write(DATA_ENCRYPTION_KEY_DOMAIN);
+        hmac.write(salt);
+        let key = SecretBytes::new(hmac.finish());
+        XChaCha20Poly1305::new_from_slice(key.expose_secret())
+            .expect("HMAC-SHA256 output has the XChaCha20-Poly1305 key length")
+    }
+}
+
+impl fmt::Debug for KeyEncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyEncryptionKey {{ id: {} }}", hex::encode(self.id))
+    }
+}
+
+fn read_kek_file(path: PathBuf) -> Result<SecretBytes, KeyEncryptionKeyLoadingError> {
+    std::fs::read(&path).map(SecretBytes::new).map_err(|e| {
+        KeyEncryptionKeyLoadingError::NotFound(format!(
+            "cannot read KEK file {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Decodes a hex- or base64-encoded KEK, which must be exactly [`ENCODED_KEK_LEN`] bytes long.
+fn decode_kek(encoded: &str) -> Result<SecretBytes, KeyEncryptionKeyLoadingError> {
+    let decoded = if encoded.len() == 2 * ENCODED_KEK_LEN {
+        hex::decode(encoded).map_err(|_| "the KEK is not valid hex")
+    } else {
+        base64::decode(encoded).map_err(|_| "the KEK is neither valid hex nor valid base64")
+    }
+    .map(SecretBytes::new)
+    .map_err(|e| KeyEncryptionKeyLoadingError::Malformed(e.to_string()))?;
+    let len = decoded.expose_secret().len();
+    if len != ENCODED_KEK_LEN {
+        return Err(KeyEncryptionKeyLoadingError::Malformed(format!(
+            "expected a {}-byte KEK but got {} bytes",
+            ENCODED_KEK_LEN, len
+        )));
+    }
+    Ok(decoded)
+}
+
+/// Errors that can occur while loading a key-encryption key
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum KeyEncryptionKeyLoadingError {
+    /// The KEK could not be read from its source.
+    NotFound(String),
+    /// The KEK is shorter than [`MIN_KEK_LEN`] bytes.
+    TooShort { len: usize },
+    /// The KEK is not properly encoded, or has the wrong length after decoding.
+    Malformed(String),
+}
+
+impl std::error::Error for KeyEncryptionKeyLoadingError {}
+
+impl fmt::Display for KeyEncryptionKeyLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyEncryptionKeyLoadingError::NotFound(e) => {
+                write!(f, "Error loading key-encryption key: {}", e)
+            }
+            KeyEncryptionKeyLoadingError::TooShort { len } => write!(
+                f,
+                "Key-encryption key is too short: expected at least {} bytes but got {}",
+                MIN_KEK_LEN, len
+            ),
+            KeyEncryptionKeyLoadingError::Malformed(e) => {
+                write!(f, "Malformed key-encryption key: {}", e)
+            }
+        }
+    }
+}
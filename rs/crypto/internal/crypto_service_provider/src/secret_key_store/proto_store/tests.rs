@@ -969,6 +969,237 @@ mod insert_or_replace {
         ]
     }
 }
+mod encryption {
+    use super::*;
+    use ic_config::crypto::KeyEncryptionKeySource;
+
+    const SKS_FILENAME: &str = "sks_data.pb";
+
+    #[test]
+    fn should_retrieve_keys_after_reopening_encrypted_secret_key_store() {
+        let rng = &mut reproducible_rng();
+        let temp_dir: TempDir = mk_temp_dir_with_permissions(0o700);
+        let (key_id, secret_key) = (make_key_id(rng), make_secret_key(rng));
+        {
+            let mut key_store = open_encrypted(&temp_dir, kek(1), None);
+            assert!(key_store.is_encrypted());
+            assert!(key_store.insert(key_id, secret_key.clone(), None).is_ok());
+        }
+
+        let key_store = open_encrypted(&temp_dir, kek(1), None);
+
+        assert_eq!(key_store.get(&key_id), Some(secret_key));
+    }
+
+    #[test]
+    fn should_not_store_secret_keys_in_plaintext() {
+        let rng = &mut reproducible_rng();
+        let temp_dir: TempDir = mk_temp_dir_with_permissions(0o700);
+        let mut key_store = open_encrypted(&temp_dir, kek(1), None);
+
+        assert!(key_store
+            .insert(make_key_id(rng), make_secret_key(rng), None)
+            .is_ok());
+
+        let sks_proto = read_sks_proto(key_store.proto_file_path());
+        assert_eq!(sks_proto.version, ENCRYPTED_SKS_VERSION);
+        assert!(sks_proto.key_id_to_secret_key_v1.is_empty());
+        assert_matches!(sks_proto.encrypted_secret_keys, Some(sealed) if sealed.kek_id == kek(1).id());
+    }
+
+    #[test]
+    fn should_encrypt_existing_unencrypted_secret_key_store_when_opening() {
+        let temp_dir: TempDir = mk_temp_dir_with_permissions(0o700);
+        copy_file_to_dir(
+            path_to_existing_secret_key_store(&SecretKeyStoreVersion::V3).as_path(),
+            temp_dir.path(),
+        );
+        let file_name = existing_secret_key_store_file_name(&SecretKeyStoreVersion::V3);
+
+        let key_store =
+            ProtoSecretKeyStore::open_encrypted(temp_dir.path(), &file_name, kek(1), None, None);
+
+        let sks_proto = read_sks_proto(key_store.proto_file_path());
+        assert_eq!(sks_proto.version, ENCRYPTED_SKS_VERSION);
+        assert!(sks_proto.key_id_to_secret_key_v1.is_empty());
+        let tls = TestVector::tls();
+        assert_eq!(key_store.get(&tls.key_id), Some(tls.secret_key));
+        assert!(!temp_dir.path().join(format!("{}.old", file_name)).exists());
+    }
+
+    #[test]
+    #[should_panic(expected = "secret key store is encrypted, but no key-encryption key")]
+    fn should_panic_when_opening_encrypted_secret_key_store_without_kek() {
+        let temp_dir: TempDir = mk_temp_dir_with_permissions(0o700);
+        write_encrypted_secret_key_store(&temp_dir, kek(1));
+
+        let _key_store = ProtoSecretKeyStore::open(temp_dir.path(), SKS_FILENAME, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "sealed with a different KEK")]
+    fn should_panic_when_opening_encrypted_secret_key_store_with_wrong_kek() {
+        let temp_dir: TempDir = mk_temp_dir_with_permissions(0o700);
+        write_encrypted_secret_key_store(&temp_dir, kek(1));
+
+        let _key_store = open_encrypted(&temp_dir, kek(2), None);
+    }
+
+    #[test]
+    fn should_reencrypt_secret_key_store_sealed_with_previous_kek_when_opening() {
+        let temp_dir: TempDir = mk_temp_dir_with_permissions(0o700);
+        let (key_id, secret_key) = write_encrypted_secret_key_store(&temp_dir, kek(1));
+
+        let key_store = open_encrypted(&temp_dir, kek(2), Some(kek(1)));
+
+        assert_eq!(key_store.get(&key_id), Some(secret_key.clone()));
+        let sks_proto = read_sks_proto(key_store.proto_file_path());
+        assert_matches!(sks_proto.encrypted_secret_keys, Some(sealed) if sealed.kek_id == kek(2).id());
+        drop(key_store);
+        let key_store = open_encrypted(&temp_dir, kek(2), None);
+        assert_eq!(key_store.get(&key_id), Some(secret_key));
+    }
+
+    #[test]
+    fn should_rotate_kek_of_open_secret_key_store() {
+        let temp_dir: TempDir = mk_temp_dir_with_permissions(0o700);
+        let (key_id, secret_key) = write_encrypted_secret_key_store(&temp_dir, kek(1));
+        let mut key_store = open_encrypted(&temp_dir, kek(1), None);
+
+        assert!(key_store.rotate_key_encryption_key(kek(2)).is_ok());
+
+        assert_eq!(key_store.get(&key_id), Some(secret_key.clone()));
+        drop(key_store);
+        let key_store = open_encrypted(&temp_dir, kek(2), None);
+        assert_eq!(key_store.get(&key_id), Some(secret_key));
+    }
+
+    #[test]
+    fn should_encrypt_unencrypted_secret_key_store_when_rotating_kek() {
+        let rng = &mut reproducible_rng();
+        let temp_dir: TempDir = mk_temp_dir_with_permissions(0o700);
+        let mut key_store = ProtoSecretKeyStore::open(temp_dir.path(), SKS_FILENAME, None);
+        let (key_id, secret_key) = (make_key_id(rng), make_secret_key(rng));
+        assert!(key_store.insert(key_id, secret_key.clone(), None).is_ok());
+        assert!(!key_store.is_encrypted());
+
+        assert!(key_store.rotate_key_encryption_key(kek(1)).is_ok());
+
+        assert!(key_store.is_encrypted());
+        let sks_proto = read_sks_proto(key_store.proto_file_path());
+        assert_eq!(sks_proto.version, ENCRYPTED_SKS_VERSION);
+        drop(key_store);
+        let key_store = open_encrypted(&temp_dir, kek(1), None);
+        assert_eq!(key_store.get(&key_id), Some(secret_key));
+    }
+
+    #[test]
+    fn should_load_kek_from_file() {
+        let temp_dir: TempDir = mk_temp_dir_with_permissions(0o700);
+        let path = temp_dir.path().join("kek");
+        fs::write(&path, [1u8; MIN_KEK_LEN]).expect("failed to write KEK file");
+
+        let kek = KeyEncryptionKey::load(&KeyEncryptionKeySource::File { path });
+
+        assert_matches!(kek, Ok(kek) if kek.id() == self::kek(1).id());
+    }
+
+    #[test]
+    fn should_load_hex_encoded_kek_from_environment_variable() {
+        let name = "IC_CRYPTO_TEST_SKS_KEK_HEX".to_string();
+        std::env::set_var(&name, hex::encode([b'a'; ENCODED_KEK_LEN]));
+
+        let kek = KeyEncryptionKey::load(&KeyEncryptionKeySource::EnvironmentVariable { name });
+
+        assert_matches!(kek, Ok(kek) if kek.id() == self::kek(b'a').id());
+    }
+
+    #[test]
+    fn should_load_base64_encoded_kek_from_environment_variable() {
+        let name = "IC_CRYPTO_TEST_SKS_KEK_BASE64".to_string();
+        std::env::set_var(&name, base64::encode([b'a'; ENCODED_KEK_LEN]));
+
+        let kek = KeyEncryptionKey::load(&KeyEncryptionKeySource::EnvironmentVariable { name });
+
+        assert_matches!(kek, Ok(kek) if kek.id() == self::kek(b'a').id());
+    }
+
+    #[test]
+    fn should_reject_unencoded_kek_from_environment_variable() {
+        let name = "IC_CRYPTO_TEST_SKS_KEK_RAW".to_string();
+        std::env::set_var(&name, "a".repeat(MIN_KEK_LEN));
+
+        let kek = KeyEncryptionKey::load(&KeyEncryptionKeySource::EnvironmentVariable { name });
+
+        assert_matches!(kek, Err(KeyEncryptionKeyLoadingError::Malformed(_)));
+    }
+
+    #[test]
+    fn should_reject_kek_of_wrong_length_from_environment_variable() {
+        for len in [
+            ENCODED_KEK_LEN - 1,
+            ENCODED_KEK_LEN + 1,
+            2 * ENCODED_KEK_LEN,
+        ] {
+            let name = format!("IC_CRYPTO_TEST_SKS_KEK_LEN_{}", len);
+            std::env::set_var(&name, base64::encode(vec![b'a'; len]));
+
+            let kek = KeyEncryptionKey::load(&KeyEncryptionKeySource::EnvironmentVariable { name });
+
+            assert_matches!(kek, Err(KeyEncryptionKeyLoadingError::Malformed(e)) if e.contains(&len.to_string()));
+        }
+    }
+
+    #[test]
+    fn should_fail_to_load_kek_from_missing_file() {
+        let temp_dir: TempDir = mk_temp_dir_with_permissions(0o700);
+        let path = temp_dir.path().join("missing_kek");
+
+        let kek = KeyEncryptionKey::load(&KeyEncryptionKeySource::File { path });
+
+        assert_matches!(kek, Err(KeyEncryptionKeyLoadingError::NotFound(_)));
+    }
+
+    #[test]
+    fn should_reject_too_short_kek() {
+        let kek = KeyEncryptionKey::from_secret(SecretBytes::new(vec![1; MIN_KEK_LEN - 1]));
+
+        assert_matches!(
+            kek,
+            Err(KeyEncryptionKeyLoadingError::TooShort { len }) if len == MIN_KEK_LEN - 1
+        );
+    }
+
+    fn kek(byte: u8) -> KeyEncryptionKey {
+        KeyEncryptionKey::from_secret(SecretBytes::new(vec![byte; MIN_KEK_LEN]))
+            .expect("failed to create KEK")
+    }
+
+    fn open_encrypted(
+        temp_dir: &TempDir,
+        kek: KeyEncryptionKey,
+        previous_kek: Option<KeyEncryptionKey>,
+    ) -> ProtoSecretKeyStore {
+        ProtoSecretKeyStore::open_encrypted(temp_dir.path(), SKS_FILENAME, kek, previous_kek, None)
+    }
+
+    fn write_encrypted_secret_key_store(
+        temp_dir: &TempDir,
+        kek: KeyEncryptionKey,
+    ) -> (KeyId, CspSecretKey) {
+        let rng = &mut reproducible_rng();
+        let (key_id, secret_key) = (make_key_id(rng), make_secret_key(rng));
+        let mut key_store = open_encrypted(temp_dir, kek, None);
+        assert!(key_store.insert(key_id, secret_key.clone(), None).is_ok());
+        (key_id, secret_key)
+    }
+
+    fn read_sks_proto(sks_data_file: &Path) -> pb::SecretKeyStore {
+        let data = fs::read(sks_data_file).expect("error reading SKS");
+        pb::SecretKeyStore::decode(&*data).expect("error parsing SKS data")
+    }
+}
+
 fn copy_file_to_dir(source_file: &Path, target_dir: &Path) {
     let filename = source_file.file_name().expect("expected file name");
     let target_file = target_dir.join(filename);
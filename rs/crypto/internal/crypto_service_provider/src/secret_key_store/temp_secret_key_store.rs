@@ -1,5 +1,5 @@
 use crate::key_id::KeyId;
-use crate::secret_key_store::proto_store::ProtoSecretKeyStore;
+use crate::secret_key_store::proto_store::{KeyEncryptionKey, ProtoSecretKeyStore};
 use crate::secret_key_store::{
     SecretKeyStore, SecretKeyStoreInsertionError, SecretKeyStoreWriteError,
 };
@@ -82,4 +82,11 @@ impl SecretKeyStore for TempSecretKeyStore {
     {
         self.store.retain(filter, scope)
     }
+
+    fn rotate_key_encryption_key(
+        &mut self,
+        kek: KeyEncryptionKey,
+    ) -> Result<(), SecretKeyStoreWriteError> {
+        self.store.rotate_key_encryption_key(kek)
+    }
 }
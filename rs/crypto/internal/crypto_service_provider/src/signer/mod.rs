@@ -281,4 +281,54 @@ impl CspSigVerifier for Csp {
         }
         Ok(())
     }
+
+    fn verify_multisig_individual_batch(
+        &self,
+        sigs_pks_msgs: &[(CspSignature, CspPublicKey, Vec<u8>)],
+        algorithm_id: AlgorithmId,
+    ) -> CryptoResult<()> {
+        match algorithm_id {
+            AlgorithmId::MultiBls12_381 => {
+                let sigs_pks: CryptoResult<
+                    Vec<(
+                        multi_sig::types::IndividualSignatureBytes,
+                        multi_sig::types::PublicKeyBytes,
+                    )>,
+                > = sigs_pks_msgs
+                    .iter()
+                    .map(|(sig, pk, _msg)| match (sig, pk) {
+                        (
+                            CspSignature::MultiBls12_381(MultiBls12_381_Signature::Individual(
+                                sig,
+                            )),
+                            CspPublicKey::MultiBls12_381(pk),
+                        ) => Ok((*sig, *pk)),
+                        _ => Err(CryptoError::SignatureVerification {
+                            algorithm: algorithm_id,
+                            public_key_bytes: pk.pk_bytes().to_vec(),
+                            sig_bytes: sig.as_ref().to_vec(),
+                            internal_error: format!(
+                                "Invalid signature or public key type: expected {algorithm_id} but found {} and {}",
+                                sig.algorithm(),
+                                pk.algorithm_id()
+                            ),
+                        }),
+                    })
+                    .collect();
+                let sigs_pks = sigs_pks?;
+                let batch: Vec<_> = sigs_pks
+                    .iter()
+                    .zip(sigs_pks_msgs.iter())
+                    .map(|((sig, pk), (_, _, msg))| (sig, pk, &msg[..]))
+                    .collect();
+                // generate a random seed to be used in batched sig verification
+                let seed = self.csp_vault.new_public_seed()?;
+                multi_sig::verify_individual_batch(&batch[..], &mut seed.into_rng())
+            }
+            _ => Err(CryptoError::AlgorithmNotSupported {
+                algorithm: algorithm_id,
+                reason: "Not a multi-signature algorithm".to_string(),
+            }),
+        }
+    }
 }
@@ -965,6 +965,68 @@ mod multi {
         );
         assert!(combination.unwrap_err().is_algorithm_not_supported());
     }
+
+    #[test]
+    fn batch_of_individual_signatures_on_different_messages_verifies() {
+        let [csp1, csp2, verifier] = csp_and_verifier_with_different_seeds();
+        let (public_key1, _pop1) = csp1.gen_committee_signing_key_pair().unwrap();
+        let (public_key2, _pop2) = csp2.gen_committee_signing_key_pair().unwrap();
+        let message1 = b"Three turtle doves".to_vec();
+        let message2 = b"Four calling birds".to_vec();
+        let signature1 = csp1
+            .sign(
+                AlgorithmId::MultiBls12_381,
+                &message1,
+                KeyId::try_from(&public_key1).unwrap(),
+            )
+            .expect("Signing failed");
+        let signature2 = csp2
+            .sign(
+                AlgorithmId::MultiBls12_381,
+                &message2,
+                KeyId::try_from(&public_key2).unwrap(),
+            )
+            .expect("Signing failed");
+
+        let valid_batch = vec![
+            (signature1.clone(), public_key1.clone(), message1.clone()),
+            (signature2.clone(), public_key2.clone(), message2.clone()),
+        ];
+        assert_matches!(
+            verifier.verify_multisig_individual_batch(&valid_batch, AlgorithmId::MultiBls12_381),
+            Ok(())
+        );
+
+        let batch_with_swapped_messages = vec![
+            (signature1, public_key1, message2),
+            (signature2, public_key2, message1),
+        ];
+        let result = verifier.verify_multisig_individual_batch(
+            &batch_with_swapped_messages,
+            AlgorithmId::MultiBls12_381,
+        );
+        assert!(result.unwrap_err().is_signature_verification_error());
+    }
+
+    #[test]
+    fn batch_verification_fails_gracefully_for_unsuitable_algorithm_id() {
+        let [csp, verifier] = csp_and_verifier_with_different_seeds();
+        let (public_key, _pop) = csp.gen_committee_signing_key_pair().unwrap();
+        let message = b"Three turtle doves".to_vec();
+        let signature = csp
+            .sign(
+                AlgorithmId::MultiBls12_381,
+                &message,
+                KeyId::try_from(&public_key).unwrap(),
+            )
+            .expect("Signing failed");
+
+        let result = verifier.verify_multisig_individual_batch(
+            &[(signature, public_key, message)],
+            AlgorithmId::Ed25519,
+        );
+        assert!(result.unwrap_err().is_algorithm_not_supported());
+    }
 }
 
 mod batch {
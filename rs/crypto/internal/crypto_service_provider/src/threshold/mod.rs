@@ -115,6 +115,35 @@ impl ThresholdSignatureCspClient for Csp {
         }
     }
 
+    fn threshold_verify_individual_signature_batch(
+        &self,
+        algorithm_id: AlgorithmId,
+        msgs_sigs_pks: &[(Vec<u8>, CspSignature, CspThresholdSigPublicKey)],
+    ) -> CryptoResult<()> {
+        match algorithm_id {
+            AlgorithmId::ThresBls12_381 => {
+                let clib_batch: CryptoResult<
+                    Vec<(&[u8], clib::types::IndividualSignatureBytes, PublicKeyBytes)>,
+                > = msgs_sigs_pks
+                    .iter()
+                    .map(|(message, signature, public_key)| {
+                        Ok((
+                            &message[..],
+                            clib::types::IndividualSignatureBytes::try_from(signature.clone())?,
+                            PublicKeyBytes::from(*public_key),
+                        ))
+                    })
+                    .collect();
+                let clib_batch = clib_batch?;
+                let seed = self.csp_vault.new_public_seed()?;
+                clib::api::verify_individual_signature_batch(&clib_batch[..], seed)
+            }
+            _ => Err(CryptoError::InvalidArgument {
+                message: format!("Unsupported algorithm: {:?}", algorithm_id),
+            }),
+        }
+    }
+
     fn threshold_verify_combined_signature(
         &self,
         algorithm_id: AlgorithmId,
@@ -163,6 +163,48 @@ pub mod util {
             }
         }
 
+        // Verify all individual signatures as a batch:
+        {
+            let public_keys: Vec<_> = (0..signatures.len())
+                .map(|index| {
+                    verifier
+                        .threshold_individual_public_key(
+                            AlgorithmId::ThresBls12_381,
+                            index as NodeIndex,
+                            public_coefficients.clone(),
+                        )
+                        .expect("Could not calculate individual public key")
+                })
+                .collect();
+            let mut batch: Vec<_> = signatures
+                .iter()
+                .zip(public_keys)
+                .map(|(signature, public_key)| (message.to_vec(), signature.clone(), public_key))
+                .collect();
+            assert_eq!(
+                verifier.threshold_verify_individual_signature_batch(
+                    AlgorithmId::ThresBls12_381,
+                    &batch
+                ),
+                Ok(()),
+                "Batch of individual signatures failed verification"
+            );
+
+            // A single incorrect message makes the batch fail:
+            if let Some(entry) = batch.last_mut() {
+                entry.0 = incorrect_message.clone();
+                assert!(
+                    verifier
+                        .threshold_verify_individual_signature_batch(
+                            AlgorithmId::ThresBls12_381,
+                            &batch
+                        )
+                        .is_err(),
+                    "Batch verification accepted an incorrect message"
+                );
+            }
+        }
+
         // Combine a random subset of signatures:
         let signature_selection = select_n(signature_selection_seed, threshold, &signatures);
         let signature = verifier
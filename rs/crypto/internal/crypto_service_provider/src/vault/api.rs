@@ -107,6 +107,19 @@ pub enum CspSecretKeyStoreContainsError {
     TransientInternalError { internal_error: String },
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CspSecretKeyStoreEncryptionKeyRotationError {
+    /// The secret key stores are not encrypted at rest.
+    EncryptionNotConfigured,
+    /// The key-encryption key could not be loaded from its configured source.
+    KeyEncryptionKeyLoadingError {
+        error: String,
+    },
+    TransientInternalError {
+        internal_error: String,
+    },
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CspPublicKeyStoreError {
     TransientInternalError(String),
@@ -626,6 +639,24 @@ pub trait SecretKeyStoreCspVault {
     /// # Arguments
     /// * `key_id` identifies the key whose presence should be checked.
     fn sks_contains(&self, key_id: &KeyId) -> Result<bool, CspSecretKeyStoreContainsError>;
+
+    /// Re-encrypts the node and the canister secret key store at rest with
+    /// the key-encryption key (KEK) that is currently provided by the
+    /// configured KEK source, and uses that KEK for all subsequent writes.
+    ///
+    /// This allows rotating the KEK without restarting the vault: the new KEK
+    /// is first installed at the configured source, and then this method is
+    /// called.
+    ///
+    /// # Errors
+    /// * `EncryptionNotConfigured` if the secret key stores are not encrypted at rest.
+    /// * `KeyEncryptionKeyLoadingError` if the KEK cannot be loaded from its source.
+    /// * `TransientInternalError` if a re-encrypted secret key store cannot be written,
+    ///   or if an RPC error occurs. The secret key store is then still sealed with
+    ///   its previous KEK.
+    fn rotate_secret_key_store_encryption_key(
+        &self,
+    ) -> Result<(), CspSecretKeyStoreEncryptionKeyRotationError>;
 }
 
 /// Operations of `CspVault` related to querying the public key store.
@@ -1,4 +1,5 @@
 use super::*;
+use ic_config::crypto::{KeyEncryptionKeySource, SecretKeyStoreEncryptionConfig};
 use rand::rngs::OsRng;
 
 pub struct LocalCspVaultBuilder<R, S, C, P> {
@@ -7,6 +8,7 @@ pub struct LocalCspVaultBuilder<R, S, C, P> {
     canister_secret_key_store: Box<dyn FnOnce() -> C>,
     public_key_store: Box<dyn FnOnce() -> P>,
     time_source: Arc<dyn TimeSource>,
+    secret_key_store_kek_source: Option<KeyEncryptionKeySource>,
     metrics: Arc<CryptoMetrics>,
     logger: ReplicaLogger,
 }
@@ -26,6 +28,7 @@ impl ProdLocalCspVault {
             canister_secret_key_store: Box::new(|| canister_secret_key_store),
             public_key_store: Box::new(|| public_key_store),
             time_source: Arc::new(CurrentSystemTimeSource::new(new_logger!(&logger))),
+            secret_key_store_kek_source: None,
             metrics,
             logger,
         }
@@ -36,16 +39,33 @@ impl ProdLocalCspVault {
         metrics: Arc<CryptoMetrics>,
         logger: ReplicaLogger,
     ) -> LocalCspVaultBuilder<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore, ProtoPublicKeyStore>
+    {
+        Self::builder_in_dir_with_encryption(key_store_dir, None, metrics, logger)
+    }
+
+    /// Like [`Self::builder_in_dir`], but the secret key stores are encrypted
+    /// at rest if `secret_key_store_encryption` is given.
+    pub fn builder_in_dir_with_encryption(
+        key_store_dir: &Path,
+        secret_key_store_encryption: Option<&SecretKeyStoreEncryptionConfig>,
+        metrics: Arc<CryptoMetrics>,
+        logger: ReplicaLogger,
+    ) -> LocalCspVaultBuilder<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore, ProtoPublicKeyStore>
     {
         const SKS_DATA_FILENAME: &str = "sks_data.pb";
         const PUBLIC_KEY_STORE_DATA_FILENAME: &str = "public_keys.pb";
         const CANISTER_SKS_DATA_FILENAME: &str = "canister_sks_data.pb";
 
-        let node_secret_key_store =
-            ProtoSecretKeyStore::open(key_store_dir, SKS_DATA_FILENAME, Some(new_logger!(logger)));
-        let canister_secret_key_store = ProtoSecretKeyStore::open(
+        let node_secret_key_store = ProtoSecretKeyStore::open_with_encryption_config(
+            key_store_dir,
+            SKS_DATA_FILENAME,
+            secret_key_store_encryption,
+            Some(new_logger!(logger)),
+        );
+        let canister_secret_key_store = ProtoSecretKeyStore::open_with_encryption_config(
             key_store_dir,
             CANISTER_SKS_DATA_FILENAME,
+            secret_key_store_encryption,
             Some(new_logger!(logger)),
         );
         let public_key_store = ProtoPublicKeyStore::open(
@@ -54,13 +74,17 @@ impl ProdLocalCspVault {
             new_logger!(logger),
         );
 
-        Self::builder(
+        let builder = Self::builder(
             node_secret_key_store,
             canister_secret_key_store,
             public_key_store,
             metrics,
             logger,
-        )
+        );
+        match secret_key_store_encryption {
+            Some(config) => builder.with_secret_key_store_kek_source(config.kek_source.clone()),
+            None => builder,
+        }
     }
 }
 
@@ -81,6 +105,7 @@ where
             canister_secret_key_store: self.canister_secret_key_store,
             public_key_store: self.public_key_store,
             time_source: self.time_source,
+            secret_key_store_kek_source: self.secret_key_store_kek_source,
             metrics: self.metrics,
             logger: self.logger,
         }
@@ -96,6 +121,7 @@ where
             canister_secret_key_store: self.canister_secret_key_store,
             public_key_store: self.public_key_store,
             time_source: self.time_source,
+            secret_key_store_kek_source: self.secret_key_store_kek_source,
             metrics: self.metrics,
             logger: self.logger,
         }
@@ -111,6 +137,7 @@ where
             canister_secret_key_store: Box::new(|| canister_secret_key_store),
             public_key_store: self.public_key_store,
             time_source: self.time_source,
+            secret_key_store_kek_source: self.secret_key_store_kek_source,
             metrics: self.metrics,
             logger: self.logger,
         }
@@ -126,6 +153,7 @@ where
             canister_secret_key_store: self.canister_secret_key_store,
             public_key_store: Box::new(|| public_key_store),
             time_source: self.time_source,
+            secret_key_store_kek_source: self.secret_key_store_kek_source,
             metrics: self.metrics,
             logger: self.logger,
         }
@@ -141,6 +169,17 @@ where
         self
     }
 
+    /// Sets the source from which the vault loads the key-encryption key when
+    /// the secret key stores are re-encrypted, see
+    /// [`SecretKeyStoreCspVault::rotate_secret_key_store_encryption_key`](crate::vault::api::SecretKeyStoreCspVault::rotate_secret_key_store_encryption_key).
+    pub fn with_secret_key_store_kek_source(
+        mut self,
+        secret_key_store_kek_source: KeyEncryptionKeySource,
+    ) -> Self {
+        self.secret_key_store_kek_source = Some(secret_key_store_kek_source);
+        self
+    }
+
     pub fn build(self) -> LocalCspVault<R, S, C, P> {
         LocalCspVault {
             csprng: CspRwLock::new_for_rng((self.csprng)(), Arc::clone(&self.metrics)),
@@ -157,6 +196,7 @@ where
                 Arc::clone(&self.metrics),
             ),
             time_source: self.time_source,
+            secret_key_store_kek_source: self.secret_key_store_kek_source,
             metrics: self.metrics,
             logger: self.logger,
         }
@@ -193,6 +233,7 @@ mod test_utils {
                 canister_secret_key_store: Box::new(|| TempSecretKeyStore::new()),
                 public_key_store: Box::new(|| TempPublicKeyStore::new()),
                 time_source: FastForwardTimeSource::new(),
+                secret_key_store_kek_source: None,
                 logger: no_op_logger(),
                 metrics: Arc::new(CryptoMetrics::none()),
             }
@@ -18,6 +18,7 @@ use crate::public_key_store::PublicKeyStore;
 use crate::secret_key_store::proto_store::ProtoSecretKeyStore;
 use crate::secret_key_store::SecretKeyStore;
 use crate::CspRwLock;
+use ic_config::crypto::{KeyEncryptionKeySource, SecretKeyStoreEncryptionConfig};
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_internal_seed::Seed;
 use ic_crypto_utils_time::CurrentSystemTimeSource;
@@ -79,6 +80,9 @@ pub struct LocalCspVault<
     canister_secret_key_store: CspRwLock<C>,
     public_key_store: CspRwLock<P>,
     time_source: Arc<dyn TimeSource>,
+    // The source of the key-encryption key with which the secret key stores
+    // are re-encrypted on request, if they are encrypted at rest.
+    secret_key_store_kek_source: Option<KeyEncryptionKeySource>,
     logger: ReplicaLogger,
     metrics: Arc<CryptoMetrics>,
 }
@@ -118,10 +122,17 @@ impl ProdLocalCspVault {
 
     pub fn new_in_dir(
         key_store_dir: &Path,
+        secret_key_store_encryption: Option<&SecretKeyStoreEncryptionConfig>,
         metrics: Arc<CryptoMetrics>,
         logger: ReplicaLogger,
     ) -> Self {
-        ProdLocalCspVault::builder_in_dir(key_store_dir, metrics, logger).build()
+        ProdLocalCspVault::builder_in_dir_with_encryption(
+            key_store_dir,
+            secret_key_store_encryption,
+            metrics,
+            logger,
+        )
+        .build()
     }
 }
 
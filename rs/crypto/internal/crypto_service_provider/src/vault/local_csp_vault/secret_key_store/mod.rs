@@ -1,6 +1,11 @@
 //! The crypto service provider API for querying secret keys.
 use crate::key_id::KeyId;
-use crate::vault::api::{CspSecretKeyStoreContainsError, SecretKeyStoreCspVault};
+use crate::secret_key_store::proto_store::KeyEncryptionKey;
+use crate::secret_key_store::SecretKeyStoreWriteError;
+use crate::vault::api::{
+    CspSecretKeyStoreContainsError, CspSecretKeyStoreEncryptionKeyRotationError,
+    SecretKeyStoreCspVault,
+};
 use crate::vault::local_csp_vault::LocalCspVault;
 use crate::SecretKeyStore;
 
@@ -16,4 +21,29 @@ impl<R: Rng + CryptoRng, S: SecretKeyStore, C: SecretKeyStore, P: PublicKeyStore
     fn sks_contains(&self, id: &KeyId) -> Result<bool, CspSecretKeyStoreContainsError> {
         Ok(self.sks_read_lock().contains(id))
     }
+
+    fn rotate_secret_key_store_encryption_key(
+        &self,
+    ) -> Result<(), CspSecretKeyStoreEncryptionKeyRotationError> {
+        let kek_source = self
+            .secret_key_store_kek_source
+            .as_ref()
+            .ok_or(CspSecretKeyStoreEncryptionKeyRotationError::EncryptionNotConfigured)?;
+        let kek = KeyEncryptionKey::load(kek_source).map_err(|e| {
+            CspSecretKeyStoreEncryptionKeyRotationError::KeyEncryptionKeyLoadingError {
+                error: e.to_string(),
+            }
+        })?;
+        let transient_error = |e: SecretKeyStoreWriteError| {
+            CspSecretKeyStoreEncryptionKeyRotationError::TransientInternalError {
+                internal_error: e.to_string(),
+            }
+        };
+        self.sks_write_lock()
+            .rotate_key_encryption_key(kek.clone())
+            .map_err(transient_error)?;
+        self.canister_sks_write_lock()
+            .rotate_key_encryption_key(kek)
+            .map_err(transient_error)
+    }
 }
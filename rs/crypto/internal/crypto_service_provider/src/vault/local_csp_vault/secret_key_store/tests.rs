@@ -1,11 +1,19 @@
 #![allow(clippy::unwrap_used)]
 //! Verifies the implementation of SecretKeyStoreCspVault for LocalCspVault.
+use crate::secret_key_store::mock_secret_key_store::MockSecretKeyStore;
+use crate::secret_key_store::proto_store::{KeyEncryptionKey, MIN_KEK_LEN};
+use crate::secret_key_store::SecretKeyStoreWriteError;
 use crate::vault::api::BasicSignatureCspVault;
+use crate::vault::api::CspSecretKeyStoreEncryptionKeyRotationError;
 use crate::vault::api::SecretKeyStoreCspVault;
 use crate::vault::api::TlsHandshakeCspVault;
 use crate::KeyId;
 use crate::LocalCspVault;
+use assert_matches::assert_matches;
+use ic_config::crypto::KeyEncryptionKeySource;
+use ic_crypto_secrets_containers::SecretBytes;
 use ic_types_test_utils::ids::node_test_id;
+use tempfile::TempDir;
 
 const NODE_1: u64 = 4241;
 const NOT_AFTER: &str = "99991231235959Z";
@@ -87,3 +95,105 @@ fn tls_key_should_be_present_only_after_generation() {
         "Key first CSP should not contain the TLS keys of the second."
     );
 }
+
+mod rotate_secret_key_store_encryption_key {
+    use super::*;
+
+    #[test]
+    fn should_fail_if_encryption_is_not_configured() {
+        let vault = LocalCspVault::builder_for_test().with_mock_stores().build();
+
+        let result = vault.rotate_secret_key_store_encryption_key();
+
+        assert_matches!(
+            result,
+            Err(CspSecretKeyStoreEncryptionKeyRotationError::EncryptionNotConfigured)
+        );
+    }
+
+    #[test]
+    fn should_fail_if_kek_cannot_be_loaded() {
+        let temp_dir = TempDir::new().expect("failed to create temp dir");
+        let vault = LocalCspVault::builder_for_test()
+            .with_mock_stores()
+            .with_secret_key_store_kek_source(KeyEncryptionKeySource::File {
+                path: temp_dir.path().join("missing_kek"),
+            })
+            .build();
+
+        let result = vault.rotate_secret_key_store_encryption_key();
+
+        assert_matches!(
+            result,
+            Err(CspSecretKeyStoreEncryptionKeyRotationError::KeyEncryptionKeyLoadingError { .. })
+        );
+    }
+
+    #[test]
+    fn should_reencrypt_node_and_canister_secret_key_stores_with_kek_from_source() {
+        let (_temp_dir, kek_source) = kek_file([1; MIN_KEK_LEN]);
+        let kek_id = *kek([1; MIN_KEK_LEN]).id();
+        let mut node_sks = MockSecretKeyStore::new();
+        node_sks
+            .expect_rotate_key_encryption_key()
+            .times(1)
+            .withf(move |kek| *kek.id() == kek_id)
+            .return_const(Ok(()));
+        let mut canister_sks = MockSecretKeyStore::new();
+        canister_sks
+            .expect_rotate_key_encryption_key()
+            .times(1)
+            .withf(move |kek| *kek.id() == kek_id)
+            .return_const(Ok(()));
+        let vault = LocalCspVault::builder_for_test()
+            .with_mock_stores()
+            .with_node_secret_key_store(node_sks)
+            .with_canister_secret_key_store(canister_sks)
+            .with_secret_key_store_kek_source(kek_source)
+            .build();
+
+        let result = vault.rotate_secret_key_store_encryption_key();
+
+        assert_matches!(result, Ok(()));
+    }
+
+    #[test]
+    fn should_not_reencrypt_canister_secret_key_store_if_node_secret_key_store_fails() {
+        let (_temp_dir, kek_source) = kek_file([1; MIN_KEK_LEN]);
+        let mut node_sks = MockSecretKeyStore::new();
+        node_sks
+            .expect_rotate_key_encryption_key()
+            .times(1)
+            .return_const(Err(SecretKeyStoreWriteError::TransientError(
+                "disk full".to_string(),
+            )));
+        let mut canister_sks = MockSecretKeyStore::new();
+        canister_sks.expect_rotate_key_encryption_key().never();
+        let vault = LocalCspVault::builder_for_test()
+            .with_mock_stores()
+            .with_node_secret_key_store(node_sks)
+            .with_canister_secret_key_store(canister_sks)
+            .with_secret_key_store_kek_source(kek_source)
+            .build();
+
+        let result = vault.rotate_secret_key_store_encryption_key();
+
+        assert_matches!(
+            result,
+            Err(CspSecretKeyStoreEncryptionKeyRotationError::TransientInternalError { internal_error })
+                if internal_error.contains("disk full")
+        );
+    }
+
+    fn kek_file(secret: [u8; MIN_KEK_LEN]) -> (TempDir, KeyEncryptionKeySource) {
+        let temp_dir = TempDir::new().expect("failed to create temp dir");
+        let path = temp_dir.path().join("kek");
+        std::fs::write(&path, secret).expect("failed to write KEK file");
+        (temp_dir, KeyEncryptionKeySource::File { path })
+    }
+
+    fn kek(secret: [u8; MIN_KEK_LEN]) -> KeyEncryptionKey {
+        KeyEncryptionKey::from_secret(SecretBytes::new(secret.to_vec()))
+            .expect("failed to create KEK")
+    }
+}
@@ -145,6 +145,7 @@ enum CspVaultMethod {
     LoadThresholdSigningKey,
     RetainThresholdKeysIfPresent,
     SksContains,
+    RotateSecretKeyStoreEncryptionKey,
     PksAndSksContains,
     ValidatePksAndSks,
     CurrentNodePublicKeys,
@@ -192,6 +193,10 @@ impl CspVaultMethod {
                 "retain_threshold_keys_if_present",
             ),
             CspVaultMethod::SksContains => (MetricsDomain::KeyManagement, "sks_contains"),
+            CspVaultMethod::RotateSecretKeyStoreEncryptionKey => (
+                MetricsDomain::KeyManagement,
+                "rotate_secret_key_store_encryption_key",
+            ),
             CspVaultMethod::PksAndSksContains => {
                 (MetricsDomain::KeyManagement, "pks_and_sks_contains")
             }
@@ -251,6 +256,9 @@ impl From<&TarpcCspVaultRequest> for CspVaultMethod {
             Req::LoadThresholdSigningKey { .. } => Method::LoadThresholdSigningKey,
             Req::RetainThresholdKeysIfPresent { .. } => Method::RetainThresholdKeysIfPresent,
             Req::SksContains { .. } => Method::SksContains,
+            Req::RotateSecretKeyStoreEncryptionKey { .. } => {
+                Method::RotateSecretKeyStoreEncryptionKey
+            }
             Req::PksAndSksContains { .. } => Method::PksAndSksContains,
             Req::ValidatePksAndSks { .. } => Method::ValidatePksAndSks,
             Req::CurrentNodePublicKeys { .. } => Method::CurrentNodePublicKeys,
@@ -289,6 +297,9 @@ impl From<&TarpcCspVaultResponse> for CspVaultMethod {
             Resp::LoadThresholdSigningKey { .. } => Method::LoadThresholdSigningKey,
             Resp::RetainThresholdKeysIfPresent { .. } => Method::RetainThresholdKeysIfPresent,
             Resp::SksContains { .. } => Method::SksContains,
+            Resp::RotateSecretKeyStoreEncryptionKey { .. } => {
+                Method::RotateSecretKeyStoreEncryptionKey
+            }
             Resp::PksAndSksContains { .. } => Method::PksAndSksContains,
             Resp::ValidatePksAndSks { .. } => Method::ValidatePksAndSks,
             Resp::CurrentNodePublicKeys { .. } => Method::CurrentNodePublicKeys,
@@ -3,7 +3,8 @@ use crate::types::{CspPop, CspPublicKey, CspSignature};
 use crate::vault::api::{
     CspBasicSignatureError, CspBasicSignatureKeygenError, CspMultiSignatureError,
     CspMultiSignatureKeygenError, CspPublicKeyStoreError, CspSecretKeyStoreContainsError,
-    CspTlsKeygenError, CspTlsSignError, PksAndSksContainsErrors, ValidatePksAndSksError,
+    CspSecretKeyStoreEncryptionKeyRotationError, CspTlsKeygenError, CspTlsSignError,
+    PksAndSksContainsErrors, ValidatePksAndSksError,
 };
 use ic_crypto_internal_seed::Seed;
 use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors;
@@ -39,6 +40,7 @@ mod tarpc_csp_vault_server;
 use crate::key_id::KeyId;
 pub use crate::vault::local_csp_vault::ProdLocalCspVault;
 use crate::ExternalPublicKeys;
+use ic_config::crypto::SecretKeyStoreEncryptionConfig;
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_node_key_validation::ValidNodePublicKeys;
 use std::sync::Arc;
@@ -127,6 +129,10 @@ pub trait TarpcCspVault {
     // Corresponds to `SecretKeyStoreCspVault.sks_contains()`.
     async fn sks_contains(key_id: KeyId) -> Result<bool, CspSecretKeyStoreContainsError>;
 
+    // Corresponds to `SecretKeyStoreCspVault.rotate_secret_key_store_encryption_key()`.
+    async fn rotate_secret_key_store_encryption_key(
+    ) -> Result<(), CspSecretKeyStoreEncryptionKeyRotationError>;
+
     // Corresponds to `PublicKeyStoreCspVault.current_node_public_keys()`.
     async fn current_node_public_keys() -> Result<CurrentNodePublicKeys, CspPublicKeyStoreError>;
 
@@ -232,11 +238,12 @@ pub trait TarpcCspVault {
 
 pub async fn run_csp_vault_server(
     sks_dir: &Path,
+    secret_key_store_encryption: Option<SecretKeyStoreEncryptionConfig>,
     listener: UnixListener,
     logger: ReplicaLogger,
     metrics: CryptoMetrics,
 ) {
-    let server = TarpcCspVaultServerImpl::builder(sks_dir)
+    let server = TarpcCspVaultServerImplBuilder::new(sks_dir, secret_key_store_encryption)
         .with_logger(logger)
         .with_metrics(Arc::new(metrics))
         .build(listener);
@@ -4,11 +4,11 @@ use crate::types::{CspPop, CspPublicKey, CspSignature};
 use crate::vault::api::{
     BasicSignatureCspVault, CspBasicSignatureError, CspBasicSignatureKeygenError,
     CspMultiSignatureError, CspMultiSignatureKeygenError, CspPublicKeyStoreError,
-    CspSecretKeyStoreContainsError, CspTlsKeygenError, CspTlsSignError, IDkgProtocolCspVault,
-    MultiSignatureCspVault, NiDkgCspVault, PksAndSksContainsErrors,
-    PublicAndSecretKeyStoreCspVault, PublicKeyStoreCspVault, PublicRandomSeedGenerator,
-    PublicRandomSeedGeneratorError, SecretKeyStoreCspVault, ThresholdEcdsaSignerCspVault,
-    ThresholdSignatureCspVault, ValidatePksAndSksError,
+    CspSecretKeyStoreContainsError, CspSecretKeyStoreEncryptionKeyRotationError, CspTlsKeygenError,
+    CspTlsSignError, IDkgProtocolCspVault, MultiSignatureCspVault, NiDkgCspVault,
+    PksAndSksContainsErrors, PublicAndSecretKeyStoreCspVault, PublicKeyStoreCspVault,
+    PublicRandomSeedGenerator, PublicRandomSeedGeneratorError, SecretKeyStoreCspVault,
+    ThresholdEcdsaSignerCspVault, ThresholdSignatureCspVault, ValidatePksAndSksError,
 };
 use crate::vault::remote_csp_vault::codec::{CspVaultClientObserver, ObservableCodec};
 use crate::vault::remote_csp_vault::{
@@ -342,6 +342,22 @@ impl SecretKeyStoreCspVault for RemoteCspVault {
             })
         })
     }
+
+    fn rotate_secret_key_store_encryption_key(
+        &self,
+    ) -> Result<(), CspSecretKeyStoreEncryptionKeyRotationError> {
+        self.tokio_block_on(
+            self.tarpc_csp_client
+                .rotate_secret_key_store_encryption_key(context_with_timeout(self.rpc_timeout)),
+        )
+        .unwrap_or_else(|rpc_error: tarpc::client::RpcError| {
+            Err(
+                CspSecretKeyStoreEncryptionKeyRotationError::TransientInternalError {
+                    internal_error: rpc_error.to_string(),
+                },
+            )
+        })
+    }
 }
 
 impl PublicKeyStoreCspVault for RemoteCspVault {
@@ -3,14 +3,16 @@ use crate::key_id::KeyId;
 use crate::types::{CspPop, CspPublicKey, CspSignature};
 use crate::vault::api::{
     CspBasicSignatureError, CspBasicSignatureKeygenError, CspMultiSignatureError,
-    CspMultiSignatureKeygenError, CspSecretKeyStoreContainsError, CspTlsKeygenError,
-    CspTlsSignError, PublicRandomSeedGeneratorError, ValidatePksAndSksError,
+    CspMultiSignatureKeygenError, CspSecretKeyStoreContainsError,
+    CspSecretKeyStoreEncryptionKeyRotationError, CspTlsKeygenError, CspTlsSignError,
+    PublicRandomSeedGeneratorError, ValidatePksAndSksError,
 };
 use crate::vault::api::{CspPublicKeyStoreError, CspVault};
 use crate::vault::local_csp_vault::{LocalCspVault, ProdLocalCspVault};
 use crate::vault::remote_csp_vault::{remote_vault_codec_builder, TarpcCspVault};
 use crate::vault::remote_csp_vault::{PksAndSksContainsErrors, FOUR_GIGA_BYTES};
 use crate::ExternalPublicKeys;
+use ic_config::crypto::SecretKeyStoreEncryptionConfig;
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_internal_seed::Seed;
 use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors::{
@@ -253,6 +255,15 @@ impl<C: CspVault + 'static> TarpcCspVault for TarpcCspVaultServerWorker<C> {
         execute_on_thread_pool(self.thread_pool_handle, job).await
     }
 
+    async fn rotate_secret_key_store_encryption_key(
+        self,
+        _: context::Context,
+    ) -> Result<(), CspSecretKeyStoreEncryptionKeyRotationError> {
+        let vault = self.local_csp_vault;
+        let job = move || vault.rotate_secret_key_store_encryption_key();
+        execute_on_thread_pool(self.thread_pool_handle, job).await
+    }
+
     // PublicKeyStoreCspVault-methods.
     async fn current_node_public_keys(
         self,
@@ -510,11 +521,15 @@ pub struct TarpcCspVaultServerImplBuilder<C> {
 }
 
 impl TarpcCspVaultServerImplBuilder<ProdLocalCspVault> {
-    pub fn new(key_store_dir: &Path) -> Self {
+    pub fn new(
+        key_store_dir: &Path,
+        secret_key_store_encryption: Option<SecretKeyStoreEncryptionConfig>,
+    ) -> Self {
         let key_store_path = key_store_dir.to_path_buf();
         let local_csp_vault_factory = Box::new(move |logger: &ReplicaLogger, metrics| {
             Arc::new(LocalCspVault::new_in_dir(
                 &key_store_path,
+                secret_key_store_encryption.as_ref(),
                 metrics,
                 new_logger!(logger),
             ))
@@ -576,7 +591,7 @@ impl<C: CspVault> TarpcCspVaultServerImplBuilder<C> {
 
 impl TarpcCspVaultServerImpl<ProdLocalCspVault> {
     pub fn builder(key_store_dir: &Path) -> TarpcCspVaultServerImplBuilder<ProdLocalCspVault> {
-        TarpcCspVaultServerImplBuilder::new(key_store_dir)
+        TarpcCspVaultServerImplBuilder::new(key_store_dir, None)
     }
 }
 
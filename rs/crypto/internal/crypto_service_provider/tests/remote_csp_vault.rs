@@ -494,6 +494,78 @@ mod secret_key_store_csp_vault {
     }
 }
 
+mod secret_key_store_encryption {
+    use super::*;
+    use ic_config::crypto::{CryptoConfig, KeyEncryptionKeySource, SecretKeyStoreEncryptionConfig};
+    use ic_crypto_internal_csp::secret_key_store::proto_store::{KeyEncryptionKey, MIN_KEK_LEN};
+    use ic_crypto_internal_csp::secret_key_store::SecretKeyStore;
+    use ic_crypto_internal_csp_proptest_utils::arb_csp_secret_key_store_encryption_key_rotation_error;
+    use ic_crypto_secrets_containers::SecretBytes;
+
+    proptest! {
+        #![proptest_config(proptest_config_for_delegation())]
+        #[test]
+        fn should_delegate_for_rotate_secret_key_store_encryption_key(
+            expected_result in maybe_err(any::<()>(), arb_csp_secret_key_store_encryption_key_rotation_error())
+        ) {
+            let mut local_vault = MockLocalCspVault::new();
+            local_vault
+                .expect_rotate_secret_key_store_encryption_key()
+                .times(1)
+                .return_const(expected_result.clone());
+            let env = RemoteVaultEnvironment::start_server_with_local_csp_vault(Arc::new(local_vault));
+            let remote_vault = env.new_vault_client();
+
+            let result = remote_vault.rotate_secret_key_store_encryption_key();
+
+            prop_assert_eq!(result, expected_result);
+        }
+    }
+
+    #[test]
+    fn should_reencrypt_secret_key_store_with_rotated_kek_via_remote_vault() {
+        let kek_dir = TempDir::new().expect("failed to create temp dir");
+        let kek_path = kek_dir.path().join("sks-kek");
+        std::fs::write(&kek_path, [1; MIN_KEK_LEN]).expect("failed to write KEK file");
+        let (config, _temp_dir) = CryptoConfig::new_in_temp_dir();
+        let local_vault = LocalCspVault::new_in_dir(
+            &config.crypto_root,
+            Some(&SecretKeyStoreEncryptionConfig {
+                kek_source: KeyEncryptionKeySource::File {
+                    path: kek_path.clone(),
+                },
+                previous_kek_source: None,
+            }),
+            Arc::new(CryptoMetrics::none()),
+            no_op_logger(),
+        );
+        let env = RemoteVaultEnvironment::start_server_with_local_csp_vault(Arc::new(local_vault));
+        let remote_vault = env.new_vault_client();
+        let node_signing_public_key = remote_vault
+            .gen_node_signing_key_pair()
+            .expect("failed generating node signing key pair");
+        let key_id = KeyId::try_from(&node_signing_public_key).unwrap();
+
+        std::fs::write(&kek_path, [2; MIN_KEK_LEN]).expect("failed to replace KEK file");
+        let result = remote_vault.rotate_secret_key_store_encryption_key();
+
+        assert_matches!(result, Ok(()));
+        let node_sks = ProtoSecretKeyStore::open_encrypted(
+            &config.crypto_root,
+            "sks_data.pb",
+            kek([2; MIN_KEK_LEN]),
+            None,
+            None,
+        );
+        assert!(node_sks.contains(&key_id));
+    }
+
+    fn kek(secret: [u8; MIN_KEK_LEN]) -> KeyEncryptionKey {
+        KeyEncryptionKey::from_secret(SecretBytes::new(secret.to_vec()))
+            .expect("failed to create KEK")
+    }
+}
+
 mod public_key_store_csp_vault {
     use super::*;
     use ic_crypto_internal_csp_proptest_utils::{
@@ -762,6 +834,7 @@ fn local_vault_in_temp_dir() -> (
     let (config, _temp_dir) = CryptoConfig::new_in_temp_dir();
     let local_vault = LocalCspVault::new_in_dir(
         &config.crypto_root,
+        config.secret_key_store_encryption.as_ref(),
         Arc::new(CryptoMetrics::none()),
         no_op_logger(),
     );
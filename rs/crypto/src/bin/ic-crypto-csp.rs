@@ -81,6 +81,7 @@ fn main() {
 
     rt.block_on(ic_crypto_internal_csp::run_csp_vault_server(
         sks_dir,
+        ic_config.crypto.secret_key_store_encryption.clone(),
         systemd_socket_listener,
         logger,
         metrics,
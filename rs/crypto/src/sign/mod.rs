@@ -350,6 +350,45 @@ impl<C: CryptoServiceProvider, H: Signable> MultiSigVerifier<H> for CryptoCompon
         result
     }
 
+    fn verify_multi_sig_individual_batch(
+        &self,
+        shares: &[(&IndividualMultiSigOf<H>, &H, NodeId)],
+        registry_version: RegistryVersion,
+    ) -> Vec<CryptoResult<()>> {
+        let log_id = get_log_id(&self.logger, module_path!());
+        let logger = new_logger!(&self.logger;
+            crypto.log_id => log_id,
+            crypto.trait_name => "MultiSigner",
+            crypto.method_name => "verify_multi_sig_individual_batch",
+        );
+        debug!(logger;
+            crypto.description => "start",
+            crypto.registry_version => registry_version.get(),
+            crypto.signer => format!("{:?}", shares.iter().map(|(_, _, signer)| signer).collect::<Vec<_>>()),
+        );
+        let start_time = self.metrics.now();
+        let results = MultiSigVerifierInternal::verify_multi_sig_individual_batch(
+            &self.csp,
+            self.registry_client.as_ref(),
+            shares,
+            registry_version,
+        );
+        let result: CryptoResult<()> = results.iter().cloned().collect();
+        self.metrics.observe_duration_seconds(
+            MetricsDomain::MultiSignature,
+            MetricsScope::Full,
+            "verify_multi_sig_individual_batch",
+            MetricsResult::from(&result),
+            start_time,
+        );
+        debug!(logger;
+            crypto.description => "end",
+            crypto.is_ok => result.is_ok(),
+            crypto.error => log_err(result.as_ref().err()),
+        );
+        results
+    }
+
     /// Combines a non-empty collection of individual signatures into a combined
     /// signature.
     fn combine_multi_sig_individuals(
@@ -522,6 +561,45 @@ impl<C: CryptoServiceProvider, T: Signable> ThresholdSigVerifier<T> for CryptoCo
         result
     }
 
+    fn verify_threshold_sig_share_batch(
+        &self,
+        shares: &[(&ThresholdSigShareOf<T>, &T, NodeId)],
+        dkg_id: NiDkgId,
+    ) -> Vec<CryptoResult<()>> {
+        let log_id = get_log_id(&self.logger, module_path!());
+        let logger = new_logger!(&self.logger;
+            crypto.log_id => log_id,
+            crypto.trait_name => "ThresholdSigVerifier",
+            crypto.method_name => "verify_threshold_sig_share_batch",
+        );
+        debug!(logger;
+            crypto.description => "start",
+            crypto.dkg_id => format!("{}", dkg_id),
+            crypto.signer => format!("{:?}", shares.iter().map(|(_, _, signer)| signer).collect::<Vec<_>>()),
+        );
+        let start_time = self.metrics.now();
+        let results = ThresholdSigVerifierInternal::verify_threshold_sig_share_batch(
+            &self.lockable_threshold_sig_data_store,
+            &self.csp,
+            shares,
+            dkg_id,
+        );
+        let result: CryptoResult<()> = results.iter().cloned().collect();
+        self.metrics.observe_duration_seconds(
+            MetricsDomain::ThresholdSignature,
+            MetricsScope::Full,
+            "verify_threshold_sig_share_batch",
+            MetricsResult::from(&result),
+            start_time,
+        );
+        debug!(logger;
+            crypto.description => "end",
+            crypto.is_ok => result.is_ok(),
+            crypto.error => log_err(result.as_ref().err()),
+        );
+        results
+    }
+
     fn combine_threshold_sig_shares(
         &self,
         shares: BTreeMap<NodeId, ThresholdSigShareOf<T>>,
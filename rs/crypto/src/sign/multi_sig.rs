@@ -1,5 +1,5 @@
 use super::*;
-use ic_crypto_internal_csp::api::CspSigner;
+use ic_crypto_internal_csp::api::{CspSigVerifier, CspSigner};
 use ic_crypto_internal_csp::key_id::KeyId;
 
 #[cfg(test)]
@@ -25,6 +25,40 @@ impl MultiSigVerifierInternal {
         csp_signer.verify(&csp_sig, &message_bytes, algorithm_id, csp_pubkey)
     }
 
+    /// Verifies a batch of individual signatures, returning one result per
+    /// entry of `shares` in the same order.
+    ///
+    /// The batch is first verified as a whole. Only if that fails (or if the
+    /// batch cannot be assembled, e.g., because a key is missing in the
+    /// registry) the signatures are verified one by one to find out which of
+    /// them are at fault.
+    pub fn verify_multi_sig_individual_batch<S: CspSigner + CspSigVerifier, H: Signable>(
+        csp: &S,
+        registry: &dyn RegistryClient,
+        shares: &[(&IndividualMultiSigOf<H>, &H, NodeId)],
+        registry_version: RegistryVersion,
+    ) -> Vec<CryptoResult<()>> {
+        if shares.len() > 1
+            && verify_multi_sig_individual_batch_at_once(csp, registry, shares, registry_version)
+                .is_ok()
+        {
+            return shares.iter().map(|_| Ok(())).collect();
+        }
+        shares
+            .iter()
+            .map(|(signature, message, signer)| {
+                Self::verify_multi_sig_individual(
+                    csp,
+                    registry,
+                    signature,
+                    message,
+                    *signer,
+                    registry_version,
+                )
+            })
+            .collect()
+    }
+
     /// Combines a non-empty collection of individual signatures into a combined
     /// signature.
     pub fn combine_multi_sig_individuals<S: CspSigner, H: Signable>(
@@ -79,6 +113,41 @@ impl MultiSigVerifierInternal {
     }
 }
 
+fn verify_multi_sig_individual_batch_at_once<S: CspSigVerifier, H: Signable>(
+    csp_sig_verifier: &S,
+    registry: &dyn RegistryClient,
+    shares: &[(&IndividualMultiSigOf<H>, &H, NodeId)],
+    registry_version: RegistryVersion,
+) -> CryptoResult<()> {
+    let mut algorithm_set = BTreeSet::<AlgorithmId>::new();
+    let sigs_pks_msgs: CryptoResult<Vec<(CspSignature, CspPublicKey, Vec<u8>)>> = shares
+        .iter()
+        .map(|(signature, message, signer)| {
+            let pk_proto =
+                key_from_registry(registry, *signer, CommitteeSigning, registry_version)?;
+            algorithm_set.insert(AlgorithmId::from(pk_proto.algorithm));
+            Ok((
+                CspSignature::try_from(*signature)?,
+                CspPublicKey::try_from(pk_proto)?,
+                message.as_signed_bytes(),
+            ))
+        })
+        .collect();
+    let sigs_pks_msgs = sigs_pks_msgs?;
+    if algorithm_set.len() != 1 {
+        return Err(CryptoError::InconsistentAlgorithms {
+            algorithms: algorithm_set,
+            key_purpose: CommitteeSigning,
+            registry_version,
+        });
+    }
+    let algorithm = algorithm_set
+        .into_iter()
+        .next()
+        .expect("the set contains exactly one algorithm");
+    csp_sig_verifier.verify_multisig_individual_batch(&sigs_pks_msgs, algorithm)
+}
+
 /// This helper method performs the following two tasks:
 /// 1. maps node IDs to CSP public keys by querying the registry
 /// 2. ensures that the public keys' algorithm IDs are all equal
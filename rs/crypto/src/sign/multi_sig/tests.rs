@@ -59,6 +59,57 @@ mod test_multi_sig_verification {
         assert_matches!(result, Ok(()));
     }
 
+    #[test]
+    fn should_verify_multi_sig_individual_batch_and_identify_invalid_signature() {
+        let mut rng = reproducible_rng();
+        let registry_data = Arc::new(ProtoRegistryDataProvider::new());
+        let registry_client =
+            Arc::new(FakeRegistryClient::new(Arc::clone(&registry_data) as Arc<_>));
+        let crypto_1 = TempCryptoComponent::builder()
+            .with_keys_in_registry_version(NodeKeysToGenerate::only_committee_signing_key(), REG_V2)
+            .with_registry_client_and_data(
+                Arc::clone(&registry_client) as Arc<_>,
+                Arc::clone(&registry_data) as Arc<_>,
+            )
+            .with_node_id(NODE_1)
+            .with_rng(rng.fork())
+            .build();
+        let crypto_2 = TempCryptoComponent::builder()
+            .with_keys_in_registry_version(NodeKeysToGenerate::only_committee_signing_key(), REG_V2)
+            .with_registry_client_and_data(
+                Arc::clone(&registry_client) as Arc<_>,
+                Arc::clone(&registry_data) as Arc<_>,
+            )
+            .with_node_id(NODE_2)
+            .with_rng(rng)
+            .build();
+        registry_client.reload();
+
+        let msg_1 = SignableMock::new(b"Hello World!".to_vec());
+        let msg_2 = SignableMock::new(b"Goodbye World!".to_vec());
+        let sig_node1_on_msg1 = crypto_1.sign_multi(&msg_1, NODE_1, REG_V2).unwrap();
+        let sig_node2_on_msg2 = crypto_2.sign_multi(&msg_2, NODE_2, REG_V2).unwrap();
+
+        let results = crypto_1.verify_multi_sig_individual_batch(
+            &[
+                (&sig_node1_on_msg1, &msg_1, NODE_1),
+                (&sig_node2_on_msg2, &msg_2, NODE_2),
+            ],
+            REG_V2,
+        );
+        assert_eq!(results, vec![Ok(()), Ok(())]);
+
+        let results = crypto_1.verify_multi_sig_individual_batch(
+            &[
+                (&sig_node1_on_msg1, &msg_1, NODE_1),
+                (&sig_node2_on_msg2, &msg_1, NODE_2),
+            ],
+            REG_V2,
+        );
+        assert_matches!(results[0], Ok(()));
+        assert_matches!(results[1], Err(CryptoError::SignatureVerification { .. }));
+    }
+
     #[test]
     fn should_combine_and_verify_multi_sig_individuals() {
         let mut rng = reproducible_rng();
@@ -157,6 +157,90 @@ impl ThresholdSigVerifierInternal {
     }
 }
 
+impl ThresholdSigVerifierInternal {
+    /// Verifies a batch of threshold signature shares, returning one result
+    /// per entry of `shares` in the same order.
+    ///
+    /// The batch is first verified as a whole. Only if that fails (or if the
+    /// batch cannot be assembled, e.g., because the transcript data for the
+    /// `dkg_id` is missing) the shares are verified one by one to find out
+    /// which of them are at fault.
+    ///
+    /// # Panics
+    /// See [`Self::verify_threshold_sig_share`].
+    pub fn verify_threshold_sig_share_batch<C: ThresholdSignatureCspClient, H: Signable>(
+        lockable_threshold_sig_data_store: &LockableThresholdSigDataStore,
+        threshold_sig_csp_client: &C,
+        shares: &[(&ThresholdSigShareOf<H>, &H, NodeId)],
+        dkg_id: NiDkgId,
+    ) -> Vec<CryptoResult<()>> {
+        if shares.len() > 1
+            && verify_threshold_sig_share_batch_at_once(
+                lockable_threshold_sig_data_store,
+                threshold_sig_csp_client,
+                shares,
+                dkg_id,
+            )
+            .is_ok()
+        {
+            return shares.iter().map(|_| Ok(())).collect();
+        }
+        shares
+            .iter()
+            .map(|(signature, message, signer)| {
+                Self::verify_threshold_sig_share(
+                    lockable_threshold_sig_data_store,
+                    threshold_sig_csp_client,
+                    signature,
+                    message,
+                    dkg_id,
+                    *signer,
+                )
+            })
+            .collect()
+    }
+}
+
+fn verify_threshold_sig_share_batch_at_once<C: ThresholdSignatureCspClient, H: Signable>(
+    lockable_threshold_sig_data_store: &LockableThresholdSigDataStore,
+    threshold_sig_csp_client: &C,
+    shares: &[(&ThresholdSigShareOf<H>, &H, NodeId)],
+    dkg_id: NiDkgId,
+) -> CryptoResult<()> {
+    let msgs_sigs_pks: CryptoResult<Vec<(Vec<u8>, CspSignature, CspThresholdSigPublicKey)>> =
+        shares
+            .iter()
+            .map(|(signature, message, signer)| {
+                let public_key = lazily_calculated_public_key_from_store(
+                    lockable_threshold_sig_data_store,
+                    threshold_sig_csp_client,
+                    dkg_id,
+                    *signer,
+                )?;
+                Ok((
+                    message.as_signed_bytes(),
+                    CspSignature::try_from(*signature)?,
+                    public_key,
+                ))
+            })
+            .collect();
+    let msgs_sigs_pks = msgs_sigs_pks?;
+    let algorithms: BTreeSet<AlgorithmId> = msgs_sigs_pks
+        .iter()
+        .map(|(_, _, public_key)| AlgorithmId::from(*public_key))
+        .collect();
+    match algorithms.iter().next() {
+        Some(algorithm_id) if algorithms.len() == 1 => threshold_sig_csp_client
+            .threshold_verify_individual_signature_batch(*algorithm_id, &msgs_sigs_pks),
+        _ => Err(CryptoError::InvalidArgument {
+            message: format!(
+                "Expected exactly one algorithm for the batch but found {:?}",
+                algorithms
+            ),
+        }),
+    }
+}
+
 /// Returns the individual public key for the given `node_id` and `dkg_id` from
 /// the store if present and otherwise calculates and stores it.
 ///
@@ -1,7 +1,13 @@
 use ic_types::crypto::{threshold_sig::IcRootOfTrust, AlgorithmId, CryptoError, CryptoResult};
 
+mod request_sig;
 mod sign_utils;
 
+pub use request_sig::{
+    check_delegation_chain, verify_delegation_chain, verify_request_sig, verify_user_sig,
+    DelegationChainError, DelegationTargets, RequestSignatureVerificationError,
+    MAXIMUM_NUMBER_OF_DELEGATIONS, MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION,
+};
 pub use sign_utils::{
     ecdsa_p256_signature_from_der_bytes, ed25519_public_key_to_der, rsa_signature_from_bytes,
     user_public_key_from_bytes, KeyBytesContentType,
@@ -0,0 +1,528 @@
+//! Verification of request signatures and delegation chains
+//!
+//! This mirrors the authentication performed by the replica's HTTP endpoint
+//! (see the `ic-validator` crate), but does not depend on a crypto component,
+//! the registry, or the system time, so that it can also be used outside the
+//! replica, e.g., when compiled to `wasm32`. The limits and the checks on the
+//! structure of delegation chains are shared with `ic-validator`, see
+//! [`check_delegation_chain`].
+use crate::{
+    ecdsa_p256_signature_from_der_bytes, rsa_signature_from_bytes, user_public_key_from_bytes,
+    verify_basic_sig_by_public_key, verify_canister_sig, KeyBytesContentType,
+};
+use ic_types::crypto::threshold_sig::IcRootOfTrust;
+use ic_types::crypto::{AlgorithmId, CryptoError, CryptoResult, Signable, UserPublicKey};
+use ic_types::messages::{
+    MessageId, SignedDelegation, UserSignature, WebAuthnEnvelope, WebAuthnSignature,
+};
+use ic_types::{CanisterId, PrincipalId, Time, UserId};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+/// Maximum number of delegations allowed in an `HttpRequest`.
+/// Requests having more delegations will be declared invalid without further verifying whether
+/// the delegation chain is correctly signed.
+/// **Note**: this limit is currently more generous than the one in the [IC specification](https://internetcomputer.org/docs/current/references/ic-interface-spec#authentication),
+/// which specifies a maximum of 4 delegations, in order to prevent potentially breaking already deployed applications
+/// since this limit was before (wrongly) not enforced.
+/// This limit will be tightened up once the number of delegations can be observed (via metrics or logs),
+/// see CRP-1961.
+pub const MAXIMUM_NUMBER_OF_DELEGATIONS: usize = 20;
+
+/// Maximum number of targets (collection of `CanisterId`s) that can be specified in a
+/// single delegation. Requests having a single delegation with more targets will be declared
+/// invalid without any further verification.
+/// **Note**: this limit part of the [IC specification](https://internetcomputer.org/docs/current/references/ic-interface-spec#authentication)
+/// and so changing this value might be breaking or result in a deviation from the specification.
+pub const MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION: usize = 1_000;
+
+/// The canister IDs that a request authenticated via a delegation chain may
+/// target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DelegationTargets {
+    /// None of the delegations restricts the targets.
+    All,
+    /// The intersection of the targets of all delegations that specify targets.
+    Some(BTreeSet<CanisterId>),
+}
+
+impl DelegationTargets {
+    /// Returns true iff `canister_id` is one of the targets.
+    pub fn contains(&self, canister_id: &CanisterId) -> bool {
+        match self {
+            DelegationTargets::All => true,
+            DelegationTargets::Some(targets) => targets.contains(canister_id),
+        }
+    }
+
+    fn intersect(self, other: Self) -> Self {
+        match (self, other) {
+            (DelegationTargets::All, other) => other,
+            (this, DelegationTargets::All) => this,
+            (DelegationTargets::Some(this), DelegationTargets::Some(other)) => {
+                DelegationTargets::Some(this.intersection(&other).cloned().collect())
+            }
+        }
+    }
+}
+
+/// Errors that can occur when verifying a request signature together with
+/// its delegation chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RequestSignatureVerificationError {
+    /// The sender is not the self-authenticating principal of the public key.
+    UserIdDoesNotMatchPublicKey(UserId, Vec<u8>),
+    /// The request of a sender other than the anonymous user is not signed.
+    MissingSignature(UserId),
+    /// The request of the anonymous user is signed.
+    AnonymousSignatureNotAllowed,
+    /// The signature on the request is invalid.
+    InvalidSignature(CryptoError),
+    /// The signature on the delegation at `index` is invalid.
+    InvalidDelegation { index: usize, error: CryptoError },
+    /// The delegation at `index` has expired.
+    DelegationExpired {
+        index: usize,
+        expiration: Time,
+        current_time: Time,
+    },
+    /// The chain of delegations is too long.
+    DelegationTooLong { length: usize, maximum: usize },
+    /// The chain of delegations contains a cycle, i.e., a public key that was
+    /// already encountered before in the chain.
+    DelegationContainsCycles { public_key: Vec<u8> },
+    /// The targets of the delegation at `index` are invalid.
+    DelegationTargetError { index: usize, error: String },
+    /// The request targets a canister that is not one of the delegation
+    /// targets.
+    CanisterNotInDelegationTargets(CanisterId),
+}
+
+impl std::error::Error for RequestSignatureVerificationError {}
+
+impl fmt::Display for RequestSignatureVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestSignatureVerificationError::UserIdDoesNotMatchPublicKey(user_id, pubkey) => {
+                write!(
+                    f,
+                    "The user id {} does not match the public key {}",
+                    user_id,
+                    hex::encode(pubkey)
+                )
+            }
+            RequestSignatureVerificationError::MissingSignature(user_id) => {
+                write!(f, "Missing signature from user: {}", user_id)
+            }
+            RequestSignatureVerificationError::AnonymousSignatureNotAllowed => {
+                write!(f, "Signature is not allowed for the anonymous user")
+            }
+            RequestSignatureVerificationError::InvalidSignature(err) => {
+                write!(f, "Invalid signature: {}", err)
+            }
+            RequestSignatureVerificationError::InvalidDelegation { index, error } => {
+                write!(f, "Invalid delegation at index {}: {}", index, error)
+            }
+            RequestSignatureVerificationError::DelegationExpired {
+                index,
+                expiration,
+                current_time,
+            } => write!(
+                f,
+                "Delegation at index {} has expired: expiration {}, current time {}",
+                index, expiration, current_time
+            ),
+            RequestSignatureVerificationError::DelegationTooLong { length, maximum } => write!(
+                f,
+                "Chain of delegations is too long: got {} delegations, but at most {} are allowed",
+                length, maximum
+            ),
+            RequestSignatureVerificationError::DelegationContainsCycles { public_key } => write!(
+                f,
+                "Chain of delegations contains at least one cycle: first repeating public key encountered {}",
+                hex::encode(public_key)
+            ),
+            RequestSignatureVerificationError::DelegationTargetError { index, error } => {
+                write!(f, "Invalid targets in delegation at index {}: {}", index, error)
+            }
+            RequestSignatureVerificationError::CanisterNotInDelegationTargets(canister_id) => {
+                write!(
+                    f,
+                    "Canister {} is not one of the delegation targets",
+                    canister_id
+                )
+            }
+        }
+    }
+}
+
+/// Errors in the structure of a chain of delegations, which are detected
+/// without verifying any signature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DelegationChainError {
+    /// The chain has more than [`MAXIMUM_NUMBER_OF_DELEGATIONS`] delegations.
+    TooLong { length: usize, maximum: usize },
+    /// The delegation at `index` has expired.
+    Expired {
+        index: usize,
+        expiration: Time,
+        current_time: Time,
+    },
+    /// The chain contains a public key that was already encountered before.
+    ContainsCycles { public_key: Vec<u8> },
+    /// The delegation at `index` has more than
+    /// [`MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION`] targets.
+    TooManyTargets {
+        index: usize,
+        number_of_targets: usize,
+        maximum: usize,
+    },
+}
+
+impl From<DelegationChainError> for RequestSignatureVerificationError {
+    fn from(err: DelegationChainError) -> Self {
+        match err {
+            DelegationChainError::TooLong { length, maximum } => {
+                RequestSignatureVerificationError::DelegationTooLong { length, maximum }
+            }
+            DelegationChainError::Expired {
+                index,
+                expiration,
+                current_time,
+            } => RequestSignatureVerificationError::DelegationExpired {
+                index,
+                expiration,
+                current_time,
+            },
+            DelegationChainError::ContainsCycles { public_key } => {
+                RequestSignatureVerificationError::DelegationContainsCycles { public_key }
+            }
+            DelegationChainError::TooManyTargets {
+                index,
+                number_of_targets,
+                maximum,
+            } => RequestSignatureVerificationError::DelegationTargetError {
+                index,
+                error: format!(
+                    "expected at most {} targets per delegation, but got {}",
+                    maximum, number_of_targets
+                ),
+            },
+        }
+    }
+}
+
+/// Verifies that the request with ID `message_id` was sent by `sender`, where
+/// `signature` contains the signature on the request, the DER-encoded public
+/// key of the sender and the (possibly empty) chain of delegations from that
+/// public key to the key that signed the request.
+///
+/// This performs the same checks as the replica's HTTP endpoint:
+/// * requests of the anonymous user must not be signed, all other requests
+///   must be signed,
+/// * `sender` is the self-authenticating principal of the public key,
+/// * the chain of delegations is not too long, does not contain cycles, and
+///   none of its delegations has too many targets,
+/// * none of the delegations has expired relative to `current_time`,
+/// * every delegation is signed by the public key of the previous one (or
+///   the sender's public key for the first one),
+/// * the request is signed by the public key of the last delegation (or
+///   the sender's public key if there are no delegations),
+/// * if `target` is given, it is contained in the targets of all delegations.
+///
+/// The sender's public key and the public keys in delegations may be
+/// * Ed25519, ECDSA P-256 or ECDSA secp256k1 keys in DER,
+/// * ECDSA P-256 or RSA PKCS #1 v1.5 keys in COSE, wrapped in DER, in which
+///   case the signatures must be WebAuthn signatures,
+/// * canister signature keys in DER,
+/// * RSA PKCS #1 v1.5 keys in DER, which are only allowed for delegations but
+///   not for signing requests directly.
+///
+/// Returns the canister IDs that the request may target.
+pub fn verify_request_sig<R: AsRef<IcRootOfTrust>>(
+    message_id: &MessageId,
+    sender: &UserId,
+    signature: Option<&UserSignature>,
+    target: Option<&CanisterId>,
+    current_time: Time,
+    root_of_trust: R,
+) -> Result<DelegationTargets, RequestSignatureVerificationError> {
+    let signature = match signature {
+        None if sender.get().is_anonymous() => return Ok(DelegationTargets::All),
+        None => return Err(RequestSignatureVerificationError::MissingSignature(*sender)),
+        Some(_) if sender.get().is_anonymous() => {
+            return Err(RequestSignatureVerificationError::AnonymousSignatureNotAllowed)
+        }
+        Some(signature) => signature,
+    };
+    if sender.get_ref() != &PrincipalId::new_self_authenticating(&signature.signer_pubkey) {
+        return Err(
+            RequestSignatureVerificationError::UserIdDoesNotMatchPublicKey(
+                *sender,
+                signature.signer_pubkey.clone(),
+            ),
+        );
+    }
+
+    let root_of_trust = root_of_trust.as_ref();
+    let (signing_pubkey, targets) = verify_delegation_chain(
+        &signature.signer_pubkey,
+        signature.sender_delegation.as_deref().unwrap_or_default(),
+        current_time,
+        root_of_trust,
+    )?;
+    verify_sig_by_public_key_der(
+        &message_id.as_signed_bytes(),
+        &signature.signature,
+        &signing_pubkey,
+        root_of_trust,
+        PlainRsa::Forbidden,
+    )
+    .map_err(RequestSignatureVerificationError::InvalidSignature)?;
+    match target {
+        Some(canister_id) if !targets.contains(canister_id) => {
+            Err(RequestSignatureVerificationError::CanisterNotInDelegationTargets(*canister_id))
+        }
+        _ => Ok(targets),
+    }
+}
+
+/// Verifies a chain of delegations rooted at the DER-encoded public key
+/// `sender_pubkey`.
+///
+/// See [`verify_request_sig`] for the checks that are performed.
+///
+/// Returns the DER-encoded public key of the last delegation (or
+/// `sender_pubkey` if there are no delegations), which is the key that is
+/// authorized to sign requests, together with the canister IDs that such
+/// requests may target.
+pub fn verify_delegation_chain<R: AsRef<IcRootOfTrust>>(
+    sender_pubkey: &[u8],
+    sender_delegation: &[SignedDelegation],
+    current_time: Time,
+    root_of_trust: R,
+) -> Result<(Vec<u8>, DelegationTargets), RequestSignatureVerificationError> {
+    let root_of_trust = root_of_trust.as_ref();
+    check_delegation_chain(sender_pubkey, sender_delegation, current_time)?;
+
+    let mut pubkey = sender_pubkey.to_vec();
+    let mut targets = DelegationTargets::All;
+    for (index, signed_delegation) in sender_delegation.iter().enumerate() {
+        let delegation = signed_delegation.delegation();
+        verify_sig_by_public_key_der(
+            &delegation.as_signed_bytes(),
+            &signed_delegation.signature().0,
+            &pubkey,
+            root_of_trust,
+            PlainRsa::Allowed,
+        )
+        .map_err(|error| RequestSignatureVerificationError::InvalidDelegation { index, error })?;
+        let delegation_targets = match delegation.targets().map_err(|error| {
+            RequestSignatureVerificationError::DelegationTargetError { index, error }
+        })? {
+            None => DelegationTargets::All,
+            Some(canister_ids) => DelegationTargets::Some(canister_ids),
+        };
+        targets = targets.intersect(delegation_targets);
+        pubkey = delegation.pubkey().to_vec();
+    }
+    Ok((pubkey, targets))
+}
+
+/// Checks the structure of a chain of delegations rooted at the DER-encoded
+/// public key `sender_pubkey`, without verifying any signature:
+/// * the chain has at most [`MAXIMUM_NUMBER_OF_DELEGATIONS`] delegations,
+/// * none of the delegations has expired relative to `current_time`,
+/// * the chain does not contain cycles,
+/// * none of the delegations has more than
+///   [`MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION`] targets.
+///
+/// The checks are performed in this order, which is also the one of the
+/// replica's HTTP endpoint.
+pub fn check_delegation_chain(
+    sender_pubkey: &[u8],
+    sender_delegation: &[SignedDelegation],
+    current_time: Time,
+) -> Result<(), DelegationChainError> {
+    if sender_delegation.len() > MAXIMUM_NUMBER_OF_DELEGATIONS {
+        return Err(DelegationChainError::TooLong {
+            length: sender_delegation.len(),
+            maximum: MAXIMUM_NUMBER_OF_DELEGATIONS,
+        });
+    }
+    for (index, signed_delegation) in sender_delegation.iter().enumerate() {
+        let expiration = signed_delegation.delegation().expiration();
+        if expiration < current_time {
+            return Err(DelegationChainError::Expired {
+                index,
+                expiration,
+                current_time,
+            });
+        }
+    }
+    ensure_no_cycles(sender_pubkey, sender_delegation)?;
+    ensure_not_too_many_targets(sender_delegation)
+}
+
+/// Verifies the signature `signature` on `signed_bytes` with the DER-encoded
+/// public key `public_key_der`.
+///
+/// The signature scheme is determined from the public key: plain signatures
+/// are verified for Ed25519, ECDSA P-256, ECDSA secp256k1 and RSA PKCS #1 v1.5
+/// keys in DER, WebAuthn signatures for COSE keys wrapped in DER, and canister
+/// signatures (verified against `root_of_trust`) for canister signature keys.
+/// For WebAuthn signatures, the challenge must be equal to `signed_bytes`.
+///
+/// # Errors
+/// * `CryptoError::MalformedPublicKey`: if the public key cannot be parsed.
+/// * `CryptoError::MalformedSignature`: if the signature cannot be parsed.
+/// * `CryptoError::SignatureVerification`: if the signature is invalid.
+/// * `CryptoError::AlgorithmNotSupported`: if the key's algorithm is not
+///   supported for the signature scheme.
+pub fn verify_user_sig<R: AsRef<IcRootOfTrust>>(
+    signed_bytes: &[u8],
+    signature: &[u8],
+    public_key_der: &[u8],
+    root_of_trust: R,
+) -> CryptoResult<()> {
+    verify_sig_by_public_key_der(
+        signed_bytes,
+        signature,
+        public_key_der,
+        root_of_trust.as_ref(),
+        PlainRsa::Allowed,
+    )
+}
+
+/// Whether RSA keys are allowed outside a WebAuthn context.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PlainRsa {
+    Allowed,
+    Forbidden,
+}
+
+fn verify_sig_by_public_key_der(
+    signed_bytes: &[u8],
+    signature: &[u8],
+    public_key_der: &[u8],
+    root_of_trust: &IcRootOfTrust,
+    plain_rsa: PlainRsa,
+) -> CryptoResult<()> {
+    let (public_key, content_type) = user_public_key_from_bytes(public_key_der)?;
+    match content_type {
+        KeyBytesContentType::EcdsaP256PublicKeyDerWrappedCose
+        | KeyBytesContentType::RsaSha256PublicKeyDerWrappedCose => {
+            verify_webauthn_sig(signed_bytes, signature, &public_key)
+        }
+        KeyBytesContentType::Ed25519PublicKeyDer
+        | KeyBytesContentType::EcdsaP256PublicKeyDer
+        | KeyBytesContentType::EcdsaSecp256k1PublicKeyDer => verify_basic_sig_by_public_key(
+            public_key.algorithm_id,
+            signed_bytes,
+            signature,
+            &public_key.key,
+        ),
+        KeyBytesContentType::RsaSha256PublicKeyDer => match plain_rsa {
+            PlainRsa::Allowed => verify_basic_sig_by_public_key(
+                public_key.algorithm_id,
+                signed_bytes,
+                signature,
+                &public_key.key,
+            ),
+            PlainRsa::Forbidden => Err(CryptoError::AlgorithmNotSupported {
+                algorithm: AlgorithmId::RsaSha256,
+                reason: "RSA signatures are not allowed except in webauthn context".to_string(),
+            }),
+        },
+        KeyBytesContentType::IcCanisterSignatureAlgPublicKeyDer => {
+            verify_canister_sig(signed_bytes, signature, &public_key.key, root_of_trust)
+        }
+    }
+}
+
+/// Verifies that the WebAuthn signature `signature` is valid w.r.t.
+/// `public_key` and that its challenge is `signed_bytes`.
+fn verify_webauthn_sig(
+    signed_bytes: &[u8],
+    signature: &[u8],
+    public_key: &UserPublicKey,
+) -> CryptoResult<()> {
+    let algorithm = public_key.algorithm_id;
+    let malformed_signature = |internal_error: String| CryptoError::MalformedSignature {
+        algorithm,
+        sig_bytes: signature.to_vec(),
+        internal_error,
+    };
+    let webauthn_sig = WebAuthnSignature::try_from(signature).map_err(malformed_signature)?;
+    let basic_sig = match algorithm {
+        // ECDSA signatures are DER wrapped, see https://www.w3.org/TR/webauthn-2/#sctn-signature-attestation-types
+        AlgorithmId::EcdsaP256 => ecdsa_p256_signature_from_der_bytes(&webauthn_sig.signature().0)?,
+        // RSA signatures are not DER wrapped, see https://www.w3.org/TR/webauthn-2/#sctn-signature-attestation-types
+        AlgorithmId::RsaSha256 => rsa_signature_from_bytes(&webauthn_sig.signature().0),
+        _ => {
+            return Err(CryptoError::AlgorithmNotSupported {
+                algorithm,
+                reason: "Only ECDSA on curve P-256 and RSA PKCS #1 v1.5 are supported for WebAuthn"
+                    .to_string(),
+            })
+        }
+    };
+    let envelope = WebAuthnEnvelope::try_from(&webauthn_sig)
+        .map_err(|e| malformed_signature(format!("WebAuthn envelope creation failed: {}", e)))?;
+    verify_basic_sig_by_public_key(
+        algorithm,
+        &envelope.as_signed_bytes(),
+        &basic_sig.0,
+        &public_key.key,
+    )?;
+    if envelope.challenge() != signed_bytes {
+        return Err(CryptoError::SignatureVerification {
+            algorithm,
+            public_key_bytes: public_key.key.clone(),
+            sig_bytes: signature.to_vec(),
+            internal_error: format!(
+                "Challenge in webauthn is {:?} while it is expected to be {:?}",
+                envelope.challenge(),
+                signed_bytes
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn ensure_no_cycles(
+    sender_pubkey: &[u8],
+    sender_delegation: &[SignedDelegation],
+) -> Result<(), DelegationChainError> {
+    let mut observed_public_keys = HashSet::with_capacity(sender_delegation.len() + 1);
+    observed_public_keys.insert(sender_pubkey);
+    for signed_delegation in sender_delegation {
+        let public_key = signed_delegation.delegation().pubkey();
+        if !observed_public_keys.insert(public_key) {
+            return Err(DelegationChainError::ContainsCycles {
+                public_key: public_key.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn ensure_not_too_many_targets(
+    sender_delegation: &[SignedDelegation],
+) -> Result<(), DelegationChainError> {
+    for (index, signed_delegation) in sender_delegation.iter().enumerate() {
+        match signed_delegation.delegation().number_of_targets() {
+            Some(number_of_targets)
+                if number_of_targets > MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION =>
+            {
+                return Err(DelegationChainError::TooManyTargets {
+                    index,
+                    number_of_targets,
+                    maximum: MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION,
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
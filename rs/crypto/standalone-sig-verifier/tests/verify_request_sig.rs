@@ -0,0 +1,394 @@
+#![allow(clippy::unwrap_used)]
+use assert_matches::assert_matches;
+use ic_crypto_internal_basic_sig_der_utils::subject_public_key_info_der;
+use ic_crypto_internal_basic_sig_ed25519 as ed25519;
+use ic_crypto_standalone_sig_verifier::{
+    ed25519_public_key_to_der, verify_delegation_chain, verify_request_sig, verify_user_sig,
+    DelegationTargets, RequestSignatureVerificationError, MAXIMUM_NUMBER_OF_DELEGATIONS,
+};
+use ic_crypto_test_utils_canister_sigs::new_valid_sig_and_crypto_component;
+use ic_crypto_test_utils_reproducible_rng::ReproducibleRng;
+use ic_types::crypto::threshold_sig::IcRootOfTrust;
+use ic_types::crypto::{CryptoError, Signable};
+use ic_types::messages::{Delegation, MessageId, SignedDelegation, UserSignature};
+use ic_types::{CanisterId, PrincipalId, Time, UserId};
+use rand::{CryptoRng, Rng};
+use simple_asn1::oid;
+
+/// An ECDSA P256 public key in COSE format, DER wrapped.
+const ECDSA_P256_PK_COSE_DER_WRAPPED_HEX: &str = "305e300c060a2b0601040183b8430101034e00a5010203262001215820b487d183dc4806058eb31a29bedefd7bcca987b77a381a3684871d8449c183942258202a122cc711a80453678c3032de4b6fff2c86342e82d1e7adb617c4165c43ce5e";
+
+/// A WebAuthn signature with the secret key corresponding to the above public
+/// key with challenge b"hello".
+const ECDSA_WEBAUTHN_SIG_HELLO_HEX: &str = "d9d9f7a37261757468656e74696361746f725f646174615825bfabc37432958b063360d3ad6461c9c4735ae7f8edd46592a5e0f01452b2e4b5010000000170636c69656e745f646174615f6a736f6e58517b2274797065223a2022776562617574686e2e676574222c20226368616c6c656e6765223a202261475673624738222c20226f726967696e223a202268747470733a2f2f6578616d706c652e6f7267227d697369676e617475726558463044022063627c69661048fb111b13dec2f3675010493c1c276c6a144f44e1fabab01d300220517d3cbd70658933dab63fd23cf05f7274aea6afad206be04d4ec5e268b471d2";
+
+const CURRENT_TIME: Time = Time::from_nanos_since_unix_epoch(1_000_000);
+
+struct Ed25519KeyPair {
+    sk: ed25519::types::SecretKeyBytes,
+    pk_der: Vec<u8>,
+}
+
+impl Ed25519KeyPair {
+    fn generate<R: Rng + CryptoRng>(rng: &mut R) -> Self {
+        let (sk, pk) = ed25519::keypair_from_rng(rng);
+        Self {
+            sk,
+            pk_der: ed25519_public_key_to_der(pk.0.to_vec()).unwrap(),
+        }
+    }
+
+    fn sign<S: Signable>(&self, message: &S) -> Vec<u8> {
+        ed25519::sign(&message.as_signed_bytes(), &self.sk)
+            .unwrap()
+            .0
+            .to_vec()
+    }
+
+    fn delegate_to(
+        &self,
+        other: &Ed25519KeyPair,
+        targets: Option<Vec<CanisterId>>,
+    ) -> SignedDelegation {
+        let expiration = CURRENT_TIME + std::time::Duration::from_secs(60);
+        let delegation = match targets {
+            None => Delegation::new(other.pk_der.clone(), expiration),
+            Some(targets) => {
+                Delegation::new_with_targets(other.pk_der.clone(), expiration, targets)
+            }
+        };
+        let signature = self.sign(&delegation);
+        SignedDelegation::new(delegation, signature)
+    }
+}
+
+fn message_id() -> MessageId {
+    MessageId::from([42; 32])
+}
+
+fn root_of_trust() -> IcRootOfTrust {
+    IcRootOfTrust::from([0; 96])
+}
+
+fn sender_of(pk_der: &[u8]) -> UserId {
+    UserId::from(PrincipalId::new_self_authenticating(pk_der))
+}
+
+fn user_signature(
+    pk_der: &[u8],
+    signature: Vec<u8>,
+    delegations: Vec<SignedDelegation>,
+) -> UserSignature {
+    UserSignature {
+        signature,
+        signer_pubkey: pk_der.to_vec(),
+        sender_delegation: Some(delegations),
+    }
+}
+
+fn key_chain<R: Rng + CryptoRng>(rng: &mut R, length: usize) -> Vec<Ed25519KeyPair> {
+    (0..length).map(|_| Ed25519KeyPair::generate(rng)).collect()
+}
+
+#[test]
+fn should_verify_request_signed_without_delegations() {
+    let rng = &mut ReproducibleRng::new();
+    let sender = Ed25519KeyPair::generate(rng);
+
+    let result = verify_request_sig(
+        &message_id(),
+        &sender_of(&sender.pk_der),
+        Some(&user_signature(
+            &sender.pk_der,
+            sender.sign(&message_id()),
+            vec![],
+        )),
+        None,
+        CURRENT_TIME,
+        root_of_trust(),
+    );
+
+    assert_eq!(result, Ok(DelegationTargets::All));
+}
+
+#[test]
+fn should_verify_request_signed_with_chain_of_delegations() {
+    let rng = &mut ReproducibleRng::new();
+    let keys = key_chain(rng, 3);
+    let delegations = vec![
+        keys[0].delegate_to(&keys[1], None),
+        keys[1].delegate_to(&keys[2], None),
+    ];
+
+    let result = verify_request_sig(
+        &message_id(),
+        &sender_of(&keys[0].pk_der),
+        Some(&user_signature(
+            &keys[0].pk_der,
+            keys[2].sign(&message_id()),
+            delegations,
+        )),
+        None,
+        CURRENT_TIME,
+        root_of_trust(),
+    );
+
+    assert_eq!(result, Ok(DelegationTargets::All));
+}
+
+#[test]
+fn should_reject_request_signed_by_key_that_is_not_last_in_chain() {
+    let rng = &mut ReproducibleRng::new();
+    let keys = key_chain(rng, 3);
+    let delegations = vec![
+        keys[0].delegate_to(&keys[1], None),
+        keys[1].delegate_to(&keys[2], None),
+    ];
+
+    let result = verify_request_sig(
+        &message_id(),
+        &sender_of(&keys[0].pk_der),
+        Some(&user_signature(
+            &keys[0].pk_der,
+            keys[1].sign(&message_id()),
+            delegations,
+        )),
+        None,
+        CURRENT_TIME,
+        root_of_trust(),
+    );
+
+    assert_matches!(
+        result,
+        Err(RequestSignatureVerificationError::InvalidSignature(
+            CryptoError::SignatureVerification { .. }
+        ))
+    );
+}
+
+#[test]
+fn should_reject_delegation_with_invalid_signature() {
+    let rng = &mut ReproducibleRng::new();
+    let keys = key_chain(rng, 3);
+    let delegations = vec![
+        keys[0].delegate_to(&keys[1], None),
+        // Signed by the wrong key.
+        keys[0].delegate_to(&keys[2], None),
+    ];
+
+    let result =
+        verify_delegation_chain(&keys[0].pk_der, &delegations, CURRENT_TIME, root_of_trust());
+
+    assert_matches!(
+        result,
+        Err(RequestSignatureVerificationError::InvalidDelegation { index: 1, .. })
+    );
+}
+
+#[test]
+fn should_reject_expired_delegation() {
+    let rng = &mut ReproducibleRng::new();
+    let keys = key_chain(rng, 2);
+    let delegations = vec![keys[0].delegate_to(&keys[1], None)];
+    let expiration = delegations[0].delegation().expiration();
+    let later = expiration + std::time::Duration::from_nanos(1);
+
+    let result = verify_delegation_chain(&keys[0].pk_der, &delegations, later, root_of_trust());
+
+    assert_eq!(
+        result,
+        Err(RequestSignatureVerificationError::DelegationExpired {
+            index: 0,
+            expiration,
+            current_time: later,
+        })
+    );
+}
+
+#[test]
+fn should_reject_too_long_chain_of_delegations() {
+    let rng = &mut ReproducibleRng::new();
+    let keys = key_chain(rng, MAXIMUM_NUMBER_OF_DELEGATIONS + 2);
+    let delegations: Vec<_> = keys
+        .windows(2)
+        .map(|pair| pair[0].delegate_to(&pair[1], None))
+        .collect();
+
+    let result =
+        verify_delegation_chain(&keys[0].pk_der, &delegations, CURRENT_TIME, root_of_trust());
+
+    assert_eq!(
+        result,
+        Err(RequestSignatureVerificationError::DelegationTooLong {
+            length: MAXIMUM_NUMBER_OF_DELEGATIONS + 1,
+            maximum: MAXIMUM_NUMBER_OF_DELEGATIONS,
+        })
+    );
+}
+
+#[test]
+fn should_reject_chain_of_delegations_with_cycle() {
+    let rng = &mut ReproducibleRng::new();
+    let keys = key_chain(rng, 2);
+    let delegations = vec![
+        keys[0].delegate_to(&keys[1], None),
+        keys[1].delegate_to(&keys[0], None),
+    ];
+
+    let result =
+        verify_delegation_chain(&keys[0].pk_der, &delegations, CURRENT_TIME, root_of_trust());
+
+    assert_eq!(
+        result,
+        Err(
+            RequestSignatureVerificationError::DelegationContainsCycles {
+                public_key: keys[0].pk_der.clone(),
+            }
+        )
+    );
+}
+
+#[test]
+fn should_intersect_delegation_targets_and_check_request_target() {
+    let rng = &mut ReproducibleRng::new();
+    let keys = key_chain(rng, 3);
+    let (canister_1, canister_2, canister_3) = (
+        CanisterId::from_u64(1),
+        CanisterId::from_u64(2),
+        CanisterId::from_u64(3),
+    );
+    let delegations = vec![
+        keys[0].delegate_to(&keys[1], Some(vec![canister_1, canister_2])),
+        keys[1].delegate_to(&keys[2], Some(vec![canister_2, canister_3])),
+    ];
+    let signature = keys[2].sign(&message_id());
+
+    let allowed = verify_request_sig(
+        &message_id(),
+        &sender_of(&keys[0].pk_der),
+        Some(&user_signature(
+            &keys[0].pk_der,
+            signature.clone(),
+            delegations.clone(),
+        )),
+        Some(&canister_2),
+        CURRENT_TIME,
+        root_of_trust(),
+    );
+    let forbidden = verify_request_sig(
+        &message_id(),
+        &sender_of(&keys[0].pk_der),
+        Some(&user_signature(&keys[0].pk_der, signature, delegations)),
+        Some(&canister_1),
+        CURRENT_TIME,
+        root_of_trust(),
+    );
+
+    assert_eq!(
+        allowed,
+        Ok(DelegationTargets::Some([canister_2].into_iter().collect()))
+    );
+    assert_eq!(
+        forbidden,
+        Err(RequestSignatureVerificationError::CanisterNotInDelegationTargets(canister_1))
+    );
+}
+
+#[test]
+fn should_verify_webauthn_signature() {
+    let pk_der = hex::decode(ECDSA_P256_PK_COSE_DER_WRAPPED_HEX).unwrap();
+    let signature = hex::decode(ECDSA_WEBAUTHN_SIG_HELLO_HEX).unwrap();
+
+    assert_eq!(
+        verify_user_sig(b"hello", &signature, &pk_der, root_of_trust()),
+        Ok(())
+    );
+}
+
+#[test]
+fn should_reject_webauthn_signature_with_wrong_challenge() {
+    let pk_der = hex::decode(ECDSA_P256_PK_COSE_DER_WRAPPED_HEX).unwrap();
+    let signature = hex::decode(ECDSA_WEBAUTHN_SIG_HELLO_HEX).unwrap();
+
+    let result = verify_user_sig(b"goodbye", &signature, &pk_der, root_of_trust());
+
+    assert_matches!(result, Err(CryptoError::SignatureVerification { internal_error, .. })
+        if internal_error.contains("Challenge in webauthn"));
+}
+
+#[test]
+fn should_verify_canister_signature_by_der_encoded_public_key() {
+    let rng = &mut ReproducibleRng::new();
+    let sig_data = new_valid_sig_and_crypto_component(rng, false);
+    let pk_der = subject_public_key_info_der(
+        oid!(1, 3, 6, 1, 4, 1, 56387, 1, 2),
+        &sig_data.canister_pk.key,
+    )
+    .unwrap();
+
+    let result = verify_user_sig(
+        &sig_data.msg.as_signed_bytes(),
+        &sig_data.canister_sig.get_ref().0,
+        &pk_der,
+        sig_data.root_of_trust,
+    );
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn should_reject_request_of_sender_that_does_not_match_public_key() {
+    let rng = &mut ReproducibleRng::new();
+    let keys = key_chain(rng, 2);
+    let signature = user_signature(&keys[0].pk_der, keys[0].sign(&message_id()), vec![]);
+
+    let result = verify_request_sig(
+        &message_id(),
+        &sender_of(&keys[1].pk_der),
+        Some(&signature),
+        None,
+        CURRENT_TIME,
+        root_of_trust(),
+    );
+
+    assert_eq!(
+        result,
+        Err(
+            RequestSignatureVerificationError::UserIdDoesNotMatchPublicKey(
+                sender_of(&keys[1].pk_der),
+                keys[0].pk_der.clone()
+            )
+        )
+    );
+}
+
+#[test]
+fn should_accept_unsigned_request_only_from_anonymous_sender() {
+    let rng = &mut ReproducibleRng::new();
+    let sender = Ed25519KeyPair::generate(rng);
+    let anonymous = UserId::from(PrincipalId::new_anonymous());
+    let verify = |sender: &UserId, signature: Option<&UserSignature>| {
+        verify_request_sig(
+            &message_id(),
+            sender,
+            signature,
+            Some(&CanisterId::from_u64(1)),
+            CURRENT_TIME,
+            root_of_trust(),
+        )
+    };
+
+    assert_eq!(verify(&anonymous, None), Ok(DelegationTargets::All));
+    assert_eq!(
+        verify(&sender_of(&sender.pk_der), None),
+        Err(RequestSignatureVerificationError::MissingSignature(
+            sender_of(&sender.pk_der)
+        ))
+    );
+
+    let signature = user_signature(&sender.pk_der, sender.sign(&message_id()), vec![]);
+    assert_eq!(
+        verify(&anonymous, Some(&signature)),
+        Err(RequestSignatureVerificationError::AnonymousSignatureNotAllowed)
+    );
+}
@@ -796,6 +796,15 @@ pub mod internal {
             )
         }
 
+        fn verify_multi_sig_individual_batch(
+            &self,
+            shares: &[(&IndividualMultiSigOf<T>, &T, NodeId)],
+            registry_version: RegistryVersion,
+        ) -> Vec<CryptoResult<()>> {
+            self.crypto_component
+                .verify_multi_sig_individual_batch(shares, registry_version)
+        }
+
         fn combine_multi_sig_individuals(
             &self,
             signatures: BTreeMap<NodeId, IndividualMultiSigOf<T>>,
@@ -835,6 +844,15 @@ pub mod internal {
                 .verify_threshold_sig_share(signature, message, dkg_id, signer)
         }
 
+        fn verify_threshold_sig_share_batch(
+            &self,
+            shares: &[(&ThresholdSigShareOf<T>, &T, NodeId)],
+            dkg_id: NiDkgId,
+        ) -> Vec<CryptoResult<()>> {
+            self.crypto_component
+                .verify_threshold_sig_share_batch(shares, dkg_id)
+        }
+
         fn combine_threshold_sig_shares(
             &self,
             shares: BTreeMap<NodeId, ThresholdSigShareOf<T>>,
@@ -96,6 +96,12 @@ mock! {
             msg: &[u8],
             algorithm_id: AlgorithmId,
         ) -> CryptoResult<()>;
+
+        fn verify_multisig_individual_batch(
+            &self,
+            sigs_pks_msgs: &[(CspSignature, CspPublicKey, Vec<u8>)],
+            algorithm_id: AlgorithmId,
+        ) -> CryptoResult<()>;
     }
 
     pub trait CspKeyGenerator {
@@ -143,6 +149,12 @@ mock! {
             public_key: CspThresholdSigPublicKey,
         ) -> CryptoResult<()>;
 
+        fn threshold_verify_individual_signature_batch(
+            &self,
+            algorithm_id: AlgorithmId,
+            msgs_sigs_pks: &[(Vec<u8>, CspSignature, CspThresholdSigPublicKey)],
+        ) -> CryptoResult<()>;
+
         fn threshold_verify_combined_signature(
             &self,
             algorithm_id: AlgorithmId,
@@ -8,6 +8,7 @@ use ic_crypto_internal_csp::vault::api::CspMultiSignatureError;
 use ic_crypto_internal_csp::vault::api::CspMultiSignatureKeygenError;
 use ic_crypto_internal_csp::vault::api::CspPublicKeyStoreError;
 use ic_crypto_internal_csp::vault::api::CspSecretKeyStoreContainsError;
+use ic_crypto_internal_csp::vault::api::CspSecretKeyStoreEncryptionKeyRotationError;
 use ic_crypto_internal_csp::vault::api::CspTlsKeygenError;
 use ic_crypto_internal_csp::vault::api::CspTlsSignError;
 use ic_crypto_internal_csp::vault::api::IDkgProtocolCspVault;
@@ -196,6 +197,10 @@ mock! {
 
     pub trait SecretKeyStoreCspVault {
         fn sks_contains(&self, key_id: &KeyId) -> Result<bool, CspSecretKeyStoreContainsError>;
+
+        fn rotate_secret_key_store_encryption_key(
+            &self,
+        ) -> Result<(), CspSecretKeyStoreEncryptionKeyRotationError>;
     }
 
     pub trait PublicAndSecretKeyStoreCspVault {
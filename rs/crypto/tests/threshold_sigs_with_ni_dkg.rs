@@ -84,6 +84,47 @@ fn should_produce_valid_signature_shares() {
     });
 }
 
+#[test]
+fn should_verify_batch_of_signature_shares_and_identify_invalid_share() {
+    let rng = &mut reproducible_rng();
+    let subnet_size = rng.gen_range(2..7);
+    let (config, dkg_id, crypto_components) = setup_with_random_ni_dkg_config(subnet_size, rng);
+
+    run_ni_dkg_and_load_transcript_for_receivers(&config, &crypto_components);
+
+    let msg = message();
+    let other_msg = SignableMock::new(b"other message".to_vec());
+    let sig_shares = sign_threshold_for_each(
+        &config.receivers().get().iter().copied().collect::<Vec<_>>(),
+        &msg,
+        dkg_id,
+        &crypto_components,
+    );
+    let verifier = crypto_for(
+        random_node_in(config.receivers().get(), rng),
+        &crypto_components,
+    );
+
+    let batch: Vec<_> = sig_shares
+        .iter()
+        .map(|(signer, sig_share)| (sig_share, &msg, *signer))
+        .collect();
+    let results = verifier.verify_threshold_sig_share_batch(&batch, dkg_id);
+    assert!(results.iter().all(|result| result.is_ok()));
+
+    let invalid_index = rng.gen_range(0..batch.len());
+    let mut batch_with_invalid_share = batch;
+    batch_with_invalid_share[invalid_index].1 = &other_msg;
+    let results = verifier.verify_threshold_sig_share_batch(&batch_with_invalid_share, dkg_id);
+    for (index, result) in results.iter().enumerate() {
+        if index == invalid_index {
+            assert_matches!(result, Err(CryptoError::SignatureVerification { .. }));
+        } else {
+            assert_eq!(result, &Ok(()));
+        }
+    }
+}
+
 #[test]
 fn should_create_same_config_and_transcript_with_same_seed() {
     let generate_transcript = || {
@@ -1048,6 +1048,17 @@ impl CyclesAccountManager {
             * (subnet_size as u64)
     }
 
+    /// Returns the fee for a non-replicated HTTP request, i.e. a request that is
+    /// made by a single node only. It is priced as a replicated request on a
+    /// subnet of one node, independently of the actual subnet size.
+    pub fn non_replicated_http_request_fee(
+        &self,
+        request_size: NumBytes,
+        response_size_limit: Option<NumBytes>,
+    ) -> Cycles {
+        self.http_request_fee(request_size, response_size_limit, 1)
+    }
+
     /// Returns the default value of the reserved balance limit for the case
     /// when the canister doesn't have it set in the settings.
     pub fn default_reserved_balance_limit(&self) -> Cycles {
@@ -1164,4 +1175,31 @@ mod tests {
             Cycles::from(1_605_046_800u64) * subnet_size
         );
     }
+
+    #[test]
+    fn non_replicated_http_requests_fee_does_not_scale() {
+        let subnet_size: u64 = 34;
+        let reference_subnet_size: u64 = 13;
+        let request_size = NumBytes::from(17);
+
+        // The fee is the same on a 13-node and on a 34-node subnet.
+        for size in [reference_subnet_size, subnet_size] {
+            let cycles_account_manager = create_cycles_account_manager(size as usize);
+            assert_eq!(
+                cycles_account_manager.non_replicated_http_request_fee(request_size, None),
+                Cycles::from(1_603_066_800u64)
+            );
+        }
+
+        // A non-replicated request is cheaper than a replicated one.
+        let cycles_account_manager = create_cycles_account_manager(reference_subnet_size as usize);
+        assert!(
+            cycles_account_manager.non_replicated_http_request_fee(request_size, None)
+                < cycles_account_manager.http_request_fee(
+                    request_size,
+                    None,
+                    reference_subnet_size as usize
+                )
+        );
+    }
 }
@@ -16,7 +16,7 @@ use crate::{
     ic00_permissions::Ic00MethodPermissions,
     util, NonReplicatedQueryKind,
 };
-use candid::Encode;
+use candid::{Decode, Encode};
 use ic_base_types::PrincipalId;
 use ic_config::execution_environment::Config as ExecutionConfig;
 use ic_config::flag_status::FlagStatus;
@@ -25,12 +25,13 @@ use ic_crypto_tecdsa::derive_tecdsa_public_key;
 use ic_cycles_account_manager::{CyclesAccountManager, IngressInductionCost, ResourceSaturation};
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
-    CanisterChangeOrigin, CanisterHttpRequestArgs, CanisterIdRecord, CanisterInfoRequest,
-    CanisterInfoResponse, CanisterSettingsArgs, ComputeInitialEcdsaDealingsArgs,
-    CreateCanisterArgs, ECDSAPublicKeyArgs, ECDSAPublicKeyResponse, EcdsaKeyId, EmptyBlob,
-    InstallCodeArgsV2, Method as Ic00Method, Payload as Ic00Payload,
-    ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs, SetControllerArgs,
-    SetupInitialDKGArgs, SignWithECDSAArgs, UninstallCodeArgs, UpdateSettingsArgs, IC_00,
+    CanisterChangeOrigin, CanisterHttpRequestArgs, CanisterHttpResponsePayload, CanisterIdRecord,
+    CanisterInfoRequest, CanisterInfoResponse, CanisterSettingsArgs,
+    ComputeInitialEcdsaDealingsArgs, CreateCanisterArgs, ECDSAPublicKeyArgs,
+    ECDSAPublicKeyResponse, EcdsaKeyId, EmptyBlob, HttpHeader, InstallCodeArgsV2,
+    Method as Ic00Method, Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs,
+    ProvisionalTopUpCanisterArgs, SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs,
+    UninstallCodeArgs, UpdateSettingsArgs, IC_00, NON_REPLICATED_RESPONSE_HEADER,
 };
 use ic_interfaces::execution_environment::{
     ExecutionComplexity, ExecutionMode, IngressHistoryWriter, RegistryExecutionSettings,
@@ -52,7 +53,7 @@ use ic_replicated_state::{
 };
 use ic_system_api::{ExecutionParameters, InstructionLimits};
 use ic_types::{
-    canister_http::CanisterHttpRequestContext,
+    canister_http::{CanisterHttpRequestContext, Replication},
     crypto::canister_threshold_sig::{ExtendedDerivationPath, MasterEcdsaPublicKey},
     crypto::threshold_sig::ni_dkg::NiDkgTargetId,
     ingress::{IngressState, IngressStatus, WasmResult},
@@ -63,8 +64,8 @@ use ic_types::{
     },
     methods::SystemMethod,
     nominal_cycles::NominalCycles,
-    CanisterId, CpuComplexity, Cycles, LongExecutionMode, NumBytes, NumInstructions, SubnetId,
-    Time,
+    CanisterId, CpuComplexity, Cycles, LongExecutionMode, NodeId, NumBytes, NumInstructions,
+    SubnetId, Time,
 };
 use ic_types::{messages::MessageId, methods::WasmMethod};
 use ic_wasm_types::WasmHash;
@@ -404,13 +405,23 @@ impl ExecutionEnvironment {
                             state.metadata.subnet_metrics.ecdsa_signature_agreements += 1;
                         }
 
+                        let response_payload = match &context {
+                            SubnetCallContext::CanisterHttpRequest(context) => {
+                                mark_http_response_replication(
+                                    &response.response_payload,
+                                    &context.replication,
+                                )
+                            }
+                            _ => response.response_payload.clone(),
+                        };
+
                         state.push_subnet_output_response(
                             Response {
                                 originator: request.sender,
                                 respondent: CanisterId::from(self.own_subnet_id),
                                 originator_reply_callback: request.sender_reply_callback,
                                 refund: request.payment,
-                                response_payload: response.response_payload.clone(),
+                                response_payload,
                             }
                             .into(),
                         );
@@ -437,6 +448,32 @@ impl ExecutionEnvironment {
             }
         }
 
+        // The state of a canister being migrated is frozen until the migration
+        // is completed: on the source subnet, so that it can be exported once
+        // stopped; on the destination subnet, so that the imported canister
+        // cannot be deleted (and imported again).
+        if let Some(canister_id) = method
+            .ok()
+            .and_then(|method| modified_canister_id(method, payload))
+        {
+            let network_topology = &state.metadata.network_topology;
+            if network_topology.is_migrating_from(canister_id, self.own_subnet_id)
+                || network_topology.is_rerouted_to(canister_id, self.own_subnet_id)
+            {
+                let err = UserError::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    format!(
+                        "Canister {} is being migrated to another subnet and cannot be modified.",
+                        canister_id
+                    ),
+                );
+                let refund = msg.take_cycles();
+                let state =
+                    self.finish_subnet_message_execution(state, msg, Err(err), refund, timer);
+                return (state, Some(NumInstructions::from(0)));
+            }
+        }
+
         let result = match method {
             Ok(Ic00Method::InstallCode) => {
                 // Tail call is needed for deterministic time slicing here to
@@ -752,58 +789,88 @@ impl ExecutionEnvironment {
                     CanisterCall::Request(request) => {
                         match CanisterHttpRequestArgs::decode(payload) {
                             Err(err) => Some((Err(err), msg.take_cycles())),
-                            Ok(args) => match CanisterHttpRequestContext::try_from((
-                                state.time(),
-                                request.as_ref(),
-                                args,
-                            )) {
-                                Err(err) => Some((Err(err.into()), msg.take_cycles())),
-                                Ok(mut canister_http_request_context) => {
-                                    let http_request_fee =
-                                        self.cycles_account_manager.http_request_fee(
-                                            canister_http_request_context.variable_parts_size(),
-                                            canister_http_request_context.max_response_bytes,
-                                            registry_settings.subnet_size,
+                            Ok(args) => {
+                                let is_replicated = args.is_replicated.unwrap_or(true);
+                                match CanisterHttpRequestContext::try_from((
+                                    state.time(),
+                                    request.as_ref(),
+                                    args,
+                                ))
+                                .map_err(UserError::from)
+                                .and_then(|mut context| {
+                                    if !is_replicated {
+                                        context.replication = Replication::NonReplicated(
+                                            choose_non_replicated_http_node(
+                                                &state,
+                                                self.own_subnet_id,
+                                                rng,
+                                            )?,
                                         );
-                                    if request.payment < http_request_fee {
-                                        let err = Err(UserError::new(
+                                    }
+                                    Ok(context)
+                                }) {
+                                    Err(err) => Some((Err(err), msg.take_cycles())),
+                                    Ok(mut canister_http_request_context) => {
+                                        let http_request_fee = if canister_http_request_context
+                                            .is_non_replicated()
+                                        {
+                                            self.cycles_account_manager
+                                                .non_replicated_http_request_fee(
+                                                    canister_http_request_context
+                                                        .variable_parts_size(),
+                                                    canister_http_request_context
+                                                        .max_response_bytes,
+                                                )
+                                        } else {
+                                            self.cycles_account_manager.http_request_fee(
+                                                canister_http_request_context.variable_parts_size(),
+                                                canister_http_request_context.max_response_bytes,
+                                                registry_settings.subnet_size,
+                                            )
+                                        };
+                                        if request.payment < http_request_fee {
+                                            let err = Err(UserError::new(
                                                         ErrorCode::CanisterRejectedMessage,
                                                         format!(
                                                             "http_request request sent with {} cycles, but {} cycles are required.",
                                                             request.payment, http_request_fee
                                                         ),
                                                     ));
-                                        Some((err, msg.take_cycles()))
-                                    } else {
-                                        canister_http_request_context.request.payment -=
-                                            http_request_fee;
-                                        let http_fee = NominalCycles::from(http_request_fee);
-                                        state
-                                            .metadata
-                                            .subnet_metrics
-                                            .consumed_cycles_http_outcalls += http_fee;
-                                        state
-                                            .metadata
-                                            .subnet_metrics
-                                            .observe_consumed_cycles_with_use_case(
-                                                CyclesUseCase::HTTPOutcalls,
-                                                http_fee,
+                                            Some((err, msg.take_cycles()))
+                                        } else {
+                                            canister_http_request_context.request.payment -=
+                                                http_request_fee;
+                                            let http_fee = NominalCycles::from(http_request_fee);
+                                            state
+                                                .metadata
+                                                .subnet_metrics
+                                                .consumed_cycles_http_outcalls += http_fee;
+                                            state
+                                                .metadata
+                                                .subnet_metrics
+                                                .observe_consumed_cycles_with_use_case(
+                                                    CyclesUseCase::HTTPOutcalls,
+                                                    http_fee,
+                                                );
+                                            state
+                                                .metadata
+                                                .subnet_call_context_manager
+                                                .push_context(
+                                                    SubnetCallContext::CanisterHttpRequest(
+                                                        canister_http_request_context,
+                                                    ),
+                                                );
+                                            self.metrics.observe_message_with_label(
+                                                &request.method_name,
+                                                timer.elapsed(),
+                                                SUBMITTED_OUTCOME_LABEL.into(),
+                                                SUCCESS_STATUS_LABEL.into(),
                                             );
-                                        state.metadata.subnet_call_context_manager.push_context(
-                                            SubnetCallContext::CanisterHttpRequest(
-                                                canister_http_request_context,
-                                            ),
-                                        );
-                                        self.metrics.observe_message_with_label(
-                                            &request.method_name,
-                                            timer.elapsed(),
-                                            SUBMITTED_OUTCOME_LABEL.into(),
-                                            SUCCESS_STATUS_LABEL.into(),
-                                        );
-                                        None
+                                            None
+                                        }
                                     }
                                 }
-                            },
+                            }
                         }
                     }
 
@@ -2685,6 +2752,102 @@ pub fn execute_canister(
     )
 }
 
+/// Pseudo-randomly chooses the node of the given subnet that makes a
+/// non-replicated HTTP request.
+fn choose_non_replicated_http_node(
+    state: &ReplicatedState,
+    subnet_id: SubnetId,
+    rng: &mut dyn RngCore,
+) -> Result<NodeId, UserError> {
+    let nodes = state
+        .metadata
+        .network_topology
+        .subnets
+        .get(&subnet_id)
+        .map(|subnet| &subnet.nodes)
+        .filter(|nodes| !nodes.is_empty())
+        .ok_or_else(|| {
+            UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!(
+                    "No node of subnet {} is available to make a non-replicated http_request.",
+                    subnet_id
+                ),
+            )
+        })?;
+    let index = (rng.next_u64() % nodes.len() as u64) as usize;
+    Ok(*nodes.iter().nth(index).unwrap())
+}
+
+/// Sets the [`NON_REPLICATED_RESPONSE_HEADER`] of a canister http response
+/// according to how the request was replicated. The header is first removed
+/// from the response so that neither the remote server nor the node making a
+/// non-replicated request can forge or strip it.
+fn mark_http_response_replication(payload: &Payload, replication: &Replication) -> Payload {
+    let data = match payload {
+        Payload::Data(data) => data,
+        Payload::Reject(_) => return payload.clone(),
+    };
+    let non_replicated = matches!(replication, Replication::NonReplicated(_));
+    let mut http_response = match Decode!(data, CanisterHttpResponsePayload) {
+        Ok(http_response) => http_response,
+        Err(_) if !non_replicated => return payload.clone(),
+        Err(err) => {
+            return Payload::Reject(RejectContext::new(
+                RejectCode::SysFatal,
+                format!("Failed to decode non-replicated http response: {}", err),
+            ))
+        }
+    };
+    http_response.headers.retain(|header| {
+        !header
+            .name
+            .eq_ignore_ascii_case(NON_REPLICATED_RESPONSE_HEADER)
+    });
+    if non_replicated {
+        http_response.headers.push(HttpHeader {
+            name: NON_REPLICATED_RESPONSE_HEADER.to_string(),
+            value: "true".to_string(),
+        });
+    }
+    match Encode!(&http_response) {
+        Ok(data) => Payload::Data(data),
+        Err(err) => Payload::Reject(RejectContext::new(
+            RejectCode::SysFatal,
+            format!("Failed to encode http response: {}", err),
+        )),
+    }
+}
+
+/// Returns the canister targeted by `method` if it is one of the management
+/// canister methods that modify a canister (its status, code, settings or
+/// cycles balance).
+fn modified_canister_id(method: Ic00Method, payload: &[u8]) -> Option<CanisterId> {
+    match method {
+        Ic00Method::StartCanister | Ic00Method::DeleteCanister | Ic00Method::DepositCycles => {
+            CanisterIdRecord::decode(payload)
+                .ok()
+                .map(|args| args.get_canister_id())
+        }
+        Ic00Method::InstallCode => InstallCodeArgsV2::decode(payload)
+            .ok()
+            .map(|args| args.get_canister_id()),
+        Ic00Method::UninstallCode => UninstallCodeArgs::decode(payload)
+            .ok()
+            .map(|args| args.get_canister_id()),
+        Ic00Method::UpdateSettings => UpdateSettingsArgs::decode(payload)
+            .ok()
+            .map(|args| args.get_canister_id()),
+        Ic00Method::SetController => SetControllerArgs::decode(payload)
+            .ok()
+            .map(|args| args.get_canister_id()),
+        Ic00Method::ProvisionalTopUpCanister => ProvisionalTopUpCanisterArgs::decode(payload)
+            .ok()
+            .map(|args| args.get_canister_id()),
+        _ => None,
+    }
+}
+
 fn get_master_ecdsa_public_key<'a>(
     ecdsa_subnet_public_keys: &'a BTreeMap<EcdsaKeyId, MasterEcdsaPublicKey>,
     subnet_id: SubnetId,
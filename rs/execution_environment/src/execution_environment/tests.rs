@@ -8,13 +8,14 @@ use ic_types::nominal_cycles::NominalCycles;
 use ic_base_types::{NumBytes, NumSeconds};
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
-    self as ic00, BoundedHttpHeaders, CanisterChange, CanisterHttpRequestArgs, CanisterIdRecord,
-    CanisterStatusResultV2, CanisterStatusType, DerivationPath, EcdsaCurve, EcdsaKeyId, EmptyBlob,
-    HttpMethod, Method, Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs,
-    ProvisionalTopUpCanisterArgs, TransformContext, TransformFunc, IC_00,
+    self as ic00, BoundedHttpHeaders, CanisterChange, CanisterHttpRequestArgs,
+    CanisterHttpResponsePayload, CanisterIdRecord, CanisterStatusResultV2, CanisterStatusType,
+    DerivationPath, EcdsaCurve, EcdsaKeyId, EmptyBlob, HttpHeader, HttpMethod, Method,
+    Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
+    TransformContext, TransformFunc, IC_00, NON_REPLICATED_RESPONSE_HEADER,
 };
 use ic_registry_routing_table::canister_id_into_u64;
-use ic_registry_routing_table::CanisterIdRange;
+use ic_registry_routing_table::{CanisterIdRange, CanisterMigrations};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
     canister_state::{DEFAULT_QUEUE_CAPACITY, WASM_PAGE_SIZE_IN_BYTES},
@@ -26,7 +27,7 @@ use ic_test_utilities_execution_environment::{
     assert_empty_reply, check_ingress_status, get_reply, ExecutionTest, ExecutionTestBuilder,
 };
 use ic_test_utilities_metrics::{fetch_histogram_vec_count, metric_vec};
-use ic_types::canister_http::Transform;
+use ic_types::canister_http::{Replication, Transform};
 use ic_types::{
     canister_http::CanisterHttpMethod,
     ingress::{IngressState, IngressStatus, WasmResult},
@@ -579,6 +580,40 @@ fn stopping_an_already_stopped_canister_succeeds() {
     );
 }
 
+#[test]
+fn starting_a_canister_being_migrated_away_fails() {
+    let mut test = ExecutionTestBuilder::new().with_manual_execution().build();
+    let canister_id = test.universal_canister().unwrap();
+    test.stop_canister(canister_id);
+    test.process_stopping_canisters();
+
+    let own_subnet_id = test.state().metadata.own_subnet_id;
+    let mut canister_migrations = CanisterMigrations::new();
+    canister_migrations
+        .insert_ranges(
+            vec![CanisterIdRange {
+                start: canister_id,
+                end: canister_id,
+            }]
+            .try_into()
+            .unwrap(),
+            own_subnet_id,
+            subnet_test_id(2),
+        )
+        .unwrap();
+    test.state_mut()
+        .metadata
+        .network_topology
+        .canister_migrations = std::sync::Arc::new(canister_migrations);
+
+    let err = test.start_canister(canister_id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterRejectedMessage);
+    assert_eq!(
+        test.canister_state(canister_id).status(),
+        CanisterStatusType::Stopped
+    );
+}
+
 #[test]
 fn stopping_a_running_canister_does_not_update_ingress_history() {
     let mut test = ExecutionTestBuilder::new().with_manual_execution().build();
@@ -1839,6 +1874,7 @@ fn execute_canister_http_request() {
             }),
             context: transform_context.clone(),
         }),
+        is_replicated: None,
     };
 
     // Create request to HTTP_REQUEST method.
@@ -1893,6 +1929,137 @@ fn execute_canister_http_request() {
     );
 }
 
+#[test]
+fn execute_non_replicated_canister_http_request() {
+    let own_subnet = subnet_test_id(1);
+    let caller_canister = canister_test_id(10);
+    let mut test = ExecutionTestBuilder::new()
+        .with_own_subnet_id(own_subnet)
+        .with_caller(own_subnet, caller_canister)
+        .build();
+    test.state_mut().metadata.own_subnet_features.http_requests = true;
+
+    let response_size_limit = 1000u64;
+    let args = CanisterHttpRequestArgs {
+        url: "https://".to_string(),
+        max_response_bytes: Some(response_size_limit),
+        headers: BoundedHttpHeaders::new(vec![]),
+        body: None,
+        method: HttpMethod::GET,
+        transform: None,
+        is_replicated: Some(false),
+    };
+
+    let payment = Cycles::new(1_000_000_000);
+    test.inject_call_to_ic00(Method::HttpRequest, args.encode(), payment);
+    test.execute_all();
+
+    let http_request_context = test
+        .state()
+        .metadata
+        .subnet_call_context_manager
+        .canister_http_request_contexts
+        .get(&CallbackId::from(0))
+        .unwrap()
+        .clone();
+    // The request is made by a single node of the own subnet.
+    match http_request_context.replication {
+        Replication::NonReplicated(node_id) => assert!(test
+            .state()
+            .metadata
+            .network_topology
+            .subnets
+            .get(&own_subnet)
+            .unwrap()
+            .nodes
+            .contains(&node_id)),
+        Replication::FullyReplicated => panic!("Expected a non-replicated request"),
+    }
+
+    // The request is charged the non-replicated fee, which is lower than the
+    // fee of a replicated request.
+    let fee = test.non_replicated_http_request_fee(
+        http_request_context.variable_parts_size(),
+        Some(NumBytes::from(response_size_limit)),
+    );
+    assert!(
+        fee < test.http_request_fee(
+            http_request_context.variable_parts_size(),
+            Some(NumBytes::from(response_size_limit)),
+        )
+    );
+    assert_eq!(http_request_context.request.payment, payment - fee);
+}
+
+fn http_response_headers(response: &Response) -> Vec<HttpHeader> {
+    match &response.response_payload {
+        Payload::Data(data) => Decode!(data, CanisterHttpResponsePayload).unwrap().headers,
+        Payload::Reject(reject) => panic!("Unexpected reject: {:?}", reject),
+    }
+}
+
+fn http_response_from_subnet(callback: u64, headers: Vec<HttpHeader>) -> Response {
+    Response {
+        originator: CanisterId::ic_00(),
+        respondent: CanisterId::ic_00(),
+        originator_reply_callback: CallbackId::from(callback),
+        refund: Cycles::zero(),
+        response_payload: Payload::Data(
+            Encode!(&CanisterHttpResponsePayload {
+                status: 200,
+                headers,
+                body: vec![],
+            })
+            .unwrap(),
+        ),
+    }
+}
+
+#[test]
+fn non_replicated_canister_http_response_is_marked_by_execution() {
+    let own_subnet = subnet_test_id(1);
+    let caller_canister = canister_test_id(10);
+    let mut test = ExecutionTestBuilder::new()
+        .with_own_subnet_id(own_subnet)
+        .with_caller(own_subnet, caller_canister)
+        .build();
+    test.state_mut().metadata.own_subnet_features.http_requests = true;
+
+    for is_replicated in [Some(false), Some(true)] {
+        let args = CanisterHttpRequestArgs {
+            url: "https://".to_string(),
+            max_response_bytes: None,
+            headers: BoundedHttpHeaders::new(vec![]),
+            body: None,
+            method: HttpMethod::GET,
+            transform: None,
+            is_replicated,
+        };
+        test.inject_call_to_ic00(
+            Method::HttpRequest,
+            args.encode(),
+            Cycles::new(1_000_000_000_000),
+        );
+    }
+    test.execute_all();
+
+    // The node making the non-replicated request strips the marker, the remote
+    // server of the replicated request tries to forge it.
+    let forged_marker = HttpHeader {
+        name: NON_REPLICATED_RESPONSE_HEADER.to_string(),
+        value: "true".to_string(),
+    };
+    test.execute_consensus_response(http_response_from_subnet(0, vec![]));
+    test.execute_consensus_response(http_response_from_subnet(1, vec![forged_marker.clone()]));
+    test.induct_messages();
+
+    assert_eq!(
+        http_response_headers(test.get_xnet_response(0)),
+        vec![forged_marker]
+    );
+    assert_eq!(http_response_headers(test.get_xnet_response(1)), vec![]);
+}
+
 #[test]
 fn execute_canister_http_request_disabled() {
     let own_subnet = subnet_test_id(1);
@@ -1918,6 +2085,7 @@ fn execute_canister_http_request_disabled() {
             }),
             context: vec![0, 1, 2],
         }),
+        is_replicated: None,
     };
 
     // Create request to HTTP_REQUEST method.
@@ -11,6 +11,7 @@ use ic_logger::ReplicaLogger;
 use ic_metrics::buckets::decimal_buckets_with_zero;
 use ic_metrics::{buckets::exponential_buckets, MetricsRegistry};
 use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::canister_state::DEFAULT_OUTPUT_QUEUES_CAPACITY;
 use ic_replicated_state::NetworkTopology;
 use ic_replicated_state::{page_map::allocated_pages_count, ExecutionState, SystemState};
 use ic_system_api::ExecutionParameters;
@@ -157,6 +158,7 @@ pub struct Hypervisor {
     deterministic_time_slicing: FlagStatus,
     cost_to_compile_wasm_instruction: NumInstructions,
     dirty_page_overhead: NumInstructions,
+    canister_output_queues_capacity: FlagStatus,
 }
 
 impl Hypervisor {
@@ -266,6 +268,7 @@ impl Hypervisor {
                 .embedders_config
                 .cost_to_compile_wasm_instruction,
             dirty_page_overhead,
+            canister_output_queues_capacity: config.canister_output_queues_capacity,
         }
     }
 
@@ -292,6 +295,7 @@ impl Hypervisor {
             deterministic_time_slicing,
             cost_to_compile_wasm_instruction,
             dirty_page_overhead,
+            canister_output_queues_capacity: FlagStatus::Disabled,
         }
     }
 
@@ -376,13 +380,19 @@ impl Hypervisor {
                 execution_parameters.instruction_limits.slice()
             ),
         }
-        let static_system_state = SandboxSafeSystemState::new(
+        let mut static_system_state = SandboxSafeSystemState::new(
             system_state,
             *self.cycles_account_manager,
             network_topology,
             self.dirty_page_overhead,
             execution_parameters.compute_allocation,
         );
+        if self.canister_output_queues_capacity == FlagStatus::Enabled {
+            static_system_state.limit_output_requests(
+                DEFAULT_OUTPUT_QUEUES_CAPACITY
+                    .saturating_sub(system_state.queues().output_queues_message_count()),
+            );
+        }
         let (compilation_result, execution_result) = Arc::clone(&self.wasm_executor).execute(
             WasmExecutionInput {
                 api_type,
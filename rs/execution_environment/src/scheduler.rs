@@ -1367,6 +1367,10 @@ impl Scheduler for SchedulerImpl {
                 self.purge_expired_ingress_messages(&mut state);
             }
 
+            // Stop canisters that are being migrated away and drop the ones
+            // whose migration was completed.
+            util::process_migrating_canisters(&mut state, self.own_subnet_id, &round_log);
+
             // See documentation around definition of `heap_delta_estimate` for an
             // explanation.
             if state.metadata.heap_delta_estimate >= self.config.subnet_heap_delta_capacity {
@@ -9,12 +9,15 @@ use ic00::{
     CanisterHttpRequestArgs, HttpMethod, SignWithECDSAArgs, TransformContext, TransformFunc,
 };
 use ic_base_types::PrincipalId;
+use ic_certification_test_utils::{encoded_time, CertificateBuilder, CertificateData};
 use ic_config::{
     embedders::Config as EmbeddersConfig,
     embedders::MeteringType,
     execution_environment::Config as HypervisorConfig,
     subnet_config::{CyclesAccountManagerConfig, SchedulerConfig, SubnetConfig},
 };
+use ic_crypto_tree_hash::{flatmap, Label, LabeledTree};
+use ic_crypto_utils_threshold_sig_der::public_key_to_der;
 use ic_embedders::wasmtime_embedder::system_api_complexity::{cpu, overhead};
 use ic_error_types::RejectCode;
 use ic_ic00_types::{
@@ -23,12 +26,15 @@ use ic_ic00_types::{
 };
 use ic_interfaces::execution_environment::SubnetAvailableMemory;
 use ic_logger::replica_logger::no_op_logger;
-use ic_registry_routing_table::CanisterIdRange;
+use ic_registry_routing_table::{
+    CanisterIdRange, CanisterImports, CanisterMigrations, RoutingTable,
+};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::canister_state::system_state::PausedExecutionId;
 use ic_replicated_state::testing::CanisterQueuesTesting;
 use ic_replicated_state::testing::SystemStateTesting;
 use ic_replicated_state::ExportedFunctions;
+use ic_replicated_state::SubnetTopology;
 use ic_state_machine_tests::{
     PayloadBuilder, StateMachine, StateMachineBuilder, StateMachineConfig, WasmResult,
 };
@@ -3048,6 +3054,92 @@ fn replicated_state_metrics_some_canisters_not_in_routing_table() {
     );
 }
 
+#[test]
+fn canister_migrating_away_is_stopped_and_dropped_once_migrated() {
+    let mut test = SchedulerTestBuilder::new().build();
+    let canister = test.create_canister();
+    let own_subnet = test.state().metadata.own_subnet_id;
+    let other_subnet = subnet_test_id(2);
+    let range = CanisterIdRange {
+        start: canister,
+        end: canister,
+    };
+
+    // Prepare the migration: the canister is still routed to this subnet.
+    let mut canister_migrations = CanisterMigrations::new();
+    canister_migrations
+        .insert_ranges(vec![range].try_into().unwrap(), own_subnet, other_subnet)
+        .unwrap();
+    test.state_mut()
+        .metadata
+        .network_topology
+        .canister_migrations = Arc::new(canister_migrations);
+
+    test.execute_round(ExecutionRoundType::OrdinaryRound);
+    assert_eq!(
+        test.canister_state(canister).status(),
+        CanisterStatusType::Stopped
+    );
+
+    // Complete the migration: the canister is routed to the other subnet and
+    // the `canister_migrations` entry is removed.
+    let mut routing_table = RoutingTable::new();
+    routing_table.insert(range, other_subnet).unwrap();
+    let network_topology = &mut test.state_mut().metadata.network_topology;
+    network_topology.routing_table = Arc::new(routing_table);
+    network_topology.canister_migrations = Arc::new(CanisterMigrations::new());
+
+    // The canister is kept until the other subnet confirms its import.
+    test.execute_round(ExecutionRoundType::OrdinaryRound);
+    assert!(test.state().canister_state(&canister).is_some());
+
+    // Record a certificate of the other subnet's state tree showing the
+    // canister with the same module.
+    let module_hash = test
+        .canister_state(canister)
+        .execution_state
+        .as_ref()
+        .unwrap()
+        .wasm_binary
+        .binary
+        .module_hash();
+    let (_, nns_public_key, import_certificate) =
+        CertificateBuilder::new(CertificateData::CustomTree(LabeledTree::SubTree(flatmap![
+            Label::from("canister") => LabeledTree::SubTree(flatmap![
+                Label::from(canister.get_ref().to_vec()) => LabeledTree::SubTree(flatmap![
+                    Label::from("controllers") => LabeledTree::Leaf(vec![]),
+                    Label::from("module_hash") => LabeledTree::Leaf(module_hash.to_vec()),
+                ])
+            ]),
+            Label::from("time") => LabeledTree::Leaf(encoded_time(0)),
+        ])))
+        .with_delegation(CertificateBuilder::new(CertificateData::SubnetData {
+            subnet_id: other_subnet,
+            canister_id_ranges: vec![(canister, canister)],
+        }))
+        .build();
+    let nns_subnet = subnet_test_id(3);
+    let network_topology = &mut test.state_mut().metadata.network_topology;
+    network_topology.nns_subnet_id = nns_subnet;
+    network_topology.subnets.insert(
+        nns_subnet,
+        SubnetTopology {
+            public_key: public_key_to_der(&nns_public_key.into_bytes()).unwrap(),
+            ..Default::default()
+        },
+    );
+    let mut canister_imports = CanisterImports::new();
+    canister_imports.insert(canister, [0; 32]);
+    canister_imports
+        .get_mut(&canister)
+        .unwrap()
+        .import_certificate = Some(import_certificate);
+    network_topology.canister_imports = Arc::new(canister_imports);
+
+    test.execute_round(ExecutionRoundType::OrdinaryRound);
+    assert!(test.state().canister_state(&canister).is_none());
+}
+
 #[test]
 fn long_open_call_context_is_recorded() {
     let mut test = SchedulerTestBuilder::new().build();
@@ -3410,6 +3502,7 @@ fn consumed_cycles_http_outcalls_are_added_to_consumed_cycles_total() {
             }),
             context: transform_context,
         }),
+        is_replicated: None,
     };
 
     // Create request to `HttpRequest` method.
@@ -1,15 +1,18 @@
 use crate::types::Response;
-use ic_base_types::SubnetId;
+use ic_base_types::{PrincipalId, SubnetId};
+use ic_certification::verify_certificate_with_cache;
+use ic_crypto_tree_hash::{LookupStatus, MixedHashTree};
+use ic_crypto_utils_threshold_sig_der::parse_threshold_sig_key_from_der;
 use ic_ic00_types::{CanisterStatusType, EmptyBlob, Payload as Ic00Payload, IC_00};
 use ic_interfaces::execution_environment::IngressHistoryWriter;
-use ic_logger::{error, info, ReplicaLogger};
-use ic_replicated_state::{CanisterStatus, ReplicatedState};
+use ic_logger::{error, info, warn, ReplicaLogger};
+use ic_replicated_state::{CanisterState, CanisterStatus, NetworkTopology, ReplicatedState};
 use ic_types::{
     ingress::{IngressState, IngressStatus, WasmResult},
     messages::{Payload, StopCanisterCallId, StopCanisterContext},
     CanisterId,
 };
-use std::{mem, sync::Arc};
+use std::{convert::TryFrom, mem, sync::Arc};
 
 pub(crate) const GOVERNANCE_CANISTER_ID: CanisterId = CanisterId::from_u64(1);
 
@@ -140,3 +143,140 @@ pub fn process_stopping_canisters(
     state.put_canister_states(canister_states);
     state
 }
+
+/// Applies the `canister_migrations` entries of the network topology to the
+/// canisters hosted by `own_subnet_id`:
+///
+/// * Running canisters that are being migrated away are transitioned to
+///   "stopping" (and, once ready, to "stopped" by `process_stopping_canisters`),
+///   so that their state can be exported to the destination subnet.
+/// * Stopped canisters whose migration was completed, i.e. that are routed to
+///   another subnet and no longer have a `canister_migrations` entry, are
+///   dropped once that subnet confirmed their import (see
+///   `verify_canister_import`). Until then, this subnet keeps its copy.
+pub fn process_migrating_canisters(
+    state: &mut ReplicatedState,
+    own_subnet_id: SubnetId,
+    log: &ReplicaLogger,
+) {
+    let network_topology = &state.metadata.network_topology;
+    let mut migrated_canister_ids = Vec::new();
+
+    for (canister_id, canister) in state.canister_states.iter_mut() {
+        if network_topology.is_migrating_from(*canister_id, own_subnet_id) {
+            if let CanisterStatus::Running {
+                call_context_manager,
+            } = &canister.system_state.status
+            {
+                info!(log, "Stopping canister {} for migration", canister_id);
+                canister.system_state.status = CanisterStatus::Stopping {
+                    call_context_manager: call_context_manager.clone(),
+                    stop_contexts: vec![],
+                };
+            }
+        } else if canister.status() == CanisterStatusType::Stopped
+            && network_topology
+                .canister_migrations
+                .lookup(*canister_id)
+                .is_none()
+        {
+            let destination = match network_topology.routing_table.route(canister_id.get()) {
+                Some(subnet_id) if subnet_id != own_subnet_id => subnet_id,
+                _ => continue,
+            };
+            match verify_canister_import(network_topology, canister, destination) {
+                Ok(()) => migrated_canister_ids.push(*canister_id),
+                Err(err) => warn!(
+                    every_n_seconds => 60,
+                    log,
+                    "Keeping migrated canister {} until subnet {} confirms its import: {}",
+                    canister_id,
+                    destination,
+                    err
+                ),
+            }
+        }
+    }
+
+    for canister_id in migrated_canister_ids {
+        info!(
+            log,
+            "Dropping canister {} after its migration was completed", canister_id
+        );
+        state.take_canister_state(&canister_id);
+    }
+}
+
+/// Checks that `destination` imported `canister`, i.e. that the import
+/// certificate recorded in `canister_imports` is a certificate of the
+/// `destination` subnet's state tree, valid w.r.t. the NNS subnet's public key,
+/// showing the canister with the same module hash as `canister`.
+///
+/// The certificate is issued by the destination subnet, so this confirmation
+/// does not depend on anything but the registry and the destination's state.
+fn verify_canister_import(
+    network_topology: &NetworkTopology,
+    canister: &CanisterState,
+    destination: SubnetId,
+) -> Result<(), String> {
+    let canister_id = canister.canister_id();
+    let certificate = network_topology
+        .canister_imports
+        .get(&canister_id)
+        .and_then(|import| import.import_certificate.as_ref())
+        .ok_or("no import certificate was recorded")?;
+
+    let nns_subnet = network_topology
+        .subnets
+        .get(&network_topology.nns_subnet_id)
+        .ok_or("the NNS subnet is not part of the network topology")?;
+    let nns_public_key = parse_threshold_sig_key_from_der(&nns_subnet.public_key)
+        .map_err(|err| format!("invalid NNS subnet public key: {}", err))?;
+    let certificate = verify_certificate_with_cache(certificate, &canister_id, &nns_public_key)
+        .map_err(|err| format!("invalid import certificate: {}", err))?;
+
+    let certifying_subnet = match &certificate.delegation {
+        Some(delegation) => PrincipalId::try_from(&delegation.subnet_id.0[..])
+            .map(SubnetId::from)
+            .map_err(|err| format!("invalid delegation subnet ID: {}", err))?,
+        None => network_topology.nns_subnet_id,
+    };
+    if certifying_subnet != destination {
+        return Err(format!(
+            "the import certificate was issued by subnet {}",
+            certifying_subnet
+        ));
+    }
+
+    let canister_path =
+        |label: &'static [u8]| [&b"canister"[..], canister_id.get_ref().as_slice(), label];
+    if !certificate
+        .tree
+        .lookup(&canister_path(b"controllers"))
+        .is_found()
+    {
+        return Err("the import certificate does not show the canister".to_string());
+    }
+    let certified_module_hash = match certificate.tree.lookup(&canister_path(b"module_hash")) {
+        LookupStatus::Found(MixedHashTree::Leaf(module_hash)) => Some(module_hash.clone()),
+        LookupStatus::Absent => None,
+        _ => {
+            return Err(
+                "the import certificate does not certify the canister's module hash".to_string(),
+            )
+        }
+    };
+    let module_hash = canister
+        .execution_state
+        .as_ref()
+        .map(|es| es.wasm_binary.binary.module_hash().to_vec());
+    if certified_module_hash != module_hash {
+        return Err(format!(
+            "the imported canister has module hash {:?}, expected {:?}",
+            certified_module_hash.map(hex::encode),
+            module_hash.map(hex::encode)
+        ));
+    }
+
+    Ok(())
+}
@@ -500,6 +500,7 @@ fn simulate_http_request_cost(subnet_type: SubnetType, subnet_size: usize) -> Cy
                         }),
                         context: vec![],
                     }),
+                    is_replicated: None,
                 })
                 .unwrap(),
             ),
@@ -191,6 +191,7 @@ pub fn default_get_latest_state() -> Labeled<Arc<ReplicatedState>> {
         subnets: BTreeMap::new(),
         routing_table: Arc::new(RoutingTable::default()),
         canister_migrations: Arc::new(CanisterMigrations::default()),
+        canister_imports: Default::default(),
         nns_subnet_id: subnet_test_id(1),
         ecdsa_signing_subnets: Default::default(),
         bitcoin_mainnet_canister_id: None,
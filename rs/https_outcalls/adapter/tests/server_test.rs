@@ -115,9 +115,26 @@ MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgob29X4H4m2XOkSZE
             .and(warp::path("head"))
             .map(|| warp::reply::reply());
 
+        let basic_put = warp::put()
+            .and(warp::path("put"))
+            .and(warp::body::json())
+            .map(|req: u64| Response::builder().body(req.to_string()));
+
+        let basic_patch = warp::patch()
+            .and(warp::path("patch"))
+            .and(warp::body::json())
+            .map(|req: u64| Response::builder().body(req.to_string()));
+
+        let basic_delete = warp::delete()
+            .and(warp::path("delete"))
+            .map(|| warp::reply::with_status(warp::reply(), StatusCode::NO_CONTENT));
+
         let routes = basic_post
             .or(basic_get)
             .or(basic_head)
+            .or(basic_put)
+            .or(basic_patch)
+            .or(basic_delete)
             .or(get_response_size)
             .or(get_delay)
             .or(invalid_header);
@@ -229,6 +246,77 @@ MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgob29X4H4m2XOkSZE
         assert_eq!(http_response.status, StatusCode::OK.as_u16() as u32);
     }
 
+    #[tokio::test]
+    async fn test_canister_http_server_put() {
+        let server_config = Config {
+            ..Default::default()
+        };
+
+        let url = start_server(CERT_INIT.get_or_init(generate_certs));
+        let mut client = spawn_grpc_server(server_config);
+
+        let request = tonic::Request::new(CanisterHttpSendRequest {
+            url: format!("https://{}/put", &url),
+            headers: Vec::new(),
+            method: HttpMethod::Put as i32,
+            body: "420".to_string().as_bytes().to_vec(),
+            max_response_size_bytes: 512,
+            socks_proxy_allowed: false,
+        });
+
+        let response = client.canister_http_send(request).await;
+        let http_response = response.unwrap().into_inner();
+        assert_eq!(http_response.status, StatusCode::OK.as_u16() as u32);
+        assert_eq!(String::from_utf8_lossy(&http_response.content), "420");
+    }
+
+    #[tokio::test]
+    async fn test_canister_http_server_patch() {
+        let server_config = Config {
+            ..Default::default()
+        };
+
+        let url = start_server(CERT_INIT.get_or_init(generate_certs));
+        let mut client = spawn_grpc_server(server_config);
+
+        let request = tonic::Request::new(CanisterHttpSendRequest {
+            url: format!("https://{}/patch", &url),
+            headers: Vec::new(),
+            method: HttpMethod::Patch as i32,
+            body: "420".to_string().as_bytes().to_vec(),
+            max_response_size_bytes: 512,
+            socks_proxy_allowed: false,
+        });
+
+        let response = client.canister_http_send(request).await;
+        let http_response = response.unwrap().into_inner();
+        assert_eq!(http_response.status, StatusCode::OK.as_u16() as u32);
+        assert_eq!(String::from_utf8_lossy(&http_response.content), "420");
+    }
+
+    #[tokio::test]
+    async fn test_canister_http_server_delete() {
+        let server_config = Config {
+            ..Default::default()
+        };
+
+        let url = start_server(CERT_INIT.get_or_init(generate_certs));
+        let mut client = spawn_grpc_server(server_config);
+
+        let request = tonic::Request::new(CanisterHttpSendRequest {
+            url: format!("https://{}/delete", &url),
+            headers: Vec::new(),
+            method: HttpMethod::Delete as i32,
+            body: "".to_string().as_bytes().to_vec(),
+            max_response_size_bytes: 512,
+            socks_proxy_allowed: false,
+        });
+
+        let response = client.canister_http_send(request).await;
+        let http_response = response.unwrap().into_inner();
+        assert_eq!(http_response.status, StatusCode::NO_CONTENT.as_u16() as u32);
+    }
+
     #[tokio::test]
     async fn test_response_limit_exceeded() {
         // Check if response with higher than allowed response limit is rejected.
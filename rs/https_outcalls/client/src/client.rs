@@ -148,6 +148,9 @@ impl NonBlockingChannel<CanisterHttpRequest> for CanisterHttpAdapterClientImpl {
                         CanisterHttpMethod::GET => HttpMethod::Get.into(),
                         CanisterHttpMethod::POST => HttpMethod::Post.into(),
                         CanisterHttpMethod::HEAD => HttpMethod::Head.into(),
+                        CanisterHttpMethod::PUT => HttpMethod::Put.into(),
+                        CanisterHttpMethod::PATCH => HttpMethod::Patch.into(),
+                        CanisterHttpMethod::DELETE => HttpMethod::Delete.into(),
                     },
                     max_response_size_bytes: request_max_response_bytes.unwrap_or(NumBytes::new(MAX_CANISTER_HTTP_RESPONSE_BYTES)).get(),
                     headers: request_headers
@@ -330,7 +333,7 @@ mod tests {
     use ic_test_utilities::{mock_time, types::messages::RequestBuilder};
     use ic_types::canister_http::Transform;
     use ic_types::{
-        canister_http::CanisterHttpMethod,
+        canister_http::{CanisterHttpMethod, Replication},
         messages::{Blob, CallbackId},
         Time,
     };
@@ -423,6 +426,7 @@ mod tests {
                     context: vec![],
                 }),
                 time: mock_time(),
+                replication: Replication::FullyReplicated,
             },
         }
     }
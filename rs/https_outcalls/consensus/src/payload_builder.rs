@@ -32,7 +32,7 @@ use ic_types::{
     canister_http::{
         CanisterHttpResponse, CanisterHttpResponseContent, CanisterHttpResponseDivergence,
         CanisterHttpResponseMetadata, CanisterHttpResponseProof, CanisterHttpResponseWithConsensus,
-        CANISTER_HTTP_MAX_RESPONSES_PER_BLOCK, CANISTER_HTTP_TIMEOUT_INTERVAL,
+        Replication, CANISTER_HTTP_MAX_RESPONSES_PER_BLOCK, CANISTER_HTTP_TIMEOUT_INTERVAL,
     },
     consensus::Committee,
     crypto::Signed,
@@ -42,7 +42,7 @@ use ic_types::{
     CanisterId, CountBytes, Cycles, Height, NodeId, NumBytes, RegistryVersion, SubnetId,
 };
 use std::{
-    collections::{BTreeSet, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
     mem::size_of,
     sync::{Arc, RwLock},
 };
@@ -197,6 +197,8 @@ impl CanisterHttpPayloadBuilderImpl {
         let mut candidates = vec![];
         let mut timeouts = vec![];
         let mut divergence_responses = vec![];
+        // The designated node of each non-replicated request
+        let mut non_replicated_nodes = BTreeMap::new();
 
         // Metrics counters
         let mut unique_includable_responses = 0;
@@ -223,6 +225,9 @@ impl CanisterHttpPayloadBuilderImpl {
                 .canister_http_request_contexts
                 .iter()
             {
+                if let Replication::NonReplicated(node_id) = request.replication {
+                    non_replicated_nodes.insert(*callback_id, node_id);
+                }
                 unique_includable_responses += 1;
                 let candidate_size = callback_id.count_bytes();
                 let size = NumBytes::new((accumulated_size + candidate_size) as u64);
@@ -276,7 +281,26 @@ impl CanisterHttpPayloadBuilderImpl {
 
             let candidates_and_divergences = response_candidates_by_callback_id
                 .into_iter()
-                .filter_map(|(_, grouped_shares)| {
+                .filter_map(|(callback_id, grouped_shares)| {
+                    // A non-replicated response is included as soon as the share of
+                    // the designated node is available. Only that share is used.
+                    if let Some(node_id) = non_replicated_nodes.get(&callback_id) {
+                        unique_responses_count += 1;
+                        return grouped_shares.iter().find_map(|(metadata, shares)| {
+                            let share = shares
+                                .iter()
+                                .find(|share| share.signature.signer == *node_id)?;
+                            pool_access
+                                .get_response_content_by_hash(&metadata.content_hash)
+                                .map(|content| {
+                                    CandidateOrDivergence::Candidate((
+                                        metadata.clone(),
+                                        BTreeSet::from([share.signature.clone()]),
+                                        content,
+                                    ))
+                                })
+                        });
+                    }
                     if let Some((metadata, shares)) = grouped_shares.iter().find(|(_, shares)| {
                         unique_responses_count += 1;
                         let signers: BTreeSet<_> =
@@ -476,6 +500,36 @@ impl CanisterHttpPayloadBuilderImpl {
         // NOTE: We do this in a separate loop because this check is expensive and we want to
         // do all the cheap checks first
         for response in &payload.responses {
+            // A non-replicated response must be signed by exactly the designated node
+            if let Some(Replication::NonReplicated(node_id)) = http_contexts
+                .get(&response.content.id)
+                .map(|context| &context.replication)
+            {
+                let signers: Vec<NodeId> = response
+                    .proof
+                    .signature
+                    .signatures_map
+                    .keys()
+                    .cloned()
+                    .collect();
+                if signers != vec![*node_id] {
+                    return Err(CanisterHttpPayloadValidationError::Permanent(
+                        CanisterHttpPermanentValidationError::InvalidNonReplicatedSigners {
+                            expected: *node_id,
+                            signers,
+                        },
+                    ));
+                }
+                self.crypto
+                    .verify_aggregate(&response.proof, consensus_registry_version)
+                    .map_err(|err| {
+                        CanisterHttpPayloadValidationError::Permanent(
+                            CanisterHttpPermanentValidationError::SignatureError(Box::new(err)),
+                        )
+                    })?;
+                continue;
+            }
+
             let threshold = match self
                 .membership
                 .get_committee_threshold(height, Committee::CanisterHttp)
@@ -533,6 +587,25 @@ impl CanisterHttpPayloadBuilderImpl {
         };
 
         for response in &payload.divergence_responses {
+            // Non-replicated requests can not diverge, since only a single node makes them
+            for share in response.shares.iter() {
+                if let Some(Replication::NonReplicated(node_id)) = http_contexts
+                    .get(&share.content.id)
+                    .map(|context| &context.replication)
+                {
+                    return Err(CanisterHttpPayloadValidationError::Permanent(
+                        CanisterHttpPermanentValidationError::InvalidNonReplicatedSigners {
+                            expected: *node_id,
+                            signers: response
+                                .shares
+                                .iter()
+                                .map(|share| share.signature.signer)
+                                .collect(),
+                        },
+                    ));
+                }
+            }
+
             let (valid_signers, invalid_signers): (Vec<NodeId>, Vec<NodeId>) = response
                 .shares
                 .iter()
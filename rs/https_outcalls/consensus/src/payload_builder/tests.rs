@@ -37,7 +37,7 @@ use ic_types::{
     canister_http::{
         CanisterHttpMethod, CanisterHttpRequestContext, CanisterHttpResponse,
         CanisterHttpResponseContent, CanisterHttpResponseDivergence, CanisterHttpResponseMetadata,
-        CanisterHttpResponseShare, CanisterHttpResponseWithConsensus,
+        CanisterHttpResponseShare, CanisterHttpResponseWithConsensus, Replication,
         CANISTER_HTTP_MAX_RESPONSES_PER_BLOCK, CANISTER_HTTP_TIMEOUT_INTERVAL,
     },
     consensus::get_faults_tolerated,
@@ -324,6 +324,7 @@ fn timeout_priority() {
                     transform: None,
                     // this is the important one
                     time: mock_time(),
+                    replication: Replication::FullyReplicated,
                 };
                 init_state
                     .metadata
@@ -761,6 +762,109 @@ fn divergence_response_validation_test() {
     }
 }
 
+/// Check that a non-replicated response is included with the share of the
+/// designated node only, and that responses signed by other nodes are rejected
+#[test]
+fn non_replicated_request_test() {
+    let context = default_validation_context();
+    let designated_node = 2;
+
+    test_config_with_http_feature(4, |mut payload_builder, canister_http_pool| {
+        let (response, metadata) = test_response_and_metadata(0);
+        let shares = metadata_to_shares(4, &metadata);
+
+        {
+            let mut init_state = ic_test_utilities::state::get_initial_state(0, 0);
+            init_state
+                .metadata
+                .subnet_call_context_manager
+                .canister_http_request_contexts
+                .insert(
+                    response.id,
+                    CanisterHttpRequestContext {
+                        request: RequestBuilder::default().build(),
+                        url: String::new(),
+                        max_response_bytes: None,
+                        headers: vec![],
+                        body: None,
+                        http_method: CanisterHttpMethod::GET,
+                        transform: None,
+                        time: mock_time(),
+                        replication: Replication::NonReplicated(node_test_id(designated_node)),
+                    },
+                );
+            let state_manager = Arc::new(RefMockStateManager::default());
+            state_manager
+                .get_mut()
+                .expect_get_state_at()
+                .return_const(Ok(ic_interfaces_state_manager::Labeled::new(
+                    Height::new(0),
+                    Arc::new(init_state),
+                )));
+            payload_builder.state_reader = state_manager;
+
+            // Add the shares of all nodes to the pool
+            let mut pool_access = canister_http_pool.write().unwrap();
+            add_own_share_to_pool(pool_access.deref_mut(), &shares[0], &response);
+            add_received_shares_to_pool(pool_access.deref_mut(), shares[1..].to_vec());
+        }
+
+        // Build a payload
+        let payload = payload_builder.build_payload(
+            Height::new(1),
+            NumBytes::new(4 * 1024 * 1024),
+            &[],
+            &context,
+        );
+
+        // The response is only signed by the designated node
+        let parsed_payload = bytes_to_payload(&payload).expect("Failed to parse the payload");
+        assert_eq!(parsed_payload.num_responses(), 1);
+        assert_eq!(parsed_payload.responses[0].content, response);
+        assert_eq!(
+            parsed_payload.responses[0]
+                .proof
+                .signature
+                .signatures_map
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>(),
+            vec![node_test_id(designated_node)]
+        );
+        assert!(payload_builder
+            .validate_payload(Height::new(1), &payload, &[], &context)
+            .is_ok());
+
+        // A response signed by the whole committee is rejected
+        let mut proof = response_and_metadata_to_proof(&response, &metadata);
+        proof.proof.signature.signatures_map = shares
+            .iter()
+            .map(|share| (share.signature.signer, share.signature.signature.clone()))
+            .collect();
+        let payload = CanisterHttpPayload {
+            responses: vec![proof],
+            timeouts: vec![],
+            divergence_responses: vec![],
+        };
+        let payload = payload_to_bytes(&payload, NumBytes::new(4 * 1024 * 1024));
+
+        match payload_builder.validate_payload(Height::new(1), &payload, &[], &context) {
+            Err(ValidationError::Permanent(
+                PayloadPermanentError::CanisterHttpPayloadValidationError(
+                    CanisterHttpPermanentValidationError::InvalidNonReplicatedSigners {
+                        expected,
+                        signers,
+                    },
+                ),
+            )) => {
+                assert_eq!(expected, node_test_id(designated_node));
+                assert_eq!(signers.len(), 4);
+            }
+            x => panic!("Expected InvalidNonReplicatedSigners, got {:?}", x),
+        }
+    });
+}
+
 /// Build some test metadata and response, which is valid and can be used in
 /// different tests
 pub(crate) fn test_response_and_metadata(
@@ -187,6 +187,12 @@ impl CanisterHttpPoolManagerImpl {
             .collect();
 
         for (id, context) in http_requests {
+            // Non-replicated requests are only made by the designated node.
+            if let Replication::NonReplicated(node_id) = context.replication {
+                if node_id != self.replica_config.node_id {
+                    continue;
+                }
+            }
             if !request_ids_already_made.contains(&id) {
                 let timeout = context.time + Duration::from_secs(5 * 60);
                 if let Err(err) = self
@@ -290,6 +296,15 @@ impl CanisterHttpPoolManagerImpl {
             return Vec::new();
         };
 
+        let http_requests = self
+            .state_reader
+            .get_latest_state()
+            .get_ref()
+            .metadata
+            .subnet_call_context_manager
+            .canister_http_request_contexts
+            .clone();
+
         canister_http_pool
             .get_unvalidated_shares()
             .filter_map(|share| {
@@ -316,6 +331,20 @@ impl CanisterHttpPoolManagerImpl {
                             .to_string(),
                     ));
                 }
+                if let Some(Replication::NonReplicated(node_id)) = http_requests
+                    .get(&share.content.id)
+                    .map(|context| &context.replication)
+                {
+                    if *node_id != share.signature.signer {
+                        self.metrics.shares_marked_invalid.inc();
+                        return Some(CanisterHttpChangeAction::HandleInvalid(
+                            ic_types::crypto::crypto_hash(share),
+                            "Share for a non-replicated request signed by a node other than \
+                            the designated node"
+                                .to_string(),
+                        ));
+                    }
+                }
                 // TODO: more precise error handling
                 if let Err(err) = self.crypto.verify(share, registry_version) {
                     error!(self.log, "Unable to verify signature of share, {}", err);
@@ -403,13 +432,13 @@ pub mod test {
     use ic_artifact_pool::canister_http_pool::CanisterHttpPoolImpl;
     use ic_consensus_mocks::{dependencies, Dependencies};
     use ic_consensus_utils::crypto::SignVerify;
-    use ic_interfaces::artifact_pool::MutablePool;
+    use ic_interfaces::artifact_pool::{MutablePool, UnvalidatedArtifact};
     use ic_interfaces::time_source::SysTimeSource;
     use ic_interfaces_state_manager::Labeled;
     use ic_logger::replica_logger::no_op_logger;
     use ic_metrics::MetricsRegistry;
     use ic_registry_subnet_type::SubnetType;
-    use ic_test_utilities::types::ids::subnet_test_id;
+    use ic_test_utilities::types::ids::{node_test_id, subnet_test_id};
     use ic_test_utilities_logger::with_test_replica_logger;
     use ic_types::{
         crypto::{CryptoHash, CryptoHashOf},
@@ -483,6 +512,7 @@ pub mod test {
                     http_method: CanisterHttpMethod::GET,
                     transform: None,
                     time: ic_types::Time::from_nanos_since_unix_epoch(10),
+                    replication: Replication::FullyReplicated,
                 };
 
                 state_manager
@@ -632,6 +662,7 @@ pub mod test {
                     http_method: CanisterHttpMethod::GET,
                     transform: None,
                     time: ic_types::Time::from_nanos_since_unix_epoch(10),
+                    replication: Replication::FullyReplicated,
                 };
 
                 // Expect times to be called exactly once to check that already
@@ -709,4 +740,113 @@ pub mod test {
             });
         });
     }
+
+    #[test]
+    pub fn test_non_replicated_requests_only_made_by_designated_node() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            with_test_replica_logger(|log| {
+                let Dependencies {
+                    pool,
+                    replica_config,
+                    crypto,
+                    state_manager,
+                    registry,
+                    membership,
+                    ..
+                } = dependencies(pool_config.clone(), 4);
+                let mut shim_mock = MockNonBlockingChannel::<CanisterHttpRequest>::new();
+                shim_mock
+                    .expect_try_receive()
+                    .return_const(Err(TryReceiveError::Empty));
+
+                let request = |node_id| CanisterHttpRequestContext {
+                    request: ic_test_utilities::types::messages::RequestBuilder::new().build(),
+                    url: "".to_string(),
+                    max_response_bytes: None,
+                    headers: vec![],
+                    body: None,
+                    http_method: CanisterHttpMethod::GET,
+                    transform: None,
+                    time: ic_types::Time::from_nanos_since_unix_epoch(10),
+                    replication: Replication::NonReplicated(node_id),
+                };
+                let own_request = request(replica_config.node_id);
+                let other_request = request(node_test_id(1000));
+
+                // Only the request assigned to this node is made.
+                shim_mock
+                    .expect_send()
+                    .with(eq(CanisterHttpRequest {
+                        id: CallbackId::from(7),
+                        timeout: ic_types::Time::from_nanos_since_unix_epoch(10)
+                            + Duration::from_secs(60 * 5),
+                        context: own_request.clone(),
+                    }))
+                    .times(1)
+                    .return_const(Ok(()));
+
+                let shim: Arc<Mutex<CanisterHttpAdapterClient>> =
+                    Arc::new(Mutex::new(Box::new(shim_mock)));
+
+                state_manager
+                    .get_mut()
+                    .expect_get_latest_state()
+                    .return_const(Labeled::new(
+                        Height::from(1),
+                        Arc::new(state_with_pending_http_calls(BTreeMap::from([
+                            (CallbackId::from(7), own_request),
+                            (CallbackId::from(8), other_request),
+                        ]))),
+                    ));
+
+                let pool_manager = CanisterHttpPoolManagerImpl::new(
+                    state_manager,
+                    shim,
+                    crypto.clone(),
+                    membership,
+                    pool.get_cache(),
+                    replica_config.clone(),
+                    Arc::clone(&registry) as Arc<_>,
+                    MetricsRegistry::new(),
+                    log,
+                );
+                let mut canister_http_pool =
+                    CanisterHttpPoolImpl::new(MetricsRegistry::new(), no_op_logger());
+                assert!(pool_manager
+                    .generate_change_set(&canister_http_pool)
+                    .is_empty());
+
+                // A share for the request assigned to another node is invalid.
+                let response_metadata = CanisterHttpResponseMetadata {
+                    id: CallbackId::from(8),
+                    timeout: ic_types::Time::from_nanos_since_unix_epoch(10),
+                    registry_version: RegistryVersion::from(1),
+                    content_hash: CryptoHashOf::new(CryptoHash(vec![])),
+                };
+                let signature = crypto
+                    .sign(
+                        &response_metadata,
+                        replica_config.node_id,
+                        RegistryVersion::from(1),
+                    )
+                    .unwrap();
+                let share = Signed {
+                    content: response_metadata,
+                    signature,
+                };
+                canister_http_pool.insert(UnvalidatedArtifact {
+                    message: share,
+                    peer_id: replica_config.node_id,
+                    timestamp: Time::from_nanos_since_unix_epoch(0),
+                });
+
+                let change_set = pool_manager.generate_change_set(&canister_http_pool);
+                assert_eq!(change_set.len(), 1);
+                assert!(matches!(
+                    change_set[0],
+                    CanisterHttpChangeAction::HandleInvalid(_, _)
+                ));
+            });
+        });
+    }
 }
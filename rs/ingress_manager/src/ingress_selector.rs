@@ -11,7 +11,7 @@ use ic_interfaces::{
         IngressPayloadValidationError, IngressPermanentError, IngressSelector, IngressSetQuery,
         IngressTransientError,
     },
-    ingress_pool::{IngressPoolSelect, SelectResult},
+    ingress_pool::{IngressPoolObject, IngressPoolSelect},
     validation::{ValidationError, ValidationResult},
 };
 use ic_interfaces_state_manager::StateManagerError;
@@ -27,7 +27,10 @@ use ic_types::{
     CanisterId, CountBytes, Cycles, Height, NumBytes, Time,
 };
 use ic_validator::RequestValidationError;
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Arc,
+};
 
 impl IngressSelector for IngressManager {
     fn get_ingress_payload(
@@ -83,36 +86,42 @@ impl IngressSelector for IngressManager {
 
         let mut messages_in_payload = self.ingress_pool.select_validated(
             expiry_range,
-            Box::new(move |ingress_obj| {
-                let result = self.validate_ingress(
-                    IngressMessageId::from(ingress_obj),
-                    &ingress_obj.signed_ingress,
-                    &state,
-                    context,
-                    &settings,
-                    &past_ingress_set,
-                    num_messages,
-                    &mut cycles_needed,
-                );
-                match result {
-                    Ok(()) => {
-                        num_messages += 1;
-                        // Calculate the size and abort once we have hit the limit
-                        accumulated_size += ingress_obj.signed_ingress.count_bytes();
-                        if accumulated_size > byte_limit.get() as usize {
-                            return SelectResult::Abort;
+            Box::new(move |ingress_objs| {
+                let mut selected = Vec::new();
+                // Interleave the candidates across canisters and senders, so that no
+                // single sender can monopolize the payload.
+                for ingress_obj in round_robin(ingress_objs) {
+                    let result = self.validate_ingress(
+                        IngressMessageId::from(ingress_obj),
+                        &ingress_obj.signed_ingress,
+                        &state,
+                        context,
+                        &settings,
+                        &past_ingress_set,
+                        num_messages,
+                        &mut cycles_needed,
+                    );
+                    match result {
+                        Ok(()) => {
+                            num_messages += 1;
+                            // Calculate the size and abort once we have hit the limit
+                            accumulated_size += ingress_obj.signed_ingress.count_bytes();
+                            if accumulated_size > byte_limit.get() as usize {
+                                break;
+                            }
+
+                            selected.push(ingress_obj.signed_ingress.clone());
                         }
-
-                        SelectResult::Selected(ingress_obj.signed_ingress.clone())
+                        Err(ValidationError::Permanent(
+                            IngressPermanentError::IngressPayloadTooBig(_, _),
+                        )) => break,
+                        Err(ValidationError::Permanent(
+                            IngressPermanentError::IngressPayloadTooManyMessages(_, _),
+                        )) => break,
+                        _ => (),
                     }
-                    Err(ValidationError::Permanent(
-                        IngressPermanentError::IngressPayloadTooBig(_, _),
-                    )) => SelectResult::Abort,
-                    Err(ValidationError::Permanent(
-                        IngressPermanentError::IngressPayloadTooManyMessages(_, _),
-                    )) => SelectResult::Abort,
-                    _ => SelectResult::Skip,
                 }
+                selected
             }),
         );
 
@@ -287,6 +296,56 @@ impl IngressSelector for IngressManager {
     }
 }
 
+/// Orders the given objects, which must be sorted by the time they were delivered to the
+/// pool, in a round-robin fashion: first across canisters, and then, for each canister,
+/// across the senders of messages to that canister. The messages of each sender to a canister
+/// are ordered by expiry time, so that those about to expire are included first; messages with
+/// the same expiry time keep their relative order. As the expiry time only decides the order
+/// among the messages of the same sender, it can't be used to get ahead of other senders.
+fn round_robin(ingress_objs: Vec<&IngressPoolObject>) -> Vec<&IngressPoolObject> {
+    let total = ingress_objs.len();
+    // For each canister, in the order of first appearance, a queue of senders, each with
+    // its queue of messages.
+    let mut canisters: Vec<VecDeque<VecDeque<&IngressPoolObject>>> = Vec::new();
+    let mut canister_positions = HashMap::new();
+    let mut sender_positions = HashMap::new();
+    for ingress_obj in ingress_objs {
+        let ingress = &ingress_obj.signed_ingress;
+        let canister_id = ingress.canister_id();
+        let canister_position = *canister_positions.entry(canister_id).or_insert_with(|| {
+            canisters.push(VecDeque::new());
+            canisters.len() - 1
+        });
+        let senders = &mut canisters[canister_position];
+        let sender_position = *sender_positions
+            .entry((canister_id, ingress.sender()))
+            .or_insert_with(|| {
+                senders.push_back(VecDeque::new());
+                senders.len() - 1
+            });
+        senders[sender_position].push_back(ingress_obj);
+    }
+    for messages in canisters.iter_mut().flatten() {
+        messages
+            .make_contiguous()
+            .sort_by_key(|ingress_obj| ingress_obj.signed_ingress.expiry_time());
+    }
+
+    let mut ordered = Vec::with_capacity(total);
+    while !canisters.is_empty() {
+        canisters.retain_mut(|senders| {
+            if let Some(mut messages) = senders.pop_front() {
+                ordered.extend(messages.pop_front());
+                if !messages.is_empty() {
+                    senders.push_back(messages);
+                }
+            }
+            !senders.is_empty()
+        });
+    }
+    ordered
+}
+
 impl IngressManager {
     #[allow(clippy::too_many_arguments)]
     fn validate_ingress(
@@ -1849,4 +1908,73 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_round_robin_prioritizes_expiry_within_sender() {
+        // Sender 1 sends three messages, the last one expiring first. The expiry time of
+        // sender 1's messages doesn't put them ahead of sender 2's.
+        let messages = [(0, 1, 30), (1, 1, 20), (2, 2, 50), (3, 1, 10), (4, 2, 40)];
+        let ingress_objs: Vec<_> = messages
+            .into_iter()
+            .map(|(nonce, sender, expiry_secs)| {
+                IngressPoolObject::from(
+                    SignedIngressBuilder::new()
+                        .nonce(nonce)
+                        .sender(user_test_id(sender))
+                        .canister_id(canister_test_id(1))
+                        .expiry_time(mock_time() + Duration::from_secs(expiry_secs))
+                        .build(),
+                )
+            })
+            .collect();
+
+        let ordered = round_robin(ingress_objs.iter().collect());
+        assert_eq!(
+            ordered
+                .iter()
+                .map(|ingress_obj| ingress_obj.signed_ingress.nonce().unwrap())
+                .collect::<Vec<_>>(),
+            [3, 4, 1, 2, 0]
+                .iter()
+                .map(|nonce: &u64| nonce.to_le_bytes().to_vec())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_round_robin_interleaves_canisters_and_senders() {
+        // Sender 1 floods canister 1 before senders 2 and 3 send their messages.
+        let messages = [
+            (0, 1, 1),
+            (1, 1, 1),
+            (2, 1, 1),
+            (3, 2, 1),
+            (4, 3, 2),
+            (5, 3, 2),
+        ];
+        let ingress_objs: Vec<_> = messages
+            .into_iter()
+            .map(|(nonce, sender, canister)| {
+                IngressPoolObject::from(
+                    SignedIngressBuilder::new()
+                        .nonce(nonce)
+                        .sender(user_test_id(sender))
+                        .canister_id(canister_test_id(canister))
+                        .build(),
+                )
+            })
+            .collect();
+
+        let ordered = round_robin(ingress_objs.iter().collect());
+        assert_eq!(
+            ordered
+                .iter()
+                .map(|ingress_obj| ingress_obj.signed_ingress.nonce().unwrap())
+                .collect::<Vec<_>>(),
+            [0, 4, 3, 5, 1, 2]
+                .iter()
+                .map(|nonce: &u64| nonce.to_le_bytes().to_vec())
+                .collect::<Vec<_>>()
+        );
+    }
 }
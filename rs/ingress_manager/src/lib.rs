@@ -13,7 +13,7 @@ use ic_cycles_account_manager::CyclesAccountManager;
 use ic_interfaces::{
     consensus_pool::ConsensusPoolCache,
     execution_environment::IngressHistoryReader,
-    ingress_pool::{IngressPoolObject, IngressPoolSelect, SelectResult},
+    ingress_pool::{IngressPoolObject, IngressPoolSelect},
 };
 use ic_interfaces_registry::RegistryClient;
 use ic_interfaces_state_manager::StateReader;
@@ -67,7 +67,7 @@ impl IngressPoolSelect for IngressPoolSelectWrapper {
     fn select_validated<'a>(
         &self,
         range: RangeInclusive<Time>,
-        f: Box<dyn FnMut(&IngressPoolObject) -> SelectResult<SignedIngress> + 'a>,
+        f: Box<dyn FnOnce(Vec<&IngressPoolObject>) -> Vec<SignedIngress> + 'a>,
     ) -> Vec<SignedIngress> {
         let pool = self.pool.read().unwrap();
         pool.select_validated(range, f)
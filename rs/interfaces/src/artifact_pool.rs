@@ -74,6 +74,21 @@ pub trait PriorityFnAndFilterProducer<Artifact: ArtifactKind, Pool>: Send + Sync
     }
 }
 
+/// Allows sharing a producer between the P2P stacks that transmit the same artifacts.
+impl<Artifact: ArtifactKind, Pool, T> PriorityFnAndFilterProducer<Artifact, Pool>
+    for std::sync::Arc<T>
+where
+    T: PriorityFnAndFilterProducer<Artifact, Pool> + ?Sized,
+{
+    fn get_priority_function(&self, pool: &Pool) -> PriorityFn<Artifact::Id, Artifact::Attribute> {
+        self.as_ref().get_priority_function(pool)
+    }
+
+    fn get_filter(&self) -> Artifact::Filter {
+        self.as_ref().get_filter()
+    }
+}
+
 /// ValidatedPoolReader trait is the generic interface used by P2P to interact
 /// with the validated portion of an artifact pool without resulting in any mutations.
 /// Every pool needs to implement this trait.
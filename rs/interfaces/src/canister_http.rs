@@ -65,6 +65,11 @@ pub enum CanisterHttpPermanentValidationError {
         signers: Vec<NodeId>,
         expected_threshold: Threshold,
     },
+    /// A non-replicated response was not signed by exactly the designated node
+    InvalidNonReplicatedSigners {
+        expected: NodeId,
+        signers: Vec<NodeId>,
+    },
     /// The payload contains a duplicate response
     DuplicateResponse(CallbackId),
     DivergenceProofContainsMultipleCallbackIds,
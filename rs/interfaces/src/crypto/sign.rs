@@ -194,6 +194,24 @@ pub trait MultiSigVerifier<T: Signable> {
         registry_version: RegistryVersion,
     ) -> CryptoResult<()>;
 
+    /// Verifies a batch of individual multi-signatures.
+    ///
+    /// Each entry of `shares` consists of an individual multi-signature, the
+    /// signed message, and the signer. The messages may differ between
+    /// entries. The batch is verified at once using a randomized linear
+    /// combination, which is considerably cheaper than verifying the
+    /// signatures one by one. If the batch verification fails, the signatures
+    /// are verified individually to identify the invalid ones.
+    ///
+    /// Returns one result per entry of `shares`, in the same order. The
+    /// possible errors per entry are the same as for
+    /// `verify_multi_sig_individual`.
+    fn verify_multi_sig_individual_batch(
+        &self,
+        shares: &[(&IndividualMultiSigOf<T>, &T, NodeId)],
+        registry_version: RegistryVersion,
+    ) -> Vec<CryptoResult<()>>;
+
     /// Combines individual multi-signature shares.
     ///
     /// The registry version is not needed for the cryptographic scheme we use
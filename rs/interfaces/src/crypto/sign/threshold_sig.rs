@@ -71,6 +71,29 @@ pub trait ThresholdSigVerifier<T: Signable> {
         signer: NodeId,
     ) -> CryptoResult<()>;
 
+    /// Verifies a batch of threshold signature shares.
+    ///
+    /// Each entry of `shares` consists of a threshold signature share, the
+    /// signed message, and the signer. The messages may differ between
+    /// entries. The batch is verified at once using a randomized linear
+    /// combination, which is considerably cheaper than verifying the shares
+    /// one by one. If the batch verification fails, the shares are verified
+    /// individually to identify the invalid ones.
+    ///
+    /// See the trait's doc comment for applicable preconditions.
+    ///
+    /// Returns one result per entry of `shares`, in the same order. The
+    /// possible errors per entry are the same as for
+    /// `verify_threshold_sig_share`.
+    ///
+    /// # Panics
+    /// In the same cases as `verify_threshold_sig_share`.
+    fn verify_threshold_sig_share_batch(
+        &self,
+        shares: &[(&ThresholdSigShareOf<T>, &T, NodeId)],
+        dkg_id: NiDkgId,
+    ) -> Vec<CryptoResult<()>>;
+
     /// Combines the given threshold signature `shares`.
     ///
     /// See the trait's doc comment for applicable preconditions.
@@ -116,25 +116,18 @@ pub trait IngressPool {
     fn unvalidated(&self) -> &dyn PoolSection<UnvalidatedIngressArtifact>;
 }
 
-/// Indicate whether something should be selected, and whether selection should
-/// continue:
-/// - 'Selected': select the object and continue;
-/// - 'Skip': skip the object and continue;
-/// - 'Abort': abort the selection process.
-pub enum SelectResult<T> {
-    Selected(T),
-    Skip,
-    Abort,
-}
-
 /// A query interface that selects qualifying artifacts from the validated pool.
 #[allow(clippy::type_complexity)]
 pub trait IngressPoolSelect: Send + Sync {
     /// Select qualifying objects from the validated pool.
+    ///
+    /// All validated objects whose expiry lies in `range` are passed to `f`,
+    /// sorted by the time they were delivered to the pool. `f` returns the
+    /// selected messages, in the order they should be included.
     fn select_validated<'a>(
         &self,
         range: std::ops::RangeInclusive<Time>,
-        f: Box<dyn FnMut(&IngressPoolObject) -> SelectResult<SignedIngress> + 'a>,
+        f: Box<dyn FnOnce(Vec<&IngressPoolObject>) -> Vec<SignedIngress> + 'a>,
     ) -> Vec<SignedIngress>;
 }
 
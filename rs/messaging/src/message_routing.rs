@@ -764,6 +764,11 @@ impl BatchProcessorImpl {
             .get_canister_migrations(registry_version)
             .map_err(|err| registry_error("canister migrations", None, err))?
             .unwrap_or_default();
+        let canister_imports = self
+            .registry
+            .get_canister_imports(registry_version)
+            .map_err(|err| registry_error("canister imports", None, err))?
+            .unwrap_or_default();
 
         let nns_subnet_id = self
             .registry
@@ -781,6 +786,7 @@ impl BatchProcessorImpl {
             routing_table: Arc::new(routing_table),
             nns_subnet_id,
             canister_migrations: Arc::new(canister_migrations),
+            canister_imports: Arc::new(canister_imports),
             ecdsa_signing_subnets,
             bitcoin_testnet_canister_id: self.bitcoin_config.testnet_canister_id,
             bitcoin_mainnet_canister_id: self.bitcoin_config.mainnet_canister_id,
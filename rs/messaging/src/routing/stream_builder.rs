@@ -16,7 +16,7 @@ use ic_types::{
         MAX_INTER_CANISTER_PAYLOAD_IN_BYTES, MAX_REJECT_MESSAGE_LEN_BYTES,
     },
     xnet::QueueId,
-    CountBytes, SubnetId,
+    CanisterId, CountBytes, SubnetId,
 };
 #[cfg(test)]
 use mockall::automock;
@@ -34,6 +34,15 @@ struct StreamBuilderMetrics {
     pub stream_bytes: IntGaugeVec,
     /// Stream begin, by destination subnet.
     pub stream_begin: IntGaugeVec,
+    /// Number of distinct canisters with messages in the stream, by destination
+    /// subnet.
+    pub stream_senders: IntGaugeVec,
+    /// Byte size of the messages of the canister with the largest share of the
+    /// stream, by destination subnet.
+    pub stream_largest_sender_bytes: IntGaugeVec,
+    /// Output queues held back because their sender exceeded its fair share of
+    /// the stream, by destination subnet.
+    pub fair_share_exceeded: IntCounterVec,
     /// Routed XNet messages, by type and status.
     pub routed_messages: IntCounterVec,
     /// Successfully routed XNet messages' total payload size.
@@ -61,6 +70,9 @@ const MAX_STREAM_MESSAGES: usize = 50_000;
 const METRIC_STREAM_MESSAGES: &str = "mr_stream_messages";
 const METRIC_STREAM_BYTES: &str = "mr_stream_bytes";
 const METRIC_STREAM_BEGIN: &str = "mr_stream_begin";
+const METRIC_STREAM_SENDERS: &str = "mr_stream_senders";
+const METRIC_STREAM_LARGEST_SENDER_BYTES: &str = "mr_stream_largest_sender_bytes";
+const METRIC_FAIR_SHARE_EXCEEDED: &str = "mr_stream_fair_share_exceeded_count";
 const METRIC_ROUTED_MESSAGES: &str = "mr_routed_message_count";
 const METRIC_ROUTED_PAYLOAD_SIZES: &str = "mr_routed_payload_size_bytes";
 
@@ -96,6 +108,21 @@ impl StreamBuilderMetrics {
             "Stream begin, by destination subnet",
             &[LABEL_REMOTE],
         );
+        let stream_senders = metrics_registry.int_gauge_vec(
+            METRIC_STREAM_SENDERS,
+            "Number of distinct canisters with messages in the stream, by destination subnet.",
+            &[LABEL_REMOTE],
+        );
+        let stream_largest_sender_bytes = metrics_registry.int_gauge_vec(
+            METRIC_STREAM_LARGEST_SENDER_BYTES,
+            "Byte size of the messages of the canister with the largest share of the stream, by destination subnet.",
+            &[LABEL_REMOTE],
+        );
+        let fair_share_exceeded = metrics_registry.int_counter_vec(
+            METRIC_FAIR_SHARE_EXCEEDED,
+            "Output queues held back because their sender exceeded its fair share of the stream, by destination subnet.",
+            &[LABEL_REMOTE],
+        );
         let routed_messages = metrics_registry.int_counter_vec(
             METRIC_ROUTED_MESSAGES,
             "Routed XNet messages, by type and status.",
@@ -134,6 +161,9 @@ impl StreamBuilderMetrics {
             stream_messages,
             stream_bytes,
             stream_begin,
+            stream_senders,
+            stream_largest_sender_bytes,
+            fair_share_exceeded,
             routed_messages,
             routed_payload_sizes,
             critical_error_infinite_loops,
@@ -286,6 +316,31 @@ impl StreamBuilderImpl {
                 && stream_messages_len >= 2 * SYSTEM_SUBNET_STREAM_MSG_LIMIT
         }
 
+        /// Tests whether `sender` already takes up at least its fair share of
+        /// `target_stream_size_bytes` in the stream, i.e. `1 / n` of it, where
+        /// `n` is the number of canisters with messages in the stream.
+        ///
+        /// A sole sender may use the whole stream, but as soon as other
+        /// canisters route messages into it, the stream is shared equally
+        /// between them, so a single canister flooding a destination subnet
+        /// cannot starve all other canisters on this subnet of XNet throughput.
+        fn exceeds_fair_share(
+            stream: Option<&Stream>,
+            sender: CanisterId,
+            target_stream_size_bytes: usize,
+        ) -> bool {
+            let size_bytes_by_sender = match stream {
+                Some(stream) => stream.messages_size_bytes_by_sender(),
+                None => return false,
+            };
+            match size_bytes_by_sender.get(&sender) {
+                Some(sender_size_bytes) => {
+                    *sender_size_bytes >= target_stream_size_bytes / size_bytes_by_sender.len()
+                }
+                None => false,
+            }
+        }
+
         let mut streams = state.take_streams();
         let routing_table = state.routing_table();
         let subnet_types: BTreeMap<_, _> = state
@@ -341,6 +396,24 @@ impl StreamBuilderImpl {
                         continue;
                     }
 
+                    if let RequestOrResponse::Request(req) = &msg {
+                        if exceeds_fair_share(
+                            streams.get(&dst_net_id),
+                            req.sender,
+                            target_stream_size_bytes,
+                        ) {
+                            // Sender is over its fair share of the stream, hold back
+                            // its requests to this destination. Responses are always
+                            // routed, as their slots have already been reserved.
+                            self.metrics
+                                .fair_share_exceeded
+                                .with_label_values(&[&dst_net_id.to_string()])
+                                .inc();
+                            output_iter.exclude_queue();
+                            continue;
+                        }
+                    }
+
                     // We will route (or reject) the message, pop it.
                     let mut msg = validated_next(&mut output_iter, (queue_id, &msg));
 
@@ -491,6 +564,21 @@ impl StreamBuilderImpl {
                     .set(begin.get() as i64);
             });
 
+        // Export the number of senders and the largest contribution of any one
+        // sender, per stream.
+        for (subnet, stream) in streams.iter() {
+            let subnet = subnet.to_string();
+            let size_bytes_by_sender = stream.messages_size_bytes_by_sender();
+            self.metrics
+                .stream_senders
+                .with_label_values(&[&subnet])
+                .set(size_bytes_by_sender.len() as i64);
+            self.metrics
+                .stream_largest_sender_bytes
+                .with_label_values(&[&subnet])
+                .set(size_bytes_by_sender.values().max().copied().unwrap_or(0) as i64);
+        }
+
         {
             // Record the enqueuing time of any messages newly enqueued into `streams`.
             let mut time_in_stream_metrics = self.time_in_stream_metrics.lock().unwrap();
@@ -552,6 +552,151 @@ fn build_streams_impl_respects_message_limit() {
     build_streams_impl_respects_limits(4, 1_000_000, 4);
 }
 
+// Tests that requests from a canister that already takes up its fair share of a
+// stream are held back, while other canisters' requests are still routed.
+#[test]
+fn build_streams_impl_holds_back_requests_over_fair_share() {
+    with_test_replica_logger(|log| {
+        let (stream_builder, mut provided_state, metrics_registry) = new_fixture(&log);
+        provided_state.metadata.network_topology.routing_table = Arc::new(RoutingTable::try_from(
+            btreemap! {
+                CanisterIdRange{ start: CanisterId::from(0), end: CanisterId::from(0xfff) } => REMOTE_SUBNET,
+            },
+        ).unwrap());
+
+        let flooder = canister_test_id(3);
+        let other = canister_test_id(4);
+        let receiver = canister_test_id(700);
+        // All generated requests have the same byte size.
+        let request = |sender: CanisterId, i: u64| {
+            generate_message_for_test(
+                sender,
+                receiver,
+                CallbackId::from(i),
+                format!("req_{:02}", i),
+                Cycles::new(100),
+            )
+        };
+
+        // The stream already holds 12 requests from `flooder` and 4 from `other`.
+        let mut streams = provided_state.take_streams();
+        let mut stream = streams.get_mut_or_insert(REMOTE_SUBNET);
+        for i in 0..12 {
+            stream.push(request(flooder, i).into());
+        }
+        for i in 0..4 {
+            stream.push(request(other, i).into());
+        }
+        provided_state.put_streams(streams);
+        let msg_size = RequestOrResponse::from(request(flooder, 0)).count_bytes();
+
+        // Both canisters have more requests in their output queues.
+        let mut msgs: Vec<Request> = (12..16).map(|i| request(flooder, i)).collect();
+        msgs.extend((4..6).map(|i| request(other, i)));
+        provided_state.put_canister_states(canister_states_with_outputs(msgs));
+
+        // With two senders in the stream, `flooder` takes up exactly its fair
+        // share (half of the target stream size), even though the stream has
+        // plenty of room left.
+        let result_state =
+            stream_builder.build_streams_impl(provided_state, usize::MAX, 24 * msg_size);
+
+        let stream = result_state.get_stream(&REMOTE_SUBNET).unwrap();
+        assert_eq!(18, stream.messages().len());
+        assert_eq!(
+            &btreemap! {
+                flooder => 12 * msg_size,
+                other => 6 * msg_size,
+            },
+            stream.messages_size_bytes_by_sender()
+        );
+        let output_queues_message_count = |canister_id: &CanisterId| {
+            result_state
+                .canister_state(canister_id)
+                .unwrap()
+                .system_state
+                .queues()
+                .output_queues_message_count()
+        };
+        assert_eq!(4, output_queues_message_count(&flooder));
+        assert_eq!(0, output_queues_message_count(&other));
+
+        assert_eq!(
+            metric_vec(&[(&[(LABEL_REMOTE, &REMOTE_SUBNET.to_string())], 1)]),
+            fetch_int_counter_vec(&metrics_registry, METRIC_FAIR_SHARE_EXCEEDED)
+        );
+        assert_eq!(
+            metric_vec(&[(&[(LABEL_REMOTE, &REMOTE_SUBNET.to_string())], 2)]),
+            fetch_int_gauge_vec(&metrics_registry, METRIC_STREAM_SENDERS)
+        );
+        assert_eq!(
+            metric_vec(&[(
+                &[(LABEL_REMOTE, &REMOTE_SUBNET.to_string())],
+                12 * msg_size as u64
+            )]),
+            fetch_int_gauge_vec(&metrics_registry, METRIC_STREAM_LARGEST_SENDER_BYTES)
+        );
+    });
+}
+
+// Tests that a canister that is the only sender into a stream is not held back
+// by the fair share limit.
+#[test]
+fn build_streams_impl_sole_sender_may_use_whole_stream() {
+    with_test_replica_logger(|log| {
+        let (stream_builder, mut provided_state, metrics_registry) = new_fixture(&log);
+        provided_state.metadata.network_topology.routing_table = Arc::new(RoutingTable::try_from(
+            btreemap! {
+                CanisterIdRange{ start: CanisterId::from(0), end: CanisterId::from(0xfff) } => REMOTE_SUBNET,
+            },
+        ).unwrap());
+
+        let sender = canister_test_id(3);
+        let receiver = canister_test_id(700);
+        let request = |i: u64| {
+            generate_message_for_test(
+                sender,
+                receiver,
+                CallbackId::from(i),
+                format!("req_{:02}", i),
+                Cycles::new(100),
+            )
+        };
+
+        // The stream already holds 12 requests from `sender`, half of the target
+        // stream size.
+        let mut streams = provided_state.take_streams();
+        let mut stream = streams.get_mut_or_insert(REMOTE_SUBNET);
+        for i in 0..12 {
+            stream.push(request(i).into());
+        }
+        provided_state.put_streams(streams);
+        let msg_size = RequestOrResponse::from(request(0)).count_bytes();
+        provided_state.put_canister_states(canister_states_with_outputs(
+            (12..16).map(request).collect(),
+        ));
+
+        let result_state =
+            stream_builder.build_streams_impl(provided_state, usize::MAX, 24 * msg_size);
+
+        let stream = result_state.get_stream(&REMOTE_SUBNET).unwrap();
+        assert_eq!(16, stream.messages().len());
+        assert_eq!(
+            0,
+            result_state
+                .canister_state(&sender)
+                .unwrap()
+                .system_state
+                .queues()
+                .output_queues_message_count()
+        );
+        assert_eq!(
+            MetricVec::new(),
+            fetch_int_counter_vec(&metrics_registry, METRIC_FAIR_SHARE_EXCEEDED)
+        );
+    });
+}
+
 // Tests that messages addressed to canisters not mapped to a known subnet
 // result in reject Responses.
 #[test]
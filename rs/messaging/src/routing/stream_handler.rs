@@ -2,6 +2,7 @@ use crate::message_routing::LatencyMetrics;
 use ic_base_types::NumBytes;
 use ic_certification_version::CertificationVersion;
 use ic_config::execution_environment::Config as HypervisorConfig;
+use ic_config::flag_status::FlagStatus;
 use ic_error_types::RejectCode;
 use ic_logger::{debug, error, fatal, trace, ReplicaLogger};
 use ic_metrics::{
@@ -176,6 +177,10 @@ pub(crate) struct StreamHandlerImpl {
 
     subnet_message_memory_capacity: NumBytes,
 
+    /// Whether requests to canisters whose output queues hold
+    /// `DEFAULT_OUTPUT_QUEUES_CAPACITY` messages are rejected.
+    canister_output_queues_capacity: FlagStatus,
+
     metrics: StreamHandlerMetrics,
     /// Per-destination-subnet histogram of wall time spent by messages in the
     /// stream before they are garbage collected.
@@ -201,6 +206,7 @@ impl StreamHandlerImpl {
         Self {
             subnet_id,
             subnet_message_memory_capacity: hypervisor_config.subnet_message_memory_capacity,
+            canister_output_queues_capacity: hypervisor_config.canister_output_queues_capacity,
             metrics: StreamHandlerMetrics::new(metrics_registry),
             time_in_stream_metrics,
             time_in_backlog_metrics: RefCell::new(LatencyMetrics::new_time_in_backlog(
@@ -556,7 +562,9 @@ impl StreamHandlerImpl {
     ///    to the reverse stream;
     ///  * `Request` not inducted (queue full, out of memory, canister not
     ///    found, canister migrated): accept signal and reject response appended
-    ///    to the reverse stream;
+    ///    to the reverse stream. If `canister_output_queues_capacity` is
+    ///    enabled, this includes requests to canisters whose output queues are
+    ///    at capacity, signaling backpressure to senders on other subnets;
     ///  * `Response` not inducted (canister migrated): reject signal appended
     ///    to loopback stream (canonical versions 9+ only).
     ///  * `Request` or `Response` silently dropped and accept signal appended
@@ -596,43 +604,47 @@ impl StreamHandlerImpl {
             let payload_size = msg.payload_size_bytes().get();
             match receiver_host_subnet {
                 // Matching receiver subnet, try inducting message.
-                Some(host_subnet) if host_subnet == self.subnet_id => match state
-                    .push_input(msg, subnet_available_memory)
-                {
-                    // Message successfully inducted, all done.
-                    Ok(()) => {
-                        self.observe_inducted_message_status(msg_type, LABEL_VALUE_SUCCESS);
-                        self.observe_inducted_payload_size(payload_size);
-                    }
+                Some(host_subnet) if host_subnet == self.subnet_id => {
+                    match self.push_input(state, msg, subnet_available_memory) {
+                        // Message successfully inducted, all done.
+                        Ok(()) => {
+                            self.observe_inducted_message_status(msg_type, LABEL_VALUE_SUCCESS);
+                            self.observe_inducted_payload_size(payload_size);
+                        }
 
-                    // Message not inducted.
-                    Err((err, msg)) => {
-                        self.observe_inducted_message_status(msg_type, err.to_label_value());
+                        // Message not inducted.
+                        Err((err, msg)) => {
+                            self.observe_inducted_message_status(msg_type, err.to_label_value());
 
-                        match msg {
-                            RequestOrResponse::Request(_) => {
-                                debug!(
+                            match msg {
+                                RequestOrResponse::Request(_) => {
+                                    debug!(
                                     self.log,
                                     "Induction failed with error '{}', generating reject Response for {:?}",
                                     &err,
                                     &msg
                                 );
-                                let code = reject_code_for_state_error(&err);
-                                stream.push(generate_reject_response(msg, code, err.to_string()))
-                            }
-                            RequestOrResponse::Response(response) => {
-                                // Critical error, responses should always be inducted successfully.
-                                error!(
-                                    self.log,
-                                    "{}: Inducting response failed: {:?}",
-                                    CRITICAL_ERROR_INDUCT_RESPONSE_FAILED,
-                                    response
-                                );
-                                self.metrics.critical_error_induct_response_failed.inc()
+                                    let code = reject_code_for_state_error(&err);
+                                    stream.push(generate_reject_response(
+                                        msg,
+                                        code,
+                                        err.to_string(),
+                                    ))
+                                }
+                                RequestOrResponse::Response(response) => {
+                                    // Critical error, responses should always be inducted successfully.
+                                    error!(
+                                        self.log,
+                                        "{}: Inducting response failed: {:?}",
+                                        CRITICAL_ERROR_INDUCT_RESPONSE_FAILED,
+                                        response
+                                    );
+                                    self.metrics.critical_error_induct_response_failed.inc()
+                                }
                             }
                         }
                     }
-                },
+                }
 
                 // Receiver canister is migrating to/from this subnet.
                 Some(host_subnet) if self.should_reroute_message_to(&msg, host_subnet, state) => {
@@ -713,6 +725,27 @@ impl StreamHandlerImpl {
         stream.increment_signals_end();
     }
 
+    /// Pushes `msg` into the induction pool of `state`. If enabled, requests to
+    /// canisters whose output queues are at capacity are rejected with
+    /// `QueueFull`, before any memory is reserved for them.
+    fn push_input(
+        &self,
+        state: &mut ReplicatedState,
+        msg: RequestOrResponse,
+        subnet_available_memory: &mut i64,
+    ) -> Result<(), (StateError, RequestOrResponse)> {
+        if let (FlagStatus::Enabled, RequestOrResponse::Request(req)) =
+            (self.canister_output_queues_capacity, &msg)
+        {
+            if let Some(canister) = state.canister_state(&req.receiver) {
+                if let Err(err) = canister.system_state.queues().check_has_output_capacity() {
+                    return Err((err, msg));
+                }
+            }
+        }
+        state.push_input(msg, subnet_available_memory)
+    }
+
     /// Checks whether `actual_subnet_id` is a valid host subnet for `msg.sender()`
     /// (i.e. whether it is its current host according to the routing table; or it
     /// and the known host subnet are both on the path of a canister migration
@@ -126,6 +126,28 @@ pub trait ImageUpgrader<V: Clone + Debug + PartialEq + Eq + Send + Sync, R: Send
         }
     }
 
+    /// Calls a corresponding script to revert to the previous image, which is
+    /// only possible as long as the boot of the current image was not
+    /// confirmed. On success, the node reboots into the previous image.
+    async fn rollback(&self) -> UpgradeResult<()> {
+        info!(self.log(), "Attempting to roll back to the previous image");
+        let script = self.binary_dir().join("manageboot.sh");
+        let mut cmd = Command::new(script.into_os_string());
+        let out = cmd
+            .arg("rollback")
+            .output()
+            .await
+            .map_err(|e| UpgradeError::file_command_error(e, &cmd))?;
+
+        if !out.status.success() {
+            warn!(self.log(), "rollback has failed: {:?}", out.status);
+            Err(UpgradeError::GenericError("rollback failed".to_string()))
+        } else {
+            info!(self.log(), "Rebooting {:?}", out);
+            exit(42);
+        }
+    }
+
     /// Return a value that would differentiate the nodes (but not necessarily unique) in order
     /// to allow them to download the new release package from different URLs.
     fn get_load_balance_number(&self) -> usize;
@@ -1,6 +1,7 @@
 use crate::{
     catch_up_package_provider::CatchUpPackageProvider, registry_helper::RegistryHelper,
     replica_process::ReplicaProcess, ssh_access_manager::SshAccessParameters,
+    upgrade_health::UpgradeHealth,
 };
 use async_trait::async_trait;
 pub use ic_dashboard::Dashboard;
@@ -22,6 +23,7 @@ pub(crate) struct OrchestratorDashboard {
     subnet_id: Arc<RwLock<Option<SubnetId>>>,
     replica_version: ReplicaVersion,
     cup_provider: Arc<CatchUpPackageProvider>,
+    upgrade_health: Arc<RwLock<UpgradeHealth>>,
     logger: ReplicaLogger,
 }
 
@@ -40,6 +42,7 @@ impl Dashboard for OrchestratorDashboard {
              replica process id: {}\n\
              replica version: {}\n\
              scheduled upgrade: {}\n\
+             last upgrade health: {}\n\
              {}\n\
              firewall config registry version: {}\n\
              {}\n\
@@ -53,6 +56,7 @@ impl Dashboard for OrchestratorDashboard {
             self.get_pid(),
             self.replica_version,
             self.get_scheduled_upgrade().await,
+            *self.upgrade_health.read().await,
             self.get_local_cup_info(),
             *self.last_applied_firewall_version.read().await,
             self.display_last_applied_ssh_parameters().await,
@@ -77,6 +81,7 @@ impl OrchestratorDashboard {
         subnet_id: Arc<RwLock<Option<SubnetId>>>,
         replica_version: ReplicaVersion,
         cup_provider: Arc<CatchUpPackageProvider>,
+        upgrade_health: Arc<RwLock<UpgradeHealth>>,
         logger: ReplicaLogger,
     ) -> Self {
         Self {
@@ -88,6 +93,7 @@ impl OrchestratorDashboard {
             subnet_id,
             replica_version,
             cup_provider,
+            upgrade_health,
             logger,
         }
     }
@@ -26,6 +26,11 @@
 //! 5. If the version is different from what we are currently running, apply
 //! upgrade and restart replica with that CUP.
 //!
+//! After booting into a new version, the orchestrator only confirms the boot
+//! once the node passed a number of health gates. If a gate fails before the
+//! subnet made progress on the new version, the upgrade is rolled back to the
+//! previous image.
+//!
 //! # Registry
 //!
 //! The orchestrator also fetches configuration updates from the
@@ -45,3 +50,4 @@ mod replica_process;
 mod signer;
 mod ssh_access_manager;
 mod upgrade;
+mod upgrade_health;
@@ -17,6 +17,8 @@ pub struct OrchestratorMetrics {
     pub key_rotation_status: IntGaugeVec,
     pub ecdsa_key_changed_errors: IntCounterVec,
     pub failed_consecutive_upgrade_checks: IntCounter,
+    pub upgrade_health_status: IntGaugeVec,
+    pub upgrade_health_failed_gate: IntGaugeVec,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, Eq, IntoStaticStr, PartialOrd, Ord, PartialEq)]
@@ -42,6 +44,14 @@ impl KeyRotationStatus {
     }
 }
 
+#[derive(Copy, Clone, Debug, EnumIter, Eq, IntoStaticStr, PartialOrd, Ord, PartialEq)]
+pub enum UpgradeHealthStatus {
+    Monitoring,
+    Healthy,
+    Unhealthy,
+    RolledBack,
+}
+
 impl OrchestratorMetrics {
     pub fn new(metrics_registry: &ic_metrics::MetricsRegistry) -> Self {
         Self {
@@ -88,6 +98,16 @@ impl OrchestratorMetrics {
                 "orchestrator_failed_consecutive_upgrade_checks_total",
                 "Number of times the upgrade check failed consecutively",
             ),
+            upgrade_health_status: metrics_registry.int_gauge_vec(
+                "orchestrator_upgrade_health_status",
+                "The health status of the last upgrade.",
+                &["status"],
+            ),
+            upgrade_health_failed_gate: metrics_registry.int_gauge_vec(
+                "orchestrator_upgrade_health_failed_gate",
+                "The health gate that failed after the last upgrade.",
+                &["gate"],
+            ),
         }
     }
 
@@ -110,4 +130,13 @@ impl OrchestratorMetrics {
             .with_label_values(&[KeyRotationStatus::Error.into()])
             .set(1);
     }
+
+    /// Set the health status of the last upgrade to the given status and clear all other states.
+    pub fn observe_upgrade_health_status(&self, status: UpgradeHealthStatus) {
+        UpgradeHealthStatus::iter().for_each(|s| {
+            self.upgrade_health_status
+                .with_label_values(&[s.into()])
+                .set((s == status) as i64);
+        });
+    }
 }
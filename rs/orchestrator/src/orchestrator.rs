@@ -203,23 +203,22 @@ impl Orchestrator {
             registration.register_node().await;
         }
 
-        let upgrade = Some(
-            Upgrade::new(
-                Arc::clone(&registry),
-                Arc::clone(&metrics),
-                Arc::clone(&replica_process),
-                Arc::clone(&cup_provider),
-                replica_version.clone(),
-                args.replica_config_file.clone(),
-                node_id,
-                ic_binary_directory,
-                registry_replicator,
-                args.replica_binary_dir.clone(),
-                logger.clone(),
-                args.orchestrator_data_directory.clone(),
-            )
-            .await,
-        );
+        let upgrade = Upgrade::new(
+            Arc::clone(&registry),
+            Arc::clone(&metrics),
+            Arc::clone(&replica_process),
+            Arc::clone(&cup_provider),
+            replica_version.clone(),
+            args.replica_config_file.clone(),
+            node_id,
+            ic_binary_directory,
+            registry_replicator,
+            args.replica_binary_dir.clone(),
+            logger.clone(),
+            args.orchestrator_data_directory.clone(),
+        )
+        .await;
+        let upgrade_health = upgrade.get_upgrade_health();
 
         let firewall = Firewall::new(
             node_id,
@@ -244,6 +243,7 @@ impl Orchestrator {
             Arc::clone(&subnet_id),
             replica_version,
             cup_provider,
+            upgrade_health,
             logger.clone(),
         ));
 
@@ -253,7 +253,7 @@ impl Orchestrator {
             logger,
             _async_log_guard,
             _metrics_runtime,
-            upgrade,
+            upgrade: Some(upgrade),
             firewall: Some(firewall),
             ssh_access_manager: Some(ssh_access_manager),
             orchestrator_dashboard,
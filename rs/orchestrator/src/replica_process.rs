@@ -21,6 +21,8 @@ pub(crate) struct ReplicaProcess {
     pub(crate) pid_cell: PIDCell,
     pub(crate) log: slog::Logger,
     pub(crate) join_handle: Option<std::thread::JoinHandle<()>>,
+    /// The number of replica processes started so far.
+    pub(crate) start_count: usize,
 }
 
 impl ReplicaProcess {
@@ -30,6 +32,7 @@ impl ReplicaProcess {
             pid_cell: Default::default(),
             log: logger.clone(),
             join_handle: None,
+            start_count: 0,
         }
     }
 
@@ -38,6 +41,11 @@ impl ReplicaProcess {
         self.get_pid().is_some()
     }
 
+    /// Returns the number of replica processes started so far.
+    pub fn start_count(&self) -> usize {
+        self.start_count
+    }
+
     /// Returns the `Pid` if the currently running replica; or `None` if no
     /// replica is running.
     pub fn get_pid(&self) -> Option<Pid> {
@@ -139,6 +147,7 @@ impl ReplicaProcess {
                 .spawn()?;
             debug!(self.log, "Process started. Pid: {}", child.id());
             self.set_pid(Pid::from_raw(child.id() as i32));
+            self.start_count += 1;
 
             self.join_handle = Some(std::thread::spawn(wait_on_exit(
                 self.log.clone(),
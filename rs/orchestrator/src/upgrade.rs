@@ -1,9 +1,14 @@
 use crate::catch_up_package_provider::CatchUpPackageProvider;
 use crate::error::{OrchestratorError, OrchestratorResult};
-use crate::metrics::OrchestratorMetrics;
+use crate::metrics::{OrchestratorMetrics, UpgradeHealthStatus};
 use crate::registry_helper::RegistryHelper;
 use crate::replica_process::ReplicaProcess;
+use crate::upgrade_health::{
+    HealthObservation, HealthVerdict, UpgradeHealth, UpgradeHealthMonitor, UpgradeRecord,
+    UPGRADE_HEALTH_TIMEOUT, UPGRADE_RECORD_FILENAME,
+};
 use async_trait::async_trait;
+use ic_canister_client::{Agent, HttpClient, Sender};
 use ic_crypto::get_tecdsa_master_public_key;
 use ic_http_utils::file_downloader::FileDownloader;
 use ic_ic00_types::EcdsaKeyId;
@@ -17,10 +22,13 @@ use ic_registry_local_store::LocalStoreImpl;
 use ic_registry_replicator::RegistryReplicator;
 use ic_types::consensus::{CatchUpPackage, HasHeight};
 use ic_types::crypto::canister_threshold_sig::MasterEcdsaPublicKey;
+use ic_types::messages::HttpStatusResponse;
 use ic_types::{Height, NodeId, RegistryVersion, ReplicaVersion, SubnetId};
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+use url::Url;
 
 const KEY_CHANGES_FILENAME: &str = "key_changed_metric.cbor";
 
@@ -43,6 +51,13 @@ pub(crate) struct Upgrade {
     /// The replica version that is prepared by 'prepare_upgrade' to upgrade to.
     pub prepared_upgrade_version: Option<ReplicaVersion>,
     pub orchestrator_data_directory: PathBuf,
+    /// Observes the health gates after an upgrade, as long as the boot of the
+    /// current image is not confirmed.
+    health_monitor: Option<UpgradeHealthMonitor>,
+    /// The replica version of an upgrade that was rolled back and must not be
+    /// retried.
+    rolled_back_version: Option<ReplicaVersion>,
+    upgrade_health: Arc<RwLock<UpgradeHealth>>,
 }
 
 impl Upgrade {
@@ -61,7 +76,7 @@ impl Upgrade {
         logger: ReplicaLogger,
         orchestrator_data_directory: PathBuf,
     ) -> Self {
-        let value = Self {
+        let mut value = Self {
             registry,
             metrics,
             replica_process,
@@ -75,6 +90,9 @@ impl Upgrade {
             logger: logger.clone(),
             prepared_upgrade_version: None,
             orchestrator_data_directory,
+            health_monitor: None,
+            rolled_back_version: None,
+            upgrade_health: Default::default(),
         };
         if let Err(e) = value.report_reboot_time() {
             warn!(logger, "Cannot report the reboot time: {}", e);
@@ -85,10 +103,222 @@ impl Upgrade {
         ) {
             warn!(logger, "Cannot report ECDSA key changed metric: {}", e);
         }
-        value.confirm_boot().await;
+        value.confirm_boot_or_monitor_upgrade_health().await;
         value
     }
 
+    pub(crate) fn get_upgrade_health(&self) -> Arc<RwLock<UpgradeHealth>> {
+        Arc::clone(&self.upgrade_health)
+    }
+
+    fn upgrade_record_path(&self) -> PathBuf {
+        self.orchestrator_data_directory
+            .join(UPGRADE_RECORD_FILENAME)
+    }
+
+    // Confirms the boot of the current image, unless it was booted by an upgrade whose health
+    // gates have not been passed yet. Also reports upgrades that were rolled back.
+    async fn confirm_boot_or_monitor_upgrade_health(&mut self) {
+        let record = match UpgradeRecord::load(&self.upgrade_record_path()) {
+            Ok(record) => record,
+            Err(e) => {
+                warn!(self.logger, "Cannot load the upgrade record: {}", e);
+                None
+            }
+        };
+        match record {
+            Some(record)
+                if record.target_version == self.replica_version
+                    && record.failed_gate.is_none() =>
+            {
+                info!(
+                    self.logger,
+                    "Checking the health gates of the upgrade {} -> {} before confirming the boot",
+                    record.previous_version,
+                    record.target_version
+                );
+                self.metrics
+                    .observe_upgrade_health_status(UpgradeHealthStatus::Monitoring);
+                *self.upgrade_health.write().await = UpgradeHealth::Monitoring(record.clone());
+                self.health_monitor =
+                    Some(UpgradeHealthMonitor::new(record, UPGRADE_HEALTH_TIMEOUT));
+                return;
+            }
+            Some(record) if record.previous_version == self.replica_version => {
+                warn!(
+                    self.logger,
+                    "The upgrade {} -> {} was rolled back, failed gate: {:?}",
+                    record.previous_version,
+                    record.target_version,
+                    record.failed_gate
+                );
+                self.metrics
+                    .observe_upgrade_health_status(UpgradeHealthStatus::RolledBack);
+                if let Some(gate) = record.failed_gate {
+                    self.metrics
+                        .upgrade_health_failed_gate
+                        .with_label_values(&[gate.into()])
+                        .set(1);
+                }
+                self.rolled_back_version = Some(record.target_version.clone());
+                *self.upgrade_health.write().await = UpgradeHealth::RolledBack(record);
+            }
+            Some(_) => {
+                // The record is stale, e.g. because the rollback of an unhealthy upgrade failed.
+                self.remove_upgrade_record();
+            }
+            None => {}
+        }
+        self.confirm_boot().await;
+    }
+
+    fn remove_upgrade_record(&self) {
+        if let Err(e) = UpgradeRecord::remove(&self.upgrade_record_path()) {
+            warn!(self.logger, "Cannot remove the upgrade record: {}", e);
+        }
+    }
+
+    // Forgets about a rolled back upgrade once the registry does not ask for its version anymore.
+    async fn clear_rolled_back_upgrade(&mut self) {
+        if self.rolled_back_version.take().is_some() {
+            self.remove_upgrade_record();
+            self.metrics.upgrade_health_status.reset();
+            self.metrics.upgrade_health_failed_gate.reset();
+            *self.upgrade_health.write().await = UpgradeHealth::None;
+        }
+    }
+
+    // Persists the upgrade record, so that the next boot checks the health gates of the upgrade,
+    // and executes the upgrade.
+    async fn execute_upgrade_with_health_gates(
+        &mut self,
+        version: &ReplicaVersion,
+        cup_height: Option<Height>,
+    ) -> OrchestratorResult<()> {
+        if self.rolled_back_version.as_ref() == Some(version) {
+            return Err(OrchestratorError::UpgradeError(format!(
+                "Not retrying the upgrade to version {}, which was rolled back",
+                version
+            )));
+        }
+        self.prepare_upgrade(version).await?;
+        UpgradeRecord {
+            previous_version: self.replica_version.clone(),
+            target_version: version.clone(),
+            cup_height,
+            failed_gate: None,
+        }
+        .persist(&self.upgrade_record_path())?;
+        // This only returns if the upgrade failed.
+        let result = self.execute_upgrade(version).await;
+        self.remove_upgrade_record();
+        result.map_err(OrchestratorError::from)
+    }
+
+    // Observes the health gates of the current upgrade given the result of the last upgrade
+    // check. Confirms the boot once all gates passed. If a gate failed, rolls back to the
+    // previous image if possible.
+    async fn check_upgrade_health(&mut self, check_result: &OrchestratorResult<Option<SubnetId>>) {
+        let local_cup_height = self.cup_provider.get_local_cup().map(|cup| cup.height());
+        let replica_starts = self.replica_process.lock().unwrap().start_count();
+        let observation = match (check_result, local_cup_height) {
+            (Ok(None), _) => HealthObservation::Unassigned,
+            (Ok(Some(_)), Some(cup_height)) => HealthObservation::Assigned {
+                cup_height,
+                replica_starts,
+                finalized_height: self.get_finalized_height(local_cup_height),
+                status: self.get_replica_status().await,
+            },
+            _ => HealthObservation::CheckFailed,
+        };
+        let (verdict, mut record) = match self.health_monitor.as_mut() {
+            Some(monitor) => (monitor.observe(observation), monitor.record().clone()),
+            None => return,
+        };
+
+        match verdict {
+            HealthVerdict::Pending => return,
+            HealthVerdict::Healthy => {
+                info!(
+                    self.logger,
+                    "The upgrade {} -> {} passed all health gates",
+                    record.previous_version,
+                    record.target_version
+                );
+                self.metrics
+                    .observe_upgrade_health_status(UpgradeHealthStatus::Healthy);
+                *self.upgrade_health.write().await = UpgradeHealth::Healthy(record);
+            }
+            HealthVerdict::Unhealthy(gate) => {
+                error!(
+                    self.logger,
+                    "The upgrade {} -> {} failed the health gate {:?}",
+                    record.previous_version,
+                    record.target_version,
+                    gate
+                );
+                self.metrics
+                    .upgrade_health_failed_gate
+                    .with_label_values(&[gate.into()])
+                    .set(1);
+                record.failed_gate = Some(gate);
+                if record.can_roll_back(local_cup_height) {
+                    match record.persist(&self.upgrade_record_path()) {
+                        Ok(()) => {
+                            if let Err(e) = self.stop_replica() {
+                                warn!(self.logger, "{}", e);
+                            }
+                            // This only returns if the rollback failed.
+                            if let Err(e) = self.rollback().await {
+                                error!(self.logger, "Failed to roll back the upgrade: {}", e);
+                            }
+                        }
+                        Err(e) => warn!(self.logger, "Cannot persist the upgrade record: {}", e),
+                    }
+                } else {
+                    warn!(
+                        self.logger,
+                        "Not rolling back the upgrade, as the subnet made progress on version {}",
+                        record.target_version
+                    );
+                }
+                self.metrics
+                    .observe_upgrade_health_status(UpgradeHealthStatus::Unhealthy);
+                *self.upgrade_health.write().await = UpgradeHealth::Unhealthy(record, gate);
+            }
+        }
+        self.health_monitor = None;
+        self.remove_upgrade_record();
+        self.confirm_boot().await;
+    }
+
+    // Returns the highest finalized height of the own replica, as read from its consensus pool,
+    // or the height of the local CUP if that is higher.
+    fn get_finalized_height(&self, local_cup_height: Option<Height>) -> Option<Height> {
+        let pool_height = read_finalized_height(self.replica_config_file.clone(), &self.logger)
+            .map_err(|e| warn!(self.logger, "Failed to read the finalized height: {}", e))
+            .ok()
+            .flatten();
+        pool_height.max(local_cup_height)
+    }
+
+    // Fetches the status of the own replica.
+    async fn get_replica_status(&self) -> Option<HttpStatusResponse> {
+        let node_record = self
+            .registry
+            .registry_client
+            .get_node_record(self.node_id, self.registry.get_latest_version())
+            .ok()
+            .flatten()?;
+        let http = node_record.http?;
+        let url = Url::parse(&format!("http://[{}]:{}", http.ip_addr, http.port)).ok()?;
+        Agent::new_with_client(HttpClient::new(), url, Sender::Anonymous)
+            .get_status()
+            .await
+            .map_err(|e| warn!(self.logger, "Failed to fetch the replica status: {}", e))
+            .ok()
+    }
+
     fn report_reboot_time(&self) -> OrchestratorResult<()> {
         let elapsed_time = self.get_time_since_last_reboot_trigger()?;
         self.metrics
@@ -207,11 +437,14 @@ impl Upgrade {
             // Only downloads the new image if it doesn't already exists locally, i.e. it
             // was previously downloaded by `prepare_upgrade_if_scheduled()`, see
             // below.
-            self.execute_upgrade(&new_replica_version).await?;
+            self.execute_upgrade_with_health_gates(&new_replica_version, Some(latest_cup.height()))
+                .await?;
             return Ok(Some(subnet_id));
         }
 
         // If we arrive here, we are on the newest replica version.
+        self.clear_rolled_back_upgrade().await;
+
         // Now we check if a subnet recovery is in progress.
         // If it is, we restart to pass the unsigned CUP to consensus.
         self.stop_replica_if_new_recovery_cup(&latest_cup, old_cup_height);
@@ -285,6 +518,13 @@ impl Upgrade {
     ) -> OrchestratorResult<()> {
         let (expected_replica_version, registry_version) =
             self.registry.get_expected_replica_version(subnet_id)?;
+        // Preparing an upgrade overwrites the previous image, which is still needed to roll
+        // back, as long as the health gates of the current upgrade have not been passed.
+        if self.health_monitor.is_some()
+            || self.rolled_back_version.as_ref() == Some(&expected_replica_version)
+        {
+            return Ok(());
+        }
         if expected_replica_version != self.replica_version {
             info!(
                 self.logger,
@@ -304,6 +544,7 @@ impl Upgrade {
             .registry
             .get_unassigned_replica_version(registry_version)?;
         if self.replica_version == replica_version {
+            self.clear_rolled_back_upgrade().await;
             return Ok(());
         }
         info!(
@@ -312,9 +553,8 @@ impl Upgrade {
             self.replica_version,
             replica_version
         );
-        self.execute_upgrade(&replica_version)
+        self.execute_upgrade_with_health_gates(&replica_version, None)
             .await
-            .map_err(OrchestratorError::from)
     }
 
     /// Stop the current replica process.
@@ -435,7 +675,11 @@ impl ImageUpgrader<ReplicaVersion, Option<SubnetId>> for Upgrade {
     }
 
     async fn check_for_upgrade(&mut self) -> UpgradeResult<Option<SubnetId>> {
-        self.check().await.map_err(UpgradeError::from)
+        let result = self.check().await;
+        if self.health_monitor.is_some() {
+            self.check_upgrade_health(&result).await;
+        }
+        result.map_err(UpgradeError::from)
     }
 }
 
@@ -515,6 +759,33 @@ fn should_node_become_unassigned(
     true
 }
 
+// Reads the highest finalized height from the consensus pool of the replica, which is opened
+// read-only. Returns `None` if the replica did not create its consensus pool yet.
+fn read_finalized_height(
+    replica_config_file: PathBuf,
+    logger: &ReplicaLogger,
+) -> Result<Option<Height>, String> {
+    use ic_artifact_pool::consensus_pool::UncachedConsensusPoolImpl;
+    use ic_config::artifact_pool::ArtifactPoolConfig;
+    use ic_config::{Config, ConfigSource};
+    use ic_interfaces::consensus_pool::{HeightIndexedPool, PoolSection};
+    let tmpdir = tempfile::Builder::new()
+        .prefix("ic_config")
+        .tempdir()
+        .map_err(|err| format!("Couldn't create a temporary directory: {:?}", err))?;
+    let config = Config::load_with_tmpdir(
+        ConfigSource::File(replica_config_file),
+        tmpdir.path().to_path_buf(),
+    );
+    if !config.artifact_pool.consensus_pool_path.exists() {
+        return Ok(None);
+    }
+    let mut pool_config = ArtifactPoolConfig::from(config.artifact_pool);
+    pool_config.persistent_pool_read_only = true;
+    let pool = UncachedConsensusPoolImpl::new(pool_config, logger.clone());
+    Ok(pool.validated.finalization().max_height())
+}
+
 // Deletes the subnet state consisting of the consensus pool, execution state,
 // the local CUP and the persisted error metric of threshold key changes.
 fn remove_node_state(
@@ -0,0 +1,485 @@
+//! Health gates that a node has to pass after booting into a new image.
+//!
+//! Before the orchestrator reboots into a new replica version, it persists an
+//! [`UpgradeRecord`] in its data directory. When the orchestrator of the new
+//! image starts and finds such a record, it does not confirm the boot right
+//! away. Instead, an [`UpgradeHealthMonitor`] observes the node on every
+//! upgrade check until all gates passed or the timeout expired:
+//!
+//! - [`HealthGate::UpgradeCheck`]: the orchestrator completes an upgrade check,
+//!   i.e. it can still follow the registry. This is the only gate for
+//!   unassigned nodes.
+//! - [`HealthGate::Finalization`]: the replica finalizes a height above the
+//!   height it started from, as read from its consensus pool or local CUP.
+//! - [`HealthGate::Certification`]: the replica certifies a state above the
+//!   height it started from.
+//! - [`HealthGate::NoCrashLoop`]: the replica process is not restarted more
+//!   than [`MAX_REPLICA_RESTARTS`] times while the gates are observed.
+//!
+//! If a gate fails and the node did not make any progress on the new version
+//! that its subnet relies on, i.e. it is unassigned or its subnet did not
+//! produce a CUP above the upgrade CUP yet, the upgrade is rolled back to the
+//! previous image.
+use crate::error::{OrchestratorError, OrchestratorResult};
+use ic_types::{messages::HttpStatusResponse, Height, ReplicaVersion};
+use ic_utils::fs::write_atomically;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::Path,
+    time::{Duration, Instant},
+};
+use strum_macros::{EnumIter, IntoStaticStr};
+
+pub(crate) const UPGRADE_RECORD_FILENAME: &str = "upgrade_record.cbor";
+
+/// The time a node has to pass all health gates after an upgrade.
+pub(crate) const UPGRADE_HEALTH_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+
+/// The number of times the replica process may be restarted after an upgrade
+/// before it is considered to be crash-looping.
+pub(crate) const MAX_REPLICA_RESTARTS: usize = 3;
+
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Deserialize,
+    EnumIter,
+    Eq,
+    IntoStaticStr,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+pub(crate) enum HealthGate {
+    UpgradeCheck,
+    Finalization,
+    Certification,
+    NoCrashLoop,
+}
+
+/// Persisted before rebooting into a new replica version, so that the
+/// orchestrator of the next boot knows that it has to check the health of the
+/// upgrade, or that the upgrade was rolled back.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub(crate) struct UpgradeRecord {
+    pub previous_version: ReplicaVersion,
+    pub target_version: ReplicaVersion,
+    /// The height of the CUP at which the upgrade was executed, or `None` if
+    /// the node was unassigned.
+    pub cup_height: Option<Height>,
+    /// The gate that failed, if the upgrade was rolled back by the
+    /// orchestrator. If the upgrade was rolled back but no gate is set, the
+    /// new image failed to confirm its boot.
+    pub failed_gate: Option<HealthGate>,
+}
+
+impl UpgradeRecord {
+    pub(crate) fn load(path: &Path) -> OrchestratorResult<Option<Self>> {
+        if !path
+            .try_exists()
+            .map_err(|e| OrchestratorError::IoError(format!("Failed to check {:?}", path), e))?
+        {
+            return Ok(None);
+        }
+        let file = std::fs::File::open(path)
+            .map_err(|e| OrchestratorError::IoError(format!("Failed to open {:?}", path), e))?;
+        serde_cbor::from_reader(file)
+            .map(Some)
+            .map_err(|e| OrchestratorError::UpgradeError(format!("Invalid upgrade record: {}", e)))
+    }
+
+    /// Writes the record atomically, as a torn record would break the rollback
+    /// decision of the next boot.
+    pub(crate) fn persist(&self, path: &Path) -> OrchestratorResult<()> {
+        write_atomically(path, |writer| {
+            serde_cbor::to_writer(writer, self)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })
+        .map_err(|e| OrchestratorError::file_write_error(path, e))
+    }
+
+    pub(crate) fn remove(path: &Path) -> OrchestratorResult<()> {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(OrchestratorError::IoError(
+                format!("Failed to remove {:?}", path),
+                e,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns true if the upgrade may be rolled back, given the height of the
+    /// local CUP. This is the case if the node was unassigned during the
+    /// upgrade and still is, or if no CUP above the upgrade CUP was produced,
+    /// i.e. the subnet did not make progress on the new version yet.
+    pub(crate) fn can_roll_back(&self, local_cup_height: Option<Height>) -> bool {
+        local_cup_height <= self.cup_height
+    }
+}
+
+/// What the orchestrator observed during an upgrade check.
+#[derive(Debug)]
+pub(crate) enum HealthObservation {
+    /// The upgrade check failed.
+    CheckFailed,
+    /// The upgrade check succeeded and the node is unassigned.
+    Unassigned,
+    /// The upgrade check succeeded and the node is assigned.
+    Assigned {
+        /// The height of the local CUP the replica started from.
+        cup_height: Height,
+        /// The number of times the replica process was started.
+        replica_starts: usize,
+        /// The highest finalized height, as read from the consensus pool or
+        /// the local CUP, if it could be read.
+        finalized_height: Option<Height>,
+        /// The status reported by the replica, if it could be fetched.
+        status: Option<HttpStatusResponse>,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum HealthVerdict {
+    Pending,
+    Healthy,
+    Unhealthy(HealthGate),
+}
+
+/// Observes the health gates of the node after booting into a new image.
+pub(crate) struct UpgradeHealthMonitor {
+    record: UpgradeRecord,
+    started: Instant,
+    timeout: Duration,
+    baseline_height: Option<Height>,
+    passed: BTreeSet<HealthGate>,
+}
+
+impl UpgradeHealthMonitor {
+    pub(crate) fn new(record: UpgradeRecord, timeout: Duration) -> Self {
+        Self {
+            baseline_height: record.cup_height,
+            record,
+            started: Instant::now(),
+            timeout,
+            passed: BTreeSet::new(),
+        }
+    }
+
+    pub(crate) fn record(&self) -> &UpgradeRecord {
+        &self.record
+    }
+
+    pub(crate) fn observe(&mut self, observation: HealthObservation) -> HealthVerdict {
+        let required = match observation {
+            HealthObservation::CheckFailed => self.required_gates(),
+            HealthObservation::Unassigned => {
+                self.passed.insert(HealthGate::UpgradeCheck);
+                BTreeSet::from([HealthGate::UpgradeCheck])
+            }
+            HealthObservation::Assigned {
+                cup_height,
+                replica_starts,
+                finalized_height,
+                status,
+            } => {
+                self.passed.insert(HealthGate::UpgradeCheck);
+                if replica_starts > MAX_REPLICA_RESTARTS + 1 {
+                    return HealthVerdict::Unhealthy(HealthGate::NoCrashLoop);
+                }
+                // A node that was unassigned during the upgrade measures the
+                // progress from the first CUP it started from.
+                let baseline = *self.baseline_height.get_or_insert(cup_height);
+                if finalized_height > Some(baseline) {
+                    self.passed.insert(HealthGate::Finalization);
+                }
+                if status.and_then(|s| s.certified_height) > Some(baseline) {
+                    self.passed.insert(HealthGate::Certification);
+                }
+                self.required_gates()
+            }
+        };
+
+        if let Some(gate) = required.difference(&self.passed).next() {
+            if self.started.elapsed() > self.timeout {
+                HealthVerdict::Unhealthy(*gate)
+            } else {
+                HealthVerdict::Pending
+            }
+        } else {
+            HealthVerdict::Healthy
+        }
+    }
+
+    fn required_gates(&self) -> BTreeSet<HealthGate> {
+        if self.baseline_height.is_some() {
+            BTreeSet::from([
+                HealthGate::UpgradeCheck,
+                HealthGate::Finalization,
+                HealthGate::Certification,
+            ])
+        } else {
+            BTreeSet::from([HealthGate::UpgradeCheck])
+        }
+    }
+}
+
+/// The outcome of the last upgrade, as shown on the dashboard.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) enum UpgradeHealth {
+    /// No upgrade was executed since the last confirmed boot.
+    #[default]
+    None,
+    Monitoring(UpgradeRecord),
+    Healthy(UpgradeRecord),
+    /// A gate failed, but the upgrade could not be rolled back.
+    Unhealthy(UpgradeRecord, HealthGate),
+    RolledBack(UpgradeRecord),
+}
+
+impl fmt::Display for UpgradeHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpgradeHealth::None => write!(f, "None"),
+            UpgradeHealth::Monitoring(r) => write!(
+                f,
+                "checking health gates of {} -> {}",
+                r.previous_version, r.target_version
+            ),
+            UpgradeHealth::Healthy(r) => {
+                write!(f, "healthy {} -> {}", r.previous_version, r.target_version)
+            }
+            UpgradeHealth::Unhealthy(r, gate) => write!(
+                f,
+                "gate {:?} failed after {} -> {}, not rolled back",
+                gate, r.previous_version, r.target_version
+            ),
+            UpgradeHealth::RolledBack(r) => match r.failed_gate {
+                Some(gate) => write!(
+                    f,
+                    "rolled back {} -> {} after gate {:?} failed",
+                    r.target_version, r.previous_version, gate
+                ),
+                None => write!(
+                    f,
+                    "rolled back {} -> {} after the boot was not confirmed",
+                    r.target_version, r.previous_version
+                ),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_types::messages::ReplicaHealthStatus;
+    use tempfile::tempdir;
+
+    fn record(cup_height: Option<u64>) -> UpgradeRecord {
+        UpgradeRecord {
+            previous_version: ReplicaVersion::try_from("old").unwrap(),
+            target_version: ReplicaVersion::try_from("new").unwrap(),
+            cup_height: cup_height.map(Height::from),
+            failed_gate: None,
+        }
+    }
+
+    fn status(certified_height: u64, health: ReplicaHealthStatus) -> Option<HttpStatusResponse> {
+        Some(HttpStatusResponse {
+            ic_api_version: String::new(),
+            root_key: None,
+            impl_version: None,
+            impl_hash: None,
+            replica_health_status: Some(health),
+            certified_height: Some(Height::from(certified_height)),
+        })
+    }
+
+    fn assigned(
+        cup_height: u64,
+        replica_starts: usize,
+        status: Option<HttpStatusResponse>,
+    ) -> HealthObservation {
+        // The replica finalized up to the certified height.
+        let finalized_height = status.as_ref().and_then(|s| s.certified_height);
+        assigned_with_finalized_height(cup_height, replica_starts, finalized_height, status)
+    }
+
+    fn assigned_with_finalized_height(
+        cup_height: u64,
+        replica_starts: usize,
+        finalized_height: Option<Height>,
+        status: Option<HttpStatusResponse>,
+    ) -> HealthObservation {
+        HealthObservation::Assigned {
+            cup_height: Height::from(cup_height),
+            replica_starts,
+            finalized_height,
+            status,
+        }
+    }
+
+    #[test]
+    fn test_upgrade_record_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(UPGRADE_RECORD_FILENAME);
+        assert_eq!(UpgradeRecord::load(&path).unwrap(), None);
+
+        let mut record = record(Some(100));
+        record.failed_gate = Some(HealthGate::Certification);
+        record.persist(&path).unwrap();
+        assert_eq!(UpgradeRecord::load(&path).unwrap(), Some(record));
+
+        UpgradeRecord::remove(&path).unwrap();
+        assert_eq!(UpgradeRecord::load(&path).unwrap(), None);
+        // Removing a missing record is not an error.
+        UpgradeRecord::remove(&path).unwrap();
+    }
+
+    #[test]
+    fn test_can_roll_back() {
+        // Unassigned during the upgrade.
+        assert!(record(None).can_roll_back(None));
+        assert!(!record(None).can_roll_back(Some(Height::from(100))));
+        // Assigned during the upgrade.
+        assert!(record(Some(100)).can_roll_back(Some(Height::from(100))));
+        assert!(!record(Some(100)).can_roll_back(Some(Height::from(200))));
+    }
+
+    #[test]
+    fn test_unassigned_node_passes_after_successful_check() {
+        let mut monitor = UpgradeHealthMonitor::new(record(None), UPGRADE_HEALTH_TIMEOUT);
+        assert_eq!(
+            monitor.observe(HealthObservation::CheckFailed),
+            HealthVerdict::Pending
+        );
+        assert_eq!(
+            monitor.observe(HealthObservation::Unassigned),
+            HealthVerdict::Healthy
+        );
+    }
+
+    #[test]
+    fn test_unassigned_node_fails_if_checks_fail() {
+        let mut monitor = UpgradeHealthMonitor::new(record(None), Duration::ZERO);
+        assert_eq!(
+            monitor.observe(HealthObservation::CheckFailed),
+            HealthVerdict::Unhealthy(HealthGate::UpgradeCheck)
+        );
+    }
+
+    #[test]
+    fn test_assigned_node_passes_after_certification() {
+        let mut monitor = UpgradeHealthMonitor::new(record(Some(100)), UPGRADE_HEALTH_TIMEOUT);
+        assert_eq!(
+            monitor.observe(assigned(100, 1, None)),
+            HealthVerdict::Pending
+        );
+        assert_eq!(
+            monitor.observe(assigned(
+                100,
+                1,
+                status(100, ReplicaHealthStatus::WaitingForCertifiedState)
+            )),
+            HealthVerdict::Pending
+        );
+        assert_eq!(
+            monitor.observe(assigned(100, 1, status(101, ReplicaHealthStatus::Healthy))),
+            HealthVerdict::Healthy
+        );
+    }
+
+    #[test]
+    fn test_assigned_node_fails_without_certification() {
+        let mut monitor = UpgradeHealthMonitor::new(record(Some(100)), Duration::ZERO);
+        assert_eq!(
+            monitor.observe(assigned_with_finalized_height(
+                100,
+                1,
+                Some(Height::from(150)),
+                status(100, ReplicaHealthStatus::CertifiedStateBehind)
+            )),
+            HealthVerdict::Unhealthy(HealthGate::Certification)
+        );
+
+        let mut monitor = UpgradeHealthMonitor::new(record(Some(100)), Duration::ZERO);
+        assert_eq!(
+            monitor.observe(assigned(100, 1, None)),
+            HealthVerdict::Unhealthy(HealthGate::Finalization)
+        );
+    }
+
+    #[test]
+    fn test_finalization_gate_uses_finalized_height() {
+        // Certifying without a finalized height above the baseline does not
+        // pass the finalization gate.
+        let mut monitor = UpgradeHealthMonitor::new(record(Some(100)), Duration::ZERO);
+        assert_eq!(
+            monitor.observe(assigned_with_finalized_height(
+                100,
+                1,
+                Some(Height::from(100)),
+                status(101, ReplicaHealthStatus::Healthy)
+            )),
+            HealthVerdict::Unhealthy(HealthGate::Finalization)
+        );
+
+        // A replica that finalizes but does not report that its certified
+        // state is behind only fails the certification gate.
+        let mut monitor = UpgradeHealthMonitor::new(record(Some(100)), Duration::ZERO);
+        assert_eq!(
+            monitor.observe(assigned_with_finalized_height(
+                100,
+                1,
+                Some(Height::from(150)),
+                status(100, ReplicaHealthStatus::Healthy)
+            )),
+            HealthVerdict::Unhealthy(HealthGate::Certification)
+        );
+    }
+
+    #[test]
+    fn test_persist_replaces_record() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(UPGRADE_RECORD_FILENAME);
+        record(None).persist(&path).unwrap();
+
+        let mut record = record(Some(100));
+        record.failed_gate = Some(HealthGate::Finalization);
+        record.persist(&path).unwrap();
+
+        assert_eq!(UpgradeRecord::load(&path).unwrap(), Some(record));
+        // No temporary file is left behind.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_assigned_node_fails_when_crash_looping() {
+        let mut monitor = UpgradeHealthMonitor::new(record(Some(100)), UPGRADE_HEALTH_TIMEOUT);
+        assert_eq!(
+            monitor.observe(assigned(100, MAX_REPLICA_RESTARTS + 1, None)),
+            HealthVerdict::Pending
+        );
+        assert_eq!(
+            monitor.observe(assigned(100, MAX_REPLICA_RESTARTS + 2, None)),
+            HealthVerdict::Unhealthy(HealthGate::NoCrashLoop)
+        );
+    }
+
+    #[test]
+    fn test_node_assigned_after_upgrade_uses_local_cup_as_baseline() {
+        let mut monitor = UpgradeHealthMonitor::new(record(None), UPGRADE_HEALTH_TIMEOUT);
+        assert_eq!(
+            monitor.observe(assigned(500, 1, status(500, ReplicaHealthStatus::Healthy))),
+            HealthVerdict::Pending
+        );
+        assert_eq!(
+            monitor.observe(assigned(500, 1, status(501, ReplicaHealthStatus::Healthy))),
+            HealthVerdict::Healthy
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Conversion of artifacts to and from the form in which they are transmitted
+//! to peers.
+use async_trait::async_trait;
+use ic_quic_transport::Transport;
+use ic_types::{artifact::ArtifactKind, NodeId};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// Errors that can occur when assembling an artifact received from a peer.
+#[derive(Debug)]
+pub enum AssembleError {
+    /// A part of the artifact is missing locally and could not be fetched from
+    /// the peer.
+    FetchFailed(String),
+    /// A part fetched from the peer does not match what the artifact
+    /// references.
+    Mismatch(String),
+    /// The local pool that holds parts of the artifact could not be read.
+    PoolUnavailable(String),
+}
+
+/// Converts artifacts into the messages that are transmitted to peers and back.
+///
+/// This allows omitting parts of an artifact that peers are expected to
+/// already have, e.g. the ingress messages of a block proposal, which most
+/// peers already received through gossip.
+#[async_trait]
+pub trait ArtifactAssembler<Artifact: ArtifactKind>: Send + Sync + 'static {
+    /// The message that is transmitted to peers.
+    type WireMessage: Serialize + for<'a> Deserialize<'a> + Send + 'static;
+
+    /// Converts a message of the validated pool into the message that is
+    /// transmitted to peers.
+    fn disassemble(&self, message: Artifact::Message) -> Self::WireMessage;
+
+    /// Reconstructs the original message from a message received from `peer`.
+    /// Parts of the message that are missing locally are fetched from `peer`
+    /// over `transport`.
+    async fn assemble(
+        &self,
+        message: Self::WireMessage,
+        peer: NodeId,
+        transport: &dyn Transport,
+    ) -> Result<Artifact::Message, AssembleError>;
+}
+
+/// An [`ArtifactAssembler`] that transmits artifacts unchanged.
+pub struct PassThroughAssembler<Artifact>(PhantomData<fn() -> Artifact>);
+
+impl<Artifact> Default for PassThroughAssembler<Artifact> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<Artifact> ArtifactAssembler<Artifact> for PassThroughAssembler<Artifact>
+where
+    Artifact: ArtifactKind + 'static,
+    <Artifact as ArtifactKind>::Message: Serialize + for<'a> Deserialize<'a> + Send + 'static,
+{
+    type WireMessage = Artifact::Message;
+
+    fn disassemble(&self, message: Artifact::Message) -> Self::WireMessage {
+        message
+    }
+
+    async fn assemble(
+        &self,
+        message: Self::WireMessage,
+        _peer: NodeId,
+        _transport: &dyn Transport,
+    ) -> Result<Artifact::Message, AssembleError> {
+        Ok(message)
+    }
+}
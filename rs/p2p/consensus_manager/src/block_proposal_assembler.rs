@@ -0,0 +1,363 @@
+//! Transmission of block proposals that reference their ingress messages by
+//! [`IngressMessageId`] instead of carrying them.
+//!
+//! Most ingress messages included in a block proposal have already been
+//! gossiped to all replicas. Block proposals are therefore transmitted without
+//! their ingress payload, and the receiver reconstructs it from its own
+//! ingress pool, fetching only the messages it is missing from the peer that
+//! sent the proposal. The reconstructed proposal retains the hashes of the
+//! original block and its payload, so a proposal that was reconstructed with
+//! the wrong messages fails the integrity check during validation.
+use crate::{
+    assembler::{ArtifactAssembler, AssembleError},
+    build_rpc_handler_request, ValidatedPoolReaderRef,
+};
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use futures::{stream, StreamExt, TryStreamExt};
+use ic_quic_transport::Transport;
+use ic_types::{
+    artifact::{ArtifactKind, IngressMessageId},
+    artifact_kind::{ConsensusArtifact, IngressArtifact},
+    batch::IngressPayload,
+    consensus::{hashed::Hashed, BlockPayload, BlockProposal, ConsensusMessage, Payload},
+    crypto::Signed,
+    messages::SignedIngress,
+    NodeId,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Timeout for fetching a single ingress message from a peer.
+const INGRESS_FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Maximum number of ingress messages of a single block proposal that are
+/// fetched from the peer concurrently.
+const MAX_CONCURRENT_INGRESS_FETCHES: usize = 16;
+
+/// A [`ConsensusMessage`] in the form in which it is transmitted to peers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum StrippedConsensusMessage {
+    /// A block proposal whose ingress messages were stripped.
+    StrippedBlockProposal(StrippedBlockProposal),
+    /// Any other message, transmitted unchanged.
+    Unstripped(ConsensusMessage),
+}
+
+/// A block proposal that references its ingress messages by id.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StrippedBlockProposal {
+    /// The block proposal with an empty ingress payload. The hashes of the
+    /// block and of its payload are the ones of the original block proposal.
+    block_proposal_without_ingress: BlockProposal,
+    /// The ids of the ingress messages of the original block proposal, in the
+    /// order in which they appear in its ingress payload.
+    ingress_message_ids: Vec<IngressMessageId>,
+}
+
+/// An [`ArtifactAssembler`] for consensus messages that strips the ingress
+/// messages of block proposals before they are transmitted, and reconstructs
+/// them from the local ingress pool on the receiving side.
+pub struct BlockProposalAssembler {
+    ingress_pool: ValidatedPoolReaderRef<IngressArtifact>,
+}
+
+impl BlockProposalAssembler {
+    pub fn new(ingress_pool: ValidatedPoolReaderRef<IngressArtifact>) -> Self {
+        Self { ingress_pool }
+    }
+
+    /// Fetches the ingress message with the given id from `peer`.
+    async fn fetch_ingress_message(
+        transport: &dyn Transport,
+        id: &IngressMessageId,
+        peer: NodeId,
+    ) -> Result<SignedIngress, AssembleError> {
+        let request = build_rpc_handler_request(IngressArtifact::TAG.into(), id);
+        let response = timeout(INGRESS_FETCH_TIMEOUT, transport.rpc(&peer, request))
+            .await
+            .map_err(|_| {
+                AssembleError::FetchFailed(format!(
+                    "Timed out fetching ingress message {} from peer {}",
+                    id, peer
+                ))
+            })?
+            .map_err(|err| {
+                AssembleError::FetchFailed(format!(
+                    "Failed to fetch ingress message {} from peer {}: {}",
+                    id, peer, err
+                ))
+            })?;
+        if response.status() != StatusCode::OK {
+            return Err(AssembleError::FetchFailed(format!(
+                "Peer {} did not return ingress message {}: {}",
+                peer,
+                id,
+                response.status()
+            )));
+        }
+        let message: SignedIngress = bincode::deserialize(response.body()).map_err(|err| {
+            AssembleError::FetchFailed(format!(
+                "Failed to deserialize ingress message {} from peer {}: {}",
+                id, peer, err
+            ))
+        })?;
+        if IngressMessageId::from(&message) != *id {
+            return Err(AssembleError::Mismatch(format!(
+                "Peer {} returned a different ingress message than {}",
+                peer, id
+            )));
+        }
+        Ok(message)
+    }
+}
+
+#[async_trait]
+impl ArtifactAssembler<ConsensusArtifact> for BlockProposalAssembler {
+    type WireMessage = StrippedConsensusMessage;
+
+    fn disassemble(&self, message: ConsensusMessage) -> StrippedConsensusMessage {
+        match message {
+            ConsensusMessage::BlockProposal(proposal) if has_ingress(&proposal) => {
+                let ingress_message_ids = proposal
+                    .as_ref()
+                    .payload
+                    .as_ref()
+                    .as_data()
+                    .batch
+                    .ingress
+                    .message_ids();
+                StrippedConsensusMessage::StrippedBlockProposal(StrippedBlockProposal {
+                    block_proposal_without_ingress: with_ingress_payload(
+                        proposal,
+                        IngressPayload::default(),
+                    ),
+                    ingress_message_ids,
+                })
+            }
+            message => StrippedConsensusMessage::Unstripped(message),
+        }
+    }
+
+    async fn assemble(
+        &self,
+        message: StrippedConsensusMessage,
+        peer: NodeId,
+        transport: &dyn Transport,
+    ) -> Result<ConsensusMessage, AssembleError> {
+        let StrippedBlockProposal {
+            block_proposal_without_ingress,
+            ingress_message_ids,
+        } = match message {
+            StrippedConsensusMessage::StrippedBlockProposal(stripped) => stripped,
+            StrippedConsensusMessage::Unstripped(message) => return Ok(message),
+        };
+        if !has_data_payload(&block_proposal_without_ingress) {
+            return Err(AssembleError::Mismatch(
+                "Stripped block proposal does not have a data payload".to_string(),
+            ));
+        }
+
+        let ingress_pool = self.ingress_pool.clone();
+        let ids = ingress_message_ids.clone();
+        let local_messages = tokio::task::spawn_blocking(move || {
+            let ingress_pool = ingress_pool.read().map_err(|_| {
+                AssembleError::PoolUnavailable("The ingress pool lock is poisoned".to_string())
+            })?;
+            Ok::<_, AssembleError>(
+                ids.iter()
+                    .map(|id| ingress_pool.get_validated_by_identifier(id))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .await
+        .map_err(|err| {
+            AssembleError::PoolUnavailable(format!("Failed to read the ingress pool: {}", err))
+        })??;
+
+        // Messages are fetched in order, and at most `MAX_CONCURRENT_INGRESS_FETCHES`
+        // of them at a time, so a proposal with many missing messages does not flood
+        // the peer with requests.
+        let messages = stream::iter(ingress_message_ids.iter().zip(local_messages))
+            .map(|(id, local_message)| async move {
+                match local_message {
+                    Some(message) => Ok(message),
+                    None => Self::fetch_ingress_message(transport, id, peer).await,
+                }
+            })
+            .buffered(MAX_CONCURRENT_INGRESS_FETCHES)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        Ok(ConsensusMessage::BlockProposal(with_ingress_payload(
+            block_proposal_without_ingress,
+            IngressPayload::from(messages),
+        )))
+    }
+}
+
+fn has_data_payload(proposal: &BlockProposal) -> bool {
+    !proposal.as_ref().payload.is_summary()
+}
+
+fn has_ingress(proposal: &BlockProposal) -> bool {
+    has_data_payload(proposal)
+        && !proposal
+            .as_ref()
+            .payload
+            .as_ref()
+            .as_data()
+            .batch
+            .ingress
+            .is_empty()
+}
+
+/// Returns the given block proposal, which must have a data payload, with its
+/// ingress payload replaced. The hashes of the block and its payload are kept,
+/// so they only remain correct if the ingress payload is restored eventually.
+fn with_ingress_payload(proposal: BlockProposal, ingress: IngressPayload) -> BlockProposal {
+    let Signed { content, signature } = proposal;
+    let (block_hash, mut block) = content.decompose();
+    let mut data = block.payload.as_ref().as_data().clone();
+    data.batch.ingress = ingress;
+    block.payload = Payload::new_with(
+        block.payload.get_hash().clone(),
+        block.payload.payload_type(),
+        Box::new(move || BlockPayload::Data(data)),
+    );
+    Signed {
+        content: Hashed::recompose(block_hash, block),
+        signature,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Response;
+    use bytes::Bytes;
+    use ic_interfaces::artifact_pool::ValidatedPoolReader;
+    use ic_p2p_test_utils::mocks::MockTransport;
+    use ic_test_utilities::{
+        consensus::{fake::*, make_genesis},
+        types::{ids::node_test_id, messages::SignedIngressBuilder},
+    };
+    use ic_types::{
+        batch::BatchPayload,
+        consensus::{dkg, Block, ConsensusMessageHashable},
+    };
+    use std::{
+        collections::HashMap,
+        sync::{Arc, RwLock},
+    };
+
+    /// An ingress pool that holds the given validated messages.
+    struct TestIngressPool(HashMap<IngressMessageId, SignedIngress>);
+
+    impl ValidatedPoolReader<IngressArtifact> for TestIngressPool {
+        fn contains(&self, id: &IngressMessageId) -> bool {
+            self.0.contains_key(id)
+        }
+
+        fn get_validated_by_identifier(&self, id: &IngressMessageId) -> Option<SignedIngress> {
+            self.0.get(id).cloned()
+        }
+
+        fn get_all_validated_by_filter(
+            &self,
+            _filter: &(),
+        ) -> Box<dyn Iterator<Item = SignedIngress> + '_> {
+            Box::new(std::iter::empty())
+        }
+    }
+
+    fn ingress_message(nonce: u64) -> SignedIngress {
+        SignedIngressBuilder::new().nonce(nonce).build()
+    }
+
+    /// Returns a block proposal whose ingress payload holds the given messages.
+    fn block_proposal(ingress: Vec<SignedIngress>) -> BlockProposal {
+        let cup = make_genesis(dkg::Summary::fake());
+        let parent = cup.content.block.as_ref();
+        let mut block = Block::from_parent(parent);
+        block.payload = Payload::new(
+            ic_types::crypto::crypto_hash,
+            (
+                BatchPayload {
+                    ingress: IngressPayload::from(ingress),
+                    ..BatchPayload::default()
+                },
+                dkg::Dealings::new_empty(parent.payload.as_ref().dkg_interval_start_height()),
+                None,
+            )
+                .into(),
+        );
+        BlockProposal::fake(block, node_test_id(0))
+    }
+
+    fn ingress_payload(message: &ConsensusMessage) -> IngressPayload {
+        match message {
+            ConsensusMessage::BlockProposal(proposal) => proposal
+                .as_ref()
+                .payload
+                .as_ref()
+                .as_data()
+                .batch
+                .ingress
+                .clone(),
+            _ => panic!("Expected a block proposal"),
+        }
+    }
+
+    #[tokio::test]
+    async fn disassembled_block_proposal_is_assembled_from_pool_and_peer() {
+        let local_message = ingress_message(1);
+        let missing_message = ingress_message(2);
+        let proposal = ConsensusMessage::BlockProposal(block_proposal(vec![
+            local_message.clone(),
+            missing_message.clone(),
+        ]));
+        let ingress_pool = TestIngressPool(HashMap::from([(
+            IngressMessageId::from(&local_message),
+            local_message,
+        )]));
+        let assembler = BlockProposalAssembler::new(Arc::new(RwLock::new(ingress_pool)));
+
+        // Only the message that is missing from the pool is fetched from the peer.
+        let mut transport = MockTransport::default();
+        transport.expect_rpc().times(1).returning(move |_, _| {
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .body(Bytes::from(bincode::serialize(&missing_message).unwrap()))
+                .unwrap())
+        });
+
+        let wire_message = assembler.disassemble(proposal.clone());
+        match &wire_message {
+            StrippedConsensusMessage::StrippedBlockProposal(stripped) => {
+                assert!(!has_ingress(&stripped.block_proposal_without_ingress));
+                assert_eq!(stripped.ingress_message_ids.len(), 2);
+            }
+            StrippedConsensusMessage::Unstripped(_) => panic!("Expected a stripped block proposal"),
+        }
+
+        let assembled = assembler
+            .assemble(wire_message, node_test_id(1), &transport)
+            .await
+            .unwrap();
+        assert_eq!(assembled, proposal);
+        assert_eq!(ingress_payload(&assembled), ingress_payload(&proposal));
+        assert!(assembled.check_integrity());
+    }
+
+    #[test]
+    fn block_proposal_with_wrong_ingress_fails_integrity_check() {
+        let proposal = block_proposal(vec![ingress_message(1)]);
+        assert!(proposal.check_integrity());
+
+        let reassembled =
+            with_ingress_payload(proposal, IngressPayload::from(vec![ingress_message(2)]));
+        assert!(!reassembled.check_integrity());
+    }
+}
@@ -7,7 +7,7 @@ use std::{
 
 use axum::{
     extract::State,
-    http::{Request, Response, StatusCode},
+    http::{Request, StatusCode},
     routing::any,
     Extension, Router,
 };
@@ -41,6 +41,14 @@ use tokio::{
     time::{self, timeout},
 };
 
+pub use assembler::{ArtifactAssembler, AssembleError, PassThroughAssembler};
+pub use block_proposal_assembler::{
+    BlockProposalAssembler, StrippedBlockProposal, StrippedConsensusMessage,
+};
+
+mod assembler;
+mod block_proposal_assembler;
+
 const ENABLE_ARTIFACT_PUSH: bool = false;
 /// Artifact push threshold. Artifacts smaller or equal than this are pushed.
 const ARTIFACT_PUSH_THRESHOLD: usize = 5 * 1024;
@@ -120,6 +128,9 @@ pub(crate) struct ConsensusManagerMetrics {
     pub receive_used_slot_to_overwrite_total: IntCounter,
 
     pub receive_used_slot_stale_total: IntCounter,
+
+    /// Number of downloaded artifacts that could not be assembled.
+    pub download_assemble_failures_total: IntCounter,
 }
 
 impl ConsensusManagerMetrics {
@@ -197,6 +208,10 @@ impl ConsensusManagerMetrics {
                 format!("{prefix}_manager_receive_used_slot_stale_total").as_str(),
                 "TODO.",
             ),
+            download_assemble_failures_total: metrics_registry.int_counter(
+                format!("{prefix}_manager_download_assemble_failures_total").as_str(),
+                "Number of downloaded artifacts that could not be assembled.",
+            ),
         }
     }
 }
@@ -205,10 +220,11 @@ type ValidatedPoolReaderRef<T> = Arc<RwLock<dyn ValidatedPoolReader<T> + Send +
 type ReceivedAdvertSender<A> = Sender<(AdvertUpdate<A>, NodeId, ConnId)>;
 
 #[allow(unused)]
-pub fn build_axum_router<Artifact: ArtifactKind>(
+pub fn build_axum_router<Artifact: ArtifactKind, Assembler: ArtifactAssembler<Artifact>>(
     log: ReplicaLogger,
     rt: Handle,
     pool: ValidatedPoolReaderRef<Artifact>,
+    assembler: Arc<Assembler>,
 ) -> (Router, Receiver<(AdvertUpdate<Artifact>, NodeId, ConnId)>)
 where
     Artifact: ArtifactKind + Serialize + for<'a> Deserialize<'a> + Send + 'static,
@@ -218,16 +234,60 @@ where
 {
     let (update_tx, update_rx) = tokio::sync::mpsc::channel(100);
     let router = Router::new()
-        .route(&format!("/{}/rpc", Artifact::TAG), any(rpc_handler))
-        .with_state(pool)
+        .route(
+            &format!("/{}/rpc", Artifact::TAG),
+            any(rpc_handler::<Artifact, Assembler>),
+        )
+        .with_state((pool, assembler))
         .route(&format!("/{}/update", Artifact::TAG), any(update_handler))
         .with_state(update_tx);
 
     (router, update_rx)
 }
 
-async fn rpc_handler<Artifact: ArtifactKind>(
-    State(pool): State<ValidatedPoolReaderRef<Artifact>>,
+/// Starts the consensus manager that sends adverts for the artifacts of `raw_pool` to peers
+/// and delivers the artifacts downloaded from peers to `sender`. `adverts_received` and
+/// `assembler` must be the ones of the router built by [`build_axum_router`] for `Artifact`.
+#[allow(clippy::too_many_arguments)]
+pub fn start_consensus_manager<Artifact, Pool, Assembler>(
+    log: ReplicaLogger,
+    metrics_registry: &MetricsRegistry,
+    rt: Handle,
+    adverts_to_send: Receiver<ArtifactProcessorEvent<Artifact>>,
+    adverts_received: Receiver<(AdvertUpdate<Artifact>, NodeId, ConnId)>,
+    raw_pool: Arc<RwLock<Pool>>,
+    priority_fn_producer: Arc<dyn PriorityFnAndFilterProducer<Artifact, Pool>>,
+    sender: CrossbeamSender<UnvalidatedArtifact<Artifact::Message>>,
+    time_source: Arc<dyn TimeSource>,
+    transport: Arc<dyn Transport>,
+    assembler: Arc<Assembler>,
+    topology_watcher: watch::Receiver<SubnetTopology>,
+) where
+    Pool: 'static + Send + Sync + ValidatedPoolReader<Artifact>,
+    Assembler: ArtifactAssembler<Artifact>,
+    Artifact: ArtifactKind + Serialize + for<'a> Deserialize<'a> + Send + 'static,
+    <Artifact as ArtifactKind>::Id: Serialize + for<'a> Deserialize<'a> + Clone + Send + Hash + Eq,
+    <Artifact as ArtifactKind>::Message: Serialize + for<'a> Deserialize<'a> + Send,
+    <Artifact as ArtifactKind>::Attribute: Serialize + for<'a> Deserialize<'a> + Send,
+{
+    ConsensusManager::<Artifact, Pool, _, Assembler>::start_consensus_manager(
+        log,
+        metrics_registry,
+        rt,
+        adverts_to_send,
+        adverts_received,
+        raw_pool,
+        priority_fn_producer,
+        sender,
+        time_source,
+        transport,
+        assembler,
+        topology_watcher,
+    )
+}
+
+async fn rpc_handler<Artifact: ArtifactKind, Assembler: ArtifactAssembler<Artifact>>(
+    State((pool, assembler)): State<(ValidatedPoolReaderRef<Artifact>, Arc<Assembler>)>,
     payload: Bytes,
 ) -> Result<Bytes, StatusCode>
 where
@@ -238,8 +298,12 @@ where
 {
     let id: Artifact::Id = bincode::deserialize(&payload).map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    let jh =
-        tokio::task::spawn_blocking(move || pool.read().unwrap().get_validated_by_identifier(&id));
+    let jh = tokio::task::spawn_blocking(move || {
+        pool.read()
+            .unwrap()
+            .get_validated_by_identifier(&id)
+            .map(|msg| assembler.disassemble(msg))
+    });
     let msg = jh
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
@@ -282,7 +346,7 @@ fn build_rpc_handler_request<T: Serialize>(uri_prefix: &str, id: &T) -> Request<
 
 #[allow(unused)]
 
-struct ConsensusManager<Artifact: ArtifactKind, Pool, ReceivedAdvert> {
+struct ConsensusManager<Artifact: ArtifactKind, Pool, ReceivedAdvert, Assembler> {
     log: ReplicaLogger,
     metrics: ConsensusManagerMetrics,
     rt_handle: Handle,
@@ -297,6 +361,7 @@ struct ConsensusManager<Artifact: ArtifactKind, Pool, ReceivedAdvert> {
     sender: CrossbeamSender<UnvalidatedArtifact<Artifact::Message>>,
     time_source: Arc<dyn TimeSource>,
     transport: Arc<dyn Transport>,
+    assembler: Arc<Assembler>,
     active_adverts: HashMap<Artifact::Id, (JoinHandle<()>, SlotNumber)>,
     current_commit_id: CommitId,
     slot_manager: SlotManager,
@@ -314,9 +379,11 @@ struct ConsensusManager<Artifact: ArtifactKind, Pool, ReceivedAdvert> {
 
 #[allow(unused)]
 
-impl<Artifact, Pool> ConsensusManager<Artifact, Pool, (AdvertUpdate<Artifact>, NodeId, ConnId)>
+impl<Artifact, Pool, Assembler>
+    ConsensusManager<Artifact, Pool, (AdvertUpdate<Artifact>, NodeId, ConnId), Assembler>
 where
     Pool: 'static + Send + Sync + ValidatedPoolReader<Artifact>,
+    Assembler: ArtifactAssembler<Artifact>,
     Artifact: ArtifactKind + Serialize + for<'a> Deserialize<'a> + Send + 'static,
     <Artifact as ArtifactKind>::Id: Serialize + for<'a> Deserialize<'a> + Clone + Send + Hash + Eq,
     <Artifact as ArtifactKind>::Message: Serialize + for<'a> Deserialize<'a> + Send,
@@ -335,6 +402,9 @@ where
         sender: CrossbeamSender<UnvalidatedArtifact<Artifact::Message>>,
         time_source: Arc<dyn TimeSource>,
         transport: Arc<dyn Transport>,
+        // Reconstructs artifacts downloaded from peers. Must be the same kind
+        // of assembler that is passed to `build_axum_router`.
+        assembler: Arc<Assembler>,
         topology_watcher: watch::Receiver<SubnetTopology>,
     ) {
         let metrics = ConsensusManagerMetrics::new::<Artifact>(metrics_registry);
@@ -356,6 +426,7 @@ where
             sender,
             time_source,
             transport,
+            assembler,
             active_adverts: HashMap::new(),
             current_commit_id: CommitId::from(0),
             slot_manager,
@@ -401,6 +472,7 @@ where
                                 self.sender.clone(),
                                 self.time_source.clone(),
                                 self.transport.clone(),
+                                self.assembler.clone(),
                                 self.metrics.clone()
                             ),
                             &self.rt_handle,
@@ -459,13 +531,14 @@ where
         sender: CrossbeamSender<UnvalidatedArtifact<Artifact::Message>>,
         time_source: Arc<dyn TimeSource>,
         transport: Arc<dyn Transport>,
+        assembler: Arc<Assembler>,
         metrics: ConsensusManagerMetrics,
     ) -> (
         watch::Receiver<HashSet<NodeId>>,
         Artifact::Id,
         Artifact::Attribute,
     ) {
-        let mut download_js: JoinSet<Result<Result<Response<Bytes>, _>, _>> = JoinSet::new();
+        let mut download_js: JoinSet<Option<(Artifact::Message, NodeId)>> = JoinSet::new();
         let mut stash_eval = time::interval(Duration::from_secs(1));
         let mut is_stash = false;
 
@@ -478,13 +551,10 @@ where
                 Ok(_) = peer_rx.changed() => {}
                 Some(result) = download_js.join_next() => {
                     match result {
-                        Ok(Ok(Ok(rpc_response))) if rpc_response.status() == StatusCode::OK => {
-                            let peer_id = *rpc_response.extensions().get::<NodeId>().unwrap();
+                        Ok(Some((message, peer_id))) => {
                             let timestamp = time_source.get_relative_time();
-                            if let Ok(message) = bincode::deserialize::<Artifact::Message>(rpc_response.body()){
-                                sender.send(UnvalidatedArtifact { message, peer_id, timestamp }).expect("Channel should not be closed");
-                                break;
-                            }
+                            sender.send(UnvalidatedArtifact { message, peer_id, timestamp }).expect("Channel should not be closed");
+                            break;
                         },
                         Err(err) => {
                             // Cancelling tasks is ok. Panicking tasks are not.
@@ -538,9 +608,32 @@ where
                         if let Some(peer) = random_peer {
                             let request = build_rpc_handler_request(Artifact::TAG.into(), &id);
                             let transport = transport.clone();
-                            download_js.spawn(timeout(Duration::from_secs(5), async move {
-                                transport.rpc(&peer, request).await
-                            }));
+                            let assembler = assembler.clone();
+                            let metrics = metrics.clone();
+                            download_js.spawn(async move {
+                                let rpc_response =
+                                    timeout(Duration::from_secs(5), transport.rpc(&peer, request))
+                                        .await
+                                        .ok()?
+                                        .ok()?;
+                                if rpc_response.status() != StatusCode::OK {
+                                    return None;
+                                }
+                                let wire_message = bincode::deserialize::<Assembler::WireMessage>(
+                                    rpc_response.body(),
+                                )
+                                .ok()?;
+                                match assembler
+                                    .assemble(wire_message, peer, transport.as_ref())
+                                    .await
+                                {
+                                    Ok(message) => Some((message, peer)),
+                                    Err(_) => {
+                                        metrics.download_assemble_failures_total.inc();
+                                        None
+                                    }
+                                }
+                            });
                         } else {
                             break;
                         }
@@ -629,6 +722,7 @@ where
                             self.sender.clone(),
                             self.time_source.clone(),
                             self.transport.clone(),
+                            self.assembler.clone(),
                             self.metrics.clone(),
                         ),
                         &self.rt_handle,
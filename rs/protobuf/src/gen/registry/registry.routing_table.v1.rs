@@ -65,3 +65,33 @@ pub mod canister_migrations {
         pub subnet_ids: ::prost::alloc::vec::Vec<super::super::super::super::types::v1::SubnetId>,
     }
 }
+/// Imports of migrated canisters by their destination subnets.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterImports {
+    /// Defined as `repeated` instead of `map` in order to preserve ordering.
+    #[prost(message, repeated, tag = "1")]
+    pub entries: ::prost::alloc::vec::Vec<canister_imports::Entry>,
+}
+/// Nested message and enum types in `CanisterImports`.
+pub mod canister_imports {
+    /// Describes the import of a single migrated canister.
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Entry {
+        /// ID of the migrated canister.
+        #[prost(message, optional, tag = "1")]
+        pub canister_id: ::core::option::Option<super::super::super::super::types::v1::CanisterId>,
+        /// Hash of the canister state exported from the source subnet, which the
+        /// destination subnet checks before importing it.
+        #[prost(bytes = "vec", tag = "2")]
+        pub state_hash: ::prost::alloc::vec::Vec<u8>,
+        /// CBOR-encoded certificate of the destination subnet's state tree, proving
+        /// that it hosts the imported canister. Empty until the migration is
+        /// completed.
+        #[prost(bytes = "vec", tag = "3")]
+        pub import_certificate: ::prost::alloc::vec::Vec<u8>,
+    }
+}
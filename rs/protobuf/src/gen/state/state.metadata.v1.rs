@@ -69,6 +69,9 @@ pub struct NetworkTopology {
     #[prost(message, repeated, tag = "7")]
     pub bitcoin_mainnet_canister_ids:
         ::prost::alloc::vec::Vec<super::super::super::types::v1::CanisterId>,
+    #[prost(message, optional, tag = "8")]
+    pub canister_imports:
+        ::core::option::Option<super::super::super::registry::routing_table::v1::CanisterImports>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -145,6 +148,9 @@ pub struct CanisterHttpRequestContext {
     pub max_response_bytes: ::core::option::Option<u64>,
     #[prost(message, optional, tag = "10")]
     pub transform_context: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// Set iff the request is non-replicated, i.e. only made by this node.
+    #[prost(message, optional, tag = "11")]
+    pub non_replicated_node_id: ::core::option::Option<super::super::super::types::v1::NodeId>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -431,6 +437,9 @@ pub enum HttpMethod {
     Get = 1,
     Post = 2,
     Head = 3,
+    Put = 4,
+    Patch = 5,
+    Delete = 6,
 }
 impl HttpMethod {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -443,6 +452,9 @@ impl HttpMethod {
             HttpMethod::Get => "HTTP_METHOD_GET",
             HttpMethod::Post => "HTTP_METHOD_POST",
             HttpMethod::Head => "HTTP_METHOD_HEAD",
+            HttpMethod::Put => "HTTP_METHOD_PUT",
+            HttpMethod::Patch => "HTTP_METHOD_PATCH",
+            HttpMethod::Delete => "HTTP_METHOD_DELETE",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -452,6 +464,9 @@ impl HttpMethod {
             "HTTP_METHOD_GET" => Some(Self::Get),
             "HTTP_METHOD_POST" => Some(Self::Post),
             "HTTP_METHOD_HEAD" => Some(Self::Head),
+            "HTTP_METHOD_PUT" => Some(Self::Put),
+            "HTTP_METHOD_PATCH" => Some(Self::Patch),
+            "HTTP_METHOD_DELETE" => Some(Self::Delete),
             _ => None,
         }
     }
@@ -38,3 +38,20 @@ pub struct MetaManifest {
     #[prost(bytes = "vec", repeated, tag = "2")]
     pub sub_manifest_hashes: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
 }
+/// Progress of a state sync that is persisted alongside its scratchpad, so
+/// that the sync can be resumed after a replica restart.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StateSyncProgress {
+    #[prost(uint64, tag = "1")]
+    pub height: u64,
+    #[prost(bytes = "vec", tag = "2")]
+    pub root_hash: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, optional, tag = "3")]
+    pub meta_manifest: ::core::option::Option<MetaManifest>,
+    #[prost(message, optional, tag = "4")]
+    pub manifest: ::core::option::Option<Manifest>,
+    /// Ids of the chunks that still need to be fetched.
+    #[prost(uint32, repeated, tag = "5")]
+    pub fetch_chunks: ::prost::alloc::vec::Vec<u32>,
+}
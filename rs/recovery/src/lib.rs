@@ -67,6 +67,10 @@ pub const IC_STATE_EXCLUDES: &[&str] = &[
     "tip",
     "backups",
     "fs_tmp",
+    // Partially fetched states of interrupted state syncs.
+    "state_sync_scratchpads",
+    // Staged states of canisters migrated to this subnet, not yet imported.
+    "canister_imports",
     "recovery",
     // The page_deltas/ directory should not be copied over on rsync as well,
     // it is a new directory used for storing the files backing up the
@@ -3,7 +3,7 @@ use crate::{
     file_sync_helper::read_file,
     util::{block_on, write_public_key_to_file},
 };
-use ic_base_types::{NodeId, PrincipalId, RegistryVersion, SubnetId};
+use ic_base_types::{CanisterId, NodeId, PrincipalId, RegistryVersion, SubnetId};
 use ic_crypto_utils_threshold_sig_der::{parse_threshold_sig_key, public_key_to_der};
 use ic_interfaces_registry::RegistryClientResult;
 use ic_protobuf::registry::{
@@ -16,7 +16,9 @@ use ic_registry_keys::{make_crypto_threshold_signing_pubkey_key, make_subnet_lis
 use ic_registry_local_store::LocalStoreImpl;
 use ic_registry_nns_data_provider::registry::RegistryCanister;
 use ic_registry_replicator::RegistryReplicator;
-use ic_registry_routing_table::{CanisterMigrations, RoutingTable};
+use ic_registry_routing_table::{
+    canister_migration_status, CanisterMigrationStatus, CanisterMigrations, RoutingTable,
+};
 use ic_registry_subnet_features::EcdsaConfig;
 use prost::Message;
 use slog::{error, info, warn, Logger};
@@ -136,6 +138,27 @@ impl RegistryHelper {
         })
     }
 
+    /// Returns the [CanisterMigrationStatus] of the given canister.
+    pub fn get_canister_migration_status(
+        &self,
+        canister_id: CanisterId,
+    ) -> VersionedRecoveryResult<CanisterMigrationStatus> {
+        self.get(|registry_version, registry_client| {
+            let Some(routing_table) = registry_client.get_routing_table(registry_version)? else {
+                return Ok(None);
+            };
+            let canister_migrations = registry_client
+                .get_canister_migrations(registry_version)?
+                .unwrap_or_default();
+
+            Ok(canister_migration_status(
+                &routing_table,
+                &canister_migrations,
+                canister_id,
+            ))
+        })
+    }
+
     /// Polls the [RegistryReplicator] for the most recent version of the registry and then
     /// gets the latest registry version.
     pub fn latest_registry_version(&self) -> RecoveryResult<RegistryVersion> {
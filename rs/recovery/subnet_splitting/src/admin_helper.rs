@@ -1,15 +1,19 @@
 use crate::utils::canister_id_range_to_string;
 
-use ic_base_types::SubnetId;
+use ic_base_types::{CanisterId, SubnetId};
 use ic_recovery::admin_helper::{
     quote, AdminHelper, CommandHelper, IcAdmin, SSH_READONLY_ACCESS_ARG, SUMMARY_ARG,
 };
 use ic_registry_routing_table::CanisterIdRange;
 
+use std::path::PathBuf;
+
 const SOURCE_SUBNET_ARG: &str = "source-subnet";
 const DESTINATION_SUBNET_ARG: &str = "destination-subnet";
 const CANISTER_ID_RANGES_ARG: &str = "canister-id-ranges";
 const MIGRATION_TRACE_ARG: &str = "migration-trace";
+const EXPORTED_CANISTER_STATES_ARG: &str = "exported-canister-states";
+const CANISTER_IMPORT_CERTIFICATES_ARG: &str = "canister-import-certificates";
 
 /// Propose additions or updates to `canister_migrations`.
 ///
@@ -38,12 +42,16 @@ pub(crate) fn get_propose_to_prepare_canister_migration_command(
 
 /// Propose to modify the routing table.
 ///
+/// The destination subnet imports the `exported_canister_states`, given as
+/// pairs of canister ID and exported state hash.
+///
 /// Step 2 of canister migration.
 pub(crate) fn get_propose_to_reroute_canister_ranges_command(
     admin_helper: &AdminHelper,
     canister_id_ranges: &[CanisterIdRange],
     source_subnet_id: SubnetId,
     destination_subnet_id: SubnetId,
+    exported_canister_states: &[(CanisterId, [u8; 32])],
 ) -> IcAdmin {
     let mut ic_admin = admin_helper.get_ic_admin_cmd_base(&admin_helper.neuron_args);
 
@@ -57,18 +65,33 @@ pub(crate) fn get_propose_to_reroute_canister_ranges_command(
             canister_id_ranges.iter().map(canister_id_range_to_string),
         );
 
+    if !exported_canister_states.is_empty() {
+        ic_admin.add_arguments(
+            EXPORTED_CANISTER_STATES_ARG,
+            exported_canister_states
+                .iter()
+                .map(|(canister_id, state_hash)| {
+                    format!("{}:{}", canister_id, hex::encode(state_hash))
+                }),
+        );
+    }
+
     AdminHelper::add_proposer_args(&mut ic_admin, &admin_helper.neuron_args);
     ic_admin
 }
 
 /// Propose to remove entries from `canister_migrations`.
 ///
+/// The `canister_import_certificates` are pairs of canister ID and path to the
+/// destination subnet's certificate of the canister's import.
+///
 /// Step 3 of canister migration.
 pub(crate) fn get_propose_to_complete_canister_migration_command(
     admin_helper: &AdminHelper,
     canister_id_ranges: &[CanisterIdRange],
     source_subnet_id: SubnetId,
     destination_subnet_id: SubnetId,
+    canister_import_certificates: &[(CanisterId, PathBuf)],
 ) -> IcAdmin {
     let mut ic_admin = admin_helper.get_ic_admin_cmd_base(&admin_helper.neuron_args);
 
@@ -84,6 +107,15 @@ pub(crate) fn get_propose_to_complete_canister_migration_command(
             canister_id_ranges.iter().map(canister_id_range_to_string),
         );
 
+    if !canister_import_certificates.is_empty() {
+        ic_admin.add_arguments(
+            CANISTER_IMPORT_CERTIFICATES_ARG,
+            canister_import_certificates
+                .iter()
+                .map(|(canister_id, path)| format!("{}:{}", canister_id, path.display())),
+        );
+    }
+
     AdminHelper::add_proposer_args(&mut ic_admin, &admin_helper.neuron_args);
     ic_admin
 }
@@ -127,7 +159,7 @@ mod tests {
     use ic_base_types::PrincipalId;
     use url::Url;
 
-    use std::{path::PathBuf, str::FromStr};
+    use std::str::FromStr;
 
     const FAKE_IC_ADMIN_DIR: &str = "/fake/ic/admin/dir/";
     const FAKE_NNS_URL: &str = "https://fake_nns_url.com:8080";
@@ -135,6 +167,7 @@ mod tests {
         "gpvux-2ejnk-3hgmh-cegwf-iekfc-b7rzs-hrvep-5euo2-3ywz3-k3hcb-cqe";
     const FAKE_SUBNET_ID_2: &str =
         "mklno-zzmhy-zutel-oujwg-dzcli-h6nfy-2serg-gnwru-vuwck-hcxit-wqe";
+    const FAKE_CANISTER_ID: &str = "53zcu-tiaaa-aaaaa-qaaba-cai";
     const FAKE_CANISTER_ID_RANGES: &[&str] = &[
         "53zcu-tiaaa-aaaaa-qaaba-cai:54yea-6qaaa-aaaaa-qaabq-cai",
         "5h5yf-eiaaa-aaaaa-qaada-cai:5a46r-jqaaa-aaaaa-qaadq-cai",
@@ -192,6 +225,7 @@ mod tests {
             &canister_id_ranges_from_strs(FAKE_CANISTER_ID_RANGES),
             subnet_id_from_str(FAKE_SUBNET_ID_1),
             subnet_id_from_str(FAKE_SUBNET_ID_2),
+            &[(canister_id_from_str(FAKE_CANISTER_ID), [0xab; 32])],
         )
         .join(" ");
 
@@ -204,6 +238,7 @@ mod tests {
             --source-subnet gpvux-2ejnk-3hgmh-cegwf-iekfc-b7rzs-hrvep-5euo2-3ywz3-k3hcb-cqe \
             --destination-subnet mklno-zzmhy-zutel-oujwg-dzcli-h6nfy-2serg-gnwru-vuwck-hcxit-wqe \
             --canister-id-ranges 53zcu-tiaaa-aaaaa-qaaba-cai:54yea-6qaaa-aaaaa-qaabq-cai 5h5yf-eiaaa-aaaaa-qaada-cai:5a46r-jqaaa-aaaaa-qaadq-cai \
+            --exported-canister-states 53zcu-tiaaa-aaaaa-qaaba-cai:abababababababababababababababababababababababababababababababab \
             --test-neuron-proposer"
         );
     }
@@ -215,6 +250,10 @@ mod tests {
             &canister_id_ranges_from_strs(FAKE_CANISTER_ID_RANGES),
             subnet_id_from_str(FAKE_SUBNET_ID_1),
             subnet_id_from_str(FAKE_SUBNET_ID_2),
+            &[(
+                canister_id_from_str(FAKE_CANISTER_ID),
+                PathBuf::from("/fake/import.cbor"),
+            )],
         )
         .join(" ");
 
@@ -226,6 +265,7 @@ mod tests {
             --summary \"Complete canister migration\" \
             --migration-trace gpvux-2ejnk-3hgmh-cegwf-iekfc-b7rzs-hrvep-5euo2-3ywz3-k3hcb-cqe mklno-zzmhy-zutel-oujwg-dzcli-h6nfy-2serg-gnwru-vuwck-hcxit-wqe \
             --canister-id-ranges 53zcu-tiaaa-aaaaa-qaaba-cai:54yea-6qaaa-aaaaa-qaabq-cai 5h5yf-eiaaa-aaaaa-qaada-cai:5a46r-jqaaa-aaaaa-qaadq-cai \
+            --canister-import-certificates 53zcu-tiaaa-aaaaa-qaaba-cai:/fake/import.cbor \
             --test-neuron-proposer"
         );
     }
@@ -244,6 +284,10 @@ mod tests {
             .unwrap()
     }
 
+    fn canister_id_from_str(canister_id: &str) -> CanisterId {
+        CanisterId::from_str(canister_id).unwrap()
+    }
+
     fn canister_id_ranges_from_strs(canister_id_ranges: &[&str]) -> Vec<CanisterIdRange> {
         canister_id_ranges
             .iter()
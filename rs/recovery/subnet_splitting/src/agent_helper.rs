@@ -1,5 +1,5 @@
-use ic_agent::{export::Principal, hash_tree::Label, lookup_value, Agent, Certificate};
-use ic_base_types::SubnetId;
+use ic_agent::{export::Principal, hash_tree::Label, lookup_value, Agent, AgentError, Certificate};
+use ic_base_types::{CanisterId, SubnetId};
 use ic_crypto_utils_threshold_sig_der::{parse_threshold_sig_key, public_key_to_der};
 use ic_recovery::{
     error::{RecoveryError, RecoveryResult},
@@ -16,6 +16,9 @@ const NNS_REGISTRY_CANISTER_ID: &str = "rwlgt-iiaaa-aaaaa-aaaaa-cai";
 const SUBNET_LABEL: &[u8] = b"subnet";
 const PUBLIC_KEY_LABEL: &[u8] = b"public_key";
 const CANISTER_RANGES_LABEL: &[u8] = b"canister_ranges";
+const CANISTER_LABEL: &[u8] = b"canister";
+const CONTROLLERS_LABEL: &[u8] = b"controllers";
+const MODULE_HASH_LABEL: &[u8] = b"module_hash";
 
 type StorageType = Vec<u8>;
 
@@ -31,10 +34,7 @@ pub(crate) struct StateTree {
 impl StateTree {
     /// Saves the raw state tree to the disk, in CBOR format.
     pub(crate) fn save_to_file(&self, path: &Path) -> RecoveryResult<()> {
-        serde_cbor::to_vec(&self.certificate)
-            .map_err(|err| agent_error("Failed to serialize the state tree", err))
-            .and_then(|bytes| write_bytes(path, bytes))
-            .map_err(|err| agent_error("Failed to write the state tree to disk", err))
+        save_certificate_to_file(&self.certificate, path)
     }
 
     /// Reads the raw state tree from the disk.
@@ -67,6 +67,44 @@ impl StateTree {
     }
 }
 
+/// Wrapper around the raw state tree of the subnet hosting a canister.
+///
+/// Note: the state tree is pruned to include only the controllers and the module hash of a single
+/// canister.
+pub(crate) struct CanisterStateTree {
+    certificate: Certificate,
+    canister_id: CanisterId,
+}
+
+impl CanisterStateTree {
+    /// Saves the raw state tree to the disk, in CBOR format.
+    pub(crate) fn save_to_file(&self, path: &Path) -> RecoveryResult<()> {
+        save_certificate_to_file(&self.certificate, path)
+    }
+
+    /// Returns an error if the state tree does not show the canister.
+    pub(crate) fn check_canister_present(&self) -> RecoveryResult<()> {
+        lookup_value(
+            &self.certificate,
+            create_canister_path(self.canister_id, CONTROLLERS_LABEL),
+        )
+        .map(|_| ())
+        .map_err(|err| agent_error("Failed to retrieve the canister controllers", err))
+    }
+
+    /// Returns the module hash of the canister, or `None` if it is empty.
+    pub(crate) fn lookup_module_hash(&self) -> RecoveryResult<Option<&[u8]>> {
+        match lookup_value(
+            &self.certificate,
+            create_canister_path(self.canister_id, MODULE_HASH_LABEL),
+        ) {
+            Ok(module_hash) => Ok(Some(module_hash)),
+            Err(AgentError::LookupPathAbsent(_)) => Ok(None),
+            Err(err) => Err(agent_error("Failed to retrieve the module hash", err)),
+        }
+    }
+}
+
 /// Wrapper around [Agent]  with some utility functions.
 pub(crate) struct AgentHelper {
     agent: Agent,
@@ -142,6 +180,44 @@ impl AgentHelper {
         })
     }
 
+    /// Reads the state tree of the subnet hosting `canister_id` and prunes it to contain only
+    /// the following paths:
+    /// * /canister/$canister_id/controllers
+    /// * /canister/$canister_id/module_hash
+    pub(crate) fn read_canister_data(
+        &self,
+        canister_id: CanisterId,
+    ) -> RecoveryResult<CanisterStateTree> {
+        let certificate = block_on(self.agent.read_state_raw(
+            vec![
+                create_canister_path(canister_id, CONTROLLERS_LABEL),
+                create_canister_path(canister_id, MODULE_HASH_LABEL),
+            ],
+            Principal::from_slice(canister_id.get().as_slice()),
+        ))
+        .map_err(|err| agent_error("Failed to read the state tree", err))?;
+
+        debug!(self.logger, "State tree: {:#?}", certificate.tree);
+
+        Ok(CanisterStateTree {
+            certificate,
+            canister_id,
+        })
+    }
+
+    /// Validates the state tree of the subnet hosting the canister, including its delegation.
+    pub(crate) fn validate_canister_state_tree(
+        &self,
+        state_tree: &CanisterStateTree,
+    ) -> RecoveryResult<()> {
+        self.agent
+            .verify(
+                &state_tree.certificate,
+                Principal::from_slice(state_tree.canister_id.get().as_slice()),
+            )
+            .map_err(|err| agent_error("Failed to verify the state tree", err))
+    }
+
     /// Validates the state tree.
     pub(crate) fn validate_state_tree(&self, state_tree: &StateTree) -> RecoveryResult<()> {
         self.agent
@@ -154,6 +230,21 @@ fn agent_error(message: impl Display, error: impl Display) -> RecoveryError {
     RecoveryError::AgentError(format!("{}: {}", message, error))
 }
 
+fn save_certificate_to_file(certificate: &Certificate, path: &Path) -> RecoveryResult<()> {
+    serde_cbor::to_vec(certificate)
+        .map_err(|err| agent_error("Failed to serialize the state tree", err))
+        .and_then(|bytes| write_bytes(path, bytes))
+        .map_err(|err| agent_error("Failed to write the state tree to disk", err))
+}
+
+fn create_canister_path(canister_id: CanisterId, label: &[u8]) -> Vec<Label<StorageType>> {
+    vec![
+        CANISTER_LABEL.into(),
+        canister_id.get().as_slice().into(),
+        label.into(),
+    ]
+}
+
 fn create_path(subnet_id: SubnetId, label: &[u8]) -> Vec<Label<StorageType>> {
     vec![
         SUBNET_LABEL.into(),
@@ -0,0 +1,377 @@
+use crate::{
+    admin_helper::{
+        get_propose_to_complete_canister_migration_command,
+        get_propose_to_prepare_canister_migration_command,
+        get_propose_to_reroute_canister_ranges_command,
+    },
+    layout::Layout,
+    steps::{CheckCanisterImportedStep, ExportCanisterStep, ReadRegistryStep, UploadCanisterStep},
+    target_subnet::TargetSubnet,
+};
+
+use clap::Parser;
+use ic_base_types::{CanisterId, SubnetId};
+use ic_recovery::{
+    cli::read_optional,
+    error::{RecoveryError, RecoveryResult},
+    get_member_ips,
+    recovery_iterator::RecoveryIterator,
+    recovery_state::{HasRecoveryState, RecoveryState},
+    registry_helper::RegistryPollingStrategy,
+    steps::{AdminStep, DownloadIcStateStep, Step},
+    NeuronArgs, Recovery, RecoveryArgs, IC_REGISTRY_LOCAL_STORE,
+};
+use ic_registry_routing_table::CanisterIdRange;
+use ic_state_manager::manifest::canister_state_hash;
+use serde::{Deserialize, Serialize};
+use slog::Logger;
+use strum::{EnumMessage, IntoEnumIterator};
+use strum_macros::{EnumIter, EnumString};
+
+use std::{iter::Peekable, net::IpAddr, path::PathBuf};
+
+/// Steps of migrating a single canister from the source subnet to the
+/// destination subnet, without halting either subnet. The migration is driven
+/// by the registry's routing table and `canister_migrations` entries, which
+/// the replicas pick up:
+///
+/// 1. Once the migration is prepared, the source subnet stops the canister
+///    and rejects any changes to it.
+/// 2. The stopped canister's state is exported from a source checkpoint and
+///    staged on all nodes of the destination subnet.
+/// 3. The canister is rerouted, recording the hash of the exported state in
+///    the registry. The destination subnet imports the staged state once it
+///    matches the hash, and messages in flight are rerouted.
+/// 4. The destination subnet's certificate of the import is read and recorded
+///    in the registry when completing the migration. The source subnet then
+///    drops the canister.
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    EnumIter,
+    EnumString,
+    Serialize,
+    Deserialize,
+    EnumMessage,
+    clap::ValueEnum,
+)]
+pub enum StepType {
+    PrepareCanisterMigration,
+    CheckCanisterMigrationStatus,
+    DeployReadonlyKeyToSourceSubnet,
+    DownloadStateFromSourceSubnet,
+    ExportCanisterState,
+    UploadCanisterStateToDestinationSubnet,
+    RerouteCanister,
+    CheckCanisterMigrationStatusAfterRerouting,
+    CheckCanisterImported,
+    CompleteCanisterMigration,
+    CheckCanisterMigrationStatusAgain,
+    Cleanup,
+}
+
+#[derive(Debug, Clone, PartialEq, Parser, Serialize, Deserialize)]
+#[clap(version = "1.0")]
+pub struct CanisterMigrationArgs {
+    /// Id of the canister to be migrated.
+    #[clap(long)]
+    pub canister_id: CanisterId,
+
+    /// Id of the subnet currently hosting the canister.
+    #[clap(long, parse(try_from_str=ic_recovery::util::subnet_id_from_str))]
+    pub source_subnet_id: SubnetId,
+
+    /// Id of the subnet the canister is migrated to.
+    #[clap(long, parse(try_from_str=ic_recovery::util::subnet_id_from_str))]
+    pub destination_subnet_id: SubnetId,
+
+    /// Public ssh key to be deployed to the source subnet for read only access.
+    #[clap(long)]
+    pub pub_key: Option<String>,
+
+    /// IP address of the node from the source subnet to download the state from.
+    #[clap(long)]
+    pub download_node_source: Option<IpAddr>,
+
+    /// Path to the NNS public key used to verify the destination subnet's
+    /// certificate of the import. If absent, the mainnet key is used.
+    #[clap(long)]
+    pub nns_public_key_path: Option<PathBuf>,
+
+    /// If present the tool will start execution for the provided step, skipping the initial ones.
+    #[clap(long = "resume")]
+    #[clap(value_enum)]
+    pub next_step: Option<StepType>,
+}
+
+pub struct CanisterMigration {
+    step_iterator: Peekable<StepTypeIter>,
+    params: CanisterMigrationArgs,
+    recovery_args: RecoveryArgs,
+    neuron_args: Option<NeuronArgs>,
+    recovery: Recovery,
+    layout: Layout,
+    logger: Logger,
+    interactive: bool,
+}
+
+impl CanisterMigration {
+    pub fn new(
+        logger: Logger,
+        recovery_args: RecoveryArgs,
+        neuron_args: Option<NeuronArgs>,
+        canister_migration_args: CanisterMigrationArgs,
+        interactive: bool,
+    ) -> Self {
+        let recovery = Recovery::new(
+            logger.clone(),
+            recovery_args.clone(),
+            neuron_args.clone(),
+            recovery_args.nns_url.clone(),
+            RegistryPollingStrategy::WithEveryRead,
+        )
+        .expect("Failed to initialize recovery");
+
+        Self {
+            step_iterator: StepType::iter().peekable(),
+            params: canister_migration_args,
+            recovery_args,
+            neuron_args,
+            layout: Layout::new(&recovery),
+            recovery,
+            logger,
+            interactive,
+        }
+    }
+
+    pub fn get_recovery_api(&self) -> &Recovery {
+        &self.recovery
+    }
+
+    /// The single canister ID range being migrated.
+    fn canister_id_ranges(&self) -> Vec<CanisterIdRange> {
+        vec![CanisterIdRange {
+            start: self.params.canister_id,
+            end: self.params.canister_id,
+        }]
+    }
+
+    fn migration_status_step(&self) -> impl Step {
+        let registry_helper = self.recovery.registry_helper.clone();
+        let canister_id = self.params.canister_id;
+
+        ReadRegistryStep {
+            logger: self.recovery.logger.clone(),
+            label: format!("Migration Status of canister {}", canister_id),
+            querier: move || registry_helper.get_canister_migration_status(canister_id),
+            interactive: self.interactive,
+        }
+    }
+
+    fn deploy_readonly_key(&self) -> RecoveryResult<impl Step> {
+        let pub_key = self
+            .params
+            .pub_key
+            .clone()
+            .ok_or(RecoveryError::StepSkipped)?;
+
+        Ok(self.recovery.halt_subnet(
+            self.params.source_subnet_id,
+            /*is_halted=*/ false,
+            &[pub_key],
+        ))
+    }
+
+    fn download_state_step(&self) -> RecoveryResult<impl Step> {
+        let node_ip = self
+            .params
+            .download_node_source
+            .ok_or(RecoveryError::StepSkipped)?;
+        let work_dir = self
+            .layout
+            .work_dir(TargetSubnet::Source)
+            .display()
+            .to_string();
+
+        Ok(DownloadIcStateStep {
+            logger: self.recovery.logger.clone(),
+            try_readonly: self.params.pub_key.is_some(),
+            node_ip,
+            target: work_dir.clone(),
+            working_dir: work_dir,
+            keep_downloaded_state: false,
+            require_confirmation: self.interactive,
+            key_file: self.recovery.key_file.clone(),
+            additional_excludes: vec![
+                "orchestrator".to_string(),
+                "ic_consensus_pool".to_string(),
+                IC_REGISTRY_LOCAL_STORE.to_string(),
+            ],
+        })
+    }
+
+    /// Hash of the exported state of the canister, recorded in the registry
+    /// so that the destination subnet only imports that exact state.
+    fn exported_canister_state_hash(&self) -> RecoveryResult<[u8; 32]> {
+        canister_state_hash(&self.layout.canister_export_dir(self.params.canister_id)).map_err(
+            |err| RecoveryError::validation_failed("Failed to hash the exported state", err),
+        )
+    }
+
+    /// IP addresses of all nodes of the destination subnet.
+    fn destination_node_ips(&self) -> RecoveryResult<Vec<IpAddr>> {
+        get_member_ips(
+            &self.recovery.registry_helper,
+            self.params.destination_subnet_id,
+        )
+    }
+}
+
+impl RecoveryIterator<StepType, StepTypeIter> for CanisterMigration {
+    fn get_step_iterator(&mut self) -> &mut Peekable<StepTypeIter> {
+        &mut self.step_iterator
+    }
+
+    fn store_next_step(&mut self, step_type: Option<StepType>) {
+        self.params.next_step = step_type;
+    }
+
+    fn get_logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    fn interactive(&self) -> bool {
+        self.interactive
+    }
+
+    fn read_step_params(&mut self, step_type: StepType) {
+        match step_type {
+            StepType::DeployReadonlyKeyToSourceSubnet => {
+                if self.params.pub_key.is_none() {
+                    self.params.pub_key = read_optional(
+                        &self.logger,
+                        "Enter public key to add readonly SSH access to the source subnet: ",
+                    )
+                }
+            }
+
+            StepType::DownloadStateFromSourceSubnet => {
+                if self.params.download_node_source.is_none() {
+                    self.params.download_node_source =
+                        read_optional(&self.logger, "Enter download IP on the Source Subnet:");
+                }
+            }
+
+            _ => (),
+        }
+    }
+
+    fn get_step_impl(&self, step_type: StepType) -> RecoveryResult<Box<dyn Step>> {
+        let step: Box<dyn Step> = match step_type {
+            StepType::PrepareCanisterMigration => AdminStep {
+                logger: self.recovery.logger.clone(),
+                ic_admin_cmd: get_propose_to_prepare_canister_migration_command(
+                    &self.recovery.admin_helper,
+                    &self.canister_id_ranges(),
+                    self.params.source_subnet_id,
+                    self.params.destination_subnet_id,
+                ),
+            }
+            .into(),
+
+            StepType::CheckCanisterMigrationStatus
+            | StepType::CheckCanisterMigrationStatusAfterRerouting
+            | StepType::CheckCanisterMigrationStatusAgain => self.migration_status_step().into(),
+
+            StepType::DeployReadonlyKeyToSourceSubnet => self.deploy_readonly_key()?.into(),
+
+            StepType::DownloadStateFromSourceSubnet => self.download_state_step()?.into(),
+
+            StepType::ExportCanisterState => ExportCanisterStep {
+                canister_id: self.params.canister_id,
+                layout: self.layout.clone(),
+                logger: self.recovery.logger.clone(),
+            }
+            .into(),
+
+            StepType::UploadCanisterStateToDestinationSubnet => UploadCanisterStep {
+                canister_id: self.params.canister_id,
+                node_ips: self.destination_node_ips()?,
+                layout: self.layout.clone(),
+                recovery: self.recovery.clone(),
+                require_confirmation: self.interactive,
+            }
+            .into(),
+
+            StepType::RerouteCanister => AdminStep {
+                logger: self.recovery.logger.clone(),
+                ic_admin_cmd: get_propose_to_reroute_canister_ranges_command(
+                    &self.recovery.admin_helper,
+                    &self.canister_id_ranges(),
+                    self.params.source_subnet_id,
+                    self.params.destination_subnet_id,
+                    &[(
+                        self.params.canister_id,
+                        self.exported_canister_state_hash()?,
+                    )],
+                ),
+            }
+            .into(),
+
+            StepType::CheckCanisterImported => CheckCanisterImportedStep {
+                canister_id: self.params.canister_id,
+                node_ips: self.destination_node_ips()?,
+                layout: self.layout.clone(),
+                nns_public_key_path: self.params.nns_public_key_path.clone(),
+                logger: self.recovery.logger.clone(),
+            }
+            .into(),
+
+            StepType::CompleteCanisterMigration => AdminStep {
+                logger: self.recovery.logger.clone(),
+                ic_admin_cmd: get_propose_to_complete_canister_migration_command(
+                    &self.recovery.admin_helper,
+                    &self.canister_id_ranges(),
+                    self.params.source_subnet_id,
+                    self.params.destination_subnet_id,
+                    &[(
+                        self.params.canister_id,
+                        self.layout
+                            .canister_import_certificate_file(self.params.canister_id),
+                    )],
+                ),
+            }
+            .into(),
+
+            StepType::Cleanup => self.recovery.get_cleanup_step().into(),
+        };
+
+        Ok(step)
+    }
+}
+
+impl Iterator for CanisterMigration {
+    type Item = (StepType, Box<dyn Step>);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_step()
+    }
+}
+
+impl HasRecoveryState for CanisterMigration {
+    type StepType = StepType;
+    type SubcommandArgsType = CanisterMigrationArgs;
+
+    fn get_next_step(&self) -> Option<Self::StepType> {
+        self.params.next_step
+    }
+
+    fn get_state(&self) -> RecoveryResult<RecoveryState<Self::SubcommandArgsType>> {
+        Ok(RecoveryState {
+            recovery_args: self.recovery_args.clone(),
+            neuron_args: self.neuron_args.clone(),
+            subcommand_args: self.params.clone(),
+        })
+    }
+}
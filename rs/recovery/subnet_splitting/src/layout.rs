@@ -1,6 +1,6 @@
 use crate::target_subnet::TargetSubnet;
 
-use ic_base_types::SubnetId;
+use ic_base_types::{CanisterId, SubnetId};
 use ic_recovery::{error::RecoveryResult, Recovery, CHECKPOINTS, CUPS_DIR, IC_STATE};
 
 use std::path::{Path, PathBuf};
@@ -93,6 +93,23 @@ impl Layout {
         }
     }
 
+    /// Directory holding the state of `canister_id`, as exported from the
+    /// source subnet for a canister migration.
+    pub(crate) fn canister_export_dir(&self, canister_id: CanisterId) -> PathBuf {
+        self.root
+            .join("canister_export")
+            .join(hex::encode(canister_id.get_ref().as_slice()))
+    }
+
+    /// File holding the destination subnet's certificate of the import of
+    /// `canister_id`, in CBOR format.
+    pub(crate) fn canister_import_certificate_file(&self, canister_id: CanisterId) -> PathBuf {
+        self.root.join(format!(
+            "canister_import_{}.cbor",
+            hex::encode(canister_id.get_ref().as_slice())
+        ))
+    }
+
     pub(crate) fn latest_checkpoint_dir(
         &self,
         target_subnet: TargetSubnet,
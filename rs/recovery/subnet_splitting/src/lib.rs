@@ -1,3 +1,4 @@
+pub mod canister_migration;
 pub mod subnet_splitting;
 pub mod validation;
 
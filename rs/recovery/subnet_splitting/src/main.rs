@@ -2,6 +2,7 @@ use clap::Parser;
 use ic_base_types::SubnetId;
 use ic_recovery::{cli, error::RecoveryResult, util, NeuronArgs, RecoveryArgs};
 use ic_subnet_splitting::{
+    canister_migration::{CanisterMigration, CanisterMigrationArgs},
     subnet_splitting::{SubnetSplitting, SubnetSplittingArgs},
     validation::validate_artifacts,
 };
@@ -43,6 +44,38 @@ struct SplitArgs {
     subnet_splitting_args: SubnetSplittingArgs,
 }
 
+#[derive(Parser)]
+struct MigrateCanisterArgs {
+    #[clap(
+        short = 'r',
+        long,
+        alias = "registry-url",
+        default_value = "https://ic0.app"
+    )]
+    /// The URL of an NNS entry point. That is, the URL of any replica on the
+    /// NNS subnet.
+    nns_url: Url,
+
+    /// replica version of ic-admin binary
+    #[clap(long, parse(try_from_str=::std::convert::TryFrom::try_from))]
+    replica_version: Option<ReplicaVersion>,
+
+    /// The directory to perform the canister migration in
+    #[clap(long, parse(from_os_str))]
+    dir: PathBuf,
+
+    /// The path to a private key to be considered for SSH connections
+    #[clap(long, parse(from_os_str))]
+    key_file: Option<PathBuf>,
+
+    /// Flag to enter test mode
+    #[clap(long)]
+    test: bool,
+
+    #[clap(flatten)]
+    canister_migration_args: CanisterMigrationArgs,
+}
+
 #[derive(Parser)]
 struct ValidateArgs {
     /// Path to the State Tree signed by the NNS
@@ -75,6 +108,9 @@ enum Subcommand {
 
     /// Validate artifacts produced during subnet splitting
     Validate(ValidateArgs),
+
+    /// Migrate a single canister to another subnet
+    MigrateCanister(MigrateCanisterArgs),
 }
 
 #[derive(Parser)]
@@ -130,6 +166,55 @@ fn do_split(args: SplitArgs, logger: Logger) -> RecoveryResult<()> {
     Ok(())
 }
 
+fn canister_migration(
+    logger: Logger,
+    recovery_args: RecoveryArgs,
+    canister_migration_args: CanisterMigrationArgs,
+    mut neuron_args: Option<NeuronArgs>,
+) {
+    cli::print_step(&logger, "Canister Migration");
+    cli::wait_for_confirmation(&logger);
+
+    if neuron_args.is_none() && !recovery_args.test_mode {
+        neuron_args = Some(cli::read_neuron_args(&logger));
+    }
+
+    let canister_migration = CanisterMigration::new(
+        logger.clone(),
+        recovery_args,
+        neuron_args,
+        canister_migration_args,
+        /*interactive=*/ true,
+    );
+
+    cli::execute_steps(&logger, canister_migration);
+}
+
+fn do_migrate_canister(args: MigrateCanisterArgs, logger: Logger) -> RecoveryResult<()> {
+    let recovery_args = RecoveryArgs {
+        dir: args.dir,
+        nns_url: args.nns_url,
+        replica_version: args.replica_version,
+        key_file: args.key_file,
+        test_mode: args.test,
+    };
+
+    let canister_migration_state = cli::read_and_maybe_update_state(
+        &logger,
+        recovery_args,
+        Some(args.canister_migration_args),
+    );
+
+    canister_migration(
+        logger,
+        canister_migration_state.recovery_args,
+        canister_migration_state.subcommand_args,
+        canister_migration_state.neuron_args,
+    );
+
+    Ok(())
+}
+
 fn do_validate(args: ValidateArgs, logger: Logger) -> RecoveryResult<()> {
     validate_artifacts(
         args.state_tree_path,
@@ -149,5 +234,8 @@ fn main() -> RecoveryResult<()> {
     match args.subcommand {
         Subcommand::Split(split_args) => do_split(split_args, logger),
         Subcommand::Validate(validate_args) => do_validate(validate_args, logger),
+        Subcommand::MigrateCanister(migrate_canister_args) => {
+            do_migrate_canister(migrate_canister_args, logger)
+        }
     }
 }
@@ -7,8 +7,9 @@ use crate::{
     validation::validate_artifacts,
 };
 
-use ic_base_types::SubnetId;
+use ic_base_types::{CanisterId, SubnetId};
 use ic_metrics::MetricsRegistry;
+use ic_protobuf::state::canister_state_bits::v1::canister_state_bits::CanisterStatus;
 use ic_recovery::{
     cli::consent_given,
     error::{RecoveryError, RecoveryResult},
@@ -16,16 +17,17 @@ use ic_recovery::{
     registry_helper::VersionedRecoveryResult,
     steps::Step,
     util::parse_hex_str,
-    Recovery, CUPS_DIR, IC_REGISTRY_LOCAL_STORE,
+    Recovery, ADMIN, CUPS_DIR, IC_DATA_PATH, IC_REGISTRY_LOCAL_STORE, IC_STATE,
 };
 use ic_registry_routing_table::CanisterIdRange;
 use ic_registry_subnet_type::SubnetType;
+use ic_state_layout::{CanisterLayout, ReadOnly, CANISTER_IMPORTS_DIR, CANISTER_STATES_DIR};
 use ic_state_manager::split::resolve_ranges_and_split;
 use ic_types::Height;
 use slog::{error, info, Logger};
 use url::Url;
 
-use std::net::IpAddr;
+use std::{net::IpAddr, path::PathBuf};
 
 pub(crate) struct CopyWorkDirStep {
     pub(crate) layout: Layout,
@@ -284,6 +286,241 @@ impl Step for WaitForCUPStep {
     }
 }
 
+pub(crate) struct ExportCanisterStep {
+    pub(crate) canister_id: CanisterId,
+    pub(crate) layout: Layout,
+    pub(crate) logger: Logger,
+}
+
+impl Step for ExportCanisterStep {
+    fn descr(&self) -> String {
+        format!(
+            "Exporting the state of the canister {} from the latest checkpoint in {} to {}.",
+            self.canister_id,
+            self.layout.checkpoints_dir(TargetSubnet::Source).display(),
+            self.layout.canister_export_dir(self.canister_id).display(),
+        )
+    }
+
+    fn exec(&self) -> RecoveryResult<()> {
+        let canister_dir = self
+            .layout
+            .latest_checkpoint_dir(TargetSubnet::Source)?
+            .join(CANISTER_STATES_DIR)
+            .join(hex::encode(self.canister_id.get_ref().as_slice()));
+        let canister_layout = CanisterLayout::<ReadOnly>::new(canister_dir.clone())
+            .map_err(|err| RecoveryError::validation_failed("Canister not found", err))?;
+
+        // Only the state of a stopped canister is frozen until the migration
+        // completes, so that it can be moved without losing messages.
+        let canister_state_bits = canister_layout
+            .canister()
+            .deserialize()
+            .map_err(|err| RecoveryError::validation_failed("Failed to read canister", err))?;
+        if !matches!(
+            canister_state_bits.canister_status,
+            Some(CanisterStatus::Stopped(_))
+        ) {
+            return Err(RecoveryError::ValidationFailed(format!(
+                "Canister {} is not stopped in the latest checkpoint. \
+                 Download the state again after the source subnet's next checkpoint",
+                self.canister_id
+            )));
+        }
+
+        info!(self.logger, "Copying the canister state");
+        rsync(
+            &self.logger,
+            Vec::<String>::default(),
+            &format!("{}/", canister_dir.display()),
+            &self
+                .layout
+                .canister_export_dir(self.canister_id)
+                .display()
+                .to_string(),
+            /*require_confirmation=*/ false,
+            /*key_file=*/ None,
+        )
+        .map(|_| ())
+    }
+}
+
+/// Path of the directory where a replica picks up the staged state of a
+/// canister migrated to its subnet.
+fn canister_import_path(canister_id: CanisterId) -> String {
+    format!(
+        "{}/{}/{}/{}",
+        IC_DATA_PATH,
+        IC_STATE,
+        CANISTER_IMPORTS_DIR,
+        hex::encode(canister_id.get_ref().as_slice())
+    )
+}
+
+pub(crate) struct UploadCanisterStep {
+    pub(crate) canister_id: CanisterId,
+    pub(crate) node_ips: Vec<IpAddr>,
+    pub(crate) layout: Layout,
+    pub(crate) recovery: Recovery,
+    pub(crate) require_confirmation: bool,
+}
+
+impl Step for UploadCanisterStep {
+    fn descr(&self) -> String {
+        format!(
+            "Uploading the state of the canister {} from {} to {} on nodes {:?}, \
+             where the replicas import it once the canister is rerouted.",
+            self.canister_id,
+            self.layout.canister_export_dir(self.canister_id).display(),
+            canister_import_path(self.canister_id),
+            self.node_ips,
+        )
+    }
+
+    fn exec(&self) -> RecoveryResult<()> {
+        let import_path = canister_import_path(self.canister_id);
+        let upload_dir = format!("{}/canister_upload", IC_DATA_PATH);
+        let ic_state_path = format!("{}/{}", IC_DATA_PATH, IC_STATE);
+        let src = format!(
+            "{}/",
+            self.layout.canister_export_dir(self.canister_id).display()
+        );
+
+        for node_ip in &self.node_ips {
+            info!(self.recovery.logger, "Uploading to node {}...", node_ip);
+            self.recovery.execute_ssh_command(
+                ADMIN,
+                *node_ip,
+                &format!(
+                    "sudo rm -rf {dir}; sudo mkdir -p {dir}; sudo chown -R {} {dir};",
+                    ADMIN,
+                    dir = upload_dir
+                ),
+            )?;
+            rsync(
+                &self.recovery.logger,
+                Vec::<String>::default(),
+                &src,
+                &format!("{}@[{}]:{}/", ADMIN, node_ip, upload_dir),
+                self.require_confirmation,
+                self.recovery.key_file.as_ref(),
+            )?;
+
+            // Moving the uploaded directory in place is atomic, so replicas
+            // never pick up a partially uploaded state.
+            let mut stage = String::new();
+            stage.push_str(&format!(
+                "sudo chmod -R --reference={} {};",
+                ic_state_path, upload_dir
+            ));
+            stage.push_str(&format!(
+                "sudo chown -R --reference={} {};",
+                ic_state_path, upload_dir
+            ));
+            stage.push_str(&format!(
+                r"sudo find {} -type f -exec chmod a-x {{}} \;;",
+                upload_dir
+            ));
+            stage.push_str(&format!("sudo rm -rf {};", import_path));
+            stage.push_str(&format!("sudo mv {} {};", upload_dir, import_path));
+            self.recovery.execute_ssh_command(ADMIN, *node_ip, &stage)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) struct CheckCanisterImportedStep {
+    pub(crate) canister_id: CanisterId,
+    pub(crate) node_ips: Vec<IpAddr>,
+    pub(crate) layout: Layout,
+    pub(crate) nns_public_key_path: Option<PathBuf>,
+    pub(crate) logger: Logger,
+}
+
+impl Step for CheckCanisterImportedStep {
+    fn descr(&self) -> String {
+        format!(
+            "Checking that the destination subnet imported the canister {}, by reading its \
+             certified state tree from one of the nodes {:?}, and saving the certificate to {}.",
+            self.canister_id,
+            self.node_ips,
+            self.layout
+                .canister_import_certificate_file(self.canister_id)
+                .display(),
+        )
+    }
+
+    fn exec(&self) -> RecoveryResult<()> {
+        let canister_layout =
+            CanisterLayout::<ReadOnly>::new(self.layout.canister_export_dir(self.canister_id))
+                .map_err(|err| RecoveryError::validation_failed("Canister not exported", err))?;
+        let wasm = canister_layout.wasm();
+        let expected_module_hash = if wasm.raw_path().exists() {
+            let module = wasm.deserialize(None).map_err(|err| {
+                RecoveryError::validation_failed("Failed to read the exported module", err)
+            })?;
+            Some(module.module_hash())
+        } else {
+            None
+        };
+
+        let mut last_error = RecoveryError::ValidationFailed(format!(
+            "No node to read the state tree of canister {} from",
+            self.canister_id
+        ));
+        for node_ip in &self.node_ips {
+            let state_tree = Recovery::get_nns_endpoint(*node_ip)
+                .and_then(|node_url| {
+                    AgentHelper::new(
+                        &node_url,
+                        self.nns_public_key_path.as_deref(),
+                        self.logger.clone(),
+                    )
+                })
+                .and_then(|agent_helper| {
+                    let state_tree = agent_helper.read_canister_data(self.canister_id)?;
+                    agent_helper.validate_canister_state_tree(&state_tree)?;
+                    Ok(state_tree)
+                });
+            match state_tree {
+                Ok(state_tree) => {
+                    state_tree.check_canister_present()?;
+                    let module_hash = state_tree.lookup_module_hash()?;
+                    if module_hash != expected_module_hash.as_ref().map(|hash| &hash[..]) {
+                        return Err(RecoveryError::ValidationFailed(format!(
+                            "Canister {} has module hash {:?} on the destination subnet, \
+                             expected {:?}",
+                            self.canister_id,
+                            module_hash.map(hex::encode),
+                            expected_module_hash.map(hex::encode),
+                        )));
+                    }
+
+                    info!(
+                        self.logger,
+                        "Canister {} imported by the destination subnet", self.canister_id
+                    );
+                    return state_tree.save_to_file(
+                        &self
+                            .layout
+                            .canister_import_certificate_file(self.canister_id),
+                    );
+                }
+                Err(err) => {
+                    error!(
+                        self.logger,
+                        "Failed to read the state tree from node {}: {}", node_ip, err
+                    );
+                    last_error = err;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
 pub(crate) struct ReadRegistryStep<T: std::fmt::Debug, F: Fn() -> VersionedRecoveryResult<T>> {
     pub(crate) logger: Logger,
     pub(crate) label: String,
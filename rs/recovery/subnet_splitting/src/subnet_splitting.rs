@@ -512,6 +512,7 @@ impl RecoveryIterator<StepType, StepTypeIter> for SubnetSplitting {
                     &self.params.canister_id_ranges_to_move,
                     self.params.source_subnet_id,
                     self.params.destination_subnet_id,
+                    /*exported_canister_states=*/ &[],
                 ),
             }
             .into(),
@@ -595,6 +596,7 @@ impl RecoveryIterator<StepType, StepTypeIter> for SubnetSplitting {
                     &self.params.canister_id_ranges_to_move,
                     self.params.source_subnet_id,
                     self.params.destination_subnet_id,
+                    /*canister_import_certificates=*/ &[],
                 ),
             }
             .into(),
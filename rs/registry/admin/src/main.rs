@@ -121,7 +121,7 @@ use maplit::hashmap;
 use prost::Message;
 use registry_canister::mutations::{
     common::decode_registry_value,
-    complete_canister_migration::CompleteCanisterMigrationPayload,
+    complete_canister_migration::{CanisterImportCertificate, CompleteCanisterMigrationPayload},
     do_add_node_operator::AddNodeOperatorPayload,
     do_add_nodes_to_subnet::AddNodesToSubnetPayload,
     do_change_subnet_membership::ChangeSubnetMembershipPayload,
@@ -143,7 +143,7 @@ use registry_canister::mutations::{
     },
     node_management::do_remove_nodes::RemoveNodesPayload,
     prepare_canister_migration::PrepareCanisterMigrationPayload,
-    reroute_canister_ranges::RerouteCanisterRangesPayload,
+    reroute_canister_ranges::{ExportedCanisterState, RerouteCanisterRangesPayload},
 };
 use serde::{Deserialize, Serialize};
 use std::{
@@ -3453,6 +3453,10 @@ struct ProposeToRerouteCanisterRangesCmd {
     /// The destination subnet for the specified canister range.
     #[clap(long, required = true)]
     destination_subnet: PrincipalId,
+    /// The hashes of the canister states exported from the source subnet, as
+    /// `<CANISTER_ID>:<HEX_STATE_HASH>`.
+    #[clap(long, multiple_values(true))]
+    exported_canister_states: Vec<String>,
 }
 
 impl ProposalTitle for ProposeToRerouteCanisterRangesCmd {
@@ -3476,10 +3480,34 @@ impl ProposalPayload<RerouteCanisterRangesPayload> for ProposeToRerouteCanisterR
             reassigned_canister_ranges: self.canister_id_ranges.clone(),
             source_subnet: SubnetId::from(self.source_subnet),
             destination_subnet: SubnetId::from(self.destination_subnet),
+            exported_canister_states: Some(
+                self.exported_canister_states
+                    .iter()
+                    .map(|arg| {
+                        let (canister_id, state_hash) = parse_canister_id_pair(arg);
+                        ExportedCanisterState {
+                            canister_id,
+                            state_hash: hex::decode(state_hash).unwrap_or_else(|_| {
+                                panic!("Could not parse the state hash in '{}'", arg)
+                            }),
+                        }
+                    })
+                    .collect(),
+            ),
         }
     }
 }
 
+/// Parses a `<CANISTER_ID>:<VALUE>` argument.
+fn parse_canister_id_pair(arg: &str) -> (CanisterId, &str) {
+    let (canister_id, value) = arg
+        .split_once(':')
+        .unwrap_or_else(|| panic!("Expected '<CANISTER_ID>:<VALUE>', got '{}'", arg));
+    let canister_id = CanisterId::from_str(canister_id)
+        .unwrap_or_else(|_| panic!("Could not parse the canister ID in '{}'", arg));
+    (canister_id, value)
+}
+
 /// Sub-command to submit a proposal to remove some entries from the canister migrations.
 #[derive_common_proposal_fields]
 #[derive(ProposalMetadata, Parser)]
@@ -3490,6 +3518,10 @@ struct ProposeToCompleteCanisterMigrationCmd {
     /// The migration trace containing a list of subnet IDs.
     #[clap(long, multiple_values(true), required = true)]
     migration_trace: Vec<PrincipalId>,
+    /// The destination subnets' certificates of the imported canisters, as
+    /// `<CANISTER_ID>:<PATH_TO_CBOR_CERTIFICATE>`.
+    #[clap(long, multiple_values(true))]
+    canister_import_certificates: Vec<String>,
 }
 
 impl ProposalTitle for ProposeToCompleteCanisterMigrationCmd {
@@ -3515,6 +3547,18 @@ impl ProposalPayload<CompleteCanisterMigrationPayload> for ProposeToCompleteCani
                 .cloned()
                 .map(SubnetId::from)
                 .collect(),
+            canister_import_certificates: Some(
+                self.canister_import_certificates
+                    .iter()
+                    .map(|arg| {
+                        let (canister_id, path) = parse_canister_id_pair(arg);
+                        CanisterImportCertificate {
+                            canister_id,
+                            certificate: read_file_fully(Path::new(path)),
+                        }
+                    })
+                    .collect(),
+            ),
         }
     }
 }
@@ -14,6 +14,7 @@ use ic_protobuf::registry::{
     node_operator::v1::{NodeOperatorRecord, RemoveNodeOperatorsPayload},
     node_rewards::v2::UpdateNodeRewardsTableProposalPayload,
 };
+use ic_registry_routing_table::CanisterMigrationStatus;
 use ic_registry_transport::{
     deserialize_atomic_mutate_request, deserialize_get_changes_since_request,
     deserialize_get_value_request,
@@ -832,6 +833,29 @@ fn get_node_operators_and_dcs_of_node_provider_(
     registry().get_node_operators_and_dcs_of_node_provider(node_provider)
 }
 
+#[export_name = "canister_query get_canister_migration_status"]
+fn get_canister_migration_status() {
+    over(
+        candid_one,
+        |arg: GetSubnetForCanisterRequest| -> Result<CanisterMigrationStatus, String> {
+            get_canister_migration_status_(arg)
+        },
+    )
+}
+
+#[candid_method(query, rename = "get_canister_migration_status")]
+fn get_canister_migration_status_(
+    arg: GetSubnetForCanisterRequest,
+) -> Result<CanisterMigrationStatus, String> {
+    let Some(principal) = arg.principal else {
+        return Err("No principal supplied".to_string());
+    };
+
+    registry()
+        .get_canister_migration_status(&principal)
+        .map_err(|e| e.to_string())
+}
+
 #[export_name = "canister_query get_subnet_for_canister"]
 fn get_subnet_for_canister() {
     over(
@@ -1,9 +1,9 @@
 use crate::registry::Registry;
 use candid::CandidType;
-use ic_base_types::SubnetId;
+use ic_base_types::{CanisterId, SubnetId};
 use ic_registry_routing_table::{CanisterIdRange, CanisterIdRanges};
 use serde::{Deserialize, Serialize};
-use std::convert::TryFrom;
+use std::{collections::BTreeMap, convert::TryFrom};
 
 impl Registry {
     /// Removes the given entries from `canister_migrations`.
@@ -19,11 +19,62 @@ impl Registry {
         let canister_id_ranges = CanisterIdRanges::try_from(canister_id_ranges)
             .map_err(|e| format!("canister ID ranges are not well formed: {:?}", e))?;
 
-        self.maybe_apply_mutation_internal(vec![self.remove_canister_migrations_mutation(
-            self.latest_version(),
+        // The source subnets only drop their copies of the migrated canisters
+        // once the destinations' certificates of the imports are recorded, so
+        // every pending import in the completed ranges needs one.
+        let version = self.latest_version();
+        let canister_imports = self.get_canister_imports(version).unwrap_or_default();
+        let certificates: BTreeMap<_, _> = payload
+            .canister_import_certificates
+            .unwrap_or_default()
+            .into_iter()
+            .map(|import_certificate| {
+                (
+                    import_certificate.canister_id,
+                    import_certificate.certificate,
+                )
+            })
+            .collect();
+        for canister_id in certificates.keys() {
+            if !canister_id_ranges.contains(canister_id)
+                || canister_imports.get(canister_id).is_none()
+            {
+                return Err(format!(
+                    "canister {} has no import in the ranges to be completed",
+                    canister_id
+                ));
+            }
+        }
+        for (canister_id, import) in canister_imports.iter() {
+            if canister_id_ranges.contains(canister_id)
+                && import.import_certificate.is_none()
+                && !certificates.contains_key(canister_id)
+            {
+                return Err(format!(
+                    "missing the import certificate of canister {}",
+                    canister_id
+                ));
+            }
+        }
+
+        let mut mutations = vec![self.remove_canister_migrations_mutation(
+            version,
             canister_id_ranges,
             payload.migration_trace,
-        )]);
+        )];
+        if !certificates.is_empty() {
+            mutations.push(
+                self.modify_canister_imports_mutation(version, |canister_imports| {
+                    for (canister_id, certificate) in certificates {
+                        canister_imports
+                            .get_mut(&canister_id)
+                            .unwrap()
+                            .import_certificate = Some(certificate);
+                    }
+                }),
+            );
+        }
+        self.maybe_apply_mutation_internal(mutations);
 
         Ok(())
     }
@@ -36,4 +87,15 @@ pub struct CompleteCanisterMigrationPayload {
     pub canister_id_ranges: Vec<CanisterIdRange>,
     /// The migration trace containing a list of subnet IDs.
     pub migration_trace: Vec<SubnetId>,
+    /// The destination subnets' certificates of the imported canisters.
+    pub canister_import_certificates: Option<Vec<CanisterImportCertificate>>,
+}
+
+/// A certificate of the destination subnet's state tree proving that it
+/// imported a migrated canister.
+#[derive(Debug, CandidType, Serialize, Deserialize)]
+pub struct CanisterImportCertificate {
+    pub canister_id: CanisterId,
+    /// The CBOR-encoded certificate.
+    pub certificate: Vec<u8>,
 }
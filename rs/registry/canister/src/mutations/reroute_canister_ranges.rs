@@ -1,6 +1,6 @@
 use crate::registry::Registry;
 use candid::CandidType;
-use ic_base_types::SubnetId;
+use ic_base_types::{CanisterId, SubnetId};
 use ic_registry_keys::make_subnet_record_key;
 use ic_registry_routing_table::{is_subset_of, CanisterIdRange, CanisterIdRanges};
 use serde::{Deserialize, Serialize};
@@ -57,14 +57,14 @@ impl Registry {
         let dest_to_src = vec![destination, source];
         // The exact range needs to be present in the map with the same trace.
         // In case of rolling back the migration, the trace from destination to source is also allowed.
-        let all_covered_by_canister_migrations = reassigned_canister_ranges
+        let is_migration = reassigned_canister_ranges
             .iter()
-            .all(|range| canister_migrations.get(range) == Some(&src_to_dest))
-            || reassigned_canister_ranges
-                .iter()
-                .all(|range| canister_migrations.get(range) == Some(&dest_to_src));
+            .all(|range| canister_migrations.get(range) == Some(&src_to_dest));
+        let is_rollback = reassigned_canister_ranges
+            .iter()
+            .all(|range| canister_migrations.get(range) == Some(&dest_to_src));
 
-        if !all_covered_by_canister_migrations {
+        if !is_migration && !is_rollback {
             // If the rerouting is neither valid normal migration nor valid rollback,
             // the rerouting cannot proceed and an error is returned.
             return Err(format!(
@@ -73,11 +73,42 @@ impl Registry {
             ));
         }
 
-        self.maybe_apply_mutation_internal(vec![self.reroute_canister_ranges_mutation(
-            version,
-            reassigned_canister_ranges,
-            destination,
-        )]);
+        // The destination only imports canister states whose hash is recorded
+        // here. A rollback leaves the canisters with their original subnet, so
+        // it discards the imports instead.
+        let exported_canister_states = payload.exported_canister_states.unwrap_or_default();
+        if is_rollback && !exported_canister_states.is_empty() {
+            return Err("a rollback cannot import exported canister states".to_string());
+        }
+        let mut state_hashes = Vec::with_capacity(exported_canister_states.len());
+        for exported in exported_canister_states {
+            if !reassigned_canister_ranges.contains(&exported.canister_id) {
+                return Err(format!(
+                    "exported canister {} is not in the ranges to be migrated",
+                    exported.canister_id
+                ));
+            }
+            let state_hash =
+                <[u8; 32]>::try_from(exported.state_hash.as_slice()).map_err(|_| {
+                    format!(
+                        "the state hash of exported canister {} is not 32 bytes long",
+                        exported.canister_id
+                    )
+                })?;
+            state_hashes.push((exported.canister_id, state_hash));
+        }
+
+        let canister_imports_mutation =
+            self.modify_canister_imports_mutation(version, |canister_imports| {
+                canister_imports.remove_ranges(&reassigned_canister_ranges);
+                for (canister_id, state_hash) in state_hashes {
+                    canister_imports.insert(canister_id, state_hash);
+                }
+            });
+        self.maybe_apply_mutation_internal(vec![
+            self.reroute_canister_ranges_mutation(version, reassigned_canister_ranges, destination),
+            canister_imports_mutation,
+        ]);
 
         Ok(())
     }
@@ -93,4 +124,15 @@ pub struct RerouteCanisterRangesPayload {
     pub source_subnet: SubnetId,
     /// The new destination for the canister ID ranges.
     pub destination_subnet: SubnetId,
+    /// The hashes of the canister states exported from the source subnet,
+    /// which the destination subnet imports. Must be empty for a rollback.
+    pub exported_canister_states: Option<Vec<ExportedCanisterState>>,
+}
+
+/// A canister state exported from the source subnet of a migration.
+#[derive(Debug, CandidType, Serialize, Deserialize)]
+pub struct ExportedCanisterState {
+    pub canister_id: CanisterId,
+    /// The 32-byte hash of the exported canister state directory.
+    pub state_hash: Vec<u8>,
 }
@@ -8,9 +8,13 @@ use std::convert::TryFrom;
 use dfn_core::CanisterId;
 use ic_base_types::{PrincipalId, SubnetId};
 use ic_protobuf::registry::routing_table::v1 as pb;
-use ic_registry_keys::{make_canister_migrations_record_key, make_routing_table_record_key};
+use ic_registry_keys::{
+    make_canister_imports_record_key, make_canister_migrations_record_key,
+    make_routing_table_record_key,
+};
 use ic_registry_routing_table::{
-    routing_table_insert_subnet, CanisterIdRanges, CanisterMigrations, RoutingTable,
+    canister_migration_status, routing_table_insert_subnet, CanisterIdRanges, CanisterImports,
+    CanisterMigrationStatus, CanisterMigrations, RoutingTable,
 };
 use ic_registry_transport::pb::v1::{registry_mutation, RegistryMutation, RegistryValue};
 use prost::Message;
@@ -61,6 +65,18 @@ fn canister_migrations_into_registry_mutation(
     }
 }
 
+/// Returns the given `CanisterImports` as an `Upsert` registry mutation.
+fn canister_imports_into_registry_mutation(canister_imports: CanisterImports) -> RegistryMutation {
+    let canister_imports = pb::CanisterImports::from(canister_imports);
+    let mut buf = vec![];
+    canister_imports.encode(&mut buf).unwrap();
+    RegistryMutation {
+        mutation_type: registry_mutation::Type::Upsert as i32,
+        key: make_canister_imports_record_key().as_bytes().to_vec(),
+        value: buf,
+    }
+}
+
 impl Registry {
     /// Get the routing table or panic on error with a message.
     pub fn get_routing_table_or_panic(&self, version: u64) -> RoutingTable {
@@ -193,6 +209,30 @@ impl Registry {
         )
     }
 
+    /// Retrieves the canister imports if the key exists.
+    pub fn get_canister_imports(&self, version: u64) -> Option<CanisterImports> {
+        self.get(make_canister_imports_record_key().as_bytes(), version)
+            .map(|registry_value| {
+                CanisterImports::try_from(decode_registry_value::<pb::CanisterImports>(
+                    registry_value.value.clone(),
+                ))
+                .expect("failed to decode the canister imports from protobuf")
+            })
+    }
+
+    /// Creates a mutation that applies the given change to `canister_imports`
+    /// at the specified version, creating the entry if it doesn't exist.
+    pub fn modify_canister_imports_mutation(
+        &self,
+        version: u64,
+        f: impl FnOnce(&mut CanisterImports),
+    ) -> RegistryMutation {
+        let mut canister_imports = self.get_canister_imports(version).unwrap_or_default();
+
+        f(&mut canister_imports);
+        canister_imports_into_registry_mutation(canister_imports)
+    }
+
     pub fn get_subnet_for_canister(
         &self,
         principal_id: &PrincipalId,
@@ -212,6 +252,24 @@ impl Registry {
             None => Err(GetSubnetForCanisterError::NoSubnetAssigned),
         }
     }
+
+    /// Returns the migration status of the given canister, as derived from the
+    /// latest routing table and `canister_migrations`.
+    pub fn get_canister_migration_status(
+        &self,
+        principal_id: &PrincipalId,
+    ) -> Result<CanisterMigrationStatus, GetSubnetForCanisterError> {
+        let latest_version = self.latest_version();
+        let routing_table = self.get_routing_table_or_panic(latest_version);
+        let canister_migrations = self
+            .get_canister_migrations(latest_version)
+            .unwrap_or_default();
+        let canister_id = CanisterId::try_from(*principal_id)
+            .map_err(|_| GetSubnetForCanisterError::InvalidCanisterId)?;
+
+        canister_migration_status(&routing_table, &canister_migrations, canister_id)
+            .ok_or(GetSubnetForCanisterError::NoSubnetAssigned)
+    }
 }
 
 #[cfg(test)]
@@ -268,4 +326,72 @@ mod tests {
 
         // GetSubnetForCanisterError::CanisterIdConversion currently not reachable - CanisterId::try_from() always succeeds
     }
+
+    #[test]
+    fn test_get_canister_migration_status() {
+        let mut registry = invariant_compliant_registry(0);
+        let system_subnet = SubnetId::from(
+            PrincipalId::try_from(registry.get_subnet_list_record().subnets.get(0).unwrap())
+                .unwrap(),
+        );
+        let other_subnet = SubnetId::from(PrincipalId::new_subnet_test_id(999));
+
+        let mut rt = RoutingTable::new();
+        rt.insert(
+            CanisterIdRange {
+                start: CanisterId::from(0),
+                end: CanisterId::from(255),
+            },
+            system_subnet,
+        )
+        .unwrap();
+        let mutation =
+            routing_table_into_registry_mutation(rt, registry_mutation::Type::Update as i32);
+        registry.maybe_apply_mutation_internal(vec![mutation]);
+
+        assert_eq!(
+            registry
+                .get_canister_migration_status(&CanisterId::from(5).get())
+                .unwrap(),
+            CanisterMigrationStatus::NotMigrating {
+                subnet_id: system_subnet
+            }
+        );
+
+        let mutation = registry.migrate_canister_ranges_mutation(
+            registry.latest_version(),
+            CanisterIdRanges::try_from(vec![CanisterIdRange {
+                start: CanisterId::from(5),
+                end: CanisterId::from(5),
+            }])
+            .unwrap(),
+            system_subnet,
+            other_subnet,
+        );
+        registry.maybe_apply_mutation_internal(vec![mutation]);
+
+        assert_eq!(
+            registry
+                .get_canister_migration_status(&CanisterId::from(5).get())
+                .unwrap(),
+            CanisterMigrationStatus::Prepared {
+                source: system_subnet,
+                destination: other_subnet,
+            }
+        );
+        assert_eq!(
+            registry
+                .get_canister_migration_status(&CanisterId::from(6).get())
+                .unwrap(),
+            CanisterMigrationStatus::NotMigrating {
+                subnet_id: system_subnet
+            }
+        );
+        assert_matches!(
+            registry
+                .get_canister_migration_status(&CanisterId::from(256).get())
+                .unwrap_err(),
+            GetSubnetForCanisterError::NoSubnetAssigned
+        );
+    }
 }
@@ -17,7 +17,7 @@ use ic_registry_keys::{
     make_catch_up_package_contents_key, make_subnet_list_record_key, make_subnet_record_key,
 };
 use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
-use ic_registry_routing_table::RoutingTable;
+use ic_registry_routing_table::{CanisterImports, RoutingTable};
 use ic_registry_subnet_features::{EcdsaConfig, DEFAULT_ECDSA_MAX_QUEUE_SIZE};
 use ic_registry_transport::pb::v1::RegistryAtomicMutateRequest;
 use ic_types::crypto::threshold_sig::ni_dkg::{NiDkgTag, NiDkgTranscript};
@@ -318,3 +318,9 @@ pub async fn get_routing_table(canister: &canister_test::Canister<'_>) -> Routin
     let pb_routing_table: pb::RoutingTable = get_value_or_panic(canister, b"routing_table").await;
     RoutingTable::try_from(pb_routing_table).expect("failed to decode routing table")
 }
+
+pub async fn get_canister_imports(canister: &canister_test::Canister<'_>) -> CanisterImports {
+    let pb_canister_imports: pb::CanisterImports =
+        get_value_or_panic(canister, b"canister_imports").await;
+    CanisterImports::try_from(pb_canister_imports).expect("failed to decode canister imports")
+}
@@ -228,6 +228,7 @@ fn test_modify_canister_migrations() {
                         },],
                         source_subnet: nns_subnet,
                         destination_subnet: subnet_id_1,
+                        exported_canister_states: None,
                     })
                     .unwrap(),
                 )
@@ -250,6 +251,7 @@ fn test_modify_canister_migrations() {
             let payload = CompleteCanisterMigrationPayload {
                 canister_id_ranges: canister_id_ranges.clone(),
                 migration_trace: vec![nns_subnet, subnet_id_2],
+                canister_import_certificates: None,
             };
 
             // Try to reassign the range in the routing table in a conflicting way.
@@ -274,6 +276,7 @@ fn test_modify_canister_migrations() {
             let payload = CompleteCanisterMigrationPayload {
                 canister_id_ranges,
                 migration_trace: vec![nns_subnet, subnet_id_1],
+                canister_import_certificates: None,
             };
 
             try_call_via_universal_canister(
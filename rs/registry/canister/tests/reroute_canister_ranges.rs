@@ -14,12 +14,12 @@ use registry_canister::{
     init::RegistryCanisterInitPayloadBuilder,
     mutations::{
         prepare_canister_migration::PrepareCanisterMigrationPayload,
-        reroute_canister_ranges::RerouteCanisterRangesPayload,
+        reroute_canister_ranges::{ExportedCanisterState, RerouteCanisterRangesPayload},
     },
 };
 
 mod common;
-use common::test_helpers::{check_error_message, get_routing_table};
+use common::test_helpers::{check_error_message, get_canister_imports, get_routing_table};
 
 #[test]
 fn test_reroute_canister_ranges() {
@@ -90,6 +90,10 @@ fn test_reroute_canister_ranges() {
                 }],
                 source_subnet: nns_subnet,
                 destination_subnet: subnet_id_1,
+                exported_canister_states: Some(vec![ExportedCanisterState {
+                    canister_id: CanisterId::from(10),
+                    state_hash: vec![7; 32],
+                }]),
             };
 
             try_call_via_universal_canister(
@@ -120,6 +124,16 @@ fn test_reroute_canister_ranges() {
                 Some(nns_subnet)
             );
 
+            // The destination imports the exported canister with the given hash.
+            let canister_imports = get_canister_imports(&registry).await;
+            assert_eq!(
+                canister_imports
+                    .iter()
+                    .map(|(canister_id, import)| (*canister_id, import.state_hash))
+                    .collect::<Vec<_>>(),
+                vec![(CanisterId::from(10), [7; 32])]
+            );
+
             check_error_message(
                 registry
                     .update_(
@@ -132,6 +146,7 @@ fn test_reroute_canister_ranges() {
                             }],
                             source_subnet: nns_subnet,
                             destination_subnet: subnet_id_1,
+                            exported_canister_states: None,
                         },
                     )
                     .await as Result<(), String>,
@@ -151,6 +166,7 @@ fn test_reroute_canister_ranges() {
                         },],
                         source_subnet: nns_subnet,
                         destination_subnet: subnet_id_1,
+                        exported_canister_states: None,
                     })
                     .unwrap(),
                 )
@@ -171,6 +187,7 @@ fn test_reroute_canister_ranges() {
                         },],
                         source_subnet: nns_subnet,
                         destination_subnet: subnet_test_id(9999),
+                        exported_canister_states: None,
                     })
                     .unwrap(),
                 )
@@ -98,6 +98,7 @@ fn test_roll_back_canister_migration() {
                 }],
                 source_subnet: nns_subnet,
                 destination_subnet: subnet_id_1,
+                exported_canister_states: None,
             };
 
             try_call_via_universal_canister(
@@ -143,6 +144,7 @@ fn test_roll_back_canister_migration() {
                         }],
                         source_subnet: subnet_id_1,
                         destination_subnet: nns_subnet,
+                        exported_canister_states: None,
                     })
                     .unwrap(),
                 )
@@ -158,6 +160,7 @@ fn test_roll_back_canister_migration() {
                 }],
                 source_subnet: subnet_id_1,
                 destination_subnet: nns_subnet,
+                exported_canister_states: None,
             };
 
             try_call_via_universal_canister(
@@ -1,8 +1,13 @@
 use crate::deserialize_registry_value;
 use ic_interfaces_registry::{RegistryClient, RegistryClientResult};
 use ic_protobuf::registry::routing_table::v1 as pb;
-use ic_registry_keys::{make_canister_migrations_record_key, make_routing_table_record_key};
-use ic_registry_routing_table::{CanisterIdRange, CanisterMigrations, RoutingTable};
+use ic_registry_keys::{
+    make_canister_imports_record_key, make_canister_migrations_record_key,
+    make_routing_table_record_key,
+};
+use ic_registry_routing_table::{
+    CanisterIdRange, CanisterImports, CanisterMigrations, RoutingTable,
+};
 use ic_types::{registry::RegistryClientError::DecodeError, RegistryVersion, SubnetId};
 use std::convert::TryFrom;
 
@@ -20,6 +25,10 @@ pub trait RoutingTableRegistry {
         &self,
         version: RegistryVersion,
     ) -> RegistryClientResult<CanisterMigrations>;
+    fn get_canister_imports(
+        &self,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<CanisterImports>;
 }
 
 impl<T: RegistryClient + ?Sized> RoutingTableRegistry for T {
@@ -70,4 +79,22 @@ impl<T: RegistryClient + ?Sized> RoutingTableRegistry for T {
             },
         )?
     }
+
+    fn get_canister_imports(
+        &self,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<CanisterImports> {
+        let bytes = self.get_value(&make_canister_imports_record_key(), version);
+        deserialize_registry_value::<pb::CanisterImports>(bytes).map(
+            |option_pb_canister_imports| {
+                option_pb_canister_imports
+                    .map(|pb_canister_imports| {
+                        CanisterImports::try_from(pb_canister_imports).map_err(|err| DecodeError {
+                            error: format!("get_canister_imports() failed with {}", err),
+                        })
+                    })
+                    .transpose()
+            },
+        )?
+    }
 }
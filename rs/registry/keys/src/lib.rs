@@ -98,6 +98,10 @@ pub fn make_canister_migrations_record_key() -> String {
     "canister_migrations".to_string()
 }
 
+pub fn make_canister_imports_record_key() -> String {
+    "canister_imports".to_string()
+}
+
 // TODO: Remove when all subnets are upgraded with IC-1026
 pub fn make_firewall_config_record_key() -> String {
     "firewall_config".to_string()
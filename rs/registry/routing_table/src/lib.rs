@@ -708,6 +708,112 @@ impl CanisterMigrations {
     }
 }
 
+/// The import of a migrated canister by its destination subnet.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanisterImport {
+    /// Hash of the canister state exported from the source subnet.
+    pub state_hash: [u8; 32],
+    /// CBOR-encoded certificate of the destination subnet's state tree proving
+    /// that it hosts the imported canister; `None` until the migration is
+    /// completed.
+    pub import_certificate: Option<Vec<u8>>,
+}
+
+/// Imports of migrated canisters, keyed by canister ID.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanisterImports(BTreeMap<CanisterId, CanisterImport>);
+
+impl CanisterImports {
+    /// Constructs an empty set of canister imports.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns an iterator over the ordered entries.
+    pub fn iter(&self) -> impl std::iter::Iterator<Item = (&CanisterId, &CanisterImport)> {
+        self.0.iter()
+    }
+
+    /// Returns a reference to the import of `canister_id`, if any.
+    pub fn get(&self, canister_id: &CanisterId) -> Option<&CanisterImport> {
+        self.0.get(canister_id)
+    }
+
+    /// Returns a mutable reference to the import of `canister_id`, if any.
+    pub fn get_mut(&mut self, canister_id: &CanisterId) -> Option<&mut CanisterImport> {
+        self.0.get_mut(canister_id)
+    }
+
+    /// Records the import of `canister_id` with the given exported state hash,
+    /// replacing any import recorded by an earlier migration of the canister.
+    pub fn insert(&mut self, canister_id: CanisterId, state_hash: [u8; 32]) {
+        self.0.insert(
+            canister_id,
+            CanisterImport {
+                state_hash,
+                import_certificate: None,
+            },
+        );
+    }
+
+    /// Removes the imports of all canisters in the given ranges.
+    pub fn remove_ranges(&mut self, canister_id_ranges: &CanisterIdRanges) {
+        self.0
+            .retain(|canister_id, _| !canister_id_ranges.contains(canister_id));
+    }
+}
+
+/// Progress of a canister migration, as derived from the routing table and
+/// `canister_migrations`.
+#[derive(CandidType, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CanisterMigrationStatus {
+    /// The canister is not being migrated and is hosted by `subnet_id`.
+    NotMigrating { subnet_id: SubnetId },
+    /// The canister has a `canister_migrations` entry but is still routed to
+    /// `source`, which must stop the canister and export its state.
+    Prepared {
+        source: SubnetId,
+        destination: SubnetId,
+    },
+    /// The canister is routed to `destination`, which hosts (or is about to
+    /// import) its state. Messages still in flight from or to `source` are
+    /// rerouted until the `canister_migrations` entry is removed.
+    Rerouted {
+        source: SubnetId,
+        destination: SubnetId,
+    },
+}
+
+/// Returns the migration status of `canister_id`; or `None` if the canister is
+/// not assigned to any subnet.
+pub fn canister_migration_status(
+    routing_table: &RoutingTable,
+    canister_migrations: &CanisterMigrations,
+    canister_id: CanisterId,
+) -> Option<CanisterMigrationStatus> {
+    let (_range, subnet_id) = routing_table.lookup_entry(canister_id)?;
+
+    let trace = match canister_migrations.lookup(canister_id) {
+        Some(trace) => trace,
+        None => return Some(CanisterMigrationStatus::NotMigrating { subnet_id }),
+    };
+    // Migration traces are well formed, i.e. contain at least two subnets.
+    let source = *trace.first().unwrap();
+    let destination = *trace.last().unwrap();
+
+    Some(if subnet_id == destination {
+        CanisterMigrationStatus::Rerouted {
+            source,
+            destination,
+        }
+    } else {
+        CanisterMigrationStatus::Prepared {
+            source,
+            destination,
+        }
+    })
+}
+
 fn lookup_in_ranges<V: Clone>(
     canister_id_range_to_value: &BTreeMap<CanisterIdRange, V>,
     canister_id: CanisterId,
@@ -1,4 +1,7 @@
-use super::{CanisterIdRange, CanisterIdRanges, CanisterMigrations, RoutingTable};
+use super::{
+    CanisterIdRange, CanisterIdRanges, CanisterImport, CanisterImports, CanisterMigrations,
+    RoutingTable,
+};
 use ic_base_types::{subnet_id_into_protobuf, subnet_id_try_from_protobuf, CanisterId};
 use ic_protobuf::{
     proxy::{try_from_option_field, ProxyDecodeError},
@@ -141,3 +144,56 @@ impl TryFrom<pb::CanisterMigrations> for CanisterMigrations {
         Ok(map.try_into()?)
     }
 }
+
+impl From<CanisterImports> for pb::CanisterImports {
+    fn from(src: CanisterImports) -> Self {
+        Self::from(&src)
+    }
+}
+
+impl From<&CanisterImports> for pb::CanisterImports {
+    fn from(src: &CanisterImports) -> Self {
+        let entries = src
+            .0
+            .iter()
+            .map(|(canister_id, import)| pb::canister_imports::Entry {
+                canister_id: Some(pb_types::CanisterId::from(*canister_id)),
+                state_hash: import.state_hash.to_vec(),
+                import_certificate: import.import_certificate.clone().unwrap_or_default(),
+            })
+            .collect();
+        Self { entries }
+    }
+}
+
+impl TryFrom<pb::CanisterImports> for CanisterImports {
+    type Error = ProxyDecodeError;
+
+    fn try_from(src: pb::CanisterImports) -> Result<Self, Self::Error> {
+        let mut map = BTreeMap::new();
+        for entry in src.entries {
+            let canister_id = CanisterId::try_from(entry.canister_id.ok_or(
+                ProxyDecodeError::MissingField("CanisterImports::Entry::canister_id"),
+            )?)?;
+            let state_hash = entry.state_hash.as_slice().try_into().map_err(|_| {
+                ProxyDecodeError::Other(format!(
+                    "CanisterImports::Entry::state_hash has length {}, expected 32",
+                    entry.state_hash.len()
+                ))
+            })?;
+            let import = CanisterImport {
+                state_hash,
+                import_certificate: Some(entry.import_certificate)
+                    .filter(|certificate| !certificate.is_empty()),
+            };
+            if let Some(prev_import) = map.insert(canister_id, import.clone()) {
+                return Err(ProxyDecodeError::DuplicateEntry {
+                    key: canister_id.to_string(),
+                    v1: format!("{:?}", prev_import),
+                    v2: format!("{:?}", import),
+                });
+            }
+        }
+        Ok(CanisterImports(map))
+    }
+}
@@ -693,3 +693,95 @@ fn canister_migrations_can_remove_ranges() {
         new_canister_migrations(vec![((6, 10), vec![0, 1])])
     );
 }
+
+#[test]
+fn canister_migration_status_follows_migration_progress() {
+    let canister_migrations = new_canister_migrations(vec![((10, 19), vec![1, 2])]);
+
+    // Canister 10 is being migrated from subnet 1 to subnet 2, but still routed to subnet 1.
+    let routing_table = new_routing_table(vec![((0, 19), 1), ((20, 29), 2)]);
+    assert_eq!(
+        canister_migration_status(&routing_table, &canister_migrations, CanisterId::from(10)),
+        Some(CanisterMigrationStatus::Prepared {
+            source: subnet_test_id(1),
+            destination: subnet_test_id(2),
+        })
+    );
+    // Canister 5 is not being migrated.
+    assert_eq!(
+        canister_migration_status(&routing_table, &canister_migrations, CanisterId::from(5)),
+        Some(CanisterMigrationStatus::NotMigrating {
+            subnet_id: subnet_test_id(1)
+        })
+    );
+    // Canister 30 is not assigned to any subnet.
+    assert_eq!(
+        canister_migration_status(&routing_table, &canister_migrations, CanisterId::from(30)),
+        None
+    );
+
+    // After rerouting, canister 10 is routed to subnet 2.
+    let routing_table = new_routing_table(vec![((0, 9), 1), ((10, 29), 2)]);
+    assert_eq!(
+        canister_migration_status(&routing_table, &canister_migrations, CanisterId::from(10)),
+        Some(CanisterMigrationStatus::Rerouted {
+            source: subnet_test_id(1),
+            destination: subnet_test_id(2),
+        })
+    );
+
+    // After completing the migration, canister 10 is hosted by subnet 2.
+    assert_eq!(
+        canister_migration_status(
+            &routing_table,
+            &CanisterMigrations::new(),
+            CanisterId::from(10)
+        ),
+        Some(CanisterMigrationStatus::NotMigrating {
+            subnet_id: subnet_test_id(2)
+        })
+    );
+}
+
+#[test]
+fn canister_imports_can_remove_ranges() {
+    let mut canister_imports = CanisterImports::new();
+    for canister_id in [3, 7, 12] {
+        canister_imports.insert(CanisterId::from(canister_id), [canister_id as u8; 32]);
+    }
+
+    canister_imports.remove_ranges(&new_canister_id_ranges(vec![(0, 5), (10, 20)]));
+
+    assert_eq!(
+        canister_imports
+            .iter()
+            .map(|(canister_id, _)| *canister_id)
+            .collect::<Vec<_>>(),
+        vec![CanisterId::from(7)]
+    );
+}
+
+#[test]
+fn canister_imports_proto_round_trip() {
+    let mut canister_imports = CanisterImports::new();
+    canister_imports.insert(CanisterId::from(1), [1; 32]);
+    canister_imports.insert(CanisterId::from(2), [2; 32]);
+    canister_imports
+        .get_mut(&CanisterId::from(2))
+        .unwrap()
+        .import_certificate = Some(vec![42; 10]);
+
+    let proto = ic_protobuf::registry::routing_table::v1::CanisterImports::from(&canister_imports);
+    assert_eq!(
+        CanisterImports::try_from(proto.clone()).unwrap(),
+        canister_imports
+    );
+
+    // State hashes must be 32 bytes long.
+    let mut invalid = proto;
+    invalid.entries[0].state_hash.pop();
+    assert_matches!(
+        CanisterImports::try_from(invalid),
+        Err(ProxyDecodeError::Other(_))
+    );
+}
@@ -17,9 +17,10 @@ use ic_artifact_pool::{
 use ic_config::{artifact_pool::ArtifactPoolConfig, transport::TransportConfig};
 use ic_consensus::{
     certification::{setup as certification_setup, CertificationCrypto},
-    consensus::{dkg_key_manager::DkgKeyManager, setup as consensus_setup},
+    consensus::{dkg_key_manager::DkgKeyManager, setup as consensus_setup, ConsensusGossipImpl},
     dkg, ecdsa,
 };
+use ic_consensus_manager::BlockProposalAssembler;
 use ic_consensus_utils::{
     crypto::ConsensusCrypto, membership::Membership, pool_reader::PoolReader,
 };
@@ -78,6 +79,9 @@ use std::{
 };
 
 const ENABLE_NEW_STATE_SYNC: bool = false;
+/// Transmit consensus artifacts over the QUIC transport, with block proposals
+/// referencing their ingress messages instead of carrying them.
+const ENABLE_NEW_P2P_CONSENSUS: bool = false;
 
 /// The P2P state sync client.
 pub enum P2PStateSyncClient {
@@ -103,6 +107,8 @@ struct ArtifactPools {
 
 struct P2PClients {
     consensus: ArtifactClientHandle<ConsensusArtifact>,
+    /// The priority function producer of the consensus client.
+    consensus_gossip: Arc<ConsensusGossipImpl>,
     ingress: ArtifactClientHandle<IngressArtifact>,
     certification: ArtifactClientHandle<CertificationArtifact>,
     dkg: ArtifactClientHandle<DkgArtifact>,
@@ -161,6 +167,7 @@ pub fn setup_consensus_and_p2p(
 ) {
     let consensus_pool_cache = consensus_pool.read().unwrap().get_cache();
     let (advert_tx, advert_rx) = bounded(MAX_ADVERT_BUFFER);
+    let (consensus_event_tx, consensus_event_rx) = tokio::sync::mpsc::channel(MAX_ADVERT_BUFFER);
 
     let (p2p_clients, mut join_handles, ingress_pool) = start_consensus(
         log,
@@ -180,16 +187,19 @@ pub fn setup_consensus_and_p2p(
         query_stats_payload_builder,
         message_router,
         ingress_history_reader,
-        consensus_pool,
+        Arc::clone(&consensus_pool),
         malicious_flags,
         cycles_account_manager,
         local_store_time_reader,
         registry_poll_delay_duration_ms,
         advert_tx.clone(),
+        consensus_event_tx,
         canister_http_adapter_client,
         time_source.clone(),
     );
     let mut ingress_sender = p2p_clients.ingress.sender.clone();
+    let consensus_sender = p2p_clients.consensus.sender.clone();
+    let consensus_gossip = p2p_clients.consensus_gossip;
     // P2P stack follows
 
     let mut backends: HashMap<ArtifactTag, Box<dyn manager::ArtifactManagerBackend>> =
@@ -274,6 +284,28 @@ pub fn setup_consensus_and_p2p(
         P2PStateSyncClient::TestClient() => (None, None),
     };
 
+    // Consensus over the new P2P stack
+    let (consensus_router, consensus_manager) = if ENABLE_NEW_P2P_CONSENSUS {
+        let assembler = Arc::new(BlockProposalAssembler::new(
+            Arc::clone(&ingress_pool) as Arc<_>
+        ));
+        let (router, adverts_received) = ic_consensus_manager::build_axum_router(
+            log.clone(),
+            rt_handle.clone(),
+            Arc::clone(&consensus_pool) as Arc<_>,
+            Arc::clone(&assembler),
+        );
+        (Some(router), Some((adverts_received, assembler)))
+    } else {
+        (None, None)
+    };
+    let router = match (state_sync_router, consensus_router) {
+        (Some(state_sync_router), Some(consensus_router)) => {
+            Some(state_sync_router.merge(consensus_router))
+        }
+        (state_sync_router, consensus_router) => state_sync_router.or(consensus_router),
+    };
+
     let sev_handshake = Arc::new(Sev::new(node_id, registry_client.clone()));
 
     // Quic transport
@@ -299,9 +331,9 @@ pub fn setup_consensus_and_p2p(
         registry_client.clone(),
         sev_handshake.clone(),
         node_id,
-        topology_watcher,
+        topology_watcher.clone(),
         Either::<_, DummyUdpSocket>::Left(transport_addr),
-        state_sync_router,
+        router,
     ));
 
     if let Some((state_sync_client, state_sync_manager_rx)) = state_sync_client {
@@ -309,12 +341,29 @@ pub fn setup_consensus_and_p2p(
             log.clone(),
             metrics_registry,
             rt_handle,
-            quic_transport,
+            quic_transport.clone(),
             state_sync_client,
             state_sync_manager_rx,
         );
     }
 
+    if let Some((adverts_received, assembler)) = consensus_manager {
+        ic_consensus_manager::start_consensus_manager(
+            log.clone(),
+            metrics_registry,
+            rt_handle.clone(),
+            consensus_event_rx,
+            adverts_received,
+            consensus_pool,
+            consensus_gossip,
+            consensus_sender,
+            Arc::clone(&time_source) as Arc<_>,
+            quic_transport,
+            assembler,
+            topology_watcher,
+        );
+    }
+
     // Tcp transport
     let oldest_registry_version_in_use = consensus_pool_cache.get_oldest_registry_version_in_use();
     let transport = transport.unwrap_or_else(|| {
@@ -381,6 +430,7 @@ fn start_consensus(
     local_store_time_reader: Arc<dyn LocalStoreCertifiedTimeReader>,
     registry_poll_delay_duration_ms: u64,
     advert_tx: Sender<GossipAdvert>,
+    consensus_event_tx: tokio::sync::mpsc::Sender<ArtifactProcessorEvent<ConsensusArtifact>>,
     canister_http_adapter_client: CanisterHttpAdapterClient,
     time_source: Arc<SysTimeSource>,
 ) -> (
@@ -440,37 +490,43 @@ fn start_consensus(
         &PoolReader::new(&*consensus_pool.read().unwrap()),
     )));
 
+    let (consensus, consensus_gossip) = consensus_setup(
+        replica_config.clone(),
+        Arc::clone(&registry_client),
+        Arc::clone(&membership) as Arc<_>,
+        Arc::clone(&consensus_crypto),
+        Arc::clone(&ingress_manager) as Arc<_>,
+        xnet_payload_builder,
+        self_validating_payload_builder,
+        canister_http_payload_builder,
+        Arc::from(query_stats_payload_builder),
+        Arc::clone(&artifact_pools.dkg_pool) as Arc<_>,
+        Arc::clone(&artifact_pools.ecdsa_pool) as Arc<_>,
+        Arc::clone(&dkg_key_manager) as Arc<_>,
+        message_router,
+        Arc::clone(&state_manager) as Arc<_>,
+        Arc::clone(&time_source) as Arc<_>,
+        malicious_flags.clone(),
+        metrics_registry.clone(),
+        log.clone(),
+        local_store_time_reader,
+        registry_poll_delay_duration_ms,
+    );
+    // Shared with the consensus manager of the new P2P stack.
+    let consensus_gossip = Arc::new(consensus_gossip);
+
     let consensus_client = {
         let advert_tx = advert_tx.clone();
         // Create the consensus client.
         let (client, jh) = create_consensus_handlers(
             move |req| {
-                if let ArtifactProcessorEvent::Advert(advert) = req {
+                if ENABLE_NEW_P2P_CONSENSUS {
+                    let _ = consensus_event_tx.blocking_send(req);
+                } else if let ArtifactProcessorEvent::Advert(advert) = req {
                     let _ = advert_tx.send(advert.into());
                 }
             },
-            consensus_setup(
-                replica_config.clone(),
-                Arc::clone(&registry_client),
-                Arc::clone(&membership) as Arc<_>,
-                Arc::clone(&consensus_crypto),
-                Arc::clone(&ingress_manager) as Arc<_>,
-                xnet_payload_builder,
-                self_validating_payload_builder,
-                canister_http_payload_builder,
-                Arc::from(query_stats_payload_builder),
-                Arc::clone(&artifact_pools.dkg_pool) as Arc<_>,
-                Arc::clone(&artifact_pools.ecdsa_pool) as Arc<_>,
-                Arc::clone(&dkg_key_manager) as Arc<_>,
-                message_router,
-                Arc::clone(&state_manager) as Arc<_>,
-                Arc::clone(&time_source) as Arc<_>,
-                malicious_flags.clone(),
-                metrics_registry.clone(),
-                log.clone(),
-                local_store_time_reader,
-                registry_poll_delay_duration_ms,
-            ),
+            (consensus, Arc::clone(&consensus_gossip)),
             Arc::clone(&time_source) as Arc<_>,
             Arc::clone(&consensus_pool),
             metrics_registry.clone(),
@@ -634,6 +690,7 @@ fn start_consensus(
     };
     let p2p_clients = P2PClients {
         consensus: consensus_client,
+        consensus_gossip,
         certification: certification_client,
         dkg: dkg_client,
         ingress: ingress_client,
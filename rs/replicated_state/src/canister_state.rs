@@ -21,7 +21,7 @@ use ic_types::{
 };
 use ic_types::{LongExecutionMode, NumInstructions};
 use phantom_newtype::AmountOf;
-pub use queues::{CanisterQueues, DEFAULT_QUEUE_CAPACITY};
+pub use queues::{CanisterQueues, DEFAULT_OUTPUT_QUEUES_CAPACITY, DEFAULT_QUEUE_CAPACITY};
 use std::collections::BTreeSet;
 use std::convert::From;
 use std::sync::Arc;
@@ -31,6 +31,14 @@ use std::{
 
 pub const DEFAULT_QUEUE_CAPACITY: usize = 500;
 
+/// The maximum number of messages a canister may have across all of its output
+/// queues, if enforced (see the `canister_output_queues_capacity` execution
+/// config flag). Beyond it, the canister can no longer enqueue requests and
+/// incoming requests are rejected, so that a canister whose output is held back
+/// (e.g. because it exceeds its fair share of an XNet stream) is throttled
+/// instead of accumulating an ever growing backlog. Subnet queues are exempt.
+pub const DEFAULT_OUTPUT_QUEUES_CAPACITY: usize = 10 * DEFAULT_QUEUE_CAPACITY;
+
 /// The default lifetime of a request in OutputQueue from which the deadline
 /// is computed as time + REQUEST_LIFETIME.
 pub const REQUEST_LIFETIME: Duration = Duration::from_secs(300);
@@ -651,6 +659,17 @@ impl CanisterQueues {
             .collect()
     }
 
+    /// Returns a `QueueFull` error if the output queues already hold
+    /// `DEFAULT_OUTPUT_QUEUES_CAPACITY` messages.
+    pub fn check_has_output_capacity(&self) -> Result<(), StateError> {
+        if self.output_queues_stats.message_count >= DEFAULT_OUTPUT_QUEUES_CAPACITY {
+            return Err(StateError::QueueFull {
+                capacity: DEFAULT_OUTPUT_QUEUES_CAPACITY,
+            });
+        }
+        Ok(())
+    }
+
     /// Pushes a `Response` type message into the relevant output queue. The
     /// protocol should have already reserved a slot, so this cannot fail.
     ///
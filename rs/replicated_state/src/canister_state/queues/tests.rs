@@ -182,6 +182,64 @@ fn can_push_input_response_after_output_request() {
     queues.push_input_response().unwrap();
 }
 
+/// Once the output queues hold `DEFAULT_OUTPUT_QUEUES_CAPACITY` messages,
+/// `check_has_output_capacity` fails. The capacity is only enforced by callers
+/// that opted in, so pushing requests is still possible.
+#[test]
+fn check_has_output_capacity_fails_at_capacity() {
+    let this = canister_test_id(13);
+    let mut queues = CanisterQueues::default();
+
+    let receivers = DEFAULT_OUTPUT_QUEUES_CAPACITY / DEFAULT_QUEUE_CAPACITY;
+    for receiver in 0..receivers as u64 {
+        assert_eq!(Ok(()), queues.check_has_output_capacity());
+        for _ in 0..DEFAULT_QUEUE_CAPACITY {
+            queues
+                .push_output_request(
+                    RequestBuilder::default()
+                        .sender(this)
+                        .receiver(canister_test_id(100 + receiver))
+                        .build()
+                        .into(),
+                    mock_time(),
+                )
+                .unwrap();
+        }
+    }
+    assert_eq!(
+        DEFAULT_OUTPUT_QUEUES_CAPACITY,
+        queues.output_queues_message_count()
+    );
+    assert_eq!(
+        Err(StateError::QueueFull {
+            capacity: DEFAULT_OUTPUT_QUEUES_CAPACITY
+        }),
+        queues.check_has_output_capacity()
+    );
+
+    let other = canister_test_id(11);
+    queues
+        .push_output_request(
+            RequestBuilder::default()
+                .sender(this)
+                .receiver(other)
+                .build()
+                .into(),
+            mock_time(),
+        )
+        .unwrap();
+    queues
+        .push_input(
+            RequestBuilder::default()
+                .sender(other)
+                .receiver(this)
+                .build()
+                .into(),
+            InputQueueType::RemoteSubnet,
+        )
+        .unwrap();
+}
+
 /// Check `available_output_request_slots` doesn't count input requests and
 /// output reservations and responses.
 #[test]
@@ -23,8 +23,9 @@ use ic_protobuf::{
     types::v1 as pb_types,
 };
 use ic_registry_routing_table::{
-    canister_id_into_u64, difference, intersection, CanisterIdRanges, CanisterMigrations,
-    RoutingTable, CANISTER_IDS_PER_SUBNET,
+    canister_id_into_u64, canister_migration_status, difference, intersection, CanisterIdRanges,
+    CanisterImport, CanisterImports, CanisterMigrationStatus, CanisterMigrations, RoutingTable,
+    CANISTER_IDS_PER_SUBNET,
 };
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
@@ -173,6 +174,10 @@ pub struct NetworkTopology {
     #[serde(serialize_with = "ic_utils::serde_arc::serialize_arc")]
     #[serde(deserialize_with = "ic_utils::serde_arc::deserialize_arc")]
     pub canister_migrations: Arc<CanisterMigrations>,
+    /// Imports of migrated canisters by their destination subnets.
+    #[serde(serialize_with = "ic_utils::serde_arc::serialize_arc")]
+    #[serde(deserialize_with = "ic_utils::serde_arc::deserialize_arc")]
+    pub canister_imports: Arc<CanisterImports>,
     pub nns_subnet_id: SubnetId,
     /// Mapping from ECDSA key_id to a list of subnets which can sign with the
     /// given key. Keys without any signing subnets are not included in the map.
@@ -191,6 +196,7 @@ impl Default for NetworkTopology {
             subnets: Default::default(),
             routing_table: Default::default(),
             canister_migrations: Default::default(),
+            canister_imports: Default::default(),
             nns_subnet_id: SubnetId::new(PrincipalId::new_anonymous()),
             ecdsa_signing_subnets: Default::default(),
             bitcoin_testnet_canister_id: None,
@@ -214,6 +220,49 @@ impl NetworkTopology {
             .get(subnet_id)
             .map(|subnet_topology| subnet_topology.nodes.len())
     }
+
+    /// Returns the migration status of `canister_id`, as derived from the
+    /// routing table and `canister_migrations`.
+    pub fn canister_migration_status(
+        &self,
+        canister_id: CanisterId,
+    ) -> Option<CanisterMigrationStatus> {
+        canister_migration_status(&self.routing_table, &self.canister_migrations, canister_id)
+    }
+
+    /// Returns `true` if `canister_id` is being migrated away from `subnet_id`,
+    /// i.e. it is either prepared for migration or already rerouted, but the
+    /// migration has not been completed yet.
+    pub fn is_migrating_from(&self, canister_id: CanisterId, subnet_id: SubnetId) -> bool {
+        match self.canister_migration_status(canister_id) {
+            Some(CanisterMigrationStatus::Prepared { source, .. })
+            | Some(CanisterMigrationStatus::Rerouted { source, .. }) => source == subnet_id,
+            Some(CanisterMigrationStatus::NotMigrating { .. }) | None => false,
+        }
+    }
+
+    /// Returns `true` if `canister_id` was rerouted to `subnet_id` by a
+    /// migration that has not been completed yet.
+    pub fn is_rerouted_to(&self, canister_id: CanisterId, subnet_id: SubnetId) -> bool {
+        matches!(
+            self.canister_migration_status(canister_id),
+            Some(CanisterMigrationStatus::Rerouted { destination, .. }) if destination == subnet_id
+        )
+    }
+
+    /// Returns the import of `canister_id` that `subnet_id` must perform, i.e.
+    /// if the canister was rerouted to `subnet_id` by a migration that has not
+    /// been completed yet and its exported state hash is recorded.
+    pub fn pending_canister_import(
+        &self,
+        canister_id: CanisterId,
+        subnet_id: SubnetId,
+    ) -> Option<&CanisterImport> {
+        if !self.is_rerouted_to(canister_id, subnet_id) {
+            return None;
+        }
+        self.canister_imports.get(&canister_id)
+    }
 }
 
 impl From<&NetworkTopology> for pb_metadata::NetworkTopology {
@@ -230,6 +279,7 @@ impl From<&NetworkTopology> for pb_metadata::NetworkTopology {
             routing_table: Some(item.routing_table.as_ref().into()),
             nns_subnet_id: Some(subnet_id_into_protobuf(item.nns_subnet_id)),
             canister_migrations: Some(item.canister_migrations.as_ref().into()),
+            canister_imports: Some(item.canister_imports.as_ref().into()),
             ecdsa_signing_subnets: item
                 .ecdsa_signing_subnets
                 .iter()
@@ -312,6 +362,12 @@ impl TryFrom<pb_metadata::NetworkTopology> for NetworkTopology {
                 .transpose()?
                 .unwrap_or_default()
                 .into(),
+            canister_imports: item
+                .canister_imports
+                .map(CanisterImports::try_from)
+                .transpose()?
+                .unwrap_or_default()
+                .into(),
             nns_subnet_id,
             ecdsa_signing_subnets,
             bitcoin_testnet_canister_id,
@@ -1129,6 +1185,10 @@ pub struct Stream {
 
     /// Estimated byte size of `self.messages`.
     messages_size_bytes: usize,
+
+    /// Estimated byte size of `self.messages`, by sender canister. Used for
+    /// enforcing per-canister fair shares of the stream.
+    sender_size_bytes: BTreeMap<CanisterId, usize>,
 }
 
 impl Default for Stream {
@@ -1137,11 +1197,13 @@ impl Default for Stream {
         let signals_end = Default::default();
         let reject_signals = VecDeque::default();
         let messages_size_bytes = Self::size_bytes(&messages);
+        let sender_size_bytes = Self::sender_size_bytes(&messages);
         Self {
             messages,
             signals_end,
             reject_signals,
             messages_size_bytes,
+            sender_size_bytes,
         }
     }
 }
@@ -1171,6 +1233,7 @@ impl TryFrom<pb_queues::Stream> for Stream {
             messages.push(req_or_resp.try_into()?);
         }
         let messages_size_bytes = Self::size_bytes(&messages);
+        let sender_size_bytes = Self::sender_size_bytes(&messages);
 
         let reject_signals = item
             .reject_signals
@@ -1183,6 +1246,7 @@ impl TryFrom<pb_queues::Stream> for Stream {
             signals_end: item.signals_end.into(),
             reject_signals,
             messages_size_bytes,
+            sender_size_bytes,
         })
     }
 }
@@ -1191,11 +1255,13 @@ impl Stream {
     /// Creates a new `Stream` with the given `messages` and `signals_end`.
     pub fn new(messages: StreamIndexedQueue<RequestOrResponse>, signals_end: StreamIndex) -> Self {
         let messages_size_bytes = Self::size_bytes(&messages);
+        let sender_size_bytes = Self::sender_size_bytes(&messages);
         Self {
             messages,
             signals_end,
             reject_signals: VecDeque::new(),
             messages_size_bytes,
+            sender_size_bytes,
         }
     }
 
@@ -1206,11 +1272,13 @@ impl Stream {
         reject_signals: VecDeque<StreamIndex>,
     ) -> Self {
         let messages_size_bytes = Self::size_bytes(&messages);
+        let sender_size_bytes = Self::sender_size_bytes(&messages);
         Self {
             messages,
             signals_end,
             reject_signals,
             messages_size_bytes,
+            sender_size_bytes,
         }
     }
 
@@ -1246,9 +1314,17 @@ impl Stream {
         self.messages.end()
     }
 
+    /// Returns the estimated byte size of the stream's messages, by sender
+    /// canister. Canisters without messages in the stream are not included.
+    pub fn messages_size_bytes_by_sender(&self) -> &BTreeMap<CanisterId, usize> {
+        &self.sender_size_bytes
+    }
+
     /// Appends the given message to the tail of the stream.
     pub fn push(&mut self, message: RequestOrResponse) {
-        self.messages_size_bytes += message.count_bytes();
+        let size_bytes = message.count_bytes();
+        self.messages_size_bytes += size_bytes;
+        *self.sender_size_bytes.entry(message.sender()).or_default() += size_bytes;
         self.messages.push(message);
         debug_assert_eq!(Self::size_bytes(&self.messages), self.messages_size_bytes);
     }
@@ -1289,8 +1365,19 @@ impl Stream {
             let (index, msg) = self.messages.pop().unwrap();
 
             // Deduct every discarded message from the stream's byte size.
-            self.messages_size_bytes -= msg.count_bytes();
+            let size_bytes = msg.count_bytes();
+            self.messages_size_bytes -= size_bytes;
             debug_assert_eq!(Self::size_bytes(&self.messages), self.messages_size_bytes);
+            let sender = msg.sender();
+            let sender_size_bytes = self
+                .sender_size_bytes
+                .get_mut(&sender)
+                .expect("No `sender_size_bytes` entry for discarded message");
+            *sender_size_bytes -= size_bytes;
+            // Drop zero counts.
+            if *sender_size_bytes == 0 {
+                self.sender_size_bytes.remove(&sender);
+            }
 
             // If we received a reject signal for this message, collect it in
             // `rejected_messages`.
@@ -1346,6 +1433,17 @@ impl Stream {
     fn size_bytes(messages: &StreamIndexedQueue<RequestOrResponse>) -> usize {
         messages.iter().map(|(_, m)| m.count_bytes()).sum()
     }
+
+    /// Calculates the estimated byte size of the given messages, by sender.
+    fn sender_size_bytes(
+        messages: &StreamIndexedQueue<RequestOrResponse>,
+    ) -> BTreeMap<CanisterId, usize> {
+        let mut sender_size_bytes: BTreeMap<CanisterId, usize> = BTreeMap::new();
+        for (_, msg) in messages.iter() {
+            *sender_size_bytes.entry(msg.sender()).or_default() += msg.count_bytes();
+        }
+        sender_size_bytes
+    }
 }
 
 impl CountBytes for Stream {
@@ -1,6 +1,7 @@
 mod checkpoint;
 pub mod int_map;
 mod page_allocator;
+mod storage;
 
 use checkpoint::Checkpoint;
 pub use checkpoint::{CheckpointSerialization, MappingSerialization};
@@ -11,11 +12,16 @@ pub use page_allocator::{
     allocated_pages_count, PageAllocator, PageAllocatorRegistry, PageAllocatorSerialization,
     PageDeltaSerialization, PageSerialization,
 };
+pub use storage::{
+    MergeCandidate, MergeDestination, OverlayFileSerialization, StorageLayout,
+    StorageSerialization, MAX_NUMBER_OF_OVERLAYS,
+};
+use storage::{OverlayFile, Storage};
 
 // NOTE: We use a persistent map to make snapshotting of a PageMap a cheap
 // operation. This allows us to simplify canister state management: we can
 // simply have a copy of the whole PageMap in every canister snapshot.
-use ic_types::{Height, NumPages, MAX_STABLE_MEMORY_IN_BYTES};
+use ic_types::{state_sync::DEFAULT_CHUNK_SIZE, Height, NumPages, MAX_STABLE_MEMORY_IN_BYTES};
 use int_map::{Bounds, IntMap};
 use libc::off_t;
 use page_allocator::Page;
@@ -30,6 +36,13 @@ use std::sync::Arc;
 // When persisting data we expand dirty pages to an aligned bucket of given size.
 const WRITE_BUCKET_PAGES: u64 = 16;
 
+/// The size of the chunks in which `PageMap` tracks modifications since the
+/// last checkpoint. It matches the chunk size of the state sync manifest, so
+/// that a dirty chunk corresponds to exactly one chunk of the manifest.
+pub const DIRTY_CHUNK_SIZE: u64 = DEFAULT_CHUNK_SIZE as u64;
+
+const PAGES_PER_DIRTY_CHUNK: u64 = DIRTY_CHUNK_SIZE / PAGE_SIZE as u64;
+
 struct WriteBuffer<'a> {
     content: Vec<&'a [u8]>,
     start_index: PageIndex,
@@ -122,6 +135,42 @@ where
     }
 }
 
+/// `DirtyChunks` is the set of indices of `DIRTY_CHUNK_SIZE` chunks touched
+/// by a `PageDelta`.
+#[derive(Clone, Default, Debug)]
+struct DirtyChunks(IntMap<()>);
+
+impl DirtyChunks {
+    /// Adds the chunks containing the pages of the given delta.
+    fn update(&mut self, delta: &PageDelta) {
+        let mut chunks = std::mem::take(&mut self.0);
+        let mut last_chunk = None;
+        for (page_index, _) in delta.iter() {
+            let chunk = page_index.get() / PAGES_PER_DIRTY_CHUNK;
+            // Pages are enumerated in order, so consecutive pages of the same
+            // chunk only need to be inserted once.
+            if last_chunk != Some(chunk) {
+                chunks = chunks.insert(chunk, ());
+                last_chunk = Some(chunk);
+            }
+        }
+        self.0 = chunks;
+    }
+
+    /// Enumerates the indices of all dirty chunks in increasing order.
+    fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.0.iter().map(|(idx, _)| idx)
+    }
+}
+
+impl From<&PageDelta> for DirtyChunks {
+    fn from(delta: &PageDelta) -> Self {
+        let mut dirty_chunks = Self::default();
+        dirty_chunks.update(delta);
+        dirty_chunks
+    }
+}
+
 /// Errors that can happen when one saves or loads a PageMap.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum PersistenceError {
@@ -145,6 +194,8 @@ pub enum PersistenceError {
     },
     /// (Slice) size is not equal to page size.
     BadPageSize { expected: usize, actual: usize },
+    /// Overlay file is malformed.
+    InvalidOverlay { path: String, message: String },
 }
 
 impl PersistenceError {
@@ -190,6 +241,9 @@ impl std::fmt::Display for PersistenceError {
                 "Bad slice size: expected {}, actual {}",
                 expected, actual
             ),
+            PersistenceError::InvalidOverlay { path, message } => {
+                write!(f, "Invalid overlay file {}: {}", path, message)
+            }
         }
     }
 }
@@ -248,14 +302,14 @@ pub enum MemoryMapOrData<'a> {
 /// versioned.
 #[derive(Clone)]
 pub struct PageMap {
-    /// The checkpoint that is used for all the pages that can not be found in
-    /// the `page_delta`.
-    checkpoint: Checkpoint,
+    /// The files on disk that are used for all the pages that can not be found
+    /// in the `page_delta`: the checkpoint file and the overlays on top of it.
+    storage: Storage,
 
     /// The height of the checkpoint that backs the page map.
     pub base_height: Option<Height>,
 
-    /// The map containing pages overriding pages from the `checkpoint`.
+    /// The map containing pages overriding pages from the `storage`.
     /// We need these pages to be able to reconstruct the full heap.
     /// It is reset when `strip_all_deltas()` method is called.
     page_delta: PageDelta,
@@ -266,6 +320,12 @@ pub struct PageMap {
     /// Invariant: unflushed_delta ⊆ page_delta
     unflushed_delta: PageDelta,
 
+    /// The chunks of `DIRTY_CHUNK_SIZE` bytes touched since the last
+    /// checkpoint. It is reset when `strip_all_deltas()` method is called.
+    ///
+    /// Invariant: dirty_chunks = { idx / PAGES_PER_DIRTY_CHUNK | idx ∈ page_delta }
+    dirty_chunks: DirtyChunks,
+
     has_stripped_unflushed_deltas: bool,
 
     /// The allocator for PageDelta pages.
@@ -279,10 +339,11 @@ impl PageMap {
     /// the page map is instantiated with.
     pub fn new(fd_factory: Arc<dyn PageAllocatorFileDescriptor>) -> Self {
         Self {
-            checkpoint: Default::default(),
+            storage: Default::default(),
             base_height: Default::default(),
             page_delta: Default::default(),
             unflushed_delta: Default::default(),
+            dirty_chunks: Default::default(),
             has_stripped_unflushed_deltas: false,
             page_allocator: PageAllocator::new(fd_factory),
         }
@@ -291,10 +352,11 @@ impl PageMap {
     /// Creates a new page map for testing purposes.
     pub fn new_for_testing() -> Self {
         Self {
-            checkpoint: Default::default(),
+            storage: Default::default(),
             base_height: Default::default(),
             page_delta: Default::default(),
             unflushed_delta: Default::default(),
+            dirty_chunks: Default::default(),
             has_stripped_unflushed_deltas: false,
             page_allocator: PageAllocator::new_for_testing(),
         }
@@ -310,10 +372,31 @@ impl PageMap {
     ) -> Result<Self, PersistenceError> {
         let checkpoint = Checkpoint::open(heap_file)?;
         Ok(Self {
-            checkpoint,
+            storage: Storage::from_checkpoint(checkpoint),
+            base_height: Some(base_height),
+            page_delta: Default::default(),
+            unflushed_delta: Default::default(),
+            dirty_chunks: Default::default(),
+            has_stripped_unflushed_deltas: false,
+            page_allocator: PageAllocator::new(fd_factory),
+        })
+    }
+
+    /// Creates a page map backed by the base file and the overlays described
+    /// by `storage_layout`. A missing base file is treated as empty.
+    ///
+    /// Note that all files are assumed to be read-only.
+    pub fn open_with_layout(
+        storage_layout: &dyn StorageLayout,
+        base_height: Height,
+        fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
+    ) -> Result<Self, PersistenceError> {
+        Ok(Self {
+            storage: Storage::load(storage_layout)?,
             base_height: Some(base_height),
             page_delta: Default::default(),
             unflushed_delta: Default::default(),
+            dirty_chunks: Default::default(),
             has_stripped_unflushed_deltas: false,
             page_allocator: PageAllocator::new(fd_factory),
         })
@@ -322,7 +405,7 @@ impl PageMap {
     /// Returns a serialization-friendly representation of the page-map.
     pub fn serialize(&self) -> PageMapSerialization {
         PageMapSerialization {
-            checkpoint: self.checkpoint.serialize(),
+            storage: self.storage.serialize(),
             base_height: self.base_height,
             page_delta: self
                 .page_allocator
@@ -344,17 +427,19 @@ impl PageMap {
         page_map: PageMapSerialization,
         registry: &PageAllocatorRegistry,
     ) -> Result<Self, PersistenceError> {
-        let checkpoint = Checkpoint::deserialize(page_map.checkpoint)?;
+        let storage = Storage::deserialize(page_map.storage)?;
         let page_allocator = PageAllocator::deserialize(page_map.page_allocator, registry);
         let page_delta =
             PageDelta::from(page_allocator.deserialize_page_delta(page_map.page_delta));
         let unflushed_delta =
             PageDelta::from(page_allocator.deserialize_page_delta(page_map.unflushed_delta));
+        let dirty_chunks = DirtyChunks::from(&page_delta);
         Ok(Self {
-            checkpoint,
+            storage,
             base_height: page_map.base_height,
             page_delta,
             unflushed_delta,
+            dirty_chunks,
             has_stripped_unflushed_deltas: page_map.has_stripped_unflushed_deltas,
             page_allocator,
         })
@@ -398,6 +483,32 @@ impl PageMap {
         self.persist_to_file(&self.unflushed_delta, dst)
     }
 
+    /// Persists the heap delta contained in this page map as a new overlay
+    /// file written at `height` according to `storage_layout`.
+    pub fn persist_delta_as_overlay(
+        &self,
+        storage_layout: &dyn StorageLayout,
+        height: Height,
+    ) -> Result<(), PersistenceError> {
+        if self.page_delta.is_empty() {
+            return Ok(());
+        }
+        OverlayFile::write(&self.page_delta, &storage_layout.overlay(height))
+    }
+
+    /// Persists the unflushed delta contained in this page map as a new
+    /// overlay file written at `height` according to `storage_layout`.
+    pub fn persist_unflushed_delta_as_overlay(
+        &self,
+        storage_layout: &dyn StorageLayout,
+        height: Height,
+    ) -> Result<(), PersistenceError> {
+        if self.unflushed_delta.is_empty() {
+            return Ok(());
+        }
+        OverlayFile::write(&self.unflushed_delta, &storage_layout.overlay(height))
+    }
+
     /// Returns the iterator over host pages managed by this `PageMap`.
     pub fn host_pages_iter(&self) -> impl Iterator<Item = (PageIndex, &PageBytes)> + '_ {
         (0..self.num_host_pages()).map(move |i| {
@@ -417,7 +528,7 @@ impl PageMap {
     pub fn get_page(&self, page_index: PageIndex) -> &PageBytes {
         match self.page_delta.get_page(page_index) {
             Some(page) => page,
-            None => self.checkpoint.get_page(page_index),
+            None => self.storage.get_page(page_index),
         }
     }
 
@@ -562,7 +673,7 @@ impl PageMap {
     /// The intention is that the instructions from this function are applied first and only once. The more expensive
     /// instructions from `get_memory_instructions(range)` are then applied on top.
     pub fn get_base_memory_instructions(&self) -> MemoryInstructions {
-        self.checkpoint.get_memory_instructions()
+        self.storage.get_base_memory_instructions()
     }
 
     /// Removes the page delta from this page map.
@@ -575,6 +686,7 @@ impl PageMap {
             std::mem::take(&mut self.page_delta);
             std::mem::take(&mut self.unflushed_delta);
         }
+        self.dirty_chunks = Default::default();
         self.page_allocator = PageAllocator::new(Arc::clone(&fd_factory));
     }
 
@@ -589,6 +701,12 @@ impl PageMap {
         self.page_delta.iter().map(|(index, _)| index).collect()
     }
 
+    /// Returns the indices of the `DIRTY_CHUNK_SIZE` chunks modified since
+    /// the last checkpoint in increasing order.
+    pub fn get_dirty_chunk_indices(&self) -> Vec<u64> {
+        self.dirty_chunks.iter().collect()
+    }
+
     /// Whether there are any page deltas
     pub fn page_delta_is_empty(&self) -> bool {
         self.page_delta.is_empty()
@@ -604,6 +722,11 @@ impl PageMap {
         self.has_stripped_unflushed_deltas
     }
 
+    /// Returns the number of overlay files backing this page map.
+    pub fn num_overlays(&self) -> usize {
+        self.storage.num_overlays()
+    }
+
     /// Returns the length of the modified prefix in host pages.
     ///
     /// Also, the following property holds:
@@ -612,8 +735,8 @@ impl PageMap {
     /// ∀ n . n ≥ self.num_host_pages() ⇒ self.get_page(n) = ZERO_PAGE
     /// ```
     pub fn num_host_pages(&self) -> usize {
-        let pages_in_checkpoint = self.checkpoint.num_pages();
-        pages_in_checkpoint.max(
+        let pages_in_storage = self.storage.num_logical_pages();
+        pages_in_storage.max(
             self.page_delta
                 .max_page_index()
                 .map(|i| i.get() + 1)
@@ -624,7 +747,7 @@ impl PageMap {
     /// Switches the checkpoint file of the current page map to the one provided
     /// by the given page map. Page deltas of both page maps must be empty.
     pub fn switch_to_checkpoint(&mut self, checkpointed_page_map: &PageMap) {
-        self.checkpoint = checkpointed_page_map.checkpoint.clone();
+        self.storage = checkpointed_page_map.storage.clone();
         // Also copy the base height to reflect the height of the new checkpoint.
         self.base_height = checkpointed_page_map.base_height;
         assert!(self.page_delta.is_empty());
@@ -640,6 +763,7 @@ impl PageMap {
         I: IntoIterator<Item = (PageIndex, Page)>,
     {
         let delta = PageDelta::from(delta);
+        self.dirty_chunks.update(&delta);
         // Delta is a persistent data structure and is cheap to clone.
         self.page_delta.update(delta.clone());
         self.unflushed_delta.update(delta)
@@ -858,7 +982,7 @@ impl std::fmt::Debug for PageMap {
 /// need `unflushed_delta`, but the field is kept for consistency here.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PageMapSerialization {
-    pub checkpoint: CheckpointSerialization,
+    pub storage: StorageSerialization,
     pub base_height: Option<Height>,
     pub page_delta: PageDeltaSerialization,
     pub unflushed_delta: PageDeltaSerialization,
@@ -0,0 +1,635 @@
+//! Log-structured storage of a `PageMap` on disk.
+//!
+//! Instead of rewriting a single file at every checkpoint, the pages of a
+//! `PageMap` can be stored as a base file (the legacy dense heap file) plus
+//! a stack of overlay files. Every overlay contains only the pages that were
+//! modified in the rounds it covers, together with an index that maps the
+//! slots of the file to page indices. A page is resolved by looking it up in
+//! the overlays from the newest to the oldest one and falling back to the base
+//! file.
+//!
+//! To bound the number of files that need to be consulted, overlays are
+//! periodically merged, see `MergeCandidate`.
+
+use crate::page_map::{
+    checkpoint::{Checkpoint, MappingSerialization},
+    CheckpointSerialization, FileDescriptor, FileOffset, MemoryInstruction, MemoryInstructions,
+    MemoryMapOrData, PageDelta, PersistenceError,
+};
+use ic_sys::{mmap::ScopedMmap, page_bytes_from_ptr, PageBytes, PageIndex, PAGE_SIZE};
+use ic_types::Height;
+use ic_utils::fs::write_all_vectored;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Version of the overlay file format. It is stored in the footer of every
+/// overlay file.
+const OVERLAY_VERSION: u64 = 1;
+
+/// Size of the footer of an overlay file: the number of pages followed by the
+/// version, both encoded as little-endian `u64`.
+const FOOTER_SIZE: usize = 2 * std::mem::size_of::<u64>();
+
+/// Size of a single entry of the index of an overlay file.
+const INDEX_ENTRY_SIZE: usize = std::mem::size_of::<u64>();
+
+/// The maximum number of overlays that a `PageMap` is allowed to have after a
+/// merge.
+pub const MAX_NUMBER_OF_OVERLAYS: usize = 8;
+
+/// Number of pages written to an overlay file with a single syscall.
+const WRITE_BATCH_PAGES: usize = 64;
+
+/// Describes where the files backing a `PageMap` live on disk.
+///
+/// The trait is implemented by `ic_state_layout` and kept here to avoid a
+/// dependency of `ic_replicated_state` on the state layout.
+pub trait StorageLayout {
+    /// Path of the base file. The file does not have to exist.
+    fn base(&self) -> PathBuf;
+
+    /// Path of the overlay file written at the given height.
+    fn overlay(&self, height: Height) -> PathBuf;
+
+    /// All existing overlay files, ordered from the oldest to the newest.
+    fn existing_overlays(&self) -> Result<Vec<PathBuf>, PersistenceError>;
+
+    /// Extracts the height from the path of an overlay file.
+    fn overlay_height(&self, overlay: &Path) -> Result<Height, PersistenceError>;
+}
+
+/// An immutable file containing a subset of the pages of a `PageMap`.
+///
+/// File format, all integers are little-endian:
+///
+/// ```text
+/// [page 0 contents][page 1 contents]...[page n-1 contents]
+/// [page index of page 0: u64]...[page index of page n-1: u64]
+/// [n: u64][version: u64]
+/// ```
+///
+/// The page indices are strictly increasing, which allows binary searching
+/// the index and mapping runs of consecutive pages with a single `mmap`.
+#[derive(Clone)]
+pub(crate) struct OverlayFile {
+    mapping: Arc<OverlayMapping>,
+}
+
+struct OverlayMapping {
+    mmap: ScopedMmap,
+    _file: File, // It is not used but it keeps the `file_descriptor` alive.
+    file_descriptor: FileDescriptor,
+    index: Vec<PageIndex>,
+}
+
+impl OverlayMapping {
+    fn new(file: File, len: usize, path: &str) -> Result<Self, PersistenceError> {
+        let invalid = |message: String| PersistenceError::InvalidOverlay {
+            path: path.to_string(),
+            message,
+        };
+        if len < FOOTER_SIZE {
+            return Err(invalid(format!("file of size {} has no footer", len)));
+        }
+        let mmap = ScopedMmap::from_readonly_file(&file, len).map_err(|err| {
+            PersistenceError::MmapError {
+                path: path.to_string(),
+                len,
+                internal_error: err.to_string(),
+            }
+        })?;
+        let bytes = mmap.as_slice();
+
+        let footer = &bytes[len - FOOTER_SIZE..];
+        let num_pages = read_u64(&footer[..INDEX_ENTRY_SIZE]) as usize;
+        let version = read_u64(&footer[INDEX_ENTRY_SIZE..]);
+        if version != OVERLAY_VERSION {
+            return Err(invalid(format!("unsupported version {}", version)));
+        }
+        let expected_len = num_pages
+            .checked_mul(PAGE_SIZE + INDEX_ENTRY_SIZE)
+            .and_then(|n| n.checked_add(FOOTER_SIZE));
+        if expected_len != Some(len) {
+            return Err(invalid(format!(
+                "file of size {} cannot contain {} pages",
+                len, num_pages
+            )));
+        }
+
+        let index_start = num_pages * PAGE_SIZE;
+        let index: Vec<PageIndex> = bytes[index_start..len - FOOTER_SIZE]
+            .chunks_exact(INDEX_ENTRY_SIZE)
+            .map(|entry| PageIndex::new(read_u64(entry)))
+            .collect();
+        if index.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(invalid(
+                "page indices are not strictly increasing".to_string(),
+            ));
+        }
+
+        let fd = file.as_raw_fd();
+        Ok(Self {
+            mmap,
+            _file: file,
+            file_descriptor: FileDescriptor { fd },
+            index,
+        })
+    }
+
+    fn get_page_in_slot(&self, slot: usize) -> &PageBytes {
+        debug_assert!(slot < self.index.len());
+        // SAFETY: The slot is within the data section of the file, which is mapped
+        // and will remain valid for the lifetime of `self`. The memory is read-only
+        // and does not have any mutable references to it.
+        unsafe { page_bytes_from_ptr(self, self.mmap.addr().add(slot * PAGE_SIZE)) }
+    }
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().expect("slice must be 8 bytes long"))
+}
+
+impl OverlayFile {
+    /// Opens an existing overlay file located at the specified path.
+    pub fn load(path: &Path) -> Result<Self, PersistenceError> {
+        let file = OpenOptions::new().read(true).open(path).map_err(|err| {
+            PersistenceError::FileSystemError {
+                path: path.display().to_string(),
+                context: "Failed to open overlay file".to_string(),
+                internal_error: err.to_string(),
+            }
+        })?;
+        let len = file
+            .metadata()
+            .map_err(|err| PersistenceError::FileSystemError {
+                path: path.display().to_string(),
+                context: "Failed to retrieve file metadata".to_string(),
+                internal_error: err.to_string(),
+            })?
+            .len() as usize;
+        let mapping = OverlayMapping::new(file, len, &path.display().to_string())?;
+        Ok(Self {
+            mapping: Arc::new(mapping),
+        })
+    }
+
+    /// Writes the pages of `delta` as a new overlay file at `path`.
+    ///
+    /// Fails if a file already exists at `path`, as overlays are immutable.
+    pub(super) fn write(delta: &PageDelta, path: &Path) -> Result<(), PersistenceError> {
+        let pages: Vec<(PageIndex, &PageBytes)> = delta
+            .iter()
+            .map(|(index, page)| (index, page.contents()))
+            .collect();
+        write_overlay(&pages, path)
+    }
+
+    /// Returns a serialization-friendly representation of `OverlayFile`.
+    pub fn serialize(&self) -> OverlayFileSerialization {
+        OverlayFileSerialization {
+            mapping: MappingSerialization {
+                file_descriptor: self.mapping.file_descriptor.clone(),
+                file_len: self.mapping.mmap.len() as FileOffset,
+            },
+        }
+    }
+
+    /// Creates `OverlayFile` from the given serialization-friendly
+    /// representation.
+    pub fn deserialize(
+        serialized_overlay: OverlayFileSerialization,
+    ) -> Result<Self, PersistenceError> {
+        let fd = serialized_overlay.mapping.file_descriptor.fd;
+        // SAFETY: the file descriptor is valid because `serialized_overlay` is
+        // guaranteed to be valid as a precondition.
+        let file = unsafe { File::from_raw_fd(fd) };
+        let mapping = OverlayMapping::new(
+            file,
+            serialized_overlay.mapping.file_len as usize,
+            &format!("/proc/self/fd/{}", fd),
+        )?;
+        Ok(Self {
+            mapping: Arc::new(mapping),
+        })
+    }
+
+    /// Returns the page with the given index if it is contained in this
+    /// overlay.
+    pub fn get_page(&self, page_index: PageIndex) -> Option<&PageBytes> {
+        self.mapping
+            .index
+            .binary_search(&page_index)
+            .ok()
+            .map(|slot| self.mapping.get_page_in_slot(slot))
+    }
+
+    /// Enumerates all the pages stored in this overlay in increasing order of
+    /// their indices.
+    pub fn iter(&self) -> impl Iterator<Item = (PageIndex, &PageBytes)> + '_ {
+        self.mapping
+            .index
+            .iter()
+            .enumerate()
+            .map(|(slot, index)| (*index, self.mapping.get_page_in_slot(slot)))
+    }
+
+    /// Returns the number of pages stored in this overlay.
+    pub fn num_pages(&self) -> usize {
+        self.mapping.index.len()
+    }
+
+    /// Returns one more than the largest page index stored in this overlay.
+    pub fn num_logical_pages(&self) -> usize {
+        self.mapping
+            .index
+            .last()
+            .map_or(0, |index| index.get() as usize + 1)
+    }
+
+    /// Returns instructions that map every run of consecutive pages of this
+    /// overlay from the backing file.
+    fn get_memory_instructions(&self) -> Vec<MemoryInstruction> {
+        let index = &self.mapping.index;
+        let mut instructions = Vec::new();
+        let mut slot = 0;
+        while slot < index.len() {
+            let run_start = slot;
+            while slot + 1 < index.len() && index[slot + 1].get() == index[slot].get() + 1 {
+                slot += 1;
+            }
+            slot += 1;
+            instructions.push((
+                index[run_start]..PageIndex::new(index[slot - 1].get() + 1),
+                MemoryMapOrData::MemoryMap(
+                    self.mapping.file_descriptor.clone(),
+                    run_start * PAGE_SIZE,
+                ),
+            ));
+        }
+        instructions
+    }
+}
+
+/// Writes the given pages, which must be sorted by their indices, as a new
+/// overlay file.
+fn write_overlay(pages: &[(PageIndex, &PageBytes)], path: &Path) -> Result<(), PersistenceError> {
+    debug_assert!(pages.windows(2).all(|w| w[0].0 < w[1].0));
+    let fs_error = |context: &str, err: std::io::Error| PersistenceError::FileSystemError {
+        path: path.display().to_string(),
+        context: context.to_string(),
+        internal_error: err.to_string(),
+    };
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|err| fs_error("Failed to create overlay file", err))?;
+
+    for batch in pages.chunks(WRITE_BATCH_PAGES) {
+        let contents: Vec<&[u8]> = batch.iter().map(|(_, page)| &page[..]).collect();
+        write_all_vectored(&mut file, &contents)
+            .map_err(|err| fs_error("Failed to write overlay pages", err))?;
+    }
+
+    let mut tail = Vec::with_capacity(pages.len() * INDEX_ENTRY_SIZE + FOOTER_SIZE);
+    for (index, _) in pages {
+        tail.extend_from_slice(&index.get().to_le_bytes());
+    }
+    tail.extend_from_slice(&(pages.len() as u64).to_le_bytes());
+    tail.extend_from_slice(&OVERLAY_VERSION.to_le_bytes());
+    file.write_all(&tail)
+        .map_err(|err| fs_error("Failed to write overlay index", err))
+}
+
+/// The files backing a `PageMap`: an optional dense base file and a stack
+/// of overlays ordered from the oldest to the newest.
+#[derive(Clone, Default)]
+pub(crate) struct Storage {
+    base: Checkpoint,
+    overlays: Vec<OverlayFile>,
+}
+
+impl Storage {
+    /// Creates a storage consisting only of the given base file.
+    pub fn from_checkpoint(base: Checkpoint) -> Self {
+        Self {
+            base,
+            overlays: vec![],
+        }
+    }
+
+    /// Opens all the files described by `layout`.
+    pub fn load(layout: &dyn StorageLayout) -> Result<Self, PersistenceError> {
+        let base_path = layout.base();
+        let base = if base_path.exists() {
+            Checkpoint::open(&base_path)?
+        } else {
+            Checkpoint::empty()
+        };
+        let overlays = layout
+            .existing_overlays()?
+            .iter()
+            .map(|path| OverlayFile::load(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { base, overlays })
+    }
+
+    /// Returns the page with the specified `page_index`.
+    pub fn get_page(&self, page_index: PageIndex) -> &PageBytes {
+        self.overlays
+            .iter()
+            .rev()
+            .find_map(|overlay| overlay.get_page(page_index))
+            .unwrap_or_else(|| self.base.get_page(page_index))
+    }
+
+    /// Returns the max number of (possibly) non-zero pages in this storage.
+    pub fn num_logical_pages(&self) -> usize {
+        self.overlays
+            .iter()
+            .map(|overlay| overlay.num_logical_pages())
+            .fold(self.base.num_pages(), usize::max)
+    }
+
+    /// Returns the number of overlays on top of the base file.
+    pub fn num_overlays(&self) -> usize {
+        self.overlays.len()
+    }
+
+    /// Returns instructions that map the base file followed by every overlay.
+    /// The instructions have to be applied in order, as the later ones shadow
+    /// the earlier ones.
+    pub fn get_base_memory_instructions(&self) -> MemoryInstructions {
+        let mut base_instructions = self.base.get_memory_instructions();
+        for overlay in &self.overlays {
+            base_instructions
+                .instructions
+                .extend(overlay.get_memory_instructions());
+        }
+        base_instructions
+    }
+
+    /// Returns a serialization-friendly representation of `Storage`.
+    pub fn serialize(&self) -> StorageSerialization {
+        StorageSerialization {
+            base: self.base.serialize(),
+            overlays: self.overlays.iter().map(|o| o.serialize()).collect(),
+        }
+    }
+
+    /// Creates `Storage` from the given serialization-friendly representation.
+    pub fn deserialize(serialized_storage: StorageSerialization) -> Result<Self, PersistenceError> {
+        let base = Checkpoint::deserialize(serialized_storage.base)?;
+        let overlays = serialized_storage
+            .overlays
+            .into_iter()
+            .map(OverlayFile::deserialize)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { base, overlays })
+    }
+}
+
+/// Where the result of a merge is written to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MergeDestination {
+    /// The merged pages are written as a new overlay at the given path.
+    Overlay(PathBuf),
+    /// The merged pages are written as a dense base file at the given path.
+    BaseFile(PathBuf),
+}
+
+/// A set of files of a single `PageMap` that should be merged into one.
+///
+/// The inputs always form a suffix of the stack of files, i.e. the newest
+/// overlays, possibly together with all the older overlays and the base
+/// file. This guarantees that merging does not change the contents of the
+/// `PageMap`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeCandidate {
+    /// The base file, if it is part of the merge.
+    pub base: Option<PathBuf>,
+    /// The overlays to merge, ordered from the oldest to the newest.
+    pub overlays: Vec<PathBuf>,
+    /// The file the merged pages are written to.
+    pub dst: MergeDestination,
+}
+
+impl MergeCandidate {
+    /// Decides which files of the `PageMap` described by `layout` should be
+    /// merged, if any.
+    ///
+    /// The overlays are kept in a pyramid shape: an overlay is merged with the
+    /// ones above it if it is smaller than twice their total size. On top of
+    /// that, more overlays are merged until at most `MAX_NUMBER_OF_OVERLAYS`
+    /// remain. The base file is merged as well once the overlays above it
+    /// have grown to its size. The result always replaces the newest overlay;
+    /// a merged base file is removed, its pages being part of the result.
+    pub fn new(layout: &dyn StorageLayout) -> Result<Option<Self>, PersistenceError> {
+        let overlays = layout.existing_overlays()?;
+        if overlays.len() < 2 {
+            return Ok(None);
+        }
+        let sizes = overlays
+            .iter()
+            .map(|path| file_size(path))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut num_merged = 1;
+        let mut merged_size = sizes[sizes.len() - 1];
+        while num_merged < sizes.len() {
+            let next = sizes[sizes.len() - 1 - num_merged];
+            // Number of files that would remain if we stopped merging here.
+            let num_remaining = sizes.len() - num_merged + 1;
+            if next < 2 * merged_size || num_remaining > MAX_NUMBER_OF_OVERLAYS {
+                merged_size += next;
+                num_merged += 1;
+            } else {
+                break;
+            }
+        }
+        if num_merged < 2 {
+            return Ok(None);
+        }
+
+        let top = overlays.last().unwrap().clone();
+        let merged_overlays = overlays[overlays.len() - num_merged..].to_vec();
+        let base_path = layout.base();
+        let base = if num_merged == overlays.len()
+            && base_path.exists()
+            && merged_size >= file_size(&base_path)?
+        {
+            Some(base_path)
+        } else {
+            None
+        };
+
+        Ok(Some(Self {
+            base,
+            overlays: merged_overlays,
+            dst: MergeDestination::Overlay(top),
+        }))
+    }
+
+    /// Returns a candidate that merges all files of the `PageMap` into a dense
+    /// base file. This is used to go back to the single-file layout.
+    pub fn full_to_base(layout: &dyn StorageLayout) -> Result<Option<Self>, PersistenceError> {
+        let overlays = layout.existing_overlays()?;
+        if overlays.is_empty() {
+            return Ok(None);
+        }
+        let base_path = layout.base();
+        Ok(Some(Self {
+            base: Some(base_path.clone()).filter(|path| path.exists()),
+            overlays,
+            dst: MergeDestination::BaseFile(base_path),
+        }))
+    }
+
+    /// Performs the merge: writes the merged file and removes the inputs.
+    ///
+    /// The merged file is first written to a temporary path next to the
+    /// destination and then renamed, so that a crash never leaves a partially
+    /// written file at the destination. The inputs are only removed once the
+    /// rename is durable.
+    pub fn apply(&self) -> Result<(), PersistenceError> {
+        let base = match &self.base {
+            Some(path) => Checkpoint::open(path)?,
+            None => Checkpoint::empty(),
+        };
+        let overlays = self
+            .overlays
+            .iter()
+            .map(|path| OverlayFile::load(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let storage = Storage { base, overlays };
+
+        let dst = match &self.dst {
+            MergeDestination::Overlay(path) | MergeDestination::BaseFile(path) => path,
+        };
+        let tmp = dst.with_extension("merge_tmp");
+        if tmp.exists() {
+            remove_file(&tmp)?;
+        }
+
+        match &self.dst {
+            MergeDestination::Overlay(_) => {
+                let mut indices: Vec<PageIndex> = storage
+                    .overlays
+                    .iter()
+                    .flat_map(|overlay| overlay.iter().map(|(index, _)| index))
+                    .chain((0..storage.base.num_pages() as u64).map(PageIndex::new))
+                    .collect();
+                indices.sort_unstable();
+                indices.dedup();
+                let pages: Vec<(PageIndex, &PageBytes)> = indices
+                    .into_iter()
+                    .map(|index| (index, storage.get_page(index)))
+                    .collect();
+                write_overlay(&pages, &tmp)?;
+            }
+            MergeDestination::BaseFile(_) => write_base_file(&storage, &tmp)?,
+        }
+
+        // Release all mappings before touching the inputs.
+        drop(storage);
+
+        // The merged file must be durable at `dst` before any input is
+        // removed. Until then, the inputs describe the same pages, so a crash
+        // at any point leaves a consistent stack of files behind.
+        sync_path(&tmp)?;
+        std::fs::rename(&tmp, dst).map_err(|err| PersistenceError::FileSystemError {
+            path: dst.display().to_string(),
+            context: format!("Failed to rename {}", tmp.display()),
+            internal_error: err.to_string(),
+        })?;
+        if let Some(dir) = dst.parent() {
+            sync_path(dir)?;
+        }
+
+        for path in self.base.iter().chain(self.overlays.iter()) {
+            if path != dst {
+                remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes all pages of `storage` as a dense file.
+fn write_base_file(storage: &Storage, path: &Path) -> Result<(), PersistenceError> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|err| PersistenceError::FileSystemError {
+            path: path.display().to_string(),
+            context: "Failed to create base file".to_string(),
+            internal_error: err.to_string(),
+        })?;
+    let num_pages = storage.num_logical_pages() as u64;
+    let indices: Vec<PageIndex> = (0..num_pages).map(PageIndex::new).collect();
+    for batch in indices.chunks(WRITE_BATCH_PAGES) {
+        let contents: Vec<&[u8]> = batch
+            .iter()
+            .map(|index| &storage.get_page(*index)[..])
+            .collect();
+        write_all_vectored(&mut file, &contents).map_err(|err| {
+            PersistenceError::FileSystemError {
+                path: path.display().to_string(),
+                context: "Failed to write base file".to_string(),
+                internal_error: err.to_string(),
+            }
+        })?;
+    }
+    Ok(())
+}
+
+fn file_size(path: &Path) -> Result<u64, PersistenceError> {
+    std::fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .map_err(|err| PersistenceError::FileSystemError {
+            path: path.display().to_string(),
+            context: "Failed to retrieve file metadata".to_string(),
+            internal_error: err.to_string(),
+        })
+}
+
+fn sync_path(path: &Path) -> Result<(), PersistenceError> {
+    ic_utils::fs::sync_path(path).map_err(|err| PersistenceError::FileSystemError {
+        path: path.display().to_string(),
+        context: "Failed to sync path".to_string(),
+        internal_error: err.to_string(),
+    })
+}
+
+fn remove_file(path: &Path) -> Result<(), PersistenceError> {
+    std::fs::remove_file(path).map_err(|err| PersistenceError::FileSystemError {
+        path: path.display().to_string(),
+        context: "Failed to remove file".to_string(),
+        internal_error: err.to_string(),
+    })
+}
+
+/// Serialization-friendly representation of `OverlayFile`.
+///
+/// It contains sufficient information to reconstruct `OverlayFile`
+/// in another process.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OverlayFileSerialization {
+    pub mapping: MappingSerialization,
+}
+
+/// Serialization-friendly representation of `Storage`.
+///
+/// It contains sufficient information to reconstruct `Storage`
+/// in another process.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageSerialization {
+    pub base: CheckpointSerialization,
+    pub overlays: Vec<OverlayFileSerialization>,
+}
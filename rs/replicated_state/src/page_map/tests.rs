@@ -1,15 +1,20 @@
 use super::{
     checkpoint::{Checkpoint, MappingSerialization},
     page_allocator::PageAllocatorSerialization,
-    Buffer, FileDescriptor, PageAllocatorRegistry, PageIndex, PageMap, PageMapSerialization,
+    Buffer, FileDescriptor, MergeCandidate, MergeDestination, OverlayFileSerialization,
+    PageAllocatorRegistry, PageIndex, PageMap, PageMapSerialization, PersistenceError,
+    StorageLayout, MAX_NUMBER_OF_OVERLAYS,
 };
 use crate::page_map::{
-    MemoryInstructions, MemoryMapOrData, TestPageAllocatorFileDescriptorImpl, WRITE_BUCKET_PAGES,
+    MemoryInstructions, MemoryMapOrData, TestPageAllocatorFileDescriptorImpl, DIRTY_CHUNK_SIZE,
+    WRITE_BUCKET_PAGES,
 };
 use ic_sys::PAGE_SIZE;
 use ic_types::{Height, MAX_STABLE_MEMORY_IN_BYTES};
 use nix::unistd::dup;
 use static_assertions::const_assert_ne;
+use std::ffi::OsStr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::{fs::OpenOptions, path::Path};
 
@@ -28,16 +33,28 @@ fn assert_equal_page_maps(page_map1: &PageMap, page_map2: &PageMap) {
 fn duplicate_file_descriptors(
     mut serialized_page_map: PageMapSerialization,
 ) -> PageMapSerialization {
-    serialized_page_map.checkpoint.mapping =
-        serialized_page_map
-            .checkpoint
-            .mapping
-            .map(|mapping| MappingSerialization {
-                file_descriptor: FileDescriptor {
-                    fd: dup(mapping.file_descriptor.fd).unwrap(),
-                },
-                ..mapping
-            });
+    fn duplicate_mapping(mapping: MappingSerialization) -> MappingSerialization {
+        MappingSerialization {
+            file_descriptor: FileDescriptor {
+                fd: dup(mapping.file_descriptor.fd).unwrap(),
+            },
+            ..mapping
+        }
+    }
+
+    serialized_page_map.storage.base.mapping = serialized_page_map
+        .storage
+        .base
+        .mapping
+        .map(duplicate_mapping);
+    serialized_page_map.storage.overlays = serialized_page_map
+        .storage
+        .overlays
+        .into_iter()
+        .map(|overlay| OverlayFileSerialization {
+            mapping: duplicate_mapping(overlay.mapping),
+        })
+        .collect();
     serialized_page_map.page_allocator = PageAllocatorSerialization {
         id: serialized_page_map.page_allocator.id,
         fd: FileDescriptor {
@@ -47,6 +64,60 @@ fn duplicate_file_descriptors(
     serialized_page_map
 }
 
+/// A `StorageLayout` that keeps all files of a single page map in a
+/// directory.
+struct TestStorageLayout {
+    root: PathBuf,
+}
+
+impl StorageLayout for TestStorageLayout {
+    fn base(&self) -> PathBuf {
+        self.root.join("base.bin")
+    }
+
+    fn overlay(&self, height: Height) -> PathBuf {
+        self.root.join(format!("{:016x}.overlay", height.get()))
+    }
+
+    fn existing_overlays(&self) -> Result<Vec<PathBuf>, PersistenceError> {
+        let mut overlays: Vec<PathBuf> = std::fs::read_dir(&self.root)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension() == Some(OsStr::new("overlay")))
+            .collect();
+        overlays.sort();
+        Ok(overlays)
+    }
+
+    fn overlay_height(&self, overlay: &Path) -> Result<Height, PersistenceError> {
+        let stem = overlay.file_stem().unwrap().to_str().unwrap();
+        Ok(Height::new(u64::from_str_radix(stem, 16).unwrap()))
+    }
+}
+
+/// Applies `pages` to `page_map` and flushes them as an overlay at `height`.
+fn write_overlay(
+    page_map: &mut PageMap,
+    layout: &TestStorageLayout,
+    height: u64,
+    pages: &[(PageIndex, [u8; PAGE_SIZE])],
+) {
+    page_map.update(&pages.iter().map(|(idx, p)| (*idx, p)).collect::<Vec<_>>());
+    page_map
+        .persist_unflushed_delta_as_overlay(layout, Height::new(height))
+        .unwrap();
+    page_map.strip_unflushed_delta();
+}
+
+fn open_with_layout(layout: &TestStorageLayout) -> PageMap {
+    PageMap::open_with_layout(
+        layout,
+        Height::new(0),
+        Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+    )
+    .unwrap()
+}
+
 #[test]
 fn can_debug_display_a_page_map() {
     let page_map = PageMap::new_for_testing();
@@ -297,6 +368,40 @@ fn serialize_page_map() {
     assert_equal_page_maps(&replica, &sandbox);
 }
 
+#[test]
+fn dirty_chunks_track_page_delta() {
+    let pages_per_chunk = DIRTY_CHUNK_SIZE / PAGE_SIZE as u64;
+    let page = [1u8; PAGE_SIZE];
+    let mut page_map = PageMap::new_for_testing();
+    assert!(page_map.get_dirty_chunk_indices().is_empty());
+
+    page_map.update(&[
+        (PageIndex::new(0), &page),
+        (PageIndex::new(1), &page),
+        (PageIndex::new(3 * pages_per_chunk + 5), &page),
+    ]);
+    assert_eq!(page_map.get_dirty_chunk_indices(), vec![0, 3]);
+
+    // Stripping the unflushed delta does not affect the dirty chunks, which
+    // cover all modifications since the last checkpoint.
+    page_map.strip_unflushed_delta();
+    page_map.update(&[(PageIndex::new(pages_per_chunk), &page)]);
+    assert_eq!(page_map.get_dirty_chunk_indices(), vec![0, 1, 3]);
+
+    // The dirty chunks are recomputed on deserialization.
+    let page_allocator_registry = PageAllocatorRegistry::new();
+    let serialized_page_map = duplicate_file_descriptors(page_map.serialize());
+    let deserialized_page_map =
+        PageMap::deserialize(serialized_page_map, &page_allocator_registry).unwrap();
+    assert_eq!(
+        deserialized_page_map.get_dirty_chunk_indices(),
+        vec![0, 1, 3]
+    );
+
+    page_map.strip_all_deltas(Arc::new(TestPageAllocatorFileDescriptorImpl::new()));
+    assert!(page_map.get_dirty_chunk_indices().is_empty());
+}
+
 /// Check that the value provided by `calculate_dirty_pages` agrees with the
 /// actual change in number of dirty pages and return the number of new dirty
 /// pages.
@@ -579,3 +684,221 @@ fn get_memory_instructions_grows_left_and_right() {
             .range
     );
 }
+
+#[test]
+fn overlays_shadow_older_overlays_and_base() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+
+    let mut page_map = PageMap::new_for_testing();
+    page_map.update(&[
+        (PageIndex::new(0), &[1u8; PAGE_SIZE]),
+        (PageIndex::new(1), &[1u8; PAGE_SIZE]),
+    ]);
+    page_map.persist_delta(&layout.base()).unwrap();
+    page_map.strip_unflushed_delta();
+
+    write_overlay(
+        &mut page_map,
+        &layout,
+        1,
+        &[
+            (PageIndex::new(1), [2u8; PAGE_SIZE]),
+            (PageIndex::new(5), [2u8; PAGE_SIZE]),
+        ],
+    );
+    write_overlay(
+        &mut page_map,
+        &layout,
+        2,
+        &[(PageIndex::new(5), [3u8; PAGE_SIZE])],
+    );
+
+    let loaded = open_with_layout(&layout);
+    assert_eq!(loaded.num_overlays(), 2);
+    assert_eq!(loaded.num_host_pages(), 6);
+    assert_eq!(loaded.get_page(PageIndex::new(0)), &[1u8; PAGE_SIZE]);
+    assert_eq!(loaded.get_page(PageIndex::new(1)), &[2u8; PAGE_SIZE]);
+    assert_eq!(loaded.get_page(PageIndex::new(3)), &[0u8; PAGE_SIZE]);
+    assert_eq!(loaded.get_page(PageIndex::new(5)), &[3u8; PAGE_SIZE]);
+    assert_eq!(page_map, loaded);
+}
+
+#[test]
+fn base_memory_instructions_map_overlays_after_base() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+
+    let mut page_map = PageMap::new_for_testing();
+    write_overlay(
+        &mut page_map,
+        &layout,
+        1,
+        &[
+            (PageIndex::new(2), [2u8; PAGE_SIZE]),
+            (PageIndex::new(3), [3u8; PAGE_SIZE]),
+            (PageIndex::new(7), [7u8; PAGE_SIZE]),
+        ],
+    );
+
+    let loaded = open_with_layout(&layout);
+    let instructions = loaded.get_base_memory_instructions().instructions;
+    assert!(matches!(
+        instructions[..],
+        [
+            (ref first, MemoryMapOrData::MemoryMap(_, 0)),
+            (ref second, MemoryMapOrData::MemoryMap(_, offset)),
+        ] if *first == (PageIndex::new(2)..PageIndex::new(4))
+            && *second == (PageIndex::new(7)..PageIndex::new(8))
+            && offset == 2 * PAGE_SIZE
+    ));
+}
+
+#[test]
+fn merging_overlays_preserves_contents_and_bounds_overlays() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+
+    let mut page_map = PageMap::new_for_testing();
+    for height in 1..=3 * MAX_NUMBER_OF_OVERLAYS as u64 {
+        write_overlay(
+            &mut page_map,
+            &layout,
+            height,
+            &[
+                (PageIndex::new(height % 5), [height as u8; PAGE_SIZE]),
+                (PageIndex::new(10 + height), [height as u8; PAGE_SIZE]),
+            ],
+        );
+        if let Some(merge) = MergeCandidate::new(&layout).unwrap() {
+            merge.apply().unwrap();
+        }
+        assert!(layout.existing_overlays().unwrap().len() <= MAX_NUMBER_OF_OVERLAYS);
+        assert_eq!(page_map, open_with_layout(&layout));
+    }
+}
+
+#[test]
+fn merging_base_file_replaces_newest_overlay() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+
+    let mut page_map = PageMap::new_for_testing();
+    page_map.update(&[(PageIndex::new(0), &[1u8; PAGE_SIZE])]);
+    page_map.persist_delta(&layout.base()).unwrap();
+    page_map.strip_unflushed_delta();
+    for height in 1..=2 {
+        write_overlay(
+            &mut page_map,
+            &layout,
+            height,
+            &[
+                (PageIndex::new(height), [height as u8; PAGE_SIZE]),
+                (PageIndex::new(10 + height), [height as u8; PAGE_SIZE]),
+            ],
+        );
+    }
+    let top = layout.existing_overlays().unwrap().last().unwrap().clone();
+
+    let merge = MergeCandidate::new(&layout).unwrap().unwrap();
+    assert_eq!(Some(layout.base()), merge.base);
+    assert_eq!(MergeDestination::Overlay(top.clone()), merge.dst);
+    merge.apply().unwrap();
+
+    assert!(!layout.base().exists());
+    assert_eq!(vec![top], layout.existing_overlays().unwrap());
+    assert_eq!(page_map, open_with_layout(&layout));
+}
+
+#[test]
+fn full_merge_to_base_file_preserves_contents() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+
+    let mut page_map = PageMap::new_for_testing();
+    page_map.update(&[(PageIndex::new(0), &[1u8; PAGE_SIZE])]);
+    page_map.persist_delta(&layout.base()).unwrap();
+    page_map.strip_unflushed_delta();
+    write_overlay(
+        &mut page_map,
+        &layout,
+        1,
+        &[(PageIndex::new(3), [3u8; PAGE_SIZE])],
+    );
+    write_overlay(
+        &mut page_map,
+        &layout,
+        2,
+        &[(PageIndex::new(0), [4u8; PAGE_SIZE])],
+    );
+
+    MergeCandidate::full_to_base(&layout)
+        .unwrap()
+        .unwrap()
+        .apply()
+        .unwrap();
+
+    assert!(layout.existing_overlays().unwrap().is_empty());
+    assert_eq!(
+        4 * PAGE_SIZE as u64,
+        layout.base().metadata().unwrap().len()
+    );
+    let loaded = PageMap::open(
+        &layout.base(),
+        Height::new(0),
+        Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+    )
+    .unwrap();
+    assert_eq!(page_map, loaded);
+}
+
+#[test]
+fn serialize_page_map_with_overlays() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+
+    let mut page_map = PageMap::new_for_testing();
+    write_overlay(
+        &mut page_map,
+        &layout,
+        1,
+        &[(PageIndex::new(4), [4u8; PAGE_SIZE])],
+    );
+    let loaded = open_with_layout(&layout);
+
+    let page_allocator_registry = PageAllocatorRegistry::new();
+    let serialized_page_map = duplicate_file_descriptors(loaded.serialize());
+    let deserialized_page_map =
+        PageMap::deserialize(serialized_page_map, &page_allocator_registry).unwrap();
+    assert_equal_page_maps(&loaded, &deserialized_page_map);
+}
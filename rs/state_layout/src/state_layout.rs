@@ -16,6 +16,7 @@ use ic_replicated_state::{
         execution_state::{NextScheduledMethod, WasmMetadata},
         system_state::{wasm_chunk_store::WasmChunkStoreMetadata, CanisterHistory, CyclesUseCase},
     },
+    page_map::{PersistenceError, StorageLayout},
     CallContextManager, CanisterStatus, ExecutionTask, ExportedFunctions, Global, NumWasmPages,
 };
 use ic_sys::mmap::ScopedMmap;
@@ -45,6 +46,7 @@ mod tests;
 // State layout directory and file names.
 pub const CHECKPOINTS_DIR: &str = "checkpoints";
 pub const CANISTER_STATES_DIR: &str = "canister_states";
+pub const CANISTER_IMPORTS_DIR: &str = "canister_imports";
 pub const QUEUES_FILE: &str = "queues.pbuf";
 pub const CANISTER_FILE: &str = "canister.pbuf";
 pub const INGRESS_HISTORY_FILE: &str = "ingress_history.pbuf";
@@ -52,6 +54,7 @@ pub const SPLIT_MARKER_FILE: &str = "split_from.pbuf";
 pub const SUBNET_QUEUES_FILE: &str = "subnet_queues.pbuf";
 pub const SYSTEM_METADATA_FILE: &str = "system_metadata.pbuf";
 pub const STATS_FILE: &str = "stats.pbuf";
+pub const OVERLAY_EXTENSION: &str = "overlay";
 
 /// `ReadOnly` is the access policy used for reading checkpoints. We
 /// don't want to ever modify persisted states.
@@ -239,6 +242,10 @@ struct CheckpointRefData {
 /// ├── diverged_state_markers
 /// │   └──<hex(round)>
 /// │
+/// ├── state_sync_scratchpads
+/// │   ├──<hex(round)>
+/// │   └──<hex(round)>.pbuf
+/// │
 /// ├── tmp
 /// └── fs_tmp
 /// ```
@@ -273,15 +280,22 @@ struct CheckpointRefData {
 /// ## Promoting a State Sync artifact to a checkpoint
 ///
 ///   1. Create state files directly in
-///      "<state_root>/fs_tmp/state_sync_scratchpad_<height>".
+///      "<state_root>/state_sync_scratchpads/<height>".
 ///
 ///   2. When all the writes are complete, call sync_and_mark_files_readonly()
-///      on "<state_root>/fs_tmp/state_sync_scratchpad_<height>".  This function
+///      on "<state_root>/state_sync_scratchpads/<height>".  This function
 ///      syncs all the files and directories under the scratchpad directory,
 ///      including the scratchpad directory itself.
 ///
-///   3. Rename "<state_root>/fs_tmp/state_sync_scratchpad_<height>" to
+///   3. Rename "<state_root>/state_sync_scratchpads/<height>" to
 ///      "<state_root>/checkpoints/<height>", sync "<state_root>/checkpoints".
+///
+/// Unlike "tmp" and "fs_tmp", the scratchpads directory survives restarts.
+/// While chunks are being fetched, the progress of the state sync is
+/// periodically persisted to "<state_root>/state_sync_scratchpads/<height>.pbuf"
+/// so that a restarted replica can resume the sync instead of starting from
+/// scratch. On startup, only the most recent scratchpad with a progress file
+/// above the latest checkpoint is kept.
 
 #[derive(Clone)]
 pub struct StateLayout {
@@ -381,6 +395,32 @@ impl TipHandler {
         }
         Ok(())
     }
+
+    /// Copies the files of a canister (e.g. the staged state of a canister
+    /// migrated to this subnet) into tip.
+    pub fn import_canister(
+        &mut self,
+        state_layout: &StateLayout,
+        height: Height,
+        canister_id: &CanisterId,
+        canister_layout: &CanisterLayout<ReadOnly>,
+    ) -> Result<(), LayoutError> {
+        let canister_path = self.tip(height)?.canister(canister_id)?.raw_path();
+        copy_recursively(
+            &state_layout.log,
+            &canister_layout.raw_path(),
+            &canister_path,
+            FilePermissions::ReadWrite,
+            FSync::No,
+            |_| true,
+            None,
+        )
+        .map_err(|err| LayoutError::IoError {
+            path: canister_path,
+            message: format!("Cannot import canister {}.", canister_id),
+            io_err: err,
+        })
+    }
 }
 
 impl StateLayout {
@@ -417,13 +457,17 @@ impl StateLayout {
         WriteOnly::check_dir(&self.diverged_checkpoints())?;
         WriteOnly::check_dir(&self.diverged_state_markers())?;
         WriteOnly::check_dir(&self.fs_tmp())?;
+        WriteOnly::check_dir(&self.state_sync_scratchpads())?;
+        WriteOnly::check_dir(&self.canister_imports())?;
         WriteOnly::check_dir(&self.tip_path())?;
         WriteOnly::check_dir(&self.tmp())?;
+        self.cleanup_state_sync_scratchpads()?;
         for path in [
             &self.backups(),
             &self.checkpoints(),
             &self.diverged_checkpoints(),
             &self.diverged_state_markers(),
+            &self.state_sync_scratchpads(),
         ] {
             sync_path(path).map_err(|err| LayoutError::IoError {
                 path: path.clone(),
@@ -517,8 +561,80 @@ impl StateLayout {
     /// Returns scratchpad used during statesync
     pub fn state_sync_scratchpad(&self, height: Height) -> Result<PathBuf, LayoutError> {
         Ok(self
-            .tmp()
-            .join(format!("state_sync_scratchpad_{:016x}", height.get())))
+            .state_sync_scratchpads()
+            .join(Self::checkpoint_name(height)))
+    }
+
+    /// Returns the path to the persisted progress of the statesync whose
+    /// scratchpad is `state_sync_scratchpad(height)`.
+    pub fn state_sync_progress(&self, height: Height) -> Result<PathBuf, LayoutError> {
+        Ok(self
+            .state_sync_scratchpads()
+            .join(format!("{}.pbuf", Self::checkpoint_name(height))))
+    }
+
+    /// Returns a sorted in ascended order list of the heights of the state
+    /// sync scratchpads on disk.
+    pub fn state_sync_scratchpad_heights(&self) -> Result<Vec<Height>, LayoutError> {
+        let scratchpads = self.state_sync_scratchpads();
+        let names = dir_file_names(&scratchpads).map_err(|err| LayoutError::IoError {
+            path: scratchpads,
+            message: "Failed to list state sync scratchpads".to_string(),
+            io_err: err,
+        })?;
+        let mut heights: Vec<Height> = names
+            .iter()
+            .filter_map(|name| u64::from_str_radix(name, 16).ok().map(Height::new))
+            .collect();
+        heights.sort_unstable();
+        Ok(heights)
+    }
+
+    /// Removes all state sync scratchpads that cannot be resumed.
+    ///
+    /// A scratchpad can be resumed if it has a persisted progress file and
+    /// its height is above the latest checkpoint. Out of those, only the
+    /// scratchpad with the largest height is kept.
+    fn cleanup_state_sync_scratchpads(&self) -> Result<(), LayoutError> {
+        let scratchpads = self.state_sync_scratchpads();
+        let names = dir_file_names(&scratchpads).map_err(|err| LayoutError::IoError {
+            path: scratchpads.clone(),
+            message: "Failed to list state sync scratchpads".to_string(),
+            io_err: err,
+        })?;
+        let latest_checkpoint = self.checkpoint_heights()?.last().copied();
+
+        let resumable = names
+            .iter()
+            .filter_map(|name| {
+                let height = Height::new(u64::from_str_radix(name, 16).ok()?);
+                let has_progress = names.iter().any(|other| *other == format!("{}.pbuf", name));
+                (has_progress && Some(height) > latest_checkpoint).then_some(height)
+            })
+            .max();
+        let keep: Vec<String> = resumable
+            .map(|height| {
+                vec![
+                    Self::checkpoint_name(height),
+                    format!("{}.pbuf", Self::checkpoint_name(height)),
+                ]
+            })
+            .unwrap_or_default();
+
+        for name in names.iter().filter(|name| !keep.contains(name)) {
+            let path = scratchpads.join(name);
+            let result = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            result.map_err(|err| LayoutError::IoError {
+                path,
+                message: "Unable to remove stale state sync scratchpad".to_string(),
+                io_err: err,
+            })?;
+        }
+        Ok(())
     }
 
     /// Returns the path to cache an unfinished statesync at `height`
@@ -1018,6 +1134,45 @@ impl StateLayout {
         self.root.join("backups")
     }
 
+    fn state_sync_scratchpads(&self) -> PathBuf {
+        self.root.join("state_sync_scratchpads")
+    }
+
+    /// Returns the directory where the states of canisters migrated to this
+    /// subnet are staged (one canister directory per canister, as found in a
+    /// checkpoint of the source subnet) until they are imported.
+    pub fn canister_imports(&self) -> PathBuf {
+        self.root.join(CANISTER_IMPORTS_DIR)
+    }
+
+    /// Returns the IDs of the canisters whose states are staged for import.
+    pub fn canister_import_ids(&self) -> Result<Vec<CanisterId>, LayoutError> {
+        collect_subdirs(self.canister_imports().as_path(), parse_canister_id)
+    }
+
+    /// Returns the layout of the staged state of `canister_id`.
+    pub fn canister_import(
+        &self,
+        canister_id: &CanisterId,
+    ) -> Result<CanisterLayout<ReadOnly>, LayoutError> {
+        CanisterLayout::new(
+            self.canister_imports()
+                .join(hex::encode(canister_id.get_ref().as_slice())),
+        )
+    }
+
+    /// Removes the staged state of `canister_id`, once it has been imported.
+    pub fn remove_canister_import(&self, canister_id: &CanisterId) -> Result<(), LayoutError> {
+        let canister_root = self
+            .canister_imports()
+            .join(hex::encode(canister_id.get_ref().as_slice()));
+        std::fs::remove_dir_all(&canister_root).map_err(|err| LayoutError::IoError {
+            path: canister_root,
+            message: format!("Cannot remove staged state of canister {}.", canister_id),
+            io_err: err,
+        })
+    }
+
     fn ensure_dir_exists(&self, p: &Path) -> std::io::Result<()> {
         std::fs::create_dir_all(p)
     }
@@ -1371,6 +1526,136 @@ impl<Permissions: AccessPolicy> CanisterLayout<Permissions> {
     pub fn wasm_chunk_store(&self) -> PathBuf {
         self.canister_root.join("wasm_chunk_store.bin")
     }
+
+    /// Base file and overlays of the Wasm memory.
+    pub fn vmemory_0_layout(&self) -> PageMapLayout<Permissions> {
+        PageMapLayout::new(self.canister_root.clone(), "vmemory_0")
+    }
+
+    /// Base file and overlays of the stable memory.
+    pub fn stable_memory_layout(&self) -> PageMapLayout<Permissions> {
+        PageMapLayout::new(self.canister_root.clone(), "stable_memory")
+    }
+
+    /// Base file and overlays of the Wasm chunk store.
+    pub fn wasm_chunk_store_layout(&self) -> PageMapLayout<Permissions> {
+        PageMapLayout::new(self.canister_root.clone(), "wasm_chunk_store")
+    }
+}
+
+/// The files backing a single `PageMap`: a base file named `<name>.bin` and
+/// overlay files named `<name>_<height>.overlay`, where the height is written
+/// as 16 hexadecimal digits so that lexicographic and numeric order agree.
+pub struct PageMapLayout<Permissions: AccessPolicy> {
+    root: PathBuf,
+    name: &'static str,
+    permissions_tag: PhantomData<Permissions>,
+}
+
+impl<Permissions: AccessPolicy> PageMapLayout<Permissions> {
+    fn new(root: PathBuf, name: &'static str) -> Self {
+        Self {
+            root,
+            name,
+            permissions_tag: PhantomData,
+        }
+    }
+
+    /// Returns all the files of this `PageMap` that exist on disk: the base
+    /// file first, followed by the overlays from the oldest to the newest.
+    pub fn existing_files(&self) -> Result<Vec<PathBuf>, LayoutError> {
+        let base = self.base();
+        let overlays = self
+            .existing_overlays()
+            .map_err(|err| LayoutError::CorruptedLayout {
+                path: self.root.clone(),
+                message: err.to_string(),
+            })?;
+        Ok(Some(base)
+            .filter(|base| base.exists())
+            .into_iter()
+            .chain(overlays)
+            .collect())
+    }
+
+    fn overlay_name_prefix(&self) -> String {
+        format!("{}_", self.name)
+    }
+}
+
+impl<Permissions> PageMapLayout<Permissions>
+where
+    Permissions: WritePolicy,
+{
+    /// Removes the base file and all the overlays of this `PageMap`.
+    pub fn delete_files(&self) -> Result<(), LayoutError> {
+        for path in self.existing_files()? {
+            remove_existing_file(&path)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Permissions: AccessPolicy> StorageLayout for PageMapLayout<Permissions> {
+    fn base(&self) -> PathBuf {
+        self.root.join(format!("{}.bin", self.name))
+    }
+
+    fn overlay(&self, height: Height) -> PathBuf {
+        self.root.join(format!(
+            "{}{:016x}.{}",
+            self.overlay_name_prefix(),
+            height.get(),
+            OVERLAY_EXTENSION
+        ))
+    }
+
+    fn existing_overlays(&self) -> Result<Vec<PathBuf>, PersistenceError> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => {
+                return Err(PersistenceError::FileSystemError {
+                    path: self.root.display().to_string(),
+                    context: "Failed to list overlay files".to_string(),
+                    internal_error: err.to_string(),
+                })
+            }
+        };
+        let mut overlays = Vec::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|err| PersistenceError::FileSystemError {
+                    path: self.root.display().to_string(),
+                    context: "Failed to list overlay files".to_string(),
+                    internal_error: err.to_string(),
+                })?
+                .path();
+            if path.extension() == Some(OsStr::new(OVERLAY_EXTENSION))
+                && self.overlay_height(&path).is_ok()
+            {
+                overlays.push(path);
+            }
+        }
+        overlays.sort();
+        Ok(overlays)
+    }
+
+    fn overlay_height(&self, overlay: &Path) -> Result<Height, PersistenceError> {
+        let invalid = || PersistenceError::InvalidOverlay {
+            path: overlay.display().to_string(),
+            message: format!("file name does not match the {} overlays", self.name),
+        };
+        let height = overlay
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.strip_prefix(&self.overlay_name_prefix()))
+            .filter(|height| height.len() == 16)
+            .ok_or_else(invalid)?;
+        u64::from_str_radix(height, 16)
+            .map(Height::new)
+            .map_err(|_| invalid())
+    }
 }
 
 fn open_for_write(path: &Path) -> Result<std::fs::File, LayoutError> {
@@ -349,3 +349,82 @@ fn test_canister_id_from_path() {
         canister_id_from_path(Path::new("canister_states/not-a-canister-ID/queues.pbuf"))
     );
 }
+
+#[test]
+fn test_page_map_layout_lists_overlays_in_height_order() {
+    let tempdir = tmpdir("state_layout");
+    let canister_layout = CanisterLayout::<WriteOnly>::new(tempdir.path().to_path_buf()).unwrap();
+    let vmemory = canister_layout.vmemory_0_layout();
+    let stable_memory = canister_layout.stable_memory_layout();
+
+    for height in [17, 2, 256] {
+        std::fs::write(vmemory.overlay(Height::new(height)), b"").unwrap();
+    }
+    std::fs::write(stable_memory.overlay(Height::new(5)), b"").unwrap();
+    std::fs::write(vmemory.base(), b"").unwrap();
+
+    let overlays = vmemory.existing_overlays().unwrap();
+    assert_eq!(
+        overlays
+            .iter()
+            .map(|path| vmemory.overlay_height(path).unwrap())
+            .collect::<Vec<_>>(),
+        vec![Height::new(2), Height::new(17), Height::new(256)]
+    );
+    assert_eq!(vmemory.base(), canister_layout.vmemory_0());
+    assert!(vmemory
+        .overlay_height(&stable_memory.overlay(Height::new(5)))
+        .is_err());
+    assert_eq!(vmemory.existing_files().unwrap().len(), 4);
+
+    vmemory.delete_files().unwrap();
+    assert!(vmemory.existing_files().unwrap().is_empty());
+    assert_eq!(stable_memory.existing_files().unwrap().len(), 1);
+}
+
+#[test]
+fn test_only_latest_resumable_state_sync_scratchpad_survives_restart() {
+    with_test_replica_logger(|log| {
+        let tempdir = tmpdir("state_layout");
+        let root_path = tempdir.path().to_path_buf();
+        let metrics_registry = ic_metrics::MetricsRegistry::new();
+        let state_layout =
+            StateLayout::try_new(log.clone(), root_path.clone(), &metrics_registry).unwrap();
+
+        // A scratchpad at a height that is already checkpointed.
+        std::fs::create_dir_all(root_path.join("checkpoints").join("0000000000000002")).unwrap();
+        // Scratchpads with progress at heights 1, 3 and 5, and one without at height 7.
+        for h in [1, 3, 5, 7] {
+            std::fs::create_dir_all(state_layout.state_sync_scratchpad(Height::new(h)).unwrap())
+                .unwrap();
+        }
+        for h in [1, 3, 5] {
+            std::fs::write(
+                state_layout.state_sync_progress(Height::new(h)).unwrap(),
+                b"",
+            )
+            .unwrap();
+        }
+        drop(state_layout);
+
+        let state_layout = StateLayout::try_new(log, root_path, &metrics_registry).unwrap();
+        for h in [1, 3, 7] {
+            assert!(!state_layout
+                .state_sync_scratchpad(Height::new(h))
+                .unwrap()
+                .exists());
+            assert!(!state_layout
+                .state_sync_progress(Height::new(h))
+                .unwrap()
+                .exists());
+        }
+        assert!(state_layout
+            .state_sync_scratchpad(Height::new(5))
+            .unwrap()
+            .exists());
+        assert!(state_layout
+            .state_sync_progress(Height::new(5))
+            .unwrap()
+            .exists());
+    });
+}
@@ -281,8 +281,8 @@ pub fn load_canister_state<P: ReadPolicy>(
         Some(execution_state_bits) => {
             let starting_time = Instant::now();
             let wasm_memory = Memory::new(
-                PageMap::open(
-                    &canister_layout.vmemory_0(),
+                PageMap::open_with_layout(
+                    &canister_layout.vmemory_0_layout(),
                     height,
                     Arc::clone(&fd_factory),
                 )?,
@@ -292,8 +292,8 @@ pub fn load_canister_state<P: ReadPolicy>(
 
             let starting_time = Instant::now();
             let stable_memory = Memory::new(
-                PageMap::open(
-                    &canister_layout.stable_memory_blob(),
+                PageMap::open_with_layout(
+                    &canister_layout.stable_memory_layout(),
                     height,
                     Arc::clone(&fd_factory),
                 )?,
@@ -351,12 +351,9 @@ pub fn load_canister_state<P: ReadPolicy>(
 
     let starting_time = Instant::now();
     // on initial rollout the checkpoint file won't exist.
-    let wasm_chunk_store_data = if canister_layout.wasm_chunk_store().exists() {
-        PageMap::open(
-            &canister_layout.wasm_chunk_store(),
-            height,
-            Arc::clone(&fd_factory),
-        )?
+    let wasm_chunk_store_layout = canister_layout.wasm_chunk_store_layout();
+    let wasm_chunk_store_data = if !wasm_chunk_store_layout.existing_files()?.is_empty() {
+        PageMap::open_with_layout(&wasm_chunk_store_layout, height, Arc::clone(&fd_factory))?
     } else {
         PageMap::new(Arc::clone(&fd_factory))
     };
@@ -1,6 +1,7 @@
 use super::*;
 use crate::{spawn_tip_thread, StateManagerMetrics, NUMBER_OF_CHECKPOINT_THREADS};
 use ic_base_types::NumSeconds;
+use ic_config::flag_status::FlagStatus;
 use ic_ic00_types::CanisterStatusType;
 use ic_metrics::MetricsRegistry;
 use ic_registry_subnet_type::SubnetType;
@@ -102,6 +103,8 @@ fn can_make_a_checkpoint() {
             layout.clone(),
             state_manager_metrics(),
             MaliciousFlags::default(),
+            FlagStatus::Disabled,
+            NUMBER_OF_CHECKPOINT_THREADS,
         );
 
         const HEIGHT: Height = Height::new(42);
@@ -166,6 +169,8 @@ fn scratchpad_dir_is_deleted_if_checkpointing_failed() {
             layout,
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
+            FlagStatus::Disabled,
+            NUMBER_OF_CHECKPOINT_THREADS,
         );
 
         const HEIGHT: Height = Height::new(42);
@@ -216,6 +221,8 @@ fn can_recover_from_a_checkpoint() {
             layout.clone(),
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
+            FlagStatus::Disabled,
+            NUMBER_OF_CHECKPOINT_THREADS,
         );
 
         const HEIGHT: Height = Height::new(42);
@@ -318,6 +325,8 @@ fn can_recover_an_empty_state() {
             layout.clone(),
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
+            FlagStatus::Disabled,
+            NUMBER_OF_CHECKPOINT_THREADS,
         );
 
         const HEIGHT: Height = Height::new(42);
@@ -402,6 +411,8 @@ fn can_recover_a_stopping_canister() {
             layout.clone(),
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
+            FlagStatus::Disabled,
+            NUMBER_OF_CHECKPOINT_THREADS,
         );
 
         const HEIGHT: Height = Height::new(42);
@@ -469,6 +480,8 @@ fn can_recover_a_stopped_canister() {
             layout.clone(),
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
+            FlagStatus::Disabled,
+            NUMBER_OF_CHECKPOINT_THREADS,
         );
 
         const HEIGHT: Height = Height::new(42);
@@ -521,6 +534,8 @@ fn can_recover_a_running_canister() {
             layout.clone(),
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
+            FlagStatus::Disabled,
+            NUMBER_OF_CHECKPOINT_THREADS,
         );
 
         const HEIGHT: Height = Height::new(42);
@@ -573,6 +588,8 @@ fn can_recover_subnet_queues() {
             layout.clone(),
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
+            FlagStatus::Disabled,
+            NUMBER_OF_CHECKPOINT_THREADS,
         );
 
         const HEIGHT: Height = Height::new(42);
@@ -623,6 +640,8 @@ fn empty_protobufs_are_loaded_correctly() {
             layout.clone(),
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
+            FlagStatus::Disabled,
+            NUMBER_OF_CHECKPOINT_THREADS,
         );
 
         const HEIGHT: Height = Height::new(42);
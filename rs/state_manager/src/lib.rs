@@ -40,10 +40,13 @@ use ic_protobuf::proxy::{ProtoProxy, ProxyDecodeError};
 use ic_protobuf::{messaging::xnet::v1, state::v1 as pb};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    canister_state::execution_state::SandboxMemory, page_map::PersistenceError, PageIndex, PageMap,
-    ReplicatedState,
+    canister_state::execution_state::SandboxMemory, page_map::PersistenceError, CanisterStatus,
+    PageMap, ReplicatedState,
+};
+use ic_state_layout::{
+    error::LayoutError, AccessPolicy, CanisterLayout, CheckpointLayout, PageMapLayout, ReadOnly,
+    StateLayout,
 };
-use ic_state_layout::{error::LayoutError, AccessPolicy, CheckpointLayout, ReadOnly, StateLayout};
 use ic_types::{
     consensus::certification::Certification,
     crypto::CryptoHash,
@@ -94,6 +97,10 @@ const CRITICAL_ERROR_CHUNK_ID_USAGE_NEARING_LIMITS: &str =
 /// How long to keep archived and diverged states.
 const ARCHIVED_DIVERGED_CHECKPOINT_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60); // 30 days
 
+/// How often to check for the staged state of a migrated canister that must be
+/// imported before execution can proceed.
+const STAGED_CANISTER_STATE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Labels for manifest metrics
 const LABEL_TYPE: &str = "type";
 const LABEL_VALUE_HASHED: &str = "hashed";
@@ -106,6 +113,7 @@ const LABEL_COPY_FILES: &str = "copy_files";
 const LABEL_COPY_CHUNKS: &str = "copy_chunks";
 const LABEL_PREALLOCATE: &str = "preallocate";
 const LABEL_STATE_SYNC_MAKE_CHECKPOINT: &str = "state_sync_make_checkpoint";
+const LABEL_PERSIST_PROGRESS: &str = "persist_progress";
 
 /// Labels for slice validation metrics
 const LABEL_VERIFY_SIG: &str = "verify";
@@ -491,7 +499,7 @@ impl StateSyncMetrics {
 
         let step_duration = metrics_registry.histogram_vec(
             "state_sync_step_duration_seconds",
-            "Duration of state sync sub-steps in seconds indexed by step ('copy_files', 'copy_chunks', 'fetch', 'state_sync_make_checkpoint')",
+            "Duration of state sync sub-steps in seconds indexed by step ('copy_files', 'copy_chunks', 'fetch', 'state_sync_make_checkpoint', 'persist_progress')",
             // 0.1s, 0.2s, 0.5s, 1s, 2s, 5s, …, 1000s, 2000s, 5000s
             decimal_buckets(-1, 3),
             &["step"],
@@ -503,6 +511,7 @@ impl StateSyncMetrics {
             LABEL_COPY_CHUNKS,
             LABEL_FETCH,
             LABEL_STATE_SYNC_MAKE_CHECKPOINT,
+            LABEL_PERSIST_PROGRESS,
         ] {
             step_duration.with_label_values(&[*step]);
         }
@@ -746,6 +755,8 @@ pub struct StateManagerImpl {
     fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
     malicious_flags: MaliciousFlags,
     latest_height_update_time: Arc<Mutex<Instant>>,
+    lsmt_storage: FlagStatus,
+    manifest_debug_check: FlagStatus,
 }
 
 fn load_checkpoint(
@@ -1018,6 +1029,22 @@ impl PageMapType {
         }
     }
 
+    /// Maps a PageMapType to the layout of its base file and overlays in a
+    /// checkpoint according to `layout`
+    fn layout<Access>(
+        &self,
+        layout: &CheckpointLayout<Access>,
+    ) -> Result<PageMapLayout<Access>, LayoutError>
+    where
+        Access: AccessPolicy,
+    {
+        match &self {
+            PageMapType::WasmMemory(id) => Ok(layout.canister(id)?.vmemory_0_layout()),
+            PageMapType::StableMemory(id) => Ok(layout.canister(id)?.stable_memory_layout()),
+            PageMapType::WasmChunkStore(id) => Ok(layout.canister(id)?.wasm_chunk_store_layout()),
+        }
+    }
+
     /// Maps a PageMapType to the the `&PageMap` in `state`
     fn get<'a>(&self, state: &'a ReplicatedState) -> Option<&'a PageMap> {
         match &self {
@@ -1061,7 +1088,8 @@ impl PageMapType {
 pub struct DirtyPageMap {
     pub height: Height,
     pub file_type: FileType,
-    pub page_delta_indices: Vec<PageIndex>,
+    /// Indices of the `DIRTY_CHUNK_SIZE` chunks modified since `height`.
+    pub dirty_chunk_indices: Vec<u64>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -1072,7 +1100,7 @@ pub enum FileType {
 
 pub type DirtyPages = Vec<DirtyPageMap>;
 
-/// Get dirty pages of all PageMaps backed by a checkpoint
+/// Get dirty chunks of all PageMaps backed by a checkpoint
 /// file.
 pub fn get_dirty_pages(
     state: &ReplicatedState,
@@ -1086,7 +1114,7 @@ pub fn get_dirty_pages(
             Some(DirtyPageMap {
                 height,
                 file_type: FileType::PageMap(entry),
-                page_delta_indices: page_map.get_page_delta_indices(),
+                dirty_chunk_indices: page_map.get_dirty_chunk_indices(),
             })
         })
         .collect();
@@ -1122,7 +1150,7 @@ pub fn get_dirty_pages(
         let dirty_pages = unchanged_ids.map(|canister_id| DirtyPageMap {
             height: previous_snapshot.height,
             file_type: FileType::WasmBinary(canister_id),
-            page_delta_indices: vec![], // empty page_delta_indices as the whole file is unchanged
+            dirty_chunk_indices: vec![], // empty dirty_chunk_indices as the whole file is unchanged
         });
 
         result.extend(dirty_pages);
@@ -1357,6 +1385,8 @@ impl StateManagerImpl {
             state_layout.clone(),
             metrics.clone(),
             malicious_flags.clone(),
+            config.lsmt_storage,
+            config.manifest_hashing_threads,
         );
 
         let starting_time = Instant::now();
@@ -1578,6 +1608,8 @@ impl StateManagerImpl {
             fd_factory,
             malicious_flags,
             latest_height_update_time: Arc::new(Mutex::new(Instant::now())),
+            lsmt_storage: config.lsmt_storage,
+            manifest_debug_check: config.manifest_debug_check,
         }
     }
     /// Returns the Page Allocator file descriptor factory. This will then be
@@ -2387,6 +2419,7 @@ impl StateManagerImpl {
                         base_height,
                         target_height: height,
                         dirty_memory_pages: dirty_pages,
+                        verify_reused_chunks: self.manifest_debug_check == FlagStatus::Enabled,
                     }
                 },
             )
@@ -2412,10 +2445,19 @@ impl StateManagerImpl {
                     states: self.states.clone(),
                     persist_metadata_guard: self.persist_metadata_guard.clone(),
                 },
-                tip_requests: vec![TipRequest::DefragTip {
-                    height,
-                    page_map_types: PageMapType::list_all(state),
-                }],
+                tip_requests: {
+                    let mut tip_requests = vec![TipRequest::DefragTip {
+                        height,
+                        page_map_types: PageMapType::list_all(state),
+                    }];
+                    if self.lsmt_storage == FlagStatus::Enabled {
+                        tip_requests.push(TipRequest::MergeOverlays {
+                            height,
+                            page_map_types: PageMapType::list_all(state),
+                        });
+                    }
+                    tip_requests
+                },
             }
         };
 
@@ -2428,6 +2470,226 @@ impl StateManagerImpl {
         result
     }
 
+    /// Takes the tip; or, if a newer checkpoint is available (e.g. after a
+    /// state sync), a new tip initialized from that checkpoint.
+    fn take_latest_tip(&self) -> (Height, ReplicatedState) {
+        let hash_at = |tip_height: Height, certifications_metadata: &CertificationsMetadata| {
+            if tip_height > Self::INITIAL_STATE_HEIGHT {
+                let tip_metadata = certifications_metadata.get(&tip_height).unwrap_or_else(|| {
+                    fatal!(self.log, "Bug: missing tip metadata @{}", tip_height)
+                });
+
+                // Since the state machine will use this tip to compute the *next* state,
+                // we populate the prev_state_hash with the hash of the current tip.
+                Some(CryptoHashOfPartialState::from(
+                    tip_metadata.certified_state_hash.clone(),
+                ))
+            } else {
+                // This code is executed at most once per subnet, no need to
+                // optimize this.
+                let hash_tree = hash_lazy_tree(&replicated_state_as_lazy_tree(
+                    initial_state(self.own_subnet_id, self.own_subnet_type).get_ref(),
+                ))
+                .unwrap_or_else(|err| fatal!(self.log, "Failed to compute hash tree: {:?}", err));
+                update_hash_tree_metrics(&hash_tree, &self.metrics);
+                Some(CryptoHashOfPartialState::from(crypto_hash_of_tree(
+                    &hash_tree,
+                )))
+            }
+        };
+
+        let mut states = self.states.write();
+        let (tip_height, mut tip) = states.tip.take().expect("failed to get TIP");
+
+        let (target_snapshot, target_hash) = match states.snapshots.back() {
+            Some(snapshot) if snapshot.height > tip_height => (
+                snapshot.clone(),
+                hash_at(snapshot.height, &states.certifications_metadata),
+            ),
+            _ => {
+                tip.metadata.prev_state_hash = hash_at(tip_height, &states.certifications_metadata);
+                return (tip_height, tip);
+            }
+        };
+
+        // The latest checkpoint is newer than tip.
+        // This can happen when we replay blocks and sync states concurrently.
+        //
+        // We release the states write lock here because loading a checkpoint
+        // can take a lot of time (many seconds), and we do not want to block
+        // state readers (like HTTP handler) for too long.
+        //
+        // We are keeping a CheckpointLayout for the checkpoint that is becoming
+        // the tip, in order to ensure that it does not get deleted.
+        //
+        // Note that we still will not call initialize_tip()
+        // concurrently because only a thread that owns the tip can call
+        // this function.
+        //
+        // This thread has already consumed states.tip, so a concurrent call to
+        // take_tip() will fail on states.tip.take().
+        //
+        // In general, there should always be one thread that calls
+        // take_tip() and commit_and_certify() — the state machine thread.
+
+        let checkpoint_layout = states
+            .states_metadata
+            .get(&target_snapshot.height)
+            .unwrap()
+            .checkpoint_layout
+            .as_ref()
+            .unwrap()
+            .clone();
+        std::mem::drop(states);
+
+        let mut new_tip = initialize_tip(
+            &self.log,
+            &self.tip_channel,
+            &target_snapshot,
+            checkpoint_layout,
+        );
+
+        new_tip.metadata.prev_state_hash = target_hash;
+
+        // This might still not be the latest version: there might have been
+        // another successful state sync while we were updating the tip.
+        // That is not a problem: we will handle this case later in commit_and_certify().
+        (target_snapshot.height, new_tip)
+    }
+
+    /// Imports into `tip` the staged states (see `StateLayout::canister_imports`)
+    /// of the canisters that a canister migration rerouted to this subnet and
+    /// that are not hosted yet.
+    ///
+    /// The routing table, `canister_migrations` and `canister_imports` are part
+    /// of the replicated state and follow the registry version agreed on by
+    /// consensus, so the import of a canister is pinned to the first height at
+    /// which it is rerouted to this subnet with a recorded state hash. Importing
+    /// it at any other height would make this replica diverge, so until the
+    /// staged state is present and matches the recorded hash, this blocks.
+    fn import_migrated_canisters(&self, height: Height, tip: &mut ReplicatedState) {
+        let canister_imports = Arc::clone(&tip.metadata.network_topology.canister_imports);
+        for (canister_id, _) in canister_imports.iter() {
+            let canister_id = *canister_id;
+            let import = match tip
+                .metadata
+                .network_topology
+                .pending_canister_import(canister_id, self.own_subnet_id)
+            {
+                Some(import) if tip.canister_state(&canister_id).is_none() => import.clone(),
+                _ => continue,
+            };
+
+            let canister_layout =
+                self.wait_for_staged_canister_state(canister_id, &import.state_hash);
+
+            // The staged state matches the hash recorded in the registry, so
+            // all replicas reach the same outcome from here on.
+            let canister_state = match checkpoint::load_canister_state(
+                &canister_layout,
+                &canister_id,
+                height,
+                Arc::clone(&self.fd_factory),
+            ) {
+                Ok((canister_state, _)) => canister_state,
+                Err(err) => {
+                    error!(
+                        self.log,
+                        "Not importing canister {}: failed to load its staged state: {}",
+                        canister_id,
+                        err
+                    );
+                    continue;
+                }
+            };
+            if !matches!(canister_state.system_state.status, CanisterStatus::Stopped) {
+                error!(
+                    self.log,
+                    "Not importing canister {}: it must be stopped, but is {:?}",
+                    canister_id,
+                    canister_state.system_state.status
+                );
+                continue;
+            }
+
+            info!(
+                self.log,
+                "Importing migrated canister {} @{}", canister_id, height
+            );
+            self.tip_channel
+                .send(TipRequest::ImportCanister {
+                    height,
+                    canister_id,
+                    canister_layout,
+                })
+                .unwrap();
+            tip.put_canister_state(canister_state);
+        }
+    }
+
+    /// Waits until the staged state of `canister_id` is present and its hash
+    /// equals `state_hash`, then returns its layout.
+    fn wait_for_staged_canister_state(
+        &self,
+        canister_id: CanisterId,
+        state_hash: &[u8; 32],
+    ) -> CanisterLayout<ReadOnly> {
+        loop {
+            let staged = self
+                .state_layout
+                .canister_import(&canister_id)
+                .map_err(|err| err.to_string())
+                .and_then(|canister_layout| {
+                    let hash = manifest::canister_state_hash(&canister_layout.raw_path())
+                        .map_err(|err| err.to_string())?;
+                    if &hash != state_hash {
+                        return Err(format!(
+                            "its hash is {}, but the registry records {}",
+                            hex::encode(hash),
+                            hex::encode(state_hash)
+                        ));
+                    }
+                    Ok(canister_layout)
+                });
+            match staged {
+                Ok(canister_layout) => return canister_layout,
+                Err(err) => {
+                    warn!(
+                        self.log,
+                        "Waiting for the staged state of migrated canister {}: {}",
+                        canister_id,
+                        err
+                    );
+                    std::thread::sleep(STAGED_CANISTER_STATE_POLL_INTERVAL);
+                }
+            }
+        }
+    }
+
+    /// Removes the staged states of migrated canisters that are part of
+    /// `checkpointed_state`. They are kept until then, so that the import can
+    /// be replayed after a restart from an earlier checkpoint.
+    fn remove_imported_canister_states(&self, checkpointed_state: &ReplicatedState) {
+        let canister_ids = match self.state_layout.canister_import_ids() {
+            Ok(canister_ids) => canister_ids,
+            Err(err) => {
+                warn!(self.log, "Failed to list staged canister states: {}", err);
+                return;
+            }
+        };
+        for canister_id in canister_ids {
+            if checkpointed_state.canister_state(&canister_id).is_none() {
+                continue;
+            }
+            if let Err(err) = self.state_layout.remove_canister_import(&canister_id) {
+                warn!(
+                    self.log,
+                    "Failed to remove staged state of canister {}: {}", canister_id, err
+                );
+            }
+        }
+    }
+
     pub fn test_only_send_wait_to_tip_channel(&self, sender: Sender<()>) {
         self.tip_channel.send(TipRequest::Wait { sender }).unwrap();
     }
@@ -2528,88 +2790,9 @@ impl StateManager for StateManagerImpl {
             .with_label_values(&["take_tip"])
             .start_timer();
 
-        let hash_at = |tip_height: Height, certifications_metadata: &CertificationsMetadata| {
-            if tip_height > Self::INITIAL_STATE_HEIGHT {
-                let tip_metadata = certifications_metadata.get(&tip_height).unwrap_or_else(|| {
-                    fatal!(self.log, "Bug: missing tip metadata @{}", tip_height)
-                });
-
-                // Since the state machine will use this tip to compute the *next* state,
-                // we populate the prev_state_hash with the hash of the current tip.
-                Some(CryptoHashOfPartialState::from(
-                    tip_metadata.certified_state_hash.clone(),
-                ))
-            } else {
-                // This code is executed at most once per subnet, no need to
-                // optimize this.
-                let hash_tree = hash_lazy_tree(&replicated_state_as_lazy_tree(
-                    initial_state(self.own_subnet_id, self.own_subnet_type).get_ref(),
-                ))
-                .unwrap_or_else(|err| fatal!(self.log, "Failed to compute hash tree: {:?}", err));
-                update_hash_tree_metrics(&hash_tree, &self.metrics);
-                Some(CryptoHashOfPartialState::from(crypto_hash_of_tree(
-                    &hash_tree,
-                )))
-            }
-        };
-
-        let mut states = self.states.write();
-        let (tip_height, mut tip) = states.tip.take().expect("failed to get TIP");
-
-        let (target_snapshot, target_hash) = match states.snapshots.back() {
-            Some(snapshot) if snapshot.height > tip_height => (
-                snapshot.clone(),
-                hash_at(snapshot.height, &states.certifications_metadata),
-            ),
-            _ => {
-                tip.metadata.prev_state_hash = hash_at(tip_height, &states.certifications_metadata);
-                return (tip_height, tip);
-            }
-        };
-
-        // The latest checkpoint is newer than tip.
-        // This can happen when we replay blocks and sync states concurrently.
-        //
-        // We release the states write lock here because loading a checkpoint
-        // can take a lot of time (many seconds), and we do not want to block
-        // state readers (like HTTP handler) for too long.
-        //
-        // We are keeping a CheckpointLayout for the checkpoint that is becoming
-        // the tip, in order to ensure that it does not get deleted.
-        //
-        // Note that we still will not call initialize_tip()
-        // concurrently because only a thread that owns the tip can call
-        // this function.
-        //
-        // This thread has already consumed states.tip, so a concurrent call to
-        // take_tip() will fail on states.tip.take().
-        //
-        // In general, there should always be one thread that calls
-        // take_tip() and commit_and_certify() — the state machine thread.
-
-        let checkpoint_layout = states
-            .states_metadata
-            .get(&target_snapshot.height)
-            .unwrap()
-            .checkpoint_layout
-            .as_ref()
-            .unwrap()
-            .clone();
-        std::mem::drop(states);
-
-        let mut new_tip = initialize_tip(
-            &self.log,
-            &self.tip_channel,
-            &target_snapshot,
-            checkpoint_layout,
-        );
-
-        new_tip.metadata.prev_state_hash = target_hash;
-
-        // This might still not be the latest version: there might have been
-        // another successful state sync while we were updating the tip.
-        // That is not a problem: we will handle this case later in commit_and_certify().
-        (target_snapshot.height, new_tip)
+        let (tip_height, mut tip) = self.take_latest_tip();
+        self.import_migrated_canisters(tip_height, &mut tip);
+        (tip_height, tip)
     }
 
     fn take_tip_at(&self, height: Height) -> StateManagerResult<ReplicatedState> {
@@ -2991,6 +3174,7 @@ impl StateManager for StateManagerImpl {
                     compute_manifest_request,
                     tip_requests,
                 } = self.create_checkpoint_and_switch(&mut state, height);
+                self.remove_imported_canister_states(&checkpointed_state);
                 state_metadata_and_compute_manifest_request =
                     Some((state_metadata, compute_manifest_request));
                 follow_up_tip_requests = tip_requests;
@@ -20,8 +20,8 @@ use hash::{chunk_hasher, file_hasher, manifest_hasher, ManifestHash};
 use ic_crypto_sha2::Sha256;
 use ic_logger::{error, fatal, replica_logger::no_op_logger, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
-use ic_replicated_state::PageIndex;
-use ic_state_layout::{CheckpointLayout, ReadOnly, CANISTER_FILE};
+use ic_replicated_state::page_map::{StorageLayout, DIRTY_CHUNK_SIZE};
+use ic_state_layout::{CheckpointLayout, PageMapLayout, ReadOnly, CANISTER_FILE};
 use ic_sys::{mmap::ScopedMmap, PAGE_SIZE};
 use ic_types::{
     crypto::CryptoHash,
@@ -232,9 +232,12 @@ pub struct ManifestDelta {
     pub(crate) base_height: Height,
     /// Current height
     pub(crate) target_height: Height,
-    /// Wasm memory and stable memory pages that might have changed since the
+    /// Wasm memory and stable memory chunks that might have changed since the
     /// state at `base_height`.
     pub(crate) dirty_memory_pages: DirtyPages,
+    /// Whether to recompute the hashes of all chunks that could be reused and
+    /// compare them against the hashes in `base_manifest`.
+    pub(crate) verify_reused_chunks: bool,
 }
 
 /// Groups small files into larger chunks.
@@ -712,7 +715,7 @@ fn default_hash_plan(files: &[FileWithSize], max_chunk_size: u32) -> Vec<ChunkAc
 
 fn dirty_chunks_of_file(
     relative_path: &Path,
-    page_indices: &[PageIndex],
+    dirty_chunk_indices: &[u64],
     files: &[FileWithSize],
     max_chunk_size: u32,
     base_manifest: &Manifest,
@@ -724,11 +727,16 @@ fn dirty_chunks_of_file(
         let num_chunks = count_chunks(size_bytes, max_chunk_size);
         let mut chunks_bitmap = BitVec::from_elem(num_chunks, false);
 
-        for page_index in page_indices {
-            // As the chunk size is a multiple of the page size, at most one chunk could
-            // possibly be affected.
-            let chunk_index = PAGE_SIZE * page_index.get() as usize / max_chunk_size as usize;
-            chunks_bitmap.set(chunk_index, true);
+        for dirty_chunk_index in dirty_chunk_indices {
+            // Page maps track dirty chunks of `DIRTY_CHUNK_SIZE` bytes, which may
+            // span several manifest chunks if the chunk size is smaller.
+            let start = dirty_chunk_index * DIRTY_CHUNK_SIZE;
+            let end = start + DIRTY_CHUNK_SIZE;
+            let from_chunk = (start / max_chunk_size as u64) as usize;
+            let to_chunk = ((end + max_chunk_size as u64 - 1) / max_chunk_size as u64) as usize;
+            for i in from_chunk..to_chunk.min(num_chunks) {
+                chunks_bitmap.set(i, true);
+            }
         }
 
         // NB. The code below handles the case when the file size increased, but the
@@ -765,6 +773,56 @@ fn dirty_chunks_of_file(
     }
 }
 
+/// Returns a bitmap marking all chunks of the file as unchanged, if the file
+/// is present with the same size both in `files` and in `base_manifest`.
+fn unchanged_chunks_of_file(
+    relative_path: &Path,
+    files: &[FileWithSize],
+    max_chunk_size: u32,
+    base_manifest: &Manifest,
+) -> Option<BitVec> {
+    let index = files
+        .binary_search_by(|FileWithSize(file_path, _)| file_path.as_path().cmp(relative_path))
+        .ok()?;
+    let size_bytes = files[index].1;
+    let base_file_index = base_manifest
+        .file_table
+        .binary_search_by(|file_info| file_info.relative_path.as_path().cmp(relative_path))
+        .ok()?;
+    if base_manifest.file_table[base_file_index].size_bytes != size_bytes {
+        return None;
+    }
+    Some(BitVec::from_elem(
+        count_chunks(size_bytes, max_chunk_size),
+        false,
+    ))
+}
+
+/// Returns true if `manifest` contains an overlay file of the page map
+/// described by `layout`.
+///
+/// `first_overlay` is the path of the overlay at height 0, which sorts before
+/// all other overlays of the page map, so it is sufficient to inspect the
+/// first file that is not smaller than it.
+fn manifest_has_overlay(
+    manifest: &Manifest,
+    layout: &PageMapLayout<ReadOnly>,
+    checkpoint_root: &Path,
+    first_overlay: &Path,
+) -> bool {
+    let first_overlay = first_overlay
+        .strip_prefix(checkpoint_root)
+        .expect("failed to strip path prefix");
+    let index = manifest
+        .file_table
+        .partition_point(|file_info| file_info.relative_path.as_path() < first_overlay);
+    manifest.file_table.get(index).map_or(false, |file_info| {
+        layout
+            .overlay_height(&checkpoint_root.join(&file_info.relative_path))
+            .is_ok()
+    })
+}
+
 /// Computes the bitmap of chunks modified since the base state.
 fn dirty_pages_to_dirty_chunks(
     manifest_delta: &ManifestDelta,
@@ -792,9 +850,50 @@ fn dirty_pages_to_dirty_chunks(
         }
 
         let path = match dirty_page.file_type {
-            FileType::PageMap(page_type) => page_type.path(checkpoint),
+            FileType::PageMap(page_type) => {
+                let layout = match page_type.layout(checkpoint) {
+                    Ok(layout) => layout,
+                    Err(_) => continue,
+                };
+                let page_map_files = layout.existing_files()?;
+                if page_map_files.iter().any(|path| *path != layout.base()) {
+                    // The page map is stored as overlays. Files are never modified
+                    // in this mode, only created or replaced by merges, so every
+                    // file that has the same name and size as in the base manifest
+                    // is unchanged. A merged overlay has the same size as the
+                    // overlay it replaces only if it contains exactly the same
+                    // pages, in which case the contents are identical, too.
+                    for path in page_map_files {
+                        let relative_path = path
+                            .strip_prefix(checkpoint.raw_path())
+                            .expect("failed to strip path prefix");
+                        if let Some(chunks_bitmap) = unchanged_chunks_of_file(
+                            relative_path,
+                            files,
+                            max_chunk_size,
+                            &manifest_delta.base_manifest,
+                        ) {
+                            dirty_chunks.insert(relative_path.to_path_buf(), chunks_bitmap);
+                        }
+                    }
+                    continue;
+                }
+                // If the page map had overlays at the base height, they have been
+                // merged into the base file and the dirty pages do not describe
+                // the changes of the base file.
+                let first_overlay = layout.overlay(Height::new(0));
+                if manifest_has_overlay(
+                    &manifest_delta.base_manifest,
+                    &layout,
+                    checkpoint.raw_path(),
+                    &first_overlay,
+                ) {
+                    continue;
+                }
+                Ok(layout.base())
+            }
             FileType::WasmBinary(canister_id) => {
-                assert!(dirty_page.page_delta_indices.is_empty());
+                assert!(dirty_page.dirty_chunk_indices.is_empty());
 
                 checkpoint
                     .canister(&canister_id)
@@ -809,7 +908,7 @@ fn dirty_pages_to_dirty_chunks(
 
             if let Some(chunks_bitmap) = dirty_chunks_of_file(
                 relative_path,
-                &dirty_page.page_delta_indices,
+                &dirty_page.dirty_chunk_indices,
                 files,
                 max_chunk_size,
                 &manifest_delta.base_manifest,
@@ -850,13 +949,18 @@ pub fn compute_manifest(
                     &files,
                     max_chunk_size,
                 )?;
+                let rehash_every_nth = if manifest_delta.verify_reused_chunks {
+                    1
+                } else {
+                    REHASH_EVERY_NTH_CHUNK
+                };
                 hash_plan(
                     &manifest_delta.base_manifest,
                     &files,
                     dirty_file_chunks,
                     max_chunk_size,
                     manifest_delta.target_height.get(),
-                    REHASH_EVERY_NTH_CHUNK,
+                    rehash_every_nth,
                 )
             } else {
                 default_hash_plan(&files, max_chunk_size)
@@ -1379,3 +1483,36 @@ pub fn manifest_from_path(path: &Path) -> Result<Manifest, CheckpointError> {
         None,
     )
 }
+
+/// Computes the hash of the canister state stored under `canister_root`, i.e.
+/// of the relative path, size and contents of each of its files.
+///
+/// Recorded in the registry when the state of a migrated canister is exported
+/// and checked by the destination subnet before importing it.
+pub fn canister_state_hash(canister_root: &Path) -> Result<[u8; 32], CheckpointError> {
+    let mut files = Vec::new();
+    files_with_sizes(canister_root, "".into(), &mut files)?;
+    files.sort_unstable_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+
+    let mut hash = manifest_hasher();
+    (files.len() as u32).update_hash(&mut hash);
+    for FileWithSize(relative_path, size_bytes) in files {
+        let absolute_path = canister_root.join(&relative_path);
+        let mmap =
+            ScopedMmap::from_path(&absolute_path).map_err(|io_err| CheckpointError::IoError {
+                path: absolute_path.clone(),
+                message: "failed to open file".to_string(),
+                io_err: io_err.to_string(),
+            })?;
+        let mut file_hash = file_hasher();
+        file_hash.write(mmap.as_slice());
+
+        relative_path
+            .to_str()
+            .expect("failed to convert path to a str")
+            .update_hash(&mut hash);
+        size_bytes.update_hash(&mut hash);
+        file_hash.finish().update_hash(&mut hash);
+    }
+    Ok(hash.finish())
+}
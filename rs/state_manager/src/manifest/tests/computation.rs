@@ -1,8 +1,8 @@
 use crate::manifest::validate_manifest_internal_consistency;
 use crate::manifest::{
-    build_file_group_chunks, build_meta_manifest, compute_manifest, diff_manifest,
-    file_chunk_range, filter_out_zero_chunks, hash::ManifestHash, manifest_hash, manifest_hash_v1,
-    manifest_hash_v2, meta_manifest_hash, validate_chunk, validate_manifest,
+    build_file_group_chunks, build_meta_manifest, canister_state_hash, compute_manifest,
+    diff_manifest, file_chunk_range, filter_out_zero_chunks, hash::ManifestHash, manifest_hash,
+    manifest_hash_v1, manifest_hash_v2, meta_manifest_hash, validate_chunk, validate_manifest,
     validate_meta_manifest, validate_sub_manifest, ChunkValidationError, DiffScript,
     ManifestMetrics, ManifestValidationError, StateSyncVersion, DEFAULT_CHUNK_SIZE,
     MAX_FILE_SIZE_TO_GROUP,
@@ -1236,3 +1236,32 @@ fn test_file_index_independent_file_hash() {
     assert_eq!(file1_hash_before, file1_hash_after);
     assert_eq!(file3_hash_before, file3_hash_after);
 }
+
+#[test]
+fn canister_state_hash_covers_file_paths_and_contents() {
+    let dir = tempfile::TempDir::new().expect("failed to create a temporary directory");
+    let root = dir.path();
+    fs::write(root.join(CANISTER_FILE), vec![1u8; 100]).unwrap();
+    fs::create_dir(root.join("snapshots")).unwrap();
+    fs::write(
+        root.join("snapshots").join("vmemory_0.bin"),
+        vec![2u8; 1000],
+    )
+    .unwrap();
+
+    let hash = canister_state_hash(root).unwrap();
+    assert_eq!(hash, canister_state_hash(root).unwrap());
+
+    // Changing the contents of a file changes the hash.
+    fs::write(root.join(CANISTER_FILE), vec![3u8; 100]).unwrap();
+    let changed_contents_hash = canister_state_hash(root).unwrap();
+    assert_ne!(hash, changed_contents_hash);
+
+    // So does moving a file.
+    fs::rename(
+        root.join("snapshots").join("vmemory_0.bin"),
+        root.join("vmemory_0.bin"),
+    )
+    .unwrap();
+    assert_ne!(changed_contents_hash, canister_state_hash(root).unwrap());
+}
@@ -6,7 +6,7 @@ use crate::{
 };
 
 use ic_base_types::CanisterId;
-use ic_config::state_manager::Config;
+use ic_config::{flag_status::FlagStatus, state_manager::Config};
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
 use ic_registry_routing_table::{
@@ -189,6 +189,8 @@ fn write_checkpoint(
         state_layout,
         metrics.clone(),
         MaliciousFlags::default(),
+        FlagStatus::Disabled,
+        NUMBER_OF_CHECKPOINT_THREADS,
     );
 
     make_checkpoint(
@@ -5,6 +5,7 @@ use crate::{
 };
 use assert_matches::assert_matches;
 use ic_base_types::{subnet_id_try_from_protobuf, CanisterId, NumSeconds};
+use ic_config::flag_status::FlagStatus;
 use ic_error_types::{ErrorCode, UserError};
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
@@ -329,6 +330,8 @@ fn new_state_layout(log: ReplicaLogger) -> (TempDir, Time) {
         layout.clone(),
         state_manager_metrics.clone(),
         MaliciousFlags::default(),
+        FlagStatus::Disabled,
+        NUMBER_OF_CHECKPOINT_THREADS,
     );
 
     let mut state = ReplicatedState::new(SUBNET_A, SubnetType::Application);
@@ -2,9 +2,10 @@ use crate::{
     manifest::{build_file_group_chunks, filter_out_zero_chunks, DiffScript},
     StateManagerMetrics, StateSyncMetrics, StateSyncRefs,
     CRITICAL_ERROR_STATE_SYNC_CORRUPTED_CHUNKS, LABEL_COPY_CHUNKS, LABEL_COPY_FILES, LABEL_FETCH,
-    LABEL_PREALLOCATE, LABEL_STATE_SYNC_MAKE_CHECKPOINT,
+    LABEL_PERSIST_PROGRESS, LABEL_PREALLOCATE, LABEL_STATE_SYNC_MAKE_CHECKPOINT,
 };
 use ic_logger::{debug, error, fatal, info, trace, warn, ReplicaLogger};
+use ic_protobuf::{proxy::try_from_option_field, state::sync::v1 as pb};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::page_map::PageAllocatorFileDescriptor;
 use ic_state_layout::utils::do_copy_overwrite;
@@ -17,6 +18,7 @@ use ic_types::{
         ArtifactErrorCode::{self, ChunkVerificationFailed, ChunksMoreNeeded},
         ChunkId, Chunkable,
     },
+    crypto::CryptoHash,
     malicious_flags::MaliciousFlags,
     state_sync::{
         decode_manifest, decode_meta_manifest, state_sync_chunk_type, FileGroupChunks, Manifest,
@@ -25,9 +27,10 @@ use ic_types::{
     },
     CryptoHashOfState, Height,
 };
+use prost::Message;
 use std::os::unix::fs::FileExt;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     sync::{Arc, Mutex},
@@ -39,6 +42,10 @@ pub mod cache;
 // necessary.
 const ALWAYS_VALIDATE: bool = false;
 
+// How often the progress of a state sync in the loading phase is persisted
+// next to its scratchpad.
+const PROGRESS_PERSIST_INTERVAL: Duration = Duration::from_secs(30);
+
 type SubManifest = Vec<u8>;
 /// The state of the communication with up-to-date nodes.
 #[derive(Clone)]
@@ -82,6 +89,13 @@ enum DownloadState {
 /// sync priority function.  When priority function returns "Fetch", P2P calls
 /// StateManager to construct an IncompleteState corresponding to the state
 /// artifact advert.
+///
+/// Once the manifest is known, the progress of the fetch is periodically
+/// persisted next to the scratchpad. If the replica restarts in the middle of
+/// the sync, a new `IncompleteState` for the same height and root hash picks
+/// up the scratchpad and only fetches the chunks that were still missing.
+/// The same holds if the fetch is aborted: the scratchpad is only abandoned
+/// once a fetch at another height starts.
 pub struct IncompleteState {
     log: ReplicaLogger,
     root: PathBuf,
@@ -93,6 +107,10 @@ pub struct IncompleteState {
     metrics: StateManagerMetrics,
     started_at: Instant,
     fetch_started_at: Option<Instant>,
+    progress_persisted_at: Instant,
+    /// Indices into the manifest's file table of the files written to since
+    /// the progress was last persisted.
+    files_to_sync: BTreeSet<usize>,
     own_subnet_type: SubnetType,
     thread_pool: Arc<Mutex<scoped_threadpool::Pool>>,
     state_sync_refs: StateSyncRefs,
@@ -167,9 +185,17 @@ impl Drop for IncompleteState {
 
         info!(self.log, "State sync @{} {}", self.height, description);
 
-        // Pass self to the cache, taking ownership of chunks on disk
-        let cache = Arc::clone(&self.state_sync_refs.cache);
-        cache.write().push(self);
+        if let DownloadState::Loading { .. } = self.state {
+            // The sync may be dropped because the replica shuts down, so the
+            // scratchpad stays in place with its latest progress. A later sync
+            // at the same height resumes from it, while a sync at any other
+            // height abandons it (see `abandon_scratchpads`).
+            self.persist_progress();
+        } else {
+            // Pass self to the cache, taking ownership of chunks on disk
+            let cache = Arc::clone(&self.state_sync_refs.cache);
+            cache.write().push(self);
+        }
 
         if self.state_sync_refs.remove(&self.height).is_none() {
             warn!(
@@ -203,19 +229,27 @@ impl IncompleteState {
             fatal!(log, "There is already a live state sync @{}.", height);
         }
 
+        let root = state_layout
+            .state_sync_scratchpad(height)
+            .expect("failed to create directory for state sync scratchpad");
+        Self::abandon_scratchpads(&log, &state_layout, &state_sync_refs, height);
+        let resumed_state =
+            Self::resume_from_scratchpad(&log, &state_layout, &root, height, &root_hash, &metrics);
+        let fetch_started_at = resumed_state.as_ref().map(|_| Instant::now());
+
         Self {
             log,
-            root: state_layout
-                .state_sync_scratchpad(height)
-                .expect("failed to create directory for state sync scratchpad"),
+            root,
             state_layout,
             height,
             root_hash,
-            state: DownloadState::Blank,
+            state: resumed_state.unwrap_or(DownloadState::Blank),
             manifest_with_checkpoint_layout,
             metrics,
             started_at: Instant::now(),
-            fetch_started_at: None,
+            fetch_started_at,
+            progress_persisted_at: Instant::now(),
+            files_to_sync: Default::default(),
             own_subnet_type,
             thread_pool,
             state_sync_refs,
@@ -224,6 +258,323 @@ impl IncompleteState {
         }
     }
 
+    /// Restores the loading phase from a scratchpad left behind by a previous
+    /// run of the replica, if its persisted progress targets the same height
+    /// and root hash. Otherwise, any leftover scratchpad at `root` is removed
+    /// and `None` is returned.
+    fn resume_from_scratchpad(
+        log: &ReplicaLogger,
+        state_layout: &StateLayout,
+        root: &Path,
+        height: Height,
+        root_hash: &CryptoHashOfState,
+        metrics: &StateManagerMetrics,
+    ) -> Option<DownloadState> {
+        let progress_path = state_layout
+            .state_sync_progress(height)
+            .expect("failed to get path of state sync progress");
+        if !progress_path.exists() {
+            Self::discard_scratchpad(log, root, &progress_path);
+            return None;
+        }
+
+        let (meta_manifest, manifest, state_sync_file_group, fetch_chunks) =
+            match Self::load_progress(&progress_path, root, height, root_hash) {
+                Ok(progress) => progress,
+                Err(err) => {
+                    warn!(log, "Discarding state sync scratchpad @{}: {}", height, err);
+                    Self::discard_scratchpad(log, root, &progress_path);
+                    return None;
+                }
+            };
+
+        let remaining_chunks: usize = fetch_chunks
+            .iter()
+            .map(|ix| {
+                if (*ix as u32) < FILE_GROUP_CHUNK_ID_OFFSET {
+                    1
+                } else {
+                    state_sync_file_group
+                        .get(&(*ix as u32))
+                        .map(|vec| vec.len())
+                        .unwrap_or(0)
+                }
+            })
+            .sum();
+        metrics
+            .state_sync_metrics
+            .remaining
+            .add(remaining_chunks as i64);
+
+        info!(
+            log,
+            "Resuming state sync @{} from scratchpad with {} chunks left to fetch",
+            height,
+            fetch_chunks.len()
+        );
+
+        Some(DownloadState::Loading {
+            meta_manifest,
+            manifest,
+            state_sync_file_group,
+            fetch_chunks,
+        })
+    }
+
+    /// Reads and validates the state sync progress persisted at `path`.
+    fn load_progress(
+        path: &Path,
+        root: &Path,
+        height: Height,
+        root_hash: &CryptoHashOfState,
+    ) -> Result<(MetaManifest, Manifest, FileGroupChunks, HashSet<usize>), String> {
+        let progress = Self::read_progress(path, root)?;
+        if progress.height != height.get() || progress.root_hash != root_hash.get_ref().0 {
+            return Err(format!(
+                "progress belongs to a different state (height {})",
+                progress.height
+            ));
+        }
+
+        let meta_manifest: MetaManifest =
+            try_from_option_field(progress.meta_manifest, "StateSyncProgress::meta_manifest")
+                .map_err(|err| err.to_string())?;
+        let manifest: Manifest =
+            try_from_option_field(progress.manifest, "StateSyncProgress::manifest")
+                .map_err(|err| err.to_string())?;
+        crate::manifest::validate_meta_manifest(&meta_manifest, root_hash)
+            .map_err(|err| err.to_string())?;
+        crate::manifest::validate_manifest(&manifest, root_hash).map_err(|err| err.to_string())?;
+
+        let fetch_chunks: HashSet<usize> = progress
+            .fetch_chunks
+            .into_iter()
+            .map(|id| id as usize)
+            .collect();
+        if fetch_chunks.is_empty() {
+            return Err("progress has no chunks left to fetch".to_string());
+        }
+
+        let state_sync_file_group = build_file_group_chunks(&manifest);
+        let valid_chunk_id = |id: &usize| match state_sync_chunk_type(*id as u32) {
+            StateSyncChunk::FileChunk(index) => (index as usize) < manifest.chunk_table.len(),
+            StateSyncChunk::FileGroupChunk(index) => state_sync_file_group.get(&index).is_some(),
+            _ => false,
+        };
+        if !fetch_chunks.iter().all(valid_chunk_id) {
+            return Err("progress refers to unknown chunks".to_string());
+        }
+
+        Ok((meta_manifest, manifest, state_sync_file_group, fetch_chunks))
+    }
+
+    /// Reads the state sync progress persisted at `path` for the scratchpad
+    /// at `root`.
+    fn read_progress(path: &Path, root: &Path) -> Result<pb::StateSyncProgress, String> {
+        if !root.is_dir() {
+            return Err(format!("scratchpad {} is missing", root.display()));
+        }
+        let bytes = std::fs::read(path)
+            .map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+        pb::StateSyncProgress::decode(&bytes[..])
+            .map_err(|err| format!("failed to decode {}: {}", path.display(), err))
+    }
+
+    /// Abandons the scratchpads that dropped state syncs at heights other than
+    /// `height` left behind. The most recent one is handed over to the state
+    /// sync cache, so that its chunks can still be reused; the others are
+    /// removed.
+    fn abandon_scratchpads(
+        log: &ReplicaLogger,
+        state_layout: &StateLayout,
+        state_sync_refs: &StateSyncRefs,
+        height: Height,
+    ) {
+        let heights = match state_layout.state_sync_scratchpad_heights() {
+            Ok(heights) => heights,
+            Err(err) => {
+                warn!(log, "Failed to list state sync scratchpads: {}", err);
+                return;
+            }
+        };
+
+        let mut cache = state_sync_refs.cache.write();
+        for abandoned_height in heights.into_iter().rev() {
+            if abandoned_height == height || state_sync_refs.get(&abandoned_height).is_some() {
+                continue;
+            }
+            let root = state_layout
+                .state_sync_scratchpad(abandoned_height)
+                .expect("failed to get path of state sync scratchpad");
+            let progress_path = state_layout
+                .state_sync_progress(abandoned_height)
+                .expect("failed to get path of state sync progress");
+
+            // The root hash of the abandoned sync is only known from its
+            // progress, so this merely checks that the progress is consistent.
+            let progress = Self::read_progress(&progress_path, &root).and_then(|progress| {
+                let root_hash = CryptoHashOfState::from(CryptoHash(progress.root_hash));
+                Self::load_progress(
+                    &progress_path,
+                    &root,
+                    Height::new(progress.height),
+                    &root_hash,
+                )
+            });
+            // Remove the progress first, so that a crash never leaves a
+            // resumable scratchpad behind whose files were moved to the cache.
+            Self::remove_progress(log, state_layout, abandoned_height);
+            match progress {
+                Ok((_, manifest, state_sync_file_group, fetch_chunks))
+                    if cache.push_abandoned(
+                        state_layout,
+                        abandoned_height,
+                        &root,
+                        manifest,
+                        fetch_chunks,
+                        state_sync_file_group,
+                    ) =>
+                {
+                    info!(
+                        log,
+                        "Abandoned state sync @{} in favor of state sync @{}",
+                        abandoned_height,
+                        height
+                    );
+                }
+                Ok(_) => Self::discard_scratchpad(log, &root, &progress_path),
+                Err(err) => {
+                    warn!(
+                        log,
+                        "Discarding state sync scratchpad @{}: {}", abandoned_height, err
+                    );
+                    Self::discard_scratchpad(log, &root, &progress_path);
+                }
+            }
+        }
+    }
+
+    /// Persists the progress of the loading phase next to the scratchpad.
+    ///
+    /// The files written to since the last call are synced first, so that
+    /// chunks recorded as fetched are durable on disk. Failing to persist the
+    /// progress only means that a restarted replica fetches more chunks again,
+    /// so errors are logged and otherwise ignored.
+    fn persist_progress(&mut self) {
+        let (meta_manifest, manifest, fetch_chunks) = match &self.state {
+            DownloadState::Loading {
+                meta_manifest,
+                manifest,
+                fetch_chunks,
+                ..
+            } => (meta_manifest, manifest, fetch_chunks),
+            _ => return,
+        };
+
+        let _timer = self
+            .metrics
+            .state_sync_metrics
+            .step_duration
+            .with_label_values(&[LABEL_PERSIST_PROGRESS])
+            .start_timer();
+
+        let mut dirs = BTreeSet::new();
+        dirs.insert(self.root.clone());
+        for file_index in self.files_to_sync.iter() {
+            let path = self
+                .root
+                .join(&manifest.file_table[*file_index].relative_path);
+            if let Err(err) = ic_utils::fs::sync_path(&path) {
+                warn!(self.log, "Failed to persist state sync progress: {}", err);
+                return;
+            }
+            dirs.extend(
+                path.ancestors()
+                    .skip(1)
+                    .take_while(|dir| dir.starts_with(&self.root))
+                    .map(Path::to_path_buf),
+            );
+        }
+        for dir in dirs {
+            if let Err(err) = ic_utils::fs::sync_path(&dir) {
+                warn!(self.log, "Failed to persist state sync progress: {}", err);
+                return;
+            }
+        }
+        self.files_to_sync.clear();
+
+        let progress = pb::StateSyncProgress {
+            height: self.height.get(),
+            root_hash: self.root_hash.get_ref().0.clone(),
+            meta_manifest: Some(meta_manifest.clone().into()),
+            manifest: Some(manifest.clone().into()),
+            fetch_chunks: fetch_chunks.iter().map(|id| *id as u32).collect(),
+        };
+        let progress_path = self
+            .state_layout
+            .state_sync_progress(self.height)
+            .expect("failed to get path of state sync progress");
+        let tmp = self.state_layout.tmp().join(format!(
+            "tmp_state_sync_progress_{:016x}.pb",
+            self.height.get()
+        ));
+        if let Err(err) = ic_utils::fs::write_atomically_using_tmp_file(&progress_path, &tmp, |w| {
+            use std::io::Write;
+            w.write_all(&progress.encode_to_vec())
+        }) {
+            warn!(
+                self.log,
+                "Failed to write state sync progress to {}: {}",
+                progress_path.display(),
+                err
+            );
+        }
+        self.progress_persisted_at = Instant::now();
+    }
+
+    /// Removes the persisted progress of the state sync at `height`, if any.
+    fn remove_progress(log: &ReplicaLogger, state_layout: &StateLayout, height: Height) {
+        let progress_path = state_layout
+            .state_sync_progress(height)
+            .expect("failed to get path of state sync progress");
+        match std::fs::remove_file(&progress_path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => warn!(
+                log,
+                "Failed to remove state sync progress {}: {}",
+                progress_path.display(),
+                err
+            ),
+        }
+    }
+
+    /// Removes a scratchpad that cannot be resumed, starting with its progress
+    /// file so that a crash in between never leaves a resumable scratchpad
+    /// with missing files behind.
+    fn discard_scratchpad(log: &ReplicaLogger, root: &Path, progress_path: &Path) {
+        if progress_path.exists() {
+            if let Err(err) = std::fs::remove_file(progress_path) {
+                warn!(
+                    log,
+                    "Failed to remove state sync progress {}: {}",
+                    progress_path.display(),
+                    err
+                );
+            }
+        }
+        if root.exists() {
+            if let Err(err) = std::fs::remove_dir_all(root) {
+                warn!(
+                    log,
+                    "Failed to remove state sync scratchpad {}: {}",
+                    root.display(),
+                    err
+                );
+            }
+        }
+    }
+
     /// Creates all the files listed in the manifest and resizes them to their
     /// expected sizes.  This way we won't have to worry about creating parent
     /// directories when we receive chunks.
@@ -1362,6 +1713,8 @@ impl Chunkable for IncompleteState {
                             fetch_chunks.insert(chunk_id as usize);
                         }
                         let num_fetch_chunks = fetch_chunks.len();
+                        // Preallocated and copied files have not been synced yet.
+                        self.files_to_sync = (0..manifest.file_table.len()).collect();
                         self.state = DownloadState::Loading {
                             meta_manifest,
                             manifest,
@@ -1374,6 +1727,7 @@ impl Chunkable for IncompleteState {
                             "state sync enters the loading phase with {} chunks to fetch",
                             num_fetch_chunks,
                         );
+                        self.persist_progress();
                         Err(ChunksMoreNeeded)
                     }
                 } else {
@@ -1468,6 +1822,9 @@ impl Chunkable for IncompleteState {
                         &payload[start..end],
                         manifest,
                     );
+                    self.files_to_sync.insert(
+                        manifest.chunk_table[*chunk_table_index as usize].file_index as usize,
+                    );
                 }
 
                 fetch_chunks.remove(&(ix as usize));
@@ -1480,6 +1837,9 @@ impl Chunkable for IncompleteState {
                         self.height
                     );
 
+                    // The scratchpad is about to become a checkpoint.
+                    Self::remove_progress(&self.log, &self.state_layout, self.height);
+
                     if let Some(fetch_start_at) = self.fetch_started_at {
                         let elapsed = fetch_start_at.elapsed();
                         self.metrics
@@ -1529,6 +1889,10 @@ impl Chunkable for IncompleteState {
                     return Ok(artifact);
                 }
 
+                if self.progress_persisted_at.elapsed() >= PROGRESS_PERSIST_INTERVAL {
+                    self.persist_progress();
+                }
+
                 Err(ChunksMoreNeeded)
             }
         }
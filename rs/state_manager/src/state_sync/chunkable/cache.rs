@@ -19,8 +19,8 @@ fn delete_folder(log: &ReplicaLogger, path: &Path) {
 ///
 /// It contains the most recent incomplete state (i.e., with the largest
 /// height). This cache is constructed by StateManagerImpl and is shared with
-/// instances of IncompleteState. IncompleteStates push their data into this
-/// cache if the artifact manager aborts the corresponding state sync.
+/// instances of IncompleteState. The scratchpad of an aborted state sync is
+/// pushed into this cache once a state sync at another height abandons it.
 pub struct StateSyncCache {
     entry: Option<Arc<StateSyncCacheEntry>>,
     highest_successful_sync: Option<Height>,
@@ -92,7 +92,9 @@ impl StateSyncCache {
     /// This function is not public and therefore cannot be called directly.
     fn push_inner(
         &mut self,
-        sync: &IncompleteState,
+        state_layout: &StateLayout,
+        height: Height,
+        root: &Path,
         manifest: Manifest,
         fetch_chunks: HashSet<usize>,
         state_sync_file_group: FileGroupChunks,
@@ -124,11 +126,10 @@ impl StateSyncCache {
         // Otherwise we'd have to assume that there won't be an active state sync at
         // the same height as the cache (as the folder name
         // only depends on the height).
-        let cache_root = sync
-            .state_layout
-            .state_sync_cache(sync.height)
+        let cache_root = state_layout
+            .state_sync_cache(height)
             .expect("failed to create directory for state sync cache");
-        if let Err(err) = std::fs::rename(&sync.root, &cache_root) {
+        if let Err(err) = std::fs::rename(root, &cache_root) {
             warn!(
                 self.log,
                 "Failed to create state sync cache at {}: {}",
@@ -139,12 +140,12 @@ impl StateSyncCache {
             // On error, make sure the cache is empty and clean up
             self.entry = None;
             delete_folder(&self.log, &cache_root);
-            delete_folder(&self.log, &sync.root);
+            delete_folder(&self.log, root);
             return;
         }
         let entry = StateSyncCacheEntry {
             manifest,
-            height: sync.height,
+            height,
             path: cache_root,
             missing_chunks,
             log: self.log.clone(),
@@ -152,23 +153,22 @@ impl StateSyncCache {
         self.entry = Some(Arc::new(entry));
     }
 
-    /// Passes an `IncompleteState` `sync` to the cache, moving out any data
-    /// relevant to caching.
+    /// Passes an `IncompleteState` `sync` that is not loading chunks to the
+    /// cache.
     ///
-    /// Only replaces the cache if `sync` has started, not finished, and
-    /// doesn't have an older height than what is currently in the cache.
+    /// Such a sync never replaces the cache, but clears it if `sync` has
+    /// started and doesn't have an older height than what is currently in the
+    /// cache. The scratchpad of a sync that is still loading chunks is kept for
+    /// resumption instead and only passed to the cache once abandoned, see
+    /// `push_abandoned`.
     ///
-    /// This function takes ownership of any data on disk at `sync.root` and
-    /// resets the `state` to `Blank`. This function is intended to
-    /// be called in the `drop` function of `sync`, so changing the state is
-    /// safe.
+    /// This function resets the `state` of `sync` to `Blank`. This function is
+    /// intended to be called in the `drop` function of `sync`, so changing the
+    /// state is safe.
     pub fn push(&mut self, sync: &mut IncompleteState) {
-        // We start by clearing the old entry
-        // This avoids any edge cases where we replace the cache with a new entry at the
-        // same height (and path)
         if let Some(ref entry) = self.entry {
             match sync.state {
-                DownloadState::Blank => {
+                DownloadState::Blank | DownloadState::Loading { .. } => {
                     // Keep what we have
                 }
                 _ => {
@@ -180,18 +180,8 @@ impl StateSyncCache {
         }
 
         match std::mem::replace(&mut sync.state, DownloadState::Blank) {
-            DownloadState::Loading {
-                meta_manifest: _,
-                manifest,
-                state_sync_file_group,
-                fetch_chunks,
-            } => {
-                if self.entry.is_some() {
-                    // The current cache is newer
-                    delete_folder(&self.log, &sync.root);
-                } else {
-                    self.push_inner(sync, manifest, fetch_chunks, state_sync_file_group);
-                }
+            DownloadState::Loading { .. } => {
+                // The scratchpad is kept for resumption, see `push_abandoned`
             }
             DownloadState::Complete(_) | DownloadState::Blank | DownloadState::Prep { .. } => {
                 // Nothing to cache
@@ -208,4 +198,38 @@ impl StateSyncCache {
             }
         }
     }
+
+    /// Passes the scratchpad at `root` of an abandoned state sync at `height`
+    /// to the cache, taking ownership of the data on disk.
+    ///
+    /// Returns false and leaves the scratchpad untouched if the cache already
+    /// contains a newer entry.
+    pub fn push_abandoned(
+        &mut self,
+        state_layout: &StateLayout,
+        height: Height,
+        root: &Path,
+        manifest: Manifest,
+        fetch_chunks: HashSet<usize>,
+        state_sync_file_group: FileGroupChunks,
+    ) -> bool {
+        if let Some(ref entry) = self.entry {
+            if entry.height > height {
+                return false;
+            }
+        }
+        // We start by clearing the old entry
+        // This avoids any edge cases where we replace the cache with a new entry at the
+        // same height (and path)
+        self.entry = None;
+        self.push_inner(
+            state_layout,
+            height,
+            root,
+            manifest,
+            fetch_chunks,
+            state_sync_file_group,
+        );
+        true
+    }
 }
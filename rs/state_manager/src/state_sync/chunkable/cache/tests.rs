@@ -149,6 +149,29 @@ fn incomplete_state_for_tests(
     result
 }
 
+/// Passes the scratchpad of the loading `sync` to the cache, as a state sync at
+/// another height does when abandoning it, and drops `sync`.
+fn abandon(env: &TestEnvironment, mut sync: IncompleteState) -> bool {
+    let pushed = match std::mem::replace(&mut sync.state, DownloadState::Blank) {
+        DownloadState::Loading {
+            meta_manifest: _,
+            manifest,
+            state_sync_file_group,
+            fetch_chunks,
+        } => env.cache.write().push_abandoned(
+            &env.state_layout,
+            sync.height,
+            &sync.root,
+            manifest,
+            fetch_chunks,
+            state_sync_file_group,
+        ),
+        _ => panic!("Only loading state syncs can be abandoned"),
+    };
+    drop(sync);
+    pushed
+}
+
 // Blank state syncs should never alter the cache
 #[test]
 fn blank_sync() {
@@ -170,8 +193,8 @@ fn blank_sync() {
     })
 }
 
-// Loading syncs should be cached, unless they are older than what's in the
-// cache Any data deleted from cache should be cleaned up properly on
+// Abandoned loading syncs should be cached, unless they are older than what's
+// in the cache Any data deleted from cache should be cleaned up properly on
 // disk.
 #[test]
 fn loading_sync() {
@@ -188,7 +211,7 @@ fn loading_sync() {
         // creating a directory with a dummy file.
         assert!(scratchpad.exists());
 
-        drop(sync);
+        abandon(&env, sync);
 
         assert!(!scratchpad.exists());
 
@@ -215,7 +238,7 @@ fn loading_sync() {
 
         assert!(scratchpad.exists());
 
-        drop(sync);
+        assert!(!abandon(&env, sync));
 
         assert!(!scratchpad.exists());
 
@@ -241,7 +264,7 @@ fn loading_sync() {
 
         assert!(scratchpad.exists());
 
-        drop(sync);
+        abandon(&env, sync);
 
         assert!(!scratchpad.exists());
 
@@ -269,7 +292,7 @@ fn loading_sync() {
         let scratchpad = sync.root.clone();
         assert!(scratchpad.exists());
 
-        drop(sync);
+        abandon(&env, sync);
 
         assert!(!scratchpad.exists());
         assert!(!old_cache_path.exists());
@@ -319,7 +342,7 @@ fn completed_sync() {
 
         let (state, _, _, _) = fake_loading(V1, 1);
         let sync = incomplete_state_for_tests(&env, Height::new(5), state);
-        drop(sync);
+        abandon(&env, sync);
 
         assert!(env.cache.read().get().is_some());
 
@@ -336,7 +359,7 @@ fn completed_sync() {
         // Populate cache again
         let (state, _, _, _) = fake_loading(V1, 1);
         let sync = incomplete_state_for_tests(&env, Height::new(5), state);
-        drop(sync);
+        abandon(&env, sync);
 
         // Can delete cache with completed sync at higher height
         let sync = incomplete_state_for_tests(&env, Height::new(6), complete);
@@ -364,9 +387,34 @@ fn existing_folder() {
         let file_path = cache_dir.join("empty_file");
         let mut _file = std::fs::File::create(&file_path).unwrap();
 
-        drop(sync);
+        abandon(&env, sync);
 
         assert!(!file_path.exists());
         assert!(env.cache.read().get().is_none());
     })
 }
+
+// Loading syncs keep their scratchpad and progress when dropped, so that they
+// can be resumed, and never alter the cache
+#[test]
+fn dropped_loading_sync_keeps_scratchpad() {
+    with_test_replica_logger(|log| {
+        let env = TestEnvironment::new(log);
+        let (state, _, _, _) = fake_loading(V1, 1);
+
+        let height = Height::new(5);
+        let sync = incomplete_state_for_tests(&env, height, state);
+        let scratchpad = sync.root.clone();
+        assert!(scratchpad.exists());
+
+        drop(sync);
+
+        assert!(scratchpad.join("1").exists());
+        assert!(env
+            .state_layout
+            .state_sync_progress(height)
+            .unwrap()
+            .exists());
+        assert!(env.cache.read().get().is_none());
+    })
+}
@@ -5,6 +5,7 @@ use crate::{
 };
 use crossbeam_channel::{unbounded, Sender};
 use ic_base_types::subnet_id_into_protobuf;
+use ic_config::flag_status::FlagStatus;
 use ic_logger::{error, fatal, info, ReplicaLogger};
 use ic_protobuf::state::{
     stats::v1::Stats,
@@ -12,12 +13,13 @@ use ic_protobuf::state::{
 };
 #[allow(unused)]
 use ic_replicated_state::{
-    canister_state::execution_state::SandboxMemory, CanisterState, NumWasmPages, PageMap,
-    ReplicatedState,
+    canister_state::execution_state::SandboxMemory,
+    page_map::{MergeCandidate, PersistenceError, StorageLayout},
+    CanisterState, NumWasmPages, PageMap, ReplicatedState,
 };
 use ic_state_layout::{
-    error::LayoutError, CanisterStateBits, CheckpointLayout, ExecutionStateBits, ReadOnly,
-    RwPolicy, StateLayout, TipHandler,
+    error::LayoutError, CanisterLayout, CanisterStateBits, CheckpointLayout, ExecutionStateBits,
+    PageMapLayout, ReadOnly, RwPolicy, StateLayout, TipHandler,
 };
 use ic_types::state_sync::{
     FILE_GROUP_CHUNK_ID_OFFSET, MANIFEST_CHUNK_ID_OFFSET, MAX_SUPPORTED_STATE_SYNC_VERSION,
@@ -32,7 +34,7 @@ use rand::{seq::IteratorRandom, Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
 use std::collections::BTreeSet;
 use std::os::unix::prelude::MetadataExt;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
@@ -80,6 +82,14 @@ pub(crate) enum TipRequest {
         height: Height,
         pagemaps: Vec<PageMapToFlush>,
     },
+    /// Copy the staged state of a canister migrated to this subnet into tip.
+    /// The staged copy is kept until the canister is part of a checkpoint.
+    /// State: !Empty
+    ImportCanister {
+        height: Height,
+        canister_id: CanisterId,
+        canister_layout: CanisterLayout<ReadOnly>,
+    },
     /// Reset tip folder to the checkpoint with given height.
     /// State: * -> ReadyForPageDeltas(checkpoint_layout.height())
     ResetTipTo {
@@ -96,6 +106,12 @@ pub(crate) enum TipRequest {
         height: Height,
         page_map_types: Vec<PageMapType>,
     },
+    /// Merge overlay files of the page maps in tip to bound their number.
+    /// State: ReadyForPageDeltas(h) -> ReadyForPageDeltas(height), height >= h
+    MergeOverlays {
+        height: Height,
+        page_map_types: Vec<PageMapType>,
+    },
     /// Compute manifest, store result into states and perist metadata as result.
     /// State: *
     ComputeManifest {
@@ -120,30 +136,20 @@ fn request_timer(metrics: &StateManagerMetrics, name: &str) -> HistogramTimer {
         .start_timer()
 }
 
-fn page_map_path(
-    log: &ReplicaLogger,
-    tip_handler: &mut TipHandler,
-    height: Height,
-    page_map_type: &PageMapType,
-) -> PathBuf {
-    page_map_type
-        .path(&tip_handler.tip(height).unwrap_or_else(|err| {
-            fatal!(log, "Failed to flush page map: {}", err);
-        }))
-        .unwrap_or_else(|err| {
-            fatal!(log, "Failed to get path for page map: {}", err);
-        })
-}
-
 pub(crate) fn spawn_tip_thread(
     log: ReplicaLogger,
     mut tip_handler: TipHandler,
     state_layout: StateLayout,
     metrics: StateManagerMetrics,
     malicious_flags: MaliciousFlags,
+    lsmt_storage: FlagStatus,
+    manifest_hashing_threads: u32,
 ) -> (JoinOnDrop<()>, Sender<TipRequest>) {
     let (tip_sender, tip_receiver) = unbounded();
     let mut thread_pool = scoped_threadpool::Pool::new(NUMBER_OF_CHECKPOINT_THREADS);
+    // Manifest computation hashes chunks of all files in parallel and gets its
+    // own pool, so that its thread budget can be configured independently.
+    let mut manifest_thread_pool = scoped_threadpool::Pool::new(manifest_hashing_threads.max(1));
     let mut tip_state = TipState::ReadyForPageDeltas(Height::from(0));
     // On top of tip state transitions, we enforce that each checkpoint gets manifest before we
     // create next one. Height(0) doesn't need manifest, so original state is true.
@@ -169,6 +175,42 @@ pub(crate) fn spawn_tip_thread(
                                     )
                                 });
                         }
+                        TipRequest::ImportCanister {
+                            height,
+                            canister_id,
+                            canister_layout,
+                        } => {
+                            debug_assert_ne!(tip_state, TipState::Empty);
+
+                            let _timer = request_timer(&metrics, "import_canister");
+                            tip_handler
+                                .import_canister(
+                                    &state_layout,
+                                    height,
+                                    &canister_id,
+                                    &canister_layout,
+                                )
+                                .unwrap_or_else(|err| {
+                                    fatal!(
+                                        log,
+                                        "Failed to import canister {} into tip @{}: {}",
+                                        canister_id,
+                                        height,
+                                        err
+                                    )
+                                });
+                            if lsmt_storage == FlagStatus::Disabled {
+                                let tip = tip_handler.tip(height).unwrap_or_else(|err| {
+                                    fatal!(log, "Failed to get tip @{} to merge: {}", height, err);
+                                });
+                                merge_overlays_into_base(
+                                    &log,
+                                    &tip,
+                                    &canister_page_map_types(&canister_id),
+                                    &mut thread_pool,
+                                );
+                            }
+                        }
                         TipRequest::TipToCheckpoint { height, sender } => {
                             debug_assert_eq!(tip_state, TipState::Serialized(height));
                             debug_assert!(have_latest_manifest);
@@ -229,6 +271,9 @@ pub(crate) fn spawn_tip_thread(
                                 _ => panic!("Unexpected tip state: {:?}", tip_state),
                             }
                             tip_state = TipState::ReadyForPageDeltas(height);
+                            let tip = tip_handler.tip(height).unwrap_or_else(|err| {
+                                fatal!(log, "Failed to flush page map: {}", err);
+                            });
                             parallel_map(
                                 &mut thread_pool,
                                 pagemaps.into_iter().map(
@@ -240,33 +285,30 @@ pub(crate) fn spawn_tip_thread(
                                         (
                                             truncate,
                                             page_map,
-                                            page_map_path(
-                                                &log,
-                                                &mut tip_handler,
-                                                height,
-                                                &page_map_type,
-                                            ),
+                                            page_map_type.layout(&tip).unwrap_or_else(|err| {
+                                                fatal!(
+                                                    log,
+                                                    "Failed to get layout for page map: {}",
+                                                    err
+                                                );
+                                            }),
                                         )
                                     },
                                 ),
-                                |(truncate, page_map, path)| {
+                                |(truncate, page_map, layout)| {
                                     if *truncate {
-                                        truncate_path(&log, path);
+                                        delete_page_map_files(&log, layout);
                                     }
                                     if page_map.is_some()
                                         && !page_map.as_ref().unwrap().unflushed_delta_is_empty()
                                     {
-                                        page_map
-                                            .as_ref()
-                                            .unwrap()
-                                            .persist_unflushed_delta(path)
-                                            .unwrap_or_else(|err| {
-                                                fatal!(
-                                                    log,
-                                                    "Failed to persist unflushed delta: {}",
-                                                    err
-                                                );
-                                            });
+                                        persist_unflushed_delta(
+                                            &log,
+                                            page_map.as_ref().unwrap(),
+                                            layout,
+                                            height,
+                                            lsmt_storage,
+                                        );
                                     }
                                 },
                             );
@@ -285,6 +327,8 @@ pub(crate) fn spawn_tip_thread(
                             serialize_to_tip(
                                 &log,
                                 &replicated_state,
+                                height,
+                                lsmt_storage,
                                 &tip_handler.tip(height).unwrap_or_else(|err| {
                                     fatal!(
                                         log,
@@ -316,6 +360,29 @@ pub(crate) fn spawn_tip_thread(
                                         err
                                     );
                                 });
+                            // The checkpoint may have been written with LSMT storage enabled,
+                            // e.g. by a previous run or by a state sync. Go back to the
+                            // single-file layout once here, so that flushes don't have to.
+                            if lsmt_storage == FlagStatus::Disabled {
+                                let height = checkpoint_layout.height();
+                                let tip = tip_handler.tip(height).unwrap_or_else(|err| {
+                                    fatal!(log, "Failed to get tip @{} to merge: {}", height, err);
+                                });
+                                let page_map_types = tip
+                                    .canister_ids()
+                                    .unwrap_or_else(|err| {
+                                        fatal!(log, "Failed to list canisters in tip: {}", err);
+                                    })
+                                    .iter()
+                                    .flat_map(canister_page_map_types)
+                                    .collect::<Vec<_>>();
+                                merge_overlays_into_base(
+                                    &log,
+                                    &tip,
+                                    &page_map_types,
+                                    &mut thread_pool,
+                                );
+                            }
                         }
                         TipRequest::DefragTip {
                             height,
@@ -338,6 +405,19 @@ pub(crate) fn spawn_tip_thread(
                             });
                         }
 
+                        TipRequest::MergeOverlays {
+                            height,
+                            page_map_types,
+                        } => {
+                            debug_assert_ne!(tip_state, TipState::Empty);
+                            tip_state = TipState::ReadyForPageDeltas(height);
+                            let _timer = request_timer(&metrics, "merge_overlays");
+                            let tip = tip_handler.tip(height).unwrap_or_else(|err| {
+                                fatal!(log, "Failed to get tip @{} to merge: {}", height, err);
+                            });
+                            merge_overlays(&log, &tip, &page_map_types, &mut thread_pool);
+                        }
+
                         TipRequest::Wait { sender } => {
                             let _timer = request_timer(&metrics, "wait");
                             let _ = sender.send(());
@@ -350,7 +430,7 @@ pub(crate) fn spawn_tip_thread(
                             persist_metadata_guard,
                         } => {
                             handle_compute_manifest_request(
-                                &mut thread_pool,
+                                &mut manifest_thread_pool,
                                 &metrics,
                                 &log,
                                 &states,
@@ -374,6 +454,8 @@ pub(crate) fn spawn_tip_thread(
 fn serialize_to_tip(
     log: &ReplicaLogger,
     state: &ReplicatedState,
+    height: Height,
+    lsmt_storage: FlagStatus,
     tip: &CheckpointLayout<RwPolicy<TipHandler>>,
     thread_pool: &mut scoped_threadpool::Pool,
 ) -> Result<(), CheckpointError> {
@@ -411,7 +493,7 @@ fn serialize_to_tip(
     })?;
 
     let results = parallel_map(thread_pool, state.canisters_iter(), |canister_state| {
-        serialize_canister_to_tip(log, canister_state, tip)
+        serialize_canister_to_tip(log, canister_state, tip, height, lsmt_storage)
     });
 
     for result in results.into_iter() {
@@ -425,6 +507,8 @@ fn serialize_canister_to_tip(
     log: &ReplicaLogger,
     canister_state: &CanisterState,
     tip: &CheckpointLayout<RwPolicy<TipHandler>>,
+    height: Height,
+    lsmt_storage: FlagStatus,
 ) -> Result<(), CheckpointError> {
     let canister_layout = tip.canister(&canister_state.canister_id())?;
     canister_layout
@@ -456,14 +540,18 @@ fn serialize_canister_to_tip(
                         .serialize(&execution_state.wasm_binary.binary)?;
                 }
             }
-            execution_state
-                .wasm_memory
-                .page_map
-                .persist_delta(&canister_layout.vmemory_0())?;
-            execution_state
-                .stable_memory
-                .page_map
-                .persist_delta(&canister_layout.stable_memory_blob())?;
+            persist_delta(
+                &execution_state.wasm_memory.page_map,
+                &canister_layout.vmemory_0_layout(),
+                height,
+                lsmt_storage,
+            )?;
+            persist_delta(
+                &execution_state.stable_memory.page_map,
+                &canister_layout.stable_memory_layout(),
+                height,
+                lsmt_storage,
+            )?;
 
             Some(ExecutionStateBits {
                 exported_globals: execution_state.exported_globals.clone(),
@@ -476,18 +564,19 @@ fn serialize_canister_to_tip(
             })
         }
         None => {
-            truncate_path(log, &canister_layout.vmemory_0());
-            truncate_path(log, &canister_layout.stable_memory_blob());
+            delete_page_map_files(log, &canister_layout.vmemory_0_layout());
+            delete_page_map_files(log, &canister_layout.stable_memory_layout());
             canister_layout.wasm().try_delete_file()?;
             None
         }
     };
 
-    canister_state
-        .system_state
-        .wasm_chunk_store
-        .page_map()
-        .persist_delta(&canister_layout.wasm_chunk_store())?;
+    persist_delta(
+        canister_state.system_state.wasm_chunk_store.page_map(),
+        &canister_layout.wasm_chunk_store_layout(),
+        height,
+        lsmt_storage,
+    )?;
 
     // Priority credit must be zero at this point
     assert_eq!(canister_state.scheduler_state.priority_credit.get(), 0);
@@ -628,18 +717,122 @@ pub fn defrag_tip(
     Ok(())
 }
 
-fn truncate_path(log: &ReplicaLogger, path: &Path) {
-    if let Err(err) = nix::unistd::truncate(path, 0) {
-        // It's OK if the file doesn't exist, everything else is a fatal error.
-        if err != nix::errno::Errno::ENOENT {
-            fatal!(
-                log,
-                "failed to truncate page map stored at {}: {}",
-                path.display(),
-                err
-            )
+/// Persists the delta of `page_map` that is not yet on disk as of a
+/// checkpoint at `height`.
+///
+/// With LSMT storage enabled the delta is written as a new overlay. Otherwise
+/// it is applied to the base file; overlays left over from a previous run with
+/// LSMT storage have already been merged into it when the tip was reset.
+fn persist_delta(
+    page_map: &PageMap,
+    layout: &PageMapLayout<RwPolicy<TipHandler>>,
+    height: Height,
+    lsmt_storage: FlagStatus,
+) -> Result<(), CheckpointError> {
+    match lsmt_storage {
+        FlagStatus::Enabled => {
+            // In the regular flow all deltas are flushed and stripped before the
+            // checkpoint is serialized, so there is nothing to write.
+            if !page_map.page_delta_is_empty() {
+                page_map.persist_delta_as_overlay(layout, height)?;
+            }
+        }
+        FlagStatus::Disabled => page_map.persist_delta(&layout.base())?,
+    }
+    Ok(())
+}
+
+/// Same as `persist_delta`, but for the unflushed delta of `page_map`.
+fn persist_unflushed_delta(
+    log: &ReplicaLogger,
+    page_map: &PageMap,
+    layout: &PageMapLayout<RwPolicy<TipHandler>>,
+    height: Height,
+    lsmt_storage: FlagStatus,
+) {
+    let result = match lsmt_storage {
+        FlagStatus::Enabled => page_map.persist_unflushed_delta_as_overlay(layout, height),
+        FlagStatus::Disabled => page_map.persist_unflushed_delta(&layout.base()),
+    };
+    result.unwrap_or_else(|err| {
+        fatal!(log, "Failed to persist unflushed delta: {}", err);
+    });
+}
+
+/// All the page maps a canister can have.
+fn canister_page_map_types(canister_id: &CanisterId) -> [PageMapType; 3] {
+    [
+        PageMapType::WasmMemory(*canister_id),
+        PageMapType::StableMemory(*canister_id),
+        PageMapType::WasmChunkStore(*canister_id),
+    ]
+}
+
+/// Merges all overlays of the given page maps in `tip` into their base files
+/// in parallel, going back to the single-file layout.
+fn merge_overlays_into_base(
+    log: &ReplicaLogger,
+    tip: &CheckpointLayout<RwPolicy<TipHandler>>,
+    page_map_types: &[PageMapType],
+    thread_pool: &mut scoped_threadpool::Pool,
+) {
+    let num_merges = apply_merges(
+        log,
+        tip,
+        page_map_types,
+        thread_pool,
+        MergeCandidate::full_to_base,
+    );
+    if num_merges > 0 {
+        info!(
+            log,
+            "Merged overlays of {} page maps into base files", num_merges
+        );
+    }
+}
+
+/// Merges overlays of the given page maps in `tip` in parallel, so that the
+/// number of overlays of every page map stays bounded.
+fn merge_overlays(
+    log: &ReplicaLogger,
+    tip: &CheckpointLayout<RwPolicy<TipHandler>>,
+    page_map_types: &[PageMapType],
+    thread_pool: &mut scoped_threadpool::Pool,
+) {
+    let num_merges = apply_merges(log, tip, page_map_types, thread_pool, MergeCandidate::new);
+    info!(log, "Merged overlays of {} page maps in tip", num_merges);
+}
+
+/// Applies the merge chosen by `candidate` to each of the given page maps in
+/// `tip` in parallel and returns the number of merges performed.
+fn apply_merges(
+    log: &ReplicaLogger,
+    tip: &CheckpointLayout<RwPolicy<TipHandler>>,
+    page_map_types: &[PageMapType],
+    thread_pool: &mut scoped_threadpool::Pool,
+    candidate: fn(&dyn StorageLayout) -> Result<Option<MergeCandidate>, PersistenceError>,
+) -> usize {
+    let results = parallel_map(thread_pool, page_map_types.iter(), |page_map_type| {
+        let layout = page_map_type.layout(tip).map_err(|err| err.to_string())?;
+        match candidate(&layout).map_err(|err| err.to_string())? {
+            Some(merge) => merge.apply().map(|()| 1).map_err(|err| err.to_string()),
+            None => Ok(0),
+        }
+    });
+    let mut num_merges = 0;
+    for result in results {
+        match result {
+            Ok(n) => num_merges += n,
+            Err(err) => fatal!(log, "Failed to merge overlays in tip: {}", err),
         }
     }
+    num_merges
+}
+
+fn delete_page_map_files(log: &ReplicaLogger, layout: &PageMapLayout<RwPolicy<TipHandler>>) {
+    layout.delete_files().unwrap_or_else(|err| {
+        fatal!(log, "Failed to delete page map files: {}", err);
+    });
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -815,8 +1008,15 @@ mod test {
             let metrics_registry = ic_metrics::MetricsRegistry::new();
             let metrics = StateManagerMetrics::new(&metrics_registry);
             let tip_handler = layout.capture_tip_handler();
-            let (_h, _s) =
-                spawn_tip_thread(log, tip_handler, layout, metrics, MaliciousFlags::default());
+            let (_h, _s) = spawn_tip_thread(
+                log,
+                tip_handler,
+                layout,
+                metrics,
+                MaliciousFlags::default(),
+                FlagStatus::Disabled,
+                NUMBER_OF_CHECKPOINT_THREADS,
+            );
         });
     }
 
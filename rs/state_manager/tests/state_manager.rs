@@ -1,5 +1,5 @@
 use ic_certification_version::{CertificationVersion::V11, CURRENT_CERTIFICATION_VERSION};
-use ic_config::state_manager::Config;
+use ic_config::{flag_status::FlagStatus, state_manager::Config};
 use ic_crypto_tree_hash::{
     flatmap, sparse_labeled_tree_from_paths, Label, LabeledTree, MixedHashTree, Path as LabelPath,
 };
@@ -11,16 +11,25 @@ use ic_interfaces_certified_stream_store::{CertifiedStreamStore, EncodeStreamErr
 use ic_interfaces_state_manager::*;
 use ic_logger::replica_logger::no_op_logger;
 use ic_metrics::MetricsRegistry;
+use ic_registry_routing_table::{
+    CanisterIdRange, CanisterImports, CanisterMigrations, RoutingTable,
+};
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    page_map::PageIndex, testing::ReplicatedStateTesting, Memory, NetworkTopology, NumWasmPages,
-    PageMap, ReplicatedState, Stream, SubnetTopology,
+    page_map::{PageIndex, StorageLayout},
+    testing::ReplicatedStateTesting,
+    CanisterStatus, Memory, NetworkTopology, NumWasmPages, PageMap, ReplicatedState, Stream,
+    SubnetTopology,
 };
 use ic_state_layout::{CheckpointLayout, ReadOnly, SYSTEM_METADATA_FILE};
 use ic_state_machine_tests::{StateMachine, StateMachineBuilder};
-use ic_state_manager::manifest::{build_meta_manifest, manifest_from_path, validate_manifest};
-use ic_state_manager::{DirtyPageMap, FileType, PageMapType, StateManagerImpl};
+use ic_state_manager::manifest::{
+    build_meta_manifest, canister_state_hash, manifest_from_path, validate_manifest,
+};
+use ic_state_manager::{
+    state_sync::StateSync, DirtyPageMap, FileType, PageMapType, StateManagerImpl,
+};
 use ic_sys::PAGE_SIZE;
 use ic_test_utilities::{
     consensus::fake::FakeVerifier,
@@ -1049,6 +1058,194 @@ fn first_manifest_after_restart_is_incremental() {
     });
 }
 
+#[test]
+fn manifest_debug_check_recomputes_all_reused_chunks() {
+    let tmp = tmpdir("sm");
+    let mut config = Config::new(tmp.path().into());
+    config.manifest_debug_check = FlagStatus::Enabled;
+
+    with_test_replica_logger(|log| {
+        let metrics = MetricsRegistry::new();
+        let state_manager = StateManagerImpl::new(
+            Arc::new(FakeVerifier::new()),
+            subnet_test_id(42),
+            SubnetType::Application,
+            log,
+            &metrics,
+            &config,
+            None,
+            ic_types::malicious_flags::MaliciousFlags::default(),
+        );
+
+        let (_height, mut state) = state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(1));
+        let execution_state = state
+            .canister_state_mut(&canister_test_id(1))
+            .unwrap()
+            .execution_state
+            .as_mut()
+            .unwrap();
+        execution_state.wasm_memory.page_map.update(&[
+            (PageIndex::new(1), &[1u8; PAGE_SIZE]),
+            (PageIndex::new(300), &[1u8; PAGE_SIZE]),
+        ]);
+        state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+        wait_for_checkpoint(&state_manager, height(1));
+
+        let (_height, mut state) = state_manager.take_tip();
+        let execution_state = state
+            .canister_state_mut(&canister_test_id(1))
+            .unwrap()
+            .execution_state
+            .as_mut()
+            .unwrap();
+        execution_state
+            .wasm_memory
+            .page_map
+            .update(&[(PageIndex::new(1), &[2u8; PAGE_SIZE])]);
+        state_manager.commit_and_certify(state, height(2), CertificationScope::Full);
+        wait_for_checkpoint(&state_manager, height(2));
+
+        // Every chunk that could have been reused was hashed and compared
+        // instead, and all of them matched.
+        let chunk_bytes = fetch_int_counter_vec(&metrics, "state_manager_manifest_chunk_bytes");
+        let reused_key = maplit::btreemap! {"type".to_string() => "reused".to_string()};
+        let hashed_and_compared_key =
+            maplit::btreemap! {"type".to_string() => "hashed_and_compared".to_string()};
+        assert_eq!(0, chunk_bytes[&reused_key]);
+        assert_ne!(0, chunk_bytes[&hashed_and_compared_key]);
+        assert_error_counters(&metrics);
+    });
+}
+
+#[test]
+fn lsmt_storage_writes_overlays_and_preserves_memory_across_restarts() {
+    let tmp = tmpdir("sm");
+    let mut config = Config::new(tmp.path().into());
+    config.lsmt_storage = FlagStatus::Enabled;
+
+    with_test_replica_logger(|log| {
+        let make_state_manager = || {
+            StateManagerImpl::new(
+                Arc::new(FakeVerifier::new()),
+                subnet_test_id(42),
+                SubnetType::Application,
+                log.clone(),
+                &MetricsRegistry::new(),
+                &config,
+                None,
+                ic_types::malicious_flags::MaliciousFlags::default(),
+            )
+        };
+
+        let state_manager = make_state_manager();
+        let (_height, mut state) = state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(100));
+        let execution_state = state
+            .canister_state_mut(&canister_test_id(100))
+            .unwrap()
+            .execution_state
+            .as_mut()
+            .unwrap();
+        execution_state.wasm_memory.page_map.update(&[
+            (PageIndex::new(1), &[1u8; PAGE_SIZE]),
+            (PageIndex::new(300), &[1u8; PAGE_SIZE]),
+        ]);
+        state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+
+        let (_height, mut state) = state_manager.take_tip();
+        let execution_state = state
+            .canister_state_mut(&canister_test_id(100))
+            .unwrap()
+            .execution_state
+            .as_mut()
+            .unwrap();
+        execution_state
+            .wasm_memory
+            .page_map
+            .update(&[(PageIndex::new(1), &[2u8; PAGE_SIZE])]);
+        state_manager.commit_and_certify(state, height(2), CertificationScope::Full);
+        wait_for_checkpoint(&state_manager, height(2));
+
+        let vmemory_layout = state_manager
+            .state_layout()
+            .checkpoint(height(2))
+            .unwrap()
+            .canister(&canister_test_id(100))
+            .unwrap()
+            .vmemory_0_layout();
+        assert!(!vmemory_layout.existing_overlays().unwrap().is_empty());
+
+        drop(state_manager);
+        let state_manager = make_state_manager();
+        let (recovered_height, state) = state_manager.take_tip();
+        assert_eq!(height(2), recovered_height);
+
+        let page_map = &state
+            .canister_state(&canister_test_id(100))
+            .unwrap()
+            .execution_state
+            .as_ref()
+            .unwrap()
+            .wasm_memory
+            .page_map;
+        assert_eq!(page_map.get_page(PageIndex::new(1)), &[2u8; PAGE_SIZE]);
+        assert_eq!(page_map.get_page(PageIndex::new(300)), &[1u8; PAGE_SIZE]);
+        assert_eq!(page_map.get_page(PageIndex::new(2)), &[0u8; PAGE_SIZE]);
+        drop(state);
+        drop(state_manager);
+
+        // Disabling LSMT storage merges the overlays back into the base file
+        let mut config = config.clone();
+        config.lsmt_storage = FlagStatus::Disabled;
+        let state_manager = StateManagerImpl::new(
+            Arc::new(FakeVerifier::new()),
+            subnet_test_id(42),
+            SubnetType::Application,
+            log.clone(),
+            &MetricsRegistry::new(),
+            &config,
+            None,
+            ic_types::malicious_flags::MaliciousFlags::default(),
+        );
+        let (_height, mut state) = state_manager.take_tip();
+        let execution_state = state
+            .canister_state_mut(&canister_test_id(100))
+            .unwrap()
+            .execution_state
+            .as_mut()
+            .unwrap();
+        execution_state
+            .wasm_memory
+            .page_map
+            .update(&[(PageIndex::new(2), &[3u8; PAGE_SIZE])]);
+        state_manager.commit_and_certify(state, height(3), CertificationScope::Full);
+        wait_for_checkpoint(&state_manager, height(3));
+
+        let vmemory_layout = state_manager
+            .state_layout()
+            .checkpoint(height(3))
+            .unwrap()
+            .canister(&canister_test_id(100))
+            .unwrap()
+            .vmemory_0_layout();
+        assert!(vmemory_layout.existing_overlays().unwrap().is_empty());
+
+        let (_height, state) = state_manager.take_tip();
+        let page_map = &state
+            .canister_state(&canister_test_id(100))
+            .unwrap()
+            .execution_state
+            .as_ref()
+            .unwrap()
+            .wasm_memory
+            .page_map;
+        assert_eq!(page_map.get_page(PageIndex::new(1)), &[2u8; PAGE_SIZE]);
+        assert_eq!(page_map.get_page(PageIndex::new(300)), &[1u8; PAGE_SIZE]);
+        assert_eq!(page_map.get_page(PageIndex::new(2)), &[3u8; PAGE_SIZE]);
+    });
+}
+
 #[test]
 fn can_filter_by_certification_mask() {
     state_manager_test(|_metrics, state_manager| {
@@ -2245,6 +2442,184 @@ fn can_state_sync_from_cache() {
     })
 }
 
+#[test]
+fn state_sync_resumes_from_scratchpad_after_restart() {
+    state_manager_test_with_state_sync(|_src_metrics, src_state_manager, src_state_sync| {
+        let (_height, mut state) = src_state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(100));
+        let time_source = ic_test_utilities::FastForwardTimeSource::new();
+
+        src_state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+        let hash = wait_for_checkpoint(&*src_state_manager, height(1));
+        let id = StateSyncArtifactId {
+            height: height(1),
+            hash,
+        };
+        let state = src_state_manager.get_latest_state().take();
+        let msg = src_state_sync
+            .get_validated_by_identifier(&id)
+            .expect("failed to get state sync messages");
+
+        let tmp = tmpdir("sm");
+        let config = Config::new(tmp.path().into());
+        with_test_replica_logger(|log| {
+            let make_state_sync = || {
+                let metrics = MetricsRegistry::new();
+                let state_manager = Arc::new(StateManagerImpl::new(
+                    Arc::new(FakeVerifier::new()),
+                    subnet_test_id(42),
+                    SubnetType::Application,
+                    log.clone(),
+                    &metrics,
+                    &config,
+                    None,
+                    ic_types::malicious_flags::MaliciousFlags::default(),
+                ));
+                let state_sync = StateSync::new(state_manager.clone(), log.clone());
+                (metrics, state_manager, state_sync)
+            };
+
+            let (_dst_metrics, dst_state_manager, dst_state_sync) = make_state_sync();
+            let mut chunkable = dst_state_sync.create_chunkable_state(&id);
+            let result = pipe_meta_manifest(&msg, &mut *chunkable, false);
+            assert!(matches!(result, Err(StateSyncErrorCode::ChunksMoreNeeded)));
+            let result = pipe_manifest(&msg, &mut *chunkable, false);
+            assert!(matches!(result, Err(StateSyncErrorCode::ChunksMoreNeeded)));
+            let chunks_to_download: HashSet<ChunkId> = chunkable.chunks_to_download().collect();
+            assert!(!chunks_to_download.is_empty());
+
+            // Simulate a crash: the incomplete state is never dropped.
+            std::mem::forget(chunkable);
+            drop(dst_state_sync);
+            drop(dst_state_manager);
+
+            let (dst_metrics, dst_state_manager, dst_state_sync) = make_state_sync();
+            let chunkable = dst_state_sync.create_chunkable_state(&id);
+
+            // The sync resumes in the loading phase without fetching the
+            // meta-manifest and the manifest again.
+            assert_eq!(
+                chunks_to_download,
+                chunkable.chunks_to_download().collect::<HashSet<_>>()
+            );
+
+            let dst_msg = pipe_state_sync(msg.clone(), chunkable);
+            dst_state_sync.process_changes(
+                time_source.as_ref(),
+                vec![UnvalidatedArtifact {
+                    message: dst_msg,
+                    peer_id: node_test_id(0),
+                    timestamp: mock_time(),
+                }],
+            );
+
+            let recovered_state = dst_state_manager
+                .get_state_at(height(1))
+                .expect("Destination state manager didn't receive the state")
+                .take();
+            assert_eq!(height(1), dst_state_manager.latest_state_height());
+            assert_eq!(state, recovered_state);
+
+            assert_error_counters(&dst_metrics);
+            assert_no_remaining_chunks(&dst_metrics);
+            assert!(!dst_state_manager
+                .state_layout()
+                .state_sync_progress(height(1))
+                .unwrap()
+                .exists());
+        });
+    })
+}
+
+#[test]
+fn state_sync_resumes_from_scratchpad_after_shutdown() {
+    state_manager_test_with_state_sync(|_src_metrics, src_state_manager, src_state_sync| {
+        let (_height, mut state) = src_state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(100));
+        let time_source = ic_test_utilities::FastForwardTimeSource::new();
+
+        src_state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+        let hash = wait_for_checkpoint(&*src_state_manager, height(1));
+        let id = StateSyncArtifactId {
+            height: height(1),
+            hash,
+        };
+        let state = src_state_manager.get_latest_state().take();
+        let msg = src_state_sync
+            .get_validated_by_identifier(&id)
+            .expect("failed to get state sync messages");
+
+        let tmp = tmpdir("sm");
+        let config = Config::new(tmp.path().into());
+        with_test_replica_logger(|log| {
+            let make_state_sync = || {
+                let metrics = MetricsRegistry::new();
+                let state_manager = Arc::new(StateManagerImpl::new(
+                    Arc::new(FakeVerifier::new()),
+                    subnet_test_id(42),
+                    SubnetType::Application,
+                    log.clone(),
+                    &metrics,
+                    &config,
+                    None,
+                    ic_types::malicious_flags::MaliciousFlags::default(),
+                ));
+                let state_sync = StateSync::new(state_manager.clone(), log.clone());
+                (metrics, state_manager, state_sync)
+            };
+
+            let (_dst_metrics, dst_state_manager, dst_state_sync) = make_state_sync();
+            let mut chunkable = dst_state_sync.create_chunkable_state(&id);
+            let result = pipe_meta_manifest(&msg, &mut *chunkable, false);
+            assert!(matches!(result, Err(StateSyncErrorCode::ChunksMoreNeeded)));
+            let result = pipe_manifest(&msg, &mut *chunkable, false);
+            assert!(matches!(result, Err(StateSyncErrorCode::ChunksMoreNeeded)));
+            let chunks_to_download: HashSet<ChunkId> = chunkable.chunks_to_download().collect();
+            assert!(!chunks_to_download.is_empty());
+
+            // Shut down gracefully: the incomplete state is dropped first.
+            drop(chunkable);
+            drop(dst_state_sync);
+            drop(dst_state_manager);
+
+            let (dst_metrics, dst_state_manager, dst_state_sync) = make_state_sync();
+            let chunkable = dst_state_sync.create_chunkable_state(&id);
+
+            // The sync resumes in the loading phase without fetching the
+            // meta-manifest and the manifest again.
+            assert_eq!(
+                chunks_to_download,
+                chunkable.chunks_to_download().collect::<HashSet<_>>()
+            );
+
+            let dst_msg = pipe_state_sync(msg.clone(), chunkable);
+            dst_state_sync.process_changes(
+                time_source.as_ref(),
+                vec![UnvalidatedArtifact {
+                    message: dst_msg,
+                    peer_id: node_test_id(0),
+                    timestamp: mock_time(),
+                }],
+            );
+
+            let recovered_state = dst_state_manager
+                .get_state_at(height(1))
+                .expect("Destination state manager didn't receive the state")
+                .take();
+            assert_eq!(height(1), dst_state_manager.latest_state_height());
+            assert_eq!(state, recovered_state);
+
+            assert_error_counters(&dst_metrics);
+            assert_no_remaining_chunks(&dst_metrics);
+            assert!(!dst_state_manager
+                .state_layout()
+                .state_sync_progress(height(1))
+                .unwrap()
+                .exists());
+        });
+    })
+}
+
 #[test]
 fn can_state_sync_after_aborting_in_prep_phase() {
     state_manager_test_with_state_sync(|src_metrics, src_state_manager, src_state_sync| {
@@ -3161,62 +3536,62 @@ fn can_get_dirty_pages() {
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::PageMap(PageMapType::WasmMemory(canister_test_id(80))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::PageMap(PageMapType::StableMemory(canister_test_id(80))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::PageMap(PageMapType::WasmMemory(canister_test_id(90))),
-                page_delta_indices: vec![PageIndex::new(1), PageIndex::new(300)],
+                dirty_chunk_indices: vec![0, 1],
             },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::PageMap(PageMapType::StableMemory(canister_test_id(90))),
-                page_delta_indices: vec![PageIndex::new(1), PageIndex::new(300)],
+                dirty_chunk_indices: vec![0, 1],
             },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::PageMap(PageMapType::WasmMemory(canister_test_id(100))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::PageMap(PageMapType::StableMemory(canister_test_id(100))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(80))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(90))),
-                page_delta_indices: vec![PageIndex::new(1), PageIndex::new(300)],
+                dirty_chunk_indices: vec![0, 1],
             },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(100))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::WasmBinary(canister_test_id(80)),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::WasmBinary(canister_test_id(90)),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::WasmBinary(canister_test_id(100)),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
         ];
 
@@ -3242,52 +3617,52 @@ fn can_get_dirty_pages() {
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::PageMap(PageMapType::WasmMemory(canister_test_id(80))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::PageMap(PageMapType::StableMemory(canister_test_id(80))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::PageMap(PageMapType::WasmMemory(canister_test_id(90))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::PageMap(PageMapType::StableMemory(canister_test_id(90))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::PageMap(PageMapType::StableMemory(canister_test_id(100))),
-                page_delta_indices: vec![PageIndex::new(1), PageIndex::new(300)],
+                dirty_chunk_indices: vec![0, 1],
             },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(80))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(90))),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(100))),
-                page_delta_indices: vec![PageIndex::new(1), PageIndex::new(300)],
+                dirty_chunk_indices: vec![0, 1],
             },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::WasmBinary(canister_test_id(80)),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::WasmBinary(canister_test_id(90)),
-                page_delta_indices: vec![],
+                dirty_chunk_indices: vec![],
             },
         ];
 
@@ -4578,6 +4953,92 @@ fn tip_is_initialized_correctly() {
     });
 }
 
+#[test]
+fn rerouted_canister_is_imported_from_staged_state() {
+    let canister_id: CanisterId = canister_test_id(100);
+    let source_subnet = subnet_test_id(1);
+    let own_subnet = subnet_test_id(42);
+
+    state_manager_test(|_metrics, source_state_manager| {
+        // Checkpoint the stopped canister on the source subnet.
+        let (_height, mut state) = source_state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_id);
+        state
+            .canister_state_mut(&canister_id)
+            .unwrap()
+            .system_state
+            .status = CanisterStatus::Stopped;
+        source_state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+        wait_for_checkpoint(&source_state_manager, height(1));
+        let source_canister_layout = source_state_manager
+            .state_layout()
+            .checkpoint(height(1))
+            .unwrap()
+            .canister(&canister_id)
+            .unwrap();
+
+        state_manager_test(|_metrics, state_manager| {
+            // Stage the canister state, as the canister migration tool does.
+            let staged_path = state_manager
+                .state_layout()
+                .canister_imports()
+                .join(hex::encode(canister_id.get_ref().as_slice()));
+            std::fs::create_dir_all(&staged_path).unwrap();
+            for entry in std::fs::read_dir(source_canister_layout.raw_path()).unwrap() {
+                let path = entry.unwrap().path();
+                std::fs::copy(&path, staged_path.join(path.file_name().unwrap())).unwrap();
+            }
+
+            // Nothing is imported before the canister is rerouted.
+            let (_height, mut state) = state_manager.take_tip();
+            assert!(state.canister_state(&canister_id).is_none());
+
+            let range = CanisterIdRange {
+                start: canister_id,
+                end: canister_id,
+            };
+            let mut routing_table = RoutingTable::new();
+            routing_table.insert(range, own_subnet).unwrap();
+            let mut canister_migrations = CanisterMigrations::new();
+            canister_migrations
+                .insert_ranges(vec![range].try_into().unwrap(), source_subnet, own_subnet)
+                .unwrap();
+            state.metadata.network_topology.routing_table = Arc::new(routing_table);
+            state.metadata.network_topology.canister_migrations = Arc::new(canister_migrations);
+            state_manager.commit_and_certify(state, height(1), CertificationScope::Metadata);
+
+            // Nor before the hash of its exported state is recorded.
+            let (_height, mut state) = state_manager.take_tip();
+            assert!(state.canister_state(&canister_id).is_none());
+
+            let mut canister_imports = CanisterImports::new();
+            canister_imports.insert(canister_id, canister_state_hash(&staged_path).unwrap());
+            state.metadata.network_topology.canister_imports = Arc::new(canister_imports);
+            state_manager.commit_and_certify(state, height(2), CertificationScope::Metadata);
+
+            let (_height, state) = state_manager.take_tip();
+            let canister = state.canister_state(&canister_id).unwrap();
+            assert_eq!(canister.system_state.status, CanisterStatus::Stopped);
+            // The staged state is kept until the imported canister is part of
+            // a checkpoint.
+            assert!(staged_path.exists());
+
+            state_manager.commit_and_certify(state, height(3), CertificationScope::Full);
+            wait_for_checkpoint(&state_manager, height(3));
+            assert_eq!(
+                state_manager
+                    .state_layout()
+                    .checkpoint(height(3))
+                    .unwrap()
+                    .canister_ids()
+                    .unwrap(),
+                vec![canister_id]
+            );
+            assert!(!staged_path.exists());
+        });
+    });
+}
+
 #[test]
 fn can_recover_ingress_history() {
     state_manager_test(|_metrics, state_manager| {
@@ -538,6 +538,9 @@ pub struct SandboxSafeSystemState {
     next_callback_id: Option<u64>,
     available_request_slots: BTreeMap<CanisterId, usize>,
     ic00_available_request_slots: usize,
+    // The maximum number of requests that may be pushed in total, across all
+    // destinations. See `limit_output_requests()`.
+    available_output_requests: usize,
     ic00_aliases: BTreeSet<CanisterId>,
     global_timer: CanisterTimer,
     canister_version: u64,
@@ -587,6 +590,7 @@ impl SandboxSafeSystemState {
             next_callback_id,
             available_request_slots,
             ic00_available_request_slots,
+            available_output_requests: usize::MAX,
             ic00_aliases,
             global_timer,
             canister_version,
@@ -710,6 +714,13 @@ impl SandboxSafeSystemState {
             .push(CallbackUpdate::Unregister(id))
     }
 
+    /// Limits the total number of requests that can be pushed during the
+    /// execution, across all destinations, to `available_output_requests`
+    /// (e.g. the space left in the canister's output queues).
+    pub fn limit_output_requests(&mut self, available_output_requests: usize) {
+        self.available_output_requests = available_output_requests;
+    }
+
     /// Computes the current main balance of the canister based
     /// on the initial value and the changes during the execution.
     pub(super) fn cycles_balance(&self) -> Cycles {
@@ -878,6 +889,10 @@ impl SandboxSafeSystemState {
             Err(_) => return Err(msg),
         };
 
+        if self.system_state_changes.requests.len() >= self.available_output_requests {
+            return Err(msg);
+        }
+
         // If the request is targeted to IC_00 or one of the known subnets
         // count it towards the available slots for IC_00 requests.
         if self.ic00_aliases.contains(&msg.receiver) {
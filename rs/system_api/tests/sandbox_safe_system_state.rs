@@ -166,6 +166,51 @@ fn push_output_request_succeeds_with_enough_cycles() {
     );
 }
 
+#[test]
+fn push_output_request_respects_output_requests_limit() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new()
+        .with_max_num_instructions(MAX_NUM_INSTRUCTIONS)
+        .build();
+
+    let system_state = SystemState::new_running_for_testing(
+        canister_test_id(0),
+        user_test_id(1).get(),
+        INITIAL_CYCLES,
+        NumSeconds::from(100_000),
+    );
+
+    let mut sandbox_safe_system_state = SandboxSafeSystemState::new(
+        &system_state,
+        cycles_account_manager,
+        &NetworkTopology::default(),
+        SchedulerConfig::application_subnet().dirty_page_overhead,
+        ComputeAllocation::default(),
+    );
+    sandbox_safe_system_state.limit_output_requests(2);
+
+    // The limit applies across all destinations.
+    for receiver in 1..=3 {
+        let request = RequestBuilder::default()
+            .sender(canister_test_id(0))
+            .receiver(canister_test_id(receiver))
+            .build();
+        let expected = if receiver <= 2 {
+            Ok(())
+        } else {
+            Err(request.clone())
+        };
+        assert_eq!(
+            sandbox_safe_system_state.push_output_request(
+                NumBytes::from(0),
+                request,
+                Cycles::zero(),
+                Cycles::zero(),
+            ),
+            expected
+        );
+    }
+}
+
 #[test]
 fn correct_charging_source_canister_for_a_request() {
     let subnet_type = SubnetType::Application;
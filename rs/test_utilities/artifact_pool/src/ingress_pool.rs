@@ -4,7 +4,7 @@ use ic_interfaces::{
     artifact_pool::{ChangeResult, MutablePool, UnvalidatedArtifact},
     ingress_pool::{
         ChangeSet, IngressPool, IngressPoolObject, IngressPoolSelect, IngressPoolThrottler,
-        PoolSection, SelectResult, UnvalidatedIngressArtifact, ValidatedIngressArtifact,
+        PoolSection, UnvalidatedIngressArtifact, ValidatedIngressArtifact,
     },
     time_source::TimeSource,
 };
@@ -70,7 +70,7 @@ impl IngressPoolSelect for TestIngressPool {
     fn select_validated<'a>(
         &self,
         range: std::ops::RangeInclusive<Time>,
-        f: Box<dyn FnMut(&IngressPoolObject) -> SelectResult<SignedIngress> + 'a>,
+        f: Box<dyn FnOnce(Vec<&IngressPoolObject>) -> Vec<SignedIngress> + 'a>,
     ) -> Vec<SignedIngress> {
         self.pool.select_validated(range, f)
     }
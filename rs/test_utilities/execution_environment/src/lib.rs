@@ -381,6 +381,15 @@ impl ExecutionTest {
         )
     }
 
+    pub fn non_replicated_http_request_fee(
+        &self,
+        request_size: NumBytes,
+        response_size_limit: Option<NumBytes>,
+    ) -> Cycles {
+        self.cycles_account_manager
+            .non_replicated_http_request_fee(request_size, response_size_limit)
+    }
+
     pub fn reduced_wasm_compilation_fee(&self, wasm: &[u8]) -> Cycles {
         let cost = wasm_compilation_cost(wasm);
         self.cycles_account_manager()
@@ -1162,6 +1171,30 @@ impl ExecutionTest {
         true
     }
 
+    /// Executes a response to a subnet call context (e.g. an http outcall)
+    /// as if it was delivered by consensus.
+    pub fn execute_consensus_response(&mut self, response: Response) {
+        let state = self.state.take().unwrap();
+        let compute_allocation_used = state.total_compute_allocation();
+        let mut round_limits = RoundLimits {
+            instructions: RoundInstructions::from(i64::MAX),
+            execution_complexity: ExecutionComplexity::MAX,
+            subnet_available_memory: self.subnet_available_memory,
+            compute_allocation_used,
+        };
+        let (new_state, _) = self.exec_env.execute_subnet_message(
+            CanisterMessage::Response(Arc::new(response)),
+            state,
+            self.install_code_instruction_limits.clone(),
+            &mut mock_random_number_generator(),
+            &self.ecdsa_subnet_public_keys,
+            &self.registry_settings,
+            &mut round_limits,
+        );
+        self.subnet_available_memory = round_limits.subnet_available_memory;
+        self.state = Some(new_state);
+    }
+
     /// Inducts and executes all pending messages.
     pub fn execute_all(&mut self) {
         loop {
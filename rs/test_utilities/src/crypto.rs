@@ -161,6 +161,14 @@ impl<T: Signable> MultiSigVerifier<T> for CryptoReturningOk {
         Ok(())
     }
 
+    fn verify_multi_sig_individual_batch(
+        &self,
+        shares: &[(&IndividualMultiSigOf<T>, &T, NodeId)],
+        _registry_version: RegistryVersion,
+    ) -> Vec<CryptoResult<()>> {
+        shares.iter().map(|_| Ok(())).collect()
+    }
+
     fn combine_multi_sig_individuals(
         &self,
         _signatures: BTreeMap<NodeId, IndividualMultiSigOf<T>>,
@@ -201,6 +209,14 @@ impl<T: Signable> ThresholdSigVerifier<T> for CryptoReturningOk {
         Ok(())
     }
 
+    fn verify_threshold_sig_share_batch(
+        &self,
+        shares: &[(&ThresholdSigShareOf<T>, &T, NodeId)],
+        _dkg_id: NiDkgId,
+    ) -> Vec<CryptoResult<()>> {
+        shares.iter().map(|_| Ok(())).collect()
+    }
+
     fn combine_threshold_sig_shares(
         &self,
         _shares: BTreeMap<NodeId, ThresholdSigShareOf<T>>,
@@ -97,6 +97,7 @@ pub fn test(env: TestEnv) {
                             context: vec![0, 1, 2],
                         }),
                         max_response_bytes: None,
+                        is_replicated: None,
                     },
                     cycles: 500_000_000_000,
                 },
@@ -124,6 +125,7 @@ pub fn test(env: TestEnv) {
                             context: vec![0, 1, 2],
                         }),
                         max_response_bytes: None,
+                        is_replicated: None,
                     },
                     cycles: 500_000_000_000,
                 },
@@ -154,6 +156,7 @@ pub fn test(env: TestEnv) {
                             context: vec![0, 1, 2],
                         }),
                         max_response_bytes: None,
+                        is_replicated: None,
                     },
                     cycles: 0,
                 },
@@ -176,6 +179,7 @@ pub fn test(env: TestEnv) {
                 context: vec![0, 1, 2],
             }),
             max_response_bytes: None,
+            is_replicated: None,
         };
         test_results.push(
             test_canister_http_property(
@@ -226,6 +230,7 @@ pub fn test(env: TestEnv) {
                 context: vec![0, 1, 2],
             }),
             max_response_bytes: Some(16384),
+            is_replicated: None,
         };
         test_results.push(
             test_canister_http_property(
@@ -281,6 +286,7 @@ pub fn test(env: TestEnv) {
                             context: vec![0, 1, 2],
                         }),
                         max_response_bytes: Some(4 * 1024 * 1024),
+                        is_replicated: None,
                     },
                     cycles: 0,
                 },
@@ -308,6 +314,7 @@ pub fn test(env: TestEnv) {
                             context: vec![0, 1, 2],
                         }),
                         max_response_bytes: None,
+                        is_replicated: None,
                     },
                     cycles: 500_000_000_000,
                 },
@@ -335,6 +342,7 @@ pub fn test(env: TestEnv) {
                             context: vec![0, 1, 2],
                         }),
                         max_response_bytes: None,
+                        is_replicated: None,
                     },
                     cycles: 500_000_000_000,
                 },
@@ -365,6 +373,7 @@ pub fn test(env: TestEnv) {
                             context: vec![0, 1, 2],
                         }),
                         max_response_bytes: None,
+                        is_replicated: None,
                     },
                     cycles: 500_000_000_000,
                 },
@@ -392,6 +401,7 @@ pub fn test(env: TestEnv) {
                             context: vec![0, 1, 2],
                         }),
                         max_response_bytes: Some(8 * 1024),
+                        is_replicated: None,
                     },
                     cycles: 500_000_000_000,
                 },
@@ -420,6 +430,7 @@ pub fn test(env: TestEnv) {
                             context: vec![0, 1, 2],
                         }),
                         max_response_bytes: None,
+                        is_replicated: None,
                     },
                     cycles: 500_000_000_000,
                 },
@@ -447,6 +458,7 @@ pub fn test(env: TestEnv) {
                             context: vec![0, 1, 2],
                         }),
                         max_response_bytes: None,
+                        is_replicated: None,
                     },
                     cycles: 500_000_000_000,
                 },
@@ -474,6 +486,7 @@ pub fn test(env: TestEnv) {
                             context: vec![0, 1, 2],
                         }),
                         max_response_bytes: None,
+                        is_replicated: None,
                     },
                     cycles: 500_000_000_000,
                 },
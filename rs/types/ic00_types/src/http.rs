@@ -55,12 +55,13 @@ pub type BoundedHttpHeaders =
 //     url : text;
 //     max_response_bytes: opt nat64;
 //     headers : vec http_header;
-//     method : variant { get; head; post };
+//     method : variant { get; head; post; put; patch; delete };
 //     body : opt blob;
 //     transform : opt record {
 //       function : func (record {response : http_response; context : blob}) -> (http_response) query;
 //       context : blob;
 //     };
+//     is_replicated : opt bool;
 //   })`
 #[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
 pub struct CanisterHttpRequestArgs {
@@ -70,6 +71,10 @@ pub struct CanisterHttpRequestArgs {
     pub body: Option<Vec<u8>>,
     pub method: HttpMethod,
     pub transform: Option<TransformContext>,
+    /// If set to `Some(false)`, the request is made by a single node only and
+    /// its response is not agreed upon by the other nodes. Defaults to a
+    /// replicated request.
+    pub is_replicated: Option<bool>,
 }
 
 impl Payload<'_> for CanisterHttpRequestArgs {}
@@ -104,6 +109,7 @@ fn test_http_headers_max_number() {
             body: None,
             method: HttpMethod::GET,
             transform: None,
+            is_replicated: None,
         };
 
         // Act.
@@ -155,6 +161,7 @@ fn test_http_headers_max_total_size() {
             body: None,
             method: HttpMethod::GET,
             transform: None,
+            is_replicated: None,
         };
 
         // Act.
@@ -231,8 +238,19 @@ pub enum HttpMethod {
     POST,
     #[serde(rename = "head")]
     HEAD,
+    #[serde(rename = "put")]
+    PUT,
+    #[serde(rename = "patch")]
+    PATCH,
+    #[serde(rename = "delete")]
+    DELETE,
 }
 
+/// Name of the header that execution adds to the response of a non-replicated
+/// canister http request. It marks that the response was returned by a single
+/// node and was not agreed upon by the other nodes of the subnet.
+pub const NON_REPLICATED_RESPONSE_HEADER: &str = "x-ic-non-replicated";
+
 /// Represents the response for a canister http request.
 /// Struct used for encoding/decoding
 /// `(record {
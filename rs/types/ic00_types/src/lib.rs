@@ -6,7 +6,7 @@ use arbitrary::{Arbitrary, Result as ArbitraryResult, Unstructured};
 use candid::{CandidType, Decode, Deserialize, Encode};
 pub use http::{
     BoundedHttpHeaders, CanisterHttpRequestArgs, CanisterHttpResponsePayload, HttpHeader,
-    HttpMethod, TransformArgs, TransformContext, TransformFunc,
+    HttpMethod, TransformArgs, TransformContext, TransformFunc, NON_REPLICATED_RESPONSE_HEADER,
 };
 use ic_base_types::{CanisterId, NodeId, NumBytes, PrincipalId, RegistryVersion, SubnetId};
 use ic_error_types::{ErrorCode, UserError};
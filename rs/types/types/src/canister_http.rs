@@ -44,8 +44,9 @@
 use crate::{
     crypto::{CryptoHashOf, Signed},
     messages::{CallbackId, RejectContext, Request},
+    node_id_into_protobuf, node_id_try_from_option,
     signature::*,
-    CanisterId, CountBytes, RegistryVersion, Time,
+    CanisterId, CountBytes, NodeId, RegistryVersion, Time,
 };
 use ic_base_types::{NumBytes, PrincipalId};
 use ic_error_types::{ErrorCode, RejectCode, UserError};
@@ -110,6 +111,16 @@ impl From<TransformContext> for Transform {
     }
 }
 
+/// Specifies which nodes make a canister http request.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Replication {
+    /// All nodes of the subnet make the request and agree on the response.
+    FullyReplicated,
+    /// Only the given node makes the request and its response is accepted
+    /// without the agreement of the other nodes.
+    NonReplicated(NodeId),
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct CanisterHttpRequestContext {
     pub request: Request,
@@ -121,6 +132,7 @@ pub struct CanisterHttpRequestContext {
     pub http_method: CanisterHttpMethod,
     pub transform: Option<Transform>,
     pub time: Time,
+    pub replication: Replication,
 }
 
 impl From<&CanisterHttpRequestContext> for pb_metadata::CanisterHttpRequestContext {
@@ -151,6 +163,10 @@ impl From<&CanisterHttpRequestContext> for pb_metadata::CanisterHttpRequestConte
                 .map(|transform| transform.context.clone()),
             http_method: pb_metadata::HttpMethod::from(&context.http_method).into(),
             time: context.time.as_nanos_since_unix_epoch(),
+            non_replicated_node_id: match context.replication {
+                Replication::FullyReplicated => None,
+                Replication::NonReplicated(node_id) => Some(node_id_into_protobuf(node_id)),
+            },
         }
     }
 }
@@ -208,6 +224,12 @@ impl TryFrom<pb_metadata::CanisterHttpRequestContext> for CanisterHttpRequestCon
                 .try_into()?,
             transform,
             time: Time::from_nanos_since_unix_epoch(context.time),
+            replication: match context.non_replicated_node_id {
+                None => Replication::FullyReplicated,
+                Some(node_id) => {
+                    Replication::NonReplicated(node_id_try_from_option(Some(node_id))?)
+                }
+            },
         })
     }
 }
@@ -327,14 +349,24 @@ impl TryFrom<(Time, &Request, CanisterHttpRequestArgs)> for CanisterHttpRequestC
                 HttpMethod::GET => CanisterHttpMethod::GET,
                 HttpMethod::POST => CanisterHttpMethod::POST,
                 HttpMethod::HEAD => CanisterHttpMethod::HEAD,
+                HttpMethod::PUT => CanisterHttpMethod::PUT,
+                HttpMethod::PATCH => CanisterHttpMethod::PATCH,
+                HttpMethod::DELETE => CanisterHttpMethod::DELETE,
             },
             transform: args.transform.map(From::from),
             time,
+            // The node making a non-replicated request is chosen by execution.
+            replication: Replication::FullyReplicated,
         })
     }
 }
 
 impl CanisterHttpRequestContext {
+    /// Returns true if only a single node makes the request.
+    pub fn is_non_replicated(&self) -> bool {
+        matches!(self.replication, Replication::NonReplicated(_))
+    }
+
     /// Calculate the size of all unbounded struct elements.
     pub fn variable_parts_size(&self) -> NumBytes {
         let request_size = self.url.len()
@@ -526,6 +558,9 @@ pub enum CanisterHttpMethod {
     GET,
     POST,
     HEAD,
+    PUT,
+    PATCH,
+    DELETE,
 }
 
 impl From<&CanisterHttpMethod> for pb_metadata::HttpMethod {
@@ -534,6 +569,9 @@ impl From<&CanisterHttpMethod> for pb_metadata::HttpMethod {
             CanisterHttpMethod::GET => pb_metadata::HttpMethod::Get,
             CanisterHttpMethod::POST => pb_metadata::HttpMethod::Post,
             CanisterHttpMethod::HEAD => pb_metadata::HttpMethod::Head,
+            CanisterHttpMethod::PUT => pb_metadata::HttpMethod::Put,
+            CanisterHttpMethod::PATCH => pb_metadata::HttpMethod::Patch,
+            CanisterHttpMethod::DELETE => pb_metadata::HttpMethod::Delete,
         }
     }
 }
@@ -546,6 +584,9 @@ impl TryFrom<pb_metadata::HttpMethod> for CanisterHttpMethod {
             pb_metadata::HttpMethod::Get => Ok(CanisterHttpMethod::GET),
             pb_metadata::HttpMethod::Post => Ok(CanisterHttpMethod::POST),
             pb_metadata::HttpMethod::Head => Ok(CanisterHttpMethod::HEAD),
+            pb_metadata::HttpMethod::Put => Ok(CanisterHttpMethod::PUT),
+            pb_metadata::HttpMethod::Patch => Ok(CanisterHttpMethod::PATCH),
+            pb_metadata::HttpMethod::Delete => Ok(CanisterHttpMethod::DELETE),
             pb_metadata::HttpMethod::Unspecified => Err(ProxyDecodeError::ValueOutOfRange {
                 typ: "ic_protobuf::state::system_metadata::v1::HttpMethod",
                 err: "Unspecified HttpMethod".to_string(),
@@ -675,6 +716,7 @@ mod tests {
                 metadata: None,
             },
             time: UNIX_EPOCH,
+            replication: Replication::FullyReplicated,
         };
 
         let expected_size = context.url.len()
@@ -716,6 +758,7 @@ mod tests {
                 metadata: None,
             },
             time: UNIX_EPOCH,
+            replication: Replication::FullyReplicated,
         };
 
         let expected_size = context.url.len()
@@ -727,4 +770,56 @@ mod tests {
             NumBytes::from(expected_size as u64)
         );
     }
+
+    #[test]
+    fn test_replication_proto_round_trip() {
+        for replication in [
+            Replication::FullyReplicated,
+            Replication::NonReplicated(NodeId::from(crate::PrincipalId::new_node_test_id(7))),
+        ] {
+            let context = CanisterHttpRequestContext {
+                url: "https://example.com".to_string(),
+                headers: vec![],
+                body: None,
+                max_response_bytes: None,
+                http_method: CanisterHttpMethod::GET,
+                transform: None,
+                request: Request {
+                    receiver: CanisterId::ic_00(),
+                    sender: CanisterId::ic_00(),
+                    sender_reply_callback: CallbackId::from(3),
+                    payment: Cycles::new(10),
+                    method_name: "http_request".to_string(),
+                    method_payload: Vec::new(),
+                    metadata: None,
+                },
+                time: UNIX_EPOCH,
+                replication,
+            };
+            let pb_context = pb_metadata::CanisterHttpRequestContext::from(&context);
+            assert_eq!(
+                CanisterHttpRequestContext::try_from(pb_context).unwrap(),
+                context
+            );
+        }
+    }
+
+    #[test]
+    fn test_http_method_proto_round_trip() {
+        for http_method in [
+            CanisterHttpMethod::GET,
+            CanisterHttpMethod::POST,
+            CanisterHttpMethod::HEAD,
+            CanisterHttpMethod::PUT,
+            CanisterHttpMethod::PATCH,
+            CanisterHttpMethod::DELETE,
+        ] {
+            let pb_http_method = pb_metadata::HttpMethod::from(&http_method);
+            assert_eq!(
+                CanisterHttpMethod::try_from(pb_http_method).unwrap(),
+                http_method
+            );
+        }
+        assert!(CanisterHttpMethod::try_from(pb_metadata::HttpMethod::Unspecified).is_err());
+    }
 }
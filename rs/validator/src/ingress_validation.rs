@@ -1,7 +1,10 @@
 use crate::webauthn::validate_webauthn_sig;
 use ic_constants::{MAX_INGRESS_TTL, PERMITTED_DRIFT_AT_VALIDATOR};
 use ic_crypto_interfaces_sig_verification::IngressSigVerifier;
-use ic_crypto_standalone_sig_verifier::{user_public_key_from_bytes, KeyBytesContentType};
+use ic_crypto_standalone_sig_verifier::{
+    check_delegation_chain, user_public_key_from_bytes, DelegationChainError, KeyBytesContentType,
+    MAXIMUM_NUMBER_OF_DELEGATIONS, MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION,
+};
 use ic_crypto_tree_hash::Path;
 use ic_types::crypto::threshold_sig::RootOfTrustProvider;
 use ic_types::crypto::{CanisterSig, CanisterSigOf};
@@ -14,7 +17,6 @@ use ic_types::{
     },
     CanisterId, PrincipalId, Time, UserId,
 };
-use std::collections::HashSet;
 use std::sync::Arc;
 use std::{collections::BTreeSet, convert::TryFrom, fmt};
 use thiserror::Error;
@@ -24,23 +26,6 @@ use RequestValidationError::*;
 #[cfg(test)]
 mod tests;
 
-/// Maximum number of delegations allowed in an `HttpRequest`.
-/// Requests having more delegations will be declared invalid without further verifying whether
-/// the delegation chain is correctly signed.
-/// **Note**: this limit is currently more generous than the one in the [IC specification](https://internetcomputer.org/docs/current/references/ic-interface-spec#authentication),
-/// which specifies a maximum of 4 delegations, in order to prevent potentially breaking already deployed applications
-/// since this limit was before (wrongly) not enforced.
-/// This limit will be tightened up once the number of delegations can be observed (via metrics or logs),
-/// see CRP-1961.
-const MAXIMUM_NUMBER_OF_DELEGATIONS: usize = 20;
-
-/// Maximum number of targets (collection of `CanisterId`s) that can be specified in a
-/// single delegation. Requests having a single delegation with more targets will be declared
-/// invalid without any further verification.
-/// **Note**: this limit part of the [IC specification](https://internetcomputer.org/docs/current/references/ic-interface-spec#authentication)
-/// and so changing this value might be breaking or result in a deviation from the specification.
-const MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION: usize = 1_000;
-
 /// Maximum number of paths that can be specified in a read state request. Requests having more paths
 /// will be declared invalid without any further verification.
 /// **Note**: this limit part of the [IC specification](https://internetcomputer.org/docs/current/references/ic-interface-spec#http-read-state)
@@ -472,42 +457,42 @@ fn validate_ingress_expiry<C: HttpRequestContent>(
     Ok(())
 }
 
-fn validate_sender_delegation_length(
-    sender_delegation: &Option<Vec<SignedDelegation>>,
-) -> Result<(), RequestValidationError> {
-    match sender_delegation
-        .as_ref()
-        .map(|delegations| delegations.len())
-    {
-        Some(number_of_delegations) if number_of_delegations > MAXIMUM_NUMBER_OF_DELEGATIONS => {
-            Err(InvalidDelegation(DelegationTooLongError {
-                length: number_of_delegations,
-                maximum: MAXIMUM_NUMBER_OF_DELEGATIONS,
-            }))
-        }
-        _ => Ok(()),
-    }
-}
-// Check if any of the sender delegation has expired with respect to the
-// `current_time`, and return an error if so.
-fn validate_sender_delegation_expiry(
-    sender_delegation: &Option<Vec<SignedDelegation>>,
+// Checks the structure of the chain of delegations, i.e., its length, the
+// expiry of the delegations, the absence of cycles and the number of targets.
+// The checks are shared with the standalone signature verifier.
+fn validate_sender_delegation_chain(
+    sender_pubkey: &[u8],
+    sender_delegation: &[SignedDelegation],
     current_time: Time,
 ) -> Result<(), RequestValidationError> {
-    if let Some(delegations) = &sender_delegation {
-        for delegation in delegations.iter() {
-            let expiry = delegation.delegation().expiration();
-            if delegation.delegation().expiration() < current_time {
-                return Err(InvalidDelegationExpiry(format!(
-                    "Specified sender delegation has expired:\n\
-                     Provided expiry:    {}\n\
-                     Local replica time: {}",
-                    expiry, current_time,
-                )));
+    check_delegation_chain(sender_pubkey, sender_delegation, current_time).map_err(
+        |err| match err {
+            DelegationChainError::TooLong { length, maximum } => {
+                InvalidDelegation(DelegationTooLongError { length, maximum })
             }
-        }
-    }
-    Ok(())
+            DelegationChainError::Expired {
+                expiration,
+                current_time,
+                ..
+            } => InvalidDelegationExpiry(format!(
+                "Specified sender delegation has expired:\n\
+                 Provided expiry:    {}\n\
+                 Local replica time: {}",
+                expiration, current_time,
+            )),
+            DelegationChainError::ContainsCycles { public_key } => {
+                InvalidDelegation(DelegationContainsCyclesError { public_key })
+            }
+            DelegationChainError::TooManyTargets {
+                number_of_targets,
+                maximum,
+                ..
+            } => InvalidDelegation(DelegationTargetError(format!(
+                "expected at most {} targets per delegation, but got {}",
+                maximum, number_of_targets
+            ))),
+        },
+    )
 }
 
 // Verifies that the user id matches the public key.  Returns an error if not.
@@ -530,10 +515,9 @@ fn validate_signature<R: RootOfTrustProvider>(
 where
     R::Error: std::error::Error,
 {
-    validate_sender_delegation_length(&signature.sender_delegation)?;
-    validate_sender_delegation_expiry(&signature.sender_delegation, current_time)?;
     let empty_vec = Vec::new();
     let signed_delegations = signature.sender_delegation.as_ref().unwrap_or(&empty_vec);
+    validate_sender_delegation_chain(&signature.signer_pubkey, signed_delegations, current_time)?;
 
     let (pubkey, targets) = validate_delegations(
         validator,
@@ -611,8 +595,6 @@ fn validate_delegations<R: RootOfTrustProvider>(
 where
     R::Error: std::error::Error,
 {
-    ensure_delegations_does_not_contain_cycles(&pubkey, signed_delegations)?;
-    ensure_delegations_does_not_contain_too_many_targets(signed_delegations)?;
     // Initially, assume that the delegations target all possible canister IDs.
     let mut targets = CanisterIdSet::all();
 
@@ -636,42 +618,6 @@ where
     Ok((pubkey, targets))
 }
 
-fn ensure_delegations_does_not_contain_cycles(
-    sender_public_key: &[u8],
-    signed_delegations: &[SignedDelegation],
-) -> Result<(), RequestValidationError> {
-    let mut observed_public_keys = HashSet::with_capacity(signed_delegations.len() + 1);
-    observed_public_keys.insert(sender_public_key);
-    for delegation in signed_delegations {
-        let current_public_key = delegation.delegation().pubkey();
-        if !observed_public_keys.insert(current_public_key) {
-            return Err(InvalidDelegation(DelegationContainsCyclesError {
-                public_key: current_public_key.clone(),
-            }));
-        }
-    }
-    Ok(())
-}
-
-fn ensure_delegations_does_not_contain_too_many_targets(
-    signed_delegations: &[SignedDelegation],
-) -> Result<(), RequestValidationError> {
-    for delegation in signed_delegations {
-        match delegation.delegation().number_of_targets() {
-            Some(number_of_targets)
-                if number_of_targets > MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION =>
-            {
-                Err(InvalidDelegation(DelegationTargetError(format!(
-                    "expected at most {} targets per delegation, but got {}",
-                    MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION, number_of_targets
-                ))))
-            }
-            _ => Ok(()),
-        }?
-    }
-    Ok(())
-}
-
 fn validate_delegation<R: RootOfTrustProvider>(
     validator: &dyn IngressSigVerifier,
     signature: &[u8],